@@ -0,0 +1,220 @@
+//! Latency and allocations of the input and commit hot paths
+//!
+//! Simulates one second of an 8 kHz mouse moving over a client surface and a client committing
+//! its surface in a loop, and reports the latency percentiles and heap allocations per event.
+//! Run with `cargo bench --bench input_latency --features wayland_test`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use smithay::{
+    delegate_compositor, delegate_seat,
+    input::{
+        pointer::{CursorImageStatus, MotionEvent},
+        Seat, SeatHandler, SeatState,
+    },
+    reexports::wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{
+            wl_compositor::WlCompositor, wl_pointer::WlPointer, wl_seat::WlSeat, wl_surface::WlSurface,
+        },
+        Client, Display, Resource,
+    },
+    utils::SERIAL_COUNTER,
+    wayland::{
+        compositor::{CompositorClientState, CompositorHandler, CompositorState},
+        test::{Arg, FakeClient},
+    },
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// 8 kHz polling rate
+const EVENTS: usize = 8000;
+/// Events between two flushes of the client connection, i.e. a 1 kHz flush rate
+const BATCH: usize = 8;
+
+struct State {
+    compositor_state: CompositorState,
+    seat_state: SeatState<Self>,
+    surfaces: Vec<WlSurface>,
+}
+
+#[derive(Default)]
+struct ClientState {
+    compositor_state: CompositorClientState,
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+impl CompositorHandler for State {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+        &client.get_data::<ClientState>().unwrap().compositor_state
+    }
+
+    fn new_surface(&mut self, surface: &WlSurface) {
+        self.surfaces.push(surface.clone());
+    }
+
+    fn commit(&mut self, _surface: &WlSurface) {}
+}
+
+impl SeatHandler for State {
+    type KeyboardFocus = WlSurface;
+    type PointerFocus = WlSurface;
+    type TouchFocus = WlSurface;
+
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+
+    fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+}
+
+delegate_compositor!(State);
+delegate_seat!(State);
+
+struct Sample {
+    latency: Duration,
+    allocations: usize,
+}
+
+fn measure(f: impl FnOnce()) -> Sample {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    let latency = start.elapsed();
+    Sample {
+        latency,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    }
+}
+
+fn report(name: &str, mut samples: Vec<Sample>) {
+    let allocations = samples.iter().map(|s| s.allocations).sum::<usize>();
+    let allocating = samples.iter().filter(|s| s.allocations > 0).count();
+    samples.sort_by_key(|s| s.latency);
+    let percentile = |p: usize| samples[(samples.len() * p / 100).min(samples.len() - 1)].latency;
+    println!(
+        "{name}: p50 {:?}, p99 {:?}, max {:?}, {:.3} allocations per event, {} of {} events allocating",
+        percentile(50),
+        percentile(99),
+        samples.last().unwrap().latency,
+        allocations as f64 / samples.len() as f64,
+        allocating,
+        samples.len(),
+    );
+}
+
+fn roundtrip(display: &mut Display<State>, state: &mut State, client: &mut FakeClient) {
+    display.dispatch_clients(state).unwrap();
+    display.flush_clients().unwrap();
+    assert!(client.receive().unwrap(), "the client was disconnected");
+}
+
+fn main() {
+    let mut display = Display::<State>::new().unwrap();
+    let dh = display.handle();
+    let mut seat_state = SeatState::new();
+    let mut seat = seat_state.new_wl_seat(&dh, "seat-0");
+    let pointer = seat.add_pointer();
+    let mut state = State {
+        compositor_state: CompositorState::new::<State>(&dh),
+        seat_state,
+        surfaces: Vec::new(),
+    };
+
+    let (mut client, _) =
+        FakeClient::connect(&mut display.handle(), Arc::new(ClientState::default())).unwrap();
+    client.get_registry().unwrap();
+    roundtrip(&mut display, &mut state, &mut client);
+    let compositor = client.bind_global(WlCompositor::interface(), 6).unwrap();
+    let wl_seat = client.bind_global(WlSeat::interface(), 9).unwrap();
+    let surface = client.new_id(WlSurface::interface());
+    client.send(compositor, 0, vec![Arg::NewId(surface)]).unwrap();
+    let wl_pointer = client.new_id(WlPointer::interface());
+    client.send(wl_seat, 0, vec![Arg::NewId(wl_pointer)]).unwrap();
+    roundtrip(&mut display, &mut state, &mut client);
+    let focus = state.surfaces[0].clone();
+
+    // warm up caches and pools
+    for i in 0..EVENTS {
+        let event = MotionEvent {
+            location: (i as f64 % 100.0, 10.0).into(),
+            serial: SERIAL_COUNTER.next_serial(),
+            time: i as u32,
+        };
+        pointer.motion(&mut state, Some((focus.clone(), (0.0, 0.0).into())), &event);
+        pointer.frame(&mut state);
+        if i % BATCH == 0 {
+            roundtrip(&mut display, &mut state, &mut client);
+        }
+    }
+
+    let mut samples = Vec::with_capacity(EVENTS);
+    for i in 0..EVENTS {
+        let event = MotionEvent {
+            location: (i as f64 % 100.0, 20.0).into(),
+            serial: SERIAL_COUNTER.next_serial(),
+            time: i as u32,
+        };
+        samples.push(measure(|| {
+            pointer.motion(&mut state, Some((focus.clone(), (0.0, 0.0).into())), &event);
+            pointer.frame(&mut state);
+        }));
+        if i % BATCH == 0 {
+            roundtrip(&mut display, &mut state, &mut client);
+        }
+    }
+    report("pointer motion", samples);
+
+    let mut samples = Vec::with_capacity(EVENTS);
+    for i in 0..EVENTS * 2 {
+        // wl_surface.commit
+        client.send(surface, 6, Vec::new()).unwrap();
+        let sample = measure(|| {
+            display.dispatch_clients(&mut state).unwrap();
+        });
+        display.flush_clients().unwrap();
+        client.receive().unwrap();
+        // the first half warms up caches and pools
+        if i >= EVENTS {
+            samples.push(sample);
+        }
+    }
+    report("surface commit", samples);
+}
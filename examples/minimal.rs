@@ -15,9 +15,10 @@ use smithay::{
         },
         winit::{self, WinitEvent},
     },
-    delegate_compositor, delegate_data_device, delegate_seat, delegate_shm, delegate_xdg_shell,
+    delegate_compositor, delegate_data_device, delegate_primary_selection, delegate_seat, delegate_shm,
+    delegate_xdg_shell,
     input::{keyboard::FilterResult, Seat, SeatHandler, SeatState},
-    reexports::wayland_server::{protocol::wl_seat, Display},
+    reexports::wayland_server::{protocol::wl_seat, Display, DisplayHandle},
     utils::{Rectangle, Serial, Transform},
     wayland::{
         buffer::BufferHandler,
@@ -27,7 +28,8 @@ use smithay::{
         },
         selection::{
             data_device::{DataDeviceHandler, DataDeviceState, WaylandDndGrabHandler},
-            SelectionHandler,
+            primary_selection::{PrimarySelectionHandler, PrimarySelectionState},
+            set_selection_focus, SelectionHandler,
         },
         shell::xdg::{PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState},
         shm::{ShmHandler, ShmState},
@@ -40,7 +42,7 @@ use wayland_server::{
         wl_buffer,
         wl_surface::{self, WlSurface},
     },
-    Client, ListeningSocket,
+    Client, ListeningSocket, Resource,
 };
 
 impl BufferHandler for App {
@@ -84,6 +86,12 @@ impl DataDeviceHandler for App {
 
 impl WaylandDndGrabHandler for App {}
 
+impl PrimarySelectionHandler for App {
+    fn primary_selection_state(&mut self) -> &mut PrimarySelectionState {
+        &mut self.primary_selection_state
+    }
+}
+
 impl CompositorHandler for App {
     fn compositor_state(&mut self) -> &mut CompositorState {
         &mut self.compositor_state
@@ -113,7 +121,10 @@ impl SeatHandler for App {
         &mut self.seat_state
     }
 
-    fn focus_changed(&mut self, _seat: &Seat<Self>, _focused: Option<&WlSurface>) {}
+    fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) {
+        let client = focused.and_then(|surface| self.display_handle.get_client(surface.id()).ok());
+        set_selection_focus(&self.display_handle, seat, client);
+    }
     fn cursor_image(&mut self, _seat: &Seat<Self>, _image: smithay::input::pointer::CursorImageStatus) {}
 }
 
@@ -123,6 +134,8 @@ struct App {
     shm_state: ShmState,
     seat_state: SeatState<Self>,
     data_device_state: DataDeviceState,
+    primary_selection_state: PrimarySelectionState,
+    display_handle: DisplayHandle,
 
     seat: Seat<Self>,
 }
@@ -153,6 +166,8 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
             shm_state,
             seat_state,
             data_device_state: DataDeviceState::new::<App>(&dh),
+            primary_selection_state: PrimarySelectionState::new::<App>(&dh),
+            display_handle: dh.clone(),
             seat,
         }
     };
@@ -296,3 +311,4 @@ delegate_compositor!(App);
 delegate_shm!(App);
 delegate_seat!(App);
 delegate_data_device!(App);
+delegate_primary_selection!(App);
@@ -0,0 +1,212 @@
+//! Demo of the Windows backend
+//!
+//! Opens a window on the Windows desktop, exercising the Windows specific backends of smithay end
+//! to end:
+//!
+//! - the displays of the desktop are enumerated to create an [`Output`] per display,
+//! - the window is created by the winit backend and rendered to through a WGL context,
+//! - keyboard and pointer input of all devices is received through the raw input backend and
+//!   dispatched by a calloop event loop,
+//! - F11 toggles borderless fullscreen, Escape quits,
+//! - the system is kept awake while the demo runs.
+//!
+//! This is not a compositor, wayland clients cannot connect: the wayland frontend depends on unix
+//! domain sockets and there is no named-pipe transport for it yet. The example draws the pointer
+//! as a square over a background reflecting its position instead.
+//!
+//! Run it with `cargo run --example windows --features backend_winit_windows`.
+
+#[cfg(windows)]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if let Ok(env_filter) = tracing_subscriber::EnvFilter::try_from_default_env() {
+        tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    } else {
+        tracing_subscriber::fmt().init();
+    }
+
+    windows::run()
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("This example only runs on Windows");
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::time::Duration;
+
+    use smithay::{
+        backend::{
+            input::{
+                ButtonState, InputEvent, KeyState, KeyboardKeyEvent, PointerButtonEvent, PointerMotionEvent,
+            },
+            wgl,
+            win32::{
+                display,
+                fullscreen::{FullscreenMode, WindowFullscreen},
+                power::KeepAwake,
+                raw_input::RawInputSource,
+            },
+            winit::{self, WinitEvent},
+        },
+        output::{Output, PhysicalProperties, Subpixel},
+        reexports::{
+            calloop::EventLoop,
+            winit::{
+                platform::pump_events::PumpStatus,
+                raw_window_handle::{HasWindowHandle, RawWindowHandle},
+            },
+        },
+        utils::Transform,
+    };
+    use tracing::{info, warn};
+
+    // scancodes offset by 8
+    const KEY_ESC: u32 = 0x01 + 8;
+    const KEY_F11: u32 = 0x57 + 8;
+    const CURSOR_SIZE: i32 = 16;
+
+    const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+    const GL_SCISSOR_TEST: u32 = 0x0C11;
+    type ClearColor = unsafe extern "system" fn(f32, f32, f32, f32);
+    type Clear = unsafe extern "system" fn(u32);
+    type Scissor = unsafe extern "system" fn(i32, i32, i32, i32);
+    type Toggle = unsafe extern "system" fn(u32);
+
+    struct State {
+        pointer: (f64, f64),
+        pressed: bool,
+        running: bool,
+        toggle_fullscreen: bool,
+    }
+
+    pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+        let outputs = display::monitors()?
+            .into_iter()
+            .enumerate()
+            .map(|(i, monitor)| {
+                info!(
+                    name = %monitor.device_name,
+                    adapter = %monitor.adapter,
+                    mode = ?monitor.current_mode,
+                    hdr = monitor.hdr_enabled,
+                    "Found display"
+                );
+                let output = Output::new(
+                    format!("display-{}", i),
+                    PhysicalProperties {
+                        size: (0, 0).into(),
+                        subpixel: Subpixel::Unknown,
+                        make: monitor.adapter.clone(),
+                        model: monitor.device_name.clone(),
+                        serial_number: "Unknown".into(),
+                    },
+                );
+                monitor.apply_to_output(&output);
+                output.change_current_state(
+                    None,
+                    Some(Transform::Normal),
+                    None,
+                    Some(monitor.position.to_logical(1)),
+                );
+                output
+            })
+            .collect::<Vec<_>>();
+        info!("Created {} outputs", outputs.len());
+
+        let (mut backend, mut winit_loop) = winit::wgl::init()?;
+        let hwnd = match backend.window().window_handle()?.as_raw() {
+            RawWindowHandle::Win32(handle) => handle.hwnd.get(),
+            _ => unreachable!("winit windows on Windows are Win32 windows"),
+        };
+        let mut fullscreen = unsafe { WindowFullscreen::new(hwnd) };
+        let _keep_awake = KeepAwake::new(true)?;
+
+        let mut size = backend.window_size();
+        let mut event_loop = EventLoop::<State>::try_new()?;
+        event_loop
+            .handle()
+            .insert_source(RawInputSource::new()?, |event, _, state| match event {
+                InputEvent::DeviceAdded { device } => info!(?device, "Input device added"),
+                InputEvent::DeviceRemoved { device } => info!(?device, "Input device removed"),
+                InputEvent::Keyboard { event } if event.state() == KeyState::Pressed => {
+                    match event.key_code() {
+                        KEY_ESC => state.running = false,
+                        KEY_F11 => state.toggle_fullscreen = true,
+                        _ => {}
+                    }
+                }
+                InputEvent::PointerMotion { event } => {
+                    state.pointer.0 += event.delta_x();
+                    state.pointer.1 += event.delta_y();
+                }
+                InputEvent::PointerButton { event } => {
+                    state.pressed = event.state() == ButtonState::Pressed;
+                }
+                _ => {}
+            })
+            .map_err(|err| err.error)?;
+
+        backend.bind()?;
+        let clear_color: ClearColor = unsafe { std::mem::transmute(wgl::get_proc_address("glClearColor")) };
+        let clear: Clear = unsafe { std::mem::transmute(wgl::get_proc_address("glClear")) };
+        let scissor: Scissor = unsafe { std::mem::transmute(wgl::get_proc_address("glScissor")) };
+        let enable: Toggle = unsafe { std::mem::transmute(wgl::get_proc_address("glEnable")) };
+        let disable: Toggle = unsafe { std::mem::transmute(wgl::get_proc_address("glDisable")) };
+
+        let mut state = State {
+            pointer: (size.w as f64 / 2.0, size.h as f64 / 2.0),
+            pressed: false,
+            running: true,
+            toggle_fullscreen: false,
+        };
+        while state.running {
+            let status = winit_loop.dispatch_new_events(|event| match event {
+                WinitEvent::Resized { size: new_size, .. } => size = new_size,
+                WinitEvent::CloseRequested => state.running = false,
+                // input is received through raw input instead
+                _ => {}
+            });
+            if let PumpStatus::Exit(_) = status {
+                break;
+            }
+            event_loop.dispatch(Duration::ZERO, &mut state)?;
+
+            if std::mem::take(&mut state.toggle_fullscreen) {
+                let mode = match fullscreen.mode() {
+                    FullscreenMode::Windowed => FullscreenMode::Borderless,
+                    _ => FullscreenMode::Windowed,
+                };
+                if let Err(err) = fullscreen.set_mode(mode) {
+                    warn!(?err, "Failed to switch fullscreen mode");
+                } else if mode == FullscreenMode::Borderless && !fullscreen.independent_flip_eligible() {
+                    info!("Window is not eligible for independent flip");
+                }
+            }
+            state.pointer.0 = state.pointer.0.clamp(0.0, size.w.max(1) as f64 - 1.0);
+            state.pointer.1 = state.pointer.1.clamp(0.0, size.h.max(1) as f64 - 1.0);
+
+            backend.bind()?;
+            let red = (state.pointer.0 / size.w.max(1) as f64) as f32;
+            let green = (state.pointer.1 / size.h.max(1) as f64) as f32;
+            let (x, y) = (state.pointer.0 as i32, state.pointer.1 as i32);
+            let cursor = if state.pressed { 1.0 } else { 0.0 };
+            unsafe {
+                clear_color(red, green, 0.2, 1.0);
+                clear(GL_COLOR_BUFFER_BIT);
+                // the origin of gl is the bottom left corner
+                enable(GL_SCISSOR_TEST);
+                scissor(x, size.h - y - CURSOR_SIZE, CURSOR_SIZE, CURSOR_SIZE);
+                clear_color(1.0, 1.0, cursor, 1.0);
+                clear(GL_COLOR_BUFFER_BIT);
+                disable(GL_SCISSOR_TEST);
+            }
+            backend.submit()?;
+
+            std::thread::sleep(Duration::from_millis(16));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Compositor shared by the fuzz targets
+//!
+//! Implements the handlers of the fuzzed protocols with as little logic as possible, so panics
+//! found by the fuzzer are in smithay's state machines and not in the harness.
+
+use std::sync::Arc;
+
+use smithay::{
+    delegate_compositor, delegate_data_device, delegate_seat, delegate_shm, delegate_xdg_shell,
+    input::{pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
+    reexports::wayland_server::{
+        backend::{protocol::Interface, ClientData, ClientId, DisconnectReason},
+        protocol::{wl_buffer, wl_seat, wl_surface::WlSurface},
+        Client, Display, DisplayHandle,
+    },
+    utils::Serial,
+    wayland::{
+        buffer::BufferHandler,
+        compositor::{
+            with_states, BufferAssignment, CompositorClientState, CompositorHandler, CompositorState,
+            SurfaceAttributes,
+        },
+        selection::{
+            data_device::{DataDeviceHandler, DataDeviceState, WaylandDndGrabHandler},
+            SelectionHandler,
+        },
+        shell::xdg::{PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState},
+        shm::{with_buffer_contents, ShmHandler, ShmState},
+        test::FakeClient,
+    },
+};
+
+pub struct State {
+    compositor_state: CompositorState,
+    xdg_shell_state: XdgShellState,
+    shm_state: ShmState,
+    seat_state: SeatState<Self>,
+    data_device_state: DataDeviceState,
+    toplevels: Vec<ToplevelSurface>,
+}
+
+impl State {
+    fn new(dh: &DisplayHandle) -> Self {
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(dh, "seat-0");
+        seat.add_keyboard(Default::default(), 200, 25)
+            .expect("Failed to initialize the keyboard");
+        seat.add_pointer();
+
+        State {
+            compositor_state: CompositorState::new::<Self>(dh),
+            xdg_shell_state: XdgShellState::new::<Self>(dh),
+            shm_state: ShmState::new::<Self>(dh, vec![]),
+            seat_state,
+            data_device_state: DataDeviceState::new::<Self>(dh),
+            toplevels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ClientState {
+    compositor_state: CompositorClientState,
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+/// Bind the given globals, then send requests generated from `data` until it is exhausted or
+/// the client was disconnected
+pub fn run(data: &[u8], globals: &[(&'static Interface, u32)]) {
+    let mut display = Display::<State>::new().expect("Failed to create the display");
+    let mut state = State::new(&display.handle());
+    let (mut client, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState::default()))
+        .expect("Failed to connect the client");
+
+    client.get_registry().unwrap();
+    dispatch(&mut display, &mut state, &mut client);
+    for &(interface, version) in globals {
+        client.bind_global(interface, version).unwrap();
+    }
+
+    let mut data = data;
+    while client.send_arbitrary(&mut data).unwrap_or(false) {
+        if !dispatch(&mut display, &mut state, &mut client) {
+            break;
+        }
+    }
+}
+
+fn dispatch(display: &mut Display<State>, state: &mut State, client: &mut FakeClient) -> bool {
+    display.dispatch_clients(state).is_ok()
+        && display.flush_clients().is_ok()
+        && client.receive().unwrap_or(false)
+}
+
+impl CompositorHandler for State {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+        &client.get_data::<ClientState>().unwrap().compositor_state
+    }
+
+    fn commit(&mut self, surface: &WlSurface) {
+        if let Some(toplevel) = self.toplevels.iter().find(|t| t.wl_surface() == surface) {
+            toplevel.send_configure();
+        }
+
+        let buffer = with_states(surface, |states| {
+            match states
+                .cached_state
+                .get::<SurfaceAttributes>()
+                .current()
+                .buffer
+                .take()
+            {
+                Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer),
+                _ => None,
+            }
+        });
+        if let Some(buffer) = buffer {
+            // touch every byte of the buffer, like a renderer uploading it would
+            let _ = with_buffer_contents(&buffer, |ptr, len, _| {
+                // SAFETY: smithay guarantees `ptr` is valid for `len` bytes
+                let contents = unsafe { std::slice::from_raw_parts(ptr, len) };
+                contents.iter().fold(0u8, |acc, b| acc ^ b)
+            });
+            buffer.release();
+        }
+    }
+}
+
+impl BufferHandler for State {
+    fn buffer_destroyed(&mut self, _buffer: &wl_buffer::WlBuffer) {}
+}
+
+impl ShmHandler for State {
+    fn shm_state(&self) -> &ShmState {
+        &self.shm_state
+    }
+}
+
+impl XdgShellHandler for State {
+    fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+        &mut self.xdg_shell_state
+    }
+
+    fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        self.toplevels.push(surface);
+    }
+
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.toplevels.retain(|t| t != &surface);
+    }
+
+    fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
+        let _ = surface.send_configure();
+    }
+
+    fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+
+    fn reposition_request(&mut self, surface: PopupSurface, _positioner: PositionerState, token: u32) {
+        surface.send_repositioned(token);
+    }
+}
+
+impl SeatHandler for State {
+    type KeyboardFocus = WlSurface;
+    type PointerFocus = WlSurface;
+    type TouchFocus = WlSurface;
+
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+
+    fn focus_changed(&mut self, _seat: &Seat<Self>, _focused: Option<&WlSurface>) {}
+
+    fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+}
+
+impl SelectionHandler for State {
+    type SelectionUserData = ();
+}
+
+impl WaylandDndGrabHandler for State {}
+
+impl DataDeviceHandler for State {
+    fn data_device_state(&mut self) -> &mut DataDeviceState {
+        &mut self.data_device_state
+    }
+}
+
+delegate_compositor!(State);
+delegate_shm!(State);
+delegate_xdg_shell!(State);
+delegate_seat!(State);
+delegate_data_device!(State);
@@ -0,0 +1,22 @@
+//! Fuzzes data sources, data devices and drag'n'drop requests
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use smithay::reexports::wayland_server::{
+    protocol::{wl_compositor::WlCompositor, wl_data_device_manager::WlDataDeviceManager, wl_seat::WlSeat},
+    Resource,
+};
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    common::run(
+        data,
+        &[
+            (WlCompositor::interface(), 6),
+            (WlSeat::interface(), 9),
+            (WlDataDeviceManager::interface(), 3),
+        ],
+    );
+});
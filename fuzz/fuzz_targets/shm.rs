@@ -0,0 +1,15 @@
+//! Fuzzes wl_shm pools and buffers attached to surfaces
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use smithay::reexports::wayland_server::{
+    protocol::{wl_compositor::WlCompositor, wl_shm::WlShm},
+    Resource,
+};
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    common::run(data, &[(WlCompositor::interface(), 6), (WlShm::interface(), 2)]);
+});
@@ -0,0 +1,25 @@
+//! Fuzzes the xdg_surface, xdg_toplevel and xdg_popup state machines
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use smithay::reexports::{
+    wayland_protocols::xdg::shell::server::xdg_wm_base::XdgWmBase,
+    wayland_server::{
+        protocol::{wl_compositor::WlCompositor, wl_shm::WlShm},
+        Resource,
+    },
+};
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    common::run(
+        data,
+        &[
+            (WlCompositor::interface(), 6),
+            (WlShm::interface(), 2),
+            (XdgWmBase::interface(), 6),
+        ],
+    );
+});
@@ -0,0 +1,101 @@
+//! Derive macros of [smithay](https://docs.rs/smithay)
+//!
+//! Do not depend on this crate directly, enable the `derive` feature of smithay and use the
+//! re-exports instead, e.g. `smithay::wayland::delegate::DelegateProtocols`.
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, punctuated::Punctuated, DeriveInput, Ident, Token};
+
+/// Delegates the protocols listed in the `#[delegate(...)]` attributes to the annotated state
+///
+/// Expands to `smithay::delegate_protocols!`, see its documentation for the supported protocols.
+#[proc_macro_derive(DelegateProtocols, attributes(delegate))]
+pub fn derive_delegate_protocols(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    delegate_protocols(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn delegate_protocols(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`DelegateProtocols` does not support generic states, use the `delegate_*` macros instead",
+        ));
+    }
+
+    let mut protocols = Vec::new();
+    let mut seen = HashSet::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("delegate")) {
+        for protocol in attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)? {
+            if !seen.insert(protocol.to_string()) {
+                return Err(syn::Error::new_spanned(
+                    &protocol,
+                    format!("protocol `{}` is delegated twice", protocol),
+                ));
+            }
+            protocols.push(protocol);
+        }
+    }
+    if protocols.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`DelegateProtocols` needs a `#[delegate(...)]` attribute listing the protocols",
+        ));
+    }
+
+    let ident = &input.ident;
+    Ok(quote! {
+        ::smithay::delegate_protocols!(#ident: [#(#protocols),*]);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::delegate_protocols;
+
+    #[test]
+    fn expands_to_delegate_protocols() {
+        let tokens = delegate_protocols(parse_quote! {
+            #[delegate(compositor, shm)]
+            #[delegate(seat)]
+            struct State {
+                shm_state: ShmState,
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            tokens.to_string(),
+            quote::quote! {
+                ::smithay::delegate_protocols!(State: [compositor, shm, seat]);
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let generic = delegate_protocols(parse_quote! {
+            #[delegate(shm)]
+            struct State<T>(T);
+        });
+        assert!(generic.is_err());
+
+        let missing = delegate_protocols(parse_quote! {
+            struct State;
+        });
+        assert!(missing.is_err());
+
+        let duplicate = delegate_protocols(parse_quote! {
+            #[delegate(shm, seat, shm)]
+            struct State;
+        });
+        assert!(duplicate.unwrap_err().to_string().contains("`shm`"));
+    }
+}
@@ -0,0 +1,734 @@
+//! Enumeration and selection of GPU adapters
+//!
+//! Systems with multiple GPUs, like laptops with an integrated and a discrete GPU, need to
+//! decide which one the renderer runs on. [`enumerate`] lists the adapters of the system as
+//! [`AdapterInfo`]s, through EGL devices on Linux and DXGI on Windows, and [`select`] picks one
+//! according to an [`AdapterPreference`], which users can override with the `SMITHAY_ADAPTER`
+//! environment variable (see [`AdapterPreference::from_env`]).
+//!
+//! ```no_run
+//! use smithay::backend::adapter::{self, AdapterPreference};
+//!
+//! let adapters = adapter::enumerate().unwrap();
+//! let preference = AdapterPreference::from_env().unwrap_or(AdapterPreference::HighPerformance);
+//! if let Some(adapter) = adapter::select(&adapters, &preference) {
+//!     println!("Rendering on {}", adapter.name);
+//!     // create the renderer on e.g. `adapter.egl_device`
+//! }
+//! ```
+//!
+//! On Windows the adapter of OpenGL contexts created through WGL is chosen by the driver, the
+//! selection is meant for renderers creating their device from the adapter LUID.
+//!
+//! ### Adapter removal
+//!
+//! Adapters can disappear at runtime, e.g. when an external GPU is unplugged or a DXGI adapter
+//! is removed by a driver update. The [`AdapterTracker`] remembers which adapters render and
+//! scan out every output. Whenever the adapters may have changed, e.g. on
+//! [`UdevEvent::Removed`](crate::backend::udev::UdevEvent::Removed) or when a renderer reports
+//! a lost context, the compositor calls [`AdapterTracker::rescan`] and reacts to the returned
+//! [`AdapterEvent`]s: renderers of outputs are recreated on another adapter, falling back to a
+//! software adapter if no hardware adapter is left, and outputs whose scanout adapter is gone
+//! are removed.
+//!
+//! ```no_run
+//! use smithay::backend::adapter::{AdapterEvent, AdapterPreference, AdapterTracker};
+//!
+//! let mut tracker = AdapterTracker::new(AdapterPreference::HighPerformance).unwrap();
+//! // ... `tracker.assign(&output, ...)` for every output
+//! for event in tracker.rescan().unwrap() {
+//!     match event {
+//!         AdapterEvent::MigrateRenderer { output, to, .. } => {
+//!             // recreate the renderer of `output` on `to`
+//!         }
+//!         AdapterEvent::RendererLost { output, .. } | AdapterEvent::ScanoutLost { output, .. } => {
+//!             // remove `output`
+//!         }
+//!         AdapterEvent::Added(_) | AdapterEvent::Removed(_) => {}
+//!     }
+//! }
+//! ```
+
+use std::io;
+
+use tracing::{info, warn};
+
+#[cfg(feature = "backend_drm")]
+use crate::backend::drm::DrmNode;
+#[cfg(all(unix, feature = "backend_egl"))]
+use crate::backend::egl::EGLDevice;
+use crate::output::Output;
+
+/// Environment variable overriding the adapter preference
+pub const ADAPTER_ENV: &str = "SMITHAY_ADAPTER";
+
+/// Kind of GPU adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdapterKind {
+    /// GPU sharing the memory with the CPU, usually power efficient
+    Integrated,
+    /// GPU with its own memory, usually the most performant
+    Discrete,
+    /// Renderer running on the CPU
+    Software,
+    /// The kind could not be determined
+    Unknown,
+}
+
+/// Description of a GPU adapter
+#[derive(Debug, Clone)]
+pub struct AdapterInfo {
+    /// Human readable name of the adapter
+    pub name: String,
+    /// PCI vendor id
+    pub vendor_id: Option<u32>,
+    /// PCI device id
+    pub device_id: Option<u32>,
+    /// Kind of the adapter
+    pub kind: AdapterKind,
+    /// Dedicated video memory in bytes
+    pub dedicated_memory: Option<u64>,
+    /// Render node of the adapter
+    #[cfg(feature = "backend_drm")]
+    pub node: Option<DrmNode>,
+    /// EGL device to create an [`EGLDisplay`](crate::backend::egl::EGLDisplay) on the adapter
+    #[cfg(all(unix, feature = "backend_egl"))]
+    pub egl_device: EGLDevice,
+    /// Locally unique identifier of the adapter
+    #[cfg(windows)]
+    pub luid: u64,
+}
+
+impl AdapterInfo {
+    /// Identifier of the adapter that stays the same across enumerations
+    pub fn id(&self) -> AdapterId {
+        #[cfg(windows)]
+        {
+            AdapterId(AdapterIdInner::Luid(self.luid))
+        }
+        #[cfg(not(windows))]
+        {
+            #[cfg(feature = "backend_drm")]
+            if let Some(node) = self.node.as_ref() {
+                return AdapterId(AdapterIdInner::Node(node.dev_id()));
+            }
+            AdapterId(AdapterIdInner::Description {
+                vendor_id: self.vendor_id,
+                device_id: self.device_id,
+                name: self.name.clone(),
+            })
+        }
+    }
+}
+
+/// Identifier of an adapter, see [`AdapterInfo::id`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AdapterId(AdapterIdInner);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AdapterIdInner {
+    #[cfg(windows)]
+    Luid(u64),
+    #[cfg(all(not(windows), feature = "backend_drm"))]
+    Node(libc::dev_t),
+    #[cfg_attr(windows, allow(dead_code))]
+    Description {
+        vendor_id: Option<u32>,
+        device_id: Option<u32>,
+        name: String,
+    },
+}
+
+/// Errors enumerating adapters
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Enumerating the EGL devices failed
+    #[cfg(all(unix, feature = "backend_egl"))]
+    #[error("Failed to enumerate EGL devices: {0}")]
+    Egl(#[from] crate::backend::egl::Error),
+    /// A system call failed
+    #[error("Failed to enumerate adapters: {0}")]
+    Os(#[from] io::Error),
+    /// Adapters cannot be enumerated on this platform or with the enabled features
+    #[error("Adapter enumeration is not supported")]
+    Unsupported,
+}
+
+/// Preference used to select an adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterPreference {
+    /// The first adapter, usually the one driving the primary display
+    Default,
+    /// Prefer discrete over integrated adapters
+    HighPerformance,
+    /// Prefer integrated over discrete adapters
+    LowPower,
+    /// The first adapter of the given PCI vendor
+    Vendor(u32),
+    /// The first adapter whose name contains the given string, ignoring case
+    Name(String),
+}
+
+impl AdapterPreference {
+    /// Read the preference from the `SMITHAY_ADAPTER` environment variable
+    ///
+    /// Accepts `default`, `high-performance`, `low-power`, a hexadecimal PCI vendor id like
+    /// `0x10de`, or otherwise a part of the adapter name. Returns `None` if the variable is unset.
+    pub fn from_env() -> Option<AdapterPreference> {
+        std::env::var(ADAPTER_ENV).ok().map(|value| Self::parse(&value))
+    }
+
+    fn parse(value: &str) -> AdapterPreference {
+        let value = value.trim();
+        match value.to_ascii_lowercase().as_str() {
+            "" | "default" => AdapterPreference::Default,
+            "high-performance" | "discrete" => AdapterPreference::HighPerformance,
+            "low-power" | "integrated" => AdapterPreference::LowPower,
+            lower => match lower
+                .strip_prefix("0x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            {
+                Some(vendor) => AdapterPreference::Vendor(vendor),
+                None => AdapterPreference::Name(value.to_owned()),
+            },
+        }
+    }
+}
+
+/// Select an adapter according to the preference
+///
+/// Software adapters are only selected if no hardware adapter is available, unless they are
+/// explicitly requested by name.
+pub fn select<'a>(adapters: &'a [AdapterInfo], preference: &AdapterPreference) -> Option<&'a AdapterInfo> {
+    let candidates = adapters
+        .iter()
+        .map(|adapter| (adapter.kind, adapter.vendor_id, adapter.name.as_str()))
+        .collect::<Vec<_>>();
+    select_index(&candidates, preference).map(|index| &adapters[index])
+}
+
+fn select_index(
+    adapters: &[(AdapterKind, Option<u32>, &str)],
+    preference: &AdapterPreference,
+) -> Option<usize> {
+    let hardware = || {
+        adapters
+            .iter()
+            .enumerate()
+            .filter(|(_, (kind, _, _))| *kind != AdapterKind::Software)
+    };
+    let of_kind = |wanted| hardware().find(|(_, (kind, _, _))| *kind == wanted);
+    let selected = match preference {
+        AdapterPreference::Default => hardware().next(),
+        AdapterPreference::HighPerformance => of_kind(AdapterKind::Discrete).or_else(|| hardware().next()),
+        AdapterPreference::LowPower => of_kind(AdapterKind::Integrated).or_else(|| hardware().next()),
+        AdapterPreference::Vendor(vendor) => {
+            return hardware()
+                .find(|(_, (_, vendor_id, _))| *vendor_id == Some(*vendor))
+                .map(|(index, _)| index);
+        }
+        AdapterPreference::Name(name) => {
+            let name = name.to_lowercase();
+            return adapters
+                .iter()
+                .position(|(_, _, adapter)| adapter.to_lowercase().contains(&name));
+        }
+    };
+    selected
+        .map(|(index, _)| index)
+        .or((!adapters.is_empty()).then_some(0))
+}
+
+/// Adapters used by an output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputAdapters {
+    /// Adapter running the renderer of the output
+    pub render: AdapterId,
+    /// Adapter scanning out the output, `None` if the output is presented by the platform,
+    /// e.g. a window of a nested backend
+    pub scanout: Option<AdapterId>,
+}
+
+/// Change of the adapters reported by [`AdapterTracker::rescan`]
+#[derive(Debug, Clone)]
+pub enum AdapterEvent {
+    /// A new adapter appeared
+    Added(AdapterInfo),
+    /// An adapter disappeared
+    ///
+    /// Reported before the events of the outputs that used it.
+    Removed(AdapterInfo),
+    /// The render adapter of an output disappeared, its renderer has to be recreated on `to`
+    MigrateRenderer {
+        /// The affected output
+        output: Output,
+        /// The removed adapter
+        from: AdapterId,
+        /// The adapter to render on from now on, may be a software adapter
+        to: AdapterInfo,
+    },
+    /// The render adapter of an output disappeared and no other adapter is available
+    ///
+    /// The output is no longer tracked.
+    RendererLost {
+        /// The affected output
+        output: Output,
+        /// The removed adapter
+        from: AdapterId,
+    },
+    /// The scanout adapter of an output disappeared, so the output is gone as well
+    ///
+    /// The output is no longer tracked.
+    ScanoutLost {
+        /// The affected output
+        output: Output,
+        /// The removed adapter
+        adapter: AdapterId,
+    },
+}
+
+/// Tracks the adapters of the system and the outputs using them
+///
+/// See the [module-level documentation](self#adapter-removal).
+#[derive(Debug)]
+pub struct AdapterTracker {
+    adapters: Vec<AdapterInfo>,
+    preference: AdapterPreference,
+    outputs: Vec<(Output, OutputAdapters)>,
+}
+
+impl AdapterTracker {
+    /// Create a new tracker with the currently available adapters
+    ///
+    /// The preference is used to pick a new render adapter for outputs losing theirs.
+    pub fn new(preference: AdapterPreference) -> Result<AdapterTracker, Error> {
+        Ok(Self::with_adapters(enumerate()?, preference))
+    }
+
+    /// Create a new tracker with an already enumerated list of adapters
+    pub fn with_adapters(adapters: Vec<AdapterInfo>, preference: AdapterPreference) -> AdapterTracker {
+        AdapterTracker {
+            adapters,
+            preference,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Returns the currently known adapters
+    pub fn adapters(&self) -> &[AdapterInfo] {
+        &self.adapters
+    }
+
+    /// Returns the known adapter with the given id
+    pub fn adapter(&self, id: &AdapterId) -> Option<&AdapterInfo> {
+        self.adapters.iter().find(|adapter| adapter.id() == *id)
+    }
+
+    /// Record the adapters used by an output, replacing a previous assignment
+    pub fn assign(&mut self, output: &Output, adapters: OutputAdapters) {
+        match self.outputs.iter_mut().find(|(o, _)| o == output) {
+            Some((_, assigned)) => *assigned = adapters,
+            None => self.outputs.push((output.clone(), adapters)),
+        }
+    }
+
+    /// Stop tracking an output, e.g. after it was removed
+    pub fn unassign(&mut self, output: &Output) {
+        self.outputs.retain(|(o, _)| o != output);
+    }
+
+    /// Returns the adapters used by an output
+    pub fn output_adapters(&self, output: &Output) -> Option<&OutputAdapters> {
+        self.outputs
+            .iter()
+            .find(|(o, _)| o == output)
+            .map(|(_, adapters)| adapters)
+    }
+
+    /// Re-enumerate the adapters of the system and report the changes
+    pub fn rescan(&mut self) -> Result<Vec<AdapterEvent>, Error> {
+        Ok(self.update(enumerate()?))
+    }
+
+    /// Replace the known adapters and report the changes
+    ///
+    /// Outputs whose render adapter is gone are migrated to the adapter they are scanned out
+    /// from if possible, to avoid copies between adapters, otherwise to an adapter chosen by
+    /// the preference of the tracker.
+    pub fn update(&mut self, adapters: Vec<AdapterInfo>) -> Vec<AdapterEvent> {
+        let old = std::mem::replace(&mut self.adapters, adapters);
+        let ids = self.adapters.iter().map(AdapterInfo::id).collect::<Vec<_>>();
+        let old_ids = old.iter().map(AdapterInfo::id).collect::<Vec<_>>();
+
+        let mut events = Vec::new();
+        for (adapter, id) in self.adapters.iter().zip(&ids) {
+            if !old_ids.contains(id) {
+                info!("Adapter added: {}", adapter.name);
+                events.push(AdapterEvent::Added(adapter.clone()));
+            }
+        }
+        let mut removed = Vec::new();
+        for (adapter, id) in old.into_iter().zip(old_ids) {
+            if !ids.contains(&id) {
+                warn!("Adapter removed: {}", adapter.name);
+                events.push(AdapterEvent::Removed(adapter));
+                removed.push(id);
+            }
+        }
+        if removed.is_empty() {
+            return events;
+        }
+
+        let candidates = self
+            .adapters
+            .iter()
+            .map(|adapter| (adapter.kind, adapter.vendor_id, adapter.name.as_str()))
+            .collect::<Vec<_>>();
+        let preference = &self.preference;
+        self.outputs.retain_mut(|(output, assigned)| {
+            if let Some(adapter) = assigned.scanout.as_ref().filter(|id| removed.contains(id)) {
+                warn!(output = %output.name(), "Scanout adapter of output removed");
+                events.push(AdapterEvent::ScanoutLost {
+                    output: output.clone(),
+                    adapter: adapter.clone(),
+                });
+                return false;
+            }
+            if !removed.contains(&assigned.render) {
+                return true;
+            }
+
+            let scanout = assigned
+                .scanout
+                .as_ref()
+                .and_then(|scanout| ids.iter().position(|id| id == scanout));
+            match migration_target(&candidates, scanout, preference) {
+                Some(index) => {
+                    let to = self.adapters[index].clone();
+                    info!(
+                        output = %output.name(),
+                        "Migrating renderer of output to {}", to.name
+                    );
+                    let from = std::mem::replace(&mut assigned.render, ids[index].clone());
+                    events.push(AdapterEvent::MigrateRenderer {
+                        output: output.clone(),
+                        from,
+                        to,
+                    });
+                    true
+                }
+                None => {
+                    warn!(output = %output.name(), "No adapter left to render output");
+                    events.push(AdapterEvent::RendererLost {
+                        output: output.clone(),
+                        from: assigned.render.clone(),
+                    });
+                    false
+                }
+            }
+        });
+
+        events
+    }
+}
+
+/// Pick the adapter an output migrates its renderer to
+///
+/// Prefers the scanout adapter of the output, then the preference, then any adapter including
+/// software ones.
+fn migration_target(
+    adapters: &[(AdapterKind, Option<u32>, &str)],
+    scanout: Option<usize>,
+    preference: &AdapterPreference,
+) -> Option<usize> {
+    scanout
+        .or_else(|| select_index(adapters, preference))
+        .or_else(|| select_index(adapters, &AdapterPreference::Default))
+}
+
+/// Enumerate the GPU adapters of the system
+#[cfg(all(unix, feature = "backend_egl"))]
+pub fn enumerate() -> Result<Vec<AdapterInfo>, Error> {
+    egl::enumerate()
+}
+
+/// Enumerate the GPU adapters of the system
+#[cfg(windows)]
+pub fn enumerate() -> Result<Vec<AdapterInfo>, Error> {
+    dxgi::enumerate()
+}
+
+/// Enumerate the GPU adapters of the system
+#[cfg(not(any(windows, all(unix, feature = "backend_egl"))))]
+pub fn enumerate() -> Result<Vec<AdapterInfo>, Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(all(unix, feature = "backend_egl"))]
+mod egl {
+    use std::path::PathBuf;
+
+    use super::{AdapterInfo, AdapterKind, Error};
+    use crate::backend::egl::EGLDevice;
+
+    fn kind_from_vendor(vendor: Option<u32>) -> AdapterKind {
+        match vendor {
+            Some(0x8086) => AdapterKind::Integrated,
+            Some(0x10de) => AdapterKind::Discrete,
+            _ => AdapterKind::Unknown,
+        }
+    }
+
+    fn read_hex(path: PathBuf) -> Option<u32> {
+        let value = std::fs::read_to_string(path).ok()?;
+        u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+    }
+
+    pub fn enumerate() -> Result<Vec<AdapterInfo>, Error> {
+        Ok(EGLDevice::enumerate()?
+            .map(|egl_device| {
+                if egl_device.is_software() {
+                    return AdapterInfo {
+                        name: String::from("Software renderer"),
+                        vendor_id: None,
+                        device_id: None,
+                        kind: AdapterKind::Software,
+                        dedicated_memory: None,
+                        #[cfg(feature = "backend_drm")]
+                        node: None,
+                        egl_device,
+                    };
+                }
+
+                #[cfg(feature = "backend_drm")]
+                let node = egl_device.try_get_render_node().ok().flatten();
+                #[cfg(feature = "backend_drm")]
+                let sysfs = node.as_ref().map(|node| {
+                    PathBuf::from(format!("/sys/dev/char/{}:{}/device", node.major(), node.minor()))
+                });
+                #[cfg(not(feature = "backend_drm"))]
+                let sysfs: Option<PathBuf> = None;
+
+                let vendor_id = sysfs.as_ref().and_then(|path| read_hex(path.join("vendor")));
+                let device_id = sysfs.as_ref().and_then(|path| read_hex(path.join("device")));
+                let dedicated_memory = sysfs.as_ref().and_then(|path| {
+                    // only reported by amdgpu
+                    std::fs::read_to_string(path.join("mem_info_vram_total"))
+                        .ok()
+                        .and_then(|value| value.trim().parse().ok())
+                });
+                let driver = sysfs.as_ref().and_then(|path| {
+                    std::fs::read_link(path.join("driver"))
+                        .ok()
+                        .and_then(|driver| driver.file_name().map(|name| name.to_string_lossy().into_owned()))
+                });
+                let name = match (
+                    driver,
+                    egl_device
+                        .render_device_path()
+                        .or_else(|_| egl_device.drm_device_path()),
+                ) {
+                    (Some(driver), Ok(path)) => format!("{} ({})", driver, path.display()),
+                    (Some(driver), Err(_)) => driver,
+                    (None, Ok(path)) => path.display().to_string(),
+                    (None, Err(_)) => String::from("Unknown adapter"),
+                };
+
+                let kind = match (vendor_id, dedicated_memory) {
+                    // amdgpu reports the carve-out of APUs as vram
+                    (Some(0x1002), Some(memory)) if memory > 1 << 30 => AdapterKind::Discrete,
+                    (Some(0x1002), Some(_)) => AdapterKind::Integrated,
+                    _ => kind_from_vendor(vendor_id),
+                };
+
+                AdapterInfo {
+                    name,
+                    vendor_id,
+                    device_id,
+                    kind,
+                    dedicated_memory,
+                    #[cfg(feature = "backend_drm")]
+                    node,
+                    egl_device,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(windows)]
+mod dxgi {
+    use std::{ffi::c_void, io};
+
+    use super::{AdapterInfo, AdapterKind, Error};
+
+    #[repr(C)]
+    struct Guid(u32, u16, u16, [u8; 8]);
+
+    const IID_IDXGI_FACTORY1: Guid = Guid(
+        0x770aae78,
+        0xf26f,
+        0x4dba,
+        [0xa8, 0x29, 0x25, 0x3c, 0x83, 0xd1, 0xb3, 0x87],
+    );
+    const DXGI_ERROR_NOT_FOUND: i32 = 0x887a0002_u32 as i32;
+    const DXGI_ADAPTER_FLAG_SOFTWARE: u32 = 2;
+
+    #[repr(C)]
+    struct AdapterDesc1 {
+        description: [u16; 128],
+        vendor_id: u32,
+        device_id: u32,
+        sub_sys_id: u32,
+        revision: u32,
+        dedicated_video_memory: usize,
+        dedicated_system_memory: usize,
+        shared_system_memory: usize,
+        luid_low: u32,
+        luid_high: i32,
+        flags: u32,
+    }
+
+    // vtable layouts of IDXGIFactory1 and IDXGIAdapter1, only the used entries are typed
+    #[repr(C)]
+    struct FactoryVtbl {
+        query_interface: usize,
+        add_ref: usize,
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        _object: [usize; 4],
+        _factory: [usize; 5],
+        enum_adapters1: unsafe extern "system" fn(*mut c_void, u32, *mut *mut c_void) -> i32,
+    }
+
+    #[repr(C)]
+    struct AdapterVtbl {
+        query_interface: usize,
+        add_ref: usize,
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        _object: [usize; 4],
+        _adapter: [usize; 3],
+        get_desc1: unsafe extern "system" fn(*mut c_void, *mut AdapterDesc1) -> i32,
+    }
+
+    #[link(name = "dxgi")]
+    extern "system" {
+        fn CreateDXGIFactory1(riid: *const Guid, factory: *mut *mut c_void) -> i32;
+    }
+
+    unsafe fn vtbl<T>(object: *mut c_void) -> &'static T {
+        unsafe { &**(object as *mut *const T) }
+    }
+
+    pub fn enumerate() -> Result<Vec<AdapterInfo>, Error> {
+        let mut factory = std::ptr::null_mut();
+        let result = unsafe { CreateDXGIFactory1(&IID_IDXGI_FACTORY1, &mut factory) };
+        if result < 0 {
+            return Err(io::Error::from_raw_os_error(result).into());
+        }
+        let factory_vtbl = unsafe { vtbl::<FactoryVtbl>(factory) };
+
+        let mut adapters = Vec::new();
+        let mut result = Ok(());
+        for index in 0.. {
+            let mut adapter = std::ptr::null_mut();
+            let hr = unsafe { (factory_vtbl.enum_adapters1)(factory, index, &mut adapter) };
+            if hr == DXGI_ERROR_NOT_FOUND {
+                break;
+            }
+            if hr < 0 {
+                result = Err(io::Error::from_raw_os_error(hr));
+                break;
+            }
+
+            let adapter_vtbl = unsafe { vtbl::<AdapterVtbl>(adapter) };
+            // SAFETY: plain old data, all zeroes is a valid value
+            let mut desc: AdapterDesc1 = unsafe { std::mem::zeroed() };
+            let hr = unsafe { (adapter_vtbl.get_desc1)(adapter, &mut desc) };
+            unsafe { (adapter_vtbl.release)(adapter) };
+            if hr < 0 {
+                continue;
+            }
+
+            let len = desc.description.iter().position(|c| *c == 0).unwrap_or(128);
+            let kind = if desc.flags & DXGI_ADAPTER_FLAG_SOFTWARE != 0 {
+                AdapterKind::Software
+            } else if desc.dedicated_video_memory >= 512 << 20 {
+                AdapterKind::Discrete
+            } else {
+                AdapterKind::Integrated
+            };
+            adapters.push(AdapterInfo {
+                name: String::from_utf16_lossy(&desc.description[..len]),
+                vendor_id: Some(desc.vendor_id),
+                device_id: Some(desc.device_id),
+                kind,
+                dedicated_memory: Some(desc.dedicated_video_memory as u64),
+                #[cfg(feature = "backend_drm")]
+                node: None,
+                luid: ((desc.luid_high as u32 as u64) << 32) | desc.luid_low as u64,
+            });
+        }
+        unsafe { (factory_vtbl.release)(factory) };
+
+        result?;
+        Ok(adapters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migration_target, select_index, AdapterKind, AdapterPreference};
+
+    #[test]
+    fn selects_by_preference() {
+        let adapters = [
+            (AdapterKind::Software, None, "llvmpipe"),
+            (AdapterKind::Integrated, Some(0x8086), "Intel UHD"),
+            (AdapterKind::Discrete, Some(0x10de), "NVIDIA RTX"),
+        ];
+        let select = |preference| select_index(&adapters, &preference);
+        assert_eq!(select(AdapterPreference::Default), Some(1));
+        assert_eq!(select(AdapterPreference::HighPerformance), Some(2));
+        assert_eq!(select(AdapterPreference::LowPower), Some(1));
+        assert_eq!(select(AdapterPreference::Vendor(0x1002)), None);
+        assert_eq!(select(AdapterPreference::Name("LLVM".into())), Some(0));
+        assert_eq!(select_index(&adapters[..1], &AdapterPreference::Default), Some(0));
+        assert_eq!(select_index(&[], &AdapterPreference::Default), None);
+    }
+
+    #[test]
+    fn migrates_to_remaining_adapter() {
+        let adapters = [
+            (AdapterKind::Software, None, "llvmpipe"),
+            (AdapterKind::Integrated, Some(0x8086), "Intel UHD"),
+        ];
+        let preference = AdapterPreference::Vendor(0x10de);
+        // the scanout adapter wins over the preference
+        assert_eq!(migration_target(&adapters, Some(0), &preference), Some(0));
+        // unmatched preferences fall back to any hardware adapter, then to software
+        assert_eq!(migration_target(&adapters, None, &preference), Some(1));
+        assert_eq!(migration_target(&adapters[..1], None, &preference), Some(0));
+        assert_eq!(migration_target(&[], None, &preference), None);
+    }
+
+    #[test]
+    fn parses_preference() {
+        assert_eq!(
+            AdapterPreference::parse("high-performance"),
+            AdapterPreference::HighPerformance
+        );
+        assert_eq!(
+            AdapterPreference::parse(" Integrated "),
+            AdapterPreference::LowPower
+        );
+        assert_eq!(
+            AdapterPreference::parse("0x10DE"),
+            AdapterPreference::Vendor(0x10de)
+        );
+        assert_eq!(
+            AdapterPreference::parse("Radeon"),
+            AdapterPreference::Name("Radeon".into())
+        );
+        assert_eq!(AdapterPreference::parse(""), AdapterPreference::Default);
+    }
+}
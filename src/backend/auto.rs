@@ -0,0 +1,748 @@
+//! Backend-agnostic bootstrapping of a compositor
+//!
+//! Setting up a backend, renderer, seat and output is the same boilerplate for every new
+//! compositor. The [`CompositorBuilder`] picks a backend suitable for the environment the
+//! compositor is started in and creates all of these in one go:
+//!
+//! - on Windows a [winit](crate::backend::winit) window rendered through WGL,
+//! - nested in a wayland or X11 session a winit window with a `GlesRenderer`,
+//! - in CI (if the `CI` environment variable is set) a headless `PixmanRenderer` drawing into an
+//!   offscreen image.
+//!
+//! The selection can be overridden with the `SMITHAY_BACKEND` environment variable, see
+//! [`BackendKind::from_env`].
+//!
+//! On a TTY [`BackendKind::Drm`] is selected. The builder opens a libseat session and the
+//! primary GPU of the seat, and drives the first connected display with a [`DrmOutput`]. The
+//! session and device notifiers are returned to be inserted into the event loop, handling of
+//! hotplug and VT switches is left to the compositor. [`BackendKind::X11`] opens a window on the
+//! X server rendered through GBM and EGL.
+//!
+//! Backends whose features are not enabled result in [`Error::Unsupported`].
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::backend::auto::CompositorBuilder;
+//! # struct State;
+//! # impl smithay::input::SeatHandler for State {
+//! #     type KeyboardFocus = smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! #     type PointerFocus = smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! #     type TouchFocus = smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! #     fn seat_state(&mut self) -> &mut smithay::input::SeatState<Self> { unimplemented!() }
+//! # }
+//!
+//! let compositor = CompositorBuilder::new()
+//!     .output_name("output-0")
+//!     .build::<State>()
+//!     .unwrap();
+//! println!("Running on the {:?} backend", compositor.backend.kind());
+//! ```
+
+use std::ffi::OsString;
+
+use tracing::info;
+
+#[cfg(any(
+    all(unix, feature = "backend_winit"),
+    all(windows, feature = "backend_winit_windows")
+))]
+use crate::backend::winit::{self, WinitEventLoop};
+#[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+use crate::backend::{
+    allocator::dmabuf::DmabufAllocator,
+    x11::{self, WindowBuilder, X11Backend, X11Error, X11Surface},
+};
+#[cfg(feature = "renderer_pixman")]
+use crate::backend::{
+    allocator::Fourcc,
+    renderer::{
+        pixman::{PixmanError, PixmanRenderer},
+        Offscreen,
+    },
+};
+#[cfg(all(
+    feature = "backend_session_libseat",
+    feature = "backend_udev",
+    feature = "backend_gbm",
+    feature = "renderer_gl",
+    feature = "wayland_frontend"
+))]
+use crate::backend::{
+    drm::{
+        exporter::gbm::GbmFramebufferExporter,
+        output::{DrmOutput, DrmOutputManager, DrmOutputRenderElements},
+        scanner::DrmScanner,
+        CreateDrmNodeError, DrmDevice, DrmDeviceFd, DrmDeviceNotifier, DrmError, DrmNode,
+    },
+    renderer::element::solid::SolidColorRenderElement,
+    session::{
+        libseat::{self, LibSeatSession, LibSeatSessionNotifier},
+        Session,
+    },
+};
+use crate::{
+    backend::capabilities::{Capabilities, QueryCapabilities},
+    input::{keyboard::Error as KeyboardError, Seat, SeatHandler, SeatState},
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    utils::{Physical, Size, Transform},
+};
+#[cfg(any(
+    all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ),
+    all(feature = "backend_x11", feature = "renderer_gl")
+))]
+use crate::{
+    backend::{
+        allocator::gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
+        egl::{EGLContext, EGLDisplay, Error as EGLError},
+        renderer::gles::{GlesError, GlesRenderer},
+    },
+    utils::DeviceFd,
+};
+#[cfg(all(
+    feature = "backend_session_libseat",
+    feature = "backend_udev",
+    feature = "backend_gbm",
+    feature = "renderer_gl",
+    feature = "wayland_frontend"
+))]
+use drm::control::ModeTypeFlags;
+#[cfg(all(
+    feature = "backend_session_libseat",
+    feature = "backend_udev",
+    feature = "backend_gbm",
+    feature = "renderer_gl",
+    feature = "wayland_frontend"
+))]
+use rustix::fs::OFlags;
+
+/// Environment variable overriding the selected backend
+pub const BACKEND_ENV: &str = "SMITHAY_BACKEND";
+
+/// Kind of backend a compositor runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BackendKind {
+    /// Directly on the GPU of a TTY
+    Drm,
+    /// In a winit window of a wayland or X11 session
+    Winit,
+    /// In an X11 window using the X11 backend
+    X11,
+    /// In a winit window on Windows
+    Windows,
+    /// Without any display, rendering offscreen
+    Headless,
+}
+
+impl BackendKind {
+    /// Select the backend for the current environment
+    ///
+    /// Returns the backend set in the `SMITHAY_BACKEND` environment variable, if any.
+    pub fn detect() -> BackendKind {
+        Self::detect_from(|name| std::env::var_os(name))
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<OsString>) -> BackendKind {
+        if let Some(kind) = var(BACKEND_ENV).and_then(|value| Self::parse(&value.to_string_lossy())) {
+            return kind;
+        }
+
+        if cfg!(windows) {
+            BackendKind::Windows
+        } else if var("CI").is_some() {
+            BackendKind::Headless
+        } else if var("WAYLAND_DISPLAY").is_some() || var("DISPLAY").is_some() {
+            BackendKind::Winit
+        } else if cfg!(feature = "backend_drm") {
+            BackendKind::Drm
+        } else {
+            BackendKind::Headless
+        }
+    }
+
+    /// Read the backend from the `SMITHAY_BACKEND` environment variable
+    ///
+    /// Accepts `drm` (or `tty`), `winit`, `x11`, `windows` and `headless`. Returns `None` if
+    /// the variable is unset or invalid.
+    pub fn from_env() -> Option<BackendKind> {
+        std::env::var(BACKEND_ENV)
+            .ok()
+            .and_then(|value| Self::parse(&value))
+    }
+
+    fn parse(value: &str) -> Option<BackendKind> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "drm" | "tty" | "udev" => Some(BackendKind::Drm),
+            "winit" => Some(BackendKind::Winit),
+            "x11" => Some(BackendKind::X11),
+            "windows" | "win32" => Some(BackendKind::Windows),
+            "headless" => Some(BackendKind::Headless),
+            _ => None,
+        }
+    }
+}
+
+/// Errors of [`CompositorBuilder::build`]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The backend cannot be created automatically or is not enabled
+    #[error("The {0:?} backend is not supported by the compositor builder")]
+    Unsupported(BackendKind),
+    /// Creating the winit window failed
+    #[cfg(any(
+        all(unix, feature = "backend_winit"),
+        all(windows, feature = "backend_winit_windows")
+    ))]
+    #[error("Failed to create the winit backend: {0}")]
+    Winit(#[from] winit::Error),
+    /// Creating the headless renderer failed
+    #[cfg(feature = "renderer_pixman")]
+    #[error("Failed to create the headless renderer: {0}")]
+    Pixman(#[from] PixmanError),
+    /// Creating the keyboard of the seat failed
+    #[error("Failed to create the keyboard: {0}")]
+    Keyboard(#[from] KeyboardError),
+    /// Opening the session failed
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    #[error("Failed to open the session: {0}")]
+    Session(#[from] libseat::Error),
+    /// The seat has no GPU or no display is connected to it
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    #[error("No GPU with a connected display was found")]
+    NoDisplay,
+    /// The primary GPU is no DRM node
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    #[error("Failed to open the GPU: {0}")]
+    Node(#[from] CreateDrmNodeError),
+    /// Accessing the DRM device failed
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    #[error("Failed to access the DRM device: {0}")]
+    Drm(#[from] DrmError),
+    /// Initializing the output of the DRM device failed
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    #[error("Failed to initialize the output: {0}")]
+    DrmOutput(Box<dyn std::error::Error + Send + Sync>),
+    /// Connecting to the X server or creating the window failed
+    #[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+    #[error("Failed to create the X11 backend: {0}")]
+    X11(#[from] X11Error),
+    /// Finding or opening the GPU failed
+    #[cfg(any(
+        all(
+            feature = "backend_session_libseat",
+            feature = "backend_udev",
+            feature = "backend_gbm",
+            feature = "renderer_gl",
+            feature = "wayland_frontend"
+        ),
+        all(feature = "backend_x11", feature = "renderer_gl")
+    ))]
+    #[error("Failed to open the GPU: {0}")]
+    Io(#[from] std::io::Error),
+    /// Initializing EGL failed
+    #[cfg(any(
+        all(
+            feature = "backend_session_libseat",
+            feature = "backend_udev",
+            feature = "backend_gbm",
+            feature = "renderer_gl",
+            feature = "wayland_frontend"
+        ),
+        all(feature = "backend_x11", feature = "renderer_gl")
+    ))]
+    #[error("Failed to initialize EGL: {0}")]
+    Egl(#[from] EGLError),
+    /// Creating the renderer failed
+    #[cfg(any(
+        all(
+            feature = "backend_session_libseat",
+            feature = "backend_udev",
+            feature = "backend_gbm",
+            feature = "renderer_gl",
+            feature = "wayland_frontend"
+        ),
+        all(feature = "backend_x11", feature = "renderer_gl")
+    ))]
+    #[error("Failed to create the renderer: {0}")]
+    Gles(#[from] GlesError),
+}
+
+/// Output manager of the DRM backend
+#[cfg(all(
+    feature = "backend_session_libseat",
+    feature = "backend_udev",
+    feature = "backend_gbm",
+    feature = "renderer_gl",
+    feature = "wayland_frontend"
+))]
+pub type GbmDrmOutputManager =
+    DrmOutputManager<GbmAllocator<DrmDeviceFd>, GbmFramebufferExporter<DrmDeviceFd>, (), DrmDeviceFd>;
+
+/// Output of the DRM backend
+#[cfg(all(
+    feature = "backend_session_libseat",
+    feature = "backend_udev",
+    feature = "backend_gbm",
+    feature = "renderer_gl",
+    feature = "wayland_frontend"
+))]
+pub type GbmDrmOutput =
+    DrmOutput<GbmAllocator<DrmDeviceFd>, GbmFramebufferExporter<DrmDeviceFd>, (), DrmDeviceFd>;
+
+/// Backend created by a [`CompositorBuilder`]
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Backend {
+    /// The first display of the primary GPU, rendered with a `GlesRenderer`
+    #[cfg(all(
+        feature = "backend_session_libseat",
+        feature = "backend_udev",
+        feature = "backend_gbm",
+        feature = "renderer_gl",
+        feature = "wayland_frontend"
+    ))]
+    Drm {
+        /// The session, to open further devices
+        session: LibSeatSession,
+        /// The session notifier, to be inserted into the calloop event loop
+        session_notifier: LibSeatSessionNotifier,
+        /// The node of the GPU
+        node: DrmNode,
+        /// The output manager of the GPU
+        device: GbmDrmOutputManager,
+        /// The notifier of the GPU, to be inserted into the calloop event loop
+        device_notifier: DrmDeviceNotifier,
+        /// The connectors of the GPU as of the initial scan, to handle hotplug
+        scanner: DrmScanner,
+        /// The output driving the display
+        drm_output: GbmDrmOutput,
+        /// The renderer
+        renderer: GlesRenderer,
+    },
+    /// A winit window rendered with a `GlesRenderer`
+    #[cfg(all(unix, feature = "backend_winit"))]
+    Winit {
+        /// The window and renderer
+        backend: winit::WinitGraphicsBackend<crate::backend::renderer::gles::GlesRenderer>,
+        /// The event loop of the window, to be inserted into the calloop event loop
+        event_loop: WinitEventLoop,
+    },
+    /// An X11 window rendered with a `GlesRenderer`
+    #[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+    X11 {
+        /// The connection to the X server, to be inserted into the calloop event loop
+        backend: X11Backend,
+        /// The window
+        window: x11::Window,
+        /// The surface presenting buffers to the window
+        surface: X11Surface,
+        /// The renderer
+        renderer: GlesRenderer,
+    },
+    /// A winit window rendered through WGL
+    #[cfg(all(windows, feature = "backend_winit_windows"))]
+    Windows {
+        /// The window and WGL context
+        backend: winit::wgl::WinitWglGraphicsBackend,
+        /// The event loop of the window, to be dispatched periodically
+        event_loop: WinitEventLoop,
+    },
+    /// An offscreen image rendered with a `PixmanRenderer`
+    #[cfg(feature = "renderer_pixman")]
+    Headless {
+        /// The renderer
+        renderer: PixmanRenderer,
+        /// The image to render into
+        buffer: pixman::Image<'static, 'static>,
+    },
+}
+
+impl Backend {
+    /// Returns the kind of this backend
+    pub fn kind(&self) -> BackendKind {
+        match *self {
+            #[cfg(all(
+                feature = "backend_session_libseat",
+                feature = "backend_udev",
+                feature = "backend_gbm",
+                feature = "renderer_gl",
+                feature = "wayland_frontend"
+            ))]
+            Backend::Drm { .. } => BackendKind::Drm,
+            #[cfg(all(unix, feature = "backend_winit"))]
+            Backend::Winit { .. } => BackendKind::Winit,
+            #[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+            Backend::X11 { .. } => BackendKind::X11,
+            #[cfg(all(windows, feature = "backend_winit_windows"))]
+            Backend::Windows { .. } => BackendKind::Windows,
+            #[cfg(feature = "renderer_pixman")]
+            Backend::Headless { .. } => BackendKind::Headless,
+        }
+    }
+}
+
+impl QueryCapabilities for Backend {
+    fn query_capabilities(&self) -> Capabilities {
+        match *self {
+            #[cfg(all(
+                feature = "backend_session_libseat",
+                feature = "backend_udev",
+                feature = "backend_gbm",
+                feature = "renderer_gl",
+                feature = "wayland_frontend"
+            ))]
+            Backend::Drm { ref renderer, .. } => renderer.query_capabilities(),
+            #[cfg(all(unix, feature = "backend_winit"))]
+            Backend::Winit { ref backend, .. } => backend.query_capabilities(),
+            #[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+            Backend::X11 { ref renderer, .. } => renderer.query_capabilities(),
+            #[cfg(all(windows, feature = "backend_winit_windows"))]
+            Backend::Windows { ref backend, .. } => backend.query_capabilities(),
+            #[cfg(feature = "renderer_pixman")]
+            Backend::Headless { ref renderer, .. } => renderer.query_capabilities(),
+        }
+    }
+}
+
+/// Everything created by a [`CompositorBuilder`]
+#[derive(Debug)]
+pub struct Compositor<D: SeatHandler + 'static> {
+    /// The backend
+    pub backend: Backend,
+    /// The seat state, to be returned by [`SeatHandler::seat_state`]
+    pub seat_state: SeatState<D>,
+    /// The seat with a keyboard and a pointer
+    pub seat: Seat<D>,
+    /// The output representing the window or offscreen buffer
+    ///
+    /// Its global still has to be created, if clients should see it.
+    pub output: Output,
+}
+
+/// Builder creating the backend, seat and output of a compositor
+#[derive(Debug, Clone)]
+pub struct CompositorBuilder {
+    backend: Option<BackendKind>,
+    seat_name: String,
+    output_name: String,
+    size: Size<i32, Physical>,
+    refresh: i32,
+}
+
+impl Default for CompositorBuilder {
+    fn default() -> Self {
+        CompositorBuilder {
+            backend: None,
+            seat_name: "seat0".into(),
+            output_name: "smithay-0".into(),
+            size: (1280, 800).into(),
+            refresh: 60_000,
+        }
+    }
+}
+
+impl CompositorBuilder {
+    /// Create a new builder selecting the backend with [`BackendKind::detect`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given backend instead of detecting one
+    pub fn backend(mut self, kind: BackendKind) -> Self {
+        self.backend = Some(kind);
+        self
+    }
+
+    /// Set the name of the seat, defaults to `seat0`
+    pub fn seat_name(mut self, name: impl Into<String>) -> Self {
+        self.seat_name = name.into();
+        self
+    }
+
+    /// Set the name of the output, defaults to `smithay-0`
+    pub fn output_name(mut self, name: impl Into<String>) -> Self {
+        self.output_name = name.into();
+        self
+    }
+
+    /// Set the size of the headless buffer, defaults to 1280x800
+    ///
+    /// Windowed backends use the size of their window instead.
+    pub fn size(mut self, size: impl Into<Size<i32, Physical>>) -> Self {
+        self.size = size.into();
+        self
+    }
+
+    /// Set the refresh rate of the output in mHz, defaults to 60Hz
+    pub fn refresh(mut self, refresh: i32) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Create the backend, seat and output
+    pub fn build<D: SeatHandler + 'static>(self) -> Result<Compositor<D>, Error> {
+        let kind = self.backend.unwrap_or_else(BackendKind::detect);
+        info!(?kind, "Creating backend");
+
+        let output = Output::new(
+            self.output_name,
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: format!("{:?}", kind),
+                serial_number: "Unknown".into(),
+            },
+        );
+
+        let (backend, mode, transform) = match kind {
+            #[cfg(all(
+                feature = "backend_session_libseat",
+                feature = "backend_udev",
+                feature = "backend_gbm",
+                feature = "renderer_gl",
+                feature = "wayland_frontend"
+            ))]
+            BackendKind::Drm => {
+                let (mut session, session_notifier) = LibSeatSession::new()?;
+                let path = crate::backend::udev::primary_gpu(session.seat())?.ok_or(Error::NoDisplay)?;
+                let fd = session.open(
+                    &path,
+                    OFlags::RDWR | OFlags::CLOEXEC | OFlags::NOCTTY | OFlags::NONBLOCK,
+                )?;
+                let fd = DrmDeviceFd::new(DeviceFd::from(fd));
+                let node = DrmNode::from_path(&path)?;
+                let (device, device_notifier) = DrmDevice::new(fd.clone(), true)?;
+
+                let mut scanner = DrmScanner::new();
+                let (connector, crtc) = scanner
+                    .scan_connectors(&device)?
+                    .connected
+                    .into_iter()
+                    .find(|(info, _)| !info.modes().is_empty())
+                    .ok_or(Error::NoDisplay)?;
+                let drm_mode = connector
+                    .modes()
+                    .iter()
+                    .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+                    .copied()
+                    .unwrap_or(connector.modes()[0]);
+                let (w, h) = drm_mode.size();
+                let mode = Mode {
+                    size: (w as i32, h as i32).into(),
+                    refresh: drm_mode.vrefresh() as i32 * 1000,
+                };
+                // the output mode has to be set, before the output can be initialized with it
+                output.change_current_state(Some(mode), Some(Transform::Normal), None, Some((0, 0).into()));
+
+                let gbm = GbmDevice::new(fd)?;
+                let egl = unsafe { EGLDisplay::new(gbm.clone())? };
+                let context = EGLContext::new(&egl)?;
+                let mut renderer = unsafe { GlesRenderer::new(context)? };
+                let renderer_formats = renderer.egl_context().dmabuf_render_formats().clone();
+
+                let allocator =
+                    GbmAllocator::new(gbm.clone(), GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT);
+                let exporter = GbmFramebufferExporter::new(gbm.clone(), node.into());
+                let mut device = DrmOutputManager::new(
+                    device,
+                    allocator,
+                    exporter,
+                    Some(gbm),
+                    [
+                        crate::backend::allocator::Fourcc::Argb8888,
+                        crate::backend::allocator::Fourcc::Xrgb8888,
+                    ],
+                    renderer_formats,
+                );
+                let drm_output = device
+                    .lock()
+                    .initialize_output::<_, SolidColorRenderElement>(
+                        crtc,
+                        drm_mode,
+                        &[connector.handle()],
+                        &output,
+                        None,
+                        &mut renderer,
+                        &DrmOutputRenderElements::new(),
+                    )
+                    .map_err(|err| Error::DrmOutput(err.to_string().into()))?;
+
+                (
+                    Backend::Drm {
+                        session,
+                        session_notifier,
+                        node,
+                        device,
+                        device_notifier,
+                        scanner,
+                        drm_output,
+                        renderer,
+                    },
+                    mode,
+                    Transform::Normal,
+                )
+            }
+            #[cfg(all(unix, feature = "backend_winit"))]
+            BackendKind::Winit => {
+                let (backend, event_loop) = winit::init()?;
+                let size = backend.window_size();
+                (
+                    Backend::Winit { backend, event_loop },
+                    Mode {
+                        size,
+                        refresh: self.refresh,
+                    },
+                    Transform::Flipped180,
+                )
+            }
+            #[cfg(all(feature = "backend_x11", feature = "renderer_gl"))]
+            BackendKind::X11 => {
+                let backend = X11Backend::new()?;
+                let handle = backend.handle();
+                let window = WindowBuilder::new()
+                    .title("Smithay")
+                    .size((self.size.w as u16, self.size.h as u16).into())
+                    .build(&handle)?;
+
+                let (_node, fd) = handle.drm_node()?;
+                let gbm = GbmDevice::new(DeviceFd::from(fd))?;
+                let egl = unsafe { EGLDisplay::new(gbm.clone())? };
+                let context = EGLContext::new(&egl)?;
+                let modifiers = context
+                    .dmabuf_render_formats()
+                    .iter()
+                    .map(|format| format.modifier)
+                    .collect::<std::collections::HashSet<_>>();
+                let surface = handle.create_surface(
+                    &window,
+                    DmabufAllocator(GbmAllocator::new(gbm, GbmBufferFlags::RENDERING)),
+                    modifiers.into_iter(),
+                )?;
+                let renderer = unsafe { GlesRenderer::new(context)? };
+
+                let size = window.size();
+                (
+                    Backend::X11 {
+                        backend,
+                        window,
+                        surface,
+                        renderer,
+                    },
+                    Mode {
+                        size: (size.w as i32, size.h as i32).into(),
+                        refresh: self.refresh,
+                    },
+                    Transform::Normal,
+                )
+            }
+            #[cfg(all(windows, feature = "backend_winit_windows"))]
+            BackendKind::Windows => {
+                let (backend, event_loop) = winit::wgl::init()?;
+                let size = backend.window_size();
+                (
+                    Backend::Windows { backend, event_loop },
+                    Mode {
+                        size,
+                        refresh: self.refresh,
+                    },
+                    Transform::Normal,
+                )
+            }
+            #[cfg(feature = "renderer_pixman")]
+            BackendKind::Headless => {
+                let mut renderer = PixmanRenderer::new()?;
+                let buffer = renderer.create_buffer(Fourcc::Argb8888, (self.size.w, self.size.h).into())?;
+                (
+                    Backend::Headless { renderer, buffer },
+                    Mode {
+                        size: self.size,
+                        refresh: self.refresh,
+                    },
+                    Transform::Normal,
+                )
+            }
+            kind => return Err(Error::Unsupported(kind)),
+        };
+
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_seat(self.seat_name);
+        seat.add_keyboard(Default::default(), 200, 25)?;
+        seat.add_pointer();
+
+        output.change_current_state(Some(mode), Some(transform), None, Some((0, 0).into()));
+        output.set_preferred(mode);
+
+        Ok(Compositor {
+            backend,
+            seat_state,
+            seat,
+            output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BackendKind;
+
+    #[test]
+    fn detect_backend() {
+        let detect = |vars: &[(&str, &str)]| {
+            BackendKind::detect_from(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.into())
+            })
+        };
+
+        assert_eq!(detect(&[(super::BACKEND_ENV, "X11")]), BackendKind::X11);
+        if cfg!(unix) {
+            assert_eq!(
+                detect(&[("CI", "true"), ("DISPLAY", ":0")]),
+                BackendKind::Headless
+            );
+            assert_eq!(detect(&[("WAYLAND_DISPLAY", "wayland-1")]), BackendKind::Winit);
+            // invalid overrides are ignored
+            assert_eq!(
+                detect(&[(super::BACKEND_ENV, "foo"), ("DISPLAY", ":0")]),
+                BackendKind::Winit
+            );
+        }
+    }
+}
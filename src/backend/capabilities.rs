@@ -0,0 +1,65 @@
+//! Runtime introspection of backend and renderer features
+//!
+//! Features like explicit sync, direct scan-out of client buffers or variable refresh rates
+//! depend on the backend, the driver and the connected display rather than on the platform a
+//! compositor is compiled for. Backends and renderers implement [`QueryCapabilities`] to report
+//! the [`Capabilities`] they support, so compositors can branch on them instead of on
+//! `cfg(target_os)`.
+//!
+//! ```no_run
+//! use smithay::backend::capabilities::{Capabilities, QueryCapabilities};
+//!
+//! fn setup(renderer: &impl QueryCapabilities) {
+//!     if renderer.query_capabilities().contains(Capabilities::DMABUF_IMPORT) {
+//!         // advertise linux-dmabuf to clients
+//!     }
+//! }
+//! ```
+//!
+//! The capabilities of an output are the union of its backend and renderer, see
+//! [`Capabilities::union`]. Capabilities which depend on the connected display, like
+//! [`Capabilities::VRR`], may change after hotplug and should be queried again.
+
+bitflags::bitflags! {
+    /// Features supported by a backend or renderer
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Capabilities: u32 {
+        /// Rendering can be synchronized with fences instead of blocking
+        const FENCES = 1;
+        /// Dmabufs can be imported
+        const DMABUF_IMPORT = 2;
+        /// Only the damaged regions have to be presented
+        const PARTIAL_PRESENT = 4;
+        /// The cursor can be presented without rendering it into the frame
+        const HARDWARE_CURSOR = 8;
+        /// The refresh rate can follow the presented content
+        const VRR = 16;
+        /// Frames can be presented immediately, without waiting for the vblank
+        const TEARING = 32;
+        /// Content can be presented with a high dynamic range
+        const HDR = 64;
+    }
+}
+
+/// Types reporting the [`Capabilities`] they support
+pub trait QueryCapabilities {
+    /// Returns the currently supported capabilities
+    fn query_capabilities(&self) -> Capabilities;
+
+    /// Returns whether all of the given capabilities are supported
+    fn supports(&self, capabilities: Capabilities) -> bool {
+        self.query_capabilities().contains(capabilities)
+    }
+}
+
+impl<T: QueryCapabilities + ?Sized> QueryCapabilities for &T {
+    fn query_capabilities(&self) -> Capabilities {
+        (**self).query_capabilities()
+    }
+}
+
+impl<T: QueryCapabilities + ?Sized> QueryCapabilities for &mut T {
+    fn query_capabilities(&self) -> Capabilities {
+        (**self).query_capabilities()
+    }
+}
@@ -156,7 +156,8 @@ use crate::{
             gbm::{GbmAllocator, GbmBuffer, GbmBufferFlags, GbmDevice},
             Allocator, Buffer, Slot, Swapchain,
         },
-        drm::{plane_has_property, DrmError, PlaneDamageClips},
+        capabilities::{Capabilities, QueryCapabilities},
+        drm::{hdr::connector_hdr_capabilities, plane_has_property, DrmError, PlaneDamageClips},
         renderer::{
             buffer_y_inverted,
             damage::{Error as OutputDamageTrackerError, OutputDamageTracker},
@@ -170,15 +171,16 @@ use crate::{
         },
         SwapBuffersError,
     },
-    output::OutputModeSource,
+    output::{ColorEncoding, OutputModeSource},
     utils::{Buffer as BufferCoords, DevPath, Physical, Point, Rectangle, Scale, Size, Transform},
-    wayland::{shm, single_pixel_buffer},
+    wayland::{dmabuf::get_dmabuf, shm, single_pixel_buffer},
 };
 
 use super::{
     error::AccessError,
     exporter::{gbm::GbmFramebufferExporter, gbm::NodeFilter, ExportBuffer, ExportFramebuffer},
     surface::VrrSupport,
+    yuv::{is_yuv, YuvColor},
     DrmSurface, Framebuffer, PlaneClaim, PlaneInfo, Planes,
 };
 
@@ -458,6 +460,7 @@ struct PlaneProperties {
     pub transform: Transform,
     pub alpha: f32,
     pub format: DrmFormat,
+    pub yuv: Option<YuvColor>,
 }
 
 impl PlaneProperties {
@@ -468,6 +471,7 @@ impl PlaneProperties {
             && self.transform == other.transform
             && self.alpha == other.alpha
             && self.format == other.format
+            && self.yuv == other.yuv
     }
 }
 
@@ -811,6 +815,7 @@ impl<B: Buffer, F: Framebuffer> FrameState<B, F> {
                     src: config.properties.src,
                     dst: config.properties.dst,
                     alpha: config.properties.alpha,
+                    yuv: config.properties.yuv,
                     transform: config.properties.transform,
                     damage_clips: config.damage_clips.as_ref().map(|d| d.blob()),
                     fb: *config.buffer.as_ref(),
@@ -1033,6 +1038,11 @@ bitflags::bitflags! {
         const ALLOW_CURSOR_PLANE_SCANOUT = 8;
         /// Return `EmptyFrame`, if only the cursor plane would have been updated
         const SKIP_CURSOR_ONLY_UPDATES = 16;
+        /// Allow to realize the frame by scanning out elements backed by YCbCr client buffers
+        /// on overlay planes, even if they are not marked as [`Kind::ScanoutCandidate`]
+        ///
+        /// Requires [`FrameFlags::ALLOW_OVERLAY_PLANE_SCANOUT`] to be set as well.
+        const ALLOW_YUV_OVERLAY_PLANE_SCANOUT = 32;
         /// Allow to realize the frame by assigning elements on any plane
         const ALLOW_SCANOUT = Self::ALLOW_PRIMARY_PLANE_SCANOUT.bits() | Self::ALLOW_OVERLAY_PLANE_SCANOUT.bits() | Self::ALLOW_CURSOR_PLANE_SCANOUT.bits();
         /// Safe default set of flags
@@ -1081,6 +1091,7 @@ where
     element_opaque_regions_workhouse: Vec<Rectangle<i32, Physical>>,
 
     debug_flags: DebugFlags,
+    yuv_colors: HashMap<Id, YuvColor>,
     span: tracing::Span,
 }
 
@@ -1267,6 +1278,7 @@ where
                         element_opaque_regions_workhouse: Vec::new(),
                         supports_fencing,
                         debug_flags: DebugFlags::empty(),
+                        yuv_colors: HashMap::new(),
                         span,
                     };
 
@@ -1449,6 +1461,7 @@ where
             element_opaque_regions_workhouse: Vec::new(),
             supports_fencing,
             debug_flags: DebugFlags::empty(),
+            yuv_colors: HashMap::new(),
             span,
         };
 
@@ -1560,6 +1573,7 @@ where
                     transform: Transform::Normal,
                     alpha: 1.0,
                     format: buffer.format(),
+                    yuv: None,
                 },
                 buffer: DrmScanoutBuffer {
                     buffer: ScanoutBuffer::Swapchain(Arc::new(buffer)),
@@ -1766,6 +1780,12 @@ where
             .unwrap()
             .clone();
 
+        // forget the colors of elements that are no longer rendered
+        if !self.yuv_colors.is_empty() {
+            self.yuv_colors
+                .retain(|id, _| elements.iter().any(|element| element.id() == id));
+        }
+
         let mut opaque_regions: Vec<Rectangle<i32, Physical>> = std::mem::take(&mut self.opaque_regions);
         std::mem::swap(&mut self.previous_element_states, &mut self.element_states);
         let mut element_states = std::mem::take(&mut self.element_states);
@@ -1826,6 +1846,7 @@ where
                     transform: Transform::Normal,
                     alpha: 1.0,
                     format: primary_plane_buffer.format(),
+                    yuv: None,
                 },
                 buffer: DrmScanoutBuffer {
                     buffer: ScanoutBuffer::Swapchain(Arc::new(primary_plane_buffer)),
@@ -2527,6 +2548,31 @@ where
         Ok(())
     }
 
+    /// Restores the compositor after the [`DrmDevice`](crate::backend::drm::DrmDevice) got re-activated,
+    /// e.g. after a VT switch.
+    ///
+    /// In addition to [`reset_state`](DrmCompositor::reset_state) this drops the buffers of the swapchain,
+    /// so new framebuffers are created and the whole output is redrawn by the next
+    /// [`render_frame`](DrmCompositor::render_frame). Frames queued or pending while the device was paused
+    /// are discarded, as their page flips might never be reported.
+    ///
+    /// Returns the user data of the discarded frames.
+    pub fn restore(&mut self) -> Result<Vec<U>, DrmError> {
+        self.reset_state()?;
+
+        let mut discarded = Vec::new();
+        if let Some(PendingFrame { user_data, .. }) = self.pending_frame.take() {
+            discarded.push(user_data);
+        }
+        if let Some(QueuedFrame { user_data, .. }) = self.queued_frame.take() {
+            discarded.push(user_data);
+        }
+        self.next_frame = None;
+        self.swapchain.reset_buffers();
+
+        Ok(discarded)
+    }
+
     #[profiling::function]
     fn submit(&mut self) -> FrameResult<(), A, F> {
         let QueuedFrame {
@@ -2748,6 +2794,22 @@ where
         self.debug_flags
     }
 
+    /// Set the color properties used to scan out the YCbCr buffer of an element
+    ///
+    /// By default they are guessed from the size of the buffer, see [`YuvColor::for_buffer`].
+    /// Passing `None` restores the default. The value is ignored for non-YCbCr buffers and
+    /// forgotten once a frame is rendered without the element.
+    pub fn set_yuv_color(&mut self, id: &Id, color: Option<YuvColor>) {
+        match color {
+            Some(color) => {
+                self.yuv_colors.insert(id.clone(), color);
+            }
+            None => {
+                self.yuv_colors.remove(id);
+            }
+        }
+    }
+
     /// Returns a reference to the underlying drm surface
     pub fn surface(&self) -> &DrmSurface {
         &self.surface
@@ -3364,6 +3426,7 @@ where
                 alpha: 1.0,
                 transform: Transform::Normal,
                 format: framebuffer.format(),
+                yuv: None,
             },
             buffer: DrmScanoutBuffer {
                 buffer: ScanoutBuffer::Cursor(Arc::new(cursor_buffer)),
@@ -3554,12 +3617,21 @@ where
             output_transform.invert(),
         );
         let alpha = element.alpha();
+        let format = fb.format();
+        let buffer_size = match &underlying_storage {
+            UnderlyingStorage::Wayland(buffer) => crate::backend::renderer::buffer_dimensions(buffer),
+            UnderlyingStorage::Memory { .. } => None,
+        };
+        let yuv = buffer_size
+            .and_then(|size| YuvColor::for_buffer(format.code, size))
+            .map(|guess| self.yuv_colors.get(element_id).copied().unwrap_or(guess));
         let properties = PlaneProperties {
             src,
             dst,
             alpha,
             transform,
-            format: fb.format(),
+            format,
+            yuv,
         };
         let buffer: DrmScanoutBuffer<
             <A as Allocator>::Buffer,
@@ -3716,8 +3788,13 @@ where
             return Err(None);
         }
 
-        // only try to assign elements on an overlay plane that indicate so
-        if element.kind() != Kind::ScanoutCandidate && element.kind() != Kind::Cursor {
+        // only try to assign elements on an overlay plane that indicate so,
+        // or video buffers if allowed to
+        if element.kind() != Kind::ScanoutCandidate
+            && element.kind() != Kind::Cursor
+            && !(frame_flags.contains(FrameFlags::ALLOW_YUV_OVERLAY_PLANE_SCANOUT)
+                && has_yuv_storage(renderer, element))
+        {
             trace!(
                 "skipping element {:?} on overlay plane(s), element kind not scanout-candidate/cursor",
                 element.id(),
@@ -4088,6 +4165,89 @@ where
     }
 }
 
+/// Suspending does nothing, the rendering work is owned by the renderer. Resuming
+/// [restores](DrmCompositor::restore) the compositor, discarding the frames whose page flip may never
+/// be reported; call [`DrmCompositor::restore`] directly to get their user data.
+impl<A, F, U, G> crate::backend::power::PowerHooks for DrmCompositor<A, F, U, G>
+where
+    A: Allocator,
+    <A as Allocator>::Error: std::error::Error + Send + Sync,
+    <A as Allocator>::Buffer: AsDmabuf,
+    <A::Buffer as AsDmabuf>::Error: std::error::Error + Send + Sync + std::fmt::Debug,
+    F: ExportFramebuffer<A::Buffer>,
+    <F as ExportFramebuffer<A::Buffer>>::Framebuffer: std::fmt::Debug + Send + Sync + 'static,
+    <F as ExportFramebuffer<A::Buffer>>::Error: std::error::Error + Send + Sync,
+    G: AsFd + Clone,
+{
+    type Error = DrmError;
+
+    fn suspend(&mut self) -> Result<(), DrmError> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), DrmError> {
+        self.restore().map(|_| ())
+    }
+}
+
+impl<A, F, U, G> QueryCapabilities for DrmCompositor<A, F, U, G>
+where
+    A: Allocator,
+    F: ExportFramebuffer<A::Buffer>,
+    <F as ExportFramebuffer<A::Buffer>>::Framebuffer: std::fmt::Debug + Send + Sync + 'static,
+    G: AsFd + 'static,
+{
+    /// Reports [`Capabilities::VRR`] and [`Capabilities::HDR`] if all current connectors
+    /// support them.
+    fn query_capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::empty();
+        if self.supports_fencing {
+            capabilities |= Capabilities::FENCES;
+        }
+        if plane_has_property(&*self.surface, self.surface.plane(), "FB_DAMAGE_CLIPS").unwrap_or(false) {
+            capabilities |= Capabilities::PARTIAL_PRESENT;
+        }
+        if !self.planes.cursor.is_empty() {
+            capabilities |= Capabilities::HARDWARE_CURSOR;
+        }
+
+        let connectors = self.surface.current_connectors().into_iter().collect::<Vec<_>>();
+        if !connectors.is_empty() {
+            if connectors.iter().all(|conn| {
+                self.surface
+                    .vrr_supported(*conn)
+                    .map(|vrr| vrr != VrrSupport::NotSupported)
+                    .unwrap_or(false)
+            }) {
+                capabilities |= Capabilities::VRR;
+            }
+            if connectors.iter().all(|conn| {
+                connector_hdr_capabilities(&*self.surface, *conn)
+                    .map(|hdr| hdr.encodings.contains(&ColorEncoding::Pq))
+                    .unwrap_or(false)
+            }) {
+                capabilities |= Capabilities::HDR;
+            }
+        }
+
+        capabilities
+    }
+}
+
+#[inline]
+fn has_yuv_storage<R, E>(renderer: &mut R, element: &E) -> bool
+where
+    R: Renderer,
+    E: RenderElement<R>,
+{
+    match element.underlying_storage(renderer) {
+        Some(UnderlyingStorage::Wayland(buffer)) => get_dmabuf(buffer)
+            .map(|dmabuf| is_yuv(dmabuf.format().code))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
 #[inline]
 fn apply_underlying_storage_transform(
     element_transform: Transform,
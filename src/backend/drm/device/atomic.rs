@@ -28,6 +28,7 @@ pub struct PropMapping {
     pub connectors: HashMap<connector::Handle, HashMap<String, property::Handle>>,
     pub crtcs: HashMap<crtc::Handle, HashMap<String, property::Handle>>,
     pub planes: HashMap<plane::Handle, HashMap<String, property::Handle>>,
+    pub enums: HashMap<property::Handle, HashMap<String, u64>>,
 }
 
 impl PropMapping {
@@ -78,6 +79,23 @@ impl PropMapping {
             })
             .copied()
     }
+
+    pub(crate) fn plane_enum_value(
+        &self,
+        handle: plane::Handle,
+        name: &'static str,
+        value: &str,
+    ) -> Result<u64, Error> {
+        let prop = self.plane_prop_handle(handle, name)?;
+        self.enums
+            .get(&prop)
+            .and_then(|values| values.get(value))
+            .copied()
+            .ok_or(Error::UnknownProperty {
+                handle: handle.into(),
+                name,
+            })
+    }
 }
 
 #[derive(Debug)]
@@ -134,6 +152,7 @@ impl AtomicDrmDevice {
         map_props(&dev.fd, res_handles.connectors(), &mut mapping.connectors)?;
         map_props(&dev.fd, res_handles.crtcs(), &mut mapping.crtcs)?;
         map_props(&dev.fd, &planes, &mut mapping.planes)?;
+        map_enum_values(&dev.fd, &mapping.planes, PLANE_ENUMS, &mut mapping.enums);
 
         dev.old_state = old_state;
         trace!("Mapping: {:#?}", mapping);
@@ -305,6 +324,35 @@ where
         })
 }
 
+// enum properties of planes, whose values are referred to by name
+const PLANE_ENUMS: &[&str] = &["COLOR_ENCODING", "COLOR_RANGE"];
+
+fn map_enum_values<D, T>(
+    fd: &D,
+    mapping: &HashMap<T, HashMap<String, property::Handle>>,
+    names: &[&str],
+    enums: &mut HashMap<property::Handle, HashMap<String, u64>>,
+) where
+    D: ControlDevice,
+{
+    let props = mapping
+        .values()
+        .flat_map(|props| names.iter().filter_map(|name| props.get(*name)));
+    for prop in props {
+        let Ok(info) = fd.get_property(*prop) else {
+            continue;
+        };
+        if let property::ValueType::Enum(values) = info.value_type() {
+            let (_, values) = values.values();
+            let values = values
+                .iter()
+                .map(|value| (value.name().to_string_lossy().into_owned(), value.value()))
+                .collect();
+            enums.insert(*prop, values);
+        }
+    }
+}
+
 /// Create a mapping of property names and handles for given handles of a given drm resource type.
 /// You may use this to easily lookup properties by name instead of going through this procedure manually.
 pub(in crate::backend::drm) fn map_props<D, T>(
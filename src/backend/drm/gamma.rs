@@ -0,0 +1,54 @@
+//! Gamma tables of crtcs
+//!
+//! Used to apply color temperature changes like
+//! [`GammaRamp::for_temperature`](crate::output::GammaRamp::for_temperature) without touching
+//! the rendered content:
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmDevice, gamma};
+//! use smithay::output::{GammaRamp, NightLightSchedule};
+//! # fn apply(device: &DrmDevice, crtc: smithay::reexports::drm::control::crtc::Handle) -> Result<(), smithay::backend::drm::DrmError> {
+//! let temperature = NightLightSchedule::default().current_temperature(0);
+//! let size = gamma::gamma_size(device.device_fd(), crtc)?;
+//! gamma::set_gamma(device.device_fd(), crtc, &GammaRamp::for_temperature(temperature, size))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use drm::control::{crtc, Device as ControlDevice};
+
+use super::{error::AccessError, DrmError};
+use crate::{output::GammaRamp, utils::DevPath};
+
+/// Number of entries of the gamma table of a crtc
+///
+/// Returns `0` if the crtc does not support gamma tables.
+pub fn gamma_size(dev: &(impl ControlDevice + DevPath), crtc: crtc::Handle) -> Result<usize, DrmError> {
+    let info = dev.get_crtc(crtc).map_err(|source| {
+        DrmError::Access(AccessError {
+            errmsg: "Error loading crtc info",
+            dev: dev.dev_path(),
+            source,
+        })
+    })?;
+    Ok(info.gamma_length() as usize)
+}
+
+/// Set the gamma table of a crtc
+///
+/// The size of the ramp has to match [`gamma_size`]. The table is applied immediately and
+/// persists across commits, until it is changed again.
+pub fn set_gamma(
+    dev: &(impl ControlDevice + DevPath),
+    crtc: crtc::Handle,
+    ramp: &GammaRamp,
+) -> Result<(), DrmError> {
+    dev.set_gamma(crtc, &ramp.red, &ramp.green, &ramp.blue)
+        .map_err(|source| {
+            DrmError::Access(AccessError {
+                errmsg: "Failed to set gamma table",
+                dev: dev.dev_path(),
+                source,
+            })
+        })
+}
@@ -0,0 +1,241 @@
+//! HDR signalling for drm connectors
+//!
+//! Displays are switched into HDR10 mode through two connector properties: `Colorspace`
+//! selects the BT.2020 primaries and `HDR_OUTPUT_METADATA` carries the transfer function and the
+//! static metadata of the content, sent to the display as an HDMI or DisplayPort infoframe.
+//!
+//! The framebuffers scanned out afterwards are expected to already be PQ encoded, so render
+//! into one of the [`ColorEncoding::buffer_formats`] of [`ColorEncoding::Pq`].
+//!
+//! The properties are set directly on the connector, which may trigger a modeset on some drivers.
+//! Ideally call [`set_connector_hdr`] before the first frame on a connector.
+
+use drm::control::{connector, property, Device as ControlDevice};
+
+use super::{error::AccessError, DrmError};
+use crate::{
+    output::{ColorEncoding, HdrCapabilities, HdrMetadata},
+    utils::DevPath,
+};
+
+const HDR_OUTPUT_METADATA: &str = "HDR_OUTPUT_METADATA";
+const COLORSPACE: &str = "Colorspace";
+
+// EOTF value of CTA-861-G, table 3
+const EOTF_SMPTE_ST2084: u8 = 2;
+// static metadata type 1, the only one defined
+const STATIC_METADATA_TYPE1: u8 = 0;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct XyCoord {
+    x: u16,
+    y: u16,
+}
+
+/// Mirrors `struct hdr_metadata_infoframe` of the kernel uapi
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct HdrMetadataInfoframe {
+    eotf: u8,
+    metadata_type: u8,
+    display_primaries: [XyCoord; 3],
+    white_point: XyCoord,
+    max_display_mastering_luminance: u16,
+    min_display_mastering_luminance: u16,
+    max_cll: u16,
+    max_fall: u16,
+}
+
+/// Mirrors `struct hdr_output_metadata` of the kernel uapi
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct HdrOutputMetadata {
+    metadata_type: u32,
+    hdmi_metadata_type1: HdrMetadataInfoframe,
+}
+
+// chromaticities are encoded in units of 0.00002
+fn xy(coord: crate::output::Chromaticity) -> XyCoord {
+    let encode = |v: f32| (v * 50_000.0).round().clamp(0.0, u16::MAX as f32) as u16;
+    XyCoord {
+        x: encode(coord.x),
+        y: encode(coord.y),
+    }
+}
+
+fn nits(value: f32) -> u16 {
+    value.round().clamp(0.0, u16::MAX as f32) as u16
+}
+
+fn output_metadata(eotf: u8, metadata: &HdrMetadata) -> HdrOutputMetadata {
+    let primaries = &metadata.primaries;
+    HdrOutputMetadata {
+        metadata_type: STATIC_METADATA_TYPE1 as u32,
+        hdmi_metadata_type1: HdrMetadataInfoframe {
+            eotf,
+            metadata_type: STATIC_METADATA_TYPE1,
+            display_primaries: [xy(primaries.red), xy(primaries.green), xy(primaries.blue)],
+            white_point: xy(primaries.white),
+            max_display_mastering_luminance: nits(metadata.max_luminance),
+            // the minimum is encoded in units of 0.0001 cd/m²
+            min_display_mastering_luminance: nits(metadata.min_luminance * 10_000.0),
+            max_cll: nits(metadata.max_cll),
+            max_fall: nits(metadata.max_fall),
+        },
+    }
+}
+
+fn connector_props(
+    dev: &(impl ControlDevice + DevPath),
+    conn: connector::Handle,
+) -> Result<Vec<(property::Info, property::RawValue)>, DrmError> {
+    let props = dev.get_properties(conn).map_err(|source| {
+        DrmError::Access(AccessError {
+            errmsg: "Failed to get properties of connector",
+            dev: dev.dev_path(),
+            source,
+        })
+    })?;
+    let (ids, vals) = props.as_props_and_values();
+    ids.iter()
+        .zip(vals.iter())
+        .map(|(&id, &val)| {
+            let info = dev.get_property(id).map_err(|source| {
+                DrmError::Access(AccessError {
+                    errmsg: "Failed to get property info",
+                    dev: dev.dev_path(),
+                    source,
+                })
+            })?;
+            Ok((info, val))
+        })
+        .collect()
+}
+
+fn find_prop<'a>(
+    props: &'a [(property::Info, property::RawValue)],
+    name: &str,
+) -> Option<&'a property::Info> {
+    props
+        .iter()
+        .map(|(info, _)| info)
+        .find(|info| info.name().to_str().map(|x| x == name).unwrap_or(false))
+}
+
+fn enum_value(info: &property::Info, name: &str) -> Option<property::RawValue> {
+    match info.value_type() {
+        property::ValueType::Enum(values) => values
+            .values()
+            .1
+            .iter()
+            .find(|value| value.name().to_str().map(|x| x == name).unwrap_or(false))
+            .map(|value| value.value()),
+        _ => None,
+    }
+}
+
+/// Query the HDR capabilities of a connector
+///
+/// A connector supports [`ColorEncoding::Pq`] if the driver exposes the `HDR_OUTPUT_METADATA`
+/// property and allows selecting the BT.2020 colorspace. Whether the connected display
+/// accepts HDR signals is only known from its EDID, which is not parsed here.
+pub fn connector_hdr_capabilities(
+    dev: &(impl ControlDevice + DevPath),
+    conn: connector::Handle,
+) -> Result<HdrCapabilities, DrmError> {
+    let props = connector_props(dev, conn)?;
+    let mut encodings = vec![ColorEncoding::Srgb];
+    let bt2020 = find_prop(&props, COLORSPACE).and_then(|info| enum_value(info, "BT2020_RGB"));
+    if find_prop(&props, HDR_OUTPUT_METADATA).is_some() && bt2020.is_some() {
+        encodings.push(ColorEncoding::Pq);
+    }
+    Ok(HdrCapabilities {
+        encodings,
+        ..Default::default()
+    })
+}
+
+/// Signal the color encoding of the scanned out content to the display of a connector
+///
+/// [`ColorEncoding::ScRgb`] cannot be scanned out and has to be converted to
+/// [`ColorEncoding::Pq`] by the renderer. Without `metadata` the display falls back to
+/// [`HdrMetadata::default`].
+pub fn set_connector_hdr(
+    dev: &(impl ControlDevice + DevPath),
+    conn: connector::Handle,
+    encoding: ColorEncoding,
+    metadata: Option<&HdrMetadata>,
+) -> Result<(), DrmError> {
+    let props = connector_props(dev, conn)?;
+    let unknown = |name| DrmError::UnknownProperty {
+        handle: conn.into(),
+        name,
+    };
+
+    let (colorspace, blob) = match encoding {
+        ColorEncoding::Srgb => ("Default", None),
+        ColorEncoding::Pq => (
+            "BT2020_RGB",
+            Some(output_metadata(
+                EOTF_SMPTE_ST2084,
+                &metadata.copied().unwrap_or_default(),
+            )),
+        ),
+        ColorEncoding::ScRgb => return Err(unknown(COLORSPACE)),
+    };
+
+    let metadata_prop = find_prop(&props, HDR_OUTPUT_METADATA).ok_or_else(|| unknown(HDR_OUTPUT_METADATA))?;
+    let colorspace_prop = find_prop(&props, COLORSPACE).ok_or_else(|| unknown(COLORSPACE))?;
+    let colorspace_value = enum_value(colorspace_prop, colorspace).ok_or_else(|| unknown(COLORSPACE))?;
+
+    // a value of 0 removes the metadata, which switches the display back to SDR
+    let blob_value = match blob {
+        Some(blob) => dev
+            .create_property_blob(&blob)
+            .map_err(|source| {
+                DrmError::Access(AccessError {
+                    errmsg: "Failed to create HDR_OUTPUT_METADATA blob",
+                    dev: dev.dev_path(),
+                    source,
+                })
+            })?
+            .into(),
+        None => 0,
+    };
+
+    for (prop, value) in [(metadata_prop, blob_value), (colorspace_prop, colorspace_value)] {
+        dev.set_property(conn, prop.handle(), value).map_err(|source| {
+            DrmError::Access(AccessError {
+                errmsg: "Failed to set HDR connector property",
+                dev: dev.dev_path(),
+                source,
+            })
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_hdr10_metadata() {
+        let packed = output_metadata(EOTF_SMPTE_ST2084, &HdrMetadata::default());
+        // layout of the kernel uapi structs, the infoframe is padded to the alignment of the u32 tag
+        assert_eq!(std::mem::size_of::<HdrMetadataInfoframe>(), 26);
+        assert_eq!(std::mem::size_of::<HdrOutputMetadata>(), 32);
+
+        let frame = packed.hdmi_metadata_type1;
+        assert_eq!(frame.eotf, 2);
+        assert_eq!(frame.display_primaries[0], XyCoord { x: 35400, y: 14600 });
+        assert_eq!(frame.display_primaries[1], XyCoord { x: 8500, y: 39850 });
+        assert_eq!(frame.display_primaries[2], XyCoord { x: 6550, y: 2300 });
+        assert_eq!(frame.white_point, XyCoord { x: 15635, y: 16450 });
+        assert_eq!(frame.max_display_mastering_luminance, 1000);
+        assert_eq!(frame.min_display_mastering_luminance, 50);
+        assert_eq!(frame.max_cll, 1000);
+        assert_eq!(frame.max_fall, 400);
+    }
+}
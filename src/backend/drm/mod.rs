@@ -63,6 +63,20 @@
 //! using hardware composition.
 //! See the [`compositor`] module docs for more information on that topic.
 //!
+//! ### VT switching
+//!
+//! Once the [`Session`](crate::backend::session::Session) gets paused, e.g. by switching to another
+//! VT, call [`DrmDevice::pause`] to stop rendering and release the drm master. When the session gets
+//! activated again, [`DrmDevice::activate`] re-acquires the drm master. Afterwards restore every
+//! `DrmCompositor` with `DrmCompositor::restore` and render a new frame for every output, as
+//! another drm master may have changed the contents of the screen. The `DrmOutputManager` does
+//! all of this in its `pause` and `activate` methods.
+//!
+//! ### Hotplug
+//!
+//! The [`DrmScanner`](scanner::DrmScanner) re-probes the connectors of a device on hotplug events,
+//! assigns crtcs to newly connected ones and reports the outputs to add or remove.
+//!
 //! ## [`DrmNode`]
 //!
 //! A drm node refers to a drm device and the capabilities that may be performed using the node.
@@ -77,10 +91,14 @@ pub(crate) mod device;
 pub mod dumb;
 mod error;
 pub mod exporter;
+pub mod gamma;
 #[cfg(feature = "backend_gbm")]
 pub mod gbm;
+pub mod hdr;
 #[cfg(all(feature = "wayland_frontend", feature = "backend_gbm"))]
 pub mod output;
+pub mod scanner;
+pub mod yuv;
 
 mod surface;
 
@@ -597,17 +597,41 @@ where
     /// the device was not active before. Otherwise you need to make sure there are no
     /// conflicting requirements when enabling or creating surfaces or you are prepared
     /// to handle errors caused by those.
-    pub fn activate(&mut self, disable_connectors: bool) -> Result<(), DrmError> {
+    ///
+    /// All outputs are restored with [`DrmCompositor::restore`], so the next frame of every
+    /// output is rendered into new framebuffers with full damage. Frames still pending from
+    /// before the device was paused are discarded, their user data is returned together with
+    /// the crtc of their output, e.g. to send pending presentation feedback as discarded.
+    pub fn activate(&mut self, disable_connectors: bool) -> Result<Vec<(crtc::Handle, U)>, DrmError> {
         self.device.activate(disable_connectors)?;
 
         // We request a write guard here to guarantee unique access
-        for compositor in self.compositor.values_mut() {
-            if let Err(err) = compositor.get_mut().unwrap().reset_state() {
-                tracing::warn!("Failed to reset drm surface state: {}", err);
-            }
+        let mut discarded = Vec::new();
+        for (crtc, compositor) in self.compositor.iter_mut() {
+            collect_discarded(*crtc, compositor.get_mut().unwrap().restore(), &mut discarded);
         }
 
-        Ok(())
+        Ok(discarded)
+    }
+}
+
+fn collect_discarded<U>(
+    crtc: crtc::Handle,
+    restored: Result<Vec<U>, DrmError>,
+    discarded: &mut Vec<(crtc::Handle, U)>,
+) {
+    match restored {
+        Ok(frames) => {
+            if !frames.is_empty() {
+                tracing::debug!(
+                    ?crtc,
+                    "Discarded {} frames pending before the pause",
+                    frames.len()
+                );
+            }
+            discarded.extend(frames.into_iter().map(|user_data| (crtc, user_data)));
+        }
+        Err(err) => tracing::warn!(?crtc, "Failed to reset drm surface state: {}", err),
     }
 }
 
@@ -1028,3 +1052,30 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use drm::control::{crtc, from_u32};
+
+    use super::collect_discarded;
+    use crate::backend::drm::DrmError;
+
+    fn crtc(id: u32) -> crtc::Handle {
+        from_u32(id).unwrap()
+    }
+
+    #[test]
+    fn discarded_frames_keep_their_user_data() {
+        let mut discarded = Vec::new();
+        collect_discarded(crtc(10), Ok(vec!["pending", "queued"]), &mut discarded);
+        collect_discarded(crtc(11), Ok(Vec::new()), &mut discarded);
+        // a failed restore does not lose the frames of the other outputs
+        collect_discarded(crtc(12), Err(DrmError::DeviceInactive), &mut discarded);
+        collect_discarded(crtc(13), Ok(vec!["pending"]), &mut discarded);
+
+        assert_eq!(
+            discarded,
+            vec![(crtc(10), "pending"), (crtc(10), "queued"), (crtc(13), "pending")]
+        );
+    }
+}
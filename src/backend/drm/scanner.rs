@@ -0,0 +1,318 @@
+//! Tracking of connectors and their crtcs across hotplug events
+//!
+//! The [`DrmScanner`] re-probes the connectors of a device, e.g. whenever
+//! [`UdevEvent::Changed`](crate::backend::udev::UdevEvent::Changed) is received for it, and reports
+//! the connectors that got connected or disconnected since the last scan. Every connected
+//! connector is assigned a crtc to drive it.
+//!
+//! If there are not enough crtcs, existing assignments are moved to other crtcs to free up a
+//! crtc for a new connector where possible. A moved connector is reported as disconnected from
+//! its old crtc and connected to the new one, so the compositor recreates its surface.
+//! Connectors that can not be driven by any free crtc stay pending, until a crtc becomes free
+//! on a later scan.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmDevice, scanner::{DrmScanEvent, DrmScanner}};
+//! # fn scan(device: &DrmDevice, scanner: &mut DrmScanner) -> Result<(), smithay::backend::drm::DrmError> {
+//! for event in scanner.scan_connectors(device.device_fd())? {
+//!     match event {
+//!         DrmScanEvent::Connected { connector, crtc } => {
+//!             // create a surface on `crtc` and add an output for `connector`
+//!         }
+//!         DrmScanEvent::Disconnected { connector, crtc } => {
+//!             // remove the output of `connector` and the surface on `crtc`
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use drm::control::{connector, crtc, Device as ControlDevice};
+
+use super::{error::AccessError, DrmError};
+use crate::utils::DevPath;
+
+/// Change of a connector found by [`DrmScanner::scan_connectors`]
+#[derive(Debug, Clone)]
+pub enum DrmScanEvent {
+    /// A connector got connected and was assigned a crtc
+    Connected {
+        /// Info of the connector
+        connector: connector::Info,
+        /// Crtc driving the connector
+        crtc: crtc::Handle,
+    },
+    /// A connector got disconnected or lost its crtc
+    Disconnected {
+        /// Info of the connector
+        connector: connector::Info,
+        /// Crtc that drove the connector
+        crtc: crtc::Handle,
+    },
+}
+
+/// Events of a scan, disconnects are ordered before connects
+#[derive(Debug, Default)]
+pub struct DrmScanResult {
+    /// Connectors that got connected
+    pub connected: Vec<(connector::Info, crtc::Handle)>,
+    /// Connectors that got disconnected
+    pub disconnected: Vec<(connector::Info, crtc::Handle)>,
+}
+
+impl DrmScanResult {
+    /// Returns whether the scan found no changes
+    pub fn is_empty(&self) -> bool {
+        self.connected.is_empty() && self.disconnected.is_empty()
+    }
+}
+
+impl IntoIterator for DrmScanResult {
+    type Item = DrmScanEvent;
+    type IntoIter = std::vec::IntoIter<DrmScanEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.disconnected
+            .into_iter()
+            .map(|(connector, crtc)| DrmScanEvent::Disconnected { connector, crtc })
+            .chain(
+                self.connected
+                    .into_iter()
+                    .map(|(connector, crtc)| DrmScanEvent::Connected { connector, crtc }),
+            )
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+/// Tracks the connected connectors of a device and the crtcs driving them
+#[derive(Debug, Default)]
+pub struct DrmScanner {
+    connectors: HashMap<connector::Handle, connector::Info>,
+    crtcs: HashMap<connector::Handle, crtc::Handle>,
+}
+
+impl DrmScanner {
+    /// Create a new scanner without any known connectors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Re-probe all connectors of the device and assign crtcs to connected ones
+    ///
+    /// Returns the changes since the last scan. The first scan reports every connector that
+    /// is connected and can be driven.
+    pub fn scan_connectors(
+        &mut self,
+        dev: &(impl ControlDevice + DevPath),
+    ) -> Result<DrmScanResult, DrmError> {
+        let access_error = |errmsg, source| {
+            DrmError::Access(AccessError {
+                errmsg,
+                dev: dev.dev_path(),
+                source,
+            })
+        };
+
+        let resources = dev
+            .resource_handles()
+            .map_err(|source| access_error("Error loading drm resources", source))?;
+
+        let mut connected = Vec::new();
+        for handle in resources.connectors() {
+            let info = dev
+                .get_connector(*handle, true)
+                .map_err(|source| access_error("Error loading connector info", source))?;
+            if info.state() != connector::State::Connected {
+                continue;
+            }
+
+            // prefer the crtc the connector is currently driven by, e.g. set up by the firmware,
+            // to avoid a modeset
+            let current = info
+                .current_encoder()
+                .and_then(|encoder| dev.get_encoder(encoder).ok())
+                .and_then(|encoder| encoder.crtc());
+            let mut possible = current.into_iter().collect::<Vec<_>>();
+            for encoder in info.encoders() {
+                let encoder = dev
+                    .get_encoder(*encoder)
+                    .map_err(|source| access_error("Error loading encoder info", source))?;
+                for crtc in resources.filter_crtcs(encoder.possible_crtcs()) {
+                    if !possible.contains(&crtc) {
+                        possible.push(crtc);
+                    }
+                }
+            }
+            connected.push((info, possible));
+        }
+
+        let mut result = DrmScanResult::default();
+
+        // forget connectors that are gone, e.g. MST connectors, or got disconnected
+        let still_connected = connected
+            .iter()
+            .map(|(info, _)| info.handle())
+            .collect::<HashSet<_>>();
+        let gone = self
+            .connectors
+            .keys()
+            .filter(|handle| !still_connected.contains(*handle))
+            .copied()
+            .collect::<Vec<_>>();
+        for handle in gone {
+            let info = self.connectors.remove(&handle).unwrap();
+            if let Some(crtc) = self.crtcs.remove(&handle) {
+                result.disconnected.push((info, crtc));
+            }
+        }
+
+        let candidates = connected
+            .iter()
+            .map(|(info, possible)| (info.handle(), possible.as_slice()))
+            .collect::<Vec<_>>();
+        let crtcs = assign_crtcs(&candidates, &self.crtcs);
+
+        for (info, _) in connected {
+            let handle = info.handle();
+            let old = self.crtcs.get(&handle).copied();
+            let new = crtcs.get(&handle).copied();
+            if old != new {
+                if let Some(crtc) = old {
+                    result.disconnected.push((info.clone(), crtc));
+                }
+                if let Some(crtc) = new {
+                    result.connected.push((info.clone(), crtc));
+                }
+            }
+            self.connectors.insert(handle, info);
+        }
+        self.crtcs = crtcs;
+
+        Ok(result)
+    }
+
+    /// Returns the crtc assigned to the given connector
+    pub fn crtc_for_connector(&self, connector: connector::Handle) -> Option<crtc::Handle> {
+        self.crtcs.get(&connector).copied()
+    }
+
+    /// Returns the infos of all connected connectors as of the last scan
+    ///
+    /// This includes connectors without a crtc.
+    pub fn connectors(&self) -> impl Iterator<Item = &connector::Info> {
+        self.connectors.values()
+    }
+
+    /// Returns all connected connectors with a crtc assigned as of the last scan
+    pub fn crtcs(&self) -> impl Iterator<Item = (&connector::Info, crtc::Handle)> {
+        self.crtcs
+            .iter()
+            .map(|(connector, crtc)| (&self.connectors[connector], *crtc))
+    }
+}
+
+/// Assign crtcs to as many connectors as possible
+///
+/// Connectors keep their current crtc if possible, otherwise they are moved to another one
+/// to make room for connectors without a crtc.
+fn assign_crtcs(
+    connectors: &[(connector::Handle, &[crtc::Handle])],
+    current: &HashMap<connector::Handle, crtc::Handle>,
+) -> HashMap<connector::Handle, crtc::Handle> {
+    fn augment(
+        connector: usize,
+        connectors: &[(connector::Handle, &[crtc::Handle])],
+        owners: &mut HashMap<crtc::Handle, usize>,
+        visited: &mut HashSet<crtc::Handle>,
+    ) -> bool {
+        for crtc in connectors[connector].1 {
+            if !visited.insert(*crtc) {
+                continue;
+            }
+            let free = match owners.get(crtc).copied() {
+                None => true,
+                Some(owner) => augment(owner, connectors, owners, visited),
+            };
+            if free {
+                owners.insert(*crtc, connector);
+                return true;
+            }
+        }
+        false
+    }
+
+    // keep the current assignments as a starting point
+    let mut owners = HashMap::new();
+    for (idx, (connector, possible)) in connectors.iter().enumerate() {
+        if let Some(crtc) = current.get(connector) {
+            if possible.contains(crtc) && !owners.contains_key(crtc) {
+                owners.insert(*crtc, idx);
+            }
+        }
+    }
+
+    // augmenting paths only ever move assigned connectors to other crtcs, never unassign them
+    let mut visited = HashSet::new();
+    for idx in 0..connectors.len() {
+        if owners.values().any(|owner| *owner == idx) {
+            continue;
+        }
+        visited.clear();
+        augment(idx, connectors, &mut owners, &mut visited);
+    }
+
+    owners
+        .into_iter()
+        .map(|(crtc, idx)| (connectors[idx].0, crtc))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use drm::control::{connector, crtc, from_u32};
+
+    use super::assign_crtcs;
+
+    fn connector(id: u32) -> connector::Handle {
+        from_u32(id).unwrap()
+    }
+
+    fn crtc(id: u32) -> crtc::Handle {
+        from_u32(id).unwrap()
+    }
+
+    #[test]
+    fn keeps_current_crtcs() {
+        let crtcs = [crtc(10), crtc(11)];
+        let current = HashMap::from([(connector(1), crtc(11))]);
+        let assigned = assign_crtcs(
+            &[(connector(1), &crtcs[..]), (connector(2), &crtcs[..])],
+            &current,
+        );
+        assert_eq!(assigned[&connector(1)], crtc(11));
+        assert_eq!(assigned[&connector(2)], crtc(10));
+    }
+
+    #[test]
+    fn steals_crtc_for_new_connector() {
+        // connector 1 holds the only crtc connector 2 can use, but may move to another one
+        let current = HashMap::from([(connector(1), crtc(10))]);
+        let assigned = assign_crtcs(
+            &[
+                (connector(1), &[crtc(10), crtc(11)][..]),
+                (connector(2), &[crtc(10)][..]),
+                (connector(3), &[crtc(10)][..]),
+            ],
+            &current,
+        );
+        assert_eq!(assigned[&connector(1)], crtc(11));
+        assert_eq!(assigned[&connector(2)], crtc(10));
+        assert!(!assigned.contains_key(&connector(3)));
+    }
+}
@@ -26,7 +26,9 @@ use crate::{
             device::atomic::{map_props, PropMapping},
             device::DrmDeviceInternal,
             error::Error,
-            plane_type, DrmDeviceFd,
+            plane_type,
+            yuv::YuvColor,
+            DrmDeviceFd,
         },
     },
     utils::DevPath,
@@ -348,6 +350,7 @@ impl AtomicDrmSurface {
                     ),
                     transform: Transform::Normal,
                     alpha: 1.0,
+                    yuv: None,
                     damage_clips: None,
                     fb: test_buffer.fb,
                     fence: None,
@@ -407,6 +410,7 @@ impl AtomicDrmSurface {
                 ),
                 transform: Transform::Normal,
                 alpha: 1.0,
+                yuv: None,
                 damage_clips: None,
                 fb: test_buffer.fb,
                 fence: None,
@@ -467,6 +471,7 @@ impl AtomicDrmSurface {
                 ),
                 transform: Transform::Normal,
                 alpha: 1.0,
+                yuv: None,
                 damage_clips: None,
                 fb: test_buffer.fb,
                 fence: None,
@@ -521,6 +526,7 @@ impl AtomicDrmSurface {
                 dst: Rectangle::from_size((mode.size().0 as i32, mode.size().1 as i32).into()),
                 transform: Transform::Normal,
                 alpha: 1.0,
+                yuv: None,
                 damage_clips: None,
                 fb: test_buffer.fb,
                 fence: None,
@@ -643,6 +649,7 @@ impl AtomicDrmSurface {
                 ),
                 transform: Transform::Normal,
                 alpha: 1.0,
+                yuv: None,
                 damage_clips: None,
                 fb: test_buffer.fb,
                 fence: None,
@@ -1060,6 +1067,15 @@ fn to_fixed<N: Coordinate>(n: N) -> u32 {
     f64::round(n.to_f64() * (1 << 16) as f64) as u32
 }
 
+// values of the plane color properties, `None` resets them to their defaults
+fn yuv_props(yuv: Option<YuvColor>) -> [(&'static str, &'static str); 2] {
+    let yuv = yuv.unwrap_or_default();
+    [
+        ("COLOR_ENCODING", yuv.encoding.property_name()),
+        ("COLOR_RANGE", yuv.range.property_name()),
+    ]
+}
+
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
     struct DrmRotation: u8 {
@@ -1260,6 +1276,17 @@ impl<'a> AtomicRequest<'a> {
                     name: "alpha",
                 });
             }
+            for (name, value) in yuv_props(config.yuv) {
+                match self.mapping.plane_enum_value(handle, name, value) {
+                    Ok(value) => {
+                        plane_props.insert(name, property::Value::UnsignedRange(value));
+                    }
+                    // without the property the driver always assumes the default,
+                    // which is what we want for any non-yuv format
+                    Err(_) if config.yuv.unwrap_or_default() == YuvColor::default() => {}
+                    Err(err) => return Err(err),
+                }
+            }
             if self.mapping.plane_prop_handle(handle, "FB_DAMAGE_CLIPS").is_ok() {
                 if let Some(damage) = config.damage_clips.as_ref() {
                     plane_props.insert("FB_DAMAGE_CLIPS", *damage);
@@ -1311,6 +1338,11 @@ impl<'a> AtomicRequest<'a> {
         if self.mapping.plane_prop_handle(plane, "alpha").is_ok() {
             plane_props.insert("alpha", property::Value::UnsignedRange(0xffff));
         }
+        for (name, value) in yuv_props(None) {
+            if let Ok(value) = self.mapping.plane_enum_value(plane, name, value) {
+                plane_props.insert(name, property::Value::UnsignedRange(value));
+            }
+        }
         if self.mapping.plane_prop_handle(plane, "FB_DAMAGE_CLIPS").is_ok() {
             plane_props.insert("FB_DAMAGE_CLIPS", property::Value::Blob(0));
         }
@@ -1509,6 +1541,21 @@ impl<'a> AtomicRequest<'a> {
                     name: "alpha",
                 });
             }
+            for (name, value) in yuv_props(config.yuv) {
+                match self.mapping.plane_enum_value(handle, name, value) {
+                    Ok(value) => {
+                        self.request.add_property(
+                            handle,
+                            self.mapping.plane_prop_handle(handle, name)?,
+                            property::Value::UnsignedRange(value),
+                        );
+                    }
+                    // without the property the driver always assumes the default,
+                    // which is what we want for any non-yuv format
+                    Err(_) if config.yuv.unwrap_or_default() == YuvColor::default() => {}
+                    Err(err) => return Err(err),
+                }
+            }
             if let Ok(prop) = self.mapping.plane_prop_handle(handle, "FB_DAMAGE_CLIPS") {
                 if let Some(damage) = config.damage_clips.as_ref() {
                     self.request.add_property(handle, prop, *damage);
@@ -1607,6 +1654,15 @@ impl<'a> AtomicRequest<'a> {
             self.request
                 .add_property(plane, prop, property::Value::UnsignedRange(0xffff));
         }
+        for (name, value) in yuv_props(None) {
+            if let Ok(value) = self.mapping.plane_enum_value(plane, name, value) {
+                self.request.add_property(
+                    plane,
+                    self.mapping.plane_prop_handle(plane, name)?,
+                    property::Value::UnsignedRange(value),
+                );
+            }
+        }
         if let Ok(prop) = self.mapping.plane_prop_handle(plane, "FB_DAMAGE_CLIPS") {
             self.request.add_property(plane, prop, property::Value::Blob(0));
         }
@@ -222,6 +222,7 @@ where
                 src: Rectangle::from_size((mode.size().0 as i32, mode.size().1 as i32).into()).to_f64(),
                 dst: Rectangle::from_size((mode.size().0 as i32, mode.size().1 as i32).into()),
                 alpha: 1.0,
+                yuv: None,
                 transform: Transform::Normal,
                 damage_clips: None,
                 fb: *handle.as_ref(),
@@ -382,6 +383,7 @@ where
                 dst,
                 transform: Transform::Normal,
                 alpha: 1.0,
+                yuv: None,
                 damage_clips: damage_clips.as_ref().map(|d| d.blob()),
                 fb: *handle.as_ref(),
                 fence: fence.as_ref().map(|fence| fence.as_fd()),
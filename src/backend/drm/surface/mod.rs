@@ -12,6 +12,7 @@ pub(super) mod atomic;
 #[cfg(feature = "backend_gbm")]
 pub(super) mod gbm;
 pub(super) mod legacy;
+use super::yuv::YuvColor;
 use super::{
     device::PlaneClaimStorage, error::Error, plane_type, DrmDeviceFd, PlaneClaim, PlaneInfo, PlaneType,
     Planes,
@@ -149,6 +150,8 @@ pub struct PlaneConfig<'a> {
     pub transform: Transform,
     /// Alpha value for the plane
     pub alpha: f32,
+    /// Color encoding and range of a YCbCr framebuffer, see [`yuv`](crate::backend::drm::yuv)
+    pub yuv: Option<YuvColor>,
     /// Damage clips of the attached framebuffer
     pub damage_clips: Option<drm::control::property::Value<'a>>,
     /// Framebuffer handle
@@ -0,0 +1,158 @@
+//! Color properties of planes scanning out YCbCr buffers
+//!
+//! Video decoders usually produce buffers in YCbCr formats like [`Nv12`](DrmFourcc::Nv12) or
+//! [`P010`](DrmFourcc::P010). Many display controllers provide overlay planes able to scan out
+//! these formats directly, which avoids converting every frame with the GPU during video playback.
+//!
+//! To display them correctly the plane has to know how the YCbCr values map to RGB.
+//! This is controlled by the `COLOR_ENCODING` and `COLOR_RANGE` plane properties, set from
+//! the [`YuvColor`] of a [`PlaneConfig`](super::PlaneConfig).
+//!
+//! Without further information from the client, [`YuvColor::for_buffer`] guesses the encoding
+//! from the size of the video, like most video players do.
+
+use drm_fourcc::DrmFourcc;
+
+use crate::utils::{Buffer, Size};
+
+/// Matrix used to convert YCbCr values into RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YuvEncoding {
+    /// ITU-R BT.601, used for standard definition video
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, used for high definition video
+    Bt709,
+    /// ITU-R BT.2020, used for ultra high definition and HDR video
+    Bt2020,
+}
+
+impl YuvEncoding {
+    /// Name of the matching value of the `COLOR_ENCODING` plane property
+    pub fn property_name(&self) -> &'static str {
+        match self {
+            YuvEncoding::Bt601 => "ITU-R BT.601 YCbCr",
+            YuvEncoding::Bt709 => "ITU-R BT.709 YCbCr",
+            YuvEncoding::Bt2020 => "ITU-R BT.2020 YCbCr",
+        }
+    }
+}
+
+/// Quantization range of YCbCr values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YuvRange {
+    /// Values use the limited range, e.g. 16-235 for 8 bit luma
+    #[default]
+    Limited,
+    /// Values use the full range of their bit depth
+    Full,
+}
+
+impl YuvRange {
+    /// Name of the matching value of the `COLOR_RANGE` plane property
+    pub fn property_name(&self) -> &'static str {
+        match self {
+            YuvRange::Limited => "YCbCr limited range",
+            YuvRange::Full => "YCbCr full range",
+        }
+    }
+}
+
+/// Color properties of a plane scanning out a YCbCr buffer
+///
+/// The default matches the default of the kernel, [`YuvEncoding::Bt601`] with [`YuvRange::Limited`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct YuvColor {
+    /// Conversion matrix
+    pub encoding: YuvEncoding,
+    /// Quantization range
+    pub range: YuvRange,
+}
+
+impl YuvColor {
+    /// Guess the color properties of a buffer
+    ///
+    /// Returns `None` for formats that are not YCbCr. Otherwise assumes limited range and selects
+    /// the encoding by resolution: BT.601 up to standard definition, BT.709 for high definition
+    /// and BT.2020 for 10 bit formats with more than 1080 lines.
+    pub fn for_buffer(format: DrmFourcc, size: Size<i32, Buffer>) -> Option<YuvColor> {
+        if !is_yuv(format) {
+            return None;
+        }
+        let encoding = if size.h > 1080 && is_high_depth(format) {
+            YuvEncoding::Bt2020
+        } else if size.h > 576 || size.w > 1024 {
+            YuvEncoding::Bt709
+        } else {
+            YuvEncoding::Bt601
+        };
+        Some(YuvColor {
+            encoding,
+            range: YuvRange::Limited,
+        })
+    }
+}
+
+/// Returns whether the format stores YCbCr values
+pub fn is_yuv(format: DrmFourcc) -> bool {
+    matches!(
+        format,
+        DrmFourcc::Nv12
+            | DrmFourcc::Nv15
+            | DrmFourcc::Nv21
+            | DrmFourcc::Nv16
+            | DrmFourcc::Nv61
+            | DrmFourcc::Nv24
+            | DrmFourcc::Nv42
+            | DrmFourcc::P010
+            | DrmFourcc::P012
+            | DrmFourcc::P016
+            | DrmFourcc::P210
+            | DrmFourcc::Yuv420
+            | DrmFourcc::Yvu420
+            | DrmFourcc::Yuv422
+            | DrmFourcc::Yvu422
+            | DrmFourcc::Yuv444
+            | DrmFourcc::Yvu444
+            | DrmFourcc::Yuyv
+            | DrmFourcc::Yvyu
+            | DrmFourcc::Uyvy
+            | DrmFourcc::Vyuy
+    )
+}
+
+fn is_high_depth(format: DrmFourcc) -> bool {
+    matches!(
+        format,
+        DrmFourcc::Nv15 | DrmFourcc::P010 | DrmFourcc::P012 | DrmFourcc::P016 | DrmFourcc::P210
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_by_resolution() {
+        assert_eq!(
+            YuvColor::for_buffer(DrmFourcc::Argb8888, (1920, 1080).into()),
+            None
+        );
+        assert_eq!(
+            YuvColor::for_buffer(DrmFourcc::Nv12, (720, 576).into()).map(|c| c.encoding),
+            Some(YuvEncoding::Bt601)
+        );
+        assert_eq!(
+            YuvColor::for_buffer(DrmFourcc::Nv12, (1920, 1080).into()).map(|c| c.encoding),
+            Some(YuvEncoding::Bt709)
+        );
+        assert_eq!(
+            YuvColor::for_buffer(DrmFourcc::Nv12, (3840, 2160).into()).map(|c| c.encoding),
+            Some(YuvEncoding::Bt709)
+        );
+        assert_eq!(
+            YuvColor::for_buffer(DrmFourcc::P010, (3840, 2160).into()).map(|c| c.encoding),
+            Some(YuvEncoding::Bt2020)
+        );
+    }
+}
@@ -0,0 +1,970 @@
+//! Input backend reading evdev devices directly, without libinput
+//!
+//! The [`EvdevInputBackend`] reads keyboards and mice from `/dev/input/event*` and translates
+//! their events with small state machines of its own. It is meant for embedded systems, where
+//! linking libinput is undesirable, and as a simple reference implementation of an
+//! [`InputBackend`]. Compared to the [`libinput`](crate::backend::libinput) backend it does not
+//! provide pointer acceleration, touchpad handling, gestures, touch or tablet support.
+//!
+//! Devices have to be added explicitly, e.g. from the list returned by [`available_devices`] or
+//! when announced by [`UdevBackend`](crate::backend::udev::UdevBackend). Devices are grabbed
+//! exclusively, so keys typed into the compositor do not also reach the console.
+//!
+//! ```no_run
+//! use smithay::backend::evdev::{available_devices, EvdevInputBackend};
+//!
+//! let mut backend = EvdevInputBackend::new().expect("Failed to create evdev backend");
+//! for path in available_devices().expect("Failed to list input devices") {
+//!     if let Err(err) = backend.add_device(&path) {
+//!         eprintln!("Skipping {}: {}", path.display(), err);
+//!     }
+//! }
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! # let loop_handle = event_loop.handle();
+//! loop_handle
+//!     .insert_source(backend, |event, _, _| {
+//!         // process the InputEvent
+//!     })
+//!     .expect("Failed to insert the evdev source into the event loop");
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    mem,
+    os::unix::{
+        fs::OpenOptionsExt,
+        io::{AsRawFd, OwnedFd},
+    },
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use calloop::{
+    generic::Generic,
+    ping::{make_ping, Ping, PingSource},
+    EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use rustix::ioctl::{
+    opcode::{self, Direction},
+    Opcode,
+};
+use tracing::{info, warn};
+
+mod uinput;
+pub use uinput::UinputInjector;
+
+use crate::backend::input::{
+    self, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device, DeviceCapability, InputBackend,
+    InputEvent, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent, PointerButtonEvent,
+    PointerMotionEvent, UnusedEvent,
+};
+
+const EVENT_SIZE: usize = mem::size_of::<libc::input_event>();
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+
+const SYN_REPORT: u16 = 0;
+const SYN_DROPPED: u16 = 3;
+
+const KEY_ESC: u16 = 1;
+const KEY_MAX: u16 = 0x2ff;
+const BTN_MISC: u16 = 0x100;
+const BTN_LEFT: u16 = 0x110;
+const BTN_TASK: u16 = 0x117;
+const KEY_OK: u16 = 0x160;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const REL_HWHEEL_HI_RES: u16 = 0x0c;
+const REL_MAX: u16 = 0x0f;
+
+// degrees of rotation per wheel click, as reported by libinput for most mice
+const WHEEL_CLICK_ANGLE: f64 = 15.0;
+
+/// Issues `request` on `fd` with the raw argument `arg`
+///
+/// # Safety
+///
+/// `arg` has to be valid for whatever the kernel reads from or writes to it for `request`, e.g.
+/// point to a buffer at least as large as the size encoded in the opcode.
+unsafe fn ioctl(fd: &File, request: Opcode, arg: *mut libc::c_void) -> io::Result<()> {
+    if libc::ioctl(fd.as_raw_fd(), request as _, arg) < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn read_bits(fd: &File, nr: u8, len: usize) -> io::Result<Vec<u8>> {
+    let mut bits = vec![0u8; len];
+    // SAFETY: EVIOCGBIT writes at most `len` bytes, the size of `bits`
+    unsafe {
+        ioctl(
+            fd,
+            opcode::from_components(Direction::Read, b'E', nr, len),
+            bits.as_mut_ptr().cast(),
+        )?
+    };
+    Ok(bits)
+}
+
+fn test_bit(bits: &[u8], bit: u16) -> bool {
+    bits.get(bit as usize / 8)
+        .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Paths of all evdev devices in `/dev/input`
+pub fn available_devices() -> io::Result<Vec<PathBuf>> {
+    let mut devices = fs::read_dir("/dev/input")?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    devices.sort();
+    Ok(devices)
+}
+
+#[derive(Debug)]
+struct DeviceInner {
+    path: PathBuf,
+    name: String,
+    usb_id: Option<(u32, u32)>,
+    keyboard: bool,
+    pointer: bool,
+}
+
+/// Input device of the [`EvdevInputBackend`]
+#[derive(Debug, Clone)]
+pub struct EvdevDevice(Arc<DeviceInner>);
+
+impl EvdevDevice {
+    fn probe(path: &Path, file: &File) -> io::Result<EvdevDevice> {
+        let mut name = [0u8; 256];
+        // SAFETY: EVIOCGNAME writes at most `name.len()` bytes into `name`
+        unsafe {
+            ioctl(
+                file,
+                opcode::from_components(Direction::Read, b'E', 0x06, name.len()),
+                name.as_mut_ptr().cast(),
+            )?
+        };
+        let name = String::from_utf8_lossy(name.split(|b| *b == 0).next().unwrap_or_default()).into_owned();
+
+        let mut id = libc::input_id {
+            bustype: 0,
+            vendor: 0,
+            product: 0,
+            version: 0,
+        };
+        // SAFETY: EVIOCGID writes exactly one `input_id` into `id`
+        unsafe {
+            ioctl(
+                file,
+                opcode::read::<libc::input_id>(b'E', 0x02),
+                (&mut id as *mut libc::input_id).cast(),
+            )?
+        };
+        const BUS_USB: u16 = 0x03;
+        let usb_id = (id.bustype == BUS_USB).then_some((id.product as u32, id.vendor as u32));
+
+        let key_bits = read_bits(file, 0x20 + EV_KEY as u8, KEY_MAX as usize / 8 + 1)?;
+        let rel_bits = read_bits(file, 0x20 + EV_REL as u8, REL_MAX as usize / 8 + 1)?;
+        let keyboard = (KEY_ESC..BTN_MISC).any(|key| test_bit(&key_bits, key));
+        let pointer =
+            test_bit(&rel_bits, REL_X) && test_bit(&rel_bits, REL_Y) && test_bit(&key_bits, BTN_LEFT);
+
+        Ok(EvdevDevice(Arc::new(DeviceInner {
+            path: path.to_owned(),
+            name,
+            usb_id,
+            keyboard,
+            pointer,
+        })))
+    }
+
+    /// Path of the device node
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+}
+
+impl PartialEq for EvdevDevice {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for EvdevDevice {}
+
+impl Hash for EvdevDevice {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state)
+    }
+}
+
+impl Device for EvdevDevice {
+    fn id(&self) -> String {
+        self.0
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn name(&self) -> String {
+        self.0.name.clone()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        match capability {
+            DeviceCapability::Keyboard => self.0.keyboard,
+            DeviceCapability::Pointer => self.0.pointer,
+            _ => false,
+        }
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        self.0.usb_id
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        let path = Path::new("/sys/class/input").join(self.id());
+        fs::canonicalize(path).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RawEvent {
+    time: u64,
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+impl RawEvent {
+    fn parse(bytes: &[u8]) -> RawEvent {
+        assert_eq!(bytes.len(), EVENT_SIZE);
+        // SAFETY: `input_event` is plain old data and `bytes` is large enough
+        let event = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const libc::input_event) };
+        RawEvent {
+            time: event.time.tv_sec as u64 * 1_000_000 + event.time.tv_usec as u64,
+            kind: event.type_,
+            code: event.code,
+            value: event.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Key { code: u16, state: KeyState },
+    Button { code: u16, state: ButtonState },
+    Motion { dx: f64, dy: f64 },
+    Axis { vertical: f64, horizontal: f64 },
+}
+
+// Per-device state machine, turning frames of raw events into actions
+#[derive(Debug, Default)]
+struct DeviceState {
+    frame: Vec<Action>,
+    motion: (i32, i32),
+    wheel: (i32, i32),
+    wheel_hi_res: Option<(i32, i32)>,
+    pressed: HashSet<u16>,
+    dropped: bool,
+    needs_sync: bool,
+}
+
+impl DeviceState {
+    fn feed(&mut self, event: RawEvent, out: &mut Vec<(u64, Action)>) {
+        match (event.kind, event.code) {
+            (EV_SYN, SYN_DROPPED) => {
+                self.dropped = true;
+                self.reset_frame();
+            }
+            (EV_SYN, SYN_REPORT) if self.dropped => {
+                // the kernel buffer overflowed, resynchronize the key state at the next dispatch
+                self.dropped = false;
+                self.needs_sync = true;
+                self.reset_frame();
+            }
+            (EV_SYN, SYN_REPORT) => self.flush(event.time, out),
+            _ if self.dropped => {}
+            (EV_KEY, code) => {
+                // autorepeat (value 2) is left to the compositor
+                let pressed = match event.value {
+                    0 => false,
+                    1 => true,
+                    _ => return,
+                };
+                if pressed == self.pressed.contains(&code) {
+                    return;
+                }
+                if pressed {
+                    self.pressed.insert(code);
+                } else {
+                    self.pressed.remove(&code);
+                }
+                if (BTN_LEFT..=BTN_TASK).contains(&code) {
+                    let state = if pressed {
+                        ButtonState::Pressed
+                    } else {
+                        ButtonState::Released
+                    };
+                    self.frame.push(Action::Button { code, state });
+                } else if !(BTN_MISC..KEY_OK).contains(&code) {
+                    let state = if pressed {
+                        KeyState::Pressed
+                    } else {
+                        KeyState::Released
+                    };
+                    self.frame.push(Action::Key { code, state });
+                }
+            }
+            (EV_REL, REL_X) => self.motion.0 += event.value,
+            (EV_REL, REL_Y) => self.motion.1 += event.value,
+            (EV_REL, REL_WHEEL) => self.wheel.0 += event.value,
+            (EV_REL, REL_HWHEEL) => self.wheel.1 += event.value,
+            (EV_REL, REL_WHEEL_HI_RES) => self.wheel_hi_res.get_or_insert((0, 0)).0 += event.value,
+            (EV_REL, REL_HWHEEL_HI_RES) => self.wheel_hi_res.get_or_insert((0, 0)).1 += event.value,
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self, time: u64, out: &mut Vec<(u64, Action)>) {
+        out.extend(self.frame.drain(..).map(|action| (time, action)));
+        if self.motion != (0, 0) {
+            out.push((
+                time,
+                Action::Motion {
+                    dx: self.motion.0 as f64,
+                    dy: self.motion.1 as f64,
+                },
+            ));
+        }
+        // devices with high resolution wheels report both, prefer the precise values
+        let (vertical, horizontal) = self
+            .wheel_hi_res
+            .unwrap_or((self.wheel.0 * 120, self.wheel.1 * 120));
+        if (vertical, horizontal) != (0, 0) {
+            // evdev reports scrolling up as positive, wayland as negative
+            out.push((
+                time,
+                Action::Axis {
+                    vertical: -vertical as f64,
+                    horizontal: horizontal as f64,
+                },
+            ));
+        }
+        self.reset_frame();
+    }
+
+    fn reset_frame(&mut self) {
+        self.frame.clear();
+        self.motion = (0, 0);
+        self.wheel = (0, 0);
+        self.wheel_hi_res = None;
+    }
+
+    // release all keys no longer set in the given key state, as reported by the kernel
+    fn sync(&mut self, time: u64, still_pressed: impl Fn(u16) -> bool, out: &mut Vec<(u64, Action)>) {
+        self.needs_sync = false;
+        let released = self
+            .pressed
+            .iter()
+            .copied()
+            .filter(|code| !still_pressed(*code))
+            .collect::<Vec<_>>();
+        for code in released {
+            self.feed(
+                RawEvent {
+                    time,
+                    kind: EV_KEY,
+                    code,
+                    value: 0,
+                },
+                out,
+            );
+        }
+        self.flush(time, out);
+    }
+}
+
+#[derive(Debug)]
+struct DeviceSource {
+    device: EvdevDevice,
+    source: Generic<File>,
+    state: DeviceState,
+    registered: bool,
+}
+
+/// Input backend reading keyboards and mice from evdev devices
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Debug)]
+pub struct EvdevInputBackend {
+    devices: Vec<DeviceSource>,
+    removed: Vec<Generic<File>>,
+    pending: VecDeque<InputEvent<EvdevInputBackend>>,
+    key_counts: HashMap<u16, u32>,
+    ping: Ping,
+    ping_source: PingSource,
+    needs_reregister: bool,
+}
+
+impl EvdevInputBackend {
+    /// Create a new backend without any devices
+    pub fn new() -> io::Result<EvdevInputBackend> {
+        let (ping, ping_source) = make_ping()?;
+        Ok(EvdevInputBackend {
+            devices: Vec::new(),
+            removed: Vec::new(),
+            pending: VecDeque::new(),
+            key_counts: HashMap::new(),
+            ping,
+            ping_source,
+            needs_reregister: false,
+        })
+    }
+
+    /// Open the device at the given path and add it
+    ///
+    /// An [`InputEvent::DeviceAdded`] event is generated once the backend is dispatched.
+    pub fn add_device(&mut self, path: impl AsRef<Path>) -> io::Result<EvdevDevice> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open(path)?;
+        self.add_device_file(path, file)
+    }
+
+    /// Add a device from a file descriptor opened elsewhere, e.g. by a
+    /// [`Session`](crate::backend::session::Session)
+    ///
+    /// The file descriptor has to be non-blocking.
+    pub fn add_device_fd(&mut self, path: impl AsRef<Path>, fd: OwnedFd) -> io::Result<EvdevDevice> {
+        self.add_device_file(path.as_ref(), File::from(fd))
+    }
+
+    fn add_device_file(&mut self, path: &Path, file: File) -> io::Result<EvdevDevice> {
+        let device = EvdevDevice::probe(path, &file)?;
+        if !device.0.keyboard && !device.0.pointer {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device is neither a keyboard nor a pointer",
+            ));
+        }
+        // SAFETY: EVIOCGRAB takes its argument by value and does not dereference it
+        unsafe { ioctl(&file, opcode::write::<libc::c_int>(b'E', 0x90), 1usize as *mut _)? };
+
+        info!("New device {:?} ({})", path, device.0.name);
+        self.devices.push(DeviceSource {
+            device: device.clone(),
+            source: Generic::new(file, Interest::READ, Mode::Level),
+            state: DeviceState::default(),
+            registered: false,
+        });
+        self.pending.push_back(InputEvent::DeviceAdded {
+            device: device.clone(),
+        });
+        self.needs_reregister = true;
+        self.ping.ping();
+        Ok(device)
+    }
+
+    /// Remove a device, closing its file descriptor
+    ///
+    /// Keys still held on the device are released, followed by an
+    /// [`InputEvent::DeviceRemoved`] event once the backend is dispatched.
+    pub fn remove_device(&mut self, device: &EvdevDevice) {
+        let Some(index) = self.devices.iter().position(|source| source.device == *device) else {
+            return;
+        };
+        let source = self.devices.remove(index);
+        self.remove_source(source);
+        self.ping.ping();
+    }
+
+    /// Iterate over the devices of the backend
+    pub fn devices(&self) -> impl Iterator<Item = &EvdevDevice> {
+        self.devices.iter().map(|source| &source.device)
+    }
+
+    fn remove_source(&mut self, mut source: DeviceSource) {
+        info!("Removed device {:?}", source.device.path());
+        let mut actions = Vec::new();
+        source.state.sync(0, |_| false, &mut actions);
+        self.queue_actions(&source.device, actions);
+        self.pending.push_back(InputEvent::DeviceRemoved {
+            device: source.device,
+        });
+        if source.registered {
+            self.removed.push(source.source);
+            self.needs_reregister = true;
+        }
+    }
+
+    fn queue_actions(&mut self, device: &EvdevDevice, actions: Vec<(u64, Action)>) {
+        for (time, action) in actions {
+            let device = device.clone();
+            let event = match action {
+                Action::Key { code, state } => {
+                    let count = self.key_counts.entry(code).or_default();
+                    *count = match state {
+                        KeyState::Pressed => *count + 1,
+                        KeyState::Released => count.saturating_sub(1),
+                    };
+                    InputEvent::Keyboard {
+                        event: EvdevKeyboardKeyEvent {
+                            device,
+                            time,
+                            key: code,
+                            state,
+                            count: *count,
+                        },
+                    }
+                }
+                Action::Button { code, state } => InputEvent::PointerButton {
+                    event: EvdevPointerButtonEvent {
+                        device,
+                        time,
+                        button: code,
+                        state,
+                    },
+                },
+                Action::Motion { dx, dy } => InputEvent::PointerMotion {
+                    event: EvdevPointerMotionEvent {
+                        device,
+                        time,
+                        delta_x: dx,
+                        delta_y: dy,
+                    },
+                },
+                Action::Axis { vertical, horizontal } => InputEvent::PointerAxis {
+                    event: EvdevPointerAxisEvent {
+                        device,
+                        time,
+                        vertical,
+                        horizontal,
+                    },
+                },
+            };
+            self.pending.push_back(event);
+        }
+    }
+}
+
+impl EventSource for EvdevInputBackend {
+    type Event = InputEvent<EvdevInputBackend>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    #[profiling::function]
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut ()) -> Self::Ret,
+    {
+        self.ping_source
+            .process_events(readiness, token, |_, _| {})
+            .map_err(io::Error::other)?;
+
+        let mut frames = Vec::new();
+        let mut gone = Vec::new();
+        for (index, source) in self.devices.iter_mut().enumerate() {
+            let state = &mut source.state;
+            let mut actions = Vec::new();
+            let mut removed = false;
+            source.source.process_events(readiness, token, |_, file| {
+                let mut buffer = [0u8; EVENT_SIZE * 64];
+                loop {
+                    match (&**file).read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(len) => {
+                            for chunk in buffer[..len].chunks_exact(EVENT_SIZE) {
+                                state.feed(RawEvent::parse(chunk), &mut actions);
+                            }
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) if err.raw_os_error() == Some(libc::ENODEV) => {
+                            removed = true;
+                            break;
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                if state.needs_sync && !removed {
+                    let keys = read_bits(&**file, 0x18, KEY_MAX as usize / 8 + 1)?;
+                    let time = actions.last().map(|(time, _)| *time).unwrap_or_default();
+                    state.sync(time, |code| test_bit(&keys, code), &mut actions);
+                }
+                Ok(PostAction::Continue)
+            })?;
+            if !actions.is_empty() {
+                frames.push((source.device.clone(), actions));
+            }
+            if removed {
+                gone.push(index);
+            }
+        }
+        for (device, actions) in frames {
+            self.queue_actions(&device, actions);
+        }
+        for index in gone.into_iter().rev() {
+            warn!("Device {:?} disappeared", self.devices[index].device.path());
+            let source = self.devices.remove(index);
+            self.remove_source(source);
+        }
+
+        while let Some(event) = self.pending.pop_front() {
+            callback(event, &mut ());
+        }
+
+        if mem::take(&mut self.needs_reregister) {
+            Ok(PostAction::Reregister)
+        } else {
+            Ok(PostAction::Continue)
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.ping_source.register(poll, token_factory)?;
+        for source in &mut self.devices {
+            source.source.register(poll, token_factory)?;
+            source.registered = true;
+        }
+        self.needs_reregister = false;
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        for mut source in self.removed.drain(..) {
+            source.unregister(poll)?;
+        }
+        self.ping_source.reregister(poll, token_factory)?;
+        for source in &mut self.devices {
+            if source.registered {
+                source.source.reregister(poll, token_factory)?;
+            } else {
+                source.source.register(poll, token_factory)?;
+                source.registered = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        for mut source in self.removed.drain(..) {
+            source.unregister(poll)?;
+        }
+        self.ping_source.unregister(poll)?;
+        for source in &mut self.devices {
+            if source.registered {
+                source.source.unregister(poll)?;
+                source.registered = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputBackend for EvdevInputBackend {
+    type Device = EvdevDevice;
+    type KeyboardKeyEvent = EvdevKeyboardKeyEvent;
+    type PointerAxisEvent = EvdevPointerAxisEvent;
+    type PointerButtonEvent = EvdevPointerButtonEvent;
+    type PointerMotionEvent = EvdevPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = UnusedEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Key event of the [`EvdevInputBackend`]
+#[derive(Debug, Clone)]
+pub struct EvdevKeyboardKeyEvent {
+    device: EvdevDevice,
+    time: u64,
+    key: u16,
+    state: KeyState,
+    count: u32,
+}
+
+impl input::Event<EvdevInputBackend> for EvdevKeyboardKeyEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> EvdevDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<EvdevInputBackend> for EvdevKeyboardKeyEvent {
+    fn key_code(&self) -> Keycode {
+        (self.key as u32 + 8).into()
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Relative pointer motion of the [`EvdevInputBackend`]
+///
+/// No acceleration is applied, the accelerated and unaccelerated deltas are identical.
+#[derive(Debug, Clone)]
+pub struct EvdevPointerMotionEvent {
+    device: EvdevDevice,
+    time: u64,
+    delta_x: f64,
+    delta_y: f64,
+}
+
+impl input::Event<EvdevInputBackend> for EvdevPointerMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> EvdevDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerMotionEvent<EvdevInputBackend> for EvdevPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta_x
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta_y
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta_x
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta_y
+    }
+}
+
+/// Pointer button event of the [`EvdevInputBackend`]
+#[derive(Debug, Clone)]
+pub struct EvdevPointerButtonEvent {
+    device: EvdevDevice,
+    time: u64,
+    button: u16,
+    state: ButtonState,
+}
+
+impl input::Event<EvdevInputBackend> for EvdevPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> EvdevDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerButtonEvent<EvdevInputBackend> for EvdevPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button as u32
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Scroll wheel event of the [`EvdevInputBackend`]
+#[derive(Debug, Clone)]
+pub struct EvdevPointerAxisEvent {
+    device: EvdevDevice,
+    time: u64,
+    vertical: f64,
+    horizontal: f64,
+}
+
+impl input::Event<EvdevInputBackend> for EvdevPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> EvdevDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerAxisEvent<EvdevInputBackend> for EvdevPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        self.amount_v120(axis)
+            .map(|v120| v120 / 120.0 * WHEEL_CLICK_ANGLE)
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        Some(match axis {
+            Axis::Vertical => self.vertical,
+            Axis::Horizontal => self.horizontal,
+        })
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(state: &mut DeviceState, events: &[(u16, u16, i32)]) -> Vec<Action> {
+        let mut out = Vec::new();
+        for (time, &(kind, code, value)) in events.iter().enumerate() {
+            let event = RawEvent {
+                time: time as u64,
+                kind,
+                code,
+                value,
+            };
+            state.feed(event, &mut out);
+        }
+        out.into_iter().map(|(_, action)| action).collect()
+    }
+
+    #[test]
+    fn keys_and_buttons() {
+        let mut state = DeviceState::default();
+        let actions = feed(
+            &mut state,
+            &[
+                (EV_KEY, 30, 1),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_KEY, 30, 2),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_KEY, BTN_LEFT, 1),
+                (EV_KEY, 30, 0),
+                (EV_SYN, SYN_REPORT, 0),
+            ],
+        );
+        assert_eq!(
+            actions,
+            [
+                Action::Key {
+                    code: 30,
+                    state: KeyState::Pressed
+                },
+                Action::Button {
+                    code: BTN_LEFT,
+                    state: ButtonState::Pressed
+                },
+                Action::Key {
+                    code: 30,
+                    state: KeyState::Released
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn motion_and_wheel() {
+        let mut state = DeviceState::default();
+        let actions = feed(
+            &mut state,
+            &[
+                (EV_REL, REL_X, 3),
+                (EV_REL, REL_Y, -2),
+                (EV_REL, REL_X, 1),
+                (EV_REL, REL_WHEEL, 1),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_REL, REL_WHEEL, 1),
+                (EV_REL, REL_WHEEL_HI_RES, 60),
+                (EV_SYN, SYN_REPORT, 0),
+            ],
+        );
+        assert_eq!(
+            actions,
+            [
+                Action::Motion { dx: 4.0, dy: -2.0 },
+                Action::Axis {
+                    vertical: -120.0,
+                    horizontal: 0.0
+                },
+                Action::Axis {
+                    vertical: -60.0,
+                    horizontal: 0.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_events_resync() {
+        let mut state = DeviceState::default();
+        feed(
+            &mut state,
+            &[(EV_KEY, 30, 1), (EV_KEY, 31, 1), (EV_SYN, SYN_REPORT, 0)],
+        );
+        let actions = feed(
+            &mut state,
+            &[(EV_SYN, SYN_DROPPED, 0), (EV_KEY, 30, 0), (EV_SYN, SYN_REPORT, 0)],
+        );
+        assert!(actions.is_empty());
+        assert!(state.needs_sync);
+
+        let mut out = Vec::new();
+        state.sync(0, |code| code == 31, &mut out);
+        assert_eq!(
+            out.into_iter().map(|(_, action)| action).collect::<Vec<_>>(),
+            [Action::Key {
+                code: 30,
+                state: KeyState::Released
+            }]
+        );
+        assert!(!state.needs_sync);
+    }
+}
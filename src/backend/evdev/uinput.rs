@@ -0,0 +1,277 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    mem,
+    os::unix::fs::OpenOptionsExt,
+    slice,
+};
+
+use rustix::ioctl::{opcode, Opcode};
+use tracing::{info, warn};
+
+use super::{
+    ioctl, BTN_LEFT, BTN_TASK, EVENT_SIZE, EV_KEY, EV_REL, EV_SYN, REL_HWHEEL, REL_HWHEEL_HI_RES, REL_WHEEL,
+    REL_WHEEL_HI_RES, REL_X, REL_Y, SYN_REPORT,
+};
+use crate::{
+    backend::input::{
+        inject::{InjectedEvent, InputInjector},
+        Axis, ButtonState, KeyState, Keycode,
+    },
+    utils::{Logical, Point},
+};
+
+const EV_ABS: u16 = 0x03;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+// range of the absolute axes of the virtual device
+const ABS_RANGE: i32 = 0xffff;
+
+const BUS_VIRTUAL: u16 = 0x06;
+
+const UI_DEV_CREATE: Opcode = opcode::none(b'U', 1);
+const UI_DEV_DESTROY: Opcode = opcode::none(b'U', 2);
+const UI_DEV_SETUP: Opcode = opcode::write::<UinputSetup>(b'U', 3);
+const UI_ABS_SETUP: Opcode = opcode::write::<UinputAbsSetup>(b'U', 4);
+const UI_SET_EVBIT: Opcode = opcode::write::<libc::c_int>(b'U', 100);
+const UI_SET_KEYBIT: Opcode = opcode::write::<libc::c_int>(b'U', 101);
+const UI_SET_RELBIT: Opcode = opcode::write::<libc::c_int>(b'U', 102);
+const UI_SET_ABSBIT: Opcode = opcode::write::<libc::c_int>(b'U', 103);
+
+#[repr(C)]
+struct UinputSetup {
+    id: libc::input_id,
+    name: [u8; 80],
+    ff_effects_max: u32,
+}
+
+#[repr(C)]
+struct AbsInfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+#[repr(C)]
+struct UinputAbsSetup {
+    code: u16,
+    absinfo: AbsInfo,
+}
+
+// ioctls setting a bit take the value itself as argument
+fn set_bit(file: &File, request: Opcode, bit: u16) -> io::Result<()> {
+    // SAFETY: the UI_SET_*BIT requests do not dereference their argument
+    unsafe { ioctl(file, request, bit as usize as *mut _) }
+}
+
+fn create_device(name: &str, configure: impl FnOnce(&File) -> io::Result<()>) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+        .open("/dev/uinput")?;
+    configure(&file)?;
+
+    let mut setup = UinputSetup {
+        id: libc::input_id {
+            bustype: BUS_VIRTUAL,
+            vendor: 0,
+            product: 0,
+            version: 1,
+        },
+        name: [0; 80],
+        ff_effects_max: 0,
+    };
+    let len = name.len().min(setup.name.len() - 1);
+    setup.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+    // SAFETY: UI_DEV_SETUP only reads one `UinputSetup` from `setup`
+    unsafe { ioctl(&file, UI_DEV_SETUP, (&mut setup as *mut UinputSetup).cast())? };
+    // SAFETY: UI_DEV_CREATE takes no argument
+    unsafe { ioctl(&file, UI_DEV_CREATE, std::ptr::null_mut())? };
+    info!("Created uinput device {:?}", name);
+    Ok(file)
+}
+
+fn emit(file: &File, events: &[(u16, u16, i32)]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity((events.len() + 1) * EVENT_SIZE);
+    for &(kind, code, value) in events.iter().chain(&[(EV_SYN, SYN_REPORT, 0)]) {
+        // SAFETY: `input_event` is plain old data, the kernel fills in the timestamp
+        let mut event: libc::input_event = unsafe { mem::zeroed() };
+        event.type_ = kind;
+        event.code = code;
+        event.value = value;
+        // SAFETY: `event` lives for the duration of the copy and is `EVENT_SIZE` bytes large
+        bytes.extend_from_slice(unsafe {
+            slice::from_raw_parts(&event as *const libc::input_event as *const u8, EVENT_SIZE)
+        });
+    }
+    (&*file).write_all(&bytes)
+}
+
+// xkb keycodes are offset by 8 from evdev codes
+fn evdev_code(key: Keycode) -> io::Result<u16> {
+    key.raw()
+        .checked_sub(8)
+        .and_then(|code| u16::try_from(code).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("keycode {} has no evdev code", key.raw()),
+            )
+        })
+}
+
+/// Injector creating virtual devices with uinput
+///
+/// Injected events pass through the kernel and reach the compositor through its regular input
+/// backend, e.g. libinput, which also makes them visible to other users of the devices. Two
+/// devices are created: a keyboard and relative pointer, and a pointer for absolute motion.
+/// Requires write access to `/dev/uinput`.
+///
+/// See the [`inject`](crate::backend::input::inject) module for alternatives.
+#[derive(Debug)]
+pub struct UinputInjector {
+    relative: File,
+    absolute: File,
+    motion_remainder: Point<f64, Logical>,
+    wheel_remainder: [f64; 2],
+}
+
+impl UinputInjector {
+    /// Create the virtual devices with the given name
+    pub fn new(name: &str) -> io::Result<UinputInjector> {
+        let relative = create_device(name, |file| {
+            set_bit(file, UI_SET_EVBIT, EV_KEY)?;
+            // keyboard keys and mouse buttons
+            for key in (1..0x100).chain(BTN_LEFT..=BTN_TASK) {
+                set_bit(file, UI_SET_KEYBIT, key)?;
+            }
+            set_bit(file, UI_SET_EVBIT, EV_REL)?;
+            for axis in [
+                REL_X,
+                REL_Y,
+                REL_WHEEL,
+                REL_HWHEEL,
+                REL_WHEEL_HI_RES,
+                REL_HWHEEL_HI_RES,
+            ] {
+                set_bit(file, UI_SET_RELBIT, axis)?;
+            }
+            Ok(())
+        })?;
+
+        let absolute = create_device(&format!("{} absolute pointer", name), |file| {
+            set_bit(file, UI_SET_EVBIT, EV_KEY)?;
+            for button in BTN_LEFT..=BTN_TASK {
+                set_bit(file, UI_SET_KEYBIT, button)?;
+            }
+            set_bit(file, UI_SET_EVBIT, EV_ABS)?;
+            for axis in [ABS_X, ABS_Y] {
+                set_bit(file, UI_SET_ABSBIT, axis)?;
+                let mut setup = UinputAbsSetup {
+                    code: axis,
+                    absinfo: AbsInfo {
+                        value: 0,
+                        minimum: 0,
+                        maximum: ABS_RANGE,
+                        fuzz: 0,
+                        flat: 0,
+                        resolution: 0,
+                    },
+                };
+                // SAFETY: UI_ABS_SETUP only reads one `UinputAbsSetup` from `setup`
+                unsafe { ioctl(file, UI_ABS_SETUP, (&mut setup as *mut UinputAbsSetup).cast())? };
+            }
+            Ok(())
+        })?;
+
+        Ok(UinputInjector {
+            relative,
+            absolute,
+            motion_remainder: Point::default(),
+            wheel_remainder: [0.0; 2],
+        })
+    }
+}
+
+impl InputInjector for UinputInjector {
+    fn inject(&mut self, event: InjectedEvent) -> io::Result<()> {
+        match event {
+            InjectedEvent::Key { key, state } => {
+                let value = (state == KeyState::Pressed) as i32;
+                emit(&self.relative, &[(EV_KEY, evdev_code(key)?, value)])
+            }
+            InjectedEvent::PointerMotion { delta } => {
+                // the kernel only accepts whole units, carry the fractions over to the next motion
+                let delta = delta + self.motion_remainder;
+                let (dx, dy) = (delta.x.trunc(), delta.y.trunc());
+                self.motion_remainder = (delta.x - dx, delta.y - dy).into();
+                emit(
+                    &self.relative,
+                    &[(EV_REL, REL_X, dx as i32), (EV_REL, REL_Y, dy as i32)],
+                )
+            }
+            InjectedEvent::PointerMotionAbsolute { position } => {
+                let scale = |v: f64| (v.clamp(0.0, 1.0) * ABS_RANGE as f64).round() as i32;
+                emit(
+                    &self.absolute,
+                    &[
+                        (EV_ABS, ABS_X, scale(position.x)),
+                        (EV_ABS, ABS_Y, scale(position.y)),
+                    ],
+                )
+            }
+            InjectedEvent::PointerButton { button, state } => {
+                let value = (state == ButtonState::Pressed) as i32;
+                emit(&self.relative, &[(EV_KEY, button as u16, value)])
+            }
+            InjectedEvent::PointerAxis { axis, v120 } => {
+                // evdev reports scrolling up as positive, wayland as negative
+                let (hi_res, low_res, value, remainder) = match axis {
+                    Axis::Vertical => (REL_WHEEL_HI_RES, REL_WHEEL, -v120, &mut self.wheel_remainder[0]),
+                    Axis::Horizontal => (REL_HWHEEL_HI_RES, REL_HWHEEL, v120, &mut self.wheel_remainder[1]),
+                };
+                // emulate the low resolution clicks sent alongside by high resolution wheels
+                *remainder += value;
+                let clicks = (*remainder / 120.0).trunc();
+                *remainder -= clicks * 120.0;
+                emit(
+                    &self.relative,
+                    &[
+                        (EV_REL, hi_res, value.round() as i32),
+                        (EV_REL, low_res, clicks as i32),
+                    ],
+                )
+            }
+        }
+    }
+}
+
+impl Drop for UinputInjector {
+    fn drop(&mut self) {
+        for file in [&self.relative, &self.absolute] {
+            // SAFETY: UI_DEV_DESTROY takes no argument
+            if let Err(err) = unsafe { ioctl(file, UI_DEV_DESTROY, std::ptr::null_mut()) } {
+                warn!("Failed to destroy uinput device: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evdev_code;
+
+    #[test]
+    fn rejects_keycodes_without_evdev_code() {
+        // KEY_A
+        assert_eq!(evdev_code(38.into()).unwrap(), 30);
+        assert_eq!(evdev_code(8.into()).unwrap(), 0);
+        for raw in [0, 7, u32::MAX] {
+            let err = evdev_code(raw.into()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Pointer acceleration for backends delivering raw motion
+//!
+//! libinput applies pointer acceleration itself, but other input sources, like raw input on
+//! Windows or unaccelerated deltas of any backend, report plain device motion.
+//! [`PointerAccel`] applies an acceleration curve to such deltas, so pointer movement feels
+//! the same across backends. It should be kept per device, as it tracks the pointer velocity.
+//!
+//! The profiles and the speed setting mirror libinput's configuration options.
+
+use super::{InputBackend, PointerMotionEvent};
+use crate::utils::{Logical, Point};
+
+/// Pointer acceleration profile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccelProfile {
+    /// Constant factor applied to all motion, depending only on the speed setting
+    Flat,
+    /// Factor increasing with the velocity of the pointer
+    #[default]
+    Adaptive,
+}
+
+// Velocity in device units per millisecond below which no acceleration is applied
+const BASE_THRESHOLD: f64 = 0.4;
+// Increase of the factor per unit of velocity above the threshold
+const INCLINE: f64 = 1.1;
+// Maximum factor at the default speed
+const BASE_MAX_FACTOR: f64 = 2.0;
+// Motion events further apart reset the velocity tracking
+const MAX_EVENT_GAP_USEC: u64 = 300_000;
+// Weight of the newest velocity sample for smoothing
+const SMOOTHING: f64 = 0.6;
+
+/// Acceleration state of a single pointer device
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointerAccel {
+    profile: AccelProfile,
+    speed: f64,
+    sensitivity: f64,
+    velocity: f64,
+    last_time: Option<u64>,
+}
+
+impl Default for PointerAccel {
+    fn default() -> Self {
+        PointerAccel::new(AccelProfile::default())
+    }
+}
+
+impl PointerAccel {
+    /// Create a new acceleration state with the given profile at the default speed
+    pub fn new(profile: AccelProfile) -> PointerAccel {
+        PointerAccel {
+            profile,
+            speed: 0.0,
+            sensitivity: 1.0,
+            velocity: 0.0,
+            last_time: None,
+        }
+    }
+
+    /// Returns the acceleration profile
+    pub fn profile(&self) -> AccelProfile {
+        self.profile
+    }
+
+    /// Set the acceleration profile
+    pub fn set_profile(&mut self, profile: AccelProfile) {
+        self.profile = profile;
+    }
+
+    /// Returns the speed setting
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Set the speed in the range `[-1.0, 1.0]`, with `0.0` being the default
+    ///
+    /// For the flat profile this scales all motion between `0.0` and `2.0`,
+    /// for the adaptive profile this also lowers the velocity acceleration starts at.
+    /// Values outside of the range are clamped.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = if speed.is_nan() {
+            0.0
+        } else {
+            speed.clamp(-1.0, 1.0)
+        };
+    }
+
+    /// Returns the sensitivity of the device
+    pub fn sensitivity(&self) -> f64 {
+        self.sensitivity
+    }
+
+    /// Set a per-device sensitivity multiplier applied after the acceleration curve
+    ///
+    /// Useful to even out devices with differing resolutions, defaults to `1.0`.
+    /// Negative values are clamped to `0.0`.
+    pub fn set_sensitivity(&mut self, sensitivity: f64) {
+        self.sensitivity = if sensitivity.is_nan() {
+            1.0
+        } else {
+            sensitivity.max(0.0)
+        };
+    }
+
+    /// Reset the velocity tracking, e.g. after the device was suspended
+    pub fn reset(&mut self) {
+        self.velocity = 0.0;
+        self.last_time = None;
+    }
+
+    /// Accelerate the given motion delta
+    ///
+    /// `time` is the timestamp of the motion in microseconds, see [`Event::time`](super::Event::time).
+    pub fn accelerate(&mut self, delta: Point<f64, Logical>, time: u64) -> Point<f64, Logical> {
+        let factor = match self.profile {
+            AccelProfile::Flat => 1.0 + self.speed,
+            AccelProfile::Adaptive => {
+                self.track_velocity(delta, time);
+                self.adaptive_factor()
+            }
+        };
+        delta.upscale(factor * self.sensitivity)
+    }
+
+    /// Accelerate the unaccelerated delta of a pointer motion event
+    pub fn accelerate_event<B: InputBackend, E: PointerMotionEvent<B>>(
+        &mut self,
+        event: &E,
+    ) -> Point<f64, Logical> {
+        self.accelerate(event.delta_unaccel(), event.time())
+    }
+
+    fn track_velocity(&mut self, delta: Point<f64, Logical>, time: u64) {
+        let distance = delta.x.hypot(delta.y);
+        let velocity = match self.last_time {
+            Some(last) if time > last && time - last <= MAX_EVENT_GAP_USEC => {
+                Some(distance / ((time - last) as f64 / 1000.0))
+            }
+            // events with the same timestamp, e.g. split x and y motion
+            Some(last) if time == last => None,
+            _ => {
+                self.velocity = 0.0;
+                None
+            }
+        };
+        if let Some(velocity) = velocity {
+            self.velocity = SMOOTHING * velocity + (1.0 - SMOOTHING) * self.velocity;
+        }
+        self.last_time = Some(time);
+    }
+
+    fn adaptive_factor(&self) -> f64 {
+        // faster speeds accelerate earlier and further
+        let threshold = BASE_THRESHOLD * (1.0 - 0.5 * self.speed);
+        let max_factor = BASE_MAX_FACTOR + self.speed;
+        let base = 1.0 + 0.5 * self.speed.min(0.0);
+        if self.velocity <= threshold {
+            base
+        } else {
+            (base + (self.velocity - threshold) * INCLINE).min(max_factor.max(base))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccelProfile, PointerAccel};
+    use crate::utils::{Logical, Point};
+
+    fn motion(accel: &mut PointerAccel, distance: f64, interval_usec: u64, count: u64) -> f64 {
+        let mut result = 0.0;
+        for i in 0..count {
+            result = accel
+                .accelerate(Point::<f64, Logical>::from((distance, 0.0)), i * interval_usec)
+                .x;
+        }
+        result
+    }
+
+    #[test]
+    fn flat_speed() {
+        let mut accel = PointerAccel::new(AccelProfile::Flat);
+        assert_eq!(motion(&mut accel, 10.0, 1000, 3), 10.0);
+        accel.set_speed(0.5);
+        assert_eq!(motion(&mut accel, 10.0, 1000, 3), 15.0);
+        accel.set_speed(-3.0);
+        assert_eq!(accel.speed(), -1.0);
+        assert_eq!(motion(&mut accel, 10.0, 1000, 3), 0.0);
+    }
+
+    #[test]
+    fn adaptive_velocity() {
+        let mut accel = PointerAccel::new(AccelProfile::Adaptive);
+        // 0.1 units per ms is below the threshold
+        assert_eq!(motion(&mut accel, 0.1, 1000, 10), 0.1);
+
+        accel.reset();
+        // 10 units per ms is well above it and reaches the maximum
+        let fast = motion(&mut accel, 10.0, 1000, 10);
+        assert!((fast - 20.0).abs() < 1e-9);
+
+        accel.reset();
+        accel.set_sensitivity(0.5);
+        let fast = motion(&mut accel, 10.0, 1000, 10);
+        assert!((fast - 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,322 @@
+//! Per-device input configuration
+//!
+//! [`DeviceConfig`] describes the user facing settings of pointer devices uniformly over all
+//! backends. Devices which can apply them implement [`ConfigurableDevice`], which is the case for
+//! libinput devices and for [`PointerAccel`], the acceleration state used by backends delivering
+//! raw motion.
+//!
+//! [`DeviceConfigs`] keeps the configuration of every device by name, so it can be re-applied
+//! when a device is plugged in again, and notifies subscribers like settings UIs about changes.
+
+use std::collections::HashMap;
+
+use calloop::channel::{self, Channel, Sender};
+
+use super::{AccelProfile, PointerAccel};
+
+/// Method used to generate scroll events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollMethod {
+    /// Never generate scroll events from motion
+    NoScroll,
+    /// Scroll with two fingers on a touchpad
+    TwoFinger,
+    /// Scroll along the edges of a touchpad
+    Edge,
+    /// Scroll by moving the pointer while a button is held down
+    OnButtonDown,
+}
+
+bitflags::bitflags! {
+    /// Set of options of a [`DeviceConfig`]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ConfigOptions: u8 {
+        /// [`DeviceConfig::tap_to_click`]
+        const TAP_TO_CLICK = 1;
+        /// [`DeviceConfig::natural_scroll`]
+        const NATURAL_SCROLL = 1 << 1;
+        /// [`DeviceConfig::scroll_method`]
+        const SCROLL_METHOD = 1 << 2;
+        /// [`DeviceConfig::accel_profile`]
+        const ACCEL_PROFILE = 1 << 3;
+        /// [`DeviceConfig::accel_speed`]
+        const ACCEL_SPEED = 1 << 4;
+        /// [`DeviceConfig::sensitivity`]
+        const SENSITIVITY = 1 << 5;
+        /// [`DeviceConfig::left_handed`]
+        const LEFT_HANDED = 1 << 6;
+    }
+}
+
+/// Settings of an input device
+///
+/// Options set to `None` keep the current setting of the device.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DeviceConfig {
+    /// Generate clicks from taps on a touchpad
+    pub tap_to_click: Option<bool>,
+    /// Invert the scroll direction, so content follows the fingers
+    pub natural_scroll: Option<bool>,
+    /// Method used to generate scroll events
+    pub scroll_method: Option<ScrollMethod>,
+    /// Pointer acceleration profile
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer speed in the range `[-1.0, 1.0]`
+    pub accel_speed: Option<f64>,
+    /// Multiplier applied to all pointer motion, evening out devices with different resolutions
+    pub sensitivity: Option<f64>,
+    /// Swap the left and right buttons
+    pub left_handed: Option<bool>,
+    /// Remapping of button codes, applied with [`DeviceConfig::map_button`]
+    pub button_map: Vec<(u32, u32)>,
+}
+
+impl DeviceConfig {
+    /// Options set in this configuration
+    pub fn options(&self) -> ConfigOptions {
+        let mut options = ConfigOptions::empty();
+        options.set(ConfigOptions::TAP_TO_CLICK, self.tap_to_click.is_some());
+        options.set(ConfigOptions::NATURAL_SCROLL, self.natural_scroll.is_some());
+        options.set(ConfigOptions::SCROLL_METHOD, self.scroll_method.is_some());
+        options.set(ConfigOptions::ACCEL_PROFILE, self.accel_profile.is_some());
+        options.set(ConfigOptions::ACCEL_SPEED, self.accel_speed.is_some());
+        options.set(ConfigOptions::SENSITIVITY, self.sensitivity.is_some());
+        options.set(ConfigOptions::LEFT_HANDED, self.left_handed.is_some());
+        options
+    }
+
+    /// Overwrite the options set in `other`
+    pub fn merge(&mut self, other: &DeviceConfig) {
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field;
+                })*
+            };
+        }
+        merge!(
+            tap_to_click,
+            natural_scroll,
+            scroll_method,
+            accel_profile,
+            accel_speed,
+            sensitivity,
+            left_handed
+        );
+        if !other.button_map.is_empty() {
+            self.button_map = other.button_map.clone();
+        }
+    }
+
+    /// Remap a button code of a pointer button event
+    ///
+    /// Backends do not remap buttons themselves, so this has to be applied by the compositor
+    /// before passing the button on.
+    pub fn map_button(&self, button: u32) -> u32 {
+        self.button_map
+            .iter()
+            .find(|(from, _)| *from == button)
+            .map(|(_, to)| *to)
+            .unwrap_or(button)
+    }
+}
+
+/// Input device which settings can be configured
+pub trait ConfigurableDevice {
+    /// Options supported by the device
+    fn supported_options(&self) -> ConfigOptions;
+
+    /// Apply the options set in the configuration
+    ///
+    /// Returns the options that could not be applied, because the device does not support them
+    /// or rejected the value.
+    fn apply_config(&mut self, config: &DeviceConfig) -> ConfigOptions;
+}
+
+impl ConfigurableDevice for PointerAccel {
+    fn supported_options(&self) -> ConfigOptions {
+        ConfigOptions::ACCEL_PROFILE | ConfigOptions::ACCEL_SPEED | ConfigOptions::SENSITIVITY
+    }
+
+    fn apply_config(&mut self, config: &DeviceConfig) -> ConfigOptions {
+        if let Some(profile) = config.accel_profile {
+            self.set_profile(profile);
+        }
+        if let Some(speed) = config.accel_speed {
+            self.set_speed(speed);
+        }
+        if let Some(sensitivity) = config.sensitivity {
+            self.set_sensitivity(sensitivity);
+        }
+        config.options() - self.supported_options()
+    }
+}
+
+/// Change of the configuration of a device, see [`DeviceConfigs::subscribe`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceConfigChanged {
+    /// Name of the device
+    pub device: String,
+    /// New configuration of the device
+    pub config: DeviceConfig,
+}
+
+/// Store of the configuration of all devices
+#[derive(Debug, Default)]
+pub struct DeviceConfigs {
+    default: DeviceConfig,
+    devices: HashMap<String, DeviceConfig>,
+    subscribers: Vec<Sender<DeviceConfigChanged>>,
+}
+
+impl DeviceConfigs {
+    /// Create an empty store
+    pub fn new() -> DeviceConfigs {
+        DeviceConfigs::default()
+    }
+
+    /// Returns the configuration applied to devices without a configuration of their own
+    pub fn default_config(&self) -> &DeviceConfig {
+        &self.default
+    }
+
+    /// Set the configuration applied to devices without a configuration of their own
+    pub fn set_default_config(&mut self, config: DeviceConfig) {
+        self.default = config;
+    }
+
+    /// Returns the effective configuration of a device
+    ///
+    /// This is the default configuration with the options of the device applied on top.
+    pub fn config(&self, device: &str) -> DeviceConfig {
+        let mut config = self.default.clone();
+        if let Some(device) = self.devices.get(device) {
+            config.merge(device);
+        }
+        config
+    }
+
+    /// Update the options set in `config` for a device
+    ///
+    /// Subscribers are notified if the effective configuration of the device changed.
+    pub fn update(&mut self, device: &str, config: &DeviceConfig) {
+        let previous = self.config(device);
+        self.devices.entry(device.to_owned()).or_default().merge(config);
+        let config = self.config(device);
+        if config != previous {
+            self.notify(device, config);
+        }
+    }
+
+    /// Reset a device to the default configuration
+    pub fn reset(&mut self, device: &str) {
+        if self
+            .devices
+            .remove(device)
+            .is_some_and(|config| config != DeviceConfig::default())
+        {
+            self.notify(device, self.config(device));
+        }
+    }
+
+    /// Apply the effective configuration of a device, e.g. when it was added
+    ///
+    /// Returns the options that could not be applied.
+    pub fn apply<C: ConfigurableDevice + ?Sized>(&self, name: &str, device: &mut C) -> ConfigOptions {
+        device.apply_config(&self.config(name))
+    }
+
+    /// Receive every change of a device configuration
+    ///
+    /// The returned channel can be inserted into the event loop.
+    pub fn subscribe(&mut self) -> Channel<DeviceConfigChanged> {
+        let (sender, channel) = channel::channel();
+        self.subscribers.push(sender);
+        channel
+    }
+
+    fn notify(&mut self, device: &str, config: DeviceConfig) {
+        let change = DeviceConfigChanged {
+            device: device.to_owned(),
+            config,
+        };
+        self.subscribers
+            .retain(|subscriber| subscriber.send(change.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigOptions, ConfigurableDevice, DeviceConfig, DeviceConfigs};
+    use crate::backend::input::{AccelProfile, PointerAccel};
+
+    #[test]
+    fn effective_config() {
+        let mut configs = DeviceConfigs::new();
+        configs.set_default_config(DeviceConfig {
+            natural_scroll: Some(false),
+            accel_speed: Some(0.2),
+            ..Default::default()
+        });
+        configs.update(
+            "mouse",
+            &DeviceConfig {
+                accel_speed: Some(-0.5),
+                button_map: vec![(0x110, 0x111)],
+                ..Default::default()
+            },
+        );
+
+        let config = configs.config("mouse");
+        assert_eq!(config.natural_scroll, Some(false));
+        assert_eq!(config.accel_speed, Some(-0.5));
+        assert_eq!(config.map_button(0x110), 0x111);
+        assert_eq!(config.map_button(0x112), 0x112);
+        assert_eq!(configs.config("touchpad").accel_speed, Some(0.2));
+
+        configs.reset("mouse");
+        assert_eq!(configs.config("mouse").accel_speed, Some(0.2));
+    }
+
+    #[test]
+    fn notifies_changes() {
+        let mut configs = DeviceConfigs::new();
+        let channel = configs.subscribe();
+        let config = DeviceConfig {
+            tap_to_click: Some(true),
+            ..Default::default()
+        };
+        configs.update("touchpad", &config);
+        // no change, no notification
+        configs.update("touchpad", &config);
+
+        let mut event_loop = calloop::EventLoop::<Vec<String>>::try_new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(channel, |event, _, changes| {
+                if let calloop::channel::Event::Msg(change) = event {
+                    changes.push(change.device);
+                }
+            })
+            .unwrap();
+        let mut changes = Vec::new();
+        event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut changes)
+            .unwrap();
+        assert_eq!(changes, vec![String::from("touchpad")]);
+    }
+
+    #[test]
+    fn pointer_accel_config() {
+        let mut accel = PointerAccel::default();
+        let unsupported = accel.apply_config(&DeviceConfig {
+            accel_profile: Some(AccelProfile::Flat),
+            accel_speed: Some(0.5),
+            tap_to_click: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(unsupported, ConfigOptions::TAP_TO_CLICK);
+        assert_eq!(accel.profile(), AccelProfile::Flat);
+        assert_eq!(accel.speed(), 0.5);
+    }
+}
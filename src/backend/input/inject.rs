@@ -0,0 +1,532 @@
+//! Injection of synthetic input events
+//!
+//! Remote desktop sessions and input portals need to inject input, that was not generated by a
+//! local device. An [`InputInjector`] delivers these events, either through the input stack of
+//! the operating system or directly to the compositor:
+//!
+//! - `UinputInjector` (Linux, `backend_evdev` feature) creates virtual devices with uinput, which
+//!   are then picked up by libinput like any other device.
+//! - `SendInputInjector` (Windows) synthesizes events with `SendInput`, so they reach the window
+//!   of the compositor like events of real devices.
+//! - [`SeatInjector`] is the fallback, when the operating system stack is not available or not
+//!   desired, e.g. because the compositor has no permission to access `/dev/uinput`. It sends
+//!   the events to an [`InjectedInputSource`], which turns them into [`InputEvent`]s of the
+//!   [`InjectedInput`] backend, to be processed like the events of any other backend.
+//!
+//! ```no_run
+//! use smithay::backend::input::inject::{injection_channel, InjectedEvent, InputInjector};
+//! use smithay::backend::input::KeyState;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let (mut injector, source) = injection_channel();
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |event, _, _| {
+//!         // process the InputEvent like the events of other backends
+//!     })
+//!     .unwrap();
+//!
+//! # let key = 38u32.into();
+//! injector
+//!     .inject(InjectedEvent::Key { key, state: KeyState::Pressed })
+//!     .unwrap();
+//! ```
+
+use std::{collections::HashMap, io};
+
+use calloop::{
+    channel::{self, Channel, Sender},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use super::{
+    AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device, DeviceCapability,
+    Event, InputBackend, InputEvent, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent,
+    PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, UnusedEvent,
+};
+use crate::utils::{Clock, Logical, Monotonic, Point, Raw};
+
+/// Synthetic input event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InjectedEvent {
+    /// A key was pressed or released
+    Key {
+        /// Code of the key
+        key: Keycode,
+        /// New state of the key
+        state: KeyState,
+    },
+    /// The pointer moved relative to its current position
+    PointerMotion {
+        /// Distance of the motion
+        delta: Point<f64, Logical>,
+    },
+    /// The pointer moved to an absolute position
+    PointerMotionAbsolute {
+        /// Position normalized to `0.0..=1.0` across the whole desktop
+        position: Point<f64, Raw>,
+    },
+    /// A pointer button was pressed or released
+    PointerButton {
+        /// Linux input event code of the button, e.g. `0x110` for the left button
+        button: u32,
+        /// New state of the button
+        state: ButtonState,
+    },
+    /// The scroll wheel was turned
+    PointerAxis {
+        /// Scrolled axis
+        axis: Axis,
+        /// Distance scrolled in fractions of 120 per wheel click, positive values scroll down
+        /// or right
+        v120: f64,
+    },
+}
+
+impl InjectedEvent {
+    /// Convert an event of any input backend, e.g. to record it
+    ///
+    /// Returns nothing for events that cannot be injected, like touch or tablet events. Scroll
+    /// events without discrete steps are converted with 15 units per wheel click.
+    pub fn from_input_event<B: InputBackend>(event: &InputEvent<B>) -> Vec<InjectedEvent> {
+        match event {
+            InputEvent::Keyboard { event } => vec![InjectedEvent::Key {
+                key: event.key_code(),
+                state: event.state(),
+            }],
+            InputEvent::PointerMotion { event } => {
+                vec![InjectedEvent::PointerMotion { delta: event.delta() }]
+            }
+            InputEvent::PointerMotionAbsolute { event } => vec![InjectedEvent::PointerMotionAbsolute {
+                position: (event.x_transformed(1), event.y_transformed(1)).into(),
+            }],
+            InputEvent::PointerButton { event } => vec![InjectedEvent::PointerButton {
+                button: event.button_code(),
+                state: event.state(),
+            }],
+            InputEvent::PointerAxis { event } => [Axis::Vertical, Axis::Horizontal]
+                .into_iter()
+                .filter_map(|axis| {
+                    let v120 = event
+                        .amount_v120(axis)
+                        .or_else(|| event.amount(axis).map(|amount| amount * 8.0))?;
+                    Some(InjectedEvent::PointerAxis { axis, v120 })
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Sink of synthetic input events
+pub trait InputInjector {
+    /// Inject a single event
+    fn inject(&mut self, event: InjectedEvent) -> io::Result<()>;
+}
+
+/// Injector delivering events directly to the compositor
+///
+/// Created with [`injection_channel`] and cheap to clone.
+#[derive(Debug, Clone)]
+pub struct SeatInjector {
+    sender: Sender<InjectedEvent>,
+}
+
+impl InputInjector for SeatInjector {
+    fn inject(&mut self, event: InjectedEvent) -> io::Result<()> {
+        self.sender
+            .send(event)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "injected input source was dropped"))
+    }
+}
+
+/// Calloop event source generating the events sent by a [`SeatInjector`]
+#[derive(Debug)]
+pub struct InjectedInputSource {
+    channel: Channel<InjectedEvent>,
+    clock: Clock<Monotonic>,
+    key_counts: HashMap<Keycode, u32>,
+    announced: bool,
+}
+
+/// Create a [`SeatInjector`] and the source receiving its events
+pub fn injection_channel() -> (SeatInjector, InjectedInputSource) {
+    let (sender, channel) = channel::channel();
+    let source = InjectedInputSource {
+        channel,
+        clock: Clock::new(),
+        key_counts: HashMap::new(),
+        announced: false,
+    };
+    (SeatInjector { sender }, source)
+}
+
+impl EventSource for InjectedInputSource {
+    type Event = InputEvent<InjectedInput>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = channel::ChannelError;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let clock = &self.clock;
+        let key_counts = &mut self.key_counts;
+        let announced = &mut self.announced;
+        self.channel.process_events(readiness, token, |event, _| {
+            let channel::Event::Msg(event) = event else {
+                return;
+            };
+            if !*announced {
+                *announced = true;
+                callback(
+                    InputEvent::DeviceAdded {
+                        device: InjectedDevice,
+                    },
+                    &mut (),
+                );
+            }
+
+            let time = clock.now().as_micros();
+            callback(input_event(event, time, key_counts), &mut ());
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.channel.unregister(poll)
+    }
+}
+
+/// Turn an injected event into an [`InputEvent`] happening at `time` in microseconds
+///
+/// `key_counts` tracks the pressed keys for [`KeyboardKeyEvent::count`].
+pub(crate) fn input_event(
+    event: InjectedEvent,
+    time: u64,
+    key_counts: &mut HashMap<Keycode, u32>,
+) -> InputEvent<InjectedInput> {
+    match event {
+        InjectedEvent::Key { key, state } => {
+            let count = key_counts.entry(key).or_default();
+            *count = match state {
+                KeyState::Pressed => *count + 1,
+                KeyState::Released => count.saturating_sub(1),
+            };
+            InputEvent::Keyboard {
+                event: InjectedKeyboardKeyEvent {
+                    time,
+                    key,
+                    state,
+                    count: *count,
+                },
+            }
+        }
+        InjectedEvent::PointerMotion { delta } => InputEvent::PointerMotion {
+            event: InjectedPointerMotionEvent { time, delta },
+        },
+        InjectedEvent::PointerMotionAbsolute { position } => InputEvent::PointerMotionAbsolute {
+            event: InjectedPointerMotionAbsoluteEvent { time, position },
+        },
+        InjectedEvent::PointerButton { button, state } => InputEvent::PointerButton {
+            event: InjectedPointerButtonEvent { time, button, state },
+        },
+        InjectedEvent::PointerAxis { axis, v120 } => InputEvent::PointerAxis {
+            event: InjectedPointerAxisEvent { time, axis, v120 },
+        },
+    }
+}
+
+/// Marker used to define the `InputBackend` types of injected input
+#[derive(Debug)]
+pub struct InjectedInput;
+
+/// Virtual device all injected events originate from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InjectedDevice;
+
+impl Device for InjectedDevice {
+    fn id(&self) -> String {
+        "injected".to_owned()
+    }
+
+    fn name(&self) -> String {
+        "injected virtual input".to_owned()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(capability, DeviceCapability::Keyboard | DeviceCapability::Pointer)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+impl InputBackend for InjectedInput {
+    type Device = InjectedDevice;
+    type KeyboardKeyEvent = InjectedKeyboardKeyEvent;
+    type PointerAxisEvent = InjectedPointerAxisEvent;
+    type PointerButtonEvent = InjectedPointerButtonEvent;
+    type PointerMotionEvent = InjectedPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = InjectedPointerMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Injected key event
+#[derive(Debug, Clone)]
+pub struct InjectedKeyboardKeyEvent {
+    time: u64,
+    key: Keycode,
+    state: KeyState,
+    count: u32,
+}
+
+impl Event<InjectedInput> for InjectedKeyboardKeyEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> InjectedDevice {
+        InjectedDevice
+    }
+}
+
+impl KeyboardKeyEvent<InjectedInput> for InjectedKeyboardKeyEvent {
+    fn key_code(&self) -> Keycode {
+        self.key
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Injected relative pointer motion
+#[derive(Debug, Clone)]
+pub struct InjectedPointerMotionEvent {
+    time: u64,
+    delta: Point<f64, Logical>,
+}
+
+impl Event<InjectedInput> for InjectedPointerMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> InjectedDevice {
+        InjectedDevice
+    }
+}
+
+impl PointerMotionEvent<InjectedInput> for InjectedPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.x
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.y
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta.x
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta.y
+    }
+}
+
+/// Injected absolute pointer motion
+#[derive(Debug, Clone)]
+pub struct InjectedPointerMotionAbsoluteEvent {
+    time: u64,
+    position: Point<f64, Raw>,
+}
+
+impl Event<InjectedInput> for InjectedPointerMotionAbsoluteEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> InjectedDevice {
+        InjectedDevice
+    }
+}
+
+impl PointerMotionAbsoluteEvent<InjectedInput> for InjectedPointerMotionAbsoluteEvent {}
+impl AbsolutePositionEvent<InjectedInput> for InjectedPointerMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.position.x
+    }
+
+    fn y(&self) -> f64 {
+        self.position.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.position.x.clamp(0.0, 1.0) * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.position.y.clamp(0.0, 1.0) * height as f64
+    }
+}
+
+/// Injected pointer button event
+#[derive(Debug, Clone)]
+pub struct InjectedPointerButtonEvent {
+    time: u64,
+    button: u32,
+    state: ButtonState,
+}
+
+impl Event<InjectedInput> for InjectedPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> InjectedDevice {
+        InjectedDevice
+    }
+}
+
+impl PointerButtonEvent<InjectedInput> for InjectedPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Injected scroll wheel event
+#[derive(Debug, Clone)]
+pub struct InjectedPointerAxisEvent {
+    time: u64,
+    axis: Axis,
+    v120: f64,
+}
+
+impl Event<InjectedInput> for InjectedPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> InjectedDevice {
+        InjectedDevice
+    }
+}
+
+impl PointerAxisEvent<InjectedInput> for InjectedPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        // 15 degrees per wheel click, like libinput reports for most mice
+        self.amount_v120(axis).map(|v120| v120 / 8.0)
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        Some(if axis == self.axis { self.v120 } else { 0.0 })
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seat_injection() {
+        let (mut injector, source) = injection_channel();
+        let mut event_loop = calloop::EventLoop::<Vec<InputEvent<InjectedInput>>>::try_new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(source, |event, _, events| events.push(event))
+            .unwrap();
+
+        let key = Keycode::from(38u32);
+        for state in [KeyState::Pressed, KeyState::Released] {
+            injector.inject(InjectedEvent::Key { key, state }).unwrap();
+        }
+        injector
+            .inject(InjectedEvent::PointerAxis {
+                axis: Axis::Vertical,
+                v120: 120.0,
+            })
+            .unwrap();
+
+        let mut events = Vec::new();
+        event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut events)
+            .unwrap();
+        assert!(matches!(
+            events[0],
+            InputEvent::DeviceAdded {
+                device: InjectedDevice
+            }
+        ));
+        let InputEvent::Keyboard { event } = &events[1] else {
+            panic!("expected a key event");
+        };
+        assert_eq!((event.state(), event.count()), (KeyState::Pressed, 1));
+        let InputEvent::Keyboard { event } = &events[2] else {
+            panic!("expected a key event");
+        };
+        assert_eq!((event.state(), event.count()), (KeyState::Released, 0));
+        let InputEvent::PointerAxis { event } = &events[3] else {
+            panic!("expected an axis event");
+        };
+        assert_eq!(event.amount(Axis::Vertical), Some(15.0));
+        assert_eq!(event.amount_v120(Axis::Horizontal), Some(0.0));
+    }
+}
@@ -0,0 +1,116 @@
+//! Synchronization of the keyboard lock indicators
+//!
+//! The lock state of a seat is tracked by its [`KeyboardHandle`](crate::input::keyboard::KeyboardHandle)
+//! across all physical keyboards, but the indicators of the devices themselves are not updated
+//! automatically. Forward the state reported by
+//! [`SeatHandler::led_state_changed`](crate::input::SeatHandler::led_state_changed) to every
+//! keyboard of the seat using [`KeyboardLeds`], so that e.g. toggling caps lock on one keyboard
+//! lights up the indicator of all of them.
+//!
+//! On Linux, [`KeyboardLeds`] is implemented for libinput devices, which sets the indicators
+//! through evdev. On Windows the indicators follow the system wide toggle state of the lock keys,
+//! which [`SystemKeyboardLeds`] updates.
+
+use crate::input::keyboard::LedState;
+
+/// Device with keyboard lock indicators
+pub trait KeyboardLeds {
+    /// Set the indicators of the device
+    ///
+    /// Indicators whose state is `None` in `leds`, because the keymap has no such indicator,
+    /// are turned off.
+    fn update_leds(&mut self, leds: LedState);
+}
+
+/// Lock indicators which have to be toggled to turn `current` into `wanted`
+///
+/// Returns the toggles for num lock, caps lock and scroll lock in that order.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn toggles(current: LedState, wanted: LedState) -> [bool; 3] {
+    let differs = |current: Option<bool>, wanted: Option<bool>| {
+        current.unwrap_or_default() != wanted.unwrap_or_default()
+    };
+    [
+        differs(current.num, wanted.num),
+        differs(current.caps, wanted.caps),
+        differs(current.scroll, wanted.scroll),
+    ]
+}
+
+/// Lock indicators of all keyboards attached to the system
+///
+/// Windows does not allow setting the indicators of individual keyboards, instead they show the
+/// global toggle state of the lock keys. Updating them synthesizes presses of the lock keys, which
+/// are reported back by the input backend as injected events and should be ignored there.
+#[cfg(windows)]
+#[derive(Debug, Default)]
+pub struct SystemKeyboardLeds;
+
+#[cfg(windows)]
+impl SystemKeyboardLeds {
+    /// Returns the current global toggle state of the lock keys
+    pub fn current() -> LedState {
+        let toggled = |vk| unsafe { ffi::GetKeyState(vk as i32) } & 1 != 0;
+        LedState {
+            num: Some(toggled(ffi::VK_NUMLOCK)),
+            caps: Some(toggled(ffi::VK_CAPITAL)),
+            scroll: Some(toggled(ffi::VK_SCROLL)),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl KeyboardLeds for SystemKeyboardLeds {
+    fn update_leds(&mut self, leds: LedState) {
+        let keys = [
+            (ffi::VK_NUMLOCK, ffi::KEYEVENTF_EXTENDEDKEY),
+            (ffi::VK_CAPITAL, 0),
+            (ffi::VK_SCROLL, 0),
+        ];
+        for ((vk, flags), toggle) in keys.into_iter().zip(toggles(Self::current(), leds)) {
+            if toggle {
+                unsafe {
+                    ffi::keybd_event(vk, 0, flags, 0);
+                    ffi::keybd_event(vk, 0, flags | ffi::KEYEVENTF_KEYUP, 0);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod ffi {
+    pub const VK_CAPITAL: u8 = 0x14;
+    pub const VK_NUMLOCK: u8 = 0x90;
+    pub const VK_SCROLL: u8 = 0x91;
+    pub const KEYEVENTF_EXTENDEDKEY: u32 = 0x1;
+    pub const KEYEVENTF_KEYUP: u32 = 0x2;
+
+    #[link(name = "user32")]
+    extern "system" {
+        pub fn GetKeyState(virt_key: i32) -> i16;
+        pub fn keybd_event(vk: u8, scan: u8, flags: u32, extra_info: usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::toggles;
+    use crate::input::keyboard::LedState;
+
+    #[test]
+    fn toggles_changed_leds() {
+        let current = LedState {
+            num: Some(true),
+            caps: Some(false),
+            scroll: None,
+        };
+        let wanted = LedState {
+            num: Some(true),
+            caps: Some(true),
+            scroll: Some(false),
+        };
+        assert_eq!(toggles(current, wanted), [false, true, false]);
+        assert_eq!(toggles(wanted, LedState::default()), [true, true, false]);
+    }
+}
@@ -10,8 +10,24 @@ pub use xkbcommon::xkb::Keycode;
 /// Keycode type for Windows (equivalent to virtual key code)
 pub type Keycode = u32;
 
+mod accel;
+mod config;
+pub mod inject;
+mod leds;
+mod switch;
 mod tablet;
 
+pub use accel::{AccelProfile, PointerAccel};
+pub use config::{
+    ConfigOptions, ConfigurableDevice, DeviceConfig, DeviceConfigChanged, DeviceConfigs, ScrollMethod,
+};
+pub use leds::KeyboardLeds;
+#[cfg(windows)]
+pub use leds::SystemKeyboardLeds;
+pub use switch::SwitchStates;
+#[cfg(windows)]
+pub use switch::TabletModeSource;
+
 pub use tablet::{
     ProximityState, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolCapabilities, TabletToolDescriptor,
     TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
@@ -0,0 +1,175 @@
+//! Tracking of switch states
+//!
+//! Switch toggle events only report changes, but compositors usually need the current state,
+//! e.g. to skip enabling the internal output of a laptop with a closed lid after a hotplug.
+//! [`SwitchStates`] keeps the last known state of every switch.
+//!
+//! On Linux switch events are delivered by libinput. On Windows [`TabletModeSource`] reports
+//! the convertible slate mode of the system as [`Switch::TabletMode`]. Windows does not expose
+//! the lid state outside of power broadcasts to a window, so lid events are not available there.
+
+use super::{InputBackend, Switch, SwitchState, SwitchToggleEvent};
+
+/// Last known state of the switches of a seat
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SwitchStates {
+    lid: Option<SwitchState>,
+    tablet_mode: Option<SwitchState>,
+}
+
+impl SwitchStates {
+    /// Create a new tracker without any known state
+    pub fn new() -> SwitchStates {
+        SwitchStates::default()
+    }
+
+    /// Update the state of a switch
+    ///
+    /// Returns `true` if the state changed.
+    pub fn update(&mut self, switch: Switch, state: SwitchState) -> bool {
+        let slot = match switch {
+            Switch::Lid => &mut self.lid,
+            Switch::TabletMode => &mut self.tablet_mode,
+        };
+        slot.replace(state) != Some(state)
+    }
+
+    /// Update the state from a switch toggle event
+    ///
+    /// Returns the toggled switch if its state changed.
+    pub fn update_from_event<B: InputBackend, E: SwitchToggleEvent<B>>(
+        &mut self,
+        event: &E,
+    ) -> Option<Switch> {
+        let switch = event.switch()?;
+        self.update(switch, event.state()).then_some(switch)
+    }
+
+    /// Returns the last known state of a switch
+    pub fn state(&self, switch: Switch) -> Option<SwitchState> {
+        match switch {
+            Switch::Lid => self.lid,
+            Switch::TabletMode => self.tablet_mode,
+        }
+    }
+
+    /// Returns `true` if the lid is known to be closed
+    pub fn lid_closed(&self) -> bool {
+        self.lid == Some(SwitchState::On)
+    }
+
+    /// Returns `true` if the device is known to be in tablet mode
+    pub fn tablet_mode(&self) -> bool {
+        self.tablet_mode == Some(SwitchState::On)
+    }
+}
+
+#[cfg(windows)]
+pub use self::windows::TabletModeSource;
+
+#[cfg(windows)]
+mod windows {
+    use std::time::{Duration, Instant};
+
+    use calloop::{
+        timer::{TimeoutAction, Timer},
+        EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+    };
+
+    use super::super::{Switch, SwitchState};
+
+    const SM_CONVERTIBLESLATEMODE: i32 = 0x2003;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetSystemMetrics(index: i32) -> i32;
+    }
+
+    fn tablet_mode() -> SwitchState {
+        // zero means the system is in slate mode
+        if unsafe { GetSystemMetrics(SM_CONVERTIBLESLATEMODE) } == 0 {
+            SwitchState::On
+        } else {
+            SwitchState::Off
+        }
+    }
+
+    /// Calloop event source reporting the tablet mode of a convertible device on Windows
+    ///
+    /// Generates a [`Switch::TabletMode`] event with the initial state and whenever
+    /// the mode changes afterwards.
+    #[derive(Debug)]
+    pub struct TabletModeSource {
+        timer: Timer,
+        interval: Duration,
+        last: Option<SwitchState>,
+    }
+
+    impl TabletModeSource {
+        /// Create a new source polling the mode at the given interval
+        pub fn new(interval: Duration) -> TabletModeSource {
+            TabletModeSource {
+                timer: Timer::immediate(),
+                interval,
+                last: None,
+            }
+        }
+    }
+
+    impl EventSource for TabletModeSource {
+        type Event = (Switch, SwitchState);
+        type Metadata = ();
+        type Ret = ();
+        type Error = <Timer as EventSource>::Error;
+
+        fn process_events<F>(
+            &mut self,
+            readiness: Readiness,
+            token: Token,
+            mut callback: F,
+        ) -> Result<PostAction, Self::Error>
+        where
+            F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+        {
+            let interval = self.interval;
+            let last = &mut self.last;
+            self.timer.process_events(readiness, token, |_: Instant, _| {
+                let state = tablet_mode();
+                if last.replace(state) != Some(state) {
+                    callback((Switch::TabletMode, state), &mut ());
+                }
+                TimeoutAction::ToDuration(interval)
+            })
+        }
+
+        fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            self.timer.register(poll, token_factory)
+        }
+
+        fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            self.timer.reregister(poll, token_factory)
+        }
+
+        fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+            self.timer.unregister(poll)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SwitchStates;
+    use crate::backend::input::{Switch, SwitchState};
+
+    #[test]
+    fn tracks_changes() {
+        let mut states = SwitchStates::new();
+        assert_eq!(states.state(Switch::Lid), None);
+        assert!(states.update(Switch::Lid, SwitchState::On));
+        assert!(!states.update(Switch::Lid, SwitchState::On));
+        assert!(states.lid_closed());
+        assert!(!states.tablet_mode());
+        assert!(states.update(Switch::Lid, SwitchState::Off));
+        assert_eq!(states.state(Switch::Lid), Some(SwitchState::Off));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use input as libinput;
+
+use crate::{
+    output::Output,
+    utils::{Point, Raw, Transform},
+};
+
+/// Touchscreen calibration matrix
+///
+/// Maps normalized device coordinates `(x, y)` to `(a * x + b * y + c, d * x + e * y + f)` for
+/// a matrix `[a, b, c, d, e, f]`, correcting digitizers that are not aligned with their
+/// panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationMatrix(pub [f32; 6]);
+
+impl CalibrationMatrix {
+    /// Matrix not changing any coordinates
+    pub const IDENTITY: CalibrationMatrix = CalibrationMatrix([1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+
+    /// Matrix for a digitizer mounted with the given transform relative to its panel
+    pub fn from_transform(transform: Transform) -> CalibrationMatrix {
+        let area = (1.0, 1.0).into();
+        let apply = |x: f32, y: f32| transform.transform_point_in(Point::<f32, Raw>::from((x, y)), &area);
+        let origin = apply(0.0, 0.0);
+        let x_axis = apply(1.0, 0.0) - origin;
+        let y_axis = apply(0.0, 1.0) - origin;
+        CalibrationMatrix([x_axis.x, y_axis.x, origin.x, x_axis.y, y_axis.y, origin.y])
+    }
+
+    /// Apply the matrix to normalized device coordinates
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + b * y + c, d * x + e * y + f)
+    }
+}
+
+impl Default for CalibrationMatrix {
+    fn default() -> Self {
+        CalibrationMatrix::IDENTITY
+    }
+}
+
+/// Additional configuration of libinput devices
+pub trait LibinputDeviceExt {
+    /// Returns `true` if the device supports a calibration matrix
+    fn has_calibration(&self) -> bool;
+    /// Current calibration matrix, if supported
+    fn calibration(&self) -> Option<CalibrationMatrix>;
+    /// Calibration matrix configured for the device by udev, if supported
+    fn default_calibration(&self) -> Option<CalibrationMatrix>;
+    /// Set the calibration matrix
+    fn set_calibration(&mut self, matrix: CalibrationMatrix) -> Result<(), libinput::DeviceConfigError>;
+    /// Name of the output the device is associated with by udev (`WL_OUTPUT`), if any
+    fn output_hint(&self) -> Option<String>;
+    /// Returns `true` if both devices are part of the same physical device, e.g. the touch
+    /// and pen digitizers of one screen
+    fn same_group(&self, other: &Self) -> bool;
+}
+
+impl LibinputDeviceExt for libinput::Device {
+    fn has_calibration(&self) -> bool {
+        self.config_calibration_has_matrix()
+    }
+
+    fn calibration(&self) -> Option<CalibrationMatrix> {
+        self.config_calibration_matrix().map(CalibrationMatrix)
+    }
+
+    fn default_calibration(&self) -> Option<CalibrationMatrix> {
+        self.config_calibration_default_matrix().map(CalibrationMatrix)
+    }
+
+    fn set_calibration(&mut self, matrix: CalibrationMatrix) -> Result<(), libinput::DeviceConfigError> {
+        self.config_calibration_set_matrix(matrix.0)
+    }
+
+    fn output_hint(&self) -> Option<String> {
+        self.output_name().map(String::from)
+    }
+
+    fn same_group(&self, other: &Self) -> bool {
+        self.device_group() == other.device_group()
+    }
+}
+
+/// Association of absolute input devices with outputs
+///
+/// Touchscreens and tablets report positions relative to the screen they are attached to.
+/// With multiple screens each device has to be mapped to its output, which is resolved in order:
+///
+/// 1. an explicit association made with [`DeviceOutputMapping::associate`],
+/// 2. the association of another device in the same device group,
+/// 3. the udev hint of the device ([`LibinputDeviceExt::output_hint`]).
+#[derive(Debug, Default)]
+pub struct DeviceOutputMapping {
+    explicit: HashMap<String, String>,
+    devices: Vec<libinput::Device>,
+}
+
+impl DeviceOutputMapping {
+    /// Create an empty mapping
+    pub fn new() -> DeviceOutputMapping {
+        DeviceOutputMapping::default()
+    }
+
+    /// Track a newly added device, for the resolution of device groups
+    pub fn add_device(&mut self, device: &libinput::Device) {
+        self.devices.push(device.clone());
+    }
+
+    /// Stop tracking a removed device
+    ///
+    /// Explicit associations are kept, so they apply again once the device is plugged back in.
+    pub fn remove_device(&mut self, device: &libinput::Device) {
+        self.devices.retain(|d| d != device);
+    }
+
+    /// Associate the device with the output of the given name
+    pub fn associate(&mut self, device: &libinput::Device, output: &str) {
+        self.explicit
+            .insert(device.sysname().to_owned(), output.to_owned());
+    }
+
+    /// Remove the explicit association of the device
+    pub fn dissociate(&mut self, device: &libinput::Device) {
+        self.explicit.remove(device.sysname());
+    }
+
+    /// Name of the output the device is associated with
+    pub fn output_name(&self, device: &libinput::Device) -> Option<String> {
+        if let Some(output) = self.explicit.get(device.sysname()) {
+            return Some(output.clone());
+        }
+        self.devices
+            .iter()
+            .filter(|other| *other != device && other.same_group(device))
+            .find_map(|other| self.explicit.get(other.sysname()).cloned())
+            .or_else(|| device.output_hint())
+    }
+
+    /// Find the output the device is associated with among the given outputs
+    pub fn output<'a>(
+        &self,
+        device: &libinput::Device,
+        outputs: impl IntoIterator<Item = &'a Output>,
+    ) -> Option<&'a Output> {
+        let name = self.output_name(device)?;
+        outputs.into_iter().find(|output| output.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CalibrationMatrix;
+    use crate::utils::Transform;
+
+    #[test]
+    fn calibration_from_transform() {
+        assert_eq!(
+            CalibrationMatrix::from_transform(Transform::Normal),
+            CalibrationMatrix::IDENTITY
+        );
+        // values documented by libinput for rotated touchscreens
+        assert_eq!(
+            CalibrationMatrix::from_transform(Transform::_90).0,
+            [0.0, -1.0, 1.0, 1.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            CalibrationMatrix::from_transform(Transform::_180).0,
+            [-1.0, 0.0, 1.0, 0.0, -1.0, 1.0]
+        );
+        assert_eq!(
+            CalibrationMatrix::from_transform(Transform::_270).0,
+            [0.0, 1.0, 0.0, -1.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            CalibrationMatrix::from_transform(Transform::Flipped).apply(0.25, 0.5),
+            (0.75, 0.5)
+        );
+    }
+}
@@ -20,8 +20,11 @@ use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, T
 
 use tracing::{debug_span, info, trace};
 
+mod devices;
 mod tablet;
 
+pub use devices::{CalibrationMatrix, DeviceOutputMapping, LibinputDeviceExt};
+
 /// Libinput based [`InputBackend`].
 ///
 /// Tracks input of all devices given manually or via a udev seat to a provided libinput
@@ -669,6 +672,108 @@ impl From<crate::input::keyboard::LedState> for libinput::Led {
     }
 }
 
+impl From<backend::ScrollMethod> for libinput::ScrollMethod {
+    #[inline]
+    fn from(method: backend::ScrollMethod) -> Self {
+        match method {
+            backend::ScrollMethod::NoScroll => libinput::ScrollMethod::NoScroll,
+            backend::ScrollMethod::TwoFinger => libinput::ScrollMethod::TwoFinger,
+            backend::ScrollMethod::Edge => libinput::ScrollMethod::Edge,
+            backend::ScrollMethod::OnButtonDown => libinput::ScrollMethod::OnButtonDown,
+        }
+    }
+}
+
+impl backend::ConfigurableDevice for libinput::Device {
+    fn supported_options(&self) -> backend::ConfigOptions {
+        let mut options = backend::ConfigOptions::empty();
+        options.set(
+            backend::ConfigOptions::TAP_TO_CLICK,
+            self.config_tap_finger_count() > 0,
+        );
+        options.set(
+            backend::ConfigOptions::NATURAL_SCROLL,
+            self.config_scroll_has_natural_scroll(),
+        );
+        options.set(
+            backend::ConfigOptions::SCROLL_METHOD,
+            self.config_scroll_methods()
+                .iter()
+                .any(|method| *method != libinput::ScrollMethod::NoScroll),
+        );
+        options.set(
+            backend::ConfigOptions::ACCEL_PROFILE | backend::ConfigOptions::ACCEL_SPEED,
+            self.config_accel_is_available(),
+        );
+        options.set(
+            backend::ConfigOptions::LEFT_HANDED,
+            self.config_left_handed_is_available(),
+        );
+        options
+    }
+
+    fn apply_config(&mut self, config: &backend::DeviceConfig) -> backend::ConfigOptions {
+        let mut failed = backend::ConfigOptions::empty();
+        let mut check = |option, result: libinput::DeviceConfigResult| {
+            if result.is_err() {
+                failed |= option;
+            }
+        };
+        if let Some(enabled) = config.tap_to_click {
+            check(
+                backend::ConfigOptions::TAP_TO_CLICK,
+                self.config_tap_set_enabled(enabled),
+            );
+        }
+        if let Some(enabled) = config.natural_scroll {
+            check(
+                backend::ConfigOptions::NATURAL_SCROLL,
+                self.config_scroll_set_natural_scroll_enabled(enabled),
+            );
+        }
+        if let Some(method) = config.scroll_method {
+            check(
+                backend::ConfigOptions::SCROLL_METHOD,
+                self.config_scroll_set_method(method.into()),
+            );
+        }
+        if let Some(profile) = config.accel_profile {
+            let profile = match profile {
+                backend::AccelProfile::Flat => libinput::AccelProfile::Flat,
+                backend::AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+            };
+            check(
+                backend::ConfigOptions::ACCEL_PROFILE,
+                self.config_accel_set_profile(profile),
+            );
+        }
+        if let Some(speed) = config.accel_speed {
+            check(
+                backend::ConfigOptions::ACCEL_SPEED,
+                self.config_accel_set_speed(speed),
+            );
+        }
+        if let Some(enabled) = config.left_handed {
+            check(
+                backend::ConfigOptions::LEFT_HANDED,
+                self.config_left_handed_set(enabled),
+            );
+        }
+        // libinput applies no sensitivity beyond the acceleration speed
+        if config.sensitivity.is_some() {
+            failed |= backend::ConfigOptions::SENSITIVITY;
+        }
+        failed
+    }
+}
+
+impl backend::KeyboardLeds for libinput::Device {
+    #[inline]
+    fn update_leds(&mut self, leds: crate::input::keyboard::LedState) {
+        self.led_update(leds.into());
+    }
+}
+
 /// Wrapper for types implementing the [`Session`] trait to provide
 /// a [`libinput::LibinputInterface`] implementation.
 #[cfg(feature = "backend_session")]
@@ -27,8 +27,10 @@
 //! all inputs events from it. Smithay is build to support different possible sources for
 //! that input data, with a generic API provided by the traits and types defined in the
 //! [`input`] module. An input provider following this API based on `libinput` is given in the
-//! [`libinput`] module, gated by the `backend_libinput` cargo feature. The winit backend
-//! (see below) also provides an input provider.
+//! [`libinput`] module, gated by the `backend_libinput` cargo feature. A minimal provider
+//! reading evdev devices directly, for systems without libinput, is given in the `evdev`
+//! module, gated by the `backend_evdev` cargo feature. The winit backend (see below) also
+//! provides an input provider.
 //!
 //! ### Graphics
 //!
@@ -81,14 +83,21 @@
 //! accessible in the [`winit`] module, gated by the `backend_winit` cargo feature.
 //!
 
+pub mod adapter;
 pub mod allocator;
+pub mod auto;
+pub mod capabilities;
 pub mod input;
+pub mod power;
 pub mod renderer;
+pub mod watchdog;
 
 #[cfg(feature = "backend_drm")]
 pub mod drm;
 #[cfg(all(unix, feature = "backend_egl"))]
 pub mod egl;
+#[cfg(all(target_os = "linux", feature = "backend_evdev"))]
+pub mod evdev;
 #[cfg(feature = "backend_libinput")]
 pub mod libinput;
 #[cfg(feature = "backend_session")]
@@ -102,7 +111,10 @@ pub mod vulkan;
 #[cfg(all(windows, feature = "backend_wgl"))]
 pub mod wgl;
 
-#[cfg(feature = "backend_winit")]
+#[cfg(windows)]
+pub mod win32;
+
+#[cfg(any(feature = "backend_winit", all(windows, feature = "backend_winit_windows")))]
 pub mod winit;
 
 #[cfg(feature = "backend_x11")]
@@ -0,0 +1,115 @@
+//! Suspend and resume of the system
+//!
+//! GPU and display state does not reliably survive a system suspend: page flips submitted right
+//! before going to sleep may never complete, displays lose their modes and swapchains of nested
+//! backends may be lost. Without handling the suspend, outputs often stay black after waking up.
+//!
+//! The system announces a suspend as [`PowerEvent::Suspend`] and the wakeup as
+//! [`PowerEvent::Resume`]. They are delivered by the platform:
+//!
+//! - on Linux by logind, see `LogindEvent::power_event` in `utils::dbus::logind`,
+//! - on Windows by the `SuspendResumeSource` in `backend::win32::power`.
+//!
+//! Before the system sleeps, the compositor pauses rendering with [`suspend_outputs`] and calls
+//! [`PowerHooks::suspend`] of its renderers and backends, which waits for all submitted GPU work.
+//! After the system resumed it calls [`PowerHooks::resume`], which rebuilds the swapchains of the
+//! outputs, and [`resume_outputs`] to redraw every output from scratch.
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::backend::power::{self, PowerEvent, PowerHooks};
+//! # use smithay::output::Output;
+//! # use smithay::backend::renderer::gles::GlesRenderer;
+//!
+//! # let outputs: Vec<Output> = Vec::new();
+//! # let renderer: &mut GlesRenderer = unimplemented!();
+//! # let event = PowerEvent::Suspend;
+//! match event {
+//!     PowerEvent::Suspend => {
+//!         power::suspend_outputs(&outputs);
+//!         renderer.suspend().unwrap();
+//!     }
+//!     PowerEvent::Resume => {
+//!         renderer.resume().unwrap();
+//!         power::resume_outputs(&outputs);
+//!     }
+//! }
+//! ```
+
+use crate::output::Output;
+
+/// Hooks of renderers and backends for system suspend and resume
+///
+/// Implemented by the renderers and output backends of smithay, see the
+/// [module-level documentation](self).
+pub trait PowerHooks {
+    /// Error returned by the hooks
+    type Error;
+
+    /// Prepare for a system suspend
+    ///
+    /// Blocks until all GPU work submitted so far is finished, so nothing is in flight while the
+    /// GPU is powered down.
+    fn suspend(&mut self) -> Result<(), Self::Error>;
+
+    /// Restore the state after the system resumed
+    ///
+    /// Swapchains are rebuilt and buffer ages reset, so the next frame is rendered from scratch.
+    fn resume(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Power state change of the system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerEvent {
+    /// The system is about to suspend
+    Suspend,
+    /// The system resumed from suspend
+    Resume,
+}
+
+/// Pause the [`FrameClock`](crate::output::FrameClock)s of the given outputs
+///
+/// No frames should be rendered until the outputs are resumed, see
+/// [`FrameClock::set_suspended`](crate::output::FrameClock::set_suspended).
+pub fn suspend_outputs<'a>(outputs: impl IntoIterator<Item = &'a Output>) {
+    for output in outputs {
+        output.frame_clock().set_suspended(true);
+    }
+}
+
+/// Resume the [`FrameClock`](crate::output::FrameClock)s of the given outputs
+///
+/// Every output is asked for a full redraw with
+/// [`RedrawReasons::RESUME`](crate::output::RedrawReasons::RESUME).
+pub fn resume_outputs<'a>(outputs: impl IntoIterator<Item = &'a Output>) {
+    for output in outputs {
+        output.frame_clock().set_suspended(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{PhysicalProperties, RedrawReasons, Subpixel};
+
+    #[test]
+    fn outputs_redraw_after_resume() {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "test".into(),
+                model: "test".into(),
+                serial_number: "test".into(),
+            },
+        );
+
+        suspend_outputs([&output]);
+        assert!(output.frame_clock().is_suspended());
+
+        resume_outputs([&output]);
+        assert!(!output.frame_clock().is_suspended());
+        assert!(output.frame_clock().pending().contains(RedrawReasons::RESUME));
+    }
+}
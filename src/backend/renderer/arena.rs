@@ -0,0 +1,443 @@
+use std::{
+    alloc::{self, Layout},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    sync::{Arc, Mutex},
+};
+
+use tracing::trace;
+
+/// Size of the first chunk of an arena created without a capacity
+const MIN_CHUNK_SIZE: usize = 4096;
+/// Alignment of all chunks, large enough for every primitive type
+const CHUNK_ALIGN: usize = 16;
+
+#[derive(Debug)]
+struct Chunk {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Chunk {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("Arena chunk too large");
+        // SAFETY: chunks are never empty
+        let ptr = unsafe { alloc::alloc(layout) };
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(layout);
+        };
+        Chunk { ptr, size }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: allocated with the same layout in `Chunk::new`
+        unsafe {
+            alloc::dealloc(
+                self.ptr.as_ptr(),
+                Layout::from_size_align_unchecked(self.size, CHUNK_ALIGN),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Chunks {
+    chunks: Vec<Chunk>,
+    /// Bytes used of the last chunk
+    used: usize,
+}
+
+impl Chunks {
+    fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.size).sum()
+    }
+
+    fn alloc(&mut self, layout: Layout) -> NonNull<u8> {
+        if let Some(chunk) = self.chunks.last() {
+            // SAFETY: `used` never exceeds the size of the chunk
+            let start = unsafe { chunk.ptr.as_ptr().add(self.used) };
+            let offset = self.used + start.align_offset(layout.align());
+            if offset <= chunk.size && chunk.size - offset >= layout.size() {
+                self.used = offset + layout.size();
+                // SAFETY: `offset` is in bounds of the chunk
+                return unsafe { NonNull::new_unchecked(chunk.ptr.as_ptr().add(offset)) };
+            }
+        }
+
+        // the last chunk is full, allocate one at least twice as large
+        let size = self
+            .chunks
+            .last()
+            .map(|chunk| chunk.size * 2)
+            .unwrap_or(MIN_CHUNK_SIZE)
+            .max(layout.size() + layout.align());
+        trace!(size, "Growing frame arena");
+        let chunk = Chunk::new(size);
+        let offset = chunk.ptr.as_ptr().align_offset(layout.align());
+        // SAFETY: the chunk is large enough for any alignment padding and the layout
+        let ptr = unsafe { NonNull::new_unchecked(chunk.ptr.as_ptr().add(offset)) };
+        self.chunks.push(chunk);
+        self.used = offset + layout.size();
+        ptr
+    }
+}
+
+/// Bump allocator for data only needed while building and rendering a single frame
+///
+/// Allocating from the arena only bumps an offset; the memory is given back all at once by
+/// [`FrameArena::reset`]. When a frame needed more memory than the arena had, the arena grows
+/// and is consolidated into a single chunk on the next reset, so once frames stop growing the
+/// arena does not allocate anymore.
+///
+/// `FrameArena` is a cheap handle, clones allocate from the same memory. Values allocated from
+/// the arena borrow the handle they were allocated with and are never dropped by the arena, which
+/// is why only [`Copy`] values can be allocated directly. Use [`ArenaVec`] for everything else.
+///
+/// Renderers keeping an arena expose it via [`Renderer::frame_arena`](super::Renderer::frame_arena)
+/// and [`Frame::arena`](super::Frame::arena) and reset it at the start of every frame, so render
+/// elements can use it for their transient data while drawing:
+///
+/// ```no_run
+/// use smithay::{
+///     backend::renderer::Frame,
+///     utils::{Physical, Rectangle},
+/// };
+///
+/// fn draw<F: Frame>(frame: &mut F, damage: &[Rectangle<i32, Physical>]) -> Result<(), F::Error> {
+///     let arena = frame.arena().cloned().unwrap_or_default();
+///     let mut split = arena.vec_with_capacity(damage.len() * 2);
+///     for rect in damage {
+///         // ...
+/// #       split.push(*rect);
+///     }
+///     // ...
+/// #   Ok(())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct FrameArena {
+    inner: Arc<Mutex<Chunks>>,
+}
+
+// SAFETY: the chunks are plain memory owned by the arena, access to the bump offset is
+// synchronized by the mutex and all allocations are disjoint
+unsafe impl Send for Chunks {}
+
+impl fmt::Debug for FrameArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameArena")
+            .field("capacity", &self.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FrameArena {
+    /// Create a new empty arena
+    ///
+    /// Memory is allocated lazily by the first allocation.
+    pub fn new() -> FrameArena {
+        FrameArena::default()
+    }
+
+    /// Create a new arena able to hold `capacity` bytes before it grows
+    pub fn with_capacity(capacity: usize) -> FrameArena {
+        let arena = FrameArena::new();
+        if capacity > 0 {
+            arena.inner.lock().unwrap().chunks.push(Chunk::new(capacity));
+        }
+        arena
+    }
+
+    /// Returns the number of bytes the arena can hold before it grows
+    pub fn capacity(&self) -> usize {
+        self.inner.lock().unwrap().capacity()
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        self.inner.lock().unwrap().alloc(layout)
+    }
+
+    /// Move `value` into the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: Copy>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+        // SAFETY: the memory is valid for a `T`, unused and lives as long as `self`
+        unsafe {
+            ptr.as_ptr().write(value);
+            &mut *ptr.as_ptr()
+        }
+    }
+
+    /// Copy a slice into the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        let layout = Layout::for_value(values);
+        let ptr = self.alloc_layout(layout).cast::<T>();
+        // SAFETY: the memory is valid for `values.len()` values of `T`, unused and lives as long as `self`
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), ptr.as_ptr(), values.len());
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), values.len())
+        }
+    }
+
+    /// Create a new empty vector growing inside the arena
+    pub fn vec<T>(&self) -> ArenaVec<'_, T> {
+        ArenaVec {
+            arena: self,
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: if mem::size_of::<T>() == 0 { usize::MAX } else { 0 },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a new empty vector with room for `capacity` values inside the arena
+    pub fn vec_with_capacity<T>(&self, capacity: usize) -> ArenaVec<'_, T> {
+        let mut vec = self.vec();
+        vec.reserve(capacity);
+        vec
+    }
+
+    /// Give back all memory allocated from the arena
+    ///
+    /// If the arena grew during the last frame, its chunks are replaced by a single one large
+    /// enough for all of them.
+    ///
+    /// If other handles of the arena are still alive, their allocations may still be in use.
+    /// In that case this handle is detached from them and gets a new arena of the same capacity.
+    pub fn reset(&mut self) {
+        let Some(inner) = Arc::get_mut(&mut self.inner) else {
+            trace!("Frame arena still in use, detaching");
+            *self = FrameArena::with_capacity(self.capacity());
+            return;
+        };
+        let chunks = inner.get_mut().unwrap();
+        if chunks.chunks.len() > 1 {
+            let capacity = chunks.capacity();
+            chunks.chunks.clear();
+            chunks.chunks.push(Chunk::new(capacity));
+        }
+        chunks.used = 0;
+    }
+}
+
+/// Vector allocated inside a [`FrameArena`]
+///
+/// Growing the vector leaves its old memory unused until the arena is reset, so prefer
+/// [`FrameArena::vec_with_capacity`] if the length is known upfront. Unlike values allocated
+/// with [`FrameArena::alloc`], the elements are dropped with the vector.
+pub struct ArenaVec<'arena, T> {
+    arena: &'arena FrameArena,
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaVec<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> ArenaVec<'_, T> {
+    /// Returns the number of values the vector can hold without growing
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Reserve room for at least `additional` more values
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("Capacity overflow");
+        if required <= self.capacity {
+            return;
+        }
+
+        let capacity = required.max(self.capacity * 2).max(4);
+        let layout = Layout::array::<T>(capacity).expect("Capacity overflow");
+        let ptr = self.arena.alloc_layout(layout).cast::<T>();
+        // SAFETY: the new memory is valid for `capacity` values and does not overlap the old one
+        unsafe { ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+        self.ptr = ptr;
+        self.capacity = capacity;
+    }
+
+    /// Append a value
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.reserve(1);
+        }
+        // SAFETY: `len` is less than the capacity
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    /// Remove the last value
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the value at `len` was initialized and is no longer part of the vector
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    /// Remove all values
+    pub fn clear(&mut self) {
+        let values: *mut [T] = &mut **self;
+        self.len = 0;
+        // SAFETY: the values were initialized and are no longer part of the vector
+        unsafe { ptr::drop_in_place(values) };
+    }
+}
+
+impl<T> Deref for ArenaVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first `len` values are initialized
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for ArenaVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: the first `len` values are initialized
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Extend<T> for ArenaVec<'_, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> Drop for ArenaVec<'_, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Copy> ArenaVec<'_, T> {
+    /// Turn the vector into a slice, which keeps the arena alive instead of borrowing it
+    ///
+    /// Resetting the arena while the slice is alive detaches the arena from the slice's memory,
+    /// see [`FrameArena::reset`].
+    pub fn into_slice(self) -> ArenaSlice<T> {
+        let slice = ArenaSlice {
+            arena: self.arena.clone(),
+            ptr: self.ptr,
+            len: self.len,
+        };
+        // `T` is `Copy`, so there is nothing to drop
+        mem::forget(self);
+        slice
+    }
+}
+
+/// Slice allocated inside a [`FrameArena`], created by [`ArenaVec::into_slice`]
+///
+/// Unlike an [`ArenaVec`] the slice does not borrow the arena, so it can be returned from a
+/// function owning the arena handle or be sent to another thread.
+pub struct ArenaSlice<T> {
+    arena: FrameArena,
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+// SAFETY: the slice owns its values and the memory is kept alive by the arena handle
+unsafe impl<T: Send> Send for ArenaSlice<T> {}
+// SAFETY: the slice only hands out shared references to its values
+unsafe impl<T: Sync> Sync for ArenaSlice<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> ArenaSlice<T> {
+    /// Returns the arena the slice was allocated in
+    pub fn arena(&self) -> &FrameArena {
+        &self.arena
+    }
+}
+
+impl<T> Deref for ArenaSlice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first `len` values are initialized and the arena is kept alive
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::FrameArena;
+
+    #[test]
+    fn reset_consolidates_chunks() {
+        let mut arena = FrameArena::with_capacity(64);
+        let byte = arena.alloc(1u8);
+        let values = arena.alloc_slice_copy(&[1u64; 32]);
+        assert_eq!(*byte, 1);
+        assert_eq!(values.as_ptr() as usize % std::mem::align_of::<u64>(), 0);
+        let capacity = arena.capacity();
+        assert!(capacity > 64);
+
+        arena.reset();
+        assert_eq!(arena.capacity(), capacity);
+        arena.alloc_slice_copy(&[1u64; 32]);
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[test]
+    fn slice_survives_reset() {
+        let mut arena = FrameArena::with_capacity(64);
+        let mut vec = arena.vec();
+        vec.extend(0..8usize);
+        let slice = vec.into_slice();
+
+        // the slice still uses the memory, so the arena gets detached from it
+        arena.reset();
+        arena.alloc_slice_copy(&[usize::MAX; 8]);
+        assert_eq!(&*slice, &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        drop(slice);
+        arena.reset();
+        assert!(arena.capacity() >= 64);
+    }
+
+    #[test]
+    fn vec_drops_values() {
+        struct Counter(Rc<Cell<usize>>);
+        impl Drop for Counter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let dropped = Rc::new(Cell::new(0));
+        let arena = FrameArena::new();
+        let mut vec = arena.vec();
+        vec.extend((0..100).map(|_| Counter(dropped.clone())));
+        assert_eq!(vec.len(), 100);
+        drop(vec.pop());
+        assert_eq!(dropped.get(), 1);
+        drop(vec);
+        assert_eq!(dropped.get(), 100);
+    }
+}
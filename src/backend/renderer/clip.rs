@@ -0,0 +1,429 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::utils::{Physical, Point, Rectangle, Size};
+
+/// Radii of the four corners of a rounded rectangle
+///
+/// Values are in physical pixels. A radius of `0.0` leaves the corner square.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CornerRadius {
+    /// Radius of the top-left corner
+    pub top_left: f32,
+    /// Radius of the top-right corner
+    pub top_right: f32,
+    /// Radius of the bottom-right corner
+    pub bottom_right: f32,
+    /// Radius of the bottom-left corner
+    pub bottom_left: f32,
+}
+
+impl CornerRadius {
+    /// Use the same radius for all corners
+    #[inline]
+    pub const fn uniform(radius: f32) -> Self {
+        CornerRadius {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    /// Returns whether all corners are square
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.top_left <= 0.0 && self.top_right <= 0.0 && self.bottom_right <= 0.0 && self.bottom_left <= 0.0
+    }
+
+    /// Scale all radii, e.g. to convert logical radii into physical ones
+    #[inline]
+    pub fn upscale(self, scale: f64) -> Self {
+        let scale = scale as f32;
+        CornerRadius {
+            top_left: self.top_left * scale,
+            top_right: self.top_right * scale,
+            bottom_right: self.bottom_right * scale,
+            bottom_left: self.bottom_left * scale,
+        }
+    }
+
+    /// Clamp the radii to fit into a rectangle of the given size
+    ///
+    /// No radius may exceed half of the smaller side of the rectangle.
+    pub fn clamped(self, size: Size<i32, Physical>) -> Self {
+        let max = (size.w.min(size.h).max(0) as f32) / 2.0;
+        CornerRadius {
+            top_left: self.top_left.clamp(0.0, max),
+            top_right: self.top_right.clamp(0.0, max),
+            bottom_right: self.bottom_right.clamp(0.0, max),
+            bottom_left: self.bottom_left.clamp(0.0, max),
+        }
+    }
+
+    /// The radii as an array in the order top-left, top-right, bottom-right, bottom-left
+    #[inline]
+    pub fn to_array(self) -> [f32; 4] {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+    }
+}
+
+impl From<f32> for CornerRadius {
+    #[inline]
+    fn from(radius: f32) -> Self {
+        CornerRadius::uniform(radius)
+    }
+}
+
+/// A rectangle with rounded corners
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RoundedRect {
+    /// Geometry of the rectangle
+    pub geometry: Rectangle<i32, Physical>,
+    /// Radii of the corners, clamped to the geometry
+    pub radius: CornerRadius,
+}
+
+impl RoundedRect {
+    /// Create a new rounded rectangle, clamping the radii to the geometry
+    pub fn new(geometry: Rectangle<i32, Physical>, radius: impl Into<CornerRadius>) -> Self {
+        RoundedRect {
+            geometry,
+            radius: radius.into().clamped(geometry.size),
+        }
+    }
+
+    /// Horizontal insets of the left and right edge for the pixel row `y`, relative to the geometry
+    fn row_insets(&self, y: i32) -> (i32, i32) {
+        let h = self.geometry.size.h;
+        let center = y as f32 + 0.5;
+        let inset = |radius: f32, dy: f32| -> i32 {
+            if radius <= 0.0 || dy >= radius {
+                return 0;
+            }
+            let dy = radius - dy;
+            (radius - (radius * radius - dy * dy).max(0.0).sqrt()).round() as i32
+        };
+        let top = center;
+        let bottom = h as f32 - center;
+        let left = inset(self.radius.top_left, top).max(inset(self.radius.bottom_left, bottom));
+        let right = inset(self.radius.top_right, top).max(inset(self.radius.bottom_right, bottom));
+        (left, right)
+    }
+
+    /// Decompose the rounded rectangle into rectangles covering it
+    ///
+    /// The corners are approximated with rows of one pixel in height, adjacent rows
+    /// with identical extent are merged. This is used by renderers without shader support
+    /// to clip their damage analytically.
+    pub fn to_rects(&self) -> Vec<Rectangle<i32, Physical>> {
+        let size = self.geometry.size;
+        if size.w <= 0 || size.h <= 0 {
+            return Vec::new();
+        }
+
+        let mut rects: Vec<Rectangle<i32, Physical>> = Vec::new();
+        let mut current: Option<(i32, (i32, i32))> = None;
+        for y in 0..=size.h {
+            let insets = (y < size.h).then(|| self.row_insets(y));
+            if let Some((start, prev)) = current {
+                if Some(prev) == insets {
+                    continue;
+                }
+                let (left, right) = prev;
+                if left + right < size.w {
+                    rects.push(Rectangle::new(
+                        self.geometry.loc + Point::from((left, start)),
+                        (size.w - left - right, y - start).into(),
+                    ));
+                }
+            }
+            current = insets.map(|insets| (y, insets));
+        }
+        rects
+    }
+
+    /// Parts of the rectangle unaffected by the rounded corners
+    pub fn inner_rects(&self) -> Vec<Rectangle<i32, Physical>> {
+        let geo = self.geometry;
+        let corner = |radius: f32| radius.ceil() as i32;
+        let corners = [
+            Rectangle::new(
+                geo.loc,
+                (corner(self.radius.top_left), corner(self.radius.top_left)).into(),
+            ),
+            Rectangle::new(
+                geo.loc + Point::from((geo.size.w - corner(self.radius.top_right), 0)),
+                (corner(self.radius.top_right), corner(self.radius.top_right)).into(),
+            ),
+            Rectangle::new(
+                geo.loc
+                    + Point::from((
+                        geo.size.w - corner(self.radius.bottom_right),
+                        geo.size.h - corner(self.radius.bottom_right),
+                    )),
+                (corner(self.radius.bottom_right), corner(self.radius.bottom_right)).into(),
+            ),
+            Rectangle::new(
+                geo.loc + Point::from((0, geo.size.h - corner(self.radius.bottom_left))),
+                (corner(self.radius.bottom_left), corner(self.radius.bottom_left)).into(),
+            ),
+        ];
+        geo.subtract_rects(corners.into_iter().filter(|rect| !rect.is_empty()))
+    }
+}
+
+/// Region a render element is clipped to while drawing
+///
+/// A clip region consists of an optional set of rectangles and an optional rectangle with
+/// rounded corners. Only the parts of an element inside of both are drawn.
+/// All coordinates are in the physical coordinate space of the frame, the same space
+/// the destination rectangle of an element is given in.
+///
+/// Renderers supporting shaders clip the rounded corners with anti-aliasing on the GPU,
+/// other renderers approximate them by clipping the damage, see [`RoundedRect::to_rects`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClipRegion {
+    rects: Option<Vec<Rectangle<i32, Physical>>>,
+    rounded: Option<RoundedRect>,
+}
+
+impl ClipRegion {
+    /// A region not clipping anything
+    #[inline]
+    pub fn unclipped() -> Self {
+        Self::default()
+    }
+
+    /// Clip to a set of rectangles
+    pub fn from_rects(rects: impl IntoIterator<Item = Rectangle<i32, Physical>>) -> Self {
+        ClipRegion {
+            rects: Some(rects.into_iter().collect()),
+            rounded: None,
+        }
+    }
+
+    /// Clip to a rectangle with rounded corners
+    pub fn rounded(geometry: Rectangle<i32, Physical>, radius: impl Into<CornerRadius>) -> Self {
+        ClipRegion {
+            rects: None,
+            rounded: Some(RoundedRect::new(geometry, radius)),
+        }
+    }
+
+    /// Additionally clip to a rectangle with rounded corners
+    pub fn with_rounded_corners(
+        mut self,
+        geometry: Rectangle<i32, Physical>,
+        radius: impl Into<CornerRadius>,
+    ) -> Self {
+        self.rounded = Some(RoundedRect::new(geometry, radius));
+        self
+    }
+
+    /// The rectangles of this region, `None` if not clipped by rectangles
+    #[inline]
+    pub fn rects(&self) -> Option<&[Rectangle<i32, Physical>]> {
+        self.rects.as_deref()
+    }
+
+    /// The rounded rectangle of this region, if any
+    #[inline]
+    pub fn rounded_rect(&self) -> Option<&RoundedRect> {
+        self.rounded.as_ref()
+    }
+
+    /// Returns whether this region clips anything
+    #[inline]
+    pub fn is_unclipped(&self) -> bool {
+        self.rects.is_none() && self.rounded.is_none()
+    }
+
+    /// Move the region by the given offset
+    pub fn translate(&self, offset: impl Into<Point<i32, Physical>>) -> ClipRegion {
+        let offset = offset.into();
+        ClipRegion {
+            rects: self.rects.as_ref().map(|rects| {
+                rects
+                    .iter()
+                    .map(|rect| Rectangle::new(rect.loc + offset, rect.size))
+                    .collect()
+            }),
+            rounded: self.rounded.map(|rounded| RoundedRect {
+                geometry: Rectangle::new(rounded.geometry.loc + offset, rounded.geometry.size),
+                radius: rounded.radius,
+            }),
+        }
+    }
+
+    /// Hash of the region, `0` if unclipped
+    ///
+    /// Clipped elements fold it into their commit, so changing the clip damages them.
+    pub(crate) fn fingerprint(&self) -> usize {
+        if self.is_unclipped() {
+            return 0;
+        }
+
+        let hash_rect = |hasher: &mut DefaultHasher, rect: &Rectangle<i32, Physical>| {
+            (rect.loc.x, rect.loc.y, rect.size.w, rect.size.h).hash(hasher);
+        };
+        let mut hasher = DefaultHasher::new();
+        if let Some(rects) = self.rects.as_ref() {
+            rects.len().hash(&mut hasher);
+            for rect in rects {
+                hash_rect(&mut hasher, rect);
+            }
+        }
+        if let Some(rounded) = self.rounded.as_ref() {
+            hash_rect(&mut hasher, &rounded.geometry);
+            for radius in rounded.radius.to_array() {
+                radius.to_bits().hash(&mut hasher);
+            }
+        }
+        hasher.finish() as usize
+    }
+
+    /// Clip damage relative to `dst` to the rectangles of this region and the bounds of its
+    /// rounded rectangle, ignoring the rounded corners
+    ///
+    /// The returned damage is relative to `dst` again.
+    pub fn clip_damage(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let bounds = self.rounded.map(|rounded| vec![rounded.geometry]);
+        self.clip_relative(dst, damage, bounds.as_deref())
+    }
+
+    /// Clip damage relative to `dst` to this region including the rounded corners
+    ///
+    /// The corners are approximated as described in [`RoundedRect::to_rects`].
+    /// The returned damage is relative to `dst` again.
+    pub fn clip_damage_analytic(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let bounds = self.rounded.map(|rounded| rounded.to_rects());
+        self.clip_relative(dst, damage, bounds.as_deref())
+    }
+
+    /// Reduce opaque regions relative to `dst` to the parts guaranteed to be fully covered
+    /// after clipping
+    pub fn clip_opaque_regions(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let bounds = self.rounded.map(|rounded| rounded.inner_rects());
+        self.clip_relative(dst, opaque_regions, bounds.as_deref())
+    }
+
+    fn clip_relative(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        rects: &[Rectangle<i32, Physical>],
+        bounds: Option<&[Rectangle<i32, Physical>]>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let mut clipped = rects
+            .iter()
+            .map(|rect| Rectangle::new(rect.loc + dst.loc, rect.size))
+            .collect::<Vec<_>>();
+        for clip in [self.rects.as_deref(), bounds].into_iter().flatten() {
+            clipped = clipped
+                .iter()
+                .flat_map(|rect| clip.iter().filter_map(|clip| rect.intersection(*clip)))
+                .collect();
+        }
+        clipped
+            .into_iter()
+            .map(|rect| Rectangle::new(rect.loc - dst.loc, rect.size))
+            .collect()
+    }
+}
+
+impl From<RoundedRect> for ClipRegion {
+    #[inline]
+    fn from(rounded: RoundedRect) -> Self {
+        ClipRegion {
+            rects: None,
+            rounded: Some(rounded),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(rects: &[Rectangle<i32, Physical>]) -> i32 {
+        rects.iter().map(|rect| rect.size.w * rect.size.h).sum()
+    }
+
+    #[test]
+    fn square_corners_are_a_single_rect() {
+        let geometry = Rectangle::new((10, 20).into(), (100, 50).into());
+        let rounded = RoundedRect::new(geometry, 0.0);
+        assert_eq!(rounded.to_rects(), vec![geometry]);
+        assert_eq!(rounded.inner_rects(), vec![geometry]);
+    }
+
+    #[test]
+    fn rounded_rects_cover_the_shape() {
+        let geometry = Rectangle::new((0, 0).into(), (100, 100).into());
+        let rounded = RoundedRect::new(geometry, 10.0);
+        let rects = rounded.to_rects();
+
+        // rows are merged, the inner part is a single rect
+        assert!(rects.iter().any(|rect| rect.size == Size::from((100, 80))));
+        assert!(rects.iter().all(|rect| geometry.contains_rect(*rect)));
+        assert!(!rects.iter().any(|rect| rect.contains((0, 0))));
+        assert!(!rects.iter().any(|rect| rect.contains((99, 99))));
+
+        let circle = std::f32::consts::PI * 10.0 * 10.0;
+        let expected = 100.0 * 100.0 - (400.0 - circle);
+        assert!((area(&rects) as f32 - expected).abs() < 40.0);
+
+        // the inner rects never include the corners
+        let inner = rounded.inner_rects();
+        assert_eq!(area(&inner), 100 * 100 - 4 * 10 * 10);
+    }
+
+    #[test]
+    fn radius_is_clamped() {
+        let geometry = Rectangle::new((0, 0).into(), (20, 10).into());
+        let rounded = RoundedRect::new(geometry, 50.0);
+        assert_eq!(rounded.radius, CornerRadius::uniform(5.0));
+    }
+
+    #[test]
+    fn damage_is_relative_to_dst() {
+        let dst = Rectangle::new((50, 50).into(), (100, 100).into());
+        let clip = ClipRegion::from_rects([Rectangle::new((100, 100).into(), (10, 10).into())]);
+        let damage = clip.clip_damage(dst, &[Rectangle::from_size(dst.size)]);
+        assert_eq!(damage, vec![Rectangle::new((50, 50).into(), (10, 10).into())]);
+
+        let clip = clip.with_rounded_corners(Rectangle::new((0, 0).into(), (105, 105).into()), 0.0);
+        let damage = clip.clip_damage_analytic(dst, &[Rectangle::from_size(dst.size)]);
+        assert_eq!(damage, vec![Rectangle::new((50, 50).into(), (5, 5).into())]);
+    }
+
+    #[test]
+    fn translate_keeps_the_fingerprint_apart() {
+        let clip = ClipRegion::rounded(Rectangle::from_size((100, 100).into()), 10.0);
+        let moved = clip.translate((20, 30));
+        assert_eq!(
+            moved.rounded_rect().unwrap().geometry,
+            Rectangle::new((20, 30).into(), (100, 100).into())
+        );
+        assert_eq!(moved.translate((-20, -30)), clip);
+
+        assert_eq!(ClipRegion::unclipped().fingerprint(), 0);
+        assert_eq!(clip.fingerprint(), clip.clone().fingerprint());
+        assert_ne!(clip.fingerprint(), moved.fingerprint());
+        assert_ne!(
+            clip.fingerprint(),
+            ClipRegion::rounded(Rectangle::from_size((100, 100).into()), 12.0).fingerprint()
+        );
+    }
+}
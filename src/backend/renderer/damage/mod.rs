@@ -23,6 +23,12 @@
 //! See the [`renderer::element`](crate::backend::renderer::element) module for more information
 //! about how to use [`RenderElement`].
 //!
+//! Computing the damage of an output does not need the renderer. When rendering several outputs,
+//! [`prepare_output`](OutputDamageTracker::prepare_output) and
+//! [`render_prepared`](OutputDamageTracker::render_prepared) allow to compute the damage of all
+//! outputs first and to only serialize the rendering itself. With the `renderer_parallel` feature
+//! `prepare_outputs` does so on a [rayon](https://docs.rs/rayon) thread pool.
+//!
 //! # How to use it
 //!
 //! ```no_run
@@ -105,20 +111,24 @@ use tracing::{info_span, instrument, trace};
 use crate::{
     backend::renderer::{element::RenderElementPresentationState, Frame},
     output::{Output, OutputModeSource, OutputNoMode},
-    utils::{Buffer as BufferCoords, Physical, Rectangle, Scale, Size, Transform},
+    utils::{Buffer as BufferCoords, Physical, Rectangle, Region, Scale, Size, Transform},
 };
 
 use super::{
     element::{Element, Id, RenderElement, RenderElementState, RenderElementStates},
     sync::SyncPoint,
     utils::CommitCounter,
-    Color32F,
+    ArenaSlice, ArenaVec, Color32F, FrameArena,
 };
 
 use super::{Renderer, Texture};
 
+#[cfg(feature = "renderer_parallel")]
+mod parallel;
 mod shaper;
 
+#[cfg(feature = "renderer_parallel")]
+pub use parallel::{prepare_outputs, OutputDamageJob};
 use shaper::DamageShaper;
 
 const MAX_AGE: usize = 4;
@@ -193,7 +203,9 @@ pub struct OutputDamageTracker {
     opaque_regions: Vec<Rectangle<i32, Physical>>,
     opaque_regions_index: Vec<Range<usize>>,
     element_opaque_regions: Vec<Rectangle<i32, Physical>>,
-    element_visible_area_workhouse: Vec<Rectangle<i32, Physical>>,
+    opaque_region: Region<Physical>,
+    damage_pool: Vec<Vec<Rectangle<i32, Physical>>>,
+    arena: FrameArena,
     span: tracing::Span,
 }
 
@@ -229,6 +241,28 @@ impl RenderOutputResult<'_> {
     }
 }
 
+/// Damage of an output computed by [`OutputDamageTracker::prepare_output`]
+///
+/// Pass it to [`OutputDamageTracker::render_prepared`] to render the output.
+#[derive(Debug)]
+pub struct PreparedOutput {
+    states: RenderElementStates,
+    /// Indices of the elements to render, front-to-back
+    render_elements: ArenaSlice<usize>,
+    elements_len: usize,
+    output_size: Size<i32, Physical>,
+    output_scale: Scale<f64>,
+    output_transform: Transform,
+    clear_color: Color32F,
+}
+
+impl PreparedOutput {
+    /// Returns the render element states
+    pub fn states(&self) -> &RenderElementStates {
+        &self.states
+    }
+}
+
 impl<E: std::error::Error> std::fmt::Debug for Error<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -258,7 +292,9 @@ impl OutputDamageTracker {
             opaque_regions: Default::default(),
             opaque_regions_index: Default::default(),
             element_opaque_regions: Default::default(),
-            element_visible_area_workhouse: Default::default(),
+            opaque_region: Default::default(),
+            damage_pool: Default::default(),
+            arena: Default::default(),
             span: info_span!("renderer_damage"),
         }
     }
@@ -277,7 +313,9 @@ impl OutputDamageTracker {
             opaque_regions: Default::default(),
             opaque_regions_index: Default::default(),
             element_opaque_regions: Default::default(),
-            element_visible_area_workhouse: Default::default(),
+            opaque_region: Default::default(),
+            damage_pool: Default::default(),
+            arena: Default::default(),
             last_state: Default::default(),
             span: info_span!("renderer_damage", output = output.name()),
         }
@@ -298,7 +336,9 @@ impl OutputDamageTracker {
             element_opaque_regions: Default::default(),
             opaque_regions: Default::default(),
             opaque_regions_index: Default::default(),
-            element_visible_area_workhouse: Default::default(),
+            opaque_region: Default::default(),
+            damage_pool: Default::default(),
+            arena: Default::default(),
             last_state: Default::default(),
         }
     }
@@ -325,6 +365,29 @@ impl OutputDamageTracker {
         E: RenderElement<R>,
         R: Renderer,
         R::TextureId: Texture,
+    {
+        let prepared = self.prepare_output(age, elements, clear_color)?;
+        self.render_prepared(renderer, framebuffer, elements, prepared)
+    }
+
+    /// Compute the damage of this output without rendering it yet
+    ///
+    /// This is the first half of [`render_output`](OutputDamageTracker::render_output), which
+    /// does not need the [`Renderer`]. The damage of several outputs can thus be computed in
+    /// parallel, while rendering them with [`render_prepared`](OutputDamageTracker::render_prepared)
+    /// stays serialized. See `damage::prepare_outputs` with the `renderer_parallel` feature.
+    ///
+    /// - `elements` for this output in front-to-back order
+    #[instrument(level = "trace", parent = &self.span, skip(elements, clear_color))]
+    #[profiling::function]
+    pub fn prepare_output<E>(
+        &mut self,
+        age: usize,
+        elements: &[E],
+        clear_color: impl Into<Color32F>,
+    ) -> Result<PreparedOutput, OutputNoMode>
+    where
+        E: Element,
     {
         let clear_color = clear_color.into();
         let (output_size, output_scale, output_transform) =
@@ -332,15 +395,17 @@ impl OutputDamageTracker {
 
         // Output transform is specified in surface-rotation, so inversion gives us the
         // render transform for the output itself.
-        let output_transform = output_transform.invert();
+        let output_transform = crate::output::render_transform(output_transform);
 
         // We have to apply to output transform to the output size so that the intersection
         // tests in damage_output_internal produces the correct results and do not crop
         // damage with the wrong size
         let output_geo = Rectangle::from_size(output_transform.transform_size(output_size));
 
-        // This will hold all the damage we need for this rendering step
-        let mut render_elements: Vec<&E> = Vec::with_capacity(elements.len());
+        // This will hold all the elements we need for this rendering step
+        self.arena.reset();
+        let arena = self.arena.clone();
+        let mut render_elements = arena.vec_with_capacity(elements.len());
         let states = self.damage_output_internal(
             age,
             elements,
@@ -351,6 +416,50 @@ impl OutputDamageTracker {
             &mut render_elements,
         );
 
+        Ok(PreparedOutput {
+            states,
+            render_elements: render_elements.into_slice(),
+            elements_len: elements.len(),
+            output_size,
+            output_scale,
+            output_transform,
+            clear_color,
+        })
+    }
+
+    /// Render an output prepared by [`prepare_output`](OutputDamageTracker::prepare_output)
+    ///
+    /// `elements` have to be the same elements the output was prepared with and no other output
+    /// may have been prepared with this damage tracker in between.
+    #[instrument(level = "trace", parent = &self.span, skip(renderer, framebuffer, elements, prepared))]
+    #[profiling::function]
+    pub fn render_prepared<E, R>(
+        &mut self,
+        renderer: &mut R,
+        framebuffer: &mut R::Framebuffer<'_>,
+        elements: &[E],
+        prepared: PreparedOutput,
+    ) -> Result<RenderOutputResult<'_>, Error<R::Error>>
+    where
+        E: RenderElement<R>,
+        R: Renderer,
+        R::TextureId: Texture,
+    {
+        let PreparedOutput {
+            states,
+            render_elements,
+            elements_len,
+            output_size,
+            output_scale,
+            output_transform,
+            clear_color,
+        } = prepared;
+        assert_eq!(
+            elements.len(),
+            elements_len,
+            "rendering other elements than the output was prepared with"
+        );
+
         if self.damage.is_empty() {
             trace!("no damage, skipping rendering");
             return Ok(RenderOutputResult::skipped(states));
@@ -369,31 +478,41 @@ impl OutputDamageTracker {
             let mut frame = renderer.render(framebuffer, output_size, output_transform)?;
 
             element_damage.clear();
-            element_damage.extend_from_slice(&self.damage);
-            element_damage =
-                Rectangle::subtract_rects_many_in_place(element_damage, self.opaque_regions.iter().copied());
+            element_damage.extend(
+                Region::from_iter(self.damage.iter().copied())
+                    .subtract(&self.opaque_region)
+                    .rects(),
+            );
 
             trace!("clearing damage {:?}", element_damage);
             frame.clear(clear_color, &element_damage)?;
 
-            for (z_index, element) in render_elements.iter().rev().enumerate() {
+            for (z_index, element) in render_elements
+                .iter()
+                .rev()
+                .map(|&index| &elements[index])
+                .enumerate()
+            {
                 let element_id = element.id();
                 let element_geometry = element.geometry(output_scale);
 
+                let element_opaque_regions_range =
+                    self.opaque_regions_index.iter().rev().nth(z_index).unwrap();
+                // only the opaque regions of elements above this one can hide its damage
+                let opaque_above = self.opaque_regions[..element_opaque_regions_range.start]
+                    .iter()
+                    .copied()
+                    .collect::<Region<Physical>>();
+
                 element_damage.clear();
                 element_damage.extend(
                     self.damage
                         .iter()
-                        .filter_map(|d| d.intersection(element_geometry)),
-                );
-
-                let element_opaque_regions_range =
-                    self.opaque_regions_index.iter().rev().nth(z_index).unwrap();
-                element_damage = Rectangle::subtract_rects_many_in_place(
-                    element_damage,
-                    self.opaque_regions[..element_opaque_regions_range.start]
-                        .iter()
-                        .copied(),
+                        .copied()
+                        .collect::<Region<Physical>>()
+                        .intersect(&Region::from_rect(element_geometry))
+                        .subtract(&opaque_above)
+                        .rects(),
                 );
                 element_damage.iter_mut().for_each(|d| {
                     d.loc -= element_geometry.loc;
@@ -473,14 +592,16 @@ impl OutputDamageTracker {
 
         // Output transform is specified in surface-rotation, so inversion gives us the
         // render transform for the output itself.
-        let output_transform = output_transform.invert();
+        let output_transform = crate::output::render_transform(output_transform);
 
         // We have to apply to output transform to the output size so that the intersection
         // tests in damage_output_internal produces the correct results and do not crop
         // damage with the wrong size
         let output_geo = Rectangle::from_size(output_transform.transform_size(output_size));
 
-        let mut render_elements: Vec<&E> = Vec::with_capacity(elements.len());
+        self.arena.reset();
+        let arena = self.arena.clone();
+        let mut render_elements = arena.vec_with_capacity(elements.len());
         let states = self.damage_output_internal(
             age,
             elements,
@@ -500,15 +621,15 @@ impl OutputDamageTracker {
 
     #[allow(clippy::too_many_arguments)]
     #[profiling::function]
-    fn damage_output_internal<'a, E>(
+    fn damage_output_internal<E>(
         &mut self,
         age: usize,
-        elements: &'a [E],
+        elements: &[E],
         output_scale: Scale<f64>,
         output_transform: Transform,
         output_geo: Rectangle<i32, Physical>,
         clear_color: Option<Color32F>,
-        render_elements: &mut Vec<&'a E>,
+        render_elements: &mut ArenaVec<'_, usize>,
     ) -> RenderElementStates
     where
         E: Element,
@@ -516,6 +637,7 @@ impl OutputDamageTracker {
         self.damage.clear();
         self.opaque_regions.clear();
         self.opaque_regions_index.clear();
+        self.opaque_region = Region::new();
 
         let mut element_render_states = RenderElementStates {
             states: HashMap::with_capacity(elements.len()),
@@ -524,8 +646,7 @@ impl OutputDamageTracker {
         // we have to take the element damage to be able to move it around
         let mut element_damage = std::mem::take(&mut self.element_damage);
 
-        let mut element_visible_area_workhouse = std::mem::take(&mut self.element_visible_area_workhouse);
-        for element in elements.iter() {
+        for (index, element) in elements.iter().enumerate() {
             let element_id = element.id();
             let element_loc = element.geometry(output_scale).loc;
 
@@ -537,14 +658,9 @@ impl OutputDamageTracker {
             };
 
             // Then test if the element is completely hidden behind opaque regions
-            element_visible_area_workhouse.clear();
-            element_visible_area_workhouse.push(element_output_geometry);
-            element_visible_area_workhouse = Rectangle::subtract_rects_many_in_place(
-                element_visible_area_workhouse,
-                self.opaque_regions.iter().copied(),
-            );
-            let element_visible_area = element_visible_area_workhouse
-                .iter()
+            let element_visible_area = Region::from_rect(element_output_geometry)
+                .subtract(&self.opaque_region)
+                .rects()
                 .fold(0usize, |acc, item| acc + (item.size.w * item.size.h) as usize);
 
             // No need to draw a completely hidden element
@@ -583,9 +699,14 @@ impl OutputDamageTracker {
                 .filter_map(|geo| geo.intersection(output_geo));
             self.opaque_regions.extend(element_opaque_regions);
             let element_opaque_regions_end_index = self.opaque_regions.len();
+            self.opaque_region.extend(
+                self.opaque_regions[element_opaque_regions_start_index..element_opaque_regions_end_index]
+                    .iter()
+                    .copied(),
+            );
             self.opaque_regions_index
                 .push(element_opaque_regions_start_index..element_opaque_regions_end_index);
-            render_elements.push(element);
+            render_elements.push(index);
 
             if let Some(state) = element_render_states.states.get_mut(element_id) {
                 if matches!(state.presentation_state, RenderElementPresentationState::Skipped) {
@@ -600,11 +721,6 @@ impl OutputDamageTracker {
                 );
             }
         }
-        std::mem::swap(
-            &mut self.element_visible_area_workhouse,
-            &mut element_visible_area_workhouse,
-        );
-
         // add the damage for elements gone that are not covered an opaque region
         let elements_gone = self.last_state.elements.iter().filter(|(id, _)| {
             element_render_states
@@ -624,7 +740,7 @@ impl OutputDamageTracker {
         }
 
         // if the element has been moved or it's alpha or z index changed, damage it
-        for (z_index, element) in render_elements.iter().enumerate() {
+        for (z_index, element) in render_elements.iter().map(|&index| &elements[index]).enumerate() {
             let element_src = element.src();
             let element_geometry = element.geometry(output_scale);
             let element_transform = element.transform();
@@ -659,9 +775,11 @@ impl OutputDamageTracker {
 
         // damage regions no longer covered by opaque regions
         element_damage.clear();
-        element_damage.extend_from_slice(&self.last_state.opaque_regions);
-        element_damage =
-            Rectangle::subtract_rects_many_in_place(element_damage, self.opaque_regions.iter().copied());
+        element_damage.extend(
+            Region::from_iter(self.last_state.opaque_regions.iter().copied())
+                .subtract(&self.opaque_region)
+                .rects(),
+        );
         self.damage.extend_from_slice(&element_damage);
 
         // we no longer need the element damage, return it so that we can
@@ -685,15 +803,17 @@ impl OutputDamageTracker {
             self.damage.push(output_geo);
         }
 
-        // That is all completely new damage, which we need to store for subsequent renders
-        let mut new_damage = self.damage.clone();
-        new_damage.shrink_to_fit();
+        // That is all completely new damage, which we need to store for subsequent renders,
+        // re-using the allocation of damage no longer needed if possible
+        let mut new_damage = self.damage_pool.pop().unwrap_or_default();
+        new_damage.clear();
+        new_damage.extend_from_slice(&self.damage);
 
         // We now add old damage states, if we have an age value
         if age > 0 && self.last_state.old_damage.len() >= age {
             trace!("age of {} recent enough, using old damage", age);
             // We do not need even older states anymore
-            self.last_state.old_damage.truncate(age);
+            self.truncate_old_damage(age);
             self.damage
                 .extend(self.last_state.old_damage.iter().take(age - 1).flatten().copied());
         } else {
@@ -705,7 +825,7 @@ impl OutputDamageTracker {
             // we still truncate the old damage to prevent growing
             // indefinitely in case we are continuously called with
             // an age of 0
-            self.last_state.old_damage.truncate(MAX_AGE);
+            self.truncate_old_damage(MAX_AGE);
             // just damage everything, if we have no damage
             self.damage.clear();
             self.damage.push(output_geo);
@@ -727,50 +847,51 @@ impl OutputDamageTracker {
 
         if self.damage.is_empty() {
             trace!("nothing damaged, exiting early");
+            self.damage_pool.push(new_damage);
             return element_render_states;
         }
 
         let mut new_elements_state = std::mem::take(&mut self.last_state.elements);
         new_elements_state.clear();
         new_elements_state.reserve(render_elements.len());
-        let new_elements_state =
-            render_elements
-                .iter()
-                .enumerate()
-                .fold(new_elements_state, |mut map, (z_index, elem)| {
-                    let id = elem.id();
-                    let elem_src = elem.src();
-                    let elem_alpha = elem.alpha();
-                    let elem_geometry = elem.geometry(output_scale);
-                    let elem_transform = elem.transform();
-
-                    if let Some(state) = map.get_mut(id) {
-                        state.last_instances.push(ElementInstanceState {
-                            last_src: elem_src,
-                            last_geometry: elem_geometry,
-                            last_transform: elem_transform,
-                            last_alpha: elem_alpha,
-                            last_z_index: z_index,
-                        });
-                    } else {
-                        let current_commit = elem.current_commit();
-                        map.insert(
-                            id.clone(),
-                            ElementState {
-                                last_commit: current_commit,
-                                last_instances: smallvec![ElementInstanceState {
-                                    last_src: elem_src,
-                                    last_geometry: elem_geometry,
-                                    last_transform: elem_transform,
-                                    last_alpha: elem_alpha,
-                                    last_z_index: z_index,
-                                }],
-                            },
-                        );
-                    }
-
-                    map
-                });
+        let new_elements_state = render_elements
+            .iter()
+            .map(|&index| &elements[index])
+            .enumerate()
+            .fold(new_elements_state, |mut map, (z_index, elem)| {
+                let id = elem.id();
+                let elem_src = elem.src();
+                let elem_alpha = elem.alpha();
+                let elem_geometry = elem.geometry(output_scale);
+                let elem_transform = elem.transform();
+
+                if let Some(state) = map.get_mut(id) {
+                    state.last_instances.push(ElementInstanceState {
+                        last_src: elem_src,
+                        last_geometry: elem_geometry,
+                        last_transform: elem_transform,
+                        last_alpha: elem_alpha,
+                        last_z_index: z_index,
+                    });
+                } else {
+                    let current_commit = elem.current_commit();
+                    map.insert(
+                        id.clone(),
+                        ElementState {
+                            last_commit: current_commit,
+                            last_instances: smallvec![ElementInstanceState {
+                                last_src: elem_src,
+                                last_geometry: elem_geometry,
+                                last_transform: elem_transform,
+                                last_alpha: elem_alpha,
+                                last_z_index: z_index,
+                            }],
+                        },
+                    );
+                }
+
+                map
+            });
 
         self.last_state.size = Some(output_geo.size);
         self.last_state.transform = Some(output_transform);
@@ -785,4 +906,142 @@ impl OutputDamageTracker {
 
         element_render_states
     }
+
+    fn truncate_old_damage(&mut self, len: usize) {
+        while self.last_state.old_damage.len() > len {
+            let damage = self.last_state.old_damage.pop_back().unwrap();
+            self.damage_pool.push(damage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::OutputDamageTracker;
+    use crate::{
+        backend::renderer::{
+            element::{Element, Id, RenderElement},
+            test::{DummyFramebuffer, DummyRenderer},
+            utils::CommitCounter,
+            Color32F, Frame, Renderer,
+        },
+        output::{Mode, Output, PhysicalProperties, Subpixel},
+        utils::{Buffer, Physical, Point, Rectangle, Scale, Transform},
+    };
+
+    // Element recording the transform and destination it was drawn with
+    #[derive(Debug)]
+    struct RecordingElement {
+        id: Id,
+        geometry: Rectangle<i32, Physical>,
+        drawn: Mutex<Option<(Transform, Rectangle<i32, Physical>)>>,
+    }
+
+    impl Element for RecordingElement {
+        fn id(&self) -> &Id {
+            &self.id
+        }
+
+        fn current_commit(&self) -> CommitCounter {
+            CommitCounter::default()
+        }
+
+        fn src(&self) -> Rectangle<f64, Buffer> {
+            Rectangle::from_size(
+                self.geometry
+                    .size
+                    .to_f64()
+                    .to_logical(1.0)
+                    .to_buffer(1.0, Transform::Normal),
+            )
+        }
+
+        fn geometry(&self, _scale: Scale<f64>) -> Rectangle<i32, Physical> {
+            self.geometry
+        }
+    }
+
+    impl RenderElement<DummyRenderer> for RecordingElement {
+        fn draw(
+            &self,
+            frame: &mut <DummyRenderer as Renderer>::Frame<'_, '_>,
+            _src: Rectangle<f64, Buffer>,
+            dst: Rectangle<i32, Physical>,
+            _damage: &[Rectangle<i32, Physical>],
+            _opaque_regions: &[Rectangle<i32, Physical>],
+        ) -> Result<(), <DummyRenderer as Renderer>::Error> {
+            *self.drawn.lock().unwrap() = Some((frame.transformation(), dst));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transformed_outputs_match_panel_mapping() {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "test".into(),
+                model: "test".into(),
+                serial_number: "test".into(),
+            },
+        );
+        let mode = Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+
+        for transform in [
+            Transform::Normal,
+            Transform::_90,
+            Transform::_180,
+            Transform::_270,
+            Transform::Flipped,
+            Transform::Flipped90,
+            Transform::Flipped180,
+            Transform::Flipped270,
+        ] {
+            output.set_transform(transform);
+            let output_size = transform.transform_size(mode.size);
+
+            let element = RecordingElement {
+                id: Id::new(),
+                geometry: Rectangle::new((100, 50).into(), (10, 20).into()),
+                drawn: Mutex::new(None),
+            };
+            let mut damage_tracker = OutputDamageTracker::from_output(&output);
+            let result = damage_tracker
+                .render_output(
+                    &mut DummyRenderer,
+                    &mut DummyFramebuffer,
+                    0,
+                    &[&element],
+                    Color32F::TRANSPARENT,
+                )
+                .unwrap();
+            // damage is reported in the transformed output space
+            assert_eq!(
+                result.damage,
+                Some(&vec![Rectangle::from_size(output_size)]),
+                "{transform:?}"
+            );
+
+            let (frame_transform, dst) = element.drawn.lock().unwrap().take().expect("element not drawn");
+            assert_eq!(dst, element.geometry, "{transform:?}");
+            assert_eq!(frame_transform, transform.invert(), "{transform:?}");
+
+            // the renderer places the element where the panel mapping expects it
+            let on_panel = frame_transform.transform_rect_in(dst, &output_size);
+            let center = Point::<f64, Physical>::from((105.0, 60.0)).to_logical(1.0);
+            assert_eq!(
+                output.global_to_panel(center),
+                Some(on_panel.to_f64().loc + on_panel.to_f64().size.downscale(2.0).to_point()),
+                "{transform:?}"
+            );
+        }
+    }
 }
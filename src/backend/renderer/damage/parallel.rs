@@ -0,0 +1,150 @@
+use rayon::{prelude::*, ThreadPool};
+
+use crate::{
+    backend::renderer::{element::Element, Color32F},
+    output::OutputNoMode,
+};
+
+use super::{OutputDamageTracker, PreparedOutput};
+
+/// Output to prepare with [`prepare_outputs`]
+#[derive(Debug)]
+pub struct OutputDamageJob<'a, E> {
+    /// Damage tracker of the output
+    pub damage_tracker: &'a mut OutputDamageTracker,
+    /// Age of the buffer the output will be rendered into
+    pub age: usize,
+    /// Elements of the output in front-to-back order
+    pub elements: &'a [E],
+    /// Color to clear the output with
+    pub clear_color: Color32F,
+}
+
+/// Prepare several outputs in parallel on the given thread pool
+///
+/// `prepare` is called for every output on one of the threads of `pool`. It may build the
+/// elements of the output, e.g. by collecting them into a vector kept in `O`, and returns the
+/// damage tracker and elements to prepare the output with, see
+/// [`OutputDamageTracker::prepare_output`].
+///
+/// The returned results are in the order of `outputs`. Render the outputs afterwards by passing
+/// them to [`OutputDamageTracker::render_prepared`] one after the other, so the submission to
+/// the renderer stays serialized.
+///
+/// ```no_run
+/// # use smithay::backend::renderer::{
+/// #     damage::{prepare_outputs, OutputDamageJob, OutputDamageTracker},
+/// #     element::solid::SolidColorRenderElement,
+/// #     test::{DummyFramebuffer, DummyRenderer},
+/// # };
+/// struct OutputState {
+///     damage_tracker: OutputDamageTracker,
+///     elements: Vec<SolidColorRenderElement>,
+/// }
+///
+/// # let mut renderer = DummyRenderer::default();
+/// # let mut outputs: Vec<OutputState> = Vec::new();
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+///
+/// let prepared = prepare_outputs(&pool, &mut outputs, |output| {
+///     output.elements.clear();
+///     // collect the elements of the output
+///     OutputDamageJob {
+///         damage_tracker: &mut output.damage_tracker,
+///         age: 0,
+///         elements: &output.elements,
+///         clear_color: [0.0, 0.0, 0.0, 1.0].into(),
+///     }
+/// });
+///
+/// for (output, prepared) in outputs.iter_mut().zip(prepared) {
+///     let prepared = prepared.expect("output without mode");
+///     output
+///         .damage_tracker
+///         .render_prepared(&mut renderer, &mut DummyFramebuffer, &output.elements, prepared)
+///         .expect("failed to render the output");
+/// }
+/// ```
+pub fn prepare_outputs<O, E, F>(
+    pool: &ThreadPool,
+    outputs: &mut [O],
+    prepare: F,
+) -> Vec<Result<PreparedOutput, OutputNoMode>>
+where
+    O: Send,
+    E: Element,
+    F: Fn(&mut O) -> OutputDamageJob<'_, E> + Sync,
+{
+    pool.install(|| {
+        outputs
+            .par_iter_mut()
+            .map(|output| {
+                let job = prepare(output);
+                job.damage_tracker
+                    .prepare_output(job.age, job.elements, job.clear_color)
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::ThreadPoolBuilder;
+
+    use super::{prepare_outputs, OutputDamageJob};
+    use crate::{
+        backend::renderer::{
+            damage::OutputDamageTracker,
+            element::{solid::SolidColorRenderElement, Id, Kind},
+            test::{DummyFramebuffer, DummyRenderer},
+        },
+        utils::{Rectangle, Transform},
+    };
+
+    struct OutputState {
+        damage_tracker: OutputDamageTracker,
+        elements: Vec<SolidColorRenderElement>,
+    }
+
+    #[test]
+    fn prepare_in_parallel() {
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let mut outputs = (0..3)
+            .map(|_| OutputState {
+                damage_tracker: OutputDamageTracker::new((100, 100), 1.0, Transform::Normal),
+                elements: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let prepared = prepare_outputs(&pool, &mut outputs, |output| {
+            output.elements.push(SolidColorRenderElement::new(
+                Id::new(),
+                Rectangle::new((10, 10).into(), (20, 20).into()),
+                0,
+                [1.0, 0.0, 0.0, 1.0],
+                Kind::Unspecified,
+            ));
+            OutputDamageJob {
+                damage_tracker: &mut output.damage_tracker,
+                age: 0,
+                elements: &output.elements,
+                clear_color: [0.0, 0.0, 0.0, 1.0].into(),
+            }
+        });
+        assert_eq!(prepared.len(), 3);
+
+        let mut renderer = DummyRenderer::default();
+        for (output, prepared) in outputs.iter_mut().zip(prepared) {
+            let prepared = prepared.unwrap();
+            assert_eq!(prepared.states().states.len(), 1);
+            let result = output
+                .damage_tracker
+                .render_prepared(&mut renderer, &mut DummyFramebuffer, &output.elements, prepared)
+                .unwrap();
+            assert_eq!(
+                result.damage.map(|damage| damage.as_slice()),
+                Some(&[Rectangle::from_size((100, 100).into())][..])
+            );
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Debug overlay showing rendering statistics of an output
+//!
+//! The [`DebugOverlay`] keeps track of the frames rendered on a single output and
+//! produces render elements visualizing the current frame rate, the age of the
+//! buffer that was rendered into and the damage of the last frame.
+//!
+//! Text is drawn using a tiny built-in bitmap font made of solid color rectangles,
+//! so no font dependency or texture upload is required and the overlay works with any [`Renderer`].
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::{
+//! #     backend::renderer::test::{DummyRenderer, DummyFramebuffer},
+//! #     utils::{Clock, Monotonic, Transform},
+//! # };
+//! use smithay::backend::renderer::{
+//!     damage::OutputDamageTracker,
+//!     element::debug::{DebugOverlay, DebugOverlayRenderElement},
+//! };
+//!
+//! let mut overlay = DebugOverlay::new();
+//! overlay.set_enabled(true);
+//!
+//! let clock = Clock::<Monotonic>::new();
+//! let mut damage_tracker = OutputDamageTracker::new((800, 600), 1.0, Transform::Normal);
+//! # let mut renderer = DummyRenderer::default();
+//! # let mut framebuffer = DummyFramebuffer;
+//! # let age = 0;
+//!
+//! loop {
+//!     let elements: Vec<DebugOverlayRenderElement> = overlay.render_elements((10, 10), 1.0);
+//!     let result = damage_tracker
+//!         .render_output(&mut renderer, &mut framebuffer, age, &elements, [0.0, 0.0, 0.0, 1.0])
+//!         .expect("failed to render output");
+//!     overlay.record_frame(clock.now(), age, result.damage.map(|damage| &**damage));
+//! }
+//! ```
+//!
+//! *Note*: Visualizing the damage does itself cause damage in the following frame,
+//! so an enabled overlay will keep the output from ever becoming idle.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    backend::renderer::{
+        utils::{CommitCounter, OpaqueRegions},
+        Color32F, Frame, Renderer,
+    },
+    utils::{Buffer, Monotonic, Physical, Point, Rectangle, Scale, Size, Time, Transform},
+};
+
+use super::{solid::SolidColorRenderElement, Element, Id, Kind, RenderElement};
+
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_HEIGHT: i32 = 5;
+const PADDING: i32 = 2;
+const BAR_HEIGHT: i32 = 6;
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+const BACKGROUND: Color32F = Color32F::new(0.0, 0.0, 0.0, 0.6);
+const FOREGROUND: Color32F = Color32F::new(1.0, 1.0, 1.0, 1.0);
+const DAMAGE: Color32F = Color32F::new(0.3, 0.0, 0.0, 0.3);
+
+/// Returns the rows of a glyph of the built-in 3x5 font, the lowest three bits of each row are the pixels.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// Lays out the given lines of text, returning the filled cells and the total size in font pixels
+fn layout_text(lines: &[String]) -> (Vec<Rectangle<i32, Physical>>, Size<i32, Physical>) {
+    let mut cells = Vec::new();
+    let mut width = 0;
+    for (line_idx, line) in lines.iter().enumerate() {
+        let y = line_idx as i32 * (GLYPH_HEIGHT + 1);
+        let mut x = 0;
+        for c in line.chars() {
+            for (row_idx, row) in glyph(c).iter().enumerate() {
+                // merge horizontally adjacent pixels of a row into a single rectangle
+                let mut col = 0;
+                while col < GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        col += 1;
+                        continue;
+                    }
+                    let start = col;
+                    while col < GLYPH_WIDTH && row & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        col += 1;
+                    }
+                    cells.push(Rectangle::new(
+                        (x + start, y + row_idx as i32).into(),
+                        (col - start, 1).into(),
+                    ));
+                }
+            }
+            x += GLYPH_WIDTH + 1;
+        }
+        width = width.max(x - 1);
+    }
+    let height = (lines.len() as i32 * (GLYPH_HEIGHT + 1) - 1).max(0);
+    (cells, (width.max(0), height).into())
+}
+
+/// Per-output state of the debug overlay
+#[derive(Debug)]
+pub struct DebugOverlay {
+    enabled: bool,
+    id: Id,
+    commit: CommitCounter,
+    pixel_size: i32,
+    frames: VecDeque<Time<Monotonic>>,
+    fps: f64,
+    buffer_age: usize,
+    damage: Vec<Rectangle<i32, Physical>>,
+    show_damage: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugOverlay {
+    /// Create a new, disabled debug overlay
+    pub fn new() -> Self {
+        DebugOverlay {
+            enabled: false,
+            id: Id::new(),
+            commit: CommitCounter::default(),
+            pixel_size: 3,
+            frames: VecDeque::new(),
+            fps: 0.0,
+            buffer_age: 0,
+            damage: Vec::new(),
+            show_damage: true,
+        }
+    }
+
+    /// Returns whether the overlay is currently enabled
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable the overlay
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.enabled = enabled;
+            self.commit.increment();
+        }
+    }
+
+    /// Toggle the overlay, returning the new state
+    pub fn toggle(&mut self) -> bool {
+        self.set_enabled(!self.enabled);
+        self.enabled
+    }
+
+    /// Set whether the damage of the last frame should be highlighted
+    pub fn set_show_damage(&mut self, show_damage: bool) {
+        self.show_damage = show_damage;
+    }
+
+    /// Set the size of a single pixel of the overlays font in physical pixels
+    pub fn set_pixel_size(&mut self, pixel_size: i32) {
+        let pixel_size = pixel_size.max(1);
+        if self.pixel_size != pixel_size {
+            self.pixel_size = pixel_size;
+            self.commit.increment();
+        }
+    }
+
+    /// Record a rendered frame.
+    ///
+    /// `age` is the buffer age the frame was rendered with and `damage`
+    /// the damage reported by the damage tracker, if anything was rendered.
+    pub fn record_frame(
+        &mut self,
+        time: Time<Monotonic>,
+        age: usize,
+        damage: Option<&[Rectangle<i32, Physical>]>,
+    ) {
+        self.frames.push_back(time);
+        while let Some(oldest) = self.frames.front() {
+            if Time::elapsed(oldest, time) > FPS_WINDOW {
+                self.frames.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.fps = match (self.frames.front(), self.frames.len()) {
+            (Some(oldest), count) if count > 1 => {
+                let elapsed = Time::elapsed(oldest, time);
+                (count - 1) as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+            }
+            _ => 0.0,
+        };
+
+        self.buffer_age = age;
+        self.damage.clear();
+        if let Some(damage) = damage {
+            self.damage.extend_from_slice(damage);
+        }
+
+        if self.enabled {
+            self.commit.increment();
+        }
+    }
+
+    /// Returns the currently measured frames per second
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Returns the buffer age of the last recorded frame
+    pub fn buffer_age(&self) -> usize {
+        self.buffer_age
+    }
+
+    /// Returns the damage of the last recorded frame
+    pub fn damage(&self) -> &[Rectangle<i32, Physical>] {
+        &self.damage
+    }
+
+    /// Create the render elements for the overlay placed at `location`.
+    ///
+    /// Returns no elements if the overlay is disabled.
+    pub fn render_elements<C>(&self, location: impl Into<Point<i32, Physical>>, alpha: f32) -> Vec<C>
+    where
+        C: From<DebugOverlayRenderElement>,
+    {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        let location = location.into();
+        let lines = [
+            format!("FPS: {:.1}", self.fps),
+            format!("AGE: {}", self.buffer_age),
+            format!("DMG: {}", self.damage.len()),
+        ];
+        let (cells, text_size) = layout_text(&lines);
+
+        let frame_time = if self.fps > 0.0 { 1000.0 / self.fps } else { 0.0 };
+        // the bar is full at a frame time of 33ms
+        let bar_width = ((frame_time / 33.3).min(1.0) * text_size.w as f64).round() as i32;
+
+        let pixel_size = self.pixel_size;
+        let mut cells = cells
+            .into_iter()
+            .map(|cell| Rectangle::new(cell.loc + Point::from((PADDING, PADDING)), cell.size))
+            .collect::<Vec<_>>();
+        if bar_width > 0 {
+            cells.push(Rectangle::new(
+                (PADDING, PADDING * 2 + text_size.h).into(),
+                (bar_width, BAR_HEIGHT / 2).into(),
+            ));
+        }
+        let cells = cells
+            .into_iter()
+            .map(|cell| cell.upscale(pixel_size))
+            .collect::<Vec<_>>();
+        let size = Size::from((
+            text_size.w + PADDING * 2,
+            text_size.h + PADDING * 3 + BAR_HEIGHT / 2,
+        ))
+        .upscale(pixel_size);
+
+        let mut elements = Vec::with_capacity(1 + self.damage.len());
+        elements.push(C::from(DebugOverlayRenderElement::Panel(DebugPanelElement {
+            id: self.id.clone(),
+            commit: self.commit,
+            geometry: Rectangle::new(location, size),
+            cells,
+            alpha,
+        })));
+        if self.show_damage {
+            elements.extend(self.damage.iter().map(|rect| {
+                C::from(DebugOverlayRenderElement::Damage(SolidColorRenderElement::new(
+                    Id::new(),
+                    *rect,
+                    CommitCounter::default(),
+                    DAMAGE * alpha,
+                    Kind::Unspecified,
+                )))
+            }));
+        }
+        elements
+    }
+}
+
+/// Element drawing the text and bars of a [`DebugOverlay`]
+#[derive(Debug, Clone)]
+pub struct DebugPanelElement {
+    id: Id,
+    commit: CommitCounter,
+    geometry: Rectangle<i32, Physical>,
+    cells: Vec<Rectangle<i32, Physical>>,
+    alpha: f32,
+}
+
+impl Element for DebugPanelElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        Rectangle::from_size(
+            self.geometry
+                .size
+                .to_f64()
+                .to_logical(1.0)
+                .to_buffer(1.0, Transform::Normal),
+        )
+    }
+
+    fn geometry(&self, _scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.geometry
+    }
+
+    fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        OpaqueRegions::default()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
+impl<R: Renderer> RenderElement<R> for DebugPanelElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        frame.draw_solid(dst, damage, BACKGROUND * self.alpha)?;
+
+        let mut cell_damage = Vec::with_capacity(damage.len());
+        for cell in &self.cells {
+            cell_damage.clear();
+            cell_damage.extend(damage.iter().filter_map(|rect| {
+                rect.intersection(*cell)
+                    .map(|rect| Rectangle::new(rect.loc - cell.loc, rect.size))
+            }));
+            if cell_damage.is_empty() {
+                continue;
+            }
+
+            let cell_dst = Rectangle::new(dst.loc + cell.loc, cell.size);
+            frame.draw_solid(cell_dst, &cell_damage, FOREGROUND * self.alpha)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Render element type produced by [`DebugOverlay::render_elements`]
+#[derive(Debug, Clone)]
+pub enum DebugOverlayRenderElement {
+    /// Panel containing the text and frame time bar
+    Panel(DebugPanelElement),
+    /// Highlighted damage rectangle of the last frame
+    Damage(SolidColorRenderElement),
+}
+
+impl Element for DebugOverlayRenderElement {
+    fn id(&self) -> &Id {
+        match self {
+            Self::Panel(elem) => elem.id(),
+            Self::Damage(elem) => elem.id(),
+        }
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        match self {
+            Self::Panel(elem) => elem.current_commit(),
+            Self::Damage(elem) => elem.current_commit(),
+        }
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        match self {
+            Self::Panel(elem) => elem.src(),
+            Self::Damage(elem) => elem.src(),
+        }
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        match self {
+            Self::Panel(elem) => elem.geometry(scale),
+            Self::Damage(elem) => elem.geometry(scale),
+        }
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        match self {
+            Self::Panel(elem) => elem.opaque_regions(scale),
+            Self::Damage(elem) => elem.opaque_regions(scale),
+        }
+    }
+
+    fn alpha(&self) -> f32 {
+        match self {
+            Self::Panel(elem) => elem.alpha(),
+            Self::Damage(elem) => elem.alpha(),
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::Unspecified
+    }
+}
+
+impl<R: Renderer> RenderElement<R> for DebugOverlayRenderElement {
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        match self {
+            Self::Panel(elem) => RenderElement::<R>::draw(elem, frame, src, dst, damage, opaque_regions),
+            Self::Damage(elem) => RenderElement::<R>::draw(elem, frame, src, dst, damage, opaque_regions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_layout_merges_rows() {
+        let (cells, size) = layout_text(&["0".into()]);
+        assert_eq!(size, Size::from((3, 5)));
+        // top and bottom row are a single rectangle, the middle rows two single pixels each
+        assert_eq!(cells.len(), 2 + 3 * 2);
+        assert!(cells.contains(&Rectangle::new((0, 0).into(), (3, 1).into())));
+    }
+
+    #[test]
+    fn fps_measurement() {
+        let mut overlay = DebugOverlay::new();
+        for i in 0..=60u64 {
+            let time = Time::<Monotonic>::from(Duration::from_micros(1_000_000 + i * 16_667));
+            overlay.record_frame(time, 2, None);
+        }
+        assert!((overlay.fps() - 60.0).abs() < 0.5);
+        assert_eq!(overlay.buffer_age(), 2);
+    }
+}
@@ -0,0 +1,620 @@
+//! Blur and drop-shadow effects
+//!
+//! The effects in this module are implemented purely on top of the [`Offscreen`] capability of a
+//! [`Renderer`], so they work with any renderer able to render into its own textures.
+//!
+//! Blurring uses the dual Kawase algorithm: the source is repeatedly downsampled into textures of
+//! half the size and afterwards upsampled again, sampling four diagonal taps per pass. The taps are
+//! averaged by rendering the same texture four times with decreasing alpha values.
+//!
+//! - [`BlurBuffer`] renders a region of the scene into an offscreen texture and blurs it, which can
+//!   be placed behind translucent windows.
+//! - [`ShadowBuffer`] blurs a solid rectangle to produce a drop shadow for windows.
+//!
+//! Both produce [`EffectRenderElement`]s, which are drawn like any other texture.
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::{
+//! #     backend::renderer::{element::solid::SolidColorRenderElement, test::{DummyRenderer, DummyFramebuffer}},
+//! #     utils::Transform,
+//! # };
+//! use smithay::{
+//!     backend::renderer::{
+//!         damage::OutputDamageTracker,
+//!         element::{
+//!             effects::{BlurBuffer, KawaseBlur, ShadowBuffer},
+//!             Kind,
+//!         },
+//!     },
+//!     utils::Rectangle,
+//! };
+//!
+//! # let mut renderer = DummyRenderer::default();
+//! # let mut framebuffer = DummyFramebuffer;
+//! # let background: Vec<SolidColorRenderElement> = Vec::new();
+//! let mut blur = BlurBuffer::new(KawaseBlur::default(), Transform::Normal);
+//! let mut shadow = ShadowBuffer::new(Transform::Normal);
+//! let mut damage_tracker = OutputDamageTracker::new((800, 600), 1.0, Transform::Normal);
+//!
+//! // geometry of a translucent window
+//! let window = Rectangle::<i32, _>::new((100, 100).into(), (400, 300).into());
+//!
+//! loop {
+//!     // blur everything behind the window
+//!     blur.update(&mut renderer, window, 1.0, &background).expect("failed to blur");
+//!     shadow
+//!         .update(&mut renderer, window.size, 16, [0.0, 0.0, 0.0, 0.5])
+//!         .expect("failed to render shadow");
+//!
+//!     let mut elements = Vec::new();
+//!     // ... the window itself
+//!     elements.extend(blur.render_element(1.0, Kind::Unspecified));
+//!     elements.extend(shadow.render_element(window.loc, 1.0, Kind::Unspecified));
+//!     // ... the background, which the blur was created from
+//!
+//!     damage_tracker
+//!         .render_output(&mut renderer, &mut framebuffer, 0, &elements, [0.0, 0.0, 0.0, 1.0])
+//!         .expect("failed to render output");
+//! }
+//! ```
+//!
+//! *Note*: Some renderers, like the [`GlesRenderer`](crate::backend::renderer::gles::GlesRenderer),
+//! render into textures upside down. Pass `Transform::Flipped180` as the buffer transform for those.
+
+use tracing::warn;
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            utils::CommitCounter, ClipRegion, Color32F, ContextId, Frame, Offscreen, Renderer, Texture,
+        },
+    },
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+
+/// Maximum amount of passes of a [`KawaseBlur`]
+pub const MAX_BLUR_PASSES: usize = 8;
+
+// diagonal taps sampled by every pass
+const TAPS: [(f64, f64); 4] = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+
+/// Parameters of the dual Kawase blur
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KawaseBlur {
+    /// Amount of downsample (and following upsample) passes, clamped to [`MAX_BLUR_PASSES`]
+    pub passes: usize,
+    /// Distance of the sampled taps in texels of the respective pass
+    pub offset: f64,
+}
+
+impl Default for KawaseBlur {
+    fn default() -> Self {
+        KawaseBlur {
+            passes: 3,
+            offset: 1.5,
+        }
+    }
+}
+
+impl KawaseBlur {
+    /// Approximate a blur of the given radius in pixels
+    pub fn for_radius(radius: f64) -> Self {
+        let radius = radius.max(1.0);
+        let passes = (radius.log2().ceil() as usize).clamp(1, MAX_BLUR_PASSES);
+        KawaseBlur {
+            passes,
+            offset: (radius / (1 << passes) as f64).max(0.5),
+        }
+    }
+}
+
+/// Offscreen textures used by a blur, the first one holding the input and the final result
+#[derive(Debug)]
+struct BlurPipeline<T> {
+    levels: Vec<T>,
+}
+
+impl<T> Default for BlurPipeline<T> {
+    fn default() -> Self {
+        BlurPipeline { levels: Vec::new() }
+    }
+}
+
+impl<T: Texture> BlurPipeline<T> {
+    fn ensure<R>(&mut self, renderer: &mut R, size: Size<i32, Buffer>, passes: usize) -> Result<(), R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let mut level_size = size;
+        for level in 0..=passes {
+            if self.levels.get(level).map(|texture| texture.size()) != Some(level_size) {
+                let texture = renderer.create_buffer(Fourcc::Abgr8888, level_size)?;
+                self.levels.truncate(level);
+                self.levels.push(texture);
+            }
+            level_size = Size::from(((level_size.w / 2).max(1), (level_size.h / 2).max(1)));
+        }
+        self.levels.truncate(passes + 1);
+        Ok(())
+    }
+
+    fn blur<R>(&mut self, renderer: &mut R, config: &KawaseBlur) -> Result<(), R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let passes = self.levels.len() - 1;
+        for level in 0..passes {
+            let (src, dst) = self.levels.split_at_mut(level + 1);
+            kawase_pass(renderer, &src[level], &mut dst[0], config.offset)?;
+        }
+        for level in (0..passes).rev() {
+            let (dst, src) = self.levels.split_at_mut(level + 1);
+            kawase_pass(renderer, &src[0], &mut dst[level], config.offset)?;
+        }
+        Ok(())
+    }
+
+    fn output(&self) -> Option<&T> {
+        self.levels.first()
+    }
+}
+
+fn kawase_pass<R, T>(renderer: &mut R, src: &T, dst: &mut T, offset: f64) -> Result<(), R::Error>
+where
+    R: Renderer<TextureId = T> + Offscreen<T>,
+    T: Texture,
+{
+    let size = dst.size();
+    let dst_rect = Rectangle::<i32, Physical>::from_size((size.w, size.h).into());
+    let src_size = src.size().to_f64();
+
+    let mut framebuffer = renderer.bind(dst)?;
+    let mut frame = renderer.render(&mut framebuffer, dst_rect.size, Transform::Normal)?;
+    frame.clear(Color32F::TRANSPARENT, &[dst_rect])?;
+    for (i, (x, y)) in TAPS.iter().enumerate() {
+        let src_rect = Rectangle::new((x * offset, y * offset).into(), src_size);
+        // blending with 1/n averages the taps rendered so far
+        frame.render_texture_from_to(
+            src,
+            src_rect,
+            dst_rect,
+            &[dst_rect],
+            &[],
+            Transform::Normal,
+            1.0 / (i + 1) as f32,
+        )?;
+    }
+    let sync = frame.finish()?;
+    renderer.wait(&sync)
+}
+
+/// Blurred copy of a region of the scene
+#[derive(Debug)]
+pub struct BlurBuffer<T: Texture> {
+    id: Id,
+    commit: CommitCounter,
+    config: KawaseBlur,
+    transform: Transform,
+    pipeline: BlurPipeline<T>,
+    context_id: Option<ContextId<T>>,
+    geometry: Rectangle<i32, Physical>,
+}
+
+impl<T: Texture + Clone> BlurBuffer<T> {
+    /// Create a new blur buffer
+    ///
+    /// `transform` is the transform of the offscreen textures of the renderer.
+    pub fn new(config: KawaseBlur, transform: Transform) -> Self {
+        BlurBuffer {
+            id: Id::new(),
+            commit: CommitCounter::default(),
+            config,
+            transform,
+            pipeline: BlurPipeline::default(),
+            context_id: None,
+            geometry: Rectangle::default(),
+        }
+    }
+
+    /// Parameters of the blur
+    pub fn config(&self) -> KawaseBlur {
+        self.config
+    }
+
+    /// Change the parameters of the blur, taking effect on the next [`update`](Self::update)
+    pub fn set_config(&mut self, config: KawaseBlur) {
+        self.config = config;
+    }
+
+    /// Render and blur the given elements inside of `region`
+    ///
+    /// `elements` are expected in front to back order, like for the
+    /// [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker), and with the
+    /// same `scale` as the output the blur is displayed on.
+    #[profiling::function]
+    pub fn update<R, E>(
+        &mut self,
+        renderer: &mut R,
+        region: Rectangle<i32, Physical>,
+        scale: impl Into<Scale<f64>>,
+        elements: &[E],
+    ) -> Result<(), R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+        E: RenderElement<R>,
+    {
+        let scale = scale.into();
+        if region.is_empty() {
+            self.geometry = region;
+            return Ok(());
+        }
+        let passes = self.config.passes.clamp(1, MAX_BLUR_PASSES);
+        self.pipeline
+            .ensure(renderer, (region.size.w, region.size.h).into(), passes)?;
+
+        {
+            let output = Rectangle::from_size(region.size);
+            let mut framebuffer = renderer.bind(&mut self.pipeline.levels[0])?;
+            let mut frame = renderer.render(&mut framebuffer, region.size, Transform::Normal)?;
+            frame.clear(Color32F::TRANSPARENT, &[output])?;
+            for element in elements.iter().rev() {
+                let mut geometry = element.geometry(scale);
+                geometry.loc -= region.loc;
+                let Some(visible) = geometry.intersection(output) else {
+                    continue;
+                };
+                let damage = [Rectangle::new(visible.loc - geometry.loc, visible.size)];
+                element.draw(&mut frame, element.src(), geometry, &damage, &[])?;
+            }
+            let sync = frame.finish()?;
+            renderer.wait(&sync)?;
+        }
+        self.pipeline.blur(renderer, &self.config)?;
+
+        self.context_id = Some(renderer.context_id());
+        self.geometry = region;
+        self.commit.increment();
+        Ok(())
+    }
+
+    /// Create a render element displaying the blurred region
+    ///
+    /// Returns `None` if the buffer was not [`update`](Self::update)d yet.
+    pub fn render_element(&self, alpha: f32, kind: Kind) -> Option<EffectRenderElement<T>> {
+        if self.geometry.is_empty() {
+            return None;
+        }
+        Some(EffectRenderElement::new(
+            self.id.clone(),
+            self.context_id.clone()?,
+            self.pipeline.output()?.clone(),
+            self.geometry,
+            self.transform,
+            self.commit,
+            alpha,
+            kind,
+        ))
+    }
+}
+
+/// Drop shadow of a rectangle
+#[derive(Debug)]
+pub struct ShadowBuffer<T: Texture> {
+    id: Id,
+    commit: CommitCounter,
+    transform: Transform,
+    pipeline: BlurPipeline<T>,
+    context_id: Option<ContextId<T>>,
+    parameters: Option<(Size<i32, Physical>, i32, Color32F)>,
+}
+
+impl<T: Texture + Clone> ShadowBuffer<T> {
+    /// Create a new shadow buffer
+    ///
+    /// `transform` is the transform of the offscreen textures of the renderer.
+    pub fn new(transform: Transform) -> Self {
+        ShadowBuffer {
+            id: Id::new(),
+            commit: CommitCounter::default(),
+            transform,
+            pipeline: BlurPipeline::default(),
+            context_id: None,
+            parameters: None,
+        }
+    }
+
+    /// Render the shadow of a rectangle of the given size
+    ///
+    /// The shadow extends `radius` pixels beyond the rectangle. This is a no-op if the parameters
+    /// did not change since the last update.
+    #[profiling::function]
+    pub fn update<R>(
+        &mut self,
+        renderer: &mut R,
+        size: Size<i32, Physical>,
+        radius: i32,
+        color: impl Into<Color32F>,
+    ) -> Result<(), R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let color = color.into();
+        let radius = radius.max(0);
+        let parameters = Some((size, radius, color));
+        if self.parameters == parameters && self.context_id == Some(renderer.context_id()) {
+            return Ok(());
+        }
+
+        let padded = Size::<i32, Physical>::from((size.w + radius * 2, size.h + radius * 2));
+        let config = KawaseBlur::for_radius(radius as f64);
+        self.pipeline
+            .ensure(renderer, (padded.w, padded.h).into(), config.passes)?;
+
+        {
+            let output = Rectangle::from_size(padded);
+            let casting = Rectangle::new((radius, radius).into(), size);
+            let mut framebuffer = renderer.bind(&mut self.pipeline.levels[0])?;
+            let mut frame = renderer.render(&mut framebuffer, padded, Transform::Normal)?;
+            frame.clear(Color32F::TRANSPARENT, &[output])?;
+            frame.draw_solid(casting, &[Rectangle::from_size(casting.size)], color)?;
+            let sync = frame.finish()?;
+            renderer.wait(&sync)?;
+        }
+        if radius > 0 {
+            self.pipeline.blur(renderer, &config)?;
+        }
+
+        self.context_id = Some(renderer.context_id());
+        self.parameters = parameters;
+        self.commit.increment();
+        Ok(())
+    }
+
+    /// Create a render element for the shadow of a rectangle at `location`
+    ///
+    /// Returns `None` if the buffer was not [`update`](Self::update)d yet.
+    pub fn render_element(
+        &self,
+        location: impl Into<Point<i32, Physical>>,
+        alpha: f32,
+        kind: Kind,
+    ) -> Option<EffectRenderElement<T>> {
+        let (size, radius, _) = self.parameters?;
+        let geometry = Rectangle::new(
+            location.into() - Point::from((radius, radius)),
+            (size.w + radius * 2, size.h + radius * 2).into(),
+        );
+        Some(EffectRenderElement::new(
+            self.id.clone(),
+            self.context_id.clone()?,
+            self.pipeline.output()?.clone(),
+            geometry,
+            self.transform,
+            self.commit,
+            alpha,
+            kind,
+        ))
+    }
+}
+
+/// Render element of an effect
+#[derive(Debug)]
+pub struct EffectRenderElement<T: Texture> {
+    id: Id,
+    context_id: ContextId<T>,
+    texture: T,
+    geometry: Rectangle<i32, Physical>,
+    transform: Transform,
+    commit: CommitCounter,
+    alpha: f32,
+    kind: Kind,
+}
+
+impl<T: Texture> EffectRenderElement<T> {
+    /// Create a render element displaying the whole `texture` at `geometry`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: Id,
+        context_id: ContextId<T>,
+        texture: T,
+        geometry: Rectangle<i32, Physical>,
+        transform: Transform,
+        commit: CommitCounter,
+        alpha: f32,
+        kind: Kind,
+    ) -> Self {
+        EffectRenderElement {
+            id,
+            context_id,
+            texture,
+            geometry,
+            transform,
+            commit,
+            alpha,
+            kind,
+        }
+    }
+
+    /// The texture holding the effect
+    pub fn texture(&self) -> &T {
+        &self.texture
+    }
+}
+
+impl<T: Texture> Element for EffectRenderElement<T> {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        Rectangle::from_size(self.texture.size().to_f64())
+    }
+
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn geometry(&self, _scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.geometry
+    }
+
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+impl<R, T> RenderElement<R> for EffectRenderElement<T>
+where
+    R: Renderer<TextureId = T>,
+    T: Texture,
+{
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        if frame.context_id() != self.context_id {
+            warn!("trying to render effect from different renderer context");
+            return Ok(());
+        }
+
+        frame.render_texture_from_to(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            self.transform,
+            self.alpha,
+        )
+    }
+
+    #[profiling::function]
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        if frame.context_id() != self.context_id {
+            warn!("trying to render effect from different renderer context");
+            return Ok(());
+        }
+
+        frame.render_texture_clipped(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            self.transform,
+            self.alpha,
+            clip,
+        )
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{element::solid::SolidColorRenderElement, test::DummyRenderer};
+
+    #[test]
+    fn blur_radius() {
+        let config = KawaseBlur::for_radius(16.0);
+        assert_eq!(config.passes, 4);
+        assert_eq!(config.offset, 1.0);
+        assert_eq!(KawaseBlur::for_radius(1_000_000.0).passes, MAX_BLUR_PASSES);
+    }
+
+    #[test]
+    fn blur_levels() {
+        let mut renderer = DummyRenderer;
+        let mut blur = BlurBuffer::new(KawaseBlur::default(), Transform::Normal);
+        assert!(blur.render_element(1.0, Kind::Unspecified).is_none());
+
+        let background = [SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::from_size((800, 600).into()),
+            CommitCounter::default(),
+            [1.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        )];
+        let region = Rectangle::new((100, 50).into(), (101, 40).into());
+        blur.update(&mut renderer, region, 1.0, &background).unwrap();
+
+        let sizes = blur
+            .pipeline
+            .levels
+            .iter()
+            .map(|texture| texture.size())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![(101, 40).into(), (50, 20).into(), (25, 10).into(), (12, 5).into()]
+        );
+
+        let element = blur.render_element(1.0, Kind::Unspecified).unwrap();
+        assert_eq!(element.geometry(1.0.into()), region);
+        let commit = element.current_commit();
+        blur.update(&mut renderer, region, 1.0, &background).unwrap();
+        assert_ne!(
+            blur.render_element(1.0, Kind::Unspecified)
+                .unwrap()
+                .current_commit(),
+            commit
+        );
+    }
+
+    #[test]
+    fn shadow_geometry() {
+        let mut renderer = DummyRenderer;
+        let mut shadow = ShadowBuffer::new(Transform::Normal);
+        shadow
+            .update(&mut renderer, (200, 100).into(), 10, [0.0, 0.0, 0.0, 0.5])
+            .unwrap();
+        let element = shadow.render_element((50, 50), 1.0, Kind::Unspecified).unwrap();
+        assert_eq!(
+            element.geometry(1.0.into()),
+            Rectangle::new((40, 40).into(), (220, 120).into())
+        );
+
+        // unchanged parameters do not cause damage
+        let commit = element.current_commit();
+        shadow
+            .update(&mut renderer, (200, 100).into(), 10, [0.0, 0.0, 0.0, 0.5])
+            .unwrap();
+        assert_eq!(
+            shadow
+                .render_element((50, 50), 1.0, Kind::Unspecified)
+                .unwrap()
+                .current_commit(),
+            commit
+        );
+    }
+}
@@ -116,7 +116,7 @@ use crate::{
         allocator::{format::get_bpp, Fourcc},
         renderer::{
             utils::{CommitCounter, DamageBag, DamageSet, DamageSnapshot, OpaqueRegions},
-            ErasedContextId, Frame, ImportMem, Renderer,
+            ClipRegion, ErasedContextId, Frame, ImportMem, Renderer,
         },
     },
     utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
@@ -654,6 +654,29 @@ where
         )
     }
 
+    #[instrument(level = "trace", skip(self, frame))]
+    #[profiling::function]
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        frame.render_texture_clipped(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            self.buffer_transform,
+            self.alpha,
+            clip,
+        )
+    }
+
     #[inline]
     fn underlying_storage(&self, _renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
         Some(UnderlyingStorage::Memory(&self.buffer))
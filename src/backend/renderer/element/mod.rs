@@ -12,6 +12,9 @@
 //! - [`texture`] - Texture based render element
 //! - [`surface`] - Wayland surface render element
 //! - [`solid`] - Solid color render element
+//! - [`effects`] - Blur and drop-shadow effects
+//!
+//! The [`scanout`] module evaluates whether elements are eligible for direct scan-out.
 //!
 //! The [`render_elements!`] macro provides an easy way to aggregate multiple different [RenderElement]s
 //! into a single enum.
@@ -36,10 +39,13 @@ use crate::{
 use super::utils::Buffer;
 use super::{
     utils::{CommitCounter, DamageSet, OpaqueRegions},
-    Renderer,
+    ClipRegion, Renderer,
 };
 
+pub mod debug;
+pub mod effects;
 pub mod memory;
+pub mod scanout;
 pub mod solid;
 #[cfg(feature = "wayland_frontend")]
 pub mod surface;
@@ -210,7 +216,7 @@ pub enum UnderlyingStorage<'a> {
 
 /// Defines the (optional) reason why a [`Element`] was selected for
 /// rendering instead of direct scan-out
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RenderingReason {
     /// The element buffer format is unsuited for direct scan-out
     FormatUnsupported,
@@ -509,6 +515,30 @@ pub trait RenderElement<R: Renderer>: Element {
         opaque_regions: &[Rectangle<i32, Physical>],
     ) -> Result<(), R::Error>;
 
+    /// Draw this element clipped to the given [`ClipRegion`]
+    ///
+    /// The clip region is given in the same coordinate space as `dst`.
+    ///
+    /// The default implementation clips the damage analytically and calls [`RenderElement::draw`].
+    /// Elements backed by a texture should override this and use [`Frame::render_texture_clipped`](super::Frame::render_texture_clipped),
+    /// which allows the renderer to clip rounded corners per pixel.
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        let damage = clip.clip_damage_analytic(dst, damage);
+        if damage.is_empty() {
+            return Ok(());
+        }
+        let opaque_regions = clip.clip_opaque_regions(dst, opaque_regions);
+        self.draw(frame, src, dst, &damage, &opaque_regions)
+    }
+
     /// Get the underlying storage of this element, may be used to optimize rendering (eg. drm planes)
     #[inline]
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
@@ -599,6 +629,18 @@ where
     ) -> Result<(), R::Error> {
         (*self).draw(frame, src, dst, damage, opaque_regions)
     }
+
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        (*self).draw_clipped(frame, src, dst, damage, opaque_regions, clip)
+    }
 }
 
 // Clippy: `large_enum_variant` is easily triggered here, as the `_GenericCatcher` is always zero-size.
@@ -749,6 +791,9 @@ macro_rules! render_elements_internal {
     (@call $renderer:ty as $other:ty; draw; $x:ident, $renderer_ref:ident, $frame:ident, $($tail:ident),*) => {
         $crate::backend::renderer::element::RenderElement::<$other>::draw($x, $renderer_ref.as_mut(), $frame.as_mut(), $($tail),*).map_err(Into::into)
     };
+    (@call $renderer:ty as $other:ty; draw_clipped; $x:ident, $frame:ident, $($tail:ident),*) => {
+        $crate::backend::renderer::element::RenderElement::<$other>::draw_clipped($x, $frame.as_mut(), $($tail),*).map_err(Into::into)
+    };
     (@call $renderer:ty as $other:ty; $name:ident; $($x:ident),*) => {
         $crate::backend::renderer::element::RenderElement::<$other>::$name($($x),*)
     };
@@ -913,6 +958,36 @@ macro_rules! render_elements_internal {
             }
         }
 
+        fn draw_clipped(
+            &self,
+            frame: &mut <$renderer as $crate::backend::renderer::RendererSuper>::Frame<'_, '_>,
+            src: $crate::utils::Rectangle<f64, $crate::utils::Buffer>,
+            dst: $crate::utils::Rectangle<i32, $crate::utils::Physical>,
+            damage: &[$crate::utils::Rectangle<i32, $crate::utils::Physical>],
+            opaque_regions: &[$crate::utils::Rectangle<i32, $crate::utils::Physical>],
+            clip: &$crate::backend::renderer::ClipRegion,
+        ) -> Result<(), <$renderer as $crate::backend::renderer::RendererSuper>::Error>
+        where
+        $(
+            $(
+                $renderer: std::convert::AsMut<$other_renderer>,
+                <$renderer as $crate::backend::renderer::RendererSuper>::Frame: std::convert::AsMut<<$other_renderer as $crate::backend::renderer::RendererSuper>::Frame>,
+                <$other_renderer as $crate::backend::renderer::RendererSuper>::Error: Into<<$renderer as $crate::backend::renderer::RendererSuper>::Error>,
+            )*
+        )*
+        {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call $renderer $(as $other_renderer)?; draw_clipped; x, frame, src, dst, damage, opaque_regions, clip)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+
         #[inline]
         fn underlying_storage(&self, renderer: &mut $renderer) -> Option<$crate::backend::renderer::element::UnderlyingStorage<'_>>
         {
@@ -950,6 +1025,28 @@ macro_rules! render_elements_internal {
             }
         }
 
+        fn draw_clipped(
+            &self,
+            frame: &mut <$renderer as $crate::backend::renderer::RendererSuper>::Frame<'_, '_>,
+            src: $crate::utils::Rectangle<f64, $crate::utils::Buffer>,
+            dst: $crate::utils::Rectangle<i32, $crate::utils::Physical>,
+            damage: &[$crate::utils::Rectangle<i32, $crate::utils::Physical>],
+            opaque_regions: &[$crate::utils::Rectangle<i32, $crate::utils::Physical>],
+            clip: &$crate::backend::renderer::ClipRegion,
+        ) -> Result<(), <$renderer as $crate::backend::renderer::RendererSuper>::Error>
+        {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call $renderer $(as $other_renderer)?; draw_clipped; x, frame, src, dst, damage, opaque_regions, clip)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+
         #[inline]
         fn underlying_storage(&self, renderer: &mut $renderer) -> Option<$crate::backend::renderer::element::UnderlyingStorage<'_>>
         {
@@ -1530,6 +1627,18 @@ where
         self.0.draw(frame, src, dst, damage, opaque_regions)
     }
 
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        self.0.draw_clipped(frame, src, dst, damage, opaque_regions, clip)
+    }
+
     #[inline]
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
         self.0.underlying_storage(renderer)
@@ -0,0 +1,519 @@
+//! Direct scan-out eligibility
+//!
+//! Presenting a client buffer directly, without compositing it into an intermediate buffer first,
+//! saves a full copy per frame and usually a significant amount of power. Whether a buffer
+//! can be presented like that depends on the buffer itself and on the capabilities of the target:
+//!
+//! - the buffer has to be a dmabuf in a format and with a modifier the target supports,
+//! - the buffer transform has to be supported by the target,
+//! - the buffer may not be cropped or scaled, unless the target can do so,
+//! - the element may not be drawn with an alpha value, unless the target supports blending it,
+//! - and for fullscreen scan-out the buffer has to be opaque and not obscured by other elements.
+//!
+//! The [`ScanoutEvaluator`] checks these conditions for a [`ScanoutTarget`] before rendering a frame
+//! and keeps [`ScanoutStats`] about why elements were rejected, which can be exposed as telemetry
+//! or used to decide on sending scan-out tranches with dmabuf feedback.
+//!
+//! On DRM the [`ScanoutTarget`] is derived from a plane, see `ScanoutTarget::from_plane`, and
+//! the outcome of an evaluation is applied by passing `ScanoutEvaluator::frame_flags` to
+//! `DrmCompositor::render_frame`, which performs the actual plane assignment.
+//!
+//! Nested compositors on Windows render through OpenGL and can not hand client buffers to DXGI
+//! directly. Instead DWM scans out the whole window with independent flip, once it covers a
+//! display and is opaque, see `backend::win32::fullscreen`. A fullscreen evaluation can be used
+//! to decide when to switch the window into borderless fullscreen.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{element::solid::SolidColorRenderElement, test::DummyRenderer};
+//! # use smithay::utils::{Physical, Rectangle};
+//! use smithay::backend::renderer::element::scanout::{ScanoutEvaluator, ScanoutTarget};
+//!
+//! # let mut renderer = DummyRenderer::default();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! # let formats = Default::default();
+//! let output_geometry = Rectangle::<i32, Physical>::from_size((1920, 1080).into());
+//! let mut evaluator = ScanoutEvaluator::new(ScanoutTarget::new(formats));
+//!
+//! match evaluator.evaluate_fullscreen(&mut renderer, &elements, 1.0, output_geometry) {
+//!     Ok(candidate) => println!("{:?} can be scanned out", candidate.id),
+//!     Err(reason) => println!("composing the output: {}", reason),
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use tracing::{debug, trace};
+
+#[cfg(feature = "wayland_frontend")]
+use crate::backend::allocator::Buffer as _;
+use crate::{
+    backend::{
+        allocator::{
+            format::{has_alpha, FormatSet},
+            Format, Fourcc,
+        },
+        renderer::Renderer,
+    },
+    utils::{Buffer as BufferCoords, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{
+    Id, Kind, RenderElement, RenderElementPresentationState, RenderElementStates, RenderingReason,
+    UnderlyingStorage,
+};
+
+/// Reason why an element can not be scanned out directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ScanoutRejection {
+    /// There was no element to scan out
+    #[error("no element to scan out")]
+    NoElement,
+    /// The element is not backed by a client buffer
+    #[error("the element provides no underlying storage")]
+    NoUnderlyingStorage,
+    /// The buffer of the element is not a dmabuf
+    #[error("the buffer is not a dmabuf")]
+    NotDmabuf,
+    /// The element does not cover the whole output
+    #[error("the element does not cover the output")]
+    NotFullscreen,
+    /// Other elements are drawn on top of the element
+    #[error("the element is obscured by other elements")]
+    Obscured,
+    /// The target does not support the format of the buffer
+    #[error("the format {0:?} is not supported")]
+    FormatUnsupported(Fourcc),
+    /// The target does not support the modifier of the buffer
+    #[error("the modifier {:?} is not supported for {:?}", .0.modifier, .0.code)]
+    ModifierUnsupported(Format),
+    /// The target does not support the buffer transform
+    #[error("the transform {0:?} is not supported")]
+    Transform(Transform),
+    /// The buffer is cropped or scaled and the target can not do so
+    #[error("the buffer is cropped or scaled")]
+    Viewport,
+    /// The element is drawn with an alpha value and the target can not blend it
+    #[error("the element is drawn with an alpha value")]
+    Alpha,
+    /// The buffer is not opaque and nothing can be blended below it
+    #[error("the buffer is not opaque")]
+    Translucent,
+}
+
+/// Capabilities of the target of a direct scan-out
+#[derive(Debug, Clone)]
+pub struct ScanoutTarget {
+    formats: FormatSet,
+    transforms: Vec<Transform>,
+    scaling: bool,
+    alpha: bool,
+}
+
+impl ScanoutTarget {
+    /// Create a target accepting buffers in the given formats
+    ///
+    /// The target initially only supports untransformed, unscaled and fully opaque buffers.
+    pub fn new(formats: FormatSet) -> Self {
+        ScanoutTarget {
+            formats,
+            transforms: vec![Transform::Normal],
+            scaling: false,
+            alpha: false,
+        }
+    }
+
+    /// Create a target for a DRM plane
+    #[cfg(feature = "backend_drm")]
+    pub fn from_plane(plane: &crate::backend::drm::PlaneInfo) -> Self {
+        Self::new(plane.formats.clone())
+    }
+
+    /// Set the buffer transforms the target supports
+    pub fn with_transforms(mut self, transforms: impl IntoIterator<Item = Transform>) -> Self {
+        self.transforms = transforms.into_iter().collect();
+        self
+    }
+
+    /// Set whether the target can crop and scale buffers
+    pub fn with_scaling(mut self, scaling: bool) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    /// Set whether the target can blend buffers with an alpha value
+    pub fn with_alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Formats supported by the target
+    pub fn formats(&self) -> &FormatSet {
+        &self.formats
+    }
+}
+
+/// Element found to be eligible for direct scan-out
+#[derive(Debug, Clone)]
+pub struct ScanoutCandidate {
+    /// Id of the element
+    pub id: Id,
+    /// Format of the buffer of the element
+    pub format: Format,
+    /// Geometry of the element on the output
+    pub geometry: Rectangle<i32, Physical>,
+    /// Whether the element covers the whole output
+    pub fullscreen: bool,
+}
+
+/// Statistics about the direct scan-out evaluations of an output
+#[derive(Debug, Clone, Default)]
+pub struct ScanoutStats {
+    /// Number of evaluations accepting an element
+    pub accepted: u64,
+    /// Number of evaluations rejecting an element, by reason
+    pub rejected: HashMap<ScanoutRejection, u64>,
+    /// Number of accepted elements, which were presented directly
+    pub presented: u64,
+    /// Number of accepted elements, which were rendered nevertheless, by reason
+    pub fallbacks: HashMap<Option<RenderingReason>, u64>,
+    /// Result of the last evaluation
+    pub last_rejection: Option<ScanoutRejection>,
+}
+
+impl ScanoutStats {
+    /// Total number of evaluations
+    pub fn evaluations(&self) -> u64 {
+        self.accepted + self.rejected.values().sum::<u64>()
+    }
+}
+
+/// Evaluates the direct scan-out eligibility of elements for a single output
+#[derive(Debug)]
+pub struct ScanoutEvaluator {
+    target: ScanoutTarget,
+    candidate: Option<ScanoutCandidate>,
+    stats: ScanoutStats,
+}
+
+impl ScanoutEvaluator {
+    /// Create a new evaluator for the given target
+    pub fn new(target: ScanoutTarget) -> Self {
+        ScanoutEvaluator {
+            target,
+            candidate: None,
+            stats: ScanoutStats::default(),
+        }
+    }
+
+    /// The target of this evaluator
+    pub fn target(&self) -> &ScanoutTarget {
+        &self.target
+    }
+
+    /// Replace the target, e.g. after a mode change
+    pub fn set_target(&mut self, target: ScanoutTarget) {
+        self.target = target;
+        self.candidate = None;
+    }
+
+    /// The candidate of the last evaluation, if it was accepted
+    pub fn candidate(&self) -> Option<&ScanoutCandidate> {
+        self.candidate.as_ref()
+    }
+
+    /// Statistics of the evaluations so far
+    pub fn stats(&self) -> &ScanoutStats {
+        &self.stats
+    }
+
+    /// Reset the statistics
+    pub fn reset_stats(&mut self) {
+        self.stats = ScanoutStats::default();
+    }
+
+    /// Evaluate whether the topmost element covering the output can be scanned out directly,
+    /// replacing composition of the output entirely
+    ///
+    /// The elements are expected front to back, like they are passed to the damage tracker.
+    /// Cursor elements on top are ignored, as they are expected to be placed on a cursor plane.
+    pub fn evaluate_fullscreen<R, E>(
+        &mut self,
+        renderer: &mut R,
+        elements: &[E],
+        scale: impl Into<Scale<f64>>,
+        output_geometry: Rectangle<i32, Physical>,
+    ) -> Result<ScanoutCandidate, ScanoutRejection>
+    where
+        R: Renderer,
+        E: RenderElement<R>,
+    {
+        let scale = scale.into();
+        let res = fullscreen_element(elements, scale, output_geometry)
+            .and_then(|element| check_element(&self.target, renderer, element, scale, output_geometry, true));
+        self.record(res)
+    }
+
+    /// Evaluate whether a single element can be scanned out directly, e.g. on an overlay plane
+    pub fn evaluate<R, E>(
+        &mut self,
+        renderer: &mut R,
+        element: &E,
+        scale: impl Into<Scale<f64>>,
+        output_geometry: Rectangle<i32, Physical>,
+    ) -> Result<ScanoutCandidate, ScanoutRejection>
+    where
+        R: Renderer,
+        E: RenderElement<R>,
+    {
+        let res = check_element(
+            &self.target,
+            renderer,
+            element,
+            scale.into(),
+            output_geometry,
+            false,
+        );
+        self.record(res)
+    }
+
+    /// Record the states of a rendered frame
+    ///
+    /// Updates the statistics with whether the last accepted candidate was actually presented
+    /// directly, or why it was rendered instead.
+    pub fn record_states(&mut self, states: &RenderElementStates) {
+        let Some(candidate) = self.candidate.as_ref() else {
+            return;
+        };
+        match states
+            .element_render_state(candidate.id.clone())
+            .map(|state| state.presentation_state)
+        {
+            Some(RenderElementPresentationState::ZeroCopy) => self.stats.presented += 1,
+            Some(RenderElementPresentationState::Rendering { reason }) => {
+                trace!(id = ?candidate.id, ?reason, "scan-out candidate was rendered");
+                *self.stats.fallbacks.entry(reason).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Frame flags for the [`DrmCompositor`](crate::backend::drm::compositor::DrmCompositor)
+    /// applying the last evaluation
+    ///
+    /// Allows scanning out the fullscreen candidate on the primary plane regardless of its format.
+    #[cfg(feature = "backend_drm")]
+    pub fn frame_flags(&self) -> crate::backend::drm::compositor::FrameFlags {
+        use crate::backend::drm::compositor::FrameFlags;
+
+        match self.candidate.as_ref() {
+            Some(candidate) if candidate.fullscreen => {
+                FrameFlags::DEFAULT | FrameFlags::ALLOW_PRIMARY_PLANE_SCANOUT_ANY
+            }
+            _ => FrameFlags::DEFAULT,
+        }
+    }
+
+    fn record(
+        &mut self,
+        res: Result<ScanoutCandidate, ScanoutRejection>,
+    ) -> Result<ScanoutCandidate, ScanoutRejection> {
+        match &res {
+            Ok(candidate) => {
+                if self.candidate.as_ref().map(|c| &c.id) != Some(&candidate.id) {
+                    debug!(id = ?candidate.id, format = ?candidate.format, "element eligible for direct scan-out");
+                }
+                self.stats.accepted += 1;
+                self.stats.last_rejection = None;
+                self.candidate = Some(candidate.clone());
+            }
+            Err(reason) => {
+                if self.stats.last_rejection != Some(*reason) {
+                    debug!(%reason, "direct scan-out rejected");
+                }
+                *self.stats.rejected.entry(*reason).or_default() += 1;
+                self.stats.last_rejection = Some(*reason);
+                self.candidate = None;
+            }
+        }
+        res
+    }
+}
+
+fn fullscreen_element<R, E>(
+    elements: &[E],
+    scale: Scale<f64>,
+    output_geometry: Rectangle<i32, Physical>,
+) -> Result<&E, ScanoutRejection>
+where
+    R: Renderer,
+    E: RenderElement<R>,
+{
+    let mut visible = elements
+        .iter()
+        .filter(|element| element.geometry(scale).overlaps(output_geometry))
+        .peekable();
+    if visible.peek().is_none() {
+        return Err(ScanoutRejection::NoElement);
+    }
+
+    let mut obscured = false;
+    for element in visible {
+        if element.geometry(scale).contains_rect(output_geometry) {
+            return if obscured {
+                Err(ScanoutRejection::Obscured)
+            } else {
+                Ok(element)
+            };
+        }
+        obscured |= element.kind() != Kind::Cursor;
+    }
+    Err(ScanoutRejection::NotFullscreen)
+}
+
+fn check_element<R, E>(
+    target: &ScanoutTarget,
+    renderer: &mut R,
+    element: &E,
+    scale: Scale<f64>,
+    output_geometry: Rectangle<i32, Physical>,
+    fullscreen: bool,
+) -> Result<ScanoutCandidate, ScanoutRejection>
+where
+    R: Renderer,
+    E: RenderElement<R>,
+{
+    let geometry = element.geometry(scale);
+    if fullscreen && !geometry.contains_rect(output_geometry) {
+        return Err(ScanoutRejection::NotFullscreen);
+    }
+
+    let storage = element
+        .underlying_storage(renderer)
+        .ok_or(ScanoutRejection::NoUnderlyingStorage)?;
+    let (format, buffer_size) = storage_format(&storage)?;
+
+    if !target.formats.iter().any(|f| f.code == format.code) {
+        return Err(ScanoutRejection::FormatUnsupported(format.code));
+    }
+    if !target.formats.contains(&format) {
+        return Err(ScanoutRejection::ModifierUnsupported(format));
+    }
+
+    let transform = element.transform();
+    if !target.transforms.contains(&transform) {
+        return Err(ScanoutRejection::Transform(transform));
+    }
+
+    if !target.scaling {
+        let src = element.src();
+        let src_size = transform.transform_size(src.size);
+        let unscaled = src.loc == Point::from((0.0, 0.0))
+            && src.size == buffer_size.to_f64()
+            && src_size.w == geometry.size.w as f64
+            && src_size.h == geometry.size.h as f64;
+        if !unscaled {
+            return Err(ScanoutRejection::Viewport);
+        }
+    }
+
+    if !target.alpha && element.alpha() < 1.0 {
+        return Err(ScanoutRejection::Alpha);
+    }
+
+    if fullscreen && has_alpha(format.code) {
+        let opaque_regions = element.opaque_regions(scale);
+        let covered = Rectangle::from_size(geometry.size)
+            .subtract_rects(opaque_regions.iter().copied())
+            .is_empty();
+        if !covered {
+            return Err(ScanoutRejection::Translucent);
+        }
+    }
+
+    Ok(ScanoutCandidate {
+        id: element.id().clone(),
+        format,
+        geometry,
+        fullscreen,
+    })
+}
+
+fn storage_format(
+    storage: &UnderlyingStorage<'_>,
+) -> Result<(Format, Size<i32, BufferCoords>), ScanoutRejection> {
+    match storage {
+        #[cfg(feature = "wayland_frontend")]
+        UnderlyingStorage::Wayland(buffer) => crate::wayland::dmabuf::get_dmabuf(buffer)
+            .map(|dmabuf| (dmabuf.format(), dmabuf.size()))
+            .map_err(|_| ScanoutRejection::NotDmabuf),
+        UnderlyingStorage::Memory(_) => Err(ScanoutRejection::NotDmabuf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{
+        element::solid::SolidColorRenderElement, test::DummyRenderer, utils::CommitCounter, Color32F,
+    };
+
+    fn solid(geometry: Rectangle<i32, Physical>, kind: Kind) -> SolidColorRenderElement {
+        SolidColorRenderElement::new(
+            Id::new(),
+            geometry,
+            CommitCounter::default(),
+            Color32F::BLACK,
+            kind,
+        )
+    }
+
+    #[test]
+    fn obscured_elements_are_rejected() {
+        let mut renderer = DummyRenderer::default();
+        let output = Rectangle::from_size((100, 100).into());
+        let mut evaluator = ScanoutEvaluator::new(ScanoutTarget::new(FormatSet::default()));
+
+        let elements = [
+            solid(
+                Rectangle::new((10, 10).into(), (10, 10).into()),
+                Kind::Unspecified,
+            ),
+            solid(output, Kind::Unspecified),
+        ];
+        let res = evaluator.evaluate_fullscreen(&mut renderer, &elements, 1.0, output);
+        assert_eq!(res.unwrap_err(), ScanoutRejection::Obscured);
+
+        // cursors are placed on their own plane
+        let elements = [
+            solid(Rectangle::new((10, 10).into(), (10, 10).into()), Kind::Cursor),
+            solid(output, Kind::Unspecified),
+        ];
+        let res = evaluator.evaluate_fullscreen(&mut renderer, &elements, 1.0, output);
+        assert_eq!(res.unwrap_err(), ScanoutRejection::NoUnderlyingStorage);
+
+        let res =
+            evaluator.evaluate_fullscreen::<_, SolidColorRenderElement>(&mut renderer, &[], 1.0, output);
+        assert_eq!(res.unwrap_err(), ScanoutRejection::NoElement);
+    }
+
+    #[test]
+    fn rejections_are_counted() {
+        let mut renderer = DummyRenderer::default();
+        let output = Rectangle::from_size((100, 100).into());
+        let mut evaluator = ScanoutEvaluator::new(ScanoutTarget::new(FormatSet::default()));
+
+        let element = solid(Rectangle::from_size((50, 50).into()), Kind::Unspecified);
+        for _ in 0..3 {
+            let _ = evaluator.evaluate(&mut renderer, &element, 1.0, output);
+        }
+        let _ = evaluator.evaluate_fullscreen(&mut renderer, &[element], 1.0, output);
+
+        let stats = evaluator.stats();
+        assert_eq!(stats.evaluations(), 4);
+        assert_eq!(stats.accepted, 0);
+        assert_eq!(stats.rejected[&ScanoutRejection::NoUnderlyingStorage], 3);
+        assert_eq!(stats.rejected[&ScanoutRejection::NotFullscreen], 1);
+        assert_eq!(stats.last_rejection, Some(ScanoutRejection::NotFullscreen));
+        assert!(evaluator.candidate().is_none());
+    }
+}
@@ -73,7 +73,7 @@ use crate::{
             Buffer, DamageSet, DamageSnapshot, OpaqueRegions, RendererSurfaceState,
             RendererSurfaceStateUserData, SurfaceView,
         },
-        Color32F, Frame, ImportAll, Renderer, Texture,
+        ClipRegion, Color32F, Frame, ImportAll, Renderer, Texture,
     },
     utils::{Buffer as BufferCoords, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
     wayland::{
@@ -374,20 +374,14 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
             .unwrap_or_else(|| DamageSet::from_slice(&[Rectangle::from_size(self.buffer_dimensions)]))
             .iter()
             .filter_map(|rect| {
-                rect.to_f64()
-                    // first bring the damage into logical space
-                    // Note: We use f64 for this as the damage could
-                    // be not dividable by the buffer scale without
-                    // a rest
-                    .to_logical(
-                        self.buffer_scale as f64,
+                // first bring the damage into logical space, cropped and scaled by the surface view
+                self.view
+                    .buffer_damage_to_surface(
+                        *rect,
+                        self.buffer_scale,
                         self.buffer_transform,
-                        &self.buffer_dimensions.to_f64(),
+                        self.buffer_dimensions,
                     )
-                    // then crop by the surface view (viewporter for example could define a src rect)
-                    .intersection(self.view.src)
-                    // move and scale the cropped rect (viewporter could define a dst size)
-                    .map(|rect| self.view.rect_to_global(rect).to_i32_up::<i32>())
                     // now bring the damage to physical space
                     .map(|rect| {
                         // We calculate the scale between to rounded
@@ -461,4 +455,33 @@ where
             WaylandSurfaceTexture::SolidColor(color) => frame.draw_solid(dst, damage, color * self.alpha),
         }
     }
+
+    #[instrument(level = "trace", skip(frame))]
+    #[profiling::function]
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        match self.texture {
+            WaylandSurfaceTexture::Texture(ref texture) => frame.render_texture_clipped(
+                texture,
+                src,
+                dst,
+                damage,
+                opaque_regions,
+                self.buffer_transform,
+                self.alpha,
+                clip,
+            ),
+            WaylandSurfaceTexture::SolidColor(color) => {
+                let damage = clip.clip_damage_analytic(dst, damage);
+                frame.draw_solid(dst, &damage, color * self.alpha)
+            }
+        }
+    }
 }
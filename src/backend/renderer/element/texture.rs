@@ -203,7 +203,7 @@ use crate::{
         allocator::Fourcc,
         renderer::{
             utils::{DamageBag, DamageSet, DamageSnapshot, OpaqueRegions},
-            ContextId, Frame, ImportMem, Renderer, Texture,
+            ClipRegion, ContextId, Frame, ImportMem, Renderer, Texture,
         },
     },
     utils::{Buffer, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
@@ -709,4 +709,31 @@ where
             self.alpha,
         )
     }
+    #[instrument(level = "trace", skip(self, frame))]
+    #[profiling::function]
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        if frame.context_id() != self.context_id {
+            warn!("trying to render texture from different renderer context");
+            return Ok(());
+        }
+
+        frame.render_texture_clipped(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            self.transform,
+            self.alpha,
+            clip,
+        )
+    }
 }
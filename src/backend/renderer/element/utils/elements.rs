@@ -4,7 +4,7 @@ use crate::{
     backend::renderer::{
         element::{AsRenderElements, Element, Id, Kind, RenderElement, UnderlyingStorage},
         utils::{DamageSet, OpaqueRegions},
-        Renderer,
+        ClipRegion, CornerRadius, Renderer,
     },
     utils::{Buffer, Physical, Point, Rectangle, Scale},
 };
@@ -104,6 +104,19 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RescaleRenderElement
         self.element.draw(frame, src, dst, damage, opaque_regions)
     }
 
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        self.element
+            .draw_clipped(frame, src, dst, damage, opaque_regions, clip)
+    }
+
     #[inline]
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
         self.element.underlying_storage(renderer)
@@ -288,12 +301,183 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for CropRenderElement<E>
         self.element.draw(frame, src, dst, damage, opaque_regions)
     }
 
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        self.element
+            .draw_clipped(frame, src, dst, damage, opaque_regions, clip)
+    }
+
     #[inline]
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
         self.element.underlying_storage(renderer)
     }
 }
 
+/// A element that clips another element to a [`ClipRegion`]
+///
+/// The clip region is relative to the geometry of the element, so it moves together with the
+/// element. E.g. to round the corners of a window pass a region with the size of its geometry at
+/// the origin together with the desired [`CornerRadius`], see
+/// [`with_rounded_corners`](ClippedRenderElement::with_rounded_corners).
+///
+/// The clip region is folded into the commit of the element, changing it damages the whole element.
+/// Clipped elements never expose their underlying storage, as scanning them out directly
+/// would bypass the clipping.
+#[derive(Debug)]
+pub struct ClippedRenderElement<E> {
+    element: E,
+    clip: ClipRegion,
+}
+
+impl<E: Element> ClippedRenderElement<E> {
+    /// Create a clipped element for an existing element
+    ///
+    /// The clip region is relative to the geometry of the element.
+    pub fn from_element(element: E, clip: ClipRegion) -> Self {
+        ClippedRenderElement { element, clip }
+    }
+
+    /// Create an element with rounded corners for an existing element
+    ///
+    /// The corners are rounded relative to the size of the element at the given scale.
+    pub fn with_rounded_corners(
+        element: E,
+        scale: impl Into<Scale<f64>>,
+        radius: impl Into<CornerRadius>,
+    ) -> Self {
+        let geometry = element.geometry(scale.into());
+        ClippedRenderElement {
+            element,
+            clip: ClipRegion::rounded(Rectangle::from_size(geometry.size), radius),
+        }
+    }
+
+    /// The clip region of this element, relative to its geometry
+    pub fn clip(&self) -> &ClipRegion {
+        &self.clip
+    }
+}
+
+impl<E: Element> Element for ClippedRenderElement<E> {
+    fn id(&self) -> &Id {
+        self.element.id()
+    }
+
+    fn current_commit(&self) -> crate::backend::renderer::utils::CommitCounter {
+        self.element.current_commit().offset(self.clip.fingerprint())
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.element.src()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.element.location(scale)
+    }
+
+    fn transform(&self) -> crate::utils::Transform {
+        self.element.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<crate::backend::renderer::utils::CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        // a commit of another clip region maps to an unrelated commit of the element,
+        // which results in full damage
+        let commit = commit.map(|commit| commit.unoffset(self.clip.fingerprint()));
+        let size = self.element.geometry(scale).size;
+        let damage = self.element.damage_since(scale, commit);
+        self.clip
+            .clip_damage(Rectangle::from_size(size), &damage)
+            .into_iter()
+            .collect()
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        let size = self.element.geometry(scale).size;
+        let opaque_regions = self.element.opaque_regions(scale);
+        self.clip
+            .clip_opaque_regions(Rectangle::from_size(size), &opaque_regions)
+            .into_iter()
+            .collect()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.element.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.element.kind()
+    }
+}
+
+impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for ClippedRenderElement<E> {
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        self.element.draw_clipped(
+            frame,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            &self.clip.translate(dst.loc),
+        )
+    }
+
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        // only the innermost clip region is applied per pixel
+        let damage = clip.clip_damage_analytic(dst, damage);
+        if damage.is_empty() {
+            return Ok(());
+        }
+        let opaque_regions = clip.clip_opaque_regions(dst, opaque_regions);
+        self.element.draw_clipped(
+            frame,
+            src,
+            dst,
+            &damage,
+            &opaque_regions,
+            &self.clip.translate(dst.loc),
+        )
+    }
+
+    #[inline]
+    fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        if self.clip.is_unclipped() {
+            self.element.underlying_storage(renderer)
+        } else {
+            None
+        }
+    }
+}
+
 /// Defines how the location parameter should apply in [`RelocateRenderElement::from_element`]
 #[derive(Debug, Copy, Clone)]
 pub enum Relocate {
@@ -390,6 +574,19 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RelocateRenderElemen
         self.element.draw(frame, src, dst, damage, opaque_regions)
     }
 
+    fn draw_clipped(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        clip: &ClipRegion,
+    ) -> Result<(), R::Error> {
+        self.element
+            .draw_clipped(frame, src, dst, damage, opaque_regions, clip)
+    }
+
     #[inline]
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
         self.element.underlying_storage(renderer)
@@ -568,3 +765,65 @@ where
         .map(move |e| RelocateRenderElement::from_element(e, offset, Relocate::Relative))
         .filter_map(move |e| CropRenderElement::from_element(e, scale, constrain))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ClippedRenderElement;
+    use crate::{
+        backend::renderer::{
+            element::{solid::SolidColorRenderElement, Element, Id, Kind},
+            ClipRegion,
+        },
+        utils::{Physical, Rectangle},
+    };
+
+    fn solid(id: &Id, geometry: Rectangle<i32, Physical>) -> SolidColorRenderElement {
+        SolidColorRenderElement::new(id.clone(), geometry, 0, [1.0, 0.0, 0.0, 1.0], Kind::Unspecified)
+    }
+
+    #[test]
+    fn clip_is_relative_to_the_element() {
+        let id = Id::new();
+        let clip = ClipRegion::from_rects([Rectangle::from_size((10, 10).into())]);
+        for loc in [(0, 0), (50, 70)] {
+            let element = ClippedRenderElement::from_element(
+                solid(&id, Rectangle::new(loc.into(), (100, 100).into())),
+                clip.clone(),
+            );
+            assert_eq!(
+                &*element.damage_since(1.0.into(), None),
+                &[Rectangle::from_size((10, 10).into())]
+            );
+            assert_eq!(
+                &*element.opaque_regions(1.0.into()),
+                &[Rectangle::from_size((10, 10).into())]
+            );
+        }
+    }
+
+    #[test]
+    fn changing_the_clip_damages_the_element() {
+        let id = Id::new();
+        let geometry = Rectangle::from_size((100, 100).into());
+        let element = ClippedRenderElement::with_rounded_corners(solid(&id, geometry), 1.0, 10.0);
+        let commit = element.current_commit();
+        assert_eq!(element.id(), &id);
+
+        // same clip, no damage
+        let element = ClippedRenderElement::with_rounded_corners(solid(&id, geometry), 1.0, 10.0);
+        assert_eq!(element.current_commit(), commit);
+        assert!(element.damage_since(1.0.into(), Some(commit)).is_empty());
+
+        // another radius, the element is damaged up to its bounds
+        let element = ClippedRenderElement::with_rounded_corners(solid(&id, geometry), 1.0, 20.0);
+        assert_ne!(element.current_commit(), commit);
+        assert_eq!(
+            &*element.damage_since(1.0.into(), Some(commit)),
+            &[Rectangle::from_size((100, 100).into())]
+        );
+
+        // unclipped elements keep the commit of the inner element
+        let element = ClippedRenderElement::from_element(solid(&id, geometry), ClipRegion::unclipped());
+        assert_eq!(element.current_commit(), solid(&id, geometry).current_commit());
+    }
+}
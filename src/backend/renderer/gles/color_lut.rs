@@ -0,0 +1,139 @@
+use super::*;
+use crate::output::ColorLut;
+
+const COLOR_LUT_SHADER: &str = r#"#version 100
+
+//_DEFINES_
+
+precision mediump float;
+uniform sampler2D tex;
+uniform sampler2D lut;
+uniform float lut_size;
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// the 3D table is packed into a 2D texture with the blue slices next to each other
+vec3 lookup(vec3 color) {
+    float scale = lut_size - 1.0;
+    float b = color.b * scale;
+    float b0 = floor(b);
+    float b1 = min(b0 + 1.0, scale);
+    vec2 rg = (color.rg * scale + 0.5) / vec2(lut_size * lut_size, lut_size);
+    vec3 c0 = texture2D(lut, rg + vec2(b0 / lut_size, 0.0)).rgb;
+    vec3 c1 = texture2D(lut, rg + vec2(b1 / lut_size, 0.0)).rgb;
+    return mix(c0, c1, b - b0);
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    if (color.a > 0.0)
+        color.rgb = lookup(color.rgb / color.a) * color.a;
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
+"#;
+
+/// 3D color lookup table uploaded to the GPU
+///
+/// Created with [`GlesRenderer::create_color_lut`] and applied with
+/// [`GlesFrame::render_texture_with_color_lut`].
+#[derive(Debug, Clone)]
+pub struct GlesColorLut {
+    texture: GlesTexture,
+    program: GlesTexProgram,
+    size: usize,
+}
+
+impl GlesRenderer {
+    /// Upload a color lookup table, e.g. computed from an [`IccProfile`](crate::output::IccProfile)
+    pub fn create_color_lut(&mut self, lut: &ColorLut) -> Result<GlesColorLut, GlesError> {
+        let size = lut.size();
+        let texture = self.import_memory(
+            &lut.to_rgba8_image(),
+            Fourcc::Abgr8888,
+            ((size * size) as i32, size as i32).into(),
+            false,
+        )?;
+        let program = self.compile_custom_texture_shader(
+            COLOR_LUT_SHADER,
+            &[
+                UniformName::new("lut", UniformType::_1i),
+                UniformName::new("lut_size", UniformType::_1f),
+            ],
+        )?;
+        Ok(GlesColorLut {
+            texture,
+            program,
+            size,
+        })
+    }
+}
+
+impl GlesFrame<'_, '_> {
+    /// Render a texture like [`GlesFrame::render_texture_from_to`], converting its colors with the
+    /// given lookup table
+    ///
+    /// Usually the whole output is rendered into an offscreen texture first, which is then
+    /// rendered to the actual framebuffer with this function.
+    #[profiling::function]
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_texture_with_color_lut(
+        &mut self,
+        texture: &GlesTexture,
+        lut: &GlesColorLut,
+        src: Rectangle<f64, BufferCoord>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        transform: Transform,
+    ) -> Result<(), GlesError> {
+        let gl = &self.renderer.gl;
+        unsafe {
+            gl.ActiveTexture(ffi::TEXTURE1);
+            gl.BindTexture(ffi::TEXTURE_2D, lut.texture.tex_id());
+            // interpolate between samples of the same blue slice in hardware
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+            gl.ActiveTexture(ffi::TEXTURE0);
+        }
+
+        let result = self.render_texture_from_to(
+            texture,
+            src,
+            dest,
+            damage,
+            &[],
+            transform,
+            1.0,
+            Some(&lut.program),
+            &[
+                Uniform::new("lut", 1i32),
+                Uniform::new("lut_size", lut.size as f32),
+            ],
+        );
+
+        let gl = &self.renderer.gl;
+        unsafe {
+            gl.ActiveTexture(ffi::TEXTURE1);
+            gl.BindTexture(ffi::TEXTURE_2D, 0);
+            gl.ActiveTexture(ffi::TEXTURE0);
+        }
+        result
+    }
+}
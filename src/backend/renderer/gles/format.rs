@@ -25,6 +25,21 @@ pub const fn fourcc_to_gl_formats(value: Fourcc) -> Option<(GLenum, GLenum, GLen
     }
 }
 
+/// Returns the fourcc to upload a format as and the texture swizzle sampling it correctly
+///
+/// Used for formats without a matching GL format, the swizzle is ordered red, green, blue, alpha
+/// as in `GL_TEXTURE_SWIZZLE_RGBA`. Requires [`Capability::TextureSwizzle`](super::Capability::TextureSwizzle).
+pub const fn fourcc_to_gl_swizzle(value: Fourcc) -> Option<(Fourcc, [GLenum; 4])> {
+    // uploaded as RGBA, so the texture channels hold the bytes of a pixel in memory order
+    match value {
+        Fourcc::Rgba8888 => Some((Fourcc::Abgr8888, [ffi::ALPHA, ffi::BLUE, ffi::GREEN, ffi::RED])),
+        Fourcc::Rgbx8888 => Some((Fourcc::Xbgr8888, [ffi::ALPHA, ffi::BLUE, ffi::GREEN, ffi::ONE])),
+        Fourcc::Bgra8888 => Some((Fourcc::Abgr8888, [ffi::GREEN, ffi::BLUE, ffi::ALPHA, ffi::RED])),
+        Fourcc::Bgrx8888 => Some((Fourcc::Xbgr8888, [ffi::GREEN, ffi::BLUE, ffi::ALPHA, ffi::ONE])),
+        _ => None,
+    }
+}
+
 /// Returns the fourcc for a given internal format
 pub const fn gl_internal_format_to_fourcc(format: GLenum) -> Option<Fourcc> {
     match format {
@@ -19,25 +19,30 @@ use std::{
 };
 use tracing::{debug, error, info, info_span, instrument, span, span::EnteredSpan, trace, warn, Level};
 
+mod color_lut;
 pub mod element;
 mod error;
 pub mod format;
 mod shaders;
 mod texture;
+mod timer;
 mod uniform;
 mod version;
 
+pub use color_lut::GlesColorLut;
 pub use error::*;
 use format::*;
 pub use shaders::*;
 pub use texture::*;
+pub use timer::*;
 pub use uniform::*;
 
 use self::version::GlVersion;
 
 use super::{
-    sync::SyncPoint, Bind, Blit, BlitFrame, Color32F, ContextId, DebugFlags, ExportMem, Frame, ImportDma,
-    ImportMem, Offscreen, Renderer, RendererSuper, Texture, TextureFilter, TextureMapping,
+    sync::SyncPoint, Bind, Blit, BlitFrame, ClipRegion, Color32F, ContextId, DebugFlags, ExportMem, Frame,
+    FrameArena, ImportDma, ImportMem, ImportPath, MemFormatConversion, Offscreen, Renderer, RendererSuper,
+    Texture, TextureFilter, TextureMapping,
 };
 use crate::{
     backend::{
@@ -46,6 +51,7 @@ use crate::{
             format::{get_bpp, get_opaque, has_alpha, FormatSet},
             Buffer, Format, Fourcc,
         },
+        capabilities::{Capabilities, QueryCapabilities},
         egl::{
             display::{EGLDisplay, PixelFormat},
             fence::EGLFence,
@@ -80,6 +86,7 @@ enum CleanupResource {
     Mapping(ffi::types::GLuint, *const std::ffi::c_void),
     Program(ffi::types::GLuint),
     Sync(ffi::types::GLsync),
+    Query(ffi::types::GLuint),
 }
 unsafe impl Send for CleanupResource {}
 
@@ -310,6 +317,10 @@ pub enum Capability {
     ExportFence,
     /// GlesRenderer supports GL debug
     Debug,
+    /// GlesRenderer supports GPU timer queries
+    TimerQuery,
+    /// GlesRenderer supports texture swizzles, used to import additional memory formats
+    TextureSwizzle,
 }
 
 /// GL resources need to be destroyed with a context active on the current thread,
@@ -375,6 +386,9 @@ impl GlesCleanup {
                 CleanupResource::Sync(sync) => unsafe {
                     gl.DeleteSync(sync);
                 },
+                CleanupResource::Query(query) => unsafe {
+                    gl.DeleteQueriesEXT(1, &query);
+                },
             }
         }
     }
@@ -402,6 +416,7 @@ pub struct GlesRenderer {
     // shaders
     tex_program: GlesTexProgram,
     solid_program: GlesSolidProgram,
+    rounded_program: Option<GlesTexProgram>,
 
     // caches
     buffers: Vec<GlesBuffer>,
@@ -410,6 +425,7 @@ pub struct GlesRenderer {
     vertices: Vec<f32>,
     non_opaque_damage: Vec<Rectangle<i32, Physical>>,
     opaque_damage: Vec<Rectangle<i32, Physical>>,
+    arena: FrameArena,
 
     // markers
     _not_send: PhantomData<*mut ()>,
@@ -543,6 +559,8 @@ impl GlesRenderer {
             debug!("10-bit formats are supported");
             capabilities.push(Capability::Fencing);
             debug!("Fencing is supported");
+            capabilities.push(Capability::TextureSwizzle);
+            debug!("Texture swizzles are supported");
         }
 
         if exts.iter().any(|ext| ext == "GL_OES_EGL_sync") {
@@ -555,6 +573,11 @@ impl GlesRenderer {
             debug!("GL Debug is supported");
         }
 
+        if exts.iter().any(|ext| ext == "GL_EXT_disjoint_timer_query") {
+            capabilities.push(Capability::TimerQuery);
+            debug!("GPU timer queries are supported");
+        }
+
         Ok(capabilities)
     }
 
@@ -617,12 +640,15 @@ impl GlesRenderer {
                 Capability::Instancing => {
                     GlesError::GLExtensionNotSupported(&["GL_EXT_instanced_arrays", "GL_EXT_draw_instanced"])
                 }
-                Capability::Blit | Capability::_10Bit | Capability::Fencing => {
+                Capability::Blit | Capability::_10Bit | Capability::Fencing | Capability::TextureSwizzle => {
                     GlesError::GLVersionNotSupported(version::GLES_3_0)
                 }
                 Capability::Renderbuffer => GlesError::GLExtensionNotSupported(&["GL_OES_rgb8_rgba8"]),
                 Capability::ExportFence => GlesError::GLExtensionNotSupported(&["GL_OES_EGL_sync"]),
                 Capability::Debug => GlesError::GLExtensionNotSupported(&["GL_KHR_debug"]),
+                Capability::TimerQuery => {
+                    GlesError::GLExtensionNotSupported(&["GL_EXT_disjoint_timer_query"])
+                }
             };
             return Err(err);
         };
@@ -734,6 +760,7 @@ impl GlesRenderer {
 
             tex_program,
             solid_program,
+            rounded_program: None,
             vbos,
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
@@ -743,6 +770,7 @@ impl GlesRenderer {
             vertices: Vec::with_capacity(6 * 16),
             non_opaque_damage: Vec::with_capacity(16),
             opaque_damage: Vec::with_capacity(16),
+            arena: FrameArena::new(),
 
             debug_flags: DebugFlags::empty(),
             _not_send: PhantomData,
@@ -783,7 +811,8 @@ impl GlesRenderer {
                 Capability::Blit, 
                 Capability::_10Bit,
                 Capability::Fencing,
-                Capability::Debug
+                Capability::Debug,
+                Capability::TextureSwizzle,
             ];
             (gl, gl_version, exts, capabilities, None)
         };
@@ -837,6 +866,7 @@ impl GlesRenderer {
 
             tex_program,
             solid_program,
+            rounded_program: None,
             vbos,
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
@@ -846,6 +876,7 @@ impl GlesRenderer {
             vertices: Vec::with_capacity(6 * 16),
             non_opaque_damage: Vec::with_capacity(16),
             opaque_damage: Vec::with_capacity(16),
+            arena: FrameArena::new(),
 
             debug_flags: DebugFlags::empty(),
             _not_send: PhantomData,
@@ -979,17 +1010,13 @@ impl ImportMemWl for GlesRenderer {
             let fourcc =
                 shm_format_to_fourcc(data.format).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
 
-            if self.gl_version.major >= 3 {
-                if !SUPPORTED_MEM_FORMATS_3.contains(&fourcc) {
-                    return Err(GlesError::UnsupportedWlPixelFormat(data.format));
-                }
-            } else if !SUPPORTED_MEM_FORMATS_2.contains(&fourcc) {
-                return Err(GlesError::UnsupportedWlPixelFormat(data.format));
-            }
+            let (upload_fourcc, swizzle) = self
+                .mem_upload_format(fourcc)
+                .ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
 
             let has_alpha = has_alpha(fourcc);
-            let (mut internal_format, read_format, type_) =
-                fourcc_to_gl_formats(fourcc).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
+            let (mut internal_format, read_format, type_) = fourcc_to_gl_formats(upload_fourcc)
+                .ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
             
             // PATCH: macOS Core Profile (3.3+) requires RGBA/RGBA8 internal format.
             // BGRA internal format is not supported (only BGRA *format* is supported for upload).
@@ -1056,6 +1083,10 @@ impl ImportMemWl for GlesRenderer {
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
                 self.gl
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                if self.capabilities.contains(&Capability::TextureSwizzle) {
+                    // the cached texture might have been used for another format before
+                    self.set_texture_swizzle(swizzle);
+                }
                 self.gl
                     .PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize as i32);
 
@@ -1126,6 +1157,46 @@ const SUPPORTED_MEM_FORMATS_3: &[Fourcc] = &[
     Fourcc::Abgr16161616f,
     Fourcc::Xbgr16161616f,
 ];
+/// Formats sampled through texture swizzles, requires [`Capability::TextureSwizzle`]
+const SWIZZLED_MEM_FORMATS: &[Fourcc] = &[
+    Fourcc::Rgba8888,
+    Fourcc::Rgbx8888,
+    Fourcc::Bgra8888,
+    Fourcc::Bgrx8888,
+];
+
+impl GlesRenderer {
+    /// Returns the format to upload memory of the given format as and the swizzle to sample it with
+    fn mem_upload_format(&self, format: Fourcc) -> Option<(Fourcc, Option<[ffi::types::GLenum; 4]>)> {
+        let native_formats = if self.gl_version.major >= 3 {
+            SUPPORTED_MEM_FORMATS_3
+        } else {
+            SUPPORTED_MEM_FORMATS_2
+        };
+        if native_formats.contains(&format) {
+            return Some((format, None));
+        }
+
+        if !self.capabilities.contains(&Capability::TextureSwizzle) {
+            return None;
+        }
+        fourcc_to_gl_swizzle(format).map(|(upload_format, swizzle)| (upload_format, Some(swizzle)))
+    }
+
+    /// Sets the swizzle of the texture bound to `TEXTURE_2D`, `None` resets it
+    unsafe fn set_texture_swizzle(&self, swizzle: Option<[ffi::types::GLenum; 4]>) {
+        let swizzle = swizzle.unwrap_or([ffi::RED, ffi::GREEN, ffi::BLUE, ffi::ALPHA]);
+        let params = [
+            ffi::TEXTURE_SWIZZLE_R,
+            ffi::TEXTURE_SWIZZLE_G,
+            ffi::TEXTURE_SWIZZLE_B,
+            ffi::TEXTURE_SWIZZLE_A,
+        ];
+        for (param, value) in params.into_iter().zip(swizzle) {
+            self.gl.TexParameteri(ffi::TEXTURE_2D, param, value as i32);
+        }
+    }
+}
 
 impl ImportMem for GlesRenderer {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
@@ -1144,17 +1215,13 @@ impl ImportMem for GlesRenderer {
             return Err(GlesError::UnexpectedSize);
         }
 
-        if self.gl_version.major >= 3 {
-            if !SUPPORTED_MEM_FORMATS_3.contains(&format) {
-                return Err(GlesError::UnsupportedPixelFormat(format));
-            }
-        } else if !SUPPORTED_MEM_FORMATS_2.contains(&format) {
-            return Err(GlesError::UnsupportedPixelFormat(format));
-        }
+        let (upload_format, swizzle) = self
+            .mem_upload_format(format)
+            .ok_or(GlesError::UnsupportedPixelFormat(format))?;
 
         let has_alpha = has_alpha(format);
         let (mut internal, format, layout) =
-            fourcc_to_gl_formats(format).expect("We check the format before");
+            fourcc_to_gl_formats(upload_format).expect("We check the format before");
             
         // PATCH: macOS Core Profile (3.3+) requires RGBA/RGBA8 internal format.
         #[cfg(target_os = "macos")]
@@ -1182,6 +1249,9 @@ impl ImportMem for GlesRenderer {
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
                 self.gl
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                if swizzle.is_some() {
+                    self.set_texture_swizzle(swizzle);
+                }
                 self.gl.TexImage2D(
                     ffi::TEXTURE_2D,
                     0,
@@ -1285,12 +1355,24 @@ impl ImportMem for GlesRenderer {
     }
 
     fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
+        let swizzled: &[Fourcc] = if self.capabilities.contains(&Capability::TextureSwizzle) {
+            SWIZZLED_MEM_FORMATS
+        } else {
+            &[]
+        };
         if self.gl_version.major >= 3 {
-            Box::new(SUPPORTED_MEM_FORMATS_3.iter().copied())
+            Box::new(SUPPORTED_MEM_FORMATS_3.iter().chain(swizzled).copied())
         } else {
-            Box::new(SUPPORTED_MEM_FORMATS_2.iter().copied())
+            Box::new(SUPPORTED_MEM_FORMATS_2.iter().chain(swizzled).copied())
         }
     }
+
+    fn mem_format_conversion(&self, format: Fourcc) -> Option<MemFormatConversion> {
+        self.mem_upload_format(format).map(|(_, swizzle)| match swizzle {
+            Some(_) => MemFormatConversion::GpuSwizzle,
+            None => MemFormatConversion::Native,
+        })
+    }
 }
 
 #[cfg(all(
@@ -1367,6 +1449,48 @@ impl ImportEgl for GlesRenderer {
     }
 }
 
+/// Reports [`Capabilities::HDR`] if the renderer can render into half-float framebuffers, as
+/// needed to composite scRGB or linear content before encoding it for the display. 10-bit
+/// formats alone are not enough.
+impl QueryCapabilities for GlesRenderer {
+    fn query_capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::empty();
+        if self.capabilities.contains(&Capability::Fencing) {
+            capabilities |= Capabilities::FENCES;
+        }
+        if self.extensions.iter().any(|ext| ext == "GL_OES_EGL_image")
+            && self.egl.dmabuf_texture_formats().iter().next().is_some()
+        {
+            capabilities |= Capabilities::DMABUF_IMPORT;
+        }
+        // rendering into float formats is core since GLES 3.2
+        if self.gl_version >= version::GLES_3_2
+            || self
+                .extensions
+                .iter()
+                .any(|ext| ext == "GL_EXT_color_buffer_float" || ext == "GL_EXT_color_buffer_half_float")
+        {
+            capabilities |= Capabilities::HDR;
+        }
+        capabilities
+    }
+}
+
+/// Suspending finishes all submitted commands of the context. The renderer does not own any
+/// swapchain, so resuming does nothing.
+impl crate::backend::power::PowerHooks for GlesRenderer {
+    type Error = GlesError;
+
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    fn suspend(&mut self) -> Result<(), GlesError> {
+        self.with_context(|gl| unsafe { gl.Finish() })
+    }
+
+    fn resume(&mut self) -> Result<(), GlesError> {
+        Ok(())
+    }
+}
+
 impl ImportDma for GlesRenderer {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
@@ -1416,6 +1540,17 @@ impl ImportDma for GlesRenderer {
     fn has_dmabuf_format(&self, format: Format) -> bool {
         self.egl.dmabuf_texture_formats().contains(&format)
     }
+
+    fn dmabuf_import_path(&self, format: Format) -> Option<ImportPath> {
+        // formats we can not render to are imported as external textures
+        if self.egl.dmabuf_render_formats().contains(&format) {
+            Some(ImportPath::Direct)
+        } else if self.egl.dmabuf_texture_formats().contains(&format) {
+            Some(ImportPath::ConvertShader)
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -2036,7 +2171,8 @@ impl GlesRenderer {
     /// - `DEBUG_FLAGS` see below
     ///
     /// They receive the following variables:
-    /// - *varying* v_coords `vec2` - contains the position from the vertex shader
+    /// - *varying* v_coords `vec2` - contains the texture coordinates from the vertex shader
+    /// - *varying* v_position `vec2` - contains the position in physical pixels relative to the destination rectangle
     /// - *uniform* size `vec2` - size of the viewport in pixels
     /// - *uniform* alpha `float` - for the alpha value passed by the renderer
     /// - *uniform* tint `float` - for the tint passed by the renderer (either 0.0 or 1.0) - only if `DEBUG_FLAGS` was defined
@@ -2198,6 +2334,28 @@ impl GlesRenderer {
     }
 }
 
+impl GlesRenderer {
+    fn rounded_program(&mut self) -> Result<GlesTexProgram, GlesError> {
+        if let Some(program) = self.rounded_program.as_ref() {
+            return Ok(program.clone());
+        }
+
+        let program = unsafe {
+            texture_program(
+                &self.gl,
+                shaders::FRAGMENT_SHADER_ROUNDED,
+                &[
+                    UniformName::new("clip_geometry", UniformType::_4f),
+                    UniformName::new("corner_radius", UniformType::_4f),
+                ],
+                self.gles_cleanup().sender.clone(),
+            )?
+        };
+        self.rounded_program = Some(program.clone());
+        Ok(program)
+    }
+}
+
 impl GlesFrame<'_, '_> {
     /// Run custom code in the GL context owned by this renderer.
     ///
@@ -2263,6 +2421,7 @@ impl Renderer for GlesRenderer {
         'buffer: 'frame,
     {
         target.0.make_current(&self.gl, &self.egl)?;
+        self.arena.reset();
 
         unsafe {
             self.gl.Viewport(0, 0, output_size.w, output_size.h);
@@ -2351,6 +2510,10 @@ impl Renderer for GlesRenderer {
         sync.wait().map_err(|_| GlesError::SyncInterrupted)
     }
 
+    fn frame_arena(&self) -> Option<&FrameArena> {
+        Some(&self.arena)
+    }
+
     #[profiling::function]
     fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
         unsafe {
@@ -2487,10 +2650,75 @@ impl Frame for GlesFrame<'_, '_> {
         )
     }
 
+    #[instrument(level = "trace", skip(self), parent = &self.span)]
+    #[profiling::function]
+    fn render_texture_clipped(
+        &mut self,
+        texture: &GlesTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        transform: Transform,
+        alpha: f32,
+        clip: &ClipRegion,
+    ) -> Result<(), GlesError> {
+        let damage = clip.clip_damage(dest, damage);
+        if damage.is_empty() {
+            return Ok(());
+        }
+        let opaque_regions = clip.clip_opaque_regions(dest, opaque_regions);
+
+        let Some(rounded) = clip.rounded_rect().filter(|rounded| !rounded.radius.is_zero()) else {
+            return self.render_texture_from_to(
+                texture,
+                src,
+                dest,
+                &damage,
+                &opaque_regions,
+                transform,
+                alpha,
+                None,
+                &[],
+            );
+        };
+
+        let program = self.renderer.rounded_program()?;
+        let geometry = rounded.geometry;
+        let [top_left, top_right, bottom_right, bottom_left] = rounded.radius.to_array();
+        let uniforms = [
+            Uniform::new(
+                "clip_geometry",
+                [
+                    (geometry.loc.x - dest.loc.x) as f32,
+                    (geometry.loc.y - dest.loc.y) as f32,
+                    geometry.size.w as f32,
+                    geometry.size.h as f32,
+                ],
+            ),
+            Uniform::new("corner_radius", [top_left, top_right, bottom_right, bottom_left]),
+        ];
+        self.render_texture_from_to(
+            texture,
+            src,
+            dest,
+            &damage,
+            &opaque_regions,
+            transform,
+            alpha,
+            Some(&program),
+            &uniforms,
+        )
+    }
+
     fn transformation(&self) -> Transform {
         self.transform
     }
 
+    fn arena(&self) -> Option<&FrameArena> {
+        Some(&self.renderer.arena)
+    }
+
     #[profiling::function]
     fn wait(&mut self, sync: &SyncPoint) -> Result<(), Self::Error> {
         self.renderer.wait(sync)
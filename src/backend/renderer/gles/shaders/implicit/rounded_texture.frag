@@ -0,0 +1,66 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+// #extension GL_OES_EGL_image_external : require
+#endif
+
+precision mediump float;
+#if defined(EXTERNAL)
+uniform sampler2D tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+varying vec2 v_position;
+
+// x, y, width, height relative to the destination rectangle
+uniform vec4 clip_geometry;
+// top-left, top-right, bottom-right, bottom-left
+uniform vec4 corner_radius;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+float rounded_coverage(vec2 position) {
+    vec2 rel = position - clip_geometry.xy;
+    vec2 half_size = clip_geometry.zw * 0.5;
+    vec2 dir = vec2(rel.x < half_size.x ? -1.0 : 1.0, rel.y < half_size.y ? -1.0 : 1.0);
+
+    float radius;
+    if (dir.x < 0.0) {
+        radius = dir.y < 0.0 ? corner_radius.x : corner_radius.w;
+    } else {
+        radius = dir.y < 0.0 ? corner_radius.y : corner_radius.z;
+    }
+
+    vec2 center = half_size + dir * (half_size - vec2(radius));
+    vec2 dist = (rel - center) * dir;
+    if (dist.x <= 0.0 || dist.y <= 0.0) {
+        return 1.0;
+    }
+    return clamp(radius - length(dist) + 0.5, 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0) * alpha;
+#else
+    color = color * alpha;
+#endif
+
+    color = color * rounded_coverage(v_position);
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
\ No newline at end of file
@@ -52,6 +52,11 @@ impl GlesTexture {
         self.0.y_inverted
     }
 
+    /// Whether the texture is bound to `GL_TEXTURE_EXTERNAL_OES` instead of `GL_TEXTURE_2D`
+    pub fn is_external(&self) -> bool {
+        self.0.is_external
+    }
+
     /// Whether this is the only reference to this texture (strong or weak)
     ///
     /// Note that this tracks only references to this Smithay object (that you can get by cloning
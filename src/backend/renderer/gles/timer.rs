@@ -0,0 +1,130 @@
+use std::{cell::Cell, time::Duration};
+
+use super::*;
+
+/// A GPU timer query
+///
+/// Measures the time the GPU spent executing the commands recorded between
+/// [`GlesFrame::begin_timer_query`] and [`GlesFrame::end_timer_query`].
+///
+/// Results become available asynchronously, usually one or two frames later,
+/// and can be polled with [`GlesRenderer::timer_query_result`].
+#[derive(Debug, Clone)]
+pub struct GlesTimerQuery(Rc<GlesTimerQueryInternal>);
+
+#[derive(Debug)]
+struct GlesTimerQueryInternal {
+    query: ffi::types::GLuint,
+    // reading GL_GPU_DISJOINT_EXT resets it, so the first result is kept
+    result: Cell<Option<TimerQueryResult>>,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+impl Drop for GlesTimerQueryInternal {
+    fn drop(&mut self) {
+        let _ = self
+            .destruction_callback_sender
+            .send(CleanupResource::Query(self.query));
+    }
+}
+
+impl GlesTimerQuery {
+    /// OpenGL query id of this timer query
+    pub fn query_id(&self) -> ffi::types::GLuint {
+        self.0.query
+    }
+}
+
+/// Result of polling a [`GlesTimerQuery`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerQueryResult {
+    /// The GPU did not finish the measured commands yet
+    Pending,
+    /// The GPU reported a disjoint operation (e.g. a frequency change) during the query,
+    /// the measurement is unreliable and was discarded
+    Disjoint,
+    /// Time the GPU spent executing the measured commands
+    Ready(Duration),
+}
+
+impl GlesFrame<'_, '_> {
+    /// Start measuring the GPU time of all following commands of this frame.
+    ///
+    /// Only one timer query may be active at any given time,
+    /// the query needs to be ended with [`GlesFrame::end_timer_query`] before the frame is finished.
+    ///
+    /// Requires [`Capability::TimerQuery`].
+    #[profiling::function]
+    pub fn begin_timer_query(&mut self) -> Result<GlesTimerQuery, GlesError> {
+        if !self.renderer.capabilities.contains(&Capability::TimerQuery) {
+            return Err(GlesError::GLExtensionNotSupported(&[
+                "GL_EXT_disjoint_timer_query",
+            ]));
+        }
+
+        let mut query = 0;
+        unsafe {
+            self.renderer.gl.GenQueriesEXT(1, &mut query);
+            self.renderer.gl.BeginQueryEXT(ffi::TIME_ELAPSED_EXT, query);
+        }
+
+        Ok(GlesTimerQuery(Rc::new(GlesTimerQueryInternal {
+            query,
+            result: Cell::new(None),
+            destruction_callback_sender: self.renderer.gles_cleanup().sender.clone(),
+        })))
+    }
+
+    /// Stop the currently active timer query.
+    #[profiling::function]
+    pub fn end_timer_query(&mut self, query: &GlesTimerQuery) {
+        debug_assert!(self.renderer.capabilities.contains(&Capability::TimerQuery));
+        trace!(query = query.0.query, "Ending timer query");
+        unsafe {
+            self.renderer.gl.EndQueryEXT(ffi::TIME_ELAPSED_EXT);
+        }
+    }
+}
+
+impl GlesRenderer {
+    /// Poll the result of a previously ended [`GlesTimerQuery`].
+    ///
+    /// Returns [`TimerQueryResult::Pending`] until the result is available. Once it is, the result
+    /// is stored in the query and polling again returns the same result, so a
+    /// [`TimerQueryResult::Disjoint`] query should be dropped.
+    #[profiling::function]
+    pub fn timer_query_result(&mut self, query: &GlesTimerQuery) -> Result<TimerQueryResult, GlesError> {
+        if let Some(result) = query.0.result.get() {
+            return Ok(result);
+        }
+
+        unsafe {
+            self.egl.make_current()?;
+        }
+
+        let mut available = 0;
+        unsafe {
+            self.gl
+                .GetQueryObjectuivEXT(query.0.query, ffi::QUERY_RESULT_AVAILABLE_EXT, &mut available);
+        }
+        if available == 0 {
+            return Ok(TimerQueryResult::Pending);
+        }
+
+        let mut disjoint = 0;
+        let mut elapsed: u64 = 0;
+        unsafe {
+            self.gl.GetIntegerv(ffi::GPU_DISJOINT_EXT, &mut disjoint);
+            self.gl
+                .GetQueryObjectui64vEXT(query.0.query, ffi::QUERY_RESULT_EXT, &mut elapsed);
+        }
+        let result = if disjoint != 0 {
+            debug!("GPU timer query was disjoint, discarding result");
+            TimerQueryResult::Disjoint
+        } else {
+            TimerQueryResult::Ready(Duration::from_nanos(elapsed))
+        };
+        query.0.result.set(Some(result));
+        Ok(result)
+    }
+}
@@ -12,6 +12,7 @@ use crate::backend::{egl::display::EGLBufferReader, renderer::ImportEgl};
 use crate::{
     backend::{
         allocator::{dmabuf::Dmabuf, format::FormatSet, Format, Fourcc},
+        capabilities::{Capabilities, QueryCapabilities},
         egl::EGLContext,
         renderer::{
             element::UnderlyingStorage,
@@ -32,7 +33,9 @@ use std::{
     sync::Arc,
 };
 
-use super::{element::RenderElement, ContextId, Frame};
+use super::{
+    element::RenderElement, ClipRegion, ContextId, Frame, FrameArena, ImportPath, MemFormatConversion,
+};
 
 #[derive(Debug)]
 /// A renderer utilizing OpenGL ES 2 and [`glow`] on top for easier custom rendering.
@@ -252,6 +255,10 @@ impl Renderer for GlowRenderer {
         self.gl.wait(sync)
     }
 
+    fn frame_arena(&self) -> Option<&FrameArena> {
+        self.gl.frame_arena()
+    }
+
     #[profiling::function]
     fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
         self.gl.cleanup_texture_cache()
@@ -304,10 +311,38 @@ impl Frame for GlowFrame<'_, '_> {
         )
     }
 
+    #[profiling::function]
+    fn render_texture_clipped(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        clip: &ClipRegion,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().render_texture_clipped(
+            texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            src_transform,
+            alpha,
+            clip,
+        )
+    }
+
     fn transformation(&self) -> Transform {
         self.frame.as_ref().unwrap().transformation()
     }
 
+    fn arena(&self) -> Option<&FrameArena> {
+        self.frame.as_ref().unwrap().arena()
+    }
+
     #[profiling::function]
     fn render_texture_at(
         &mut self,
@@ -404,6 +439,10 @@ impl ImportMem for GlowRenderer {
     fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
         self.gl.mem_formats()
     }
+
+    fn mem_format_conversion(&self, format: Fourcc) -> Option<MemFormatConversion> {
+        self.gl.mem_format_conversion(format)
+    }
 }
 
 #[cfg(all(
@@ -438,6 +477,12 @@ impl ImportEgl for GlowRenderer {
     }
 }
 
+impl QueryCapabilities for GlowRenderer {
+    fn query_capabilities(&self) -> Capabilities {
+        self.gl.query_capabilities()
+    }
+}
+
 impl ImportDma for GlowRenderer {
     #[profiling::function]
     fn import_dmabuf(
@@ -453,6 +498,9 @@ impl ImportDma for GlowRenderer {
     fn has_dmabuf_format(&self, format: Format) -> bool {
         self.gl.has_dmabuf_format(format)
     }
+    fn dmabuf_import_path(&self, format: Format) -> Option<ImportPath> {
+        self.gl.dmabuf_import_path(format)
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -20,7 +20,10 @@ use std::{
 };
 
 #[cfg(feature = "wayland_frontend")]
-use crate::wayland::{compositor::SurfaceData, shm::fourcc_to_shm_format};
+use crate::wayland::{
+    compositor::SurfaceData,
+    shm::{fourcc_to_shm_format, shm_format_to_fourcc},
+};
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::protocol::{wl_buffer, wl_shm};
 
@@ -46,8 +49,18 @@ pub mod glow;
 #[cfg(feature = "renderer_pixman")]
 pub mod pixman;
 
+#[cfg(feature = "renderer_skia")]
+pub mod skia;
+
+#[cfg(all(feature = "renderer_wgpu", unix))]
+pub mod wgpu;
+
+mod clip;
+pub use clip::{ClipRegion, CornerRadius, RoundedRect};
 mod color;
 pub use color::Color32F;
+mod arena;
+pub use arena::{ArenaSlice, ArenaVec, FrameArena};
 
 #[cfg(unix)]
 use crate::backend::allocator::{dmabuf::Dmabuf, Format, Fourcc};
@@ -168,6 +181,32 @@ impl<T: Texture> Hash for ContextId<T> {
     }
 }
 
+impl ErasedContextId {
+    /// Returns a weak reference to this context, which does not keep it alive
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) fn downgrade(&self) -> WeakContextId {
+        WeakContextId(Arc::downgrade(&self.0), self.1)
+    }
+}
+
+/// Weak reference to an [`ErasedContextId`]
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug, Clone)]
+pub(crate) struct WeakContextId(std::sync::Weak<InnerContextId>, TypeId);
+
+#[cfg(feature = "wayland_frontend")]
+impl WeakContextId {
+    /// Returns whether the renderer context is still alive
+    pub(crate) fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+
+    /// Returns whether this references the given context
+    pub(crate) fn is(&self, id: &ErasedContextId) -> bool {
+        self.1 == id.1 && std::ptr::eq(self.0.as_ptr(), Arc::as_ptr(&id.0))
+    }
+}
+
 id_gen!(context_id);
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -358,9 +397,42 @@ pub trait Frame {
         alpha: f32,
     ) -> Result<(), Self::Error>;
 
+    /// Render part of a texture like [`Frame::render_texture_from_to`], but only inside of the given [`ClipRegion`].
+    ///
+    /// The clip region is given in the same coordinate space as `dst`.
+    ///
+    /// The default implementation clips the damage analytically, approximating rounded corners
+    /// without anti-aliasing. Renderers supporting shaders should override this to clip per pixel.
+    #[allow(clippy::too_many_arguments)]
+    fn render_texture_clipped(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        clip: &ClipRegion,
+    ) -> Result<(), Self::Error> {
+        let damage = clip.clip_damage_analytic(dst, damage);
+        if damage.is_empty() {
+            return Ok(());
+        }
+        let opaque_regions = clip.clip_opaque_regions(dst, opaque_regions);
+        self.render_texture_from_to(texture, src, dst, &damage, &opaque_regions, src_transform, alpha)
+    }
+
     /// Output transformation that is applied to this frame
     fn transformation(&self) -> Transform;
 
+    /// Returns the [`FrameArena`] for transient data of this frame, if the renderer keeps one
+    ///
+    /// The arena is reset when the next frame is started, so allocations must not outlive the frame.
+    fn arena(&self) -> Option<&FrameArena> {
+        None
+    }
+
     /// Wait for a [`SyncPoint`] to be signaled
     fn wait(&mut self, sync: &sync::SyncPoint) -> Result<(), Self::Error>;
 
@@ -441,6 +513,14 @@ pub trait Renderer: RendererSuper {
     /// Wait for a [`SyncPoint`] to be signaled
     fn wait(&mut self, sync: &sync::SyncPoint) -> Result<(), Self::Error>;
 
+    /// Returns the [`FrameArena`] the frames of this renderer allocate transient data from, if any
+    ///
+    /// The arena is reset at the start of every [`Renderer::render`] call. Render elements may use
+    /// it to avoid allocations while building or drawing a frame, see [`Frame::arena`].
+    fn frame_arena(&self) -> Option<&FrameArena> {
+        None
+    }
+
     /// Forcibly clean up the renderer internal texture cache
     ///
     /// Note: Resources used by the renderer will be implicitly cleaned-up after finishing
@@ -497,6 +577,95 @@ pub trait ImportMemWl: ImportMem {
     fn shm_formats(&self) -> Box<dyn Iterator<Item = wl_shm::Format>> {
         Box::new(self.mem_formats().flat_map(fourcc_to_shm_format))
     }
+
+    /// Returns how expensive importing shared memory buffers of the given format is,
+    /// or `None` if the format isn't supported.
+    ///
+    /// The default implementation derives the cost of every format returned by
+    /// [`ImportMemWl::shm_formats`] from [`ImportMem::mem_format_conversion`].
+    fn shm_format_cost(&self, format: wl_shm::Format) -> Option<ShmImportCost> {
+        if !self.shm_formats().any(|f| f == format) {
+            return None;
+        }
+        let conversion = shm_format_to_fourcc(format).and_then(|fourcc| self.mem_format_conversion(fourcc));
+        Some(conversion.map_or(ShmImportCost::Direct, ShmImportCost::from))
+    }
+}
+
+/// Cost of importing a shared memory buffer format, see [`ImportMemWl::shm_format_cost`]
+///
+/// Variants are ordered from cheapest to most expensive.
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShmImportCost {
+    /// The buffer contents are uploaded as-is
+    Direct,
+    /// The color channels are reordered on upload, e.g. using the SIMD fast path of
+    /// [`swizzle_bgra_rgba`](crate::utils::simd_utils::swizzle_bgra_rgba)
+    Swizzle,
+    /// The buffer contents are converted per pixel on upload
+    Convert,
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl From<MemFormatConversion> for ShmImportCost {
+    #[inline]
+    fn from(conversion: MemFormatConversion) -> Self {
+        match conversion {
+            MemFormatConversion::Native | MemFormatConversion::GpuSwizzle => ShmImportCost::Direct,
+            MemFormatConversion::CpuSwizzle => ShmImportCost::Swizzle,
+            MemFormatConversion::CpuConvert => ShmImportCost::Convert,
+        }
+    }
+}
+
+/// How memory of a format is converted on import, see [`ImportMem::mem_format_conversion`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemFormatConversion {
+    /// The format is supported as-is
+    Native,
+    /// The color channels are reordered by the GPU while sampling, e.g. with texture swizzles
+    GpuSwizzle,
+    /// The color channels are reordered on the CPU on upload, e.g. using
+    /// [`swizzle_bgra_rgba`](crate::utils::simd_utils::swizzle_bgra_rgba)
+    CpuSwizzle,
+    /// The contents are converted per pixel on the CPU on upload
+    CpuConvert,
+}
+
+impl MemFormatConversion {
+    /// Returns whether the conversion is done on the CPU
+    #[inline]
+    pub fn is_cpu(&self) -> bool {
+        matches!(
+            self,
+            MemFormatConversion::CpuSwizzle | MemFormatConversion::CpuConvert
+        )
+    }
+}
+
+/// Path a buffer takes when it is imported into a renderer, see [`ImportAll::import_path`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportPath {
+    /// The buffer is sampled as-is
+    Direct,
+    /// The color channels are reordered by the GPU while sampling
+    Swizzle,
+    /// The buffer is sampled through a shader converting its format, e.g. external YUV textures
+    ConvertShader,
+    /// The buffer contents are converted on the CPU on upload
+    Cpu,
+}
+
+impl From<MemFormatConversion> for ImportPath {
+    #[inline]
+    fn from(conversion: MemFormatConversion) -> Self {
+        match conversion {
+            MemFormatConversion::Native => ImportPath::Direct,
+            MemFormatConversion::GpuSwizzle => ImportPath::Swizzle,
+            MemFormatConversion::CpuSwizzle | MemFormatConversion::CpuConvert => ImportPath::Cpu,
+        }
+    }
 }
 
 /// Trait for Renderers supporting importing bitmaps from memory.
@@ -548,6 +717,18 @@ pub trait ImportMem: Renderer {
 
     /// Returns supported formats for memory imports.
     fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>>;
+
+    /// Returns how memory of the given format is converted on import, or `None` if the format
+    /// isn't supported.
+    ///
+    /// Formats needing a conversion on the CPU are comparatively expensive to import, see
+    /// [`MemFormatConversion::is_cpu`]. The default implementation reports every format returned
+    /// by [`ImportMem::mem_formats`] as [`MemFormatConversion::Native`].
+    fn mem_format_conversion(&self, format: Fourcc) -> Option<MemFormatConversion> {
+        self.mem_formats()
+            .any(|f| f == format)
+            .then_some(MemFormatConversion::Native)
+    }
 }
 
 #[cfg(all(
@@ -644,6 +825,14 @@ pub trait ImportDma: Renderer {
         self.dmabuf_formats().contains(&format)
     }
 
+    /// Returns the [`ImportPath`] a dmabuf of the given [`Format`] takes on import,
+    /// or `None` if the format is not supported.
+    ///
+    /// The default implementation reports every supported format as [`ImportPath::Direct`].
+    fn dmabuf_import_path(&self, format: Format) -> Option<ImportPath> {
+        self.has_dmabuf_format(format).then_some(ImportPath::Direct)
+    }
+
     /// Import a given raw dmabuf into the renderer.
     ///
     /// Returns a texture_id, which can be used with [`Frame::render_texture_from_to`] (or [`Frame::render_texture_at`])
@@ -692,6 +881,79 @@ pub trait ImportAll: Renderer {
         surface: Option<&crate::wayland::compositor::SurfaceData>,
         damage: &[Rectangle<i32, BufferCoord>],
     ) -> Option<Result<Self::TextureId, Self::Error>>;
+
+    /// Import a given buffer of a known [`BufferType`] into the renderer.
+    ///
+    /// Behaves like [`ImportAll::import_buffer`], but does not determine the type of the buffer again.
+    /// [`import_surface`](utils::import_surface) caches the type and [`ImportPath`] of every buffer
+    /// per renderer and imports through this method.
+    ///
+    /// The default implementation calls [`ImportAll::import_buffer`].
+    fn import_buffer_of_type(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        buffer_type: BufferType,
+        surface: Option<&crate::wayland::compositor::SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Option<Result<Self::TextureId, Self::Error>> {
+        let _ = buffer_type;
+        self.import_buffer(buffer, surface, damage)
+    }
+
+    /// Returns the [`ImportPath`] the given buffer takes when imported by [`ImportAll::import_buffer`].
+    ///
+    /// Returns `None`, if the buffer type cannot be determined, its format is not supported or the
+    /// buffer does not correspond to a texture. The default implementation does not report any path.
+    ///
+    /// The path only depends on the format of the buffer, which never changes for a given buffer.
+    /// [`import_surface`](utils::import_surface) therefore caches it per buffer and renderer,
+    /// see [`RendererSurfaceState::import_path`](utils::RendererSurfaceState::import_path).
+    fn import_path(&self, buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
+        let _ = buffer;
+        None
+    }
+}
+
+/// Returns the [`ImportPath`] of an shm buffer from the [`ImportMem::mem_format_conversion`] of its format
+#[cfg(feature = "wayland_frontend")]
+fn shm_import_path<R: ImportMemWl>(renderer: &R, buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
+    let format = crate::wayland::shm::with_buffer_contents(buffer, |_, _, data| data.format).ok()?;
+    if !renderer.shm_formats().any(|f| f == format) {
+        return None;
+    }
+    let conversion = shm_format_to_fourcc(format).and_then(|fourcc| renderer.mem_format_conversion(fourcc));
+    Some(conversion.map_or(ImportPath::Direct, ImportPath::from))
+}
+
+/// Returns the [`ImportPath`] of a dmabuf-based buffer from [`ImportDma::dmabuf_import_path`]
+#[cfg(feature = "wayland_frontend")]
+fn dma_import_path<R: ImportDmaWl>(renderer: &R, buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
+    use crate::backend::allocator::Buffer;
+
+    let dmabuf = crate::wayland::dmabuf::get_dmabuf(buffer).ok()?;
+    renderer.dmabuf_import_path(dmabuf.format())
+}
+
+/// Returns the [`ImportPath`] of a wl_drm-based buffer from the format of its EGL images
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+fn egl_import_path(buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
+    use crate::backend::egl::Format as EGLFormat;
+
+    let format = BUFFER_READER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|x| x.upgrade())
+        .and_then(|x| x.egl_buffer_contents(buffer).ok())
+        .map(|b| b.format)?;
+    match format {
+        EGLFormat::RGB | EGLFormat::RGBA => Some(ImportPath::Direct),
+        _ => Some(ImportPath::ConvertShader),
+    }
 }
 
 // TODO: Do this with specialization, when possible and do default implementations
@@ -708,10 +970,29 @@ impl<R: Renderer + ImportMemWl + ImportEgl + ImportDmaWl> ImportAll for R {
         surface: Option<&SurfaceData>,
         damage: &[Rectangle<i32, BufferCoord>],
     ) -> Option<Result<Self::TextureId, Self::Error>> {
+        self.import_buffer_of_type(buffer, buffer_type(buffer)?, surface, damage)
+    }
+
+    fn import_buffer_of_type(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        buffer_type: BufferType,
+        surface: Option<&SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Option<Result<Self::TextureId, Self::Error>> {
+        match buffer_type {
+            BufferType::Shm => Some(self.import_shm_buffer(buffer, surface, damage)),
+            BufferType::Egl => Some(self.import_egl_buffer(buffer, surface, damage)),
+            BufferType::Dma => Some(self.import_dma_buffer(buffer, surface, damage)),
+            _ => None,
+        }
+    }
+
+    fn import_path(&self, buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
         match buffer_type(buffer) {
-            Some(BufferType::Shm) => Some(self.import_shm_buffer(buffer, surface, damage)),
-            Some(BufferType::Egl) => Some(self.import_egl_buffer(buffer, surface, damage)),
-            Some(BufferType::Dma) => Some(self.import_dma_buffer(buffer, surface, damage)),
+            Some(BufferType::Shm) => shm_import_path(self, buffer),
+            Some(BufferType::Egl) => egl_import_path(buffer),
+            Some(BufferType::Dma) => dma_import_path(self, buffer),
             _ => None,
         }
     }
@@ -728,9 +1009,27 @@ impl<R: Renderer + ImportMemWl + ImportDmaWl> ImportAll for R {
         surface: Option<&SurfaceData>,
         damage: &[Rectangle<i32, BufferCoord>],
     ) -> Option<Result<Self::TextureId, Self::Error>> {
+        self.import_buffer_of_type(buffer, buffer_type(buffer)?, surface, damage)
+    }
+
+    fn import_buffer_of_type(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        buffer_type: BufferType,
+        surface: Option<&SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Option<Result<Self::TextureId, Self::Error>> {
+        match buffer_type {
+            BufferType::Shm => Some(self.import_shm_buffer(buffer, surface, damage)),
+            BufferType::Dma => Some(self.import_dma_buffer(buffer, surface, damage)),
+            _ => None,
+        }
+    }
+
+    fn import_path(&self, buffer: &wl_buffer::WlBuffer) -> Option<ImportPath> {
         match buffer_type(buffer) {
-            Some(BufferType::Shm) => Some(self.import_shm_buffer(buffer, surface, damage)),
-            Some(BufferType::Dma) => Some(self.import_dma_buffer(buffer, surface, damage)),
+            Some(BufferType::Shm) => shm_import_path(self, buffer),
+            Some(BufferType::Dma) => dma_import_path(self, buffer),
             _ => None,
         }
     }
@@ -793,6 +1092,39 @@ pub trait ExportMem: Renderer {
     /// - There is not enough space in memory
     fn map_texture<'a>(&mut self, texture_mapping: &'a Self::TextureMapping)
         -> Result<&'a [u8], Self::Error>;
+
+    /// Reads back the given region of a framebuffer as an [`RgbaImage`](utils::RgbaImage).
+    ///
+    /// This works with every renderer implementing [`ExportMem`], so it can be used
+    /// to take screenshots of outputs on any backend, including headless or offscreen
+    /// setups, e.g. for tests or bug reports.
+    ///
+    /// ```no_run
+    /// use smithay::backend::renderer::ExportMem;
+    /// use smithay::utils::Rectangle;
+    ///
+    /// fn screenshot<R: ExportMem>(renderer: &mut R, framebuffer: &R::Framebuffer<'_>) {
+    ///     let image = renderer
+    ///         .capture_output_to_image(framebuffer, Rectangle::from_size((800, 600).into()))
+    ///         .expect("Failed to capture output");
+    ///     assert_eq!(image.width(), 800);
+    /// }
+    /// ```
+    fn capture_output_to_image(
+        &mut self,
+        target: &Self::Framebuffer<'_>,
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<utils::RgbaImage, utils::CaptureError<Self::Error>> {
+        let mapping = self
+            .copy_framebuffer(target, region, Fourcc::Abgr8888)
+            .map_err(utils::CaptureError::Renderer)?;
+        let format = TextureMapping::format(&mapping);
+        let flipped = mapping.flipped();
+        let data = self
+            .map_texture(&mapping)
+            .map_err(utils::CaptureError::Renderer)?;
+        utils::RgbaImage::convert(region.size, format, flipped, data).map_err(utils::CaptureError::from)
+    }
 }
 
 /// Trait for renderers supporting blitting contents from one framebuffer to another.
@@ -876,7 +1208,7 @@ where
 #[cfg(feature = "wayland_frontend")]
 #[non_exhaustive]
 /// Buffer type of a given wl_buffer, if managed by smithay
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BufferType {
     /// Buffer is managed by the [`crate::wayland::shm`] global
     Shm,
@@ -928,6 +1260,102 @@ pub fn buffer_type(buffer: &wl_buffer::WlBuffer) -> Option<BufferType> {
     None
 }
 
+#[cfg(feature = "wayland_frontend")]
+impl BufferType {
+    /// Returns the type of the given wl_buffer, see [`buffer_type`]
+    pub fn of(buffer: &wl_buffer::WlBuffer) -> Option<BufferType> {
+        buffer_type(buffer)
+    }
+}
+
+/// Typed view of a wl_buffer managed by smithay
+///
+/// Gives access to the data backing a buffer of any known [`BufferType`],
+/// so compositors can handle buffers without caring about the global that created them
+/// and only downcast where they need to, e.g. for direct scan-out of dmabufs.
+/// Rendering any of them is done with [`ImportAll::import_buffer`].
+#[cfg(feature = "wayland_frontend")]
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum WaylandBuffer<'a> {
+    /// Buffer managed by the [`crate::wayland::shm`] global
+    Shm(crate::wayland::shm::BufferData),
+    #[cfg(all(feature = "backend_egl", feature = "use_system_lib"))]
+    /// Buffer managed by a currently initialized [`crate::backend::egl::display::EGLBufferReader`]
+    Egl,
+    /// Buffer managed by the [`crate::wayland::dmabuf`] global
+    Dma(&'a Dmabuf),
+    /// Buffer representing a single pixel
+    SinglePixel(&'a crate::wayland::single_pixel_buffer::SinglePixelBufferUserData),
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl<'a> WaylandBuffer<'a> {
+    /// Returns a typed view of the given wl_buffer
+    ///
+    /// Returns `None` if the type is not known to smithay, see [`buffer_type`].
+    pub fn from_buffer(buffer: &'a wl_buffer::WlBuffer) -> Option<WaylandBuffer<'a>> {
+        use wayland_server::Resource;
+
+        if let Ok(dmabuf) = crate::wayland::dmabuf::get_dmabuf(buffer) {
+            return Some(WaylandBuffer::Dma(dmabuf));
+        }
+
+        // only the parameters are needed, without touching the memory of the pool
+        if let Some(data) = buffer.data::<crate::wayland::shm::ShmBufferUserData>() {
+            return Some(WaylandBuffer::Shm(data.data));
+        }
+
+        if let Ok(spb) = crate::wayland::single_pixel_buffer::get_single_pixel_buffer(buffer) {
+            return Some(WaylandBuffer::SinglePixel(spb));
+        }
+
+        #[cfg(all(feature = "backend_egl", feature = "use_system_lib"))]
+        if matches!(buffer_type(buffer), Some(BufferType::Egl)) {
+            return Some(WaylandBuffer::Egl);
+        }
+
+        None
+    }
+
+    /// Returns the [`BufferType`] of this buffer
+    pub fn buffer_type(&self) -> BufferType {
+        match self {
+            WaylandBuffer::Shm(_) => BufferType::Shm,
+            #[cfg(all(feature = "backend_egl", feature = "use_system_lib"))]
+            WaylandBuffer::Egl => BufferType::Egl,
+            WaylandBuffer::Dma(_) => BufferType::Dma,
+            WaylandBuffer::SinglePixel(_) => BufferType::SinglePixel,
+        }
+    }
+
+    /// Returns the shm buffer parameters, if this is an shm buffer
+    pub fn as_shm(&self) -> Option<&crate::wayland::shm::BufferData> {
+        match self {
+            WaylandBuffer::Shm(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns the dmabuf, if this is a dmabuf buffer
+    pub fn as_dmabuf(&self) -> Option<&'a Dmabuf> {
+        match self {
+            WaylandBuffer::Dma(dmabuf) => Some(dmabuf),
+            _ => None,
+        }
+    }
+
+    /// Returns the pixel data, if this is a single pixel buffer
+    pub fn as_single_pixel(
+        &self,
+    ) -> Option<&'a crate::wayland::single_pixel_buffer::SinglePixelBufferUserData> {
+        match self {
+            WaylandBuffer::SinglePixel(spb) => Some(spb),
+            _ => None,
+        }
+    }
+}
+
 /// Returns if the buffer has an alpha channel
 ///
 /// Returns `None` if the type is not known to smithay
@@ -49,8 +49,9 @@ use std::{
 
 use super::{
     sync::{self, SyncPoint},
-    Bind, Blit, BlitFrame, Color32F, ContextId, DebugFlags, ErasedContextId, ExportMem, Frame, ImportDma,
-    ImportMem, Offscreen, Renderer, RendererSuper, Texture, TextureFilter, TextureMapping,
+    Bind, Blit, BlitFrame, ClipRegion, Color32F, ContextId, DebugFlags, ErasedContextId, ExportMem, Frame,
+    FrameArena, ImportDma, ImportMem, ImportPath, MemFormatConversion, Offscreen, Renderer, RendererSuper,
+    Texture, TextureFilter, TextureMapping,
 };
 #[cfg(feature = "wayland_frontend")]
 use super::{ImportDmaWl, ImportMemWl};
@@ -1261,6 +1262,10 @@ where
         self.render.renderer_mut().wait(sync).map_err(Error::Render)
     }
 
+    fn frame_arena(&self) -> Option<&FrameArena> {
+        self.render.renderer().frame_arena()
+    }
+
     #[profiling::function]
     fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
         if let Some(target) = self.target.as_mut() {
@@ -1909,10 +1914,66 @@ where
         }
     }
 
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    fn render_texture_clipped(
+        &mut self,
+        texture: &MultiTexture,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        clip: &ClipRegion,
+    ) -> Result<(), Error<R, T>> {
+        let render_id = self.frame.as_mut().unwrap().context_id();
+        let sync = texture.needs_synchronization::<R>(&render_id);
+        if let Some(texture) = texture.get::<R>(&render_id) {
+            self.damage
+                .extend(clip.clip_damage(dst, damage).into_iter().map(|mut rect| {
+                    rect.loc += dst.loc;
+                    rect
+                }));
+            if let Some(sync) = sync {
+                if let Err(err) = self.frame.as_mut().unwrap().wait(&sync) {
+                    trace!(?err, "Failed to import sync point, blocking");
+                    let _ = sync.wait(); // ignore interrupt errors
+                }
+            }
+            self.frame
+                .as_mut()
+                .unwrap()
+                .render_texture_clipped(
+                    &texture,
+                    src,
+                    dst,
+                    damage,
+                    opaque_regions,
+                    src_transform,
+                    alpha,
+                    clip,
+                )
+                .map_err(Error::Render)
+        } else {
+            warn!(
+                "Failed to render texture {:?}, import for wrong devices {:?}? {:?}",
+                Arc::as_ptr(&texture.0),
+                self.node,
+                texture.0.lock().unwrap(),
+            );
+            Ok(())
+        }
+    }
+
     fn transformation(&self) -> Transform {
         self.frame.as_ref().unwrap().transformation()
     }
 
+    fn arena(&self) -> Option<&FrameArena> {
+        self.frame.as_ref().unwrap().arena()
+    }
+
     #[profiling::function]
     fn wait(&mut self, sync: &sync::SyncPoint) -> Result<(), Self::Error> {
         self.frame.as_mut().unwrap().wait(sync).map_err(Error::Render)
@@ -2031,6 +2092,10 @@ where
     fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
         ImportMem::mem_formats(self.render.renderer())
     }
+
+    fn mem_format_conversion(&self, format: Fourcc) -> Option<MemFormatConversion> {
+        ImportMem::mem_format_conversion(self.render.renderer(), format)
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -2093,6 +2158,10 @@ where
         ImportDma::has_dmabuf_format(self.render.renderer(), format)
     }
 
+    fn dmabuf_import_path(&self, format: Format) -> Option<ImportPath> {
+        ImportDma::dmabuf_import_path(self.render.renderer(), format)
+    }
+
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     fn import_dmabuf(
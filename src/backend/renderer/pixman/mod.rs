@@ -10,10 +10,15 @@ use pixman::{Filter, FormatCode, Image, Operation, Repeat};
 use tracing::warn;
 
 use crate::{
-    backend::allocator::{
-        dmabuf::{Dmabuf, DmabufMapping, DmabufMappingMode, DmabufSyncFailed, DmabufSyncFlags, WeakDmabuf},
-        format::{has_alpha, FormatSet},
-        Buffer,
+    backend::{
+        allocator::{
+            dmabuf::{
+                Dmabuf, DmabufMapping, DmabufMappingMode, DmabufSyncFailed, DmabufSyncFlags, WeakDmabuf,
+            },
+            format::{has_alpha, FormatSet},
+            Buffer,
+        },
+        capabilities::{Capabilities, QueryCapabilities},
     },
     utils::{Buffer as BufferCoords, Physical, Rectangle, Scale, Size, Transform},
 };
@@ -1164,6 +1169,13 @@ impl ImportMemWl for PixmanRenderer {
     }
 }
 
+impl QueryCapabilities for PixmanRenderer {
+    fn query_capabilities(&self) -> Capabilities {
+        // dmabufs are imported by mapping them
+        Capabilities::DMABUF_IMPORT
+    }
+}
+
 impl ImportDma for PixmanRenderer {
     #[profiling::function]
     fn import_dmabuf(
@@ -0,0 +1,976 @@
+//! Integration for using [`skia_safe`] on top of smithays OpenGL ES 2 renderer
+//!
+//! The [`SkiaRenderer`] wraps a [`GlesRenderer`] and creates a skia [`DirectContext`] on the same
+//! [`EGLContext`]. All buffer imports go through the GL renderer, so the resulting
+//! [`GlesTexture`]s can be used by elements rendered by the GL renderer as well as drawn with skia
+//! through [`SkiaCanvas::texture_image`].
+//!
+//! Custom drawing, like vector decorations or effects, is done through [`SkiaFrame::with_canvas`],
+//! which provides a [`SkiaCanvas`] targeting the currently bound framebuffer. The canvas uses the same
+//! physical coordinate space as the [`Frame`] and already accounts for the output transformation.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{gles::GlesRenderer, skia::SkiaRenderer, Bind, Renderer, Frame};
+//! # use smithay::utils::{Size, Transform};
+//! use skia_safe::{Color, Paint, RRect, Rect};
+//!
+//! # let gles: GlesRenderer = todo!();
+//! # let mut target: smithay::backend::renderer::gles::GlesRenderbuffer = todo!();
+//! let mut renderer = SkiaRenderer::try_from(gles).expect("Failed to create skia context");
+//! let mut framebuffer = renderer.bind(&mut target).unwrap();
+//! let mut frame = renderer
+//!     .render(&mut framebuffer, Size::from((1920, 1080)), Transform::Normal)
+//!     .unwrap();
+//! frame
+//!     .with_canvas(|canvas| {
+//!         let mut paint = Paint::default();
+//!         paint.set_anti_alias(true);
+//!         paint.set_color(Color::from_argb(0xff, 0x30, 0x30, 0x30));
+//!         let rect = RRect::new_rect_xy(Rect::from_xywh(100., 100., 640., 32.), 8., 8.);
+//!         canvas.draw_rrect(rect, &paint);
+//!     })
+//!     .unwrap();
+//! frame.finish().unwrap();
+//! ```
+use tracing::warn;
+
+#[cfg(feature = "wayland_frontend")]
+use crate::backend::renderer::{ImportDmaWl, ImportMemWl};
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+use crate::backend::{egl::display::EGLBufferReader, renderer::ImportEgl};
+use crate::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, format::FormatSet, Format, Fourcc},
+        capabilities::{Capabilities, QueryCapabilities},
+        egl::EGLContext,
+        power::PowerHooks,
+        renderer::{
+            element::UnderlyingStorage,
+            gles::{element::*, format::fourcc_to_gl_formats, *},
+            sync, Bind, Blit, BlitFrame, Color32F, DebugFlags, ExportMem, ImportDma, ImportMem, Offscreen,
+            Renderer, RendererSuper, Texture, TextureFilter,
+        },
+    },
+    utils::{Buffer as BufferCoord, Physical, Point, Rectangle, Size, Transform},
+};
+
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::protocol::{wl_buffer, wl_shm};
+
+use skia_safe::{
+    gpu::{self, gl::FramebufferInfo, gl::TextureInfo, DirectContext, Mipmapped, SurfaceOrigin},
+    AlphaType, Canvas, ColorType, Image, Matrix,
+};
+use std::{
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+};
+
+use super::{
+    element::RenderElement, ClipRegion, ContextId, Frame, FrameArena, ImportPath, MemFormatConversion,
+};
+
+// GL_BGRA8_EXT, which skia expects for BGRA textures instead of the unsized GL_BGRA_EXT
+const BGRA8_EXT: u32 = 0x93A1;
+
+/// A renderer utilizing OpenGL ES 2 and [`skia_safe`] on top for high quality 2D drawing.
+pub struct SkiaRenderer {
+    gl: GlesRenderer,
+    skia: DirectContext,
+}
+
+impl fmt::Debug for SkiaRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkiaRenderer")
+            .field("gl", &self.gl)
+            .finish_non_exhaustive()
+    }
+}
+
+/// [`Frame`] implementation of a [`SkiaRenderer`].
+///
+/// Leaking the frame will cause the same problems as leaking a [`GlesFrame`].
+pub struct SkiaFrame<'frame, 'buffer> {
+    frame: Option<GlesFrame<'frame, 'buffer>>,
+    skia: &'frame mut DirectContext,
+    output_size: Size<i32, Physical>,
+    transform: Transform,
+    format: Option<Fourcc>,
+    borrowed_textures: Vec<GlesTexture>,
+}
+
+impl fmt::Debug for SkiaFrame<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkiaFrame")
+            .field("frame", &self.frame)
+            .field("output_size", &self.output_size)
+            .field("transform", &self.transform)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Skia [`Canvas`] targeting the framebuffer of a [`SkiaFrame`], see [`SkiaFrame::with_canvas`].
+pub struct SkiaCanvas<'a> {
+    canvas: &'a Canvas,
+    skia: &'a mut DirectContext,
+    borrowed_textures: &'a mut Vec<GlesTexture>,
+}
+
+impl fmt::Debug for SkiaCanvas<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkiaCanvas")
+            .field("borrowed_textures", &self.borrowed_textures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Deref for SkiaCanvas<'_> {
+    type Target = Canvas;
+
+    #[inline]
+    fn deref(&self) -> &Canvas {
+        self.canvas
+    }
+}
+
+/// Skia [`Image`] borrowing a [`GlesTexture`], created by [`SkiaCanvas::texture_image`].
+///
+/// The image is bound to the canvas it was created for and can not outlive the
+/// [`SkiaFrame::with_canvas`] call.
+#[derive(Debug)]
+pub struct TextureImage<'a> {
+    image: Image,
+    _canvas: PhantomData<&'a ()>,
+}
+
+impl Deref for TextureImage<'_> {
+    type Target = Image;
+
+    #[inline]
+    fn deref(&self) -> &Image {
+        &self.image
+    }
+}
+
+impl AsRef<Image> for TextureImage<'_> {
+    #[inline]
+    fn as_ref(&self) -> &Image {
+        &self.image
+    }
+}
+
+fn create_direct_context(context: &EGLContext) -> Result<DirectContext, GlesError> {
+    unsafe { context.make_current()? };
+    let interface = gpu::gl::Interface::new_load_with(|s| unsafe {
+        crate::backend::egl::get_proc_address(s) as *const _
+    })
+    .ok_or(GlesError::GLFunctionLoaderError)?;
+    gpu::direct_contexts::make_gl(interface, None).ok_or(GlesError::GLFunctionLoaderError)
+}
+
+impl SkiaRenderer {
+    /// Get the supported [`Capabilities`](Capability) of the renderer
+    ///
+    /// # Safety
+    ///
+    /// This operation will cause undefined behavior if the given EGLContext is active in another thread.
+    pub unsafe fn supported_capabilities(context: &EGLContext) -> Result<Vec<Capability>, GlesError> {
+        GlesRenderer::supported_capabilities(context)
+    }
+
+    /// Creates a new OpenGL ES 2 + Skia renderer from a given [`EGLContext`]
+    /// with all [`supported capabilities`](Self::supported_capabilities).
+    ///
+    /// # Safety
+    ///
+    /// This operation will cause undefined behavior if the given EGLContext is active in another thread.
+    ///
+    /// See: [`with_capabilities`](Self::with_capabilities) for more information
+    pub unsafe fn new(context: EGLContext) -> Result<SkiaRenderer, GlesError> {
+        let supported_capabilities = Self::supported_capabilities(&context)?;
+        Self::with_capabilities(context, supported_capabilities)
+    }
+
+    /// Creates a new OpenGL ES 2 + Skia renderer from a given [`EGLContext`]
+    /// with the specified [`Capabilities`](Capability). If a requested [`Capability`] is not supported an
+    /// error will be returned.
+    ///
+    /// # Safety
+    ///
+    /// This operation will cause undefined behavior if the given EGLContext is active in another thread.
+    ///
+    /// See [`GlesRenderer::with_capabilities`] for the implementation details of the underlying renderer.
+    pub unsafe fn with_capabilities(
+        context: EGLContext,
+        capabilities: impl IntoIterator<Item = Capability>,
+    ) -> Result<SkiaRenderer, GlesError> {
+        let skia = create_direct_context(&context)?;
+        let gl = GlesRenderer::with_capabilities(context, capabilities)?;
+        Ok(SkiaRenderer { gl, skia })
+    }
+
+    /// Get access to the underlying [`EGLContext`].
+    ///
+    /// *Note*: Modifying the context state, might result in rendering issues.
+    /// The context state is considerd an implementation detail
+    /// and no guarantee is made about what can or cannot be changed.
+    /// To make sure a certain modification does not interfere with
+    /// the renderer's behaviour, check the source.
+    pub fn egl_context(&self) -> &EGLContext {
+        self.gl.egl_context()
+    }
+
+    /// Run custom code with the skia [`DirectContext`] of this renderer, e.g. to create
+    /// offscreen surfaces or upload images.
+    pub fn with_context<F, R>(&mut self, func: F) -> Result<R, GlesError>
+    where
+        F: FnOnce(&mut DirectContext) -> R,
+    {
+        unsafe {
+            self.gl.egl_context().make_current()?;
+        }
+        self.skia.reset(None);
+        let result = func(&mut self.skia);
+        self.skia.flush_and_submit();
+        Ok(result)
+    }
+}
+
+impl SkiaFrame<'_, '_> {
+    /// Draw onto the currently bound framebuffer using a [`SkiaCanvas`].
+    ///
+    /// The canvas operates in the physical coordinate space of the frame. All pending skia work
+    /// is submitted before returning, so it is correctly ordered with the following draw calls
+    /// of the frame.
+    ///
+    /// Fails with [`GlesError::UnsupportedPixelFormat`] if skia is unable to render into the
+    /// format of the bound framebuffer.
+    #[profiling::function]
+    pub fn with_canvas<F, R>(&mut self, func: F) -> Result<R, GlesError>
+    where
+        F: FnOnce(&mut SkiaCanvas<'_>) -> R,
+    {
+        let format = self.format.ok_or(GlesError::UnknownPixelFormat)?;
+        let (gl_format, color_type) =
+            framebuffer_format(format).ok_or(GlesError::UnsupportedPixelFormat(format))?;
+
+        let frame = self.frame.as_mut().unwrap();
+        let size = self.output_size;
+        let fboid = frame.with_context(|gl| unsafe {
+            let mut fbo = 0;
+            gl.GetIntegerv(ffi::FRAMEBUFFER_BINDING, &mut fbo);
+            fbo as u32
+        })?;
+
+        // skia caches the GL state, which the GL renderer modified since the last draw
+        self.skia.reset(None);
+        let target = gpu::backend_render_targets::make_gl(
+            (size.w, size.h),
+            0,
+            0,
+            FramebufferInfo {
+                fboid,
+                format: gl_format,
+                ..Default::default()
+            },
+        );
+        let mut surface = gpu::surfaces::wrap_backend_render_target(
+            self.skia,
+            &target,
+            SurfaceOrigin::BottomLeft,
+            color_type,
+            None,
+            None,
+        )
+        .ok_or(GlesError::FramebufferBindingError)?;
+
+        let canvas = surface.canvas();
+        canvas.set_matrix(&output_matrix(self.transform, size).into());
+        let result = func(&mut SkiaCanvas {
+            canvas,
+            skia: self.skia,
+            borrowed_textures: &mut self.borrowed_textures,
+        });
+        drop(surface);
+        self.skia.flush_and_submit();
+
+        // restore the state set up by `GlesRenderer::render`
+        frame.with_context(|gl| unsafe {
+            gl.BindFramebuffer(ffi::FRAMEBUFFER, fboid);
+            gl.Viewport(0, 0, size.w, size.h);
+            gl.Scissor(0, 0, size.w, size.h);
+            gl.Enable(ffi::SCISSOR_TEST);
+            gl.Enable(ffi::BLEND);
+            gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+        })?;
+
+        Ok(result)
+    }
+}
+
+impl<'a> SkiaCanvas<'a> {
+    /// Wrap a texture of the renderer into a skia [`Image`] to draw it onto this canvas.
+    ///
+    /// The texture is kept alive until the frame is finished.
+    ///
+    /// Returns `None` for external textures and formats skia is unable to sample.
+    pub fn texture_image(&mut self, texture: &GlesTexture) -> Option<TextureImage<'a>> {
+        if texture.is_external() {
+            return None;
+        }
+        let format = texture.format()?;
+        let (color_type, alpha_type) = skia_color_type(format)?;
+        let internal_format = match fourcc_to_gl_formats(format)?.0 {
+            ffi::BGRA_EXT => BGRA8_EXT,
+            format => format,
+        };
+        let size = texture.size();
+
+        let backend_texture = unsafe {
+            gpu::backend_textures::make_gl(
+                (size.w, size.h),
+                Mipmapped::No,
+                TextureInfo {
+                    target: ffi::TEXTURE_2D,
+                    id: texture.tex_id(),
+                    format: internal_format,
+                    ..Default::default()
+                },
+                "smithay texture",
+            )
+        };
+        let origin = if texture.is_y_inverted() {
+            SurfaceOrigin::BottomLeft
+        } else {
+            SurfaceOrigin::TopLeft
+        };
+        let image = gpu::images::borrow_texture_from(
+            self.skia,
+            &backend_texture,
+            origin,
+            color_type,
+            alpha_type,
+            None,
+        )?;
+        self.borrowed_textures.push(texture.clone());
+        Some(TextureImage {
+            image,
+            _canvas: PhantomData,
+        })
+    }
+}
+
+// Sized GL format and skia color type to render into a framebuffer of the given format
+fn framebuffer_format(format: Fourcc) -> Option<(u32, ColorType)> {
+    Some(match format {
+        Fourcc::Abgr8888 | Fourcc::Xbgr8888 => (ffi::RGBA8, ColorType::RGBA8888),
+        Fourcc::Argb8888 | Fourcc::Xrgb8888 => (BGRA8_EXT, ColorType::BGRA8888),
+        Fourcc::Bgr888 => (ffi::RGB8, ColorType::RGB888x),
+        Fourcc::Abgr2101010 | Fourcc::Xbgr2101010 => (ffi::RGB10_A2, ColorType::RGBA1010102),
+        Fourcc::Abgr16161616f | Fourcc::Xbgr16161616f => (ffi::RGBA16F, ColorType::RGBAF16),
+        _ => return None,
+    })
+}
+
+fn skia_color_type(format: Fourcc) -> Option<(ColorType, AlphaType)> {
+    Some(match format {
+        Fourcc::Abgr8888 => (ColorType::RGBA8888, AlphaType::Premul),
+        Fourcc::Xbgr8888 => (ColorType::RGB888x, AlphaType::Opaque),
+        Fourcc::Argb8888 => (ColorType::BGRA8888, AlphaType::Premul),
+        Fourcc::Xrgb8888 => (ColorType::BGRA8888, AlphaType::Opaque),
+        Fourcc::Abgr2101010 => (ColorType::RGBA1010102, AlphaType::Premul),
+        Fourcc::Xbgr2101010 => (ColorType::RGB101010x, AlphaType::Opaque),
+        Fourcc::Abgr16161616f => (ColorType::RGBAF16, AlphaType::Premul),
+        _ => return None,
+    })
+}
+
+// Maps the physical coordinate space of a frame onto the framebuffer
+fn output_matrix(transform: Transform, framebuffer_size: Size<i32, Physical>) -> Matrix {
+    let area = transform.transform_size(framebuffer_size.to_f64());
+    let origin = transform.transform_point_in(Point::<f64, Physical>::from((0., 0.)), &area);
+    let x = transform.transform_point_in(Point::from((1., 0.)), &area) - origin;
+    let y = transform.transform_point_in(Point::from((0., 1.)), &area) - origin;
+    Matrix::new_all(
+        x.x as f32,
+        y.x as f32,
+        origin.x as f32,
+        x.y as f32,
+        y.y as f32,
+        origin.y as f32,
+        0.,
+        0.,
+        1.,
+    )
+}
+
+// TODO: When GAT, use TryFrom and be generic over the Error,
+//  so `TryFrom<GlesRenderer, Error=Infaillable> for GlesRenderer` qualifies
+//  just as `TryFrom<GlesRenderer, Error=GlesError> for SkiaRenderer`
+impl TryFrom<GlesRenderer> for SkiaRenderer {
+    type Error = GlesError;
+
+    #[inline]
+    fn try_from(renderer: GlesRenderer) -> Result<SkiaRenderer, GlesError> {
+        let skia = create_direct_context(renderer.egl_context())?;
+        Ok(SkiaRenderer { gl: renderer, skia })
+    }
+}
+
+impl Borrow<GlesRenderer> for SkiaRenderer {
+    #[inline]
+    fn borrow(&self) -> &GlesRenderer {
+        &self.gl
+    }
+}
+
+impl BorrowMut<GlesRenderer> for SkiaRenderer {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut GlesRenderer {
+        &mut self.gl
+    }
+}
+
+impl<'frame, 'buffer> Borrow<GlesFrame<'frame, 'buffer>> for SkiaFrame<'frame, 'buffer> {
+    #[inline]
+    fn borrow(&self) -> &GlesFrame<'frame, 'buffer> {
+        self.frame.as_ref().unwrap()
+    }
+}
+
+impl<'frame, 'buffer> BorrowMut<GlesFrame<'frame, 'buffer>> for SkiaFrame<'frame, 'buffer> {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut GlesFrame<'frame, 'buffer> {
+        self.frame.as_mut().unwrap()
+    }
+}
+
+impl RendererSuper for SkiaRenderer {
+    type Error = GlesError;
+    type TextureId = GlesTexture;
+    type Framebuffer<'buffer> = GlesTarget<'buffer>;
+    type Frame<'frame, 'buffer>
+        = SkiaFrame<'frame, 'buffer>
+    where
+        'buffer: 'frame,
+        Self: 'frame;
+}
+
+impl Renderer for SkiaRenderer {
+    fn context_id(&self) -> ContextId<GlesTexture> {
+        self.gl.context_id()
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.gl.downscale_filter(filter)
+    }
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.gl.upscale_filter(filter)
+    }
+
+    fn set_debug_flags(&mut self, flags: DebugFlags) {
+        self.gl.set_debug_flags(flags)
+    }
+    fn debug_flags(&self) -> DebugFlags {
+        self.gl.debug_flags()
+    }
+
+    #[profiling::function]
+    fn render<'frame, 'buffer>(
+        &'frame mut self,
+        target: &'frame mut GlesTarget<'buffer>,
+        output_size: Size<i32, Physical>,
+        transform: Transform,
+    ) -> Result<SkiaFrame<'frame, 'buffer>, Self::Error>
+    where
+        'buffer: 'frame,
+    {
+        let format = Texture::format(&*target);
+        let frame = self.gl.render(target, output_size, transform)?;
+        Ok(SkiaFrame {
+            frame: Some(frame),
+            skia: &mut self.skia,
+            output_size,
+            transform,
+            format,
+            borrowed_textures: Vec::new(),
+        })
+    }
+
+    #[profiling::function]
+    fn wait(&mut self, sync: &sync::SyncPoint) -> Result<(), Self::Error> {
+        self.gl.wait(sync)
+    }
+
+    fn frame_arena(&self) -> Option<&FrameArena> {
+        self.gl.frame_arena()
+    }
+
+    #[profiling::function]
+    fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
+        self.gl.cleanup_texture_cache()?;
+        self.skia
+            .perform_deferred_cleanup(std::time::Duration::ZERO, None);
+        Ok(())
+    }
+}
+
+impl Frame for SkiaFrame<'_, '_> {
+    type Error = GlesError;
+    type TextureId = GlesTexture;
+
+    fn context_id(&self) -> ContextId<GlesTexture> {
+        self.frame.as_ref().unwrap().context_id()
+    }
+
+    #[profiling::function]
+    fn clear(&mut self, color: Color32F, at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().clear(color, at)
+    }
+
+    #[profiling::function]
+    fn draw_solid(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        color: Color32F,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().draw_solid(dst, damage, color)
+    }
+
+    #[profiling::function]
+    fn render_texture_from_to(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        Frame::render_texture_from_to(
+            self.frame.as_mut().unwrap(),
+            texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            src_transform,
+            alpha,
+        )
+    }
+
+    #[profiling::function]
+    fn render_texture_clipped(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        clip: &ClipRegion,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().render_texture_clipped(
+            texture,
+            src,
+            dst,
+            damage,
+            opaque_regions,
+            src_transform,
+            alpha,
+            clip,
+        )
+    }
+
+    fn transformation(&self) -> Transform {
+        self.frame.as_ref().unwrap().transformation()
+    }
+
+    fn arena(&self) -> Option<&FrameArena> {
+        self.frame.as_ref().unwrap().arena()
+    }
+
+    #[profiling::function]
+    fn render_texture_at(
+        &mut self,
+        texture: &Self::TextureId,
+        pos: crate::utils::Point<i32, Physical>,
+        texture_scale: i32,
+        output_scale: impl Into<crate::utils::Scale<f64>>,
+        src_transform: Transform,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().render_texture_at(
+            texture,
+            pos,
+            texture_scale,
+            output_scale,
+            src_transform,
+            damage,
+            opaque_regions,
+            alpha,
+        )
+    }
+
+    #[profiling::function]
+    fn wait(&mut self, sync: &sync::SyncPoint) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().wait(sync)
+    }
+
+    #[profiling::function]
+    fn finish(mut self) -> Result<sync::SyncPoint, Self::Error> {
+        self.finish_internal()
+    }
+}
+
+impl SkiaFrame<'_, '_> {
+    #[profiling::function]
+    fn finish_internal(&mut self) -> Result<sync::SyncPoint, GlesError> {
+        if let Some(frame) = self.frame.take() {
+            let result = frame.finish();
+            self.borrowed_textures.clear();
+            result
+        } else {
+            Ok(sync::SyncPoint::default())
+        }
+    }
+}
+
+impl Drop for SkiaFrame<'_, '_> {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish_internal() {
+            warn!("Ignored error finishing SkiaFrame on drop: {}", err);
+        }
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportMemWl for SkiaRenderer {
+    #[profiling::function]
+    fn import_shm_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        surface: Option<&crate::wayland::compositor::SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<GlesTexture, GlesError> {
+        self.gl.import_shm_buffer(buffer, surface, damage)
+    }
+
+    fn shm_formats(&self) -> Box<dyn Iterator<Item = wl_shm::Format>> {
+        self.gl.shm_formats()
+    }
+}
+
+impl ImportMem for SkiaRenderer {
+    #[profiling::function]
+    fn import_memory(
+        &mut self,
+        data: &[u8],
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+        flipped: bool,
+    ) -> Result<GlesTexture, GlesError> {
+        self.gl.import_memory(data, format, size, flipped)
+    }
+
+    #[profiling::function]
+    fn update_memory(
+        &mut self,
+        texture: &Self::TextureId,
+        data: &[u8],
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), Self::Error> {
+        self.gl.update_memory(texture, data, region)
+    }
+
+    fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
+        self.gl.mem_formats()
+    }
+
+    fn mem_format_conversion(&self, format: Fourcc) -> Option<MemFormatConversion> {
+        self.gl.mem_format_conversion(format)
+    }
+}
+
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+impl ImportEgl for SkiaRenderer {
+    fn bind_wl_display(
+        &mut self,
+        display: &wayland_server::DisplayHandle,
+    ) -> Result<(), crate::backend::egl::Error> {
+        self.gl.bind_wl_display(display)
+    }
+
+    fn unbind_wl_display(&mut self) {
+        self.gl.unbind_wl_display()
+    }
+
+    fn egl_reader(&self) -> Option<&EGLBufferReader> {
+        self.gl.egl_reader()
+    }
+
+    #[profiling::function]
+    fn import_egl_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        surface: Option<&crate::wayland::compositor::SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<GlesTexture, GlesError> {
+        self.gl.import_egl_buffer(buffer, surface, damage)
+    }
+}
+
+impl QueryCapabilities for SkiaRenderer {
+    fn query_capabilities(&self) -> Capabilities {
+        self.gl.query_capabilities()
+    }
+}
+
+impl PowerHooks for SkiaRenderer {
+    type Error = GlesError;
+
+    fn suspend(&mut self) -> Result<(), GlesError> {
+        self.gl.suspend()
+    }
+
+    fn resume(&mut self) -> Result<(), GlesError> {
+        self.gl.resume()
+    }
+}
+
+impl ImportDma for SkiaRenderer {
+    #[profiling::function]
+    fn import_dmabuf(
+        &mut self,
+        buffer: &Dmabuf,
+        damage: Option<&[Rectangle<i32, BufferCoord>]>,
+    ) -> Result<GlesTexture, GlesError> {
+        self.gl.import_dmabuf(buffer, damage)
+    }
+    fn dmabuf_formats(&self) -> FormatSet {
+        self.gl.dmabuf_formats()
+    }
+    fn has_dmabuf_format(&self, format: Format) -> bool {
+        self.gl.has_dmabuf_format(format)
+    }
+    fn dmabuf_import_path(&self, format: Format) -> Option<ImportPath> {
+        self.gl.dmabuf_import_path(format)
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportDmaWl for SkiaRenderer {}
+
+impl ExportMem for SkiaRenderer {
+    type TextureMapping = GlesMapping;
+
+    #[profiling::function]
+    fn copy_framebuffer(
+        &mut self,
+        from: &GlesTarget<'_>,
+        region: Rectangle<i32, BufferCoord>,
+        format: Fourcc,
+    ) -> Result<Self::TextureMapping, Self::Error> {
+        self.gl.copy_framebuffer(from, region, format)
+    }
+
+    #[profiling::function]
+    fn copy_texture(
+        &mut self,
+        texture: &Self::TextureId,
+        region: Rectangle<i32, BufferCoord>,
+        format: Fourcc,
+    ) -> Result<Self::TextureMapping, Self::Error> {
+        self.gl.copy_texture(texture, region, format)
+    }
+
+    fn can_read_texture(&mut self, texture: &Self::TextureId) -> Result<bool, Self::Error> {
+        self.gl.can_read_texture(texture)
+    }
+
+    #[profiling::function]
+    fn map_texture<'a>(
+        &mut self,
+        texture_mapping: &'a Self::TextureMapping,
+    ) -> Result<&'a [u8], Self::Error> {
+        self.gl.map_texture(texture_mapping)
+    }
+}
+
+impl<T> Bind<T> for SkiaRenderer
+where
+    GlesRenderer: Bind<T>,
+{
+    #[profiling::function]
+    fn bind<'a>(&mut self, target: &'a mut T) -> Result<GlesTarget<'a>, GlesError> {
+        self.gl.bind(target)
+    }
+    fn supported_formats(&self) -> Option<FormatSet> {
+        self.gl.supported_formats()
+    }
+}
+
+impl<T> Offscreen<T> for SkiaRenderer
+where
+    GlesRenderer: Offscreen<T>,
+{
+    #[profiling::function]
+    fn create_buffer(&mut self, format: Fourcc, size: Size<i32, BufferCoord>) -> Result<T, GlesError> {
+        self.gl.create_buffer(format, size)
+    }
+}
+
+impl<'buffer> BlitFrame<GlesTarget<'buffer>> for SkiaFrame<'_, 'buffer> {
+    fn blit_to(
+        &mut self,
+        to: &mut GlesTarget<'buffer>,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().blit_to(to, src, dst, filter)
+    }
+
+    fn blit_from(
+        &mut self,
+        from: &GlesTarget<'buffer>,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<(), Self::Error> {
+        self.frame.as_mut().unwrap().blit_from(from, src, dst, filter)
+    }
+}
+
+impl Blit for SkiaRenderer {
+    #[profiling::function]
+    fn blit(
+        &mut self,
+        from: &GlesTarget<'_>,
+        to: &mut GlesTarget<'_>,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<sync::SyncPoint, GlesError> {
+        self.gl.blit(from, to, src, dst, filter)
+    }
+}
+
+impl RenderElement<SkiaRenderer> for PixelShaderElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut SkiaFrame<'_, '_>,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        RenderElement::<GlesRenderer>::draw(self, frame.borrow_mut(), src, dst, damage, opaque_regions)
+    }
+
+    fn underlying_storage(&self, renderer: &mut SkiaRenderer) -> Option<UnderlyingStorage<'_>> {
+        RenderElement::<GlesRenderer>::underlying_storage(self, renderer.borrow_mut())
+    }
+}
+
+impl RenderElement<SkiaRenderer> for TextureShaderElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut SkiaFrame<'_, '_>,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        RenderElement::<GlesRenderer>::draw(self, frame.borrow_mut(), src, dst, damage, opaque_regions)
+    }
+
+    fn underlying_storage(&self, renderer: &mut SkiaRenderer) -> Option<UnderlyingStorage<'_>> {
+        RenderElement::<GlesRenderer>::underlying_storage(self, renderer.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{
+        allocator::format::get_opaque, renderer::gles::format::gl_internal_format_to_fourcc,
+    };
+
+    #[test]
+    fn framebuffer_formats() {
+        // every format a `GlesTarget` may report must be renderable by skia
+        for format in [ffi::RGBA8, ffi::BGRA_EXT, ffi::RGB8, ffi::RGB10_A2, ffi::RGBA16F] {
+            let fourcc = gl_internal_format_to_fourcc(format).unwrap();
+            assert!(framebuffer_format(fourcc).is_some(), "{:?}", fourcc);
+        }
+        assert_eq!(
+            framebuffer_format(Fourcc::Argb8888),
+            Some((BGRA8_EXT, ColorType::BGRA8888))
+        );
+        assert_eq!(
+            framebuffer_format(Fourcc::Xbgr2101010),
+            Some((ffi::RGB10_A2, ColorType::RGBA1010102))
+        );
+        assert_eq!(framebuffer_format(Fourcc::Nv12), None);
+    }
+
+    #[test]
+    fn texture_color_types() {
+        for format in [ffi::RGBA8, ffi::BGRA_EXT, ffi::RGB10_A2, ffi::RGBA16F] {
+            let fourcc = gl_internal_format_to_fourcc(format).unwrap();
+            let (_, alpha_type) = skia_color_type(fourcc).unwrap();
+            assert_eq!(alpha_type, AlphaType::Premul);
+            let (_, alpha_type) = skia_color_type(get_opaque(fourcc).unwrap()).unwrap();
+            assert_eq!(alpha_type, AlphaType::Opaque);
+        }
+        assert_eq!(skia_color_type(Fourcc::Yuyv), None);
+    }
+
+    #[test]
+    fn output_matrix_matches_transform() {
+        let size = Size::<i32, Physical>::from((1920, 1080));
+        for transform in [
+            Transform::Normal,
+            Transform::_90,
+            Transform::_180,
+            Transform::_270,
+            Transform::Flipped,
+            Transform::Flipped90,
+            Transform::Flipped180,
+            Transform::Flipped270,
+        ] {
+            let matrix = output_matrix(transform, size);
+            let area = transform.transform_size(size.to_f64());
+            for point in [(0., 0.), (100., 20.), (area.w, area.h)] {
+                let expected = transform.transform_point_in(Point::<f64, Physical>::from(point), &area);
+                let mapped = matrix.map_point((point.0 as f32, point.1 as f32));
+                assert_eq!(
+                    (mapped.x, mapped.y),
+                    (expected.x as f32, expected.y as f32),
+                    "{:?}",
+                    transform
+                );
+            }
+        }
+        assert!(output_matrix(Transform::Normal, size).is_identity());
+    }
+}
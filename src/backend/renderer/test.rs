@@ -1,5 +1,7 @@
 #![allow(missing_docs)]
 
+pub mod snapshot;
+
 #[cfg(all(
     feature = "wayland_frontend",
     feature = "use_system_lib",
@@ -16,8 +18,8 @@ use crate::{
     backend::{
         allocator::{dmabuf::Dmabuf, Fourcc},
         renderer::{
-            sync::SyncPoint, DebugFlags, Frame, ImportDma, ImportMem, Renderer, RendererSuper, Texture,
-            TextureFilter,
+            sync::SyncPoint, Bind, DebugFlags, Frame, ImportDma, ImportMem, Offscreen, Renderer,
+            RendererSuper, Texture, TextureFilter,
         },
         SwapBuffersError,
     },
@@ -89,12 +91,14 @@ impl Renderer for DummyRenderer {
         &'frame mut self,
         _target: &'frame mut DummyFramebuffer,
         _size: Size<i32, Physical>,
-        _dst_transform: Transform,
+        dst_transform: Transform,
     ) -> Result<DummyFrame, Self::Error>
     where
         'buffer: 'frame,
     {
-        Ok(DummyFrame)
+        Ok(DummyFrame {
+            transform: dst_transform,
+        })
     }
 
     fn wait(&mut self, sync: &SyncPoint) -> Result<(), Self::Error> {
@@ -211,6 +215,25 @@ impl ImportEgl for DummyRenderer {
 #[cfg(feature = "wayland_frontend")]
 impl ImportDmaWl for DummyRenderer {}
 
+impl Bind<DummyTexture> for DummyRenderer {
+    fn bind<'a>(&mut self, _target: &'a mut DummyTexture) -> Result<DummyFramebuffer, Self::Error> {
+        Ok(DummyFramebuffer)
+    }
+}
+
+impl Offscreen<DummyTexture> for DummyRenderer {
+    fn create_buffer(
+        &mut self,
+        _format: Fourcc,
+        size: Size<i32, Buffer>,
+    ) -> Result<DummyTexture, Self::Error> {
+        Ok(DummyTexture {
+            width: size.w as u32,
+            height: size.h as u32,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct DummyFramebuffer;
 
@@ -229,7 +252,9 @@ impl Texture for DummyFramebuffer {
 }
 
 #[derive(Debug)]
-pub struct DummyFrame;
+pub struct DummyFrame {
+    transform: Transform,
+}
 
 impl Frame for DummyFrame {
     type Error = DummyError;
@@ -266,7 +291,7 @@ impl Frame for DummyFrame {
     }
 
     fn transformation(&self) -> Transform {
-        Transform::Normal
+        self.transform
     }
 
     fn wait(&mut self, sync: &SyncPoint) -> Result<(), Self::Error> {
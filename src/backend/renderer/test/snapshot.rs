@@ -0,0 +1,361 @@
+//! Snapshot based regression testing of renderers
+//!
+//! This module allows rendering a known set of [`RenderElement`]s into an offscreen buffer
+//! and comparing the result against stored reference images. As any renderer supporting
+//! [`Offscreen`] and [`ExportMem`] can be used, the same element trees can be rendered with
+//! different renderers (e.g. gles and pixman) to validate they produce visually identical results.
+//!
+//! Reference images are stored as png files and require the `image_png` feature.
+//! If the environment variable `SMITHAY_UPDATE_SNAPSHOTS` is set, missing or
+//! mismatching references are (re-)written instead of failing the test.
+//!
+//! ```no_run
+//! # #[cfg(all(feature = "image_png", feature = "renderer_pixman"))]
+//! # fn main() {
+//! # use smithay::backend::renderer::{
+//! #     element::solid::SolidColorRenderElement,
+//! #     pixman::PixmanRenderer,
+//! #     test::snapshot::{render_snapshot, SnapshotStore},
+//! # };
+//! # use smithay::reexports::pixman;
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! let mut renderer = PixmanRenderer::new().unwrap();
+//! let image = render_snapshot::<_, pixman::Image<'static, 'static>, _>(
+//!     &mut renderer,
+//!     (64, 64),
+//!     &elements,
+//!     [0.0, 0.0, 0.0, 1.0],
+//! )
+//! .unwrap();
+//!
+//! SnapshotStore::new("tests/snapshots").with_tolerance(2).assert_matches("solid", &image);
+//! # }
+//! # #[cfg(not(all(feature = "image_png", feature = "renderer_pixman")))]
+//! # fn main() {}
+//! ```
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{self, OutputDamageTracker},
+            element::RenderElement,
+            utils::{CaptureError, RgbaImage},
+            Bind, Color32F, ExportMem, Offscreen, Renderer,
+        },
+    },
+    utils::{Physical, Rectangle, Size, Transform},
+};
+
+/// Result of comparing two images with [`compare_images`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// The images have different sizes, no pixels were compared
+    pub size_mismatch: bool,
+    /// Number of pixels with at least one channel differing more than the tolerance
+    pub mismatched_pixels: usize,
+    /// Largest difference of any channel of any pixel
+    pub max_difference: u8,
+    /// Location of the first mismatching pixel
+    pub first_mismatch: Option<(i32, i32)>,
+}
+
+impl ImageDiff {
+    /// Returns true if the compared images matched within the tolerance
+    pub fn is_match(&self) -> bool {
+        !self.size_mismatch && self.mismatched_pixels == 0
+    }
+}
+
+/// Compare two images allowing each channel of each pixel to differ by at most `tolerance`.
+pub fn compare_images(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> ImageDiff {
+    if actual.size() != expected.size() {
+        return ImageDiff {
+            size_mismatch: true,
+            ..Default::default()
+        };
+    }
+
+    let width = actual.size().w.max(1) as usize;
+    let mut diff = ImageDiff::default();
+    for (idx, (a, e)) in actual
+        .data()
+        .chunks_exact(4)
+        .zip(expected.data().chunks_exact(4))
+        .enumerate()
+    {
+        let difference = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap_or(0);
+        diff.max_difference = diff.max_difference.max(difference);
+        if difference > tolerance {
+            diff.mismatched_pixels += 1;
+            if diff.first_mismatch.is_none() {
+                diff.first_mismatch = Some(((idx % width) as i32, (idx / width) as i32));
+            }
+        }
+    }
+    diff
+}
+
+/// Errors thrown by [`render_snapshot`]
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError<E: std::error::Error> {
+    /// The renderer failed to create or bind the offscreen buffer
+    #[error(transparent)]
+    Renderer(E),
+    /// Rendering the elements failed
+    #[error(transparent)]
+    Render(damage::Error<E>),
+    /// Reading back the rendered image failed
+    #[error(transparent)]
+    Capture(CaptureError<E>),
+}
+
+/// Render the given elements into a new offscreen buffer of the given size and read back the result.
+///
+/// Elements are expected in front-to-back order, like for [`OutputDamageTracker::render_output`].
+/// The output is rendered with a scale of `1.0` and without any transform.
+pub fn render_snapshot<R, T, E>(
+    renderer: &mut R,
+    size: impl Into<Size<i32, Physical>>,
+    elements: &[E],
+    clear_color: impl Into<Color32F>,
+) -> Result<RgbaImage, SnapshotError<R::Error>>
+where
+    R: Renderer + Offscreen<T> + ExportMem,
+    E: RenderElement<R>,
+{
+    let size = size.into();
+    let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+
+    let mut buffer = renderer
+        .create_buffer(Fourcc::Abgr8888, buffer_size)
+        .map_err(SnapshotError::Renderer)?;
+    let mut framebuffer = renderer.bind(&mut buffer).map_err(SnapshotError::Renderer)?;
+
+    let mut damage_tracker = OutputDamageTracker::new(size, 1.0, Transform::Normal);
+    let result = damage_tracker
+        .render_output(renderer, &mut framebuffer, 0, elements, clear_color)
+        .map_err(SnapshotError::Render)?;
+    renderer.wait(&result.sync).map_err(SnapshotError::Renderer)?;
+
+    renderer
+        .capture_output_to_image(&framebuffer, Rectangle::from_size(buffer_size))
+        .map_err(SnapshotError::Capture)
+}
+
+/// Directory of reference images for snapshot tests
+#[cfg(feature = "image_png")]
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: std::path::PathBuf,
+    tolerance: u8,
+    update: bool,
+}
+
+#[cfg(feature = "image_png")]
+impl SnapshotStore {
+    /// Environment variable causing references to be (re-)written instead of compared
+    pub const UPDATE_ENV: &'static str = "SMITHAY_UPDATE_SNAPSHOTS";
+
+    /// Create a store for reference images in the given directory
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        SnapshotStore {
+            dir: dir.into(),
+            tolerance: 0,
+            update: std::env::var_os(Self::UPDATE_ENV).is_some(),
+        }
+    }
+
+    /// Set the per-channel tolerance used to compare images
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Path of the reference image with the given name
+    pub fn reference_path(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{}.png", name))
+    }
+
+    /// Compare an image with the stored reference of the given name
+    ///
+    /// Returns `Ok(None)` if no reference exists.
+    pub fn compare(&self, name: &str, image: &RgbaImage) -> Result<Option<ImageDiff>, png::DecodingError> {
+        let path = self.reference_path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let reference = RgbaImage::load_png(&path)?;
+        Ok(Some(compare_images(image, &reference, self.tolerance)))
+    }
+
+    /// Assert that an image matches the stored reference of the given name.
+    ///
+    /// On mismatch the actual image is written next to the reference as `<name>.actual.png`.
+    ///
+    /// ## Panics
+    ///
+    /// If the reference is missing or does not match and updating is not enabled.
+    pub fn assert_matches(&self, name: &str, image: &RgbaImage) {
+        let path = self.reference_path(name);
+        let diff = self
+            .compare(name, image)
+            .unwrap_or_else(|err| panic!("Failed to load reference {}: {}", path.display(), err));
+
+        match diff {
+            Some(diff) if diff.is_match() => {}
+            _ if self.update => {
+                std::fs::create_dir_all(&self.dir).expect("Failed to create snapshot directory");
+                image
+                    .save_png(&path)
+                    .unwrap_or_else(|err| panic!("Failed to write reference {}: {}", path.display(), err));
+            }
+            None => panic!(
+                "Missing reference {}, run with {} set to create it",
+                path.display(),
+                Self::UPDATE_ENV
+            ),
+            Some(diff) => {
+                let actual = self.dir.join(format!("{}.actual.png", name));
+                let _ = image.save_png(&actual);
+                panic!(
+                    "Snapshot {} does not match reference (tolerance {}): {:?}, actual image written to {}",
+                    name,
+                    self.tolerance,
+                    diff,
+                    actual.display()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "renderer_pixman"))]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id, Kind},
+        pixman::PixmanRenderer,
+        utils::CommitCounter,
+    };
+
+    #[cfg(feature = "image_png")]
+    const SNAPSHOT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+    fn solid(loc: (i32, i32), size: (i32, i32), color: [f32; 4]) -> SolidColorRenderElement {
+        SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::new(loc.into(), size.into()),
+            CommitCounter::default(),
+            color,
+            Kind::Unspecified,
+        )
+    }
+
+    // overlapping elements, leaving the right edge to the clear color
+    #[cfg(feature = "image_png")]
+    fn overlapping_elements() -> [SolidColorRenderElement; 3] {
+        [
+            solid((2, 2), (6, 6), [1.0, 0.0, 0.0, 1.0]),
+            solid((6, 6), (8, 8), [0.0, 1.0, 0.0, 1.0]),
+            solid((0, 0), (12, 16), [0.0, 0.0, 1.0, 1.0]),
+        ]
+    }
+
+    #[cfg(all(feature = "image_png", feature = "renderer_gl"))]
+    fn gles_renderer() -> Option<crate::backend::renderer::gles::GlesRenderer> {
+        use crate::backend::{
+            egl::{native::EGLSurfacelessDisplay, EGLContext, EGLDisplay},
+            renderer::gles::GlesRenderer,
+        };
+
+        // SAFETY: the display is only used by the renderer created from it
+        let display = unsafe { EGLDisplay::new(EGLSurfacelessDisplay) }.ok()?;
+        let context = EGLContext::new(&display).ok()?;
+        // SAFETY: the context is not shared with anything else
+        unsafe { GlesRenderer::new(context) }.ok()
+    }
+
+    #[cfg(feature = "image_png")]
+    #[test]
+    fn overlapping_elements_parity() {
+        let store = SnapshotStore::new(SNAPSHOT_DIR);
+
+        let mut pixman = PixmanRenderer::new().unwrap();
+        let image = render_snapshot::<_, pixman::Image<'static, 'static>, _>(
+            &mut pixman,
+            (16, 16),
+            &overlapping_elements(),
+            [0.0, 0.0, 0.0, 1.0],
+        )
+        .unwrap();
+        store.assert_matches("overlapping_elements", &image);
+
+        // gles has to match the reference rendered by pixman, skip it without a gpu
+        #[cfg(feature = "renderer_gl")]
+        match gles_renderer() {
+            Some(mut gles) => {
+                let image = render_snapshot::<_, crate::backend::renderer::gles::GlesRenderbuffer, _>(
+                    &mut gles,
+                    (16, 16),
+                    &overlapping_elements(),
+                    [0.0, 0.0, 0.0, 1.0],
+                )
+                .unwrap();
+                let store = SnapshotStore {
+                    update: false,
+                    ..store.with_tolerance(1)
+                };
+                store.assert_matches("overlapping_elements", &image);
+            }
+            None => eprintln!("No EGL display available, skipping the gles snapshot"),
+        }
+    }
+
+    #[test]
+    fn solid_elements_pixman() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let elements = [
+            solid((2, 2), (4, 4), [1.0, 0.0, 0.0, 1.0]),
+            solid((0, 0), (8, 8), [0.0, 0.0, 1.0, 1.0]),
+        ];
+
+        let image = render_snapshot::<_, pixman::Image<'static, 'static>, _>(
+            &mut renderer,
+            (8, 8),
+            &elements,
+            [0.0, 0.0, 0.0, 1.0],
+        )
+        .unwrap();
+
+        let mut expected = Vec::new();
+        for y in 0..8 {
+            for x in 0..8 {
+                if (2..6).contains(&x) && (2..6).contains(&y) {
+                    expected.extend_from_slice(&[255, 0, 0, 255]);
+                } else {
+                    expected.extend_from_slice(&[0, 0, 255, 255]);
+                }
+            }
+        }
+        let expected = RgbaImage::from_raw((8, 8), expected).unwrap();
+
+        let diff = compare_images(&image, &expected, 0);
+        assert!(diff.is_match(), "{:?}", diff);
+    }
+
+    #[test]
+    fn compare_tolerance() {
+        let a = RgbaImage::from_raw((2, 1), vec![10, 10, 10, 255, 0, 0, 0, 255]).unwrap();
+        let b = RgbaImage::from_raw((2, 1), vec![12, 10, 10, 255, 0, 0, 0, 255]).unwrap();
+        assert!(compare_images(&a, &b, 2).is_match());
+
+        let diff = compare_images(&a, &b, 1);
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_difference, 2);
+        assert_eq!(diff.first_mismatch, Some((0, 0)));
+
+        let c = RgbaImage::from_raw((1, 2), vec![0; 8]).unwrap();
+        assert!(compare_images(&a, &c, 255).size_mismatch);
+    }
+}
@@ -0,0 +1,255 @@
+//! Helpers for reading back rendered contents into memory
+
+use std::fmt;
+
+use crate::{
+    backend::allocator::Fourcc,
+    utils::{Buffer as BufferCoord, Size},
+};
+
+/// An image read back from a renderer
+///
+/// Pixels are stored as tightly packed 8-bit RGBA (in that byte order), with the first row being the top
+/// row of the captured region, which is the layout expected by most image libraries.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    size: Size<i32, BufferCoord>,
+    data: Vec<u8>,
+}
+
+impl fmt::Debug for RgbaImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RgbaImage")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RgbaImage {
+    /// Create a new image from tightly packed RGBA data
+    ///
+    /// Returns `None` if the length of `data` does not match `size`.
+    pub fn from_raw(size: impl Into<Size<i32, BufferCoord>>, data: Vec<u8>) -> Option<Self> {
+        let size = size.into();
+        if size.w < 0 || size.h < 0 || data.len() != size.w as usize * size.h as usize * 4 {
+            return None;
+        }
+        Some(RgbaImage { size, data })
+    }
+
+    /// Size of the image
+    pub fn size(&self) -> Size<i32, BufferCoord> {
+        self.size
+    }
+
+    /// Width of the image
+    pub fn width(&self) -> u32 {
+        self.size.w as u32
+    }
+
+    /// Height of the image
+    pub fn height(&self) -> u32 {
+        self.size.h as u32
+    }
+
+    /// Raw RGBA pixel data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume the image returning the raw RGBA pixel data
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Returns the pixel at the given location, if it is inside the image
+    pub fn pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        if x < 0 || y < 0 || x >= self.size.w || y >= self.size.h {
+            return None;
+        }
+        let offset = (y as usize * self.size.w as usize + x as usize) * 4;
+        self.data[offset..offset + 4].try_into().ok()
+    }
+
+    /// Encode the image as png into the given writer
+    #[cfg(feature = "image_png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width(), self.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()
+    }
+
+    /// Encode the image as png and write it to the file at `path`
+    #[cfg(feature = "image_png")]
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), png::EncodingError> {
+        let file = std::fs::File::create(path)?;
+        self.write_png(std::io::BufWriter::new(file))
+    }
+
+    /// Decode a png image from the given reader
+    ///
+    /// Images without an alpha channel are treated as opaque.
+    #[cfg(feature = "image_png")]
+    pub fn read_png<R: std::io::Read>(reader: R) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|p| [*p, *p, *p, 255]).collect(),
+            png::ColorType::Indexed => unreachable!("Indexed images are expanded by the decoder"),
+        };
+
+        Ok(RgbaImage {
+            size: (info.width as i32, info.height as i32).into(),
+            data,
+        })
+    }
+
+    /// Decode the png image stored in the file at `path`
+    #[cfg(feature = "image_png")]
+    pub fn load_png(path: impl AsRef<std::path::Path>) -> Result<Self, png::DecodingError> {
+        let file = std::fs::File::open(path)?;
+        Self::read_png(std::io::BufReader::new(file))
+    }
+
+    /// Convert pixel data of the given format into an image
+    ///
+    /// `flipped` indicates the rows of `data` are stored bottom-up.
+    pub(crate) fn convert(
+        size: Size<i32, BufferCoord>,
+        format: Fourcc,
+        flipped: bool,
+        data: &[u8],
+    ) -> Result<Self, ConvertError> {
+        // byte offsets of the red, green, blue and alpha channels in a little-endian pixel
+        let (r, g, b, a) = match format {
+            Fourcc::Abgr8888 => (0, 1, 2, Some(3)),
+            Fourcc::Xbgr8888 => (0, 1, 2, None),
+            Fourcc::Argb8888 => (2, 1, 0, Some(3)),
+            Fourcc::Xrgb8888 => (2, 1, 0, None),
+            Fourcc::Rgba8888 => (3, 2, 1, Some(0)),
+            Fourcc::Rgbx8888 => (3, 2, 1, None),
+            Fourcc::Bgra8888 => (1, 2, 3, Some(0)),
+            Fourcc::Bgrx8888 => (1, 2, 3, None),
+            format => return Err(ConvertError::UnsupportedFormat(format)),
+        };
+
+        let width = size.w.max(0) as usize;
+        let height = size.h.max(0) as usize;
+        let stride = width * 4;
+        if data.len() < stride * height {
+            return Err(ConvertError::InvalidSize {
+                expected: stride * height,
+                actual: data.len(),
+            });
+        }
+        let mut out = Vec::with_capacity(stride * height);
+        for row in 0..height {
+            let src_row = if flipped { height - 1 - row } else { row };
+            let src = &data[src_row * stride..(src_row + 1) * stride];
+            for pixel in src.chunks_exact(4) {
+                out.extend_from_slice(&[pixel[r], pixel[g], pixel[b], a.map(|a| pixel[a]).unwrap_or(255)]);
+            }
+        }
+
+        Ok(RgbaImage { size, data: out })
+    }
+}
+
+/// Error converting pixel data into an [`RgbaImage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum ConvertError {
+    /// The format cannot be converted to RGBA
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(Fourcc),
+    /// The data is shorter than the size of the image requires
+    #[error("Expected {expected} bytes of pixel data, got {actual}")]
+    InvalidSize {
+        /// Required length in bytes
+        expected: usize,
+        /// Actual length in bytes
+        actual: usize,
+    },
+}
+
+/// Error returned by [`ExportMem::capture_output_to_image`](crate::backend::renderer::ExportMem::capture_output_to_image)
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError<E: std::error::Error> {
+    /// The renderer failed to read back the framebuffer
+    #[error(transparent)]
+    Renderer(E),
+    /// The renderer returned a format that cannot be converted to RGBA
+    #[error("Unsupported format of the read back pixels: {0}")]
+    UnsupportedFormat(Fourcc),
+    /// The renderer returned less pixel data than the size of the captured region requires
+    #[error("Expected {expected} bytes of pixel data, got {actual}")]
+    InvalidSize {
+        /// Required length in bytes
+        expected: usize,
+        /// Actual length in bytes
+        actual: usize,
+    },
+}
+
+impl<E: std::error::Error> From<ConvertError> for CaptureError<E> {
+    fn from(err: ConvertError) -> Self {
+        match err {
+            ConvertError::UnsupportedFormat(format) => CaptureError::UnsupportedFormat(format),
+            ConvertError::InvalidSize { expected, actual } => CaptureError::InvalidSize { expected, actual },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_flipped_bgra() {
+        // two rows of a single argb pixel each, stored bottom-up
+        let data = [0x30, 0x20, 0x10, 0xff, 0x03, 0x02, 0x01, 0x80];
+        let image = RgbaImage::convert((1, 2).into(), Fourcc::Argb8888, true, &data).unwrap();
+        assert_eq!(image.pixel(0, 0), Some([0x01, 0x02, 0x03, 0x80]));
+        assert_eq!(image.pixel(0, 1), Some([0x10, 0x20, 0x30, 0xff]));
+        assert_eq!(image.pixel(0, 2), None);
+    }
+
+    #[test]
+    fn convert_opaque() {
+        let data = [0x10, 0x20, 0x30, 0x00];
+        let image = RgbaImage::convert((1, 1).into(), Fourcc::Xbgr8888, false, &data).unwrap();
+        assert_eq!(image.data(), &[0x10, 0x20, 0x30, 0xff]);
+    }
+
+    #[test]
+    fn convert_short_data() {
+        let data = [0u8; 4];
+        assert_eq!(
+            RgbaImage::convert((2, 1).into(), Fourcc::Abgr8888, false, &data),
+            Err(ConvertError::InvalidSize {
+                expected: 8,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            RgbaImage::convert((1, 1).into(), Fourcc::Nv12, false, &data),
+            Err(ConvertError::UnsupportedFormat(Fourcc::Nv12))
+        );
+    }
+}
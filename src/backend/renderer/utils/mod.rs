@@ -1,9 +1,14 @@
 //! Utility module for helpers around drawing [`WlSurface`](wayland_server::protocol::wl_surface::WlSurface)s
 //! and [`RenderElement`](super::element::RenderElement)s with [`Renderer`](super::Renderer)s.
 
-use crate::utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Size};
+use crate::utils::{
+    Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform,
+};
 use std::{collections::VecDeque, fmt, sync::Arc};
 
+mod capture;
+pub use self::capture::*;
+
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
 #[cfg(feature = "wayland_frontend")]
@@ -42,6 +47,16 @@ impl CommitCounter {
             .filter(|commit| commit <= self)
             .map(|commit| self.0.wrapping_sub(commit.0))
     }
+
+    /// Offset the counter, e.g. to fold additional state of a wrapping element into it
+    pub(crate) fn offset(self, offset: usize) -> CommitCounter {
+        CommitCounter(self.0.wrapping_add(offset))
+    }
+
+    /// Revert [`CommitCounter::offset`]
+    pub(crate) fn unoffset(self, offset: usize) -> CommitCounter {
+        CommitCounter(self.0.wrapping_sub(offset))
+    }
 }
 
 impl From<usize> for CommitCounter {
@@ -544,3 +559,141 @@ pub struct SurfaceView {
     /// The logical offset for a sub-surface
     pub offset: Point<i32, Logical>,
 }
+
+impl SurfaceView {
+    /// Translates buffer damage into surface-local damage
+    ///
+    /// Applies the inverse buffer transform and scale, crops by the viewport source rectangle
+    /// and scales to the viewport destination size. The result is relative to the surface
+    /// origin, not including [`offset`](SurfaceView::offset).
+    ///
+    /// Returns `None` if the damage lies outside of the viewport source rectangle.
+    pub fn buffer_damage_to_surface(
+        &self,
+        damage: Rectangle<i32, BufferCoord>,
+        buffer_scale: i32,
+        buffer_transform: Transform,
+        buffer_size: Size<i32, BufferCoord>,
+    ) -> Option<Rectangle<i32, Logical>> {
+        // Use f64, the damage might not be dividable by the buffer scale without a rest
+        let rect = damage
+            .to_f64()
+            .to_logical(buffer_scale as f64, buffer_transform, &buffer_size.to_f64())
+            .intersection(self.src)?;
+        Some(self.rect_to_global(rect).to_i32_up())
+    }
+
+    /// Translates surface-local damage into buffer damage
+    ///
+    /// This is the inverse of [`buffer_damage_to_surface`](SurfaceView::buffer_damage_to_surface),
+    /// used for damage submitted with `wl_surface.damage`.
+    ///
+    /// Returns `None` if the damage lies outside of the buffer.
+    pub fn surface_damage_to_buffer(
+        &self,
+        damage: Rectangle<i32, Logical>,
+        buffer_scale: i32,
+        buffer_transform: Transform,
+        buffer_size: Size<i32, BufferCoord>,
+    ) -> Option<Rectangle<i32, BufferCoord>> {
+        let surface_size = buffer_size.to_logical(buffer_scale, buffer_transform);
+        self.rect_to_local(damage)
+            .to_i32_up()
+            .to_buffer(buffer_scale, buffer_transform, &surface_size)
+            .intersection(Rectangle::from_size(buffer_size))
+    }
+
+    pub(crate) fn rect_to_global<N>(&self, rect: Rectangle<N, Logical>) -> Rectangle<f64, Logical>
+    where
+        N: Coordinate,
+    {
+        let scale = self.scale();
+        let mut rect = rect.to_f64();
+        rect.loc -= self.src.loc;
+        rect.upscale(scale)
+    }
+
+    pub(crate) fn rect_to_local<N>(&self, rect: Rectangle<N, Logical>) -> Rectangle<f64, Logical>
+    where
+        N: Coordinate,
+    {
+        let scale = self.scale();
+        let mut rect = rect.to_f64().downscale(scale);
+        rect.loc += self.src.loc;
+        rect
+    }
+
+    fn scale(&self) -> Scale<f64> {
+        Scale::from((
+            self.dst.w as f64 / self.src.size.w,
+            self.dst.h as f64 / self.src.size.h,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SurfaceView;
+    use crate::utils::{Buffer, Logical, Rectangle, Size, Transform};
+
+    fn view(src: Rectangle<f64, Logical>, dst: Size<i32, Logical>) -> SurfaceView {
+        SurfaceView {
+            src,
+            dst,
+            offset: Default::default(),
+        }
+    }
+
+    #[test]
+    fn buffer_damage_scale() {
+        let view = view(Rectangle::from_size((50., 25.).into()), (50, 25).into());
+        let damage = view.buffer_damage_to_surface(
+            Rectangle::<i32, Buffer>::new((10, 10).into(), (20, 20).into()),
+            2,
+            Transform::Normal,
+            (100, 50).into(),
+        );
+        assert_eq!(damage, Some(Rectangle::new((5, 5).into(), (10, 10).into())));
+    }
+
+    #[test]
+    fn buffer_damage_viewport() {
+        // crop to 20x10 at 10,10 and scale it up to 40x20
+        let view = view(
+            Rectangle::new((10., 10.).into(), (20., 10.).into()),
+            (40, 20).into(),
+        );
+        let damage = view.buffer_damage_to_surface(
+            Rectangle::<i32, Buffer>::new((20, 20).into(), (20, 20).into()),
+            2,
+            Transform::Normal,
+            (100, 50).into(),
+        );
+        assert_eq!(damage, Some(Rectangle::new((0, 0).into(), (20, 20).into())));
+
+        let outside = view.buffer_damage_to_surface(
+            Rectangle::<i32, Buffer>::new((0, 0).into(), (10, 10).into()),
+            2,
+            Transform::Normal,
+            (100, 50).into(),
+        );
+        assert_eq!(outside, None);
+    }
+
+    #[test]
+    fn transformed_damage_round_trip() {
+        let buffer_size = Size::<i32, Buffer>::from((50, 100));
+        let surface_size = buffer_size.to_logical(1, Transform::_90);
+        let view = view(Rectangle::from_size(surface_size.to_f64()), surface_size);
+
+        let surface_damage = Rectangle::<i32, Logical>::new((0, 0).into(), (10, 5).into());
+        let buffer_damage = view
+            .surface_damage_to_buffer(surface_damage, 1, Transform::_90, buffer_size)
+            .unwrap();
+        assert_eq!(buffer_damage.size, (5, 10).into());
+        assert_eq!(
+            view.buffer_damage_to_surface(buffer_damage, 1, Transform::_90, buffer_size),
+            Some(surface_damage)
+        );
+    }
+}
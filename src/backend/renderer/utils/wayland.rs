@@ -2,11 +2,12 @@
 use crate::wayland::drm_syncobj::{DrmSyncPoint, DrmSyncobjCachedState};
 use crate::{
     backend::renderer::{
-        buffer_dimensions, buffer_has_alpha, element::RenderElement, ContextId, ErasedContextId, ImportAll,
-        Renderer, Texture,
+        buffer_dimensions, buffer_has_alpha, buffer_type, element::RenderElement, BufferType, ContextId,
+        ErasedContextId, ImportAll, ImportPath, Renderer, Texture, WeakContextId,
     },
-    utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+    utils::{Buffer as BufferCoord, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
     wayland::{
+        audit::{ResourceAudit, ResourceKind},
         compositor::{
             self, add_destruction_hook, is_sync_subsurface, with_surface_tree_downward,
             with_surface_tree_upward, BufferAssignment, Damage, RectangleKind, SubsurfaceCachedState,
@@ -23,8 +24,12 @@ use std::{
 };
 
 use super::{CommitCounter, DamageBag, DamageSet, DamageSnapshot, SurfaceView};
-use tracing::{error, instrument, warn};
-use wayland_server::protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface};
+use tracing::{error, instrument, trace, warn};
+use wayland_server::{
+    backend::ClientId,
+    protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface},
+    Resource, Weak,
+};
 
 /// Type stored in WlSurface states data_map
 ///
@@ -46,8 +51,58 @@ pub struct RendererSurfaceState {
     pub(crate) damage: DamageBag<i32, BufferCoord>,
     pub(crate) renderer_seen: HashMap<ErasedContextId, CommitCounter>,
     pub(crate) textures: HashMap<ErasedContextId, Box<dyn Any>>,
+    pub(crate) textures_audit: Option<(ResourceAudit, ClientId)>,
     pub(crate) surface_view: Option<SurfaceView>,
     pub(crate) opaque_regions: Vec<Rectangle<i32, Logical>>,
+    pub(crate) import_paths: Vec<CachedImportPath>,
+}
+
+/// Type and import path of a buffer negotiated with a renderer
+///
+/// Both only depend on the buffer, so they stay valid for as long as the buffer and the
+/// renderer are alive. Clients usually cycle through a few buffers, which are all kept here.
+#[derive(Debug)]
+pub(crate) struct CachedImportPath {
+    buffer: Weak<WlBuffer>,
+    context_id: WeakContextId,
+    buffer_type: Option<BufferType>,
+    path: Option<ImportPath>,
+}
+
+impl CachedImportPath {
+    fn matches(&self, buffer: &WlBuffer, context_id: &ErasedContextId) -> bool {
+        self.buffer.id() == buffer.id() && self.context_id.is(context_id)
+    }
+}
+
+fn cached_import_path<R: ImportAll>(
+    import_paths: &mut Vec<CachedImportPath>,
+    renderer: &R,
+    context_id: &ErasedContextId,
+    buffer: &WlBuffer,
+) -> (Option<BufferType>, Option<ImportPath>) {
+    if let Some(cached) = import_paths
+        .iter()
+        .find(|cached| cached.matches(buffer, context_id))
+    {
+        return (cached.buffer_type, cached.path);
+    }
+
+    // a new renderer replacing a dropped one has to negotiate again
+    import_paths.retain(|cached| cached.context_id.is_alive());
+
+    let buffer_type = buffer_type(buffer);
+    let path = match buffer_type {
+        None | Some(BufferType::SinglePixel) => None,
+        Some(_) => renderer.import_path(buffer),
+    };
+    import_paths.push(CachedImportPath {
+        buffer: buffer.downgrade(),
+        context_id: context_id.downgrade(),
+        buffer_type,
+        path,
+    });
+    (buffer_type, path)
 }
 
 /// SAFETY: Only thing unsafe here is the `Box<dyn Any>`, which are the textures.
@@ -56,6 +111,12 @@ pub struct RendererSurfaceState {
 unsafe impl Send for RendererSurfaceState {}
 unsafe impl Sync for RendererSurfaceState {}
 
+impl Drop for RendererSurfaceState {
+    fn drop(&mut self) {
+        self.clear_textures();
+    }
+}
+
 #[derive(Debug)]
 struct InnerBuffer {
     buffer: WlBuffer,
@@ -166,6 +227,8 @@ impl RendererSurfaceState {
                 self.buffer_transform = attrs.buffer_transform.into();
 
                 if !self.buffer.as_ref().is_some_and(|b| b == buffer) {
+                    self.import_paths
+                        .retain(|cached| cached.buffer.upgrade().is_ok() && cached.context_id.is_alive());
                     self.buffer = Some(Buffer {
                         inner: Arc::new(InnerBuffer {
                             buffer,
@@ -177,7 +240,7 @@ impl RendererSurfaceState {
                     });
                 }
 
-                self.textures.clear();
+                self.clear_textures();
             }
             Some(BufferAssignment::Removed) => {
                 self.reset();
@@ -197,16 +260,14 @@ impl RendererSurfaceState {
 
         // if we received a new buffer also process the attached damage
         if new_buffer {
-            let buffer_damage = attrs.damage.drain(..).flat_map(|dmg| {
-                match dmg {
-                    Damage::Buffer(rect) => rect,
-                    Damage::Surface(rect) => surface_view.rect_to_local(rect).to_i32_up().to_buffer(
-                        self.buffer_scale,
-                        self.buffer_transform,
-                        &surface_size,
-                    ),
-                }
-                .intersection(Rectangle::from_size(buffer_dimensions))
+            let buffer_damage = attrs.damage.drain(..).flat_map(|dmg| match dmg {
+                Damage::Buffer(rect) => rect.intersection(Rectangle::from_size(buffer_dimensions)),
+                Damage::Surface(rect) => surface_view.surface_damage_to_buffer(
+                    rect,
+                    self.buffer_scale,
+                    self.buffer_transform,
+                    buffer_dimensions,
+                ),
             });
             self.damage.add(buffer_damage);
         }
@@ -345,10 +406,41 @@ impl RendererSurfaceState {
         self.surface_view
     }
 
+    /// Gets the [`ImportPath`] the current buffer takes when imported by the renderer with the given context
+    ///
+    /// Returns `None` if the buffer was not imported by [`import_surface`] with this renderer yet, or if
+    /// the renderer can not import the buffer.
+    pub fn import_path(&self, context_id: &ErasedContextId) -> Option<ImportPath> {
+        let buffer = self.buffer.as_ref()?;
+        self.import_paths
+            .iter()
+            .find(|cached| cached.matches(buffer, context_id))
+            .and_then(|cached| cached.path)
+    }
+
+    /// Forgets the import paths negotiated with the renderer of the given context
+    ///
+    /// Paths negotiated with a renderer, that got dropped, are discarded automatically, once a new
+    /// renderer imports the surface. This only needs to be called if the formats supported by a
+    /// renderer changed while it was alive, so the paths are renegotiated on the next import.
+    pub fn invalidate_import_paths(&mut self, context_id: &ErasedContextId) {
+        self.import_paths
+            .retain(|cached| !cached.context_id.is(context_id));
+    }
+
+    fn clear_textures(&mut self) {
+        if let Some((audit, client)) = self.textures_audit.take() {
+            for _ in 0..self.textures.len() {
+                audit.destroyed(&client, ResourceKind::Texture);
+            }
+        }
+        self.textures.clear();
+    }
+
     fn reset(&mut self) {
         self.buffer_dimensions = None;
         self.buffer = None;
-        self.textures.clear();
+        self.clear_textures();
         self.damage.reset();
         self.surface_view = None;
         self.buffer_has_alpha = None;
@@ -437,33 +529,6 @@ impl SurfaceView {
         };
         SurfaceView { src, dst, offset }
     }
-
-    pub(crate) fn rect_to_global<N>(&self, rect: Rectangle<N, Logical>) -> Rectangle<f64, Logical>
-    where
-        N: Coordinate,
-    {
-        let scale = self.scale();
-        let mut rect = rect.to_f64();
-        rect.loc -= self.src.loc;
-        rect.upscale(scale)
-    }
-
-    pub(crate) fn rect_to_local<N>(&self, rect: Rectangle<N, Logical>) -> Rectangle<f64, Logical>
-    where
-        N: Coordinate,
-    {
-        let scale = self.scale();
-        let mut rect = rect.to_f64().downscale(scale);
-        rect.loc += self.src.loc;
-        rect
-    }
-
-    fn scale(&self) -> Scale<f64> {
-        Scale::from((
-            self.dst.w as f64 / self.src.size.w,
-            self.dst.h as f64 / self.src.size.h,
-        ))
-    }
 }
 
 /// Access the buffer related states associated to this surface
@@ -504,17 +569,26 @@ where
         let buffer_damage = data.damage_since(last_commit.copied());
         if let Entry::Vacant(e) = data.textures.entry(context_id.clone()) {
             if let Some(buffer) = data.buffer.as_ref() {
+                let (buffer_type, import_path) =
+                    cached_import_path(&mut data.import_paths, &*renderer, &context_id, buffer);
+                let Some(buffer_type) = buffer_type else {
+                    error!("Unknown buffer format for: {:?}", buffer);
+                    return Ok(());
+                };
                 // There is no point in importing a single pixel buffer
-                if matches!(
-                    crate::backend::renderer::buffer_type(buffer),
-                    Some(crate::backend::renderer::BufferType::SinglePixel)
-                ) {
+                if buffer_type == BufferType::SinglePixel {
                     return Ok(());
                 }
+                trace!(?buffer_type, ?import_path, "importing buffer");
 
-                match renderer.import_buffer(buffer, Some(states), &buffer_damage) {
+                match renderer.import_buffer_of_type(buffer, buffer_type, Some(states), &buffer_damage) {
                     Some(Ok(m)) => {
                         e.insert(Box::new(m));
+                        let audit = states.data_map.get::<ResourceAudit>();
+                        if let Some((audit, client)) = audit.zip(buffer.client()) {
+                            audit.created(&client.id(), ResourceKind::Texture);
+                            data.textures_audit = Some((audit.clone(), client.id()));
+                        }
                         data.renderer_seen.insert(context_id, data.current_commit());
                     }
                     Some(Err(err)) => {
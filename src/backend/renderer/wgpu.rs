@@ -0,0 +1,477 @@
+//! Interoperability with [`wgpu`]
+//!
+//! Compositors rendering with wgpu can consume the client buffers imported by a [`GlesRenderer`]
+//! without copying them. This requires a wgpu device running on the OpenGL context of the
+//! renderer, created through [`create_adapter`]:
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::gles::GlesRenderer;
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! use smithay::backend::renderer::wgpu::{create_adapter, surface_texture};
+//!
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let surface: WlSurface = todo!();
+//! let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+//!     backends: wgpu::Backends::GL,
+//!     ..Default::default()
+//! });
+//! let adapter = unsafe { create_adapter(&instance, &mut renderer) }.expect("GL adapter");
+//! # let device: wgpu::Device = todo!();
+//! // request a device from `adapter`
+//!
+//! // after the surface was imported, e.g. by rendering it
+//! if let Some(texture) = unsafe { surface_texture(&renderer, &device, &surface) } {
+//!     let texture = texture.expect("Unsupported texture");
+//!     let view = texture.texture().create_view(&Default::default());
+//!     // sample `view` in a wgpu render pass
+//! }
+//! ```
+//!
+//! As the wgpu device shares the context of the renderer, the context has to be current while
+//! using the device, e.g. by issuing wgpu commands inside of [`GlesRenderer::with_context`].
+//!
+//! With the `backend_vulkan` feature, devices using the Vulkan backend of wgpu can import
+//! client dmabufs directly through [`dmabuf_to_wgpu`] instead. The device has to be created with
+//! the `VK_KHR_external_memory_fd`, `VK_EXT_external_memory_dma_buf` and
+//! `VK_EXT_image_drm_format_modifier` extensions enabled. [`surface_texture`] picks the matching
+//! path for the backend of the device.
+
+use std::num::NonZeroU32;
+
+use wgpu::hal::{api::Gles, gles};
+#[cfg(feature = "backend_vulkan")]
+use wgpu::hal::{api::Vulkan, vulkan};
+
+#[cfg(feature = "backend_vulkan")]
+use crate::backend::allocator::{dmabuf::Dmabuf, Buffer};
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            gles::{GlesError, GlesRenderer, GlesTexture},
+            Texture,
+        },
+    },
+    utils::{Buffer as BufferCoords, Size},
+};
+
+/// Errors sharing textures with wgpu
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The wgpu device does not use the OpenGL backend
+    #[error("The wgpu device does not use the OpenGL backend")]
+    NotGles,
+    /// The wgpu device does not use the Vulkan backend
+    #[error("The wgpu device does not use the Vulkan backend")]
+    NotVulkan,
+    /// The texture has no format representable in wgpu
+    #[error("The texture format {0:?} has no wgpu equivalent")]
+    UnsupportedFormat(Option<Fourcc>),
+    /// External textures cannot be sampled by wgpu
+    #[error("External textures cannot be shared with wgpu")]
+    ExternalTexture,
+    /// The texture has no GL texture object, e.g. because it was already destroyed
+    #[error("The texture has no GL texture object")]
+    InvalidTexture,
+    /// Dmabufs with more than one plane cannot be imported
+    #[error("Dmabufs with {0} planes cannot be imported")]
+    UnsupportedPlanes(usize),
+    /// The Vulkan device is missing an extension required to import dmabufs
+    #[error("The Vulkan device is missing the {0:?} extension")]
+    MissingExtension(&'static std::ffi::CStr),
+    /// Duplicating the file descriptor of the dmabuf failed
+    #[error("Failed to duplicate the dmabuf file descriptor")]
+    Io(#[source] std::io::Error),
+    /// Importing the dmabuf into Vulkan failed
+    #[cfg(feature = "backend_vulkan")]
+    #[error("Importing the dmabuf into Vulkan failed: {0}")]
+    Vulkan(#[from] ash::vk::Result),
+    /// The renderer failed to make its context current
+    #[error(transparent)]
+    Gles(#[from] GlesError),
+    /// wgpu could not create an adapter for the context of the renderer
+    #[error("wgpu could not create an adapter for the context of the renderer")]
+    AdapterCreation,
+}
+
+/// Texture of a [`GlesRenderer`] shared with a wgpu device
+///
+/// Keeps the underlying [`GlesTexture`] alive as long as the wgpu texture exists.
+#[derive(Debug)]
+pub struct WgpuTexture {
+    texture: wgpu::Texture,
+    y_inverted: bool,
+}
+
+impl WgpuTexture {
+    /// The texture usable with the wgpu device
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Whether the contents are stored upside down and need to be flipped while sampling
+    pub fn is_y_inverted(&self) -> bool {
+        self.y_inverted
+    }
+
+    /// Take the wgpu texture
+    pub fn into_inner(self) -> wgpu::Texture {
+        self.texture
+    }
+}
+
+/// Map the format of a texture to the matching wgpu format
+///
+/// Formats without alpha map to their alpha counterparts, the alpha channel has to be ignored
+/// while sampling.
+pub fn fourcc_to_wgpu(format: Fourcc) -> Option<wgpu::TextureFormat> {
+    Some(match format {
+        Fourcc::Abgr8888 | Fourcc::Xbgr8888 => wgpu::TextureFormat::Rgba8Unorm,
+        Fourcc::Argb8888 | Fourcc::Xrgb8888 => wgpu::TextureFormat::Bgra8Unorm,
+        Fourcc::Abgr2101010 | Fourcc::Xbgr2101010 => wgpu::TextureFormat::Rgb10a2Unorm,
+        Fourcc::Abgr16161616f | Fourcc::Xbgr16161616f => wgpu::TextureFormat::Rgba16Float,
+        _ => return None,
+    })
+}
+
+/// Map a wgpu texture format to the Vulkan format of the same memory layout
+#[cfg(feature = "backend_vulkan")]
+fn vk_format(format: wgpu::TextureFormat) -> Option<ash::vk::Format> {
+    use ash::vk::Format;
+
+    Some(match format {
+        wgpu::TextureFormat::Rgba8Unorm => Format::R8G8B8A8_UNORM,
+        wgpu::TextureFormat::Bgra8Unorm => Format::B8G8R8A8_UNORM,
+        wgpu::TextureFormat::Rgb10a2Unorm => Format::A2B10G10R10_UNORM_PACK32,
+        wgpu::TextureFormat::Rgba16Float => Format::R16G16B16A16_SFLOAT,
+        _ => return None,
+    })
+}
+
+fn hal_descriptor(
+    extent: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+) -> wgpu::hal::TextureDescriptor<'static> {
+    wgpu::hal::TextureDescriptor {
+        label: Some("smithay shared texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::hal::TextureUses::RESOURCE | wgpu::hal::TextureUses::COPY_SRC,
+        memory_flags: wgpu::hal::MemoryFlags::empty(),
+        view_formats: Vec::new(),
+    }
+}
+
+fn texture_descriptor(
+    extent: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureDescriptor<'static> {
+    wgpu::TextureDescriptor {
+        label: Some("smithay shared texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    }
+}
+
+fn extent(size: Size<i32, BufferCoords>) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: size.w as u32,
+        height: size.h as u32,
+        depth_or_array_layers: 1,
+    }
+}
+
+/// Create a wgpu adapter running on the OpenGL context of a renderer
+///
+/// # Safety
+///
+/// The context of the renderer has to be current whenever the adapter, or any device or resource
+/// created from it, is used.
+pub unsafe fn create_adapter(
+    instance: &wgpu::Instance,
+    renderer: &mut GlesRenderer,
+) -> Result<wgpu::Adapter, Error> {
+    let exposed = renderer.with_context(|_| unsafe {
+        gles::Adapter::new_external(|name| crate::backend::egl::get_proc_address(name))
+    })?;
+    let exposed = exposed.ok_or(Error::AdapterCreation)?;
+    Ok(unsafe { instance.create_adapter_from_hal(exposed) })
+}
+
+/// Share a texture of a [`GlesRenderer`] with a wgpu device
+///
+/// The device has to be created from an adapter returned by [`create_adapter`] for the same
+/// renderer, or a renderer sharing its context.
+///
+/// # Safety
+///
+/// The context of the renderer has to be current and the device has to run on a context
+/// sharing textures with it.
+pub unsafe fn texture_to_wgpu(device: &wgpu::Device, texture: &GlesTexture) -> Result<WgpuTexture, Error> {
+    if texture.is_external() {
+        return Err(Error::ExternalTexture);
+    }
+    let format = texture
+        .format()
+        .and_then(fourcc_to_wgpu)
+        .ok_or(Error::UnsupportedFormat(texture.format()))?;
+    let extent = extent(texture.size());
+    let name = NonZeroU32::new(texture.tex_id()).ok_or(Error::InvalidTexture)?;
+
+    let hal_desc = hal_descriptor(extent, format);
+    // the gl texture must outlive the wgpu texture, which drops this callback on destruction
+    let keep_alive = texture.clone();
+    let drop_callback: wgpu::hal::DropCallback = Box::new(move || drop(keep_alive));
+
+    let hal_texture = unsafe {
+        device.as_hal::<Gles, _, _>(|hal_device| {
+            hal_device.map(|hal_device| hal_device.texture_from_raw(name, &hal_desc, Some(drop_callback)))
+        })
+    }
+    .ok_or(Error::NotGles)?;
+
+    let texture_desc = texture_descriptor(extent, format);
+    let wgpu_texture = unsafe { device.create_texture_from_hal::<Gles>(hal_texture, &texture_desc) };
+
+    Ok(WgpuTexture {
+        texture: wgpu_texture,
+        y_inverted: texture.is_y_inverted(),
+    })
+}
+
+/// Import a dmabuf into a wgpu device using the Vulkan backend
+///
+/// The dmabuf is kept alive as long as the wgpu texture exists. Only single plane dmabufs in
+/// formats supported by [`fourcc_to_wgpu`] can be imported.
+///
+/// # Safety
+///
+/// The device has to be created with the `VK_KHR_external_memory_fd`,
+/// `VK_EXT_external_memory_dma_buf` and `VK_EXT_image_drm_format_modifier` extensions enabled.
+/// Synchronization with the producer of the dmabuf is left to the caller.
+#[cfg(feature = "backend_vulkan")]
+pub unsafe fn dmabuf_to_wgpu(device: &wgpu::Device, dmabuf: &Dmabuf) -> Result<WgpuTexture, Error> {
+    let code = dmabuf.format().code;
+    let format = fourcc_to_wgpu(code).ok_or(Error::UnsupportedFormat(Some(code)))?;
+    let vk_format = vk_format(format).ok_or(Error::UnsupportedFormat(Some(code)))?;
+    if dmabuf.num_planes() != 1 {
+        return Err(Error::UnsupportedPlanes(dmabuf.num_planes()));
+    }
+    let extent = extent(dmabuf.size());
+    let hal_desc = hal_descriptor(extent, format);
+
+    let hal_texture = unsafe {
+        device.as_hal::<Vulkan, _, _>(|hal_device| {
+            hal_device.map(|hal_device| import_dmabuf(hal_device, dmabuf, vk_format, &hal_desc))
+        })
+    }
+    .ok_or(Error::NotVulkan)??;
+
+    let texture_desc = texture_descriptor(extent, format);
+    let wgpu_texture = unsafe { device.create_texture_from_hal::<Vulkan>(hal_texture, &texture_desc) };
+
+    Ok(WgpuTexture {
+        texture: wgpu_texture,
+        y_inverted: dmabuf.y_inverted(),
+    })
+}
+
+#[cfg(feature = "backend_vulkan")]
+unsafe fn import_dmabuf(
+    hal_device: &vulkan::Device,
+    dmabuf: &Dmabuf,
+    format: ash::vk::Format,
+    desc: &wgpu::hal::TextureDescriptor<'_>,
+) -> Result<vulkan::Texture, Error> {
+    use ash::vk;
+    use std::os::unix::io::{AsRawFd, IntoRawFd};
+
+    let enabled = hal_device.enabled_device_extensions();
+    for extension in [
+        ash::khr::external_memory_fd::NAME,
+        ash::ext::external_memory_dma_buf::NAME,
+        ash::ext::image_drm_format_modifier::NAME,
+    ] {
+        if !enabled.contains(&extension) {
+            return Err(Error::MissingExtension(extension));
+        }
+    }
+    let device = hal_device.raw_device();
+    let external_memory_fd =
+        ash::khr::external_memory_fd::Device::new(hal_device.shared_instance().raw_instance(), device);
+
+    let plane_layouts = dmabuf
+        .offsets()
+        .zip(dmabuf.strides())
+        .map(|(offset, stride)| vk::SubresourceLayout {
+            offset: offset as u64,
+            row_pitch: stride as u64,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    let mut modifier_info = vk::ImageDrmFormatModifierExplicitCreateInfoEXT::default()
+        .drm_format_modifier(dmabuf.format().modifier.into())
+        .plane_layouts(&plane_layouts);
+    let mut external_memory_info = vk::ExternalMemoryImageCreateInfo::default()
+        .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+    let image_info = vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(vk::Extent3D {
+            width: desc.size.width,
+            height: desc.size.height,
+            depth: 1,
+        })
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .mip_levels(1)
+        .array_layers(1)
+        .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+        .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .push_next(&mut modifier_info)
+        .push_next(&mut external_memory_info);
+
+    let image = scopeguard::guard(
+        unsafe { device.create_image(&image_info, None) }?,
+        |image| unsafe { device.destroy_image(image, None) },
+    );
+
+    let fd = dmabuf
+        .handles()
+        .next()
+        .unwrap()
+        .try_clone_to_owned()
+        .map_err(Error::Io)?;
+    let mut fd_properties = vk::MemoryFdPropertiesKHR::default();
+    unsafe {
+        external_memory_fd.get_memory_fd_properties(
+            vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+            fd.as_raw_fd(),
+            &mut fd_properties,
+        )
+    }?;
+    let requirements = unsafe { device.get_image_memory_requirements(*image) };
+    let memory_type_bits = requirements.memory_type_bits & fd_properties.memory_type_bits;
+    if memory_type_bits == 0 {
+        return Err(Error::Vulkan(vk::Result::ERROR_INVALID_EXTERNAL_HANDLE));
+    }
+
+    let mut import_info = vk::ImportMemoryFdInfoKHR::default()
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
+        .fd(fd.as_raw_fd());
+    let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default().image(*image);
+    let allocate_info = vk::MemoryAllocateInfo::default()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type_bits.trailing_zeros())
+        .push_next(&mut import_info)
+        .push_next(&mut dedicated_info);
+    let memory = unsafe { device.allocate_memory(&allocate_info, None) }?;
+    // a successful import transfers the ownership of the file descriptor to vulkan
+    let _ = fd.into_raw_fd();
+    let memory = scopeguard::guard(memory, |memory| unsafe { device.free_memory(memory, None) });
+    unsafe { device.bind_image_memory(*image, *memory, 0) }?;
+
+    let image = scopeguard::ScopeGuard::into_inner(image);
+    let memory = scopeguard::ScopeGuard::into_inner(memory);
+    // wgpu does not destroy raw images with a drop callback, so the image and its memory are
+    // released here, after which the dmabuf may be reused by the client
+    let raw_device = device.clone();
+    let keep_alive = dmabuf.clone();
+    let drop_callback: wgpu::hal::DropCallback = Box::new(move || {
+        unsafe {
+            raw_device.destroy_image(image, None);
+            raw_device.free_memory(memory, None);
+        }
+        drop(keep_alive);
+    });
+
+    Ok(unsafe { hal_device.texture_from_raw(image, desc, Some(drop_callback)) })
+}
+
+/// Share the current buffer of a surface with a wgpu device
+///
+/// For devices using the OpenGL backend the texture imported by the renderer is shared, see
+/// [`texture_to_wgpu`]. Returns `None` if the current buffer of the surface has not been imported
+/// by the renderer.
+///
+/// With the `backend_vulkan` feature, devices using the Vulkan backend import the dmabuf of the
+/// surface instead, see [`dmabuf_to_wgpu`]. Returns `None` if the current buffer is not a dmabuf.
+///
+/// # Safety
+///
+/// See [`texture_to_wgpu`] and [`dmabuf_to_wgpu`].
+#[cfg(feature = "wayland_frontend")]
+pub unsafe fn surface_texture(
+    renderer: &GlesRenderer,
+    device: &wgpu::Device,
+    surface: &wayland_server::protocol::wl_surface::WlSurface,
+) -> Option<Result<WgpuTexture, Error>> {
+    use crate::backend::renderer::{utils::RendererSurfaceStateUserData, Renderer};
+
+    let is_gles = unsafe { device.as_hal::<Gles, _, _>(|hal_device| hal_device.is_some()) };
+    if !is_gles {
+        #[cfg(feature = "backend_vulkan")]
+        {
+            let dmabuf = crate::wayland::compositor::with_states(surface, |states| {
+                let state = states.data_map.get::<RendererSurfaceStateUserData>()?;
+                let state = state.lock().unwrap();
+                crate::wayland::dmabuf::get_dmabuf(state.buffer()?).ok().cloned()
+            })?;
+            return Some(unsafe { dmabuf_to_wgpu(device, &dmabuf) });
+        }
+        #[cfg(not(feature = "backend_vulkan"))]
+        return Some(Err(Error::NotGles));
+    }
+
+    let texture = crate::wayland::compositor::with_states(surface, |states| {
+        let state = states.data_map.get::<RendererSurfaceStateUserData>()?;
+        state.lock().unwrap().texture(renderer.context_id()).cloned()
+    })?;
+    Some(unsafe { texture_to_wgpu(device, &texture) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            fourcc_to_wgpu(Fourcc::Xrgb8888),
+            Some(wgpu::TextureFormat::Bgra8Unorm)
+        );
+        assert_eq!(
+            fourcc_to_wgpu(Fourcc::Abgr8888),
+            Some(wgpu::TextureFormat::Rgba8Unorm)
+        );
+        assert_eq!(fourcc_to_wgpu(Fourcc::Nv12), None);
+    }
+
+    #[cfg(feature = "backend_vulkan")]
+    #[test]
+    fn vulkan_formats() {
+        // every shareable format must be importable as a dmabuf as well
+        for format in [
+            Fourcc::Abgr8888,
+            Fourcc::Argb8888,
+            Fourcc::Abgr2101010,
+            Fourcc::Abgr16161616f,
+        ] {
+            let format = fourcc_to_wgpu(format).unwrap();
+            assert!(vk_format(format).is_some(), "{:?}", format);
+        }
+        assert_eq!(
+            vk_format(wgpu::TextureFormat::Bgra8Unorm),
+            Some(ash::vk::Format::B8G8R8A8_UNORM)
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Detection of stalled render loops
+//!
+//! A GPU hang or a blocking call (e.g. waiting on a fence of a misbehaving client) can stall
+//! rendering or presenting frames for an output indefinitely, often without any error being
+//! returned by the driver. A [`Watchdog`] tracks the progress of every output on a separate
+//! thread and reports outputs stuck in a [`Stage`] for longer than the configured timeout.
+//!
+//! The watchdog thread logs a diagnostic as soon as it detects a stall, even if the thread
+//! running the event loop is blocked. A [`Stall`] is additionally delivered through the
+//! [`WatchdogSource`], once the event loop is able to dispatch again, to trigger the configured
+//! [`RecoveryAction`]. Renderers are not shared between threads, so recovering is left to the
+//! compositor, e.g. by recreating the renderer of the output.
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::backend::watchdog::{RecoveryAction, Stage, Watchdog, WatchdogConfig};
+//! # use smithay::output::{Output, PhysicalProperties, Subpixel};
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! # let output = Output::new("output-0".into(), PhysicalProperties {
+//! #     size: (0, 0).into(),
+//! #     subpixel: Subpixel::Unknown,
+//! #     make: "".into(),
+//! #     model: "".into(),
+//! #     serial_number: "".into(),
+//! # });
+//! let (watchdog, source) = Watchdog::new(WatchdogConfig {
+//!     action: RecoveryAction::ResetRenderer,
+//!     ..Default::default()
+//! })
+//! .unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |stall, _, _state| {
+//!         if stall.action == RecoveryAction::ResetRenderer {
+//!             // recreate the renderer used for `stall.output`
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! watchdog.begin(&output, Stage::Render);
+//! // render the frame
+//! watchdog.begin(&output, Stage::Present);
+//! // once the frame was presented
+//! watchdog.finish(&output);
+//! ```
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::{error, warn};
+
+use crate::{
+    compat::notifier::{self, Notifier, NotifierSource},
+    output::Output,
+    utils::{system_time_source, Monotonic, Time, TimeSource},
+};
+
+/// Part of the render loop an output is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Rendering a frame
+    Render,
+    /// Waiting for a submitted frame to be presented
+    Present,
+}
+
+/// Action the compositor is asked to take after a stall was detected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RecoveryAction {
+    /// Only report the stall
+    #[default]
+    None,
+    /// Recreate the renderer of the stalled output
+    ResetRenderer,
+}
+
+/// Configuration of a [`Watchdog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// Time an output may spend in a single [`Stage`] before it is considered stalled
+    pub timeout: Duration,
+    /// Interval in which the watchdog thread checks all outputs
+    pub interval: Duration,
+    /// Action delivered with every [`Stall`]
+    pub action: RecoveryAction,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            timeout: Duration::from_secs(2),
+            interval: Duration::from_millis(500),
+            action: RecoveryAction::None,
+        }
+    }
+}
+
+/// A stalled output detected by a [`Watchdog`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stall {
+    /// Name of the output
+    pub output: String,
+    /// Stage the output got stuck in
+    pub stage: Stage,
+    /// Time spent in the stage when the stall was detected
+    pub duration: Duration,
+    /// Action to take
+    pub action: RecoveryAction,
+}
+
+#[derive(Debug)]
+struct Progress {
+    stage: Stage,
+    since: Time<Monotonic>,
+    reported: bool,
+}
+
+#[derive(Debug)]
+struct WatchdogInner {
+    config: WatchdogConfig,
+    outputs: Mutex<HashMap<String, Progress>>,
+    stalls: Arc<Mutex<Vec<Stall>>>,
+    notifier: Notifier,
+    time_source: Arc<dyn TimeSource>,
+}
+
+impl WatchdogInner {
+    fn check(&self) {
+        let now = self.time_source.now();
+        let mut detected = false;
+        for (output, progress) in self.outputs.lock().unwrap().iter_mut() {
+            let duration = now.saturating_duration_since(progress.since);
+            if progress.reported || duration < self.config.timeout {
+                continue;
+            }
+
+            error!(
+                output = %output,
+                stage = ?progress.stage,
+                ?duration,
+                action = ?self.config.action,
+                "Render loop stalled"
+            );
+            progress.reported = true;
+            self.stalls.lock().unwrap().push(Stall {
+                output: output.clone(),
+                stage: progress.stage,
+                duration,
+                action: self.config.action,
+            });
+            detected = true;
+        }
+
+        if detected {
+            self.notifier.notify();
+        }
+    }
+}
+
+/// Tracks the progress of the render loop of outputs, see the [module-level documentation](self)
+///
+/// Cloning a `Watchdog` returns a handle to the same watchdog. The watchdog thread exits
+/// once all handles are dropped.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    inner: Arc<WatchdogInner>,
+}
+
+impl Watchdog {
+    /// Create a new watchdog and spawn its thread
+    ///
+    /// Stalls are delivered through the returned [`WatchdogSource`].
+    pub fn new(config: WatchdogConfig) -> io::Result<(Watchdog, WatchdogSource)> {
+        Self::with_time_source(config, system_time_source())
+    }
+
+    /// Create a new watchdog reading the time from `time_source` and spawn its thread
+    ///
+    /// The thread still wakes up in the configured [`interval`](WatchdogConfig::interval) of
+    /// real time, a [`ManualClock`](crate::utils::ManualClock) only controls how long outputs
+    /// are considered to be stuck.
+    pub fn with_time_source(
+        config: WatchdogConfig,
+        time_source: Arc<dyn TimeSource>,
+    ) -> io::Result<(Watchdog, WatchdogSource)> {
+        let (notifier, source) = notifier::new()?;
+        let stalls = Arc::new(Mutex::new(Vec::new()));
+        let inner = Arc::new(WatchdogInner {
+            config,
+            outputs: Mutex::new(HashMap::new()),
+            stalls: stalls.clone(),
+            notifier,
+            time_source,
+        });
+
+        let weak: Weak<WatchdogInner> = Arc::downgrade(&inner);
+        std::thread::Builder::new()
+            .name("smithay-watchdog".into())
+            .spawn(move || loop {
+                std::thread::sleep(config.interval);
+                match weak.upgrade() {
+                    Some(inner) => inner.check(),
+                    None => break,
+                }
+            })?;
+
+        Ok((Watchdog { inner }, WatchdogSource { source, stalls }))
+    }
+
+    /// Returns the configuration of the watchdog
+    pub fn config(&self) -> WatchdogConfig {
+        self.inner.config
+    }
+
+    /// Mark an output as entering a new stage of its render loop
+    pub fn begin(&self, output: &Output, stage: Stage) {
+        let mut outputs = self.inner.outputs.lock().unwrap();
+        if let Some(progress) = outputs.get(&output.name()) {
+            if progress.reported {
+                warn!(output = %output.name(), ?stage, "Render loop recovered from stall");
+            }
+        }
+        outputs.insert(
+            output.name(),
+            Progress {
+                stage,
+                since: self.inner.time_source.now(),
+                reported: false,
+            },
+        );
+    }
+
+    /// Mark an output as idle, e.g. after its frame was presented
+    pub fn finish(&self, output: &Output) {
+        self.inner.outputs.lock().unwrap().remove(&output.name());
+    }
+}
+
+/// Event source delivering the [`Stall`]s detected by a [`Watchdog`]
+#[derive(Debug)]
+pub struct WatchdogSource {
+    source: NotifierSource,
+    stalls: Arc<Mutex<Vec<Stall>>>,
+}
+
+impl EventSource for WatchdogSource {
+    type Event = Stall;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let stalls = &self.stalls;
+        self.source.process_events(readiness, token, |_, _| {
+            let pending = std::mem::take(&mut *stalls.lock().unwrap());
+            for stall in pending {
+                callback(stall, &mut ());
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        output::{PhysicalProperties, Subpixel},
+        utils::ManualClock,
+    };
+
+    #[test]
+    fn reports_stall_once() {
+        let clock = ManualClock::default();
+        let (watchdog, _source) = Watchdog::with_time_source(
+            WatchdogConfig {
+                timeout: Duration::from_secs(1),
+                interval: Duration::from_secs(3600),
+                action: RecoveryAction::ResetRenderer,
+            },
+            Arc::new(clock.clone()),
+        )
+        .unwrap();
+        let output = Output::new(
+            "output-0".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "".into(),
+                model: "".into(),
+                serial_number: "".into(),
+            },
+        );
+
+        watchdog.begin(&output, Stage::Present);
+        clock.advance(Duration::from_millis(500));
+        watchdog.inner.check();
+        assert!(watchdog.inner.stalls.lock().unwrap().is_empty());
+
+        clock.advance(Duration::from_secs(2));
+        watchdog.inner.check();
+        watchdog.inner.check();
+        let stalls = watchdog.inner.stalls.lock().unwrap();
+        assert_eq!(stalls.len(), 1);
+        assert_eq!(stalls[0].output, "output-0");
+        assert_eq!(stalls[0].stage, Stage::Present);
+        assert_eq!(stalls[0].duration, Duration::from_millis(2500));
+        assert_eq!(stalls[0].action, RecoveryAction::ResetRenderer);
+    }
+}
@@ -93,4 +93,18 @@ impl WGLContext {
     pub fn hglrc(&self) -> isize {
         self.handle.hglrc
     }
+
+    /// Make this context current and block until all of its submitted commands are finished
+    pub fn finish(&self) -> Result<(), MakeCurrentError> {
+        self.make_current()?;
+        let gl_finish = ffi::get_proc_address("glFinish");
+        if !gl_finish.is_null() {
+            // SAFETY: glFinish takes no arguments and the context is current
+            unsafe {
+                let gl_finish: unsafe extern "system" fn() = std::mem::transmute(gl_finish);
+                gl_finish();
+            }
+        }
+        Ok(())
+    }
 }
@@ -4,6 +4,8 @@
 
 use std::sync::Arc;
 
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
 use super::ffi;
 use super::Error;
 
@@ -82,6 +84,19 @@ impl WGLDisplay {
         })
     }
     
+    /// Create a new WGLDisplay from any window providing a raw window handle
+    ///
+    /// This accepts e.g. windows created by `winit` and fails for handles of other platforms.
+    ///
+    /// # Safety
+    /// The window must outlive the display.
+    pub unsafe fn from_window_handle(window: &impl HasWindowHandle) -> Result<Self, Error> {
+        match window.window_handle()?.as_raw() {
+            RawWindowHandle::Win32(handle) => Self::from_window(handle.hwnd.get()),
+            _ => Err(Error::UnsupportedWindowHandle),
+        }
+    }
+
     /// Create from existing HDC (caller retains ownership)
     ///
     /// # Safety
@@ -38,6 +38,12 @@ pub enum Error {
     /// Library loading failed
     #[error("Failed to load OpenGL library: {0}")]
     LibraryLoadFailed(String),
+    /// The window handle is not available
+    #[error("The window handle is not available: {0}")]
+    WindowHandle(#[from] raw_window_handle::HandleError),
+    /// The window handle does not refer to a Win32 window
+    #[error("The window handle does not refer to a Win32 window")]
+    UnsupportedWindowHandle,
 }
 
 /// Error when making a context current fails
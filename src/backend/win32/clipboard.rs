@@ -0,0 +1,414 @@
+//! Access to the Windows clipboard
+//!
+//! [`ClipboardSource`] notifies the event loop whenever an application changes the clipboard,
+//! [`get_text`] and [`set_text`] read and write its text content.
+//!
+//! [`ClipboardBridge`] mirrors text between the Windows clipboard and the clipboard selection of
+//! a seat. It uses a [`SelectionLoopGuard`], so selections it imported are not exported again
+//! when a clipboard manager using data control re-announces them, and ignores the clipboard
+//! changes caused by its own writes.
+//!
+//! ```no_run
+//! # #[cfg(feature = "wayland_frontend")]
+//! # fn main() {
+//! use smithay::backend::win32::clipboard::{ClipboardBridge, ClipboardSource};
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let mut bridge = ClipboardBridge::new();
+//! event_loop
+//!     .handle()
+//!     .insert_source(ClipboardSource::new().unwrap(), move |_, _, _state| {
+//!         if let Some(mime_types) = bridge.import() {
+//!             // set_data_device_selection(&dh, &seat, mime_types, ());
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! // in SelectionHandler::send_selection
+//! //   if let Ok(Some(data)) = bridge.read(&mime_type) { /* write data into fd */ }
+//! //   (dropping the fd otherwise answers requests for the marker of the guard)
+//! //
+//! // in SelectionHandler::new_selection
+//! //   if let Some(mime_type) = bridge.export(&source.mime_types()) {
+//! //       request_data_device_client_selection(&seat, mime_type, write_fd);
+//! //       // once the data was read: bridge.write(&data)
+//! //   }
+//! # }
+//! # #[cfg(not(feature = "wayland_frontend"))]
+//! # fn main() {}
+//! ```
+
+use std::{fmt, io, sync::mpsc, thread::JoinHandle, time::Duration};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::warn;
+
+use super::ffi;
+use crate::compat::notifier::{self, Notifier, NotifierSource};
+
+#[cfg(feature = "wayland_frontend")]
+use crate::wayland::selection::{preferred_mime_type, SelectionLoopGuard, TEXT_MIME_TYPES};
+
+// another application may hold the clipboard open for a moment
+const OPEN_ATTEMPTS: u32 = 10;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(5);
+
+/// Calloop event source dispatched whenever the content of the clipboard changes
+///
+/// Changes are coalesced, the source is dispatched once for all changes since the last
+/// dispatch. This includes the changes made through [`set_text`].
+pub struct ClipboardSource {
+    source: NotifierSource,
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for ClipboardSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClipboardSource")
+            .field("source", &self.source)
+            .field("thread_id", &self.thread_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClipboardSource {
+    /// Start listening for clipboard changes
+    pub fn new() -> io::Result<ClipboardSource> {
+        let (notifier, source) = notifier::new()?;
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("smithay-clipboard".into())
+            .spawn(move || {
+                let hwnd = match create_window() {
+                    Ok(hwnd) => hwnd,
+                    Err(err) => {
+                        let _ = ready_tx.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(unsafe { ffi::GetCurrentThreadId() }));
+                run(notifier);
+                unsafe {
+                    ffi::RemoveClipboardFormatListener(hwnd);
+                    ffi::DestroyWindow(hwnd);
+                }
+            })?;
+        match ready_rx.recv() {
+            Ok(Ok(thread_id)) => Ok(ClipboardSource {
+                source,
+                thread_id,
+                thread: Some(thread),
+            }),
+            Ok(Err(err)) => {
+                let _ = thread.join();
+                Err(err)
+            }
+            Err(_) => Err(io::Error::other("clipboard thread panicked")),
+        }
+    }
+}
+
+impl Drop for ClipboardSource {
+    fn drop(&mut self) {
+        unsafe { ffi::PostThreadMessageW(self.thread_id, ffi::WM_QUIT, 0, 0) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Creates the message-only window receiving the clipboard notifications
+fn create_window() -> io::Result<ffi::HWND> {
+    // the predefined static control forwards all messages to `DefWindowProcW`
+    let class = ffi::to_wide("STATIC");
+    let hwnd = unsafe {
+        ffi::CreateWindowExW(
+            0,
+            class.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            ffi::HWND_MESSAGE,
+            0,
+            0,
+            std::ptr::null(),
+        )
+    };
+    if hwnd == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { ffi::AddClipboardFormatListener(hwnd) } == 0 {
+        let err = io::Error::last_os_error();
+        unsafe { ffi::DestroyWindow(hwnd) };
+        return Err(err);
+    }
+    Ok(hwnd)
+}
+
+// Message loop of the clipboard thread, until `WM_QUIT` is posted
+fn run(notifier: Notifier) {
+    loop {
+        let mut msg: ffi::MSG = unsafe { std::mem::zeroed() };
+        match unsafe { ffi::GetMessageW(&mut msg, 0, 0, 0) } {
+            0 => break,
+            -1 => {
+                warn!(err = ?io::Error::last_os_error(), "Failed to receive clipboard notifications");
+                break;
+            }
+            _ => {}
+        }
+        if msg.message == ffi::WM_CLIPBOARDUPDATE {
+            notifier.notify();
+        }
+        unsafe { ffi::DispatchMessageW(&msg) };
+    }
+}
+
+impl EventSource for ClipboardSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.source.process_events(readiness, token, callback)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+// Keeps the clipboard open until dropped
+struct OpenClipboard;
+
+impl OpenClipboard {
+    fn new() -> io::Result<OpenClipboard> {
+        for _ in 1..OPEN_ATTEMPTS {
+            if unsafe { ffi::OpenClipboard(0) } != 0 {
+                return Ok(OpenClipboard);
+            }
+            std::thread::sleep(OPEN_RETRY_DELAY);
+        }
+        if unsafe { ffi::OpenClipboard(0) } != 0 {
+            return Ok(OpenClipboard);
+        }
+        Err(io::Error::last_os_error())
+    }
+}
+
+impl Drop for OpenClipboard {
+    fn drop(&mut self) {
+        unsafe { ffi::CloseClipboard() };
+    }
+}
+
+/// Read the text on the clipboard
+///
+/// Returns `None` if the clipboard holds no text. Line endings are converted to `\n`.
+pub fn get_text() -> io::Result<Option<String>> {
+    if unsafe { ffi::IsClipboardFormatAvailable(ffi::CF_UNICODETEXT) } == 0 {
+        return Ok(None);
+    }
+    let _clipboard = OpenClipboard::new()?;
+    let memory = unsafe { ffi::GetClipboardData(ffi::CF_UNICODETEXT) };
+    if memory == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let data = unsafe { ffi::GlobalLock(memory) } as *const u16;
+    if data.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let len = unsafe { ffi::GlobalSize(memory) } / std::mem::size_of::<u16>();
+    // SAFETY: the locked memory holds `len` UTF-16 code units
+    let text = from_windows_text(unsafe { std::slice::from_raw_parts(data, len) });
+    unsafe { ffi::GlobalUnlock(memory) };
+    Ok(Some(text))
+}
+
+/// Replace the content of the clipboard with the given text
+///
+/// Line endings are converted to `\r\n`.
+pub fn set_text(text: &str) -> io::Result<()> {
+    let wide = to_windows_text(text);
+    let size = wide.len() * std::mem::size_of::<u16>();
+
+    let _clipboard = OpenClipboard::new()?;
+    if unsafe { ffi::EmptyClipboard() } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let memory = unsafe { ffi::GlobalAlloc(ffi::GMEM_MOVEABLE, size) };
+    if memory == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let data = unsafe { ffi::GlobalLock(memory) } as *mut u16;
+    if data.is_null() {
+        let err = io::Error::last_os_error();
+        unsafe { ffi::GlobalFree(memory) };
+        return Err(err);
+    }
+    // SAFETY: the allocation holds `wide.len()` code units
+    unsafe {
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), data, wide.len());
+        ffi::GlobalUnlock(memory);
+    }
+    // the system owns the memory once the data was set
+    if unsafe { ffi::SetClipboardData(ffi::CF_UNICODETEXT, memory) } == 0 {
+        let err = io::Error::last_os_error();
+        unsafe { ffi::GlobalFree(memory) };
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn to_windows_text(text: &str) -> Vec<u16> {
+    ffi::to_wide(&text.replace("\r\n", "\n").replace('\n', "\r\n"))
+}
+
+fn from_windows_text(wide: &[u16]) -> String {
+    ffi::from_wide(wide).replace("\r\n", "\n")
+}
+
+/// Mirrors text between the Windows clipboard and the clipboard selection of a seat
+///
+/// See the [module-level documentation](self).
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug, Clone)]
+pub struct ClipboardBridge {
+    guard: SelectionLoopGuard,
+    // sequence number of the clipboard after the last write of the bridge
+    written: Option<u32>,
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl Default for ClipboardBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ClipboardBridge {
+    /// Create a new bridge
+    pub fn new() -> ClipboardBridge {
+        ClipboardBridge {
+            guard: SelectionLoopGuard::new("win32-clipboard"),
+            written: None,
+        }
+    }
+
+    /// Access the loop guard of the bridge, e.g. to change its echo window
+    pub fn guard_mut(&mut self) -> &mut SelectionLoopGuard {
+        &mut self.guard
+    }
+
+    /// The mime types to set as clipboard selection after the Windows clipboard changed
+    ///
+    /// Returns `None` if the clipboard holds no text, or if the change was caused by
+    /// [`ClipboardBridge::write`].
+    pub fn import(&mut self) -> Option<Vec<String>> {
+        if self.written == Some(unsafe { ffi::GetClipboardSequenceNumber() }) {
+            return None;
+        }
+        self.written = None;
+        if unsafe { ffi::IsClipboardFormatAvailable(ffi::CF_UNICODETEXT) } == 0 {
+            return None;
+        }
+        let mime_types = TEXT_MIME_TYPES
+            .iter()
+            .map(|mime_type| mime_type.to_string())
+            .collect();
+        Some(self.guard.import(mime_types))
+    }
+
+    /// Read the Windows clipboard for a client requesting the imported selection
+    ///
+    /// Returns `None` for mime types other than text, including the marker of the
+    /// [`SelectionLoopGuard`], whose file descriptor should just be closed.
+    pub fn read(&self, mime_type: &str) -> io::Result<Option<Vec<u8>>> {
+        if mime_type == self.guard.marker() || preferred_mime_type(&[mime_type], TEXT_MIME_TYPES).is_none() {
+            return Ok(None);
+        }
+        Ok(get_text()?.map(String::into_bytes))
+    }
+
+    /// The mime type to request from a new clipboard selection to mirror it to Windows
+    ///
+    /// Returns `None` for selections without text and for echoes of an import.
+    pub fn export<S: AsRef<str>>(&mut self, mime_types: &[S]) -> Option<String> {
+        if self.guard.is_echo(mime_types) {
+            return None;
+        }
+        let mime_types = self.guard.export(
+            mime_types
+                .iter()
+                .map(|mime_type| mime_type.as_ref().to_string())
+                .collect(),
+        );
+        preferred_mime_type(&mime_types, TEXT_MIME_TYPES).map(String::from)
+    }
+
+    /// Write the data of an exported selection to the Windows clipboard
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        set_text(&String::from_utf8_lossy(data))?;
+        self.written = Some(unsafe { ffi::GetClipboardSequenceNumber() });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings() {
+        let wide = to_windows_text("a\nb\r\nc");
+        assert_eq!(ffi::from_wide(&wide), "a\r\nb\r\nc");
+        assert_eq!(wide.last(), Some(&0));
+        assert_eq!(from_windows_text(&wide), "a\nb\nc");
+    }
+
+    #[cfg(feature = "wayland_frontend")]
+    #[test]
+    fn bridge_skips_echoes() {
+        let mut bridge = ClipboardBridge::new();
+        // what a clipboard manager re-announces after an import
+        let mut imported = bridge.guard.import(
+            TEXT_MIME_TYPES
+                .iter()
+                .map(|mime_type| mime_type.to_string())
+                .collect(),
+        );
+        assert_eq!(bridge.export(&imported), None);
+        imported.pop();
+        assert_eq!(bridge.export(&imported), None);
+
+        assert_eq!(
+            bridge.export(&["text/html", "text/plain", "text/plain"]),
+            Some("text/plain".to_string())
+        );
+        assert_eq!(bridge.export(&["image/png"]), None);
+        assert_eq!(bridge.read(bridge.guard.marker()).unwrap(), None);
+        assert_eq!(bridge.read("image/png").unwrap(), None);
+    }
+}
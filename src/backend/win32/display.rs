@@ -0,0 +1,274 @@
+//! Enumeration of displays and their modes
+//!
+//! [`monitors`] lists the displays attached to the desktop with all modes supported by their
+//! driver, so an [`Output`] representing them can advertise the full mode list to
+//! clients and output management protocols.
+//!
+//! GDI only reports refresh rates as whole numbers, rounding down rates like 59.94 Hz to 59 Hz.
+//! The exact rate of the current mode is queried through the display configuration API, the
+//! remaining modes are advertised with the whole rates reported by GDI.
+//!
+//! Displays supporting HDR ("advanced color") report [`ColorEncoding::Pq`] and
+//! [`ColorEncoding::ScRgb`] in their [`Monitor::hdr`] capabilities. While advanced color is
+//! enabled, the Desktop Window Manager composes in scRGB and converts to HDR10 for the display.
+//! Rendering to a window goes through OpenGL, so it can only present in sRGB for now; DXGI HDR10
+//! swapchains are not available.
+
+use std::io;
+
+use super::ffi;
+use crate::{
+    output::{ColorEncoding, HdrCapabilities, Mode, Output},
+    utils::{Physical, Point, Size},
+};
+
+/// Display attached to the desktop
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    /// GDI name of the display, e.g. `\\.\DISPLAY1`
+    pub device_name: String,
+    /// Human readable name of the display adapter driving the display
+    pub adapter: String,
+    /// Whether this is the primary display
+    pub primary: bool,
+    /// Position of the display on the virtual desktop
+    pub position: Point<i32, Physical>,
+    /// Current mode of the display
+    pub current_mode: Mode,
+    /// Whether the current mode is interlaced
+    pub interlaced: bool,
+    /// All modes supported by the display, without duplicates
+    pub modes: Vec<Mode>,
+    /// HDR capabilities of the display
+    pub hdr: HdrCapabilities,
+    /// Whether HDR is currently enabled for the display in the system settings
+    pub hdr_enabled: bool,
+}
+
+impl Monitor {
+    /// Advertise the modes of this display on an output
+    ///
+    /// Adds all modes, and sets the current mode as the current and preferred mode of the output.
+    /// Also sets the HDR capabilities of the output.
+    pub fn apply_to_output(&self, output: &Output) {
+        output.set_hdr_capabilities(self.hdr.clone());
+        for mode in &self.modes {
+            output.add_mode(*mode);
+        }
+        output.set_preferred(self.current_mode);
+        output.change_current_state(Some(self.current_mode), None, None, None);
+    }
+}
+
+/// Refresh rate of a mode reported by GDI in whole hertz, in millihertz
+///
+/// GDI rounds fractional rates down, e.g. 59.94 Hz is reported as 59 Hz. The rate is not
+/// corrected, as the actual rate of a mode cannot be told from its rounded value.
+pub fn refresh_from_hz(hz: u32) -> i32 {
+    (hz as u64 * 1000).min(i32::MAX as u64) as i32
+}
+
+fn refresh_from_rational(rate: ffi::DISPLAYCONFIG_RATIONAL) -> Option<i32> {
+    if rate.Denominator == 0 || rate.Numerator == 0 {
+        return None;
+    }
+    let denominator = rate.Denominator as u64;
+    let millihertz = (rate.Numerator as u64 * 1000 + denominator / 2) / denominator;
+    Some(millihertz.min(i32::MAX as u64) as i32)
+}
+
+fn mode_from_devmode(devmode: &ffi::DEVMODEW) -> Mode {
+    Mode {
+        size: Size::from((devmode.dmPelsWidth as i32, devmode.dmPelsHeight as i32)),
+        refresh: refresh_from_hz(devmode.dmDisplayFrequency),
+    }
+}
+
+#[derive(Debug)]
+struct ActivePath {
+    device_name: String,
+    // exact refresh rate of the active mode
+    refresh: Option<i32>,
+    advanced_color_supported: bool,
+    advanced_color_enabled: bool,
+}
+
+fn advanced_color(path: &ffi::DISPLAYCONFIG_PATH_INFO) -> (bool, bool) {
+    let mut info = ffi::DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO {
+        header: ffi::DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            r#type: ffi::DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+            size: std::mem::size_of::<ffi::DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>() as u32,
+            adapterId: path.targetInfo.adapterId,
+            id: path.targetInfo.id,
+        },
+        value: 0,
+        colorEncoding: 0,
+        bitsPerColorChannel: 0,
+    };
+    if unsafe { ffi::DisplayConfigGetDeviceInfo(&mut info.header) } != ffi::ERROR_SUCCESS {
+        return (false, false);
+    }
+    (info.value & 0x1 != 0, info.value & 0x2 != 0)
+}
+
+fn active_paths() -> io::Result<Vec<ActivePath>> {
+    let mut num_paths = 0;
+    let mut num_modes = 0;
+    let result = unsafe {
+        ffi::GetDisplayConfigBufferSizes(ffi::QDC_ONLY_ACTIVE_PATHS, &mut num_paths, &mut num_modes)
+    };
+    if result != ffi::ERROR_SUCCESS {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+
+    let mut paths = Vec::<ffi::DISPLAYCONFIG_PATH_INFO>::with_capacity(num_paths as usize);
+    let mut modes = Vec::<ffi::DISPLAYCONFIG_MODE_INFO>::with_capacity(num_modes as usize);
+    let result = unsafe {
+        ffi::QueryDisplayConfig(
+            ffi::QDC_ONLY_ACTIVE_PATHS,
+            &mut num_paths,
+            paths.as_mut_ptr(),
+            &mut num_modes,
+            modes.as_mut_ptr(),
+            std::ptr::null_mut(),
+        )
+    };
+    if result != ffi::ERROR_SUCCESS {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    // SAFETY: QueryDisplayConfig initialized the returned number of elements
+    unsafe { paths.set_len(num_paths as usize) };
+
+    Ok(paths
+        .iter()
+        .filter_map(|path| {
+            let mut source_name = ffi::DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+                header: ffi::DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                    r#type: ffi::DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                    size: std::mem::size_of::<ffi::DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
+                    adapterId: path.sourceInfo.adapterId,
+                    id: path.sourceInfo.id,
+                },
+                viewGdiDeviceName: [0; 32],
+            };
+            let result = unsafe { ffi::DisplayConfigGetDeviceInfo(&mut source_name.header) };
+            if result != ffi::ERROR_SUCCESS {
+                return None;
+            }
+            let (advanced_color_supported, advanced_color_enabled) = advanced_color(path);
+            Some(ActivePath {
+                device_name: ffi::from_wide(&source_name.viewGdiDeviceName),
+                refresh: refresh_from_rational(path.targetInfo.refreshRate),
+                advanced_color_supported,
+                advanced_color_enabled,
+            })
+        })
+        .collect())
+}
+
+/// Enumerate the displays attached to the desktop
+pub fn monitors() -> io::Result<Vec<Monitor>> {
+    let active_paths = active_paths().unwrap_or_else(|err| {
+        tracing::warn!(?err, "Failed to query the display configuration");
+        Vec::new()
+    });
+
+    let mut monitors = Vec::new();
+    for index in 0.. {
+        // SAFETY: plain old data, all zeroes is a valid value
+        let mut device: ffi::DISPLAY_DEVICEW = unsafe { std::mem::zeroed() };
+        device.cb = std::mem::size_of::<ffi::DISPLAY_DEVICEW>() as u32;
+        if unsafe { ffi::EnumDisplayDevicesW(std::ptr::null(), index, &mut device, 0) } == 0 {
+            break;
+        }
+        if device.StateFlags & ffi::DISPLAY_DEVICE_ATTACHED_TO_DESKTOP == 0 {
+            continue;
+        }
+
+        let device_name = ffi::from_wide(&device.DeviceName);
+        let wide_name = ffi::to_wide(&device_name);
+
+        let mut current = ffi::DEVMODEW::zeroed();
+        let result = unsafe {
+            ffi::EnumDisplaySettingsExW(wide_name.as_ptr(), ffi::ENUM_CURRENT_SETTINGS, &mut current, 0)
+        };
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut current_mode = mode_from_devmode(&current);
+        let path = active_paths.iter().find(|path| path.device_name == device_name);
+        if let Some(refresh) = path.and_then(|path| path.refresh) {
+            current_mode.refresh = refresh;
+        }
+        let mut hdr = HdrCapabilities {
+            encodings: vec![ColorEncoding::Srgb],
+            ..Default::default()
+        };
+        if path.is_some_and(|path| path.advanced_color_supported) {
+            hdr.encodings.extend([ColorEncoding::ScRgb, ColorEncoding::Pq]);
+        }
+
+        let mut modes = vec![current_mode];
+        for mode_index in 0.. {
+            let mut devmode = ffi::DEVMODEW::zeroed();
+            if unsafe { ffi::EnumDisplaySettingsExW(wide_name.as_ptr(), mode_index, &mut devmode, 0) } == 0 {
+                break;
+            }
+            // the current mode, listed with its rounded rate
+            if devmode.dmPelsWidth == current.dmPelsWidth
+                && devmode.dmPelsHeight == current.dmPelsHeight
+                && devmode.dmDisplayFrequency == current.dmDisplayFrequency
+            {
+                continue;
+            }
+            // the same mode is listed for every color depth
+            let mode = mode_from_devmode(&devmode);
+            if !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+
+        monitors.push(Monitor {
+            device_name,
+            adapter: ffi::from_wide(&device.DeviceString),
+            primary: device.StateFlags & ffi::DISPLAY_DEVICE_PRIMARY_DEVICE != 0,
+            position: Point::from((current.dmPositionX, current.dmPositionY)),
+            current_mode,
+            interlaced: current.dmFields & ffi::DM_DISPLAYFLAGS != 0
+                && current.dmDisplayFlags & ffi::DM_INTERLACED != 0,
+            modes,
+            hdr,
+            hdr_enabled: path.is_some_and(|path| path.advanced_color_enabled),
+        });
+    }
+    Ok(monitors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ffi, refresh_from_hz, refresh_from_rational};
+
+    fn rational(numerator: u32, denominator: u32) -> Option<i32> {
+        refresh_from_rational(ffi::DISPLAYCONFIG_RATIONAL {
+            Numerator: numerator,
+            Denominator: denominator,
+        })
+    }
+
+    #[test]
+    fn fractional_refresh_rates() {
+        // whole rates of GDI are taken as they are
+        assert_eq!(refresh_from_hz(59), 59_000);
+        assert_eq!(refresh_from_hz(60), 60_000);
+        assert_eq!(refresh_from_hz(144), 144_000);
+
+        // exact rates of the display configuration
+        assert_eq!(rational(60_000, 1001), Some(59_940));
+        assert_eq!(rational(24_000, 1001), Some(23_976));
+        assert_eq!(rational(120_000, 1001), Some(119_880));
+        assert_eq!(rational(148_500_000, 2_475_000), Some(60_000));
+        assert_eq!(rational(143_981, 1000), Some(143_981));
+        assert_eq!(rational(60, 0), None);
+        assert_eq!(rational(0, 1), None);
+    }
+}
@@ -0,0 +1,524 @@
+#![allow(non_snake_case, non_camel_case_types, clippy::upper_case_acronyms)]
+
+use std::ffi::c_void;
+
+pub type HWND = isize;
+
+pub const HWND_BROADCAST: HWND = 0xffff;
+pub const WM_SYSCOMMAND: u32 = 0x0112;
+pub const SC_MONITORPOWER: usize = 0xf170;
+
+pub const DEVICE_NOTIFY_CALLBACK: u32 = 2;
+pub const PBT_APMSUSPEND: u32 = 0x4;
+pub const PBT_APMRESUMESUSPEND: u32 = 0x7;
+pub const PBT_APMRESUMEAUTOMATIC: u32 = 0x12;
+
+pub type DEVICE_NOTIFY_CALLBACK_ROUTINE =
+    unsafe extern "system" fn(context: *mut c_void, type_: u32, setting: *mut c_void) -> u32;
+
+#[repr(C)]
+pub struct DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+    pub Callback: DEVICE_NOTIFY_CALLBACK_ROUTINE,
+    pub Context: *mut c_void,
+}
+
+pub const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+pub const ES_DISPLAY_REQUIRED: u32 = 0x0000_0002;
+pub const ES_CONTINUOUS: u32 = 0x8000_0000;
+
+#[repr(C)]
+pub struct LASTINPUTINFO {
+    pub cbSize: u32,
+    pub dwTime: u32,
+}
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn GetLastInputInfo(info: *mut LASTINPUTINFO) -> i32;
+    pub fn PostMessageW(hwnd: HWND, msg: u32, wparam: usize, lparam: isize) -> i32;
+    pub fn RegisterSuspendResumeNotification(recipient: *mut c_void, flags: u32) -> isize;
+    pub fn UnregisterSuspendResumeNotification(handle: isize) -> i32;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn GetTickCount() -> u32;
+    pub fn SetThreadExecutionState(flags: u32) -> u32;
+}
+
+pub const ENUM_CURRENT_SETTINGS: u32 = 0xffff_ffff;
+pub const DISPLAY_DEVICE_ATTACHED_TO_DESKTOP: u32 = 0x1;
+pub const DISPLAY_DEVICE_PRIMARY_DEVICE: u32 = 0x4;
+pub const DM_DISPLAYFLAGS: u32 = 0x0020_0000;
+pub const DM_INTERLACED: u32 = 0x2;
+
+pub const QDC_ONLY_ACTIVE_PATHS: u32 = 0x2;
+pub const DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME: u32 = 1;
+pub const DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO: u32 = 9;
+pub const ERROR_SUCCESS: i32 = 0;
+
+#[repr(C)]
+pub struct DISPLAY_DEVICEW {
+    pub cb: u32,
+    pub DeviceName: [u16; 32],
+    pub DeviceString: [u16; 128],
+    pub StateFlags: u32,
+    pub DeviceID: [u16; 128],
+    pub DeviceKey: [u16; 128],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DEVMODEW {
+    pub dmDeviceName: [u16; 32],
+    pub dmSpecVersion: u16,
+    pub dmDriverVersion: u16,
+    pub dmSize: u16,
+    pub dmDriverExtra: u16,
+    pub dmFields: u32,
+    pub dmPositionX: i32,
+    pub dmPositionY: i32,
+    pub dmDisplayOrientation: u32,
+    pub dmDisplayFixedOutput: u32,
+    pub dmColor: i16,
+    pub dmDuplex: i16,
+    pub dmYResolution: i16,
+    pub dmTTOption: i16,
+    pub dmCollate: i16,
+    pub dmFormName: [u16; 32],
+    pub dmLogPixels: u16,
+    pub dmBitsPerPel: u32,
+    pub dmPelsWidth: u32,
+    pub dmPelsHeight: u32,
+    pub dmDisplayFlags: u32,
+    pub dmDisplayFrequency: u32,
+    pub dmICMMethod: u32,
+    pub dmICMIntent: u32,
+    pub dmMediaType: u32,
+    pub dmDitherType: u32,
+    pub dmReserved1: u32,
+    pub dmReserved2: u32,
+    pub dmPanningWidth: u32,
+    pub dmPanningHeight: u32,
+}
+
+impl DEVMODEW {
+    pub fn zeroed() -> DEVMODEW {
+        // SAFETY: plain old data, all zeroes is a valid value
+        let mut mode: DEVMODEW = unsafe { std::mem::zeroed() };
+        mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+        mode
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LUID {
+    pub LowPart: u32,
+    pub HighPart: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_RATIONAL {
+    pub Numerator: u32,
+    pub Denominator: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_SOURCE_INFO {
+    pub adapterId: LUID,
+    pub id: u32,
+    pub modeInfoIdx: u32,
+    pub statusFlags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_TARGET_INFO {
+    pub adapterId: LUID,
+    pub id: u32,
+    pub modeInfoIdx: u32,
+    pub outputTechnology: u32,
+    pub rotation: u32,
+    pub scaling: u32,
+    pub refreshRate: DISPLAYCONFIG_RATIONAL,
+    pub scanLineOrdering: u32,
+    pub targetAvailable: i32,
+    pub statusFlags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_INFO {
+    pub sourceInfo: DISPLAYCONFIG_PATH_SOURCE_INFO,
+    pub targetInfo: DISPLAYCONFIG_PATH_TARGET_INFO,
+    pub flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_MODE_INFO {
+    pub infoType: u32,
+    pub id: u32,
+    pub adapterId: LUID,
+    // union of the source, target and desktop image modes
+    pub data: [u64; 6],
+}
+
+#[repr(C)]
+pub struct DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    pub r#type: u32,
+    pub size: u32,
+    pub adapterId: LUID,
+    pub id: u32,
+}
+
+#[repr(C)]
+pub struct DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+    pub header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    pub viewGdiDeviceName: [u16; 32],
+}
+
+#[repr(C)]
+pub struct DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO {
+    pub header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    // bit 0: supported, bit 1: enabled
+    pub value: u32,
+    pub colorEncoding: u32,
+    pub bitsPerColorChannel: u32,
+}
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn EnumDisplayDevicesW(
+        device: *const u16,
+        index: u32,
+        display_device: *mut DISPLAY_DEVICEW,
+        flags: u32,
+    ) -> i32;
+    pub fn EnumDisplaySettingsExW(
+        device: *const u16,
+        mode_num: u32,
+        dev_mode: *mut DEVMODEW,
+        flags: u32,
+    ) -> i32;
+    pub fn GetDisplayConfigBufferSizes(flags: u32, num_paths: *mut u32, num_modes: *mut u32) -> i32;
+    pub fn QueryDisplayConfig(
+        flags: u32,
+        num_paths: *mut u32,
+        paths: *mut DISPLAYCONFIG_PATH_INFO,
+        num_modes: *mut u32,
+        modes: *mut DISPLAYCONFIG_MODE_INFO,
+        topology: *mut u32,
+    ) -> i32;
+    pub fn DisplayConfigGetDeviceInfo(packet: *mut DISPLAYCONFIG_DEVICE_INFO_HEADER) -> i32;
+}
+
+/// Converts a string into a nul terminated UTF-16 buffer
+pub fn to_wide(string: &str) -> Vec<u16> {
+    string.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Converts a nul terminated UTF-16 buffer into a string
+pub fn from_wide(wide: &[u16]) -> String {
+    let len = wide.iter().position(|c| *c == 0).unwrap_or(wide.len());
+    String::from_utf16_lossy(&wide[..len])
+}
+
+pub const GWL_STYLE: i32 = -16;
+pub const GWL_EXSTYLE: i32 = -20;
+pub const WS_OVERLAPPEDWINDOW: isize = 0x00cf_0000;
+pub const WS_EX_TRANSPARENT: isize = 0x0000_0020;
+pub const WS_EX_LAYERED: isize = 0x0008_0000;
+pub const HWND_TOP: HWND = 0;
+pub const SWP_NOSIZE: u32 = 0x0001;
+pub const SWP_NOMOVE: u32 = 0x0002;
+pub const SWP_NOZORDER: u32 = 0x0004;
+pub const SWP_FRAMECHANGED: u32 = 0x0020;
+pub const SWP_NOOWNERZORDER: u32 = 0x0200;
+pub const MONITOR_DEFAULTTONEAREST: u32 = 0x2;
+pub const CDS_FULLSCREEN: u32 = 0x4;
+pub const DISP_CHANGE_SUCCESSFUL: i32 = 0;
+pub const DM_PELSWIDTH: u32 = 0x0008_0000;
+pub const DM_PELSHEIGHT: u32 = 0x0010_0000;
+pub const DM_DISPLAYFREQUENCY: u32 = 0x0040_0000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct RECT {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct POINT {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[repr(C)]
+pub struct MONITORINFOEXW {
+    pub cbSize: u32,
+    pub rcMonitor: RECT,
+    pub rcWork: RECT,
+    pub dwFlags: u32,
+    pub szDevice: [u16; 32],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct WINDOWPLACEMENT {
+    pub length: u32,
+    pub flags: u32,
+    pub showCmd: u32,
+    pub ptMinPosition: POINT,
+    pub ptMaxPosition: POINT,
+    pub rcNormalPosition: RECT,
+}
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn GetWindowLongPtrW(hwnd: HWND, index: i32) -> isize;
+    pub fn SetWindowLongPtrW(hwnd: HWND, index: i32, value: isize) -> isize;
+    pub fn GetWindowPlacement(hwnd: HWND, placement: *mut WINDOWPLACEMENT) -> i32;
+    pub fn SetWindowPlacement(hwnd: HWND, placement: *const WINDOWPLACEMENT) -> i32;
+    pub fn GetWindowRect(hwnd: HWND, rect: *mut RECT) -> i32;
+    pub fn SetWindowPos(hwnd: HWND, after: HWND, x: i32, y: i32, cx: i32, cy: i32, flags: u32) -> i32;
+    pub fn MonitorFromWindow(hwnd: HWND, flags: u32) -> isize;
+    pub fn GetMonitorInfoW(monitor: isize, info: *mut MONITORINFOEXW) -> i32;
+    pub fn ChangeDisplaySettingsExW(
+        device: *const u16,
+        dev_mode: *const DEVMODEW,
+        hwnd: HWND,
+        flags: u32,
+        param: *const std::ffi::c_void,
+    ) -> i32;
+}
+
+pub const INPUT_MOUSE: u32 = 0;
+pub const INPUT_KEYBOARD: u32 = 1;
+pub const KEYEVENTF_KEYUP: u32 = 0x0002;
+pub const MOUSEEVENTF_MOVE: u32 = 0x0001;
+pub const MOUSEEVENTF_LEFTDOWN: u32 = 0x0002;
+pub const MOUSEEVENTF_LEFTUP: u32 = 0x0004;
+pub const MOUSEEVENTF_RIGHTDOWN: u32 = 0x0008;
+pub const MOUSEEVENTF_RIGHTUP: u32 = 0x0010;
+pub const MOUSEEVENTF_MIDDLEDOWN: u32 = 0x0020;
+pub const MOUSEEVENTF_MIDDLEUP: u32 = 0x0040;
+pub const MOUSEEVENTF_XDOWN: u32 = 0x0080;
+pub const MOUSEEVENTF_XUP: u32 = 0x0100;
+pub const MOUSEEVENTF_WHEEL: u32 = 0x0800;
+pub const MOUSEEVENTF_HWHEEL: u32 = 0x1000;
+pub const MOUSEEVENTF_VIRTUALDESK: u32 = 0x4000;
+pub const MOUSEEVENTF_ABSOLUTE: u32 = 0x8000;
+pub const XBUTTON1: u32 = 0x0001;
+pub const XBUTTON2: u32 = 0x0002;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MOUSEINPUT {
+    pub dx: i32,
+    pub dy: i32,
+    pub mouseData: u32,
+    pub dwFlags: u32,
+    pub time: u32,
+    pub dwExtraInfo: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct KEYBDINPUT {
+    pub wVk: u16,
+    pub wScan: u16,
+    pub dwFlags: u32,
+    pub time: u32,
+    pub dwExtraInfo: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union INPUT_0 {
+    pub mi: MOUSEINPUT,
+    pub ki: KEYBDINPUT,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct INPUT {
+    pub r#type: u32,
+    pub Anonymous: INPUT_0,
+}
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn SendInput(count: u32, inputs: *const INPUT, size: i32) -> u32;
+    pub fn SetCursorPos(x: i32, y: i32) -> i32;
+    pub fn GetCursorPos(point: *mut POINT) -> i32;
+}
+
+pub const HWND_MESSAGE: HWND = -3;
+pub const WM_QUIT: u32 = 0x0012;
+pub const WM_INPUT_DEVICE_CHANGE: u32 = 0x00fe;
+pub const WM_INPUT: u32 = 0x00ff;
+pub const GIDC_ARRIVAL: usize = 1;
+pub const GIDC_REMOVAL: usize = 2;
+
+pub const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+pub const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
+pub const HID_USAGE_GENERIC_KEYBOARD: u16 = 0x06;
+pub const RIDEV_INPUTSINK: u32 = 0x0000_0100;
+pub const RIDEV_DEVNOTIFY: u32 = 0x0000_2000;
+pub const RID_INPUT: u32 = 0x1000_0003;
+pub const RIDI_DEVICENAME: u32 = 0x2000_0007;
+pub const RIM_TYPEMOUSE: u32 = 0;
+pub const RIM_TYPEKEYBOARD: u32 = 1;
+
+pub const RI_KEY_BREAK: u16 = 0x01;
+pub const RI_KEY_E0: u16 = 0x02;
+pub const RI_KEY_E1: u16 = 0x04;
+pub const KEYBOARD_OVERRUN_MAKE_CODE: u16 = 0xff;
+
+pub const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+pub const MOUSE_VIRTUAL_DESKTOP: u16 = 0x02;
+pub const RI_MOUSE_LEFT_BUTTON_DOWN: u16 = 0x0001;
+pub const RI_MOUSE_LEFT_BUTTON_UP: u16 = 0x0002;
+pub const RI_MOUSE_RIGHT_BUTTON_DOWN: u16 = 0x0004;
+pub const RI_MOUSE_RIGHT_BUTTON_UP: u16 = 0x0008;
+pub const RI_MOUSE_MIDDLE_BUTTON_DOWN: u16 = 0x0010;
+pub const RI_MOUSE_MIDDLE_BUTTON_UP: u16 = 0x0020;
+pub const RI_MOUSE_BUTTON_4_DOWN: u16 = 0x0040;
+pub const RI_MOUSE_BUTTON_4_UP: u16 = 0x0080;
+pub const RI_MOUSE_BUTTON_5_DOWN: u16 = 0x0100;
+pub const RI_MOUSE_BUTTON_5_UP: u16 = 0x0200;
+pub const RI_MOUSE_WHEEL: u16 = 0x0400;
+pub const RI_MOUSE_HWHEEL: u16 = 0x0800;
+
+#[repr(C)]
+pub struct MSG {
+    pub hwnd: HWND,
+    pub message: u32,
+    pub wParam: usize,
+    pub lParam: isize,
+    pub time: u32,
+    pub pt: POINT,
+}
+
+#[repr(C)]
+pub struct RAWINPUTDEVICE {
+    pub usUsagePage: u16,
+    pub usUsage: u16,
+    pub dwFlags: u32,
+    pub hwndTarget: HWND,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWINPUTHEADER {
+    pub dwType: u32,
+    pub dwSize: u32,
+    pub hDevice: isize,
+    pub wParam: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWMOUSE_0_0 {
+    pub usButtonFlags: u16,
+    pub usButtonData: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union RAWMOUSE_0 {
+    pub ulButtons: u32,
+    pub Anonymous: RAWMOUSE_0_0,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWMOUSE {
+    pub usFlags: u16,
+    pub Anonymous: RAWMOUSE_0,
+    pub ulRawButtons: u32,
+    pub lLastX: i32,
+    pub lLastY: i32,
+    pub ulExtraInformation: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWKEYBOARD {
+    pub MakeCode: u16,
+    pub Flags: u16,
+    pub Reserved: u16,
+    pub VKey: u16,
+    pub Message: u32,
+    pub ExtraInformation: u32,
+}
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn CreateWindowExW(
+        ex_style: u32,
+        class_name: *const u16,
+        window_name: *const u16,
+        style: u32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        parent: HWND,
+        menu: isize,
+        instance: isize,
+        param: *const c_void,
+    ) -> HWND;
+    pub fn DestroyWindow(hwnd: HWND) -> i32;
+    pub fn GetMessageW(msg: *mut MSG, hwnd: HWND, filter_min: u32, filter_max: u32) -> i32;
+    pub fn DispatchMessageW(msg: *const MSG) -> isize;
+    pub fn PostThreadMessageW(thread_id: u32, msg: u32, wparam: usize, lparam: isize) -> i32;
+    pub fn RegisterRawInputDevices(devices: *const RAWINPUTDEVICE, count: u32, size: u32) -> i32;
+    pub fn GetRawInputData(
+        raw_input: isize,
+        command: u32,
+        data: *mut c_void,
+        size: *mut u32,
+        header_size: u32,
+    ) -> u32;
+    pub fn GetRawInputDeviceInfoW(device: isize, command: u32, data: *mut c_void, size: *mut u32) -> u32;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn GetCurrentThreadId() -> u32;
+}
+
+pub const CF_UNICODETEXT: u32 = 13;
+pub const WM_CLIPBOARDUPDATE: u32 = 0x031d;
+pub const GMEM_MOVEABLE: u32 = 0x2;
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn AddClipboardFormatListener(hwnd: HWND) -> i32;
+    pub fn RemoveClipboardFormatListener(hwnd: HWND) -> i32;
+    pub fn OpenClipboard(owner: HWND) -> i32;
+    pub fn CloseClipboard() -> i32;
+    pub fn EmptyClipboard() -> i32;
+    pub fn GetClipboardData(format: u32) -> isize;
+    pub fn SetClipboardData(format: u32, memory: isize) -> isize;
+    pub fn IsClipboardFormatAvailable(format: u32) -> i32;
+    pub fn GetClipboardSequenceNumber() -> u32;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn GlobalAlloc(flags: u32, bytes: usize) -> isize;
+    pub fn GlobalFree(memory: isize) -> isize;
+    pub fn GlobalLock(memory: isize) -> *mut c_void;
+    pub fn GlobalUnlock(memory: isize) -> i32;
+    pub fn GlobalSize(memory: isize) -> usize;
+}
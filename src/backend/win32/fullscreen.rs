@@ -0,0 +1,266 @@
+//! Fullscreen presentation of a window
+//!
+//! A nested compositor running in a window gets the lowest latency when the Desktop Window
+//! Manager stops composing its window and scans it out directly ("independent flip"). DWM does
+//! that for windows covering an entire display without any transparency, so switching to
+//! [`FullscreenMode::Borderless`] is usually enough. [`FullscreenMode::Exclusive`] additionally
+//! changes the mode of the display, e.g. to run at a lower resolution or a different refresh rate.
+//!
+//! DXGI exclusive fullscreen is not available, as the window is rendered to with OpenGL.
+//!
+//! ```no_run
+//! use smithay::backend::win32::fullscreen::{FullscreenMode, WindowFullscreen};
+//!
+//! # let hwnd: isize = 0;
+//! let mut fullscreen = unsafe { WindowFullscreen::new(hwnd) };
+//! fullscreen.set_mode(FullscreenMode::Borderless).unwrap();
+//! if !fullscreen.independent_flip_eligible() {
+//!     // expect an additional frame of latency
+//! }
+//! ```
+
+use std::io;
+
+use super::ffi;
+use crate::output::Mode;
+
+/// Fullscreen state of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// Regular window with decorations
+    Windowed,
+    /// Window without decorations covering the display it is on
+    Borderless,
+    /// Like [`FullscreenMode::Borderless`], but switching the display to the given mode
+    Exclusive(Mode),
+}
+
+/// Errors switching the fullscreen mode
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A Win32 call failed
+    #[error("Win32 call failed: {0}")]
+    Os(#[from] io::Error),
+    /// The display rejected the requested mode
+    #[error("The display rejected the mode with error {0}")]
+    ModeRejected(i32),
+}
+
+struct Saved {
+    style: isize,
+    placement: ffi::WINDOWPLACEMENT,
+}
+
+impl std::fmt::Debug for Saved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Saved")
+            .field("style", &self.style)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Controls the fullscreen state of a window
+#[derive(Debug)]
+pub struct WindowFullscreen {
+    hwnd: isize,
+    mode: FullscreenMode,
+    saved: Option<Saved>,
+    // display whose mode was changed for exclusive fullscreen
+    changed_display: Option<Vec<u16>>,
+}
+
+impl WindowFullscreen {
+    /// Control the fullscreen state of the given window, which is currently windowed
+    ///
+    /// # Safety
+    ///
+    /// `hwnd` has to be a valid window handle, that outlives the returned value.
+    pub unsafe fn new(hwnd: isize) -> WindowFullscreen {
+        WindowFullscreen {
+            hwnd,
+            mode: FullscreenMode::Windowed,
+            saved: None,
+            changed_display: None,
+        }
+    }
+
+    /// Returns the handle of the controlled window
+    pub fn hwnd(&self) -> isize {
+        self.hwnd
+    }
+
+    /// Returns the current fullscreen mode
+    pub fn mode(&self) -> FullscreenMode {
+        self.mode
+    }
+
+    /// Switch the window to the given fullscreen mode
+    pub fn set_mode(&mut self, mode: FullscreenMode) -> Result<(), Error> {
+        if mode == self.mode {
+            return Ok(());
+        }
+
+        self.restore_display_mode();
+        match mode {
+            FullscreenMode::Windowed => self.restore_window()?,
+            FullscreenMode::Borderless => self.cover_monitor()?,
+            FullscreenMode::Exclusive(display_mode) => {
+                self.change_display_mode(display_mode)?;
+                self.cover_monitor()?;
+            }
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Returns whether DWM is likely to present the window with independent flip
+    ///
+    /// This is the case if the window covers the display exactly and is neither layered nor
+    /// transparent. DWM may still decide otherwise, e.g. while an overlay like a volume
+    /// indicator is shown.
+    pub fn independent_flip_eligible(&self) -> bool {
+        let ex_style = unsafe { ffi::GetWindowLongPtrW(self.hwnd, ffi::GWL_EXSTYLE) };
+        if ex_style & (ffi::WS_EX_LAYERED | ffi::WS_EX_TRANSPARENT) != 0 {
+            return false;
+        }
+        let mut rect = ffi::RECT::default();
+        if unsafe { ffi::GetWindowRect(self.hwnd, &mut rect) } == 0 {
+            return false;
+        }
+        self.monitor_info().is_ok_and(|info| info.rcMonitor == rect)
+    }
+
+    fn monitor_info(&self) -> io::Result<ffi::MONITORINFOEXW> {
+        let monitor = unsafe { ffi::MonitorFromWindow(self.hwnd, ffi::MONITOR_DEFAULTTONEAREST) };
+        let mut info = ffi::MONITORINFOEXW {
+            cbSize: std::mem::size_of::<ffi::MONITORINFOEXW>() as u32,
+            rcMonitor: ffi::RECT::default(),
+            rcWork: ffi::RECT::default(),
+            dwFlags: 0,
+            szDevice: [0; 32],
+        };
+        if unsafe { ffi::GetMonitorInfoW(monitor, &mut info) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(info)
+    }
+
+    fn cover_monitor(&mut self) -> io::Result<()> {
+        if self.saved.is_none() {
+            let mut placement = ffi::WINDOWPLACEMENT {
+                length: std::mem::size_of::<ffi::WINDOWPLACEMENT>() as u32,
+                ..Default::default()
+            };
+            if unsafe { ffi::GetWindowPlacement(self.hwnd, &mut placement) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let style = unsafe { ffi::GetWindowLongPtrW(self.hwnd, ffi::GWL_STYLE) };
+            self.saved = Some(Saved { style, placement });
+        }
+
+        let style = self.saved.as_ref().unwrap().style;
+        let rect = self.monitor_info()?.rcMonitor;
+        unsafe {
+            ffi::SetWindowLongPtrW(self.hwnd, ffi::GWL_STYLE, style & !ffi::WS_OVERLAPPEDWINDOW);
+            if ffi::SetWindowPos(
+                self.hwnd,
+                ffi::HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                ffi::SWP_NOOWNERZORDER | ffi::SWP_FRAMECHANGED,
+            ) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    fn restore_window(&mut self) -> io::Result<()> {
+        let Some(saved) = self.saved.take() else {
+            return Ok(());
+        };
+        unsafe {
+            ffi::SetWindowLongPtrW(self.hwnd, ffi::GWL_STYLE, saved.style);
+            if ffi::SetWindowPlacement(self.hwnd, &saved.placement) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            ffi::SetWindowPos(
+                self.hwnd,
+                0,
+                0,
+                0,
+                0,
+                0,
+                ffi::SWP_NOMOVE
+                    | ffi::SWP_NOSIZE
+                    | ffi::SWP_NOZORDER
+                    | ffi::SWP_NOOWNERZORDER
+                    | ffi::SWP_FRAMECHANGED,
+            );
+        }
+        Ok(())
+    }
+
+    fn change_display_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        // nul terminated by the system
+        let device = self.monitor_info()?.szDevice.to_vec();
+
+        let mut devmode = ffi::DEVMODEW::zeroed();
+        devmode.dmFields = ffi::DM_PELSWIDTH | ffi::DM_PELSHEIGHT | ffi::DM_DISPLAYFREQUENCY;
+        devmode.dmPelsWidth = mode.size.w as u32;
+        devmode.dmPelsHeight = mode.size.h as u32;
+        // GDI takes whole hertz, rounded down like it reports them
+        devmode.dmDisplayFrequency = (mode.refresh / 1000) as u32;
+
+        let result = unsafe {
+            ffi::ChangeDisplaySettingsExW(
+                device.as_ptr(),
+                &devmode,
+                0,
+                ffi::CDS_FULLSCREEN,
+                std::ptr::null(),
+            )
+        };
+        if result != ffi::DISP_CHANGE_SUCCESSFUL {
+            return Err(Error::ModeRejected(result));
+        }
+        self.changed_display = Some(device);
+        Ok(())
+    }
+
+    fn restore_display_mode(&mut self) {
+        if let Some(device) = self.changed_display.take() {
+            // restores the mode stored in the registry
+            unsafe {
+                ffi::ChangeDisplaySettingsExW(device.as_ptr(), std::ptr::null(), 0, 0, std::ptr::null())
+            };
+        }
+    }
+}
+
+impl Drop for WindowFullscreen {
+    fn drop(&mut self) {
+        self.restore_display_mode();
+    }
+}
+
+#[cfg(feature = "backend_wgl")]
+impl raw_window_handle::HasWindowHandle for WindowFullscreen {
+    fn window_handle(&self) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
+        let hwnd =
+            std::num::NonZeroIsize::new(self.hwnd).ok_or(raw_window_handle::HandleError::Unavailable)?;
+        let handle = raw_window_handle::Win32WindowHandle::new(hwnd);
+        // SAFETY: `WindowFullscreen::new` requires the window to outlive `self`
+        Ok(unsafe { raw_window_handle::WindowHandle::borrow_raw(handle.into()) })
+    }
+}
+
+#[cfg(feature = "backend_wgl")]
+impl raw_window_handle::HasDisplayHandle for WindowFullscreen {
+    fn display_handle(&self) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
+        Ok(raw_window_handle::DisplayHandle::windows())
+    }
+}
@@ -0,0 +1,160 @@
+//! Input injection through `SendInput`
+//!
+//! [`SendInputInjector`] implements [`InputInjector`] by synthesizing system input events. They
+//! are delivered to the window with focus like events of real devices, so the compositor receives
+//! them through its window backend. See the [`inject`](crate::backend::input::inject) module for
+//! the direct fallback.
+//!
+//! [`set_cursor_position`] repositions the host cursor, e.g. after the compositor
+//! [warped](crate::input::pointer::PointerHandle::warp) its pointer.
+
+use std::{io, mem};
+
+use super::ffi;
+use crate::{
+    backend::input::{
+        inject::{InjectedEvent, InputInjector},
+        Axis, ButtonState, KeyState,
+    },
+    utils::{Logical, Physical, Point},
+};
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+// absolute coordinates are normalized to this range across the virtual desktop
+const ABSOLUTE_RANGE: f64 = 65535.0;
+
+fn mouse_input(dx: i32, dy: i32, data: u32, flags: u32) -> ffi::INPUT {
+    ffi::INPUT {
+        r#type: ffi::INPUT_MOUSE,
+        Anonymous: ffi::INPUT_0 {
+            mi: ffi::MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: data,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+fn send(input: ffi::INPUT) -> io::Result<()> {
+    let sent = unsafe { ffi::SendInput(1, &input, mem::size_of::<ffi::INPUT>() as i32) };
+    if sent == 1 {
+        Ok(())
+    } else {
+        // also fails if blocked by UIPI, e.g. while an elevated window has focus
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Injector synthesizing system input with `SendInput`
+///
+/// Key codes are virtual key codes, like the [`Keycode`](crate::backend::input::Keycode)s of the
+/// Windows backends. Relative motion is subject to the pointer speed settings of the system.
+#[derive(Debug, Default)]
+pub struct SendInputInjector {
+    motion_remainder: Point<f64, Logical>,
+}
+
+impl SendInputInjector {
+    /// Create a new injector
+    pub fn new() -> SendInputInjector {
+        SendInputInjector::default()
+    }
+}
+
+impl InputInjector for SendInputInjector {
+    fn inject(&mut self, event: InjectedEvent) -> io::Result<()> {
+        let input = match event {
+            InjectedEvent::Key { key, state } => ffi::INPUT {
+                r#type: ffi::INPUT_KEYBOARD,
+                Anonymous: ffi::INPUT_0 {
+                    ki: ffi::KEYBDINPUT {
+                        wVk: key as u16,
+                        wScan: 0,
+                        dwFlags: match state {
+                            KeyState::Pressed => 0,
+                            KeyState::Released => ffi::KEYEVENTF_KEYUP,
+                        },
+                        time: 0,
+                        dwExtraInfo: 0,
+                    },
+                },
+            },
+            InjectedEvent::PointerMotion { delta } => {
+                // only whole pixels can be sent, carry the fractions over to the next motion
+                let delta = delta + self.motion_remainder;
+                let (dx, dy) = (delta.x.trunc(), delta.y.trunc());
+                self.motion_remainder = (delta.x - dx, delta.y - dy).into();
+                mouse_input(dx as i32, dy as i32, 0, ffi::MOUSEEVENTF_MOVE)
+            }
+            InjectedEvent::PointerMotionAbsolute { position } => {
+                let scale = |v: f64| (v.clamp(0.0, 1.0) * ABSOLUTE_RANGE).round() as i32;
+                mouse_input(
+                    scale(position.x),
+                    scale(position.y),
+                    0,
+                    ffi::MOUSEEVENTF_MOVE | ffi::MOUSEEVENTF_ABSOLUTE | ffi::MOUSEEVENTF_VIRTUALDESK,
+                )
+            }
+            InjectedEvent::PointerButton { button, state } => {
+                let pressed = state == ButtonState::Pressed;
+                let (data, flags) = match (button, pressed) {
+                    (BTN_LEFT, true) => (0, ffi::MOUSEEVENTF_LEFTDOWN),
+                    (BTN_LEFT, false) => (0, ffi::MOUSEEVENTF_LEFTUP),
+                    (BTN_RIGHT, true) => (0, ffi::MOUSEEVENTF_RIGHTDOWN),
+                    (BTN_RIGHT, false) => (0, ffi::MOUSEEVENTF_RIGHTUP),
+                    (BTN_MIDDLE, true) => (0, ffi::MOUSEEVENTF_MIDDLEDOWN),
+                    (BTN_MIDDLE, false) => (0, ffi::MOUSEEVENTF_MIDDLEUP),
+                    (BTN_SIDE, true) => (ffi::XBUTTON1, ffi::MOUSEEVENTF_XDOWN),
+                    (BTN_SIDE, false) => (ffi::XBUTTON1, ffi::MOUSEEVENTF_XUP),
+                    (BTN_EXTRA, true) => (ffi::XBUTTON2, ffi::MOUSEEVENTF_XDOWN),
+                    (BTN_EXTRA, false) => (ffi::XBUTTON2, ffi::MOUSEEVENTF_XUP),
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            format!("button {:#x} cannot be injected", button),
+                        ))
+                    }
+                };
+                mouse_input(0, 0, data, flags)
+            }
+            // windows uses the same units of 120 per wheel click, but scrolls up for positive values
+            InjectedEvent::PointerAxis { axis, v120 } => match axis {
+                Axis::Vertical => mouse_input(0, 0, (-v120).round() as i32 as u32, ffi::MOUSEEVENTF_WHEEL),
+                Axis::Horizontal => mouse_input(0, 0, v120.round() as i32 as u32, ffi::MOUSEEVENTF_HWHEEL),
+            },
+        };
+        send(input)
+    }
+}
+
+/// Move the host cursor to a position on the virtual desktop
+///
+/// Positions on a display are offset by its [`Monitor::position`](super::display::Monitor::position).
+/// Without repositioning the host cursor after a warp, the next motion of the physical mouse
+/// reports the cursor next to its old position again.
+pub fn set_cursor_position(position: Point<i32, Physical>) -> io::Result<()> {
+    if unsafe { ffi::SetCursorPos(position.x, position.y) } != 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Current position of the host cursor on the virtual desktop
+pub fn cursor_position() -> io::Result<Point<i32, Physical>> {
+    let mut point = ffi::POINT::default();
+    if unsafe { ffi::GetCursorPos(&mut point) } != 0 {
+        Ok((point.x, point.y).into())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -0,0 +1,22 @@
+//! Helpers for running a compositor on Windows
+//!
+//! Windows has no equivalent of the session, DRM and libinput stacks used on Linux. Instead
+//! this module wraps the Win32 APIs a compositor needs to integrate with the system:
+//!
+//! - [`clipboard`]: access to the clipboard, and a bridge mirroring it to the clipboard selection.
+//! - [`display`]: enumeration of the displays and their modes, to populate [`Output`](crate::output::Output)s.
+//! - [`fullscreen`]: borderless and exclusive fullscreen of the window of a nested compositor.
+//! - [`input`]: injection of synthetic input, e.g. for remote desktop sessions, and
+//!   repositioning of the host cursor.
+//! - [`power`]: idle detection, display power control and suspend notifications, backing the
+//!   idle notify protocol and output power management.
+//! - [`raw_input`]: an input backend receiving the keyboards and mice of the system through the
+//!   raw input API.
+
+pub mod clipboard;
+pub mod display;
+mod ffi;
+pub mod fullscreen;
+pub mod input;
+pub mod power;
+pub mod raw_input;
@@ -0,0 +1,346 @@
+//! Idle detection and display power control
+//!
+//! [`IdleActivitySource`] watches the system wide time of the last user input, which also covers
+//! input not delivered to the compositor, e.g. while another application has focus. Feed its
+//! events to [`IdleNotifierState::notify_activity`](crate::wayland::idle_notify::IdleNotifierState::notify_activity):
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use smithay::backend::win32::power::IdleActivitySource;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(IdleActivitySource::new(Duration::from_millis(500)), |_, _, _state| {
+//!         // state.idle_notifier_state.notify_activity(&seat);
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! Displays are turned off and on with [`set_display_power`], and [`KeepAwake`] prevents the
+//! system from blanking the displays or going to sleep while e.g. an idle inhibitor is active.
+//!
+//! The [`SuspendResumeSource`] delivers the [`PowerEvent`]s of the system, see
+//! [`backend::power`](crate::backend::power) for how to handle them.
+
+use std::{
+    ffi::c_void,
+    io,
+    marker::PhantomData,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use super::ffi;
+use crate::{
+    backend::power::PowerEvent,
+    compat::notifier::{self, Notifier, NotifierSource},
+};
+
+fn last_input_tick() -> Option<u32> {
+    let mut info = ffi::LASTINPUTINFO {
+        cbSize: std::mem::size_of::<ffi::LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    (unsafe { ffi::GetLastInputInfo(&mut info) } != 0).then_some(info.dwTime)
+}
+
+/// Time since the last user input on the system
+pub fn idle_time() -> Duration {
+    last_input_tick()
+        .map(|tick| {
+            // the tick count wraps around after 49.7 days
+            let now = unsafe { ffi::GetTickCount() };
+            Duration::from_millis(now.wrapping_sub(tick) as u64)
+        })
+        .unwrap_or_default()
+}
+
+/// Calloop event source generating an event whenever the user provided input
+///
+/// The system only exposes the time of the last input, so it is polled at the given interval
+/// and at most one event is generated per interval.
+#[derive(Debug)]
+pub struct IdleActivitySource {
+    timer: Timer,
+    interval: Duration,
+    last: Option<u32>,
+}
+
+impl IdleActivitySource {
+    /// Create a new source polling at the given interval
+    pub fn new(interval: Duration) -> IdleActivitySource {
+        IdleActivitySource {
+            timer: Timer::from_duration(interval),
+            interval,
+            last: last_input_tick(),
+        }
+    }
+}
+
+impl EventSource for IdleActivitySource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = <Timer as EventSource>::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let interval = self.interval;
+        let last = &mut self.last;
+        self.timer.process_events(readiness, token, |_: Instant, _| {
+            let tick = last_input_tick();
+            if tick.is_some() && *last != tick {
+                *last = tick;
+                callback((), &mut ());
+            }
+            TimeoutAction::ToDuration(interval)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+/// Power state of the displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisplayPower {
+    /// Displays are on
+    On,
+    /// Displays are in a low power state
+    Standby,
+    /// Displays are off
+    Off,
+}
+
+/// Set the power state of all displays
+///
+/// Windows only allows controlling all displays at once. Displays turn on again by themselves
+/// on the next user input.
+pub fn set_display_power(power: DisplayPower) -> io::Result<()> {
+    let state: isize = match power {
+        DisplayPower::On => -1,
+        DisplayPower::Standby => 1,
+        DisplayPower::Off => 2,
+    };
+    // posted instead of sent, as sending blocks until every top-level window handled it
+    let result = unsafe {
+        ffi::PostMessageW(
+            ffi::HWND_BROADCAST,
+            ffi::WM_SYSCOMMAND,
+            ffi::SC_MONITORPOWER,
+            state,
+        )
+    };
+    if result == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Prevents the system from going idle while alive
+///
+/// The system does not sleep and, if requested, the displays are not blanked until this is
+/// dropped. The request is bound to the creating thread, so this cannot be sent to
+/// another thread.
+#[derive(Debug)]
+pub struct KeepAwake {
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl KeepAwake {
+    /// Keep the system, and the displays if `display` is `true`, awake
+    pub fn new(display: bool) -> io::Result<KeepAwake> {
+        let mut flags = ffi::ES_CONTINUOUS | ffi::ES_SYSTEM_REQUIRED;
+        if display {
+            flags |= ffi::ES_DISPLAY_REQUIRED;
+        }
+        if unsafe { ffi::SetThreadExecutionState(flags) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(KeepAwake {
+            _thread_bound: PhantomData,
+        })
+    }
+}
+
+impl Drop for KeepAwake {
+    fn drop(&mut self) {
+        unsafe { ffi::SetThreadExecutionState(ffi::ES_CONTINUOUS) };
+    }
+}
+
+// the system gives applications two seconds to prepare for a suspend
+const SUSPEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Default)]
+struct PowerEvents {
+    events: Vec<PowerEvent>,
+    suspended: bool,
+    // a suspend was announced, but not dispatched yet
+    suspend_pending: bool,
+}
+
+#[derive(Debug)]
+struct SuspendResumeShared {
+    events: Mutex<PowerEvents>,
+    dispatched: Condvar,
+    notifier: Notifier,
+}
+
+unsafe extern "system" fn suspend_resume_callback(
+    context: *mut c_void,
+    type_: u32,
+    _setting: *mut c_void,
+) -> u32 {
+    // SAFETY: the context is the shared state of the source, which unregisters the callback
+    // before freeing it
+    let shared = &*(context as *const SuspendResumeShared);
+    let mut events = shared.events.lock().unwrap();
+    match type_ {
+        ffi::PBT_APMSUSPEND if !events.suspended => {
+            events.suspended = true;
+            events.suspend_pending = true;
+            events.events.push(PowerEvent::Suspend);
+            drop(events);
+            shared.notifier.notify();
+
+            // the system suspends once the callback returns, so wait for the compositor
+            let events = shared.events.lock().unwrap();
+            let _ = shared
+                .dispatched
+                .wait_timeout_while(events, SUSPEND_TIMEOUT, |events| events.suspend_pending);
+            return 0;
+        }
+        // both are sent if the resume was triggered by the user
+        ffi::PBT_APMRESUMEAUTOMATIC | ffi::PBT_APMRESUMESUSPEND if events.suspended => {
+            events.suspended = false;
+            events.events.push(PowerEvent::Resume);
+        }
+        _ => return 0,
+    }
+    drop(events);
+    shared.notifier.notify();
+    0
+}
+
+/// Calloop event source delivering the suspend and resume [`PowerEvent`]s of the system
+///
+/// The notifications arrive on a thread of the system. The suspend is delayed until the
+/// callback handling [`PowerEvent::Suspend`] returned, so rendering can be paused and the GPU
+/// work finished there, but at most for two seconds. A suspend cannot be prevented, [`KeepAwake`]
+/// only keeps the system from going to sleep because it is idle.
+pub struct SuspendResumeSource {
+    source: NotifierSource,
+    handle: isize,
+    // boxed, so the context passed to the callback stays valid
+    shared: Box<SuspendResumeShared>,
+    // the subscribe parameters have to outlive the registration
+    _params: Box<ffi::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS>,
+}
+
+impl std::fmt::Debug for SuspendResumeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SuspendResumeSource")
+            .field("source", &self.source)
+            .field("events", &self.shared.events)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SuspendResumeSource {
+    /// Register for suspend and resume notifications
+    pub fn new() -> io::Result<SuspendResumeSource> {
+        let (notifier, source) = notifier::new()?;
+        let shared = Box::new(SuspendResumeShared {
+            events: Mutex::new(PowerEvents::default()),
+            dispatched: Condvar::new(),
+            notifier,
+        });
+        let mut params = Box::new(ffi::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: suspend_resume_callback,
+            Context: &*shared as *const SuspendResumeShared as *mut c_void,
+        });
+        let handle = unsafe {
+            ffi::RegisterSuspendResumeNotification(
+                &mut *params as *mut ffi::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as *mut c_void,
+                ffi::DEVICE_NOTIFY_CALLBACK,
+            )
+        };
+        if handle == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(SuspendResumeSource {
+            source,
+            handle,
+            shared,
+            _params: params,
+        })
+    }
+}
+
+impl Drop for SuspendResumeSource {
+    fn drop(&mut self) {
+        unsafe { ffi::UnregisterSuspendResumeNotification(self.handle) };
+    }
+}
+
+impl EventSource for SuspendResumeSource {
+    type Event = PowerEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let shared = &self.shared;
+        self.source.process_events(readiness, token, |_, _| {
+            let events = std::mem::take(&mut shared.events.lock().unwrap().events);
+            for event in events {
+                callback(event, &mut ());
+            }
+            shared.events.lock().unwrap().suspend_pending = false;
+            shared.dispatched.notify_all();
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
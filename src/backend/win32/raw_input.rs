@@ -0,0 +1,960 @@
+//! Input backend using the raw input API
+//!
+//! [`RawInputSource`] receives the input of all keyboards and mice connected to the system,
+//! independent of which window has focus, e.g. for a compositor running fullscreen on the
+//! desktop. Devices are reported individually and accelerate their pointer motion with a
+//! [`PointerAccel`] of their own, like libinput does, so the pointer feels the same on both
+//! backends. [`RawInputDevice`] implements [`ConfigurableDevice`] to change the acceleration
+//! profile, speed and sensitivity, e.g. through
+//! [`DeviceConfigs::apply`](crate::backend::input::DeviceConfigs::apply) once a device was added.
+//! The unaccelerated motion stays available through [`PointerMotionEvent::delta_unaccel`].
+//!
+//! The events are read on a dedicated thread owning a message-only window, and handed to the
+//! event loop through a [ring buffer](crate::utils::ring), so high polling-rate mice do not
+//! stall on a busy event loop.
+//!
+//! ```no_run
+//! use smithay::backend::{input::InputEvent, win32::raw_input::RawInputSource};
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let source = RawInputSource::new().expect("failed to register for raw input");
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |event, _, _state| match event {
+//!         InputEvent::DeviceAdded { device } => { /* configure the device */ }
+//!         InputEvent::Keyboard { event } => { /* forward the key */ }
+//!         _ => {}
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::c_void,
+    fmt, io, mem,
+    sync::{mpsc, Arc, Mutex},
+    thread::JoinHandle,
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::{trace, warn};
+
+use super::ffi;
+use crate::{
+    backend::input::{
+        self, AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, ConfigOptions,
+        ConfigurableDevice, Device, DeviceCapability, DeviceConfig, InputBackend, InputEvent, KeyState,
+        KeyboardKeyEvent, Keycode, PointerAccel, PointerAxisEvent, PointerButtonEvent,
+        PointerMotionAbsoluteEvent, PointerMotionEvent, UnusedEvent,
+    },
+    compat::time,
+    utils::{
+        ring::{self, RingSender, RingSource},
+        Logical, Point,
+    },
+};
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+// one notch of a scroll wheel
+const WHEEL_DELTA: f64 = 120.0;
+// absolute positions are normalized to this range
+const ABSOLUTE_RANGE: f64 = 65535.0;
+// events buffered before the event loop has to catch up
+const RING_CAPACITY: usize = 4096;
+
+/// Marker used to define the `InputBackend` types for the raw input backend
+#[derive(Debug)]
+pub struct RawInput;
+
+/// Kind of a raw input device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawInputDeviceKind {
+    /// A keyboard
+    Keyboard,
+    /// A mouse, touchpad or other pointing device
+    Mouse,
+}
+
+/// Keyboard or mouse reported by the raw input API
+///
+/// Clones of a device share its configuration.
+#[derive(Debug, Clone)]
+pub struct RawInputDevice {
+    handle: isize,
+    kind: RawInputDeviceKind,
+    name: Arc<str>,
+    // locked by the raw input thread for every motion event
+    accel: Arc<Mutex<PointerAccel>>,
+}
+
+impl RawInputDevice {
+    fn new(handle: isize, kind: RawInputDeviceKind, name: Arc<str>) -> RawInputDevice {
+        RawInputDevice {
+            handle,
+            kind,
+            name,
+            accel: Default::default(),
+        }
+    }
+
+    /// Kind of the device
+    pub fn kind(&self) -> RawInputDeviceKind {
+        self.kind
+    }
+
+    /// Returns the current pointer acceleration settings of the device
+    pub fn pointer_accel(&self) -> PointerAccel {
+        self.accel.lock().unwrap().clone()
+    }
+}
+
+impl ConfigurableDevice for RawInputDevice {
+    fn supported_options(&self) -> ConfigOptions {
+        match self.kind {
+            RawInputDeviceKind::Mouse => self.accel.lock().unwrap().supported_options(),
+            RawInputDeviceKind::Keyboard => ConfigOptions::empty(),
+        }
+    }
+
+    fn apply_config(&mut self, config: &DeviceConfig) -> ConfigOptions {
+        match self.kind {
+            RawInputDeviceKind::Mouse => self.accel.lock().unwrap().apply_config(config),
+            RawInputDeviceKind::Keyboard => config.options(),
+        }
+    }
+}
+
+impl PartialEq for RawInputDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl Eq for RawInputDevice {}
+
+impl std::hash::Hash for RawInputDevice {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
+impl Device for RawInputDevice {
+    fn id(&self) -> String {
+        format!("{:x}", self.handle)
+    }
+
+    /// Returns the device interface path, e.g. `\\?\HID#VID_046D&PID_C52B...`
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(
+            (self.kind, capability),
+            (RawInputDeviceKind::Keyboard, DeviceCapability::Keyboard)
+                | (RawInputDeviceKind::Mouse, DeviceCapability::Pointer)
+        )
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        usb_id_from_path(&self.name)
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+// parses the vendor and product id out of a device interface path
+fn usb_id_from_path(path: &str) -> Option<(u32, u32)> {
+    let path = path.to_ascii_uppercase();
+    let id = |prefix: &str| {
+        let start = path.find(prefix)? + prefix.len();
+        u32::from_str_radix(path.get(start..start + 4)?, 16).ok()
+    };
+    Some((id("VID_")?, id("PID_")?))
+}
+
+/// Key press or release of a raw input keyboard
+#[derive(Debug, Clone)]
+pub struct RawKeyboardKeyEvent {
+    time: u64,
+    device: RawInputDevice,
+    key: u32,
+    count: u32,
+    state: KeyState,
+}
+
+impl input::Event<RawInput> for RawKeyboardKeyEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RawInputDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<RawInput> for RawKeyboardKeyEvent {
+    /// Returns the scancode offset by 8, like the winit backend
+    fn key_code(&self) -> Keycode {
+        self.key + 8
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Relative motion of a raw input mouse
+#[derive(Debug, Clone)]
+pub struct RawPointerMotionEvent {
+    time: u64,
+    device: RawInputDevice,
+    delta: Point<f64, Logical>,
+    delta_unaccel: Point<f64, Logical>,
+}
+
+impl input::Event<RawInput> for RawPointerMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RawInputDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerMotionEvent<RawInput> for RawPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.x
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.y
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta_unaccel.x
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta_unaccel.y
+    }
+}
+
+/// Absolute motion of a raw input pointer, e.g. a pen or a remote desktop session
+#[derive(Debug, Clone)]
+pub struct RawPointerMotionAbsoluteEvent {
+    time: u64,
+    device: RawInputDevice,
+    x: f64,
+    y: f64,
+    virtual_desktop: bool,
+}
+
+impl RawPointerMotionAbsoluteEvent {
+    /// Returns `true` if the position is relative to the whole virtual desktop instead of the
+    /// primary display
+    pub fn virtual_desktop(&self) -> bool {
+        self.virtual_desktop
+    }
+}
+
+impl input::Event<RawInput> for RawPointerMotionAbsoluteEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RawInputDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerMotionAbsoluteEvent<RawInput> for RawPointerMotionAbsoluteEvent {}
+impl AbsolutePositionEvent<RawInput> for RawPointerMotionAbsoluteEvent {
+    /// Returns the position normalized to `0..=65535`
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// Returns the position normalized to `0..=65535`
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x * width as f64 / ABSOLUTE_RANGE
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y * height as f64 / ABSOLUTE_RANGE
+    }
+}
+
+/// Button press or release of a raw input mouse
+#[derive(Debug, Clone)]
+pub struct RawPointerButtonEvent {
+    time: u64,
+    device: RawInputDevice,
+    button: u32,
+    state: ButtonState,
+}
+
+impl input::Event<RawInput> for RawPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RawInputDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerButtonEvent<RawInput> for RawPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Scroll wheel rotation of a raw input mouse
+#[derive(Debug, Clone)]
+pub struct RawPointerAxisEvent {
+    time: u64,
+    device: RawInputDevice,
+    axis: Axis,
+    // in multiples of 120 per notch, positive is down or right
+    v120: f64,
+}
+
+impl input::Event<RawInput> for RawPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RawInputDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerAxisEvent<RawInput> for RawPointerAxisEvent {
+    fn amount(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        Some(if self.axis == axis { self.v120 } else { 0.0 })
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+impl InputBackend for RawInput {
+    type Device = RawInputDevice;
+    type KeyboardKeyEvent = RawKeyboardKeyEvent;
+    type PointerAxisEvent = RawPointerAxisEvent;
+    type PointerButtonEvent = RawPointerButtonEvent;
+    type PointerMotionEvent = RawPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = RawPointerMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+// Turns raw input reports into input events, tracking the pressed keys
+#[derive(Debug, Default)]
+struct Translator {
+    pressed: HashSet<u32>,
+}
+
+impl Translator {
+    fn keyboard(
+        &mut self,
+        time: u64,
+        device: &RawInputDevice,
+        keyboard: &ffi::RAWKEYBOARD,
+    ) -> Option<InputEvent<RawInput>> {
+        // fake keys injected by the keyboard driver, e.g. around the pause key
+        if keyboard.MakeCode == ffi::KEYBOARD_OVERRUN_MAKE_CODE || keyboard.VKey == 0xff {
+            return None;
+        }
+        // the same scancodes winit reports
+        let key = match keyboard.Flags {
+            flags if flags & ffi::RI_KEY_E0 != 0 => 0xe000 | keyboard.MakeCode as u32,
+            flags if flags & ffi::RI_KEY_E1 != 0 => 0xe100 | keyboard.MakeCode as u32,
+            _ => keyboard.MakeCode as u32,
+        };
+        let state = if keyboard.Flags & ffi::RI_KEY_BREAK != 0 {
+            // releases of keys pressed before the backend started are dropped
+            if !self.pressed.remove(&key) {
+                return None;
+            }
+            KeyState::Released
+        } else {
+            // held keys are repeated by the system
+            if !self.pressed.insert(key) {
+                return None;
+            }
+            KeyState::Pressed
+        };
+        Some(InputEvent::Keyboard {
+            event: RawKeyboardKeyEvent {
+                time,
+                device: device.clone(),
+                key,
+                count: self.pressed.len() as u32,
+                state,
+            },
+        })
+    }
+
+    fn mouse(
+        &mut self,
+        time: u64,
+        device: &RawInputDevice,
+        mouse: &ffi::RAWMOUSE,
+        mut emit: impl FnMut(InputEvent<RawInput>),
+    ) {
+        if mouse.usFlags & ffi::MOUSE_MOVE_ABSOLUTE != 0 {
+            emit(InputEvent::PointerMotionAbsolute {
+                event: RawPointerMotionAbsoluteEvent {
+                    time,
+                    device: device.clone(),
+                    x: mouse.lLastX as f64,
+                    y: mouse.lLastY as f64,
+                    virtual_desktop: mouse.usFlags & ffi::MOUSE_VIRTUAL_DESKTOP != 0,
+                },
+            });
+        } else if mouse.lLastX != 0 || mouse.lLastY != 0 {
+            let delta_unaccel = Point::from((mouse.lLastX as f64, mouse.lLastY as f64));
+            emit(InputEvent::PointerMotion {
+                event: RawPointerMotionEvent {
+                    time,
+                    device: device.clone(),
+                    delta: device.accel.lock().unwrap().accelerate(delta_unaccel, time),
+                    delta_unaccel,
+                },
+            });
+        }
+
+        // SAFETY: both variants of the union are plain integers
+        let buttons = unsafe { mouse.Anonymous.Anonymous };
+        for (down, up, button) in [
+            (
+                ffi::RI_MOUSE_LEFT_BUTTON_DOWN,
+                ffi::RI_MOUSE_LEFT_BUTTON_UP,
+                BTN_LEFT,
+            ),
+            (
+                ffi::RI_MOUSE_RIGHT_BUTTON_DOWN,
+                ffi::RI_MOUSE_RIGHT_BUTTON_UP,
+                BTN_RIGHT,
+            ),
+            (
+                ffi::RI_MOUSE_MIDDLE_BUTTON_DOWN,
+                ffi::RI_MOUSE_MIDDLE_BUTTON_UP,
+                BTN_MIDDLE,
+            ),
+            (ffi::RI_MOUSE_BUTTON_4_DOWN, ffi::RI_MOUSE_BUTTON_4_UP, BTN_SIDE),
+            (ffi::RI_MOUSE_BUTTON_5_DOWN, ffi::RI_MOUSE_BUTTON_5_UP, BTN_EXTRA),
+        ] {
+            for (flag, state) in [(down, ButtonState::Pressed), (up, ButtonState::Released)] {
+                if buttons.usButtonFlags & flag != 0 {
+                    emit(InputEvent::PointerButton {
+                        event: RawPointerButtonEvent {
+                            time,
+                            device: device.clone(),
+                            button,
+                            state,
+                        },
+                    });
+                }
+            }
+        }
+
+        // the wheel delta is signed, positive rotates away from the user or to the right
+        let delta = buttons.usButtonData as i16 as f64;
+        for (flag, axis, v120) in [
+            (ffi::RI_MOUSE_WHEEL, Axis::Vertical, -delta),
+            (ffi::RI_MOUSE_HWHEEL, Axis::Horizontal, delta),
+        ] {
+            if buttons.usButtonFlags & flag != 0 {
+                emit(InputEvent::PointerAxis {
+                    event: RawPointerAxisEvent {
+                        time,
+                        device: device.clone(),
+                        axis,
+                        v120: v120 * 120.0 / WHEEL_DELTA,
+                    },
+                });
+            }
+        }
+    }
+}
+
+/// Calloop event source delivering the input of all keyboards and mice through raw input
+///
+/// See the [module-level documentation](self).
+pub struct RawInputSource {
+    source: RingSource<InputEvent<RawInput>>,
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for RawInputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawInputSource")
+            .field("source", &self.source)
+            .field("thread_id", &self.thread_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RawInputSource {
+    /// Start receiving raw input of all keyboards and mice
+    ///
+    /// Connected devices are reported with [`InputEvent::DeviceAdded`] once the source is
+    /// dispatched for the first time.
+    pub fn new() -> io::Result<RawInputSource> {
+        let (sender, source) = ring::source(RING_CAPACITY)?;
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("smithay-raw-input".into())
+            .spawn(move || {
+                let hwnd = match create_window() {
+                    Ok(hwnd) => hwnd,
+                    Err(err) => {
+                        let _ = ready_tx.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(unsafe { ffi::GetCurrentThreadId() }));
+                run(hwnd, sender);
+                unsafe { ffi::DestroyWindow(hwnd) };
+            })?;
+        match ready_rx.recv() {
+            Ok(Ok(thread_id)) => Ok(RawInputSource {
+                source,
+                thread_id,
+                thread: Some(thread),
+            }),
+            Ok(Err(err)) => {
+                let _ = thread.join();
+                Err(err)
+            }
+            Err(_) => Err(io::Error::other("raw input thread panicked")),
+        }
+    }
+}
+
+impl Drop for RawInputSource {
+    fn drop(&mut self) {
+        unsafe { ffi::PostThreadMessageW(self.thread_id, ffi::WM_QUIT, 0, 0) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Creates the message-only window receiving the input and registers it for keyboards and mice
+fn create_window() -> io::Result<ffi::HWND> {
+    // the predefined static control forwards all messages to `DefWindowProcW`
+    let class = ffi::to_wide("STATIC");
+    let hwnd = unsafe {
+        ffi::CreateWindowExW(
+            0,
+            class.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            ffi::HWND_MESSAGE,
+            0,
+            0,
+            std::ptr::null(),
+        )
+    };
+    if hwnd == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let devices = [ffi::HID_USAGE_GENERIC_KEYBOARD, ffi::HID_USAGE_GENERIC_MOUSE].map(|usage| {
+        ffi::RAWINPUTDEVICE {
+            usUsagePage: ffi::HID_USAGE_PAGE_GENERIC,
+            usUsage: usage,
+            // receive input while other windows have focus, and device changes
+            dwFlags: ffi::RIDEV_INPUTSINK | ffi::RIDEV_DEVNOTIFY,
+            hwndTarget: hwnd,
+        }
+    });
+    let registered = unsafe {
+        ffi::RegisterRawInputDevices(
+            devices.as_ptr(),
+            devices.len() as u32,
+            mem::size_of::<ffi::RAWINPUTDEVICE>() as u32,
+        )
+    };
+    if registered == 0 {
+        let err = io::Error::last_os_error();
+        unsafe { ffi::DestroyWindow(hwnd) };
+        return Err(err);
+    }
+    Ok(hwnd)
+}
+
+// Message loop of the raw input thread, until `WM_QUIT` is posted
+fn run(hwnd: ffi::HWND, mut sender: RingSender<InputEvent<RawInput>>) {
+    let mut translator = Translator::default();
+    let mut devices = HashMap::<isize, RawInputDevice>::new();
+    // u64 keeps the buffer aligned for the header
+    let mut buffer = Vec::<u64>::new();
+
+    loop {
+        let mut msg: ffi::MSG = unsafe { mem::zeroed() };
+        match unsafe { ffi::GetMessageW(&mut msg, 0, 0, 0) } {
+            0 => break,
+            -1 => {
+                warn!(err = ?io::Error::last_os_error(), "Failed to receive raw input");
+                break;
+            }
+            _ => {}
+        }
+        if sender.is_abandoned() {
+            break;
+        }
+
+        match msg.message {
+            ffi::WM_INPUT_DEVICE_CHANGE if msg.wParam == ffi::GIDC_ARRIVAL => {
+                // the kind of the device is only known with its first report
+                trace!(handle = msg.lParam, "Raw input device connected");
+            }
+            ffi::WM_INPUT_DEVICE_CHANGE if msg.wParam == ffi::GIDC_REMOVAL => {
+                if let Some(device) = devices.remove(&msg.lParam) {
+                    push(&mut sender, InputEvent::DeviceRemoved { device });
+                }
+            }
+            ffi::WM_INPUT => {
+                let Some((header, data)) = read_raw_input(msg.lParam, &mut buffer) else {
+                    unsafe { ffi::DispatchMessageW(&msg) };
+                    continue;
+                };
+                let kind = match header.dwType {
+                    ffi::RIM_TYPEKEYBOARD => RawInputDeviceKind::Keyboard,
+                    ffi::RIM_TYPEMOUSE => RawInputDeviceKind::Mouse,
+                    _ => {
+                        unsafe { ffi::DispatchMessageW(&msg) };
+                        continue;
+                    }
+                };
+                let device = devices.entry(header.hDevice).or_insert_with(|| {
+                    let device =
+                        RawInputDevice::new(header.hDevice, kind, device_name(header.hDevice).into());
+                    push(
+                        &mut sender,
+                        InputEvent::DeviceAdded {
+                            device: device.clone(),
+                        },
+                    );
+                    device
+                });
+
+                let time = now_usec();
+                match kind {
+                    RawInputDeviceKind::Keyboard => {
+                        // SAFETY: the report of a keyboard holds a RAWKEYBOARD
+                        let keyboard = unsafe { (data as *const ffi::RAWKEYBOARD).read_unaligned() };
+                        if let Some(event) = translator.keyboard(time, device, &keyboard) {
+                            push(&mut sender, event);
+                        }
+                    }
+                    RawInputDeviceKind::Mouse => {
+                        // SAFETY: the report of a mouse holds a RAWMOUSE
+                        let mouse = unsafe { (data as *const ffi::RAWMOUSE).read_unaligned() };
+                        translator.mouse(time, device, &mouse, |event| push(&mut sender, event));
+                    }
+                }
+            }
+            _ => {}
+        }
+        // lets the system clean up the input of WM_INPUT
+        unsafe { ffi::DispatchMessageW(&msg) };
+    }
+}
+
+fn push(sender: &mut RingSender<InputEvent<RawInput>>, event: InputEvent<RawInput>) {
+    if sender.push(event).is_err() {
+        trace!("Raw input ring is full, dropping event");
+    }
+}
+
+// Reads the report of a WM_INPUT message, returning the header and a pointer to the data
+fn read_raw_input(handle: isize, buffer: &mut Vec<u64>) -> Option<(ffi::RAWINPUTHEADER, *const c_void)> {
+    let header_size = mem::size_of::<ffi::RAWINPUTHEADER>() as u32;
+    let mut size = 0;
+    let res = unsafe {
+        ffi::GetRawInputData(
+            handle,
+            ffi::RID_INPUT,
+            std::ptr::null_mut(),
+            &mut size,
+            header_size,
+        )
+    };
+    if res != 0 || size < header_size {
+        return None;
+    }
+    buffer.resize((size as usize).div_ceil(mem::size_of::<u64>()), 0);
+    let read = unsafe {
+        ffi::GetRawInputData(
+            handle,
+            ffi::RID_INPUT,
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut size,
+            header_size,
+        )
+    };
+    if read == u32::MAX || read < header_size {
+        return None;
+    }
+    // SAFETY: the buffer holds at least a header and is aligned for it
+    let header = unsafe { (buffer.as_ptr() as *const ffi::RAWINPUTHEADER).read() };
+    let data = unsafe { (buffer.as_ptr() as *const u8).add(header_size as usize) };
+    Some((header, data as *const c_void))
+}
+
+fn device_name(handle: isize) -> String {
+    let mut len = 0;
+    unsafe { ffi::GetRawInputDeviceInfoW(handle, ffi::RIDI_DEVICENAME, std::ptr::null_mut(), &mut len) };
+    let mut name = vec![0u16; len as usize];
+    let res = unsafe {
+        ffi::GetRawInputDeviceInfoW(
+            handle,
+            ffi::RIDI_DEVICENAME,
+            name.as_mut_ptr() as *mut c_void,
+            &mut len,
+        )
+    };
+    if res == u32::MAX {
+        return String::new();
+    }
+    ffi::from_wide(&name)
+}
+
+fn now_usec() -> u64 {
+    let now = time::counter_to_timespec(time::performance_counter());
+    now.tv_sec as u64 * 1_000_000 + now.tv_nsec as u64 / 1000
+}
+
+impl EventSource for RawInputSource {
+    type Event = InputEvent<RawInput>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.source.process_events(readiness, token, callback)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::input::AccelProfile;
+
+    fn device(kind: RawInputDeviceKind) -> RawInputDevice {
+        RawInputDevice::new(1, kind, r"\\?\HID#VID_046D&PID_C52B&MI_00#7&1234".into())
+    }
+
+    fn key(make_code: u16, flags: u16) -> ffi::RAWKEYBOARD {
+        ffi::RAWKEYBOARD {
+            MakeCode: make_code,
+            Flags: flags,
+            Reserved: 0,
+            VKey: 0,
+            Message: 0,
+            ExtraInformation: 0,
+        }
+    }
+
+    #[test]
+    fn keys_without_repeat() {
+        let keyboard = device(RawInputDeviceKind::Keyboard);
+        let mut translator = Translator::default();
+
+        let event = |event: Option<InputEvent<RawInput>>| match event {
+            Some(InputEvent::Keyboard { event }) => Some((event.key_code(), event.state(), event.count())),
+            None => None,
+            _ => unreachable!(),
+        };
+        // escape
+        assert_eq!(
+            event(translator.keyboard(0, &keyboard, &key(0x01, 0))),
+            Some((9, KeyState::Pressed, 1))
+        );
+        assert_eq!(event(translator.keyboard(1, &keyboard, &key(0x01, 0))), None);
+        // right control
+        assert_eq!(
+            event(translator.keyboard(2, &keyboard, &key(0x1d, ffi::RI_KEY_E0))),
+            Some((0xe01d + 8, KeyState::Pressed, 2))
+        );
+        assert_eq!(
+            event(translator.keyboard(3, &keyboard, &key(0x01, ffi::RI_KEY_BREAK))),
+            Some((9, KeyState::Released, 1))
+        );
+        assert_eq!(
+            event(translator.keyboard(4, &keyboard, &key(0x01, ffi::RI_KEY_BREAK))),
+            None
+        );
+        assert_eq!(event(translator.keyboard(5, &keyboard, &key(0xff, 0))), None);
+    }
+
+    #[test]
+    fn mouse_motion_buttons_and_wheel() {
+        let mouse = device(RawInputDeviceKind::Mouse);
+        let mut translator = Translator::default();
+        let report = ffi::RAWMOUSE {
+            usFlags: 0,
+            Anonymous: ffi::RAWMOUSE_0 {
+                Anonymous: ffi::RAWMOUSE_0_0 {
+                    usButtonFlags: ffi::RI_MOUSE_LEFT_BUTTON_DOWN | ffi::RI_MOUSE_WHEEL,
+                    usButtonData: 120,
+                },
+            },
+            ulRawButtons: 0,
+            lLastX: 3,
+            lLastY: -2,
+            ulExtraInformation: 0,
+        };
+
+        let mut events = Vec::new();
+        translator.mouse(0, &mouse, &report, |event| events.push(event));
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[0],
+            InputEvent::PointerMotion { event } if event.delta_x() == 3.0 && event.delta_y() == -2.0
+        ));
+        assert!(matches!(
+            &events[1],
+            InputEvent::PointerButton { event }
+                if event.button_code() == BTN_LEFT && event.state() == ButtonState::Pressed
+        ));
+        // scrolling away from the user scrolls up
+        assert!(matches!(
+            &events[2],
+            InputEvent::PointerAxis { event }
+                if event.amount_v120(Axis::Vertical) == Some(-120.0)
+                    && event.amount_v120(Axis::Horizontal) == Some(0.0)
+        ));
+    }
+
+    #[test]
+    fn configured_acceleration() {
+        let mut mouse = device(RawInputDeviceKind::Mouse);
+        let unsupported = mouse.apply_config(&DeviceConfig {
+            accel_profile: Some(AccelProfile::Flat),
+            accel_speed: Some(0.5),
+            tap_to_click: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(unsupported, ConfigOptions::TAP_TO_CLICK);
+
+        let mut translator = Translator::default();
+        let report = ffi::RAWMOUSE {
+            usFlags: 0,
+            Anonymous: ffi::RAWMOUSE_0 {
+                Anonymous: ffi::RAWMOUSE_0_0 {
+                    usButtonFlags: 0,
+                    usButtonData: 0,
+                },
+            },
+            ulRawButtons: 0,
+            lLastX: 4,
+            lLastY: 0,
+            ulExtraInformation: 0,
+        };
+        // clones share the configuration
+        let mut events = Vec::new();
+        translator.mouse(0, &mouse.clone(), &report, |event| events.push(event));
+        assert!(matches!(
+            &events[..],
+            [InputEvent::PointerMotion { event }] if event.delta_x() == 6.0 && event.delta_x_unaccel() == 4.0
+        ));
+
+        let mut keyboard = device(RawInputDeviceKind::Keyboard);
+        assert_eq!(keyboard.supported_options(), ConfigOptions::empty());
+        assert_eq!(
+            keyboard.apply_config(&DeviceConfig {
+                accel_speed: Some(0.5),
+                ..Default::default()
+            }),
+            ConfigOptions::ACCEL_SPEED
+        );
+    }
+
+    #[test]
+    fn usb_id() {
+        assert_eq!(device(RawInputDeviceKind::Mouse).usb_id(), Some((0x046d, 0xc52b)));
+        assert_eq!(usb_id_from_path(r"\\?\ACPI#PNP0303#4&1234"), None);
+    }
+}
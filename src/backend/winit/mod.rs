@@ -17,18 +17,31 @@
 //!
 //! The other types in this module are the instances of the associated types of these
 //! two traits for the winit backend.
+//!
+//! ## Windows
+//!
+//! With the `backend_winit_windows` feature the window is rendered to through a
+//! [WGL](crate::backend::wgl) context instead, created by `wgl::init`. As calloop cannot
+//! poll the winit event loop on Windows, [`WinitEventLoop::dispatch_new_events`] has to be called
+//! periodically there.
 
+#[cfg(unix)]
 use std::io::Error as IoError;
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(unix)]
 use calloop::generic::Generic;
-use calloop::{EventSource, Interest, PostAction, Readiness, Token};
+#[cfg(feature = "backend_winit")]
+use calloop::Interest;
+#[cfg(unix)]
+use calloop::{EventSource, PostAction, Readiness, Token};
 use tracing::{debug, info, info_span, instrument, trace, warn};
 #[cfg(feature = "backend_winit_wayland")]
 use wayland_egl as wegl;
 use winit::platform::pump_events::PumpStatus;
 use winit::platform::scancode::PhysicalKeyExtScancode;
+#[cfg(feature = "backend_winit")]
 use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
 use winit::{
     application::ApplicationHandler,
@@ -39,6 +52,14 @@ use winit::{
     window::{Window as WinitWindow, WindowAttributes, WindowId},
 };
 
+use crate::{
+    backend::{
+        capabilities::{Capabilities, QueryCapabilities},
+        input::InputEvent,
+    },
+    utils::{Clock, Monotonic, Physical, Size},
+};
+#[cfg(feature = "backend_winit")]
 use crate::{
     backend::{
         egl::{
@@ -46,21 +67,28 @@ use crate::{
             display::EGLDisplay,
             native, EGLContext, EGLSurface, Error as EGLError,
         },
-        input::InputEvent,
         renderer::{
             gles::{GlesError, GlesRenderer},
             Bind,
         },
     },
-    utils::{Clock, Monotonic, Physical, Rectangle, Size},
+    utils::Rectangle,
 };
 
 mod input;
+#[cfg(all(windows, feature = "backend_winit_windows"))]
+pub mod wgl;
 
 pub use self::input::*;
 
+#[cfg(unix)]
+type EventLoopSource = Generic<EventLoop<()>>;
+#[cfg(windows)]
+type EventLoopSource = EventLoop<()>;
+
 /// Create a new [`WinitGraphicsBackend`], which implements the
 /// [`Renderer`](crate::backend::renderer::Renderer) trait and a corresponding [`WinitEventLoop`].
+#[cfg(feature = "backend_winit")]
 pub fn init<R>() -> Result<(WinitGraphicsBackend<R>, WinitEventLoop), Error>
 where
     R: From<GlesRenderer> + Bind<EGLSurface>,
@@ -77,6 +105,7 @@ where
 /// Create a new [`WinitGraphicsBackend`], which implements the [`Renderer`](crate::backend::renderer::Renderer)
 /// trait, from a given [`WindowAttributes`] struct and a corresponding
 /// [`WinitEventLoop`].
+#[cfg(feature = "backend_winit")]
 pub fn init_from_attributes<R>(
     attributes: WindowAttributes,
 ) -> Result<(WinitGraphicsBackend<R>, WinitEventLoop), Error>
@@ -99,6 +128,7 @@ where
 /// trait, from a given [`WindowAttributes`] struct, as well as given
 /// [`GlAttributes`] for further customization of the rendering pipeline and a
 /// corresponding [`WinitEventLoop`].
+#[cfg(feature = "backend_winit")]
 pub fn init_from_attributes_with_gl_attr<R>(
     attributes: WindowAttributes,
     gl_attributes: GlAttributes,
@@ -197,6 +227,7 @@ where
             egl_surface: surface,
             damage_tracking,
             bind_size: None,
+            resumed: false,
             renderer,
         },
         WinitEventLoop {
@@ -231,14 +262,21 @@ pub enum Error {
     #[error("Context creation is not supported on the current window system")]
     NotSupported,
     /// EGL error.
+    #[cfg(feature = "backend_winit")]
     #[error("EGL error: {0}")]
     Egl(#[from] EGLError),
     /// Renderer initialization failed.
+    #[cfg(feature = "backend_winit")]
     #[error("Renderer creation failed: {0}")]
     RendererCreationError(#[from] GlesError),
+    /// WGL error.
+    #[cfg(all(windows, feature = "backend_winit_windows"))]
+    #[error("WGL error: {0}")]
+    Wgl(#[from] crate::backend::wgl::Error),
 }
 
 /// Window with an active EGL Context created by `winit`.
+#[cfg(feature = "backend_winit")]
 #[derive(Debug)]
 pub struct WinitGraphicsBackend<R> {
     renderer: R,
@@ -248,9 +286,12 @@ pub struct WinitGraphicsBackend<R> {
     window: Arc<WinitWindow>,
     damage_tracking: bool,
     bind_size: Option<Size<i32, Physical>>,
+    // the contents of the back buffers are undefined after a resume
+    resumed: bool,
     span: tracing::Span,
 }
 
+#[cfg(feature = "backend_winit")]
 impl<R> WinitGraphicsBackend<R>
 where
     R: Bind<EGLSurface>,
@@ -314,7 +355,7 @@ where
     /// likely interpret an error just as if "0" was returned.
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     pub fn buffer_age(&self) -> Option<usize> {
-        if self.damage_tracking {
+        if self.damage_tracking && !self.resumed {
             self.egl_surface.buffer_age().map(|x| x as usize)
         } else {
             Some(0)
@@ -351,10 +392,45 @@ where
         // Request frame callback.
         self.window.pre_present_notify();
         self.egl_surface.swap_buffers(damage.as_deref_mut())?;
+        self.resumed = false;
         Ok(())
     }
 }
 
+/// Suspending finishes the work of the renderer. Resuming resizes the surface of the window on the
+/// next [`bind`](WinitGraphicsBackend::bind) and reports a buffer age of `0` until the next
+/// [`submit`](WinitGraphicsBackend::submit), so the next frame is rendered from scratch.
+#[cfg(feature = "backend_winit")]
+impl<R> crate::backend::power::PowerHooks for WinitGraphicsBackend<R>
+where
+    R: Bind<EGLSurface> + crate::backend::power::PowerHooks,
+{
+    type Error = R::Error;
+
+    fn suspend(&mut self) -> Result<(), Self::Error> {
+        self.renderer.suspend()
+    }
+
+    fn resume(&mut self) -> Result<(), Self::Error> {
+        self.bind_size = None;
+        self.resumed = true;
+        self.renderer.resume()
+    }
+}
+
+/// Includes the capabilities of the renderer. The cursor can be presented by the host through
+/// [`WinitWindow::set_cursor`].
+#[cfg(feature = "backend_winit")]
+impl<R: QueryCapabilities> QueryCapabilities for WinitGraphicsBackend<R> {
+    fn query_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.renderer.query_capabilities() | Capabilities::HARDWARE_CURSOR;
+        if self.damage_tracking {
+            capabilities |= Capabilities::PARTIAL_PRESENT;
+        }
+        capabilities
+    }
+}
+
 #[derive(Debug)]
 struct WinitEventLoopInner {
     window: Arc<WinitWindow>,
@@ -372,9 +448,11 @@ struct WinitEventLoopInner {
 #[derive(Debug)]
 pub struct WinitEventLoop {
     inner: WinitEventLoopInner,
+    #[cfg(unix)]
     fake_token: Option<Token>,
+    #[cfg(unix)]
     pending_events: Vec<WinitEvent>,
-    event_loop: Generic<EventLoop<()>>,
+    event_loop: EventLoopSource,
     span: tracing::Span,
 }
 
@@ -397,7 +475,10 @@ impl WinitEventLoop {
         F: FnMut(WinitEvent),
     {
         // SAFETY: we don't drop event loop ourselves.
+        #[cfg(unix)]
         let event_loop = unsafe { self.event_loop.get_mut() };
+        #[cfg(windows)]
+        let event_loop = &mut self.event_loop;
 
         tracing::info!("Smithay: Starting pump_app_events");
         event_loop.pump_app_events(
@@ -622,6 +703,7 @@ impl<F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'_, F> {
     }
 }
 
+#[cfg(unix)]
 impl EventSource for WinitEventLoop {
     type Event = WinitEvent;
     type Metadata = ();
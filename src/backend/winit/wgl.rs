@@ -0,0 +1,176 @@
+//! Windows implementation of the winit graphics backend
+//!
+//! [`init`] creates a winit window together with a [`WGLContext`] rendering to it, so a
+//! compositor can run nested inside a window on a Windows desktop:
+//!
+//! ```no_run
+//! use smithay::backend::winit::{self, WinitEvent};
+//!
+//! let (mut backend, mut event_loop) = winit::wgl::init().expect("Failed to create the window");
+//!
+//! loop {
+//!     event_loop.dispatch_new_events(|event| match event {
+//!         WinitEvent::Redraw => { /* render */ }
+//!         _ => {}
+//!     });
+//!
+//!     backend.bind().expect("Failed to make the context current");
+//!     // draw with functions loaded through `smithay::backend::wgl::get_proc_address`
+//!     backend.submit().expect("Failed to swap buffers");
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use tracing::{debug, info, info_span, instrument};
+use winit::{
+    dpi::LogicalSize,
+    event_loop::EventLoop,
+    window::{Window as WinitWindow, WindowAttributes},
+};
+
+use super::{Error, WinitEventLoop, WinitEventLoopInner};
+use crate::{
+    backend::{
+        capabilities::{Capabilities, QueryCapabilities},
+        wgl::{WGLContext, WGLDisplay},
+        SwapBuffersError,
+    },
+    utils::{Clock, Monotonic, Physical, Size},
+};
+
+/// Create a new [`WinitWglGraphicsBackend`] with a default window and a corresponding [`WinitEventLoop`]
+pub fn init() -> Result<(WinitWglGraphicsBackend, WinitEventLoop), Error> {
+    init_from_attributes(
+        WinitWindow::default_attributes()
+            .with_inner_size(LogicalSize::new(1280.0, 800.0))
+            .with_title("Smithay")
+            .with_visible(true),
+    )
+}
+
+/// Create a new [`WinitWglGraphicsBackend`] from a given [`WindowAttributes`] struct and a
+/// corresponding [`WinitEventLoop`]
+pub fn init_from_attributes(
+    attributes: WindowAttributes,
+) -> Result<(WinitWglGraphicsBackend, WinitEventLoop), Error> {
+    let span = info_span!("backend_winit", window = tracing::field::Empty);
+    let _guard = span.enter();
+    info!("Initializing a winit backend using WGL");
+
+    let event_loop = EventLoop::builder().build().map_err(Error::EventLoopCreation)?;
+
+    #[allow(deprecated)]
+    let window = Arc::new(
+        event_loop
+            .create_window(attributes)
+            .map_err(Error::WindowCreation)?,
+    );
+
+    span.record("window", Into::<u64>::into(window.id()));
+    debug!("Window created");
+
+    // SAFETY: the window is kept alive by the backend as long as the display
+    let display = unsafe { WGLDisplay::from_window_handle(&*window)? };
+    let context = WGLContext::new(&display)?;
+
+    drop(_guard);
+
+    Ok((
+        WinitWglGraphicsBackend {
+            window: window.clone(),
+            context,
+            span: span.clone(),
+        },
+        WinitEventLoop {
+            inner: WinitEventLoopInner {
+                scale_factor: window.scale_factor(),
+                clock: Clock::<Monotonic>::new(),
+                key_counter: 0,
+                window,
+                is_x11: false,
+            },
+            event_loop,
+            span,
+        },
+    ))
+}
+
+/// Window with an active WGL context created by `winit`
+#[derive(Debug)]
+pub struct WinitWglGraphicsBackend {
+    // Dropped before the window, the context releases the device context of the window.
+    context: WGLContext,
+    window: Arc<WinitWindow>,
+    span: tracing::Span,
+}
+
+impl WinitWglGraphicsBackend {
+    /// Window size of the underlying window
+    pub fn window_size(&self) -> Size<i32, Physical> {
+        let (w, h): (i32, i32) = self.window.inner_size().into();
+        (w, h).into()
+    }
+
+    /// Scale factor of the underlying window
+    pub fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    /// Reference to the underlying window
+    pub fn window(&self) -> &WinitWindow {
+        &self.window
+    }
+
+    /// Reference to the WGL context rendering to the window
+    pub fn context(&self) -> &WGLContext {
+        &self.context
+    }
+
+    /// Make the context current, so the window is rendered to
+    ///
+    /// The default framebuffer of the window always matches its current size.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn bind(&mut self) -> Result<(), SwapBuffersError> {
+        self.context
+            .make_current()
+            .map_err(|err| SwapBuffersError::ContextLost(Box::new(err)))
+    }
+
+    /// Submits the back buffer to the window by swapping, requires the context to be
+    /// previously bound (see [`WinitWglGraphicsBackend::bind`])
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn submit(&mut self) -> Result<(), SwapBuffersError> {
+        self.window.pre_present_notify();
+        if self.context.swap_buffers() {
+            Ok(())
+        } else {
+            Err(SwapBuffersError::TemporaryFailure(Box::new(
+                std::io::Error::last_os_error(),
+            )))
+        }
+    }
+}
+
+/// Suspending finishes the commands of the context. The default framebuffer of the window is
+/// managed by the driver, so resuming does nothing.
+impl crate::backend::power::PowerHooks for WinitWglGraphicsBackend {
+    type Error = SwapBuffersError;
+
+    fn suspend(&mut self) -> Result<(), SwapBuffersError> {
+        self.context
+            .finish()
+            .map_err(|err| SwapBuffersError::ContextLost(Box::new(err)))
+    }
+
+    fn resume(&mut self) -> Result<(), SwapBuffersError> {
+        Ok(())
+    }
+}
+
+/// The cursor can be presented by the host through [`WinitWindow::set_cursor`].
+impl QueryCapabilities for WinitWglGraphicsBackend {
+    fn query_capabilities(&self) -> Capabilities {
+        Capabilities::HARDWARE_CURSOR
+    }
+}
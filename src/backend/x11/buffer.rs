@@ -161,7 +161,12 @@ where
         let msc = window.0.last_msc.load(Ordering::SeqCst) + 1;
 
         // options parameter does not take the enum but a u32.
-        const OPTIONS: present::Option = present::Option::NONE;
+        let options = if window.0.async_present.load(Ordering::SeqCst) {
+            // Present immediately, even if this tears, when the target msc was already missed.
+            present::Option::ASYNC
+        } else {
+            present::Option::NONE
+        };
 
         connection.present_pixmap(
             window.id(),
@@ -171,10 +176,10 @@ where
             x11rb::NONE, // Update the entire window
             0,           // No offsets
             0,
-            x11rb::NONE,    // Let the X server pick the most suitable crtc
-            x11rb::NONE,    // Do not wait to present
-            x11rb::NONE,    // We will wait for the X server to tell us when it is done with the pixmap.
-            OPTIONS.into(), // No special presentation options.
+            x11rb::NONE, // Let the X server pick the most suitable crtc
+            x11rb::NONE, // Do not wait to present
+            x11rb::NONE, // We will wait for the X server to tell us when it is done with the pixmap.
+            options.into(),
             msc,
             0,
             0,
@@ -84,7 +84,7 @@ use crate::{
         egl::{native::X11DefaultDisplay, EGLDevice, EGLDisplay, Error as EGLError},
         input::{Axis, ButtonState, InputEvent, KeyState, Keycode},
     },
-    utils::{x11rb::X11Source, Logical, Size},
+    utils::{x11rb::X11Source, Logical, Monotonic, Size, Time},
 };
 use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
 use drm::node::path_to_type;
@@ -104,6 +104,7 @@ use std::{
         atomic::{AtomicU32, Ordering},
         mpsc, Arc, Mutex, Weak,
     },
+    time::Duration,
 };
 use tracing::{debug_span, error, info, instrument, warn};
 use x11rb::{
@@ -112,7 +113,7 @@ use x11rb::{
     protocol::{
         self as x11,
         dri3::ConnectionExt as _,
-        xinput,
+        present, xinput,
         xproto::{ColormapAlloc, ConnectionExt, CreateWindowAux, VisualClass, WindowClass, WindowWrapper},
         ErrorKind,
     },
@@ -169,6 +170,8 @@ pub enum X11Event {
     PresentCompleted {
         /// XID of the window
         window_id: u32,
+        /// When and how the buffer was displayed
+        timing: PresentTiming,
     },
 
     /// The window has received a request to be closed.
@@ -490,6 +493,25 @@ impl<'a> WindowBuilder<'a> {
     }
 }
 
+/// Feedback about a buffer displayed in a window, as reported by the present extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentTiming {
+    /// Time the buffer was displayed at
+    ///
+    /// The X server reports this with microsecond precision.
+    pub time: Time<Monotonic>,
+    /// Value of the vertical blank counter of the crtc the window was displayed on
+    pub sequence: u64,
+    /// Refresh interval of the crtc, estimated from the preceding presentations
+    ///
+    /// This is `None` until two presentations on successive vertical blanks have completed.
+    pub refresh: Option<Duration>,
+    /// Whether the X server flipped to the buffer instead of copying its contents
+    pub zero_copy: bool,
+    /// Whether the buffer was skipped in favor of a later one and has never been displayed
+    pub skipped: bool,
+}
+
 /// An X11 window.
 ///
 /// Dropping an instance of the window will destroy it.
@@ -540,6 +562,40 @@ impl Window {
     pub fn format(&self) -> DrmFourcc {
         self.0.format
     }
+
+    /// Sets whether presentation waits for the vertical blank.
+    ///
+    /// Disabling vsync lets buffers be displayed immediately if they missed their vertical blank,
+    /// which may tear. Vsync is enabled by default.
+    pub fn set_vsync(&self, vsync: bool) {
+        self.0.async_present.store(!vsync, Ordering::SeqCst);
+    }
+
+    /// Returns whether presentation waits for the vertical blank.
+    pub fn vsync(&self) -> bool {
+        !self.0.async_present.load(Ordering::SeqCst)
+    }
+
+    /// Returns the refresh interval of the crtc showing the window, estimated from completed presentations.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        match self.0.refresh.load(Ordering::SeqCst) {
+            0 => None,
+            refresh => Some(Duration::from_micros(refresh)),
+        }
+    }
+
+    /// Returns the estimated time the next vertical blank of the window will happen at.
+    ///
+    /// This allows to schedule rendering shortly before the next buffer can be displayed,
+    /// like the frame clocks of the other backends do.
+    pub fn next_presentation_time(&self) -> Option<Time<Monotonic>> {
+        let last = self.0.last_ust.load(Ordering::SeqCst);
+        let refresh = self.refresh_interval()?;
+        if last == 0 {
+            return None;
+        }
+        Some(Time::from(Duration::from_micros(last) + refresh))
+    }
 }
 
 impl PartialEq for Window {
@@ -961,11 +1017,33 @@ impl X11Inner {
                 if let Some(window) =
                     X11Inner::window_ref_from_id(inner, &complete_notify.window).and_then(|w| w.upgrade())
                 {
-                    window.last_msc.store(complete_notify.msc, Ordering::SeqCst);
+                    if complete_notify.kind != present::CompleteKind::PIXMAP {
+                        return;
+                    }
+
+                    let last_msc = window.last_msc.swap(complete_notify.msc, Ordering::SeqCst);
+                    let last_ust = window.last_ust.swap(complete_notify.ust, Ordering::SeqCst);
+                    if last_ust != 0 && complete_notify.msc > last_msc && complete_notify.ust > last_ust {
+                        let refresh = (complete_notify.ust - last_ust) / (complete_notify.msc - last_msc);
+                        window.refresh.store(refresh, Ordering::SeqCst);
+                    }
+                    let refresh = match window.refresh.load(Ordering::SeqCst) {
+                        0 => None,
+                        refresh => Some(Duration::from_micros(refresh)),
+                    };
+
+                    let timing = PresentTiming {
+                        time: Time::from(Duration::from_micros(complete_notify.ust)),
+                        sequence: complete_notify.msc,
+                        refresh,
+                        zero_copy: complete_notify.mode == present::CompleteMode::FLIP,
+                        skipped: complete_notify.mode == present::CompleteMode::SKIP,
+                    };
 
                     (callback)(
                         X11Event::PresentCompleted {
                             window_id: complete_notify.window,
+                            timing,
                         },
                         &mut (),
                     );
@@ -144,3 +144,18 @@ impl X11Surface {
         self.height = size.h;
     }
 }
+
+/// Suspending does nothing, the rendering work is owned by the renderer. Resuming
+/// [resets the buffers](X11Surface::reset_buffers), so the next frame is rendered into new ones.
+impl crate::backend::power::PowerHooks for X11Surface {
+    type Error = std::convert::Infallible;
+
+    fn suspend(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Self::Error> {
+        self.reset_buffers();
+        Ok(())
+    }
+}
@@ -14,7 +14,7 @@ use crate::utils::{Logical, Size};
 use super::{extension::Extensions, Atoms, Window, X11Error};
 use drm_fourcc::DrmFourcc;
 use std::sync::{
-    atomic::{AtomicU32, AtomicU64},
+    atomic::{AtomicBool, AtomicU32, AtomicU64},
     mpsc::Sender,
     Arc, Mutex, Weak,
 };
@@ -70,6 +70,12 @@ pub(crate) struct WindowInner {
     pub resize: Mutex<Option<Sender<Size<u16, Logical>>>>,
     pub next_serial: AtomicU32,
     pub last_msc: Arc<AtomicU64>,
+    /// Time of the last completed presentation in microseconds, `0` if unknown.
+    pub last_ust: AtomicU64,
+    /// Estimated refresh interval in microseconds, `0` if unknown.
+    pub refresh: AtomicU64,
+    /// Whether presentation may tear instead of waiting for the vertical blank.
+    pub async_present: AtomicBool,
     pub format: DrmFourcc,
     pub depth: Depth,
     pub extensions: Extensions,
@@ -170,6 +176,9 @@ impl WindowInner {
             size: Mutex::new(size),
             next_serial: AtomicU32::new(0),
             last_msc: Arc::new(AtomicU64::new(0)),
+            last_ust: AtomicU64::new(0),
+            refresh: AtomicU64::new(0),
+            async_present: AtomicBool::new(false),
             format,
             depth,
             extensions,
@@ -0,0 +1,483 @@
+//! Portable timer and readiness event sources for calloop
+//!
+//! On Linux and FreeBSD [`TimerSource`] is backed by a `timerfd` and [`HandleSource`] polls
+//! a file descriptor for readability. On Windows, where calloop can only poll sockets,
+//! both are implemented with waitable objects: the timer is a high-resolution waitable timer
+//! (`CreateWaitableTimerExW`) and readiness of arbitrary handles is observed with
+//! `RegisterWaitForSingleObject`, whose thread pool callback wakes the event loop through a
+//! [`calloop::ping`].
+//!
+//! This allows code that would use `timerfd`/`eventfd` sources on Linux to run unchanged on Windows:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! use smithay::compat::event::TimerSource;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let mut timer = TimerSource::new().unwrap();
+//! timer.set(Duration::from_millis(16), Some(Duration::from_millis(16))).unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(timer, |_, _, _state| {
+//!         // called roughly every 16ms
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", windows))]
+use std::time::Duration;
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+
+#[cfg(unix)]
+use calloop::generic::Generic;
+#[cfg(unix)]
+use calloop::{Interest, Mode};
+
+#[cfg(windows)]
+use calloop::ping::{make_ping, Ping, PingSource};
+
+/// A monotonic timer usable as a calloop event source
+///
+/// The timer is created disarmed. Each dispatch of the source signals that the timer expired
+/// at least once since the last dispatch.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", windows))]
+#[derive(Debug)]
+pub struct TimerSource {
+    #[cfg(unix)]
+    source: Generic<super::OwnedFd>,
+    #[cfg(windows)]
+    timer: std::os::windows::io::OwnedHandle,
+    #[cfg(windows)]
+    wait: Option<ffi::RegisteredWait>,
+    #[cfg(windows)]
+    source: PingSource,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+impl TimerSource {
+    /// Create a new disarmed timer
+    pub fn new() -> io::Result<TimerSource> {
+        use rustix::time::{timerfd_create, TimerfdClockId, TimerfdFlags};
+
+        let fd = timerfd_create(
+            TimerfdClockId::Monotonic,
+            TimerfdFlags::NONBLOCK | TimerfdFlags::CLOEXEC,
+        )?;
+        Ok(TimerSource {
+            source: Generic::new(fd, Interest::READ, Mode::Level),
+        })
+    }
+
+    /// Arm the timer to expire after `initial` and then every `interval`, if set
+    pub fn set(&mut self, initial: Duration, interval: Option<Duration>) -> io::Result<()> {
+        use rustix::time::{timerfd_settime, Itimerspec, TimerfdTimerFlags};
+
+        // a zero value would disarm the timer
+        let initial = initial.max(Duration::from_nanos(1));
+        let spec = Itimerspec {
+            it_interval: timespec(interval.unwrap_or(Duration::ZERO)),
+            it_value: timespec(initial),
+        };
+        timerfd_settime(self.source.get_ref(), TimerfdTimerFlags::empty(), &spec)?;
+        Ok(())
+    }
+
+    /// Disarm the timer
+    pub fn disarm(&mut self) -> io::Result<()> {
+        use rustix::time::{timerfd_settime, Itimerspec, TimerfdTimerFlags};
+
+        let spec = Itimerspec {
+            it_interval: timespec(Duration::ZERO),
+            it_value: timespec(Duration::ZERO),
+        };
+        timerfd_settime(self.source.get_ref(), TimerfdTimerFlags::empty(), &spec)?;
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn timespec(duration: Duration) -> rustix::time::Timespec {
+    rustix::time::Timespec {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+impl EventSource for TimerSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.source.process_events(readiness, token, |_, fd| {
+            // reading the expiration count resets the readiness
+            let mut expirations = [0u8; 8];
+            match rustix::io::read(&**fd, &mut expirations[..]) {
+                Ok(_) => callback((), &mut ()),
+                Err(rustix::io::Errno::AGAIN) => {}
+                Err(err) => return Err(err.into()),
+            }
+            Ok(PostAction::Continue)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(windows)]
+impl TimerSource {
+    /// Create a new disarmed timer
+    pub fn new() -> io::Result<TimerSource> {
+        use std::os::windows::io::FromRawHandle;
+
+        // high resolution timers are only available since Windows 10 1803
+        let mut raw = unsafe {
+            ffi::CreateWaitableTimerExW(
+                std::ptr::null(),
+                std::ptr::null(),
+                ffi::CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+                ffi::TIMER_ALL_ACCESS,
+            )
+        };
+        if raw == 0 {
+            raw = unsafe {
+                ffi::CreateWaitableTimerExW(std::ptr::null(), std::ptr::null(), 0, ffi::TIMER_ALL_ACCESS)
+            };
+        }
+        if raw == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle was just created and is owned by us
+        let timer = unsafe { std::os::windows::io::OwnedHandle::from_raw_handle(raw as _) };
+
+        let (ping, source) = make_ping()?;
+        // the timer is an auto-reset object, so the wait can stay registered
+        let wait = ffi::RegisteredWait::new(raw, ping, false)?;
+
+        Ok(TimerSource {
+            timer,
+            wait: Some(wait),
+            source,
+        })
+    }
+
+    /// Arm the timer to expire after `initial` and then every `interval`, if set
+    pub fn set(&mut self, initial: Duration, interval: Option<Duration>) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+
+        // negative values are relative due times in 100ns intervals
+        let due = -(((initial.as_nanos() / 100).max(1)).min(i64::MAX as u128) as i64);
+        // the period only supports millisecond precision
+        let period = interval
+            .map(|interval| interval.as_millis().clamp(1, i32::MAX as u128) as i32)
+            .unwrap_or(0);
+        let ret = unsafe {
+            ffi::SetWaitableTimer(
+                self.timer.as_raw_handle() as isize,
+                &due,
+                period,
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        if ret == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Disarm the timer
+    pub fn disarm(&mut self) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+
+        if unsafe { ffi::CancelWaitableTimer(self.timer.as_raw_handle() as isize) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for TimerSource {
+    fn drop(&mut self) {
+        // make sure no callback references the timer anymore before closing it
+        self.wait.take();
+    }
+}
+
+#[cfg(windows)]
+impl EventSource for TimerSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.source
+            .process_events(readiness, token, |_, _| callback((), &mut ()))
+            .map_err(io::Error::other)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+/// Readiness of a file descriptor or handle as a calloop event source
+///
+/// On unix the source is dispatched while the file descriptor is readable.
+/// On Windows it is dispatched while the handle is signaled, e.g. an event is set or a process exited.
+/// The source is dispatched again, if the object is still ready after the callback.
+/// Use [`HandleSource::get_ref`] to access the handle.
+#[derive(Debug)]
+pub struct HandleSource<H: AsHandleSource> {
+    #[cfg(unix)]
+    source: Generic<H>,
+    #[cfg(windows)]
+    handle: H,
+    #[cfg(windows)]
+    wait: Option<ffi::RegisteredWait>,
+    #[cfg(windows)]
+    ping: Ping,
+    #[cfg(windows)]
+    source: PingSource,
+}
+
+/// Objects which can be observed by a [`HandleSource`]
+#[cfg(unix)]
+pub trait AsHandleSource: std::os::unix::io::AsFd + std::fmt::Debug {}
+#[cfg(unix)]
+impl<T: std::os::unix::io::AsFd + std::fmt::Debug> AsHandleSource for T {}
+
+/// Objects which can be observed by a [`HandleSource`]
+#[cfg(windows)]
+pub trait AsHandleSource: std::os::windows::io::AsRawHandle + std::fmt::Debug {}
+#[cfg(windows)]
+impl<T: std::os::windows::io::AsRawHandle + std::fmt::Debug> AsHandleSource for T {}
+
+impl<H: AsHandleSource> HandleSource<H> {
+    /// Create a new source observing the given handle
+    pub fn new(handle: H) -> io::Result<HandleSource<H>> {
+        #[cfg(unix)]
+        {
+            Ok(HandleSource {
+                source: Generic::new(handle, Interest::READ, Mode::Level),
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            let (ping, source) = make_ping()?;
+            let raw = std::os::windows::io::AsRawHandle::as_raw_handle(&handle) as isize;
+            let wait = ffi::RegisteredWait::new(raw, ping.clone(), true)?;
+            Ok(HandleSource {
+                handle,
+                wait: Some(wait),
+                ping,
+                source,
+            })
+        }
+    }
+
+    /// Access the observed handle
+    pub fn get_ref(&self) -> &H {
+        #[cfg(unix)]
+        {
+            self.source.get_ref()
+        }
+
+        #[cfg(windows)]
+        {
+            &self.handle
+        }
+    }
+}
+
+#[cfg(windows)]
+impl<H: AsHandleSource> Drop for HandleSource<H> {
+    fn drop(&mut self) {
+        // make sure no callback references the handle anymore before it is closed
+        self.wait.take();
+    }
+}
+
+impl<H: AsHandleSource> EventSource for HandleSource<H> {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        #[cfg(unix)]
+        {
+            self.source.process_events(readiness, token, |_, _| {
+                callback((), &mut ());
+                Ok(PostAction::Continue)
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            let mut signaled = false;
+            self.source
+                .process_events(readiness, token, |_, _| signaled = true)
+                .map_err(io::Error::other)?;
+            if signaled {
+                callback((), &mut ());
+                // the wait only fires once, to not spin on manual-reset objects
+                self.wait = None;
+                self.wait = Some(ffi::RegisteredWait::new(
+                    std::os::windows::io::AsRawHandle::as_raw_handle(&self.handle) as isize,
+                    self.ping.clone(),
+                    true,
+                )?);
+            }
+            Ok(PostAction::Continue)
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(windows)]
+mod ffi {
+    use std::{ffi::c_void, io};
+
+    use calloop::ping::Ping;
+
+    pub const CREATE_WAITABLE_TIMER_HIGH_RESOLUTION: u32 = 0x0000_0002;
+    pub const TIMER_ALL_ACCESS: u32 = 0x001F_0003;
+    const INFINITE: u32 = 0xFFFF_FFFF;
+    const INVALID_HANDLE_VALUE: isize = -1;
+    const WT_EXECUTEONLYONCE: u32 = 0x0000_0008;
+
+    type WaitOrTimerCallback = unsafe extern "system" fn(context: *mut c_void, timer_or_wait_fired: u8);
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn CreateWaitableTimerExW(
+            attributes: *const c_void,
+            name: *const u16,
+            flags: u32,
+            desired_access: u32,
+        ) -> isize;
+        pub fn SetWaitableTimer(
+            timer: isize,
+            due_time: *const i64,
+            period: i32,
+            completion_routine: *const c_void,
+            arg: *const c_void,
+            resume: i32,
+        ) -> i32;
+        pub fn CancelWaitableTimer(timer: isize) -> i32;
+        fn RegisterWaitForSingleObject(
+            new_wait_object: *mut isize,
+            object: isize,
+            callback: WaitOrTimerCallback,
+            context: *mut c_void,
+            milliseconds: u32,
+            flags: u32,
+        ) -> i32;
+        fn UnregisterWaitEx(wait_handle: isize, completion_event: isize) -> i32;
+    }
+
+    unsafe extern "system" fn wake(context: *mut c_void, _timer_or_wait_fired: u8) {
+        // SAFETY: the context is a `Box<Ping>` kept alive until the wait is unregistered
+        let ping = unsafe { &*(context as *const Ping) };
+        ping.ping();
+    }
+
+    /// A wait registered with the system thread pool, pinging the event loop when the object is signaled
+    #[derive(Debug)]
+    pub struct RegisteredWait {
+        wait: isize,
+        ping: *mut Ping,
+    }
+
+    impl RegisteredWait {
+        pub fn new(object: isize, ping: Ping, once: bool) -> io::Result<RegisteredWait> {
+            let ping = Box::into_raw(Box::new(ping));
+            let mut wait = 0;
+            let flags = if once { WT_EXECUTEONLYONCE } else { 0 };
+            let ret = unsafe {
+                RegisterWaitForSingleObject(&mut wait, object, wake, ping as *mut c_void, INFINITE, flags)
+            };
+            if ret == 0 {
+                let err = io::Error::last_os_error();
+                // SAFETY: the wait was not registered, so nothing else references the ping
+                drop(unsafe { Box::from_raw(ping) });
+                return Err(err);
+            }
+            Ok(RegisteredWait { wait, ping })
+        }
+    }
+
+    impl Drop for RegisteredWait {
+        fn drop(&mut self) {
+            // blocks until running callbacks completed, afterwards the ping can be freed
+            unsafe { UnregisterWaitEx(self.wait, INVALID_HANDLE_VALUE) };
+            // SAFETY: no callback can run anymore
+            drop(unsafe { Box::from_raw(self.ping) });
+        }
+    }
+
+    // SAFETY: the ping is only accessed by the thread pool callback and on drop
+    unsafe impl Send for RegisteredWait {}
+}
@@ -70,6 +70,10 @@ pub mod fd {
 
 pub use fd::*;
 
+pub mod event;
+pub mod notifier;
+pub mod signals;
+
 /// Cross-platform time utilities
 #[cfg(unix)]
 pub mod time {
@@ -78,12 +82,15 @@ pub mod time {
 
 #[cfg(windows)]
 pub mod time {
-    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use std::sync::OnceLock;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     /// Clock ID for Windows (simplified)
     #[derive(Debug, Clone, Copy)]
     pub enum ClockId {
+        /// Performance counter, counting from system boot
         Monotonic,
+        /// Wall clock time since the unix epoch
         Realtime,
     }
 
@@ -100,19 +107,58 @@ pub mod time {
         }
     }
 
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn QueryPerformanceCounter(count: *mut i64) -> i32;
+        fn QueryPerformanceFrequency(frequency: *mut i64) -> i32;
+    }
+
+    /// Frequency of the performance counter in ticks per second
+    pub fn performance_frequency() -> i64 {
+        static FREQUENCY: OnceLock<i64> = OnceLock::new();
+        *FREQUENCY.get_or_init(|| {
+            let mut frequency = 0;
+            // SAFETY: the pointer is valid for the duration of the call.
+            // The call cannot fail on Windows XP and later.
+            unsafe { QueryPerformanceFrequency(&mut frequency) };
+            frequency.max(1)
+        })
+    }
+
+    /// Current value of the performance counter
+    ///
+    /// This is the same time base used by e.g. `DXGI_FRAME_STATISTICS` and raw input.
+    pub fn performance_counter() -> i64 {
+        let mut count = 0;
+        // SAFETY: the pointer is valid for the duration of the call.
+        // The call cannot fail on Windows XP and later.
+        unsafe { QueryPerformanceCounter(&mut count) };
+        count
+    }
+
+    /// Convert a performance counter value into a timespec of [`ClockId::Monotonic`]
+    pub fn counter_to_timespec(count: i64) -> Timespec {
+        let frequency = performance_frequency();
+        let count = count.max(0);
+        Timespec {
+            tv_sec: count / frequency,
+            // the remainder is smaller than the frequency, but avoid overflow for exotic frequencies
+            tv_nsec: ((count % frequency) as i128 * 1_000_000_000 / frequency as i128) as i64,
+        }
+    }
+
+    /// Convert a performance counter value into the 32-bit millisecond timestamps used by wayland events
+    ///
+    /// Like the timestamps of window messages (`GetMessageTime`) the value wraps around after ~49.7 days.
+    pub fn counter_to_millis(count: i64) -> u32 {
+        let frequency = performance_frequency() as i128;
+        (count.max(0) as i128 * 1000 / frequency) as u32
+    }
+
     /// Get current time for the given clock
     pub fn clock_gettime(clock: ClockId) -> Timespec {
         match clock {
-            ClockId::Monotonic => {
-                // Use Instant for monotonic time
-                static START: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
-                let start = START.get_or_init(Instant::now);
-                let elapsed = start.elapsed();
-                Timespec {
-                    tv_sec: elapsed.as_secs() as i64,
-                    tv_nsec: elapsed.subsec_nanos() as i64,
-                }
-            }
+            ClockId::Monotonic => counter_to_timespec(performance_counter()),
             ClockId::Realtime => {
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -126,28 +172,74 @@ pub mod time {
     }
 }
 
-/// Cross-platform memory mapping (stub for Windows)
+/// Cross-platform memory mapping
+///
+/// On Windows the mapped handle is expected to be a section object (file mapping), which is
+/// what clients share instead of file descriptors, e.g. a [`SealedFile`](crate::utils::SealedFile).
 #[cfg(windows)]
 pub mod mman {
-    use std::ptr;
+    use std::{ffi::c_void, io};
 
     pub const PROT_READ: i32 = 1;
     pub const PROT_WRITE: i32 = 2;
     pub const MAP_SHARED: i32 = 1;
 
-    /// Memory-mapped region (Windows stub)
+    const FILE_MAP_WRITE: u32 = 0x0002;
+    const FILE_MAP_READ: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn MapViewOfFile(
+            section: isize,
+            access: u32,
+            offset_high: u32,
+            offset_low: u32,
+            len: usize,
+        ) -> *mut c_void;
+        fn UnmapViewOfFile(address: *const c_void) -> i32;
+    }
+
+    /// Memory-mapped view of a section object, unmapped on drop
+    #[derive(Debug)]
     pub struct MmapRegion {
         ptr: *mut u8,
         len: usize,
     }
 
+    // SAFETY: the region is plain shared memory, synchronization of the contents is up to the user
+    unsafe impl Send for MmapRegion {}
+    unsafe impl Sync for MmapRegion {}
+
     impl MmapRegion {
-        pub fn new(_fd: super::RawFd, _len: usize, _prot: i32, _flags: i32) -> std::io::Result<Self> {
-            // TODO: Implement Windows memory mapping with CreateFileMapping + MapViewOfFile
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "Memory mapping not yet implemented on Windows",
-            ))
+        /// Map `len` bytes of the given section object
+        ///
+        /// Only `MAP_SHARED` mappings are supported. Mapping a section shared read-only with
+        /// `PROT_WRITE` fails with [`io::ErrorKind::PermissionDenied`].
+        pub fn new(fd: super::RawFd, len: usize, prot: i32, flags: i32) -> io::Result<Self> {
+            if flags & MAP_SHARED == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "only shared mappings are supported",
+                ));
+            }
+            if len == 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty mapping"));
+            }
+
+            let access = if prot & PROT_WRITE != 0 {
+                FILE_MAP_WRITE
+            } else {
+                FILE_MAP_READ
+            };
+            // SAFETY: an invalid handle or length makes the call fail, but does not cause UB
+            let ptr = unsafe { MapViewOfFile(fd as isize, access, 0, 0, len) };
+            if ptr.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(MmapRegion {
+                ptr: ptr as *mut u8,
+                len,
+            })
         }
 
         pub fn as_ptr(&self) -> *const u8 {
@@ -161,5 +253,38 @@ pub mod mman {
         pub fn len(&self) -> usize {
             self.len
         }
+
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        /// Release ownership of the view, returning its address and length
+        pub fn into_raw(self) -> (*mut u8, usize) {
+            let this = std::mem::ManuallyDrop::new(self);
+            (this.ptr, this.len)
+        }
+
+        /// Take ownership of a view previously released with [`MmapRegion::into_raw`]
+        ///
+        /// # Safety
+        ///
+        /// `ptr` and `len` must have been returned by [`MmapRegion::into_raw`] and the view
+        /// must not have been unmapped since.
+        pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Self {
+            MmapRegion { ptr, len }
+        }
+
+        /// Returns `true` if the address is part of this region
+        pub fn contains(&self, ptr: *const u8) -> bool {
+            let start = self.ptr as usize;
+            (start..start + self.len).contains(&(ptr as usize))
+        }
+    }
+
+    impl Drop for MmapRegion {
+        fn drop(&mut self) {
+            // SAFETY: the view was mapped by `MmapRegion::new`
+            unsafe { UnmapViewOfFile(self.ptr as *const c_void) };
+        }
     }
 }
@@ -0,0 +1,121 @@
+//! Cross-thread wakeups of the event loop
+//!
+//! A [`Notifier`] can be cloned and sent to other threads to wake up the thread running
+//! the matching [`NotifierSource`], e.g. once a background worker finished its job.
+//! Notifications are coalesced: the source is dispatched once for any number of
+//! notifications sent since the last dispatch.
+//!
+//! This is a thin wrapper around [`calloop::ping`], which already provides a wakeup source on
+//! all supported platforms. It only adds an [`io::Error`] error type, so the sources built on
+//! top of it can forward its errors alongside their own I/O errors.
+//!
+//! ```no_run
+//! use smithay::compat::notifier;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let (notifier, source) = notifier::new().unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |_, _, _state| {
+//!         // the worker finished
+//!     })
+//!     .unwrap();
+//!
+//! std::thread::spawn(move || {
+//!     // do some work
+//!     notifier.notify();
+//! });
+//! ```
+
+use std::io;
+
+use calloop::{
+    ping::{make_ping, Ping, PingSource},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+/// Sending side of a notifier, see the [module-level documentation](self)
+#[derive(Debug, Clone)]
+pub struct Notifier(Ping);
+
+/// Receiving side of a notifier usable as a calloop event source
+#[derive(Debug)]
+pub struct NotifierSource(PingSource);
+
+/// Create a new notifier and the matching event source
+pub fn new() -> io::Result<(Notifier, NotifierSource)> {
+    let (ping, source) = make_ping()?;
+    Ok((Notifier(ping), NotifierSource(source)))
+}
+
+impl Notifier {
+    /// Wake up the thread running the matching [`NotifierSource`]
+    pub fn notify(&self) {
+        self.0.ping();
+    }
+}
+
+impl EventSource for NotifierSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.0
+            .process_events(readiness, token, |(), &mut ()| callback((), &mut ()))
+            .map_err(io::Error::other)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.0.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.0.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.0.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use calloop::EventLoop;
+
+    #[test]
+    fn wakes_from_thread() {
+        let mut event_loop = EventLoop::<u32>::try_new().unwrap();
+        let (notifier, source) = super::new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(source, |_, _, count| *count += 1)
+            .unwrap();
+
+        let thread = std::thread::spawn(move || {
+            notifier.notify();
+            notifier.notify();
+        });
+        thread.join().unwrap();
+
+        let mut count = 0;
+        event_loop
+            .dispatch(Some(Duration::from_secs(1)), &mut count)
+            .unwrap();
+        assert_eq!(count, 1);
+
+        event_loop.dispatch(Some(Duration::ZERO), &mut count).unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,534 @@
+//! Graceful shutdown requests from the operating system
+//!
+//! [`ShutdownSignals`] is a calloop event source emitting a [`ShutdownSignal`] whenever the
+//! compositor is asked to terminate:
+//!
+//! - on Linux `SIGINT` and `SIGTERM` are received through a `signalfd`,
+//! - on other unix platforms these signals are forwarded by a signal handler through a self-pipe,
+//! - on Windows console control events (Ctrl+C, closing the console, logoff and shutdown)
+//!   are received through `SetConsoleCtrlHandler`.
+//!
+//! Closing the window of a windowed backend (`WM_CLOSE`) is reported by the backend itself,
+//! it can be fed into the same code path with [`ShutdownSignals::trigger`].
+//!
+//! On Linux the signals are blocked for the thread creating the source, so it should be created
+//! before spawning any other threads, which would otherwise inherit the default signal handling.
+//!
+//! ```no_run
+//! use smithay::compat::signals::ShutdownSignals;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<bool>::try_new().unwrap();
+//! let signals = ShutdownSignals::new().unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(signals, |signal, _, running| {
+//!         tracing::info!(?signal, "Shutting down");
+//!         *running = false;
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+
+use super::notifier::{self, Notifier, NotifierSource};
+
+/// Reason the compositor was asked to shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShutdownSignal {
+    /// Interactive interruption, `SIGINT` or Ctrl+C / Ctrl+Break on Windows
+    Interrupt,
+    /// Request to terminate, `SIGTERM` or a logoff / system shutdown on Windows
+    Terminate,
+    /// The console or window of the compositor was closed
+    Close,
+}
+
+impl ShutdownSignal {
+    const ALL: [ShutdownSignal; 3] = [
+        ShutdownSignal::Interrupt,
+        ShutdownSignal::Terminate,
+        ShutdownSignal::Close,
+    ];
+
+    fn bit(self) -> u32 {
+        match self {
+            ShutdownSignal::Interrupt => 1 << 0,
+            ShutdownSignal::Terminate => 1 << 1,
+            ShutdownSignal::Close => 1 << 2,
+        }
+    }
+}
+
+/// Handle to manually trigger a [`ShutdownSignals`] source, e.g. when the window of the compositor was closed
+#[derive(Debug, Clone)]
+pub struct ShutdownTrigger {
+    pending: Arc<AtomicU32>,
+    notifier: Notifier,
+}
+
+impl ShutdownTrigger {
+    /// Emit the given signal from the matching [`ShutdownSignals`] source
+    pub fn trigger(&self, signal: ShutdownSignal) {
+        self.set_pending(signal);
+        self.notifier.notify();
+    }
+
+    fn set_pending(&self, signal: ShutdownSignal) {
+        self.pending.fetch_or(signal.bit(), Ordering::SeqCst);
+    }
+}
+
+/// Event source emitting a [`ShutdownSignal`] when the compositor is asked to terminate
+///
+/// Only one instance may exist at a time, as the signal handling is process wide.
+#[derive(Debug)]
+pub struct ShutdownSignals {
+    os: platform::OsSignals,
+    trigger: ShutdownTrigger,
+    source: NotifierSource,
+}
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+impl ShutdownSignals {
+    /// Start listening for shutdown requests
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if another instance exists.
+    pub fn new() -> io::Result<ShutdownSignals> {
+        if ACTIVE.swap(true, Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "shutdown signals are already handled by another source",
+            ));
+        }
+
+        let setup = || -> io::Result<ShutdownSignals> {
+            let (notifier, source) = notifier::new()?;
+            let trigger = ShutdownTrigger {
+                pending: Arc::new(AtomicU32::new(0)),
+                notifier,
+            };
+            let os = platform::OsSignals::new(&trigger)?;
+            Ok(ShutdownSignals { os, trigger, source })
+        };
+        setup().inspect_err(|_| ACTIVE.store(false, Ordering::SeqCst))
+    }
+
+    /// Returns a handle to emit signals from this source manually
+    pub fn trigger(&self) -> ShutdownTrigger {
+        self.trigger.clone()
+    }
+}
+
+impl Drop for ShutdownSignals {
+    fn drop(&mut self) {
+        self.os.restore();
+        ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
+
+impl EventSource for ShutdownSignals {
+    type Event = ShutdownSignal;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        // both sources only process events for their own token
+        self.os.process_events(readiness, token, &self.trigger)?;
+        self.source.process_events(readiness, token, |_, _| {})?;
+
+        let pending = self.trigger.pending.swap(0, Ordering::SeqCst);
+        for signal in ShutdownSignal::ALL {
+            if pending & signal.bit() != 0 {
+                callback(signal, &mut ());
+            }
+        }
+        Ok(PostAction::Continue)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.os.register(poll, token_factory)?;
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.os.reregister(poll, token_factory)?;
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.os.unregister(poll)?;
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(unix)]
+fn signal_from_raw(signo: i32) -> Option<ShutdownSignal> {
+    match signo {
+        libc::SIGINT => Some(ShutdownSignal::Interrupt),
+        libc::SIGTERM => Some(ShutdownSignal::Terminate),
+        libc::SIGHUP => Some(ShutdownSignal::Close),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+const SIGNALS: [i32; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod platform {
+    use std::{
+        io,
+        mem::{size_of, MaybeUninit},
+        os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+    };
+
+    use calloop::{
+        generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+    };
+
+    use super::{signal_from_raw, ShutdownTrigger, SIGNALS};
+
+    /// Signals blocked for the creating thread and received through a `signalfd`
+    #[derive(Debug)]
+    pub struct OsSignals {
+        old_mask: libc::sigset_t,
+        source: Option<Generic<OwnedFd>>,
+    }
+
+    fn cvt(ret: i32) -> io::Result<i32> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    impl OsSignals {
+        pub fn new(_trigger: &ShutdownTrigger) -> io::Result<OsSignals> {
+            let mut mask = MaybeUninit::<libc::sigset_t>::uninit();
+            let mut old_mask = MaybeUninit::<libc::sigset_t>::uninit();
+            // SAFETY: all pointers are valid, the sets are initialized by sigemptyset and pthread_sigmask
+            let (mask, old_mask) = unsafe {
+                cvt(libc::sigemptyset(mask.as_mut_ptr()))?;
+                for signal in SIGNALS {
+                    cvt(libc::sigaddset(mask.as_mut_ptr(), signal))?;
+                }
+                let ret = libc::pthread_sigmask(libc::SIG_BLOCK, mask.as_ptr(), old_mask.as_mut_ptr());
+                if ret != 0 {
+                    return Err(io::Error::from_raw_os_error(ret));
+                }
+                (mask.assume_init(), old_mask.assume_init())
+            };
+            let mut signals = OsSignals {
+                old_mask,
+                source: None,
+            };
+
+            // SAFETY: the mask is initialized and the returned fd is owned by us
+            let fd = cvt(unsafe { libc::signalfd(-1, &mask, libc::SFD_CLOEXEC | libc::SFD_NONBLOCK) })
+                .inspect_err(|_| signals.restore())?;
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            signals.source = Some(Generic::new(fd, Interest::READ, Mode::Level));
+            Ok(signals)
+        }
+
+        pub fn process_events(
+            &mut self,
+            readiness: Readiness,
+            token: Token,
+            trigger: &ShutdownTrigger,
+        ) -> io::Result<PostAction> {
+            let Some(source) = self.source.as_mut() else {
+                return Ok(PostAction::Continue);
+            };
+            source.process_events(readiness, token, |_, fd| {
+                loop {
+                    let mut info = MaybeUninit::<libc::signalfd_siginfo>::uninit();
+                    // SAFETY: the buffer is large enough for one siginfo
+                    let ret = unsafe {
+                        libc::read(
+                            fd.as_raw_fd(),
+                            info.as_mut_ptr() as *mut libc::c_void,
+                            size_of::<libc::signalfd_siginfo>(),
+                        )
+                    };
+                    if ret as usize != size_of::<libc::signalfd_siginfo>() {
+                        break;
+                    }
+                    // SAFETY: the kernel filled the whole struct
+                    let info = unsafe { info.assume_init() };
+                    if let Some(signal) = signal_from_raw(info.ssi_signo as i32) {
+                        trigger.set_pending(signal);
+                    }
+                }
+                Ok(PostAction::Continue)
+            })
+        }
+
+        pub fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            match self.source.as_mut() {
+                Some(source) => source.register(poll, token_factory),
+                None => Ok(()),
+            }
+        }
+
+        pub fn reregister(
+            &mut self,
+            poll: &mut Poll,
+            token_factory: &mut TokenFactory,
+        ) -> calloop::Result<()> {
+            match self.source.as_mut() {
+                Some(source) => source.reregister(poll, token_factory),
+                None => Ok(()),
+            }
+        }
+
+        pub fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+            match self.source.as_mut() {
+                Some(source) => source.unregister(poll),
+                None => Ok(()),
+            }
+        }
+
+        pub fn restore(&mut self) {
+            self.source.take();
+            // SAFETY: the old mask was initialized by pthread_sigmask
+            unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &self.old_mask, std::ptr::null_mut()) };
+        }
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+mod platform {
+    use std::{
+        io,
+        mem::MaybeUninit,
+        os::unix::io::{AsRawFd, OwnedFd},
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    use calloop::{
+        generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+    };
+
+    use super::{signal_from_raw, ShutdownTrigger, SIGNALS};
+
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    extern "C" fn handler(signo: libc::c_int) {
+        let fd = PIPE.load(Ordering::SeqCst);
+        if fd >= 0 {
+            let byte = signo as u8;
+            // SAFETY: write is async-signal-safe, if the pipe is full a wakeup is already pending
+            unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+        }
+    }
+
+    /// Signal handlers writing the signal number to a self-pipe
+    #[derive(Debug)]
+    pub struct OsSignals {
+        source: Generic<OwnedFd>,
+        write: Option<OwnedFd>,
+        old_actions: Vec<(i32, libc::sigaction)>,
+    }
+
+    impl OsSignals {
+        pub fn new(_trigger: &ShutdownTrigger) -> io::Result<OsSignals> {
+            use rustix::{
+                fs::{fcntl_getfl, fcntl_setfl, OFlags},
+                io::{fcntl_setfd, FdFlags},
+            };
+
+            let (read, write) = rustix::pipe::pipe()?;
+            for fd in [&read, &write] {
+                fcntl_setfd(fd, FdFlags::CLOEXEC)?;
+                fcntl_setfl(fd, fcntl_getfl(fd)? | OFlags::NONBLOCK)?;
+            }
+            PIPE.store(write.as_raw_fd(), Ordering::SeqCst);
+
+            let mut signals = OsSignals {
+                source: Generic::new(read, Interest::READ, Mode::Level),
+                write: Some(write),
+                old_actions: Vec::new(),
+            };
+            for signal in SIGNALS {
+                // SAFETY: the handler only uses async-signal-safe functions
+                unsafe {
+                    let mut action = MaybeUninit::<libc::sigaction>::zeroed().assume_init();
+                    action.sa_sigaction = handler as usize;
+                    action.sa_flags = libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    let mut old = MaybeUninit::<libc::sigaction>::zeroed().assume_init();
+                    if libc::sigaction(signal, &action, &mut old) != 0 {
+                        let err = io::Error::last_os_error();
+                        signals.restore();
+                        return Err(err);
+                    }
+                    signals.old_actions.push((signal, old));
+                }
+            }
+
+            Ok(signals)
+        }
+
+        pub fn process_events(
+            &mut self,
+            readiness: Readiness,
+            token: Token,
+            trigger: &ShutdownTrigger,
+        ) -> io::Result<PostAction> {
+            self.source.process_events(readiness, token, |_, fd| {
+                let mut buf = [0u8; 16];
+                while let Ok(len @ 1..) = rustix::io::read(&**fd, &mut buf[..]) {
+                    for signo in &buf[..len] {
+                        if let Some(signal) = signal_from_raw(*signo as i32) {
+                            trigger.set_pending(signal);
+                        }
+                    }
+                }
+                Ok(PostAction::Continue)
+            })
+        }
+
+        pub fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            self.source.register(poll, token_factory)
+        }
+
+        pub fn reregister(
+            &mut self,
+            poll: &mut Poll,
+            token_factory: &mut TokenFactory,
+        ) -> calloop::Result<()> {
+            self.source.reregister(poll, token_factory)
+        }
+
+        pub fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+            self.source.unregister(poll)
+        }
+
+        pub fn restore(&mut self) {
+            for (signal, old) in self.old_actions.drain(..) {
+                // SAFETY: restores the previously installed action
+                unsafe { libc::sigaction(signal, &old, std::ptr::null_mut()) };
+            }
+            PIPE.store(-1, Ordering::SeqCst);
+            self.write.take();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{io, sync::Mutex, time::Duration};
+
+    use calloop::{Poll, PostAction, Readiness, Token, TokenFactory};
+
+    use super::{ShutdownSignal, ShutdownTrigger};
+
+    const CTRL_C_EVENT: u32 = 0;
+    const CTRL_BREAK_EVENT: u32 = 1;
+    const CTRL_CLOSE_EVENT: u32 = 2;
+    const CTRL_LOGOFF_EVENT: u32 = 5;
+    const CTRL_SHUTDOWN_EVENT: u32 = 6;
+
+    /// Time granted to the compositor to shut down after the console was closed,
+    /// Windows terminates the process after about 5 seconds regardless.
+    const CLOSE_GRACE_PERIOD: Duration = Duration::from_millis(4500);
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<unsafe extern "system" fn(u32) -> i32>, add: i32) -> i32;
+    }
+
+    static TRIGGER: Mutex<Option<ShutdownTrigger>> = Mutex::new(None);
+
+    unsafe extern "system" fn handler(ctrl_type: u32) -> i32 {
+        let signal = match ctrl_type {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT => ShutdownSignal::Interrupt,
+            CTRL_CLOSE_EVENT => ShutdownSignal::Close,
+            CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => ShutdownSignal::Terminate,
+            _ => return 0,
+        };
+        let Some(trigger) = TRIGGER.lock().unwrap().clone() else {
+            return 0;
+        };
+        trigger.trigger(signal);
+
+        if signal != ShutdownSignal::Interrupt {
+            // the process is terminated once the handler returns
+            std::thread::sleep(CLOSE_GRACE_PERIOD);
+        }
+        1
+    }
+
+    /// Console control handler forwarding to the trigger, which wakes up the event loop
+    #[derive(Debug)]
+    pub struct OsSignals {
+        installed: bool,
+    }
+
+    impl OsSignals {
+        pub fn new(trigger: &ShutdownTrigger) -> io::Result<OsSignals> {
+            *TRIGGER.lock().unwrap() = Some(trigger.clone());
+            if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+                *TRIGGER.lock().unwrap() = None;
+                return Err(io::Error::last_os_error());
+            }
+            Ok(OsSignals { installed: true })
+        }
+
+        pub fn process_events(
+            &mut self,
+            _readiness: Readiness,
+            _token: Token,
+            _trigger: &ShutdownTrigger,
+        ) -> io::Result<PostAction> {
+            Ok(PostAction::Continue)
+        }
+
+        pub fn register(
+            &mut self,
+            _poll: &mut Poll,
+            _token_factory: &mut TokenFactory,
+        ) -> calloop::Result<()> {
+            Ok(())
+        }
+
+        pub fn reregister(
+            &mut self,
+            _poll: &mut Poll,
+            _token_factory: &mut TokenFactory,
+        ) -> calloop::Result<()> {
+            Ok(())
+        }
+
+        pub fn unregister(&mut self, _poll: &mut Poll) -> calloop::Result<()> {
+            Ok(())
+        }
+
+        pub fn restore(&mut self) {
+            if std::mem::take(&mut self.installed) {
+                unsafe { SetConsoleCtrlHandler(Some(handler), 0) };
+                *TRIGGER.lock().unwrap() = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,619 @@
+//! Animations for element transforms
+//!
+//! An [`Animation`] interpolates a single value over time, either following an [`Easing`] curve for
+//! a fixed duration or simulating a [`Spring`]. Animations are sampled at the presentation time of
+//! the frame being rendered, which keeps them in sync with the output refresh regardless of when
+//! the compositor actually starts rendering.
+//!
+//! [`TransformAnimation`] combines animations of the offset, scale and alpha of an element and
+//! applies them to anything implementing [`AsRenderElements`], e.g. a [`Window`](super::Window).
+//! A [`Timeline`] keeps track of multiple keyed animations, for example one per window, and tells
+//! whether another frame needs to be scheduled.
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::test::DummyRenderer;
+//! # use smithay::backend::renderer::element::solid::{SolidColorBuffer, SolidColorRenderElement};
+//! # use smithay::backend::renderer::element::utils::RescaleRenderElement;
+//! use std::time::Duration;
+//! use smithay::desktop::animation::{Easing, Timeline, TransformAnimation};
+//! use smithay::utils::{Clock, Monotonic};
+//!
+//! # let mut renderer = DummyRenderer::default();
+//! # let window = SolidColorBuffer::new((400, 300), [1.0, 1.0, 1.0, 1.0]);
+//! let clock = Clock::<Monotonic>::new();
+//! let mut timeline = Timeline::new();
+//!
+//! // a window got mapped
+//! timeline.insert(1u32, TransformAnimation::open(clock.now(), Duration::from_millis(200), Easing::EaseOutCubic));
+//!
+//! // while rendering, sample the animations at the time the frame is going to be presented
+//! # let presentation_time = clock.now();
+//! let elements: Vec<RescaleRenderElement<SolidColorRenderElement>> = match timeline.get(&1) {
+//!     Some(animation) => animation.render_elements(
+//!         &window,
+//!         &mut renderer,
+//!         presentation_time,
+//!         (100, 100).into(),
+//!         (300, 250).into(),
+//!         1.0.into(),
+//!         1.0,
+//!     ),
+//!     None => Vec::new(),
+//! };
+//!
+//! // after rendering drop finished animations and check, if another frame is required
+//! timeline.retain_running(presentation_time);
+//! let schedule_frame = timeline.is_running(presentation_time);
+//! ```
+
+use std::{collections::HashMap, hash::Hash, time::Duration};
+
+use crate::{
+    backend::renderer::{
+        element::{utils::RescaleRenderElement, AsRenderElements},
+        Renderer,
+    },
+    utils::{Monotonic, Physical, Point, Scale, Time},
+};
+
+/// Easing curves for duration based animations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Quadratic acceleration from zero velocity
+    EaseInQuad,
+    /// Quadratic deceleration to zero velocity
+    EaseOutQuad,
+    /// Quadratic acceleration until halfway, then deceleration
+    EaseInOutQuad,
+    /// Cubic acceleration from zero velocity
+    EaseInCubic,
+    /// Cubic deceleration to zero velocity
+    EaseOutCubic,
+    /// Cubic acceleration until halfway, then deceleration
+    EaseInOutCubic,
+    /// Exponential deceleration to zero velocity
+    EaseOutExpo,
+    /// Cubic bézier curve with the control points `(x1, y1)` and `(x2, y2)`, like in CSS
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    /// Map the linear progress `t` (between `0.0` and `1.0`) onto the curve
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t).powi(2),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::EaseInCubic => t.powi(3),
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::EaseOutExpo => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2f64.powf(-10.0 * t)
+                }
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    let bezier = |a: f64, b: f64, s: f64| {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * s * a + 3.0 * inv * s * s * b + s * s * s
+    };
+    let derivative = |a: f64, b: f64, s: f64| {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * a + 6.0 * inv * s * (b - a) + 3.0 * s * s * (1.0 - b)
+    };
+
+    // find the curve parameter for x using newton's method, falling back to bisection
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, s) - x;
+        if error.abs() < 1e-7 {
+            return bezier(y1, y2, s);
+        }
+        let slope = derivative(x1, x2, s);
+        if slope.abs() < 1e-7 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    for _ in 0..32 {
+        let value = bezier(x1, x2, s);
+        if (value - x).abs() < 1e-7 {
+            break;
+        }
+        if value < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    bezier(y1, y2, s)
+}
+
+/// Parameters of a damped spring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    /// Ratio of the damping to the critical damping
+    ///
+    /// Values below `1.0` overshoot the target, values above approach it slower.
+    pub damping_ratio: f64,
+    /// Stiffness of the spring for a mass of one
+    pub stiffness: f64,
+    /// Distance to the target (and velocity) below which the animation is considered done
+    pub epsilon: f64,
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Spring {
+            damping_ratio: 1.0,
+            stiffness: 800.0,
+            epsilon: 0.0001,
+        }
+    }
+}
+
+impl Spring {
+    /// Displacement from the target after `t` seconds, starting at displacement `x0` with velocity `v0`
+    fn displacement(&self, x0: f64, v0: f64, t: f64) -> f64 {
+        let omega = self.stiffness.max(f64::EPSILON).sqrt();
+        let zeta = self.damping_ratio.max(0.0);
+
+        if (zeta - 1.0).abs() < 1e-6 {
+            (-omega * t).exp() * (x0 + (v0 + omega * x0) * t)
+        } else if zeta < 1.0 {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            (-zeta * omega * t).exp()
+                * (x0 * (omega_d * t).cos() + (v0 + zeta * omega * x0) / omega_d * (omega_d * t).sin())
+        } else {
+            let root = (zeta * zeta - 1.0).sqrt();
+            let r1 = -omega * (zeta - root);
+            let r2 = -omega * (zeta + root);
+            let c1 = (v0 - r2 * x0) / (r1 - r2);
+            let c2 = x0 - c1;
+            c1 * (r1 * t).exp() + c2 * (r2 * t).exp()
+        }
+    }
+
+    fn velocity(&self, x0: f64, v0: f64, t: f64) -> f64 {
+        const DT: f64 = 0.001;
+        (self.displacement(x0, v0, t + DT) - self.displacement(x0, v0, t)) / DT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Curve {
+    Easing { duration: Duration, easing: Easing },
+    Spring(Spring),
+}
+
+/// Animation of a single value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    from: f64,
+    to: f64,
+    start: Time<Monotonic>,
+    initial_velocity: f64,
+    curve: Curve,
+}
+
+impl Animation {
+    /// Animate from `from` to `to` over `duration` following `easing`
+    pub fn new(from: f64, to: f64, start: Time<Monotonic>, duration: Duration, easing: Easing) -> Self {
+        Animation {
+            from,
+            to,
+            start,
+            initial_velocity: 0.0,
+            curve: Curve::Easing { duration, easing },
+        }
+    }
+
+    /// Animate from `from` to `to` using a spring simulation
+    ///
+    /// `initial_velocity` is given in units per second.
+    pub fn spring(from: f64, to: f64, start: Time<Monotonic>, initial_velocity: f64, spring: Spring) -> Self {
+        Animation {
+            from,
+            to,
+            start,
+            initial_velocity,
+            curve: Curve::Spring(spring),
+        }
+    }
+
+    /// Value the animation starts at
+    pub fn initial_value(&self) -> f64 {
+        self.from
+    }
+
+    /// Value the animation ends at
+    pub fn target(&self) -> f64 {
+        self.to
+    }
+
+    /// Time the animation started at
+    pub fn start(&self) -> Time<Monotonic> {
+        self.start
+    }
+
+    /// Value of the animation at the given time
+    pub fn value_at(&self, time: Time<Monotonic>) -> f64 {
+        let elapsed = time.saturating_duration_since(self.start);
+        match self.curve {
+            Curve::Easing { duration, easing } => {
+                if elapsed >= duration {
+                    return self.to;
+                }
+                let t = elapsed.as_secs_f64() / duration.as_secs_f64();
+                self.from + (self.to - self.from) * easing.apply(t)
+            }
+            Curve::Spring(spring) => {
+                if self.is_done(time) {
+                    return self.to;
+                }
+                self.to
+                    + spring.displacement(self.from - self.to, self.initial_velocity, elapsed.as_secs_f64())
+            }
+        }
+    }
+
+    /// Velocity of the animated value at the given time in units per second
+    pub fn velocity_at(&self, time: Time<Monotonic>) -> f64 {
+        if self.is_done(time) {
+            return 0.0;
+        }
+        let elapsed = time.saturating_duration_since(self.start).as_secs_f64();
+        match self.curve {
+            Curve::Easing { duration, easing } => {
+                const DT: f64 = 0.001;
+                let duration = duration.as_secs_f64();
+                let t = elapsed / duration;
+                let slope = (easing.apply(t + DT) - easing.apply(t)) / DT;
+                (self.to - self.from) * slope / duration
+            }
+            Curve::Spring(spring) => spring.velocity(self.from - self.to, self.initial_velocity, elapsed),
+        }
+    }
+
+    /// Whether the animation has reached its target at the given time
+    pub fn is_done(&self, time: Time<Monotonic>) -> bool {
+        let elapsed = time.saturating_duration_since(self.start);
+        match self.curve {
+            Curve::Easing { duration, .. } => elapsed >= duration,
+            Curve::Spring(spring) => {
+                let t = elapsed.as_secs_f64();
+                let x0 = self.from - self.to;
+                // scale the threshold with the distance, so it works with pixels as well as factors
+                let epsilon = spring.epsilon * x0.abs().max(1.0);
+                spring.displacement(x0, self.initial_velocity, t).abs() < epsilon
+                    && spring.velocity(x0, self.initial_velocity, t).abs() < epsilon
+            }
+        }
+    }
+
+    /// Change the target of the animation, continuing from the value at `time`
+    ///
+    /// Spring animations keep their current velocity, easing animations restart with their
+    /// original duration.
+    pub fn retarget(&mut self, to: f64, time: Time<Monotonic>) {
+        let value = self.value_at(time);
+        let velocity = self.velocity_at(time);
+        self.from = value;
+        self.to = to;
+        self.start = time;
+        if let Curve::Spring(_) = self.curve {
+            self.initial_velocity = velocity;
+        }
+    }
+}
+
+/// Offset, scale and alpha applied to an element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementTransform {
+    /// Offset added to the location of the element
+    pub offset: Point<f64, Physical>,
+    /// Scale applied around the origin of the element
+    pub scale: f64,
+    /// Alpha multiplied with the alpha of the element
+    pub alpha: f32,
+}
+
+impl Default for ElementTransform {
+    fn default() -> Self {
+        ElementTransform {
+            offset: Point::default(),
+            scale: 1.0,
+            alpha: 1.0,
+        }
+    }
+}
+
+/// Animation of the [`ElementTransform`] of an element
+///
+/// Properties without an animation keep their default value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransformAnimation {
+    /// Animation of the horizontal offset in physical pixels
+    pub offset_x: Option<Animation>,
+    /// Animation of the vertical offset in physical pixels
+    pub offset_y: Option<Animation>,
+    /// Animation of the scale
+    pub scale: Option<Animation>,
+    /// Animation of the alpha
+    pub alpha: Option<Animation>,
+}
+
+impl TransformAnimation {
+    /// Typical animation of a window being opened, growing from 90% of its size while fading in
+    pub fn open(start: Time<Monotonic>, duration: Duration, easing: Easing) -> Self {
+        TransformAnimation {
+            scale: Some(Animation::new(0.9, 1.0, start, duration, easing)),
+            alpha: Some(Animation::new(0.0, 1.0, start, duration, easing)),
+            ..Default::default()
+        }
+    }
+
+    /// Typical animation of a window being closed, shrinking to 90% of its size while fading out
+    pub fn close(start: Time<Monotonic>, duration: Duration, easing: Easing) -> Self {
+        TransformAnimation {
+            scale: Some(Animation::new(1.0, 0.9, start, duration, easing)),
+            alpha: Some(Animation::new(1.0, 0.0, start, duration, easing)),
+            ..Default::default()
+        }
+    }
+
+    /// Typical animation of a window being moved, sliding from its previous location
+    ///
+    /// `delta` is the previous location relative to the new one.
+    pub fn slide(delta: Point<f64, Physical>, start: Time<Monotonic>, spring: Spring) -> Self {
+        TransformAnimation {
+            offset_x: Some(Animation::spring(delta.x, 0.0, start, 0.0, spring)),
+            offset_y: Some(Animation::spring(delta.y, 0.0, start, 0.0, spring)),
+            ..Default::default()
+        }
+    }
+
+    fn animations(&self) -> impl Iterator<Item = &Animation> {
+        [&self.offset_x, &self.offset_y, &self.scale, &self.alpha]
+            .into_iter()
+            .flatten()
+    }
+
+    /// Transform of the element at the given time
+    pub fn transform_at(&self, time: Time<Monotonic>) -> ElementTransform {
+        let value = |animation: &Option<Animation>, default: f64| {
+            animation
+                .as_ref()
+                .map(|animation| animation.value_at(time))
+                .unwrap_or(default)
+        };
+        ElementTransform {
+            offset: (value(&self.offset_x, 0.0), value(&self.offset_y, 0.0)).into(),
+            scale: value(&self.scale, 1.0).max(0.0),
+            alpha: value(&self.alpha, 1.0).clamp(0.0, 1.0) as f32,
+        }
+    }
+
+    /// Whether all animations reached their target at the given time
+    pub fn is_done(&self, time: Time<Monotonic>) -> bool {
+        self.animations().all(|animation| animation.is_done(time))
+    }
+
+    /// Render elements with the transform at the given time applied
+    ///
+    /// `origin` is the point the element is scaled around, e.g. its center.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_elements<R, A, C>(
+        &self,
+        element: &A,
+        renderer: &mut R,
+        time: Time<Monotonic>,
+        location: Point<i32, Physical>,
+        origin: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer,
+        A: AsRenderElements<R>,
+        C: From<RescaleRenderElement<A::RenderElement>>,
+    {
+        let transform = self.transform_at(time);
+        let offset = transform.offset.to_i32_round();
+        element
+            .render_elements::<A::RenderElement>(renderer, location + offset, scale, alpha * transform.alpha)
+            .into_iter()
+            .map(|element| {
+                RescaleRenderElement::from_element(element, origin + offset, transform.scale).into()
+            })
+            .collect()
+    }
+}
+
+/// Keyed collection of running [`TransformAnimation`]s
+#[derive(Debug)]
+pub struct Timeline<K> {
+    animations: HashMap<K, TransformAnimation>,
+}
+
+impl<K> Default for Timeline<K> {
+    fn default() -> Self {
+        Timeline {
+            animations: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> Timeline<K> {
+    /// Create an empty timeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start an animation for `key`, replacing a previous one
+    pub fn insert(&mut self, key: K, animation: TransformAnimation) -> Option<TransformAnimation> {
+        self.animations.insert(key, animation)
+    }
+
+    /// Animation of `key`
+    pub fn get(&self, key: &K) -> Option<&TransformAnimation> {
+        self.animations.get(key)
+    }
+
+    /// Mutable access to the animation of `key`, e.g. to retarget it
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut TransformAnimation> {
+        self.animations.get_mut(key)
+    }
+
+    /// Stop the animation of `key`
+    pub fn remove(&mut self, key: &K) -> Option<TransformAnimation> {
+        self.animations.remove(key)
+    }
+
+    /// Transform of `key` at the given time, the identity if it is not animated
+    pub fn transform_at(&self, key: &K, time: Time<Monotonic>) -> ElementTransform {
+        self.animations
+            .get(key)
+            .map(|animation| animation.transform_at(time))
+            .unwrap_or_default()
+    }
+
+    /// Whether any animation is still running at the given time and requires further frames
+    pub fn is_running(&self, time: Time<Monotonic>) -> bool {
+        self.animations.values().any(|animation| !animation.is_done(time))
+    }
+
+    /// Drop all animations finished at the given time, returning their keys
+    ///
+    /// This can be used to unmap windows after their close animation.
+    pub fn retain_running(&mut self, time: Time<Monotonic>) -> Vec<K>
+    where
+        K: Clone,
+    {
+        let finished = self
+            .animations
+            .iter()
+            .filter(|(_, animation)| animation.is_done(time))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &finished {
+            self.animations.remove(key);
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: u64) -> Time<Monotonic> {
+        Time::from(Duration::from_millis(1_000 + millis))
+    }
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseInQuad,
+            Easing::EaseOutQuad,
+            Easing::EaseInOutQuad,
+            Easing::EaseInCubic,
+            Easing::EaseOutCubic,
+            Easing::EaseInOutCubic,
+            Easing::EaseOutExpo,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+        }
+        assert!((Easing::CubicBezier(0.0, 0.0, 1.0, 1.0).apply(0.3) - 0.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn easing_animation() {
+        let animation = Animation::new(10.0, 20.0, at(0), Duration::from_millis(100), Easing::Linear);
+        assert_eq!(animation.value_at(at(0)), 10.0);
+        assert!((animation.value_at(at(50)) - 15.0).abs() < 1e-9);
+        assert!(!animation.is_done(at(99)));
+        assert_eq!(animation.value_at(at(200)), 20.0);
+        assert!(animation.is_done(at(100)));
+    }
+
+    #[test]
+    fn spring_settles() {
+        for damping_ratio in [0.5, 1.0, 2.0] {
+            let spring = Spring {
+                damping_ratio,
+                ..Default::default()
+            };
+            let animation = Animation::spring(0.0, 100.0, at(0), 0.0, spring);
+            assert!(!animation.is_done(at(10)));
+            assert!(animation.value_at(at(10)) > 0.0);
+            assert!(animation.is_done(at(5_000)));
+            assert_eq!(animation.value_at(at(5_000)), 100.0);
+        }
+    }
+
+    #[test]
+    fn retarget_keeps_value() {
+        let mut animation = Animation::spring(0.0, 100.0, at(0), 0.0, Spring::default());
+        let value = animation.value_at(at(30));
+        animation.retarget(-50.0, at(30));
+        assert!((animation.value_at(at(30)) - value).abs() < 1e-9);
+        assert!(animation.velocity_at(at(30)) > 0.0);
+        assert_eq!(animation.value_at(at(10_000)), -50.0);
+    }
+
+    #[test]
+    fn timeline() {
+        let mut timeline = Timeline::new();
+        timeline.insert(
+            1,
+            TransformAnimation::open(at(0), Duration::from_millis(100), Easing::Linear),
+        );
+        timeline.insert(
+            2,
+            TransformAnimation::close(at(0), Duration::from_millis(300), Easing::Linear),
+        );
+
+        let transform = timeline.transform_at(&1, at(50));
+        assert!((transform.scale - 0.95).abs() < 1e-9);
+        assert!((transform.alpha - 0.5).abs() < 1e-6);
+        assert_eq!(timeline.transform_at(&3, at(50)), ElementTransform::default());
+
+        assert_eq!(timeline.retain_running(at(150)), vec![1]);
+        assert!(timeline.is_running(at(150)));
+        assert_eq!(timeline.retain_running(at(300)), vec![2]);
+        assert!(!timeline.is_running(at(300)));
+    }
+}
@@ -0,0 +1,304 @@
+//! Focus policies and focus history
+//!
+//! A [`FocusTracker`] decides which element receives the keyboard focus according to a
+//! [`FocusPolicy`] and remembers the most recently focused elements of every workspace or output,
+//! so the focus can be restored when switching back to it or when the focused element closes.
+//!
+//! The tracker is fed with the pointer events of the compositor and returns a [`FocusChange`],
+//! which the compositor applies to its seat, e.g. with [`FocusTracker::apply`]. Elements are
+//! grouped by a key of the compositor's choice, like the handle of a workspace or an output.
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::backend::input::ButtonState;
+//! use smithay::desktop::focus::{FocusChange, FocusPolicy, FocusTracker};
+//! # #[derive(Debug, Clone, PartialEq)]
+//! # struct Element;
+//! # impl smithay::utils::IsAlive for Element { fn alive(&self) -> bool { true } }
+//! # let element_under_pointer: Option<Element> = None;
+//!
+//! // elements grouped by workspace index
+//! let mut focus = FocusTracker::<Element, usize>::new(FocusPolicy::ClickToFocus);
+//!
+//! // on every pointer button event
+//! match focus.pointer_button(&0, element_under_pointer.as_ref(), ButtonState::Pressed) {
+//!     FocusChange::Focus(element) => { /* raise the element and set the keyboard focus */ }
+//!     FocusChange::Unchanged => {}
+//! }
+//!
+//! // after switching to another workspace, restore its focus
+//! let change = focus.restore(&1);
+//! ```
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{backend::input::ButtonState, utils::IsAlive};
+
+/// When the focus follows the pointer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FocusPolicy {
+    /// Elements are focused by clicking on them
+    #[default]
+    ClickToFocus,
+    /// The element under the pointer is focused, moving the pointer over empty space unfocuses
+    FocusFollowsMouse,
+    /// The element under the pointer is focused, moving the pointer over empty space keeps the
+    /// focus
+    SloppyFocus,
+}
+
+/// Result of feeding an event to a [`FocusTracker`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FocusChange<E> {
+    /// The focus stays the same
+    Unchanged,
+    /// The focus moves to the given element, or is cleared
+    Focus(Option<E>),
+}
+
+/// Applies a [`FocusPolicy`] and tracks the focus history
+///
+/// `K` identifies the groups focus history is kept for, e.g. workspaces or outputs.
+#[derive(Debug)]
+pub struct FocusTracker<E, K> {
+    policy: FocusPolicy,
+    focused: Option<(K, E)>,
+    history: HashMap<K, Vec<E>>,
+}
+
+impl<E, K> FocusTracker<E, K>
+where
+    E: Clone + PartialEq + IsAlive,
+    K: Clone + Eq + Hash,
+{
+    /// Create a new tracker using the given policy
+    pub fn new(policy: FocusPolicy) -> Self {
+        FocusTracker {
+            policy,
+            focused: None,
+            history: HashMap::new(),
+        }
+    }
+
+    /// Returns the current policy
+    pub fn policy(&self) -> FocusPolicy {
+        self.policy
+    }
+
+    /// Change the policy
+    pub fn set_policy(&mut self, policy: FocusPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the focused element
+    pub fn focused(&self) -> Option<&E> {
+        self.focused.as_ref().map(|(_, element)| element)
+    }
+
+    /// Returns the key of the group of the focused element
+    pub fn focused_key(&self) -> Option<&K> {
+        self.focused.as_ref().map(|(key, _)| key)
+    }
+
+    /// The pointer moved over `under` in the group `key`
+    ///
+    /// Only changes the focus with [`FocusPolicy::FocusFollowsMouse`] or
+    /// [`FocusPolicy::SloppyFocus`]. Should not be called while the pointer is grabbed, e.g.
+    /// during an interactive move.
+    pub fn pointer_motion(&mut self, key: &K, under: Option<&E>) -> FocusChange<E> {
+        match (self.policy, under) {
+            (FocusPolicy::ClickToFocus, _) => FocusChange::Unchanged,
+            (FocusPolicy::SloppyFocus, None) => FocusChange::Unchanged,
+            (_, under) => self.focus(key, under.cloned()),
+        }
+    }
+
+    /// A pointer button was pressed or released over `under` in the group `key`
+    ///
+    /// Pressing a button focuses the element under the pointer with any policy. Clicking empty
+    /// space only clears the focus with [`FocusPolicy::ClickToFocus`] and
+    /// [`FocusPolicy::FocusFollowsMouse`].
+    pub fn pointer_button(&mut self, key: &K, under: Option<&E>, state: ButtonState) -> FocusChange<E> {
+        if state != ButtonState::Pressed {
+            return FocusChange::Unchanged;
+        }
+        match (self.policy, under) {
+            (FocusPolicy::SloppyFocus, None) => FocusChange::Unchanged,
+            (_, under) => self.focus(key, under.cloned()),
+        }
+    }
+
+    /// Focus an element of the group `key`, or clear the focus
+    ///
+    /// The element becomes the most recent entry of the history of its group.
+    pub fn focus(&mut self, key: &K, element: Option<E>) -> FocusChange<E> {
+        let unchanged = match (&self.focused, &element) {
+            (Some((focused_key, focused)), Some(element)) => focused_key == key && focused == element,
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return FocusChange::Unchanged;
+        }
+
+        if let Some(element) = &element {
+            let history = self.history.entry(key.clone()).or_default();
+            history.retain(|e| e != element);
+            history.insert(0, element.clone());
+        }
+        self.focused = element.clone().map(|element| (key.clone(), element));
+        FocusChange::Focus(element)
+    }
+
+    /// Focus the most recently focused element of the group `key`
+    ///
+    /// Clears the focus if the group has no live elements, e.g. after switching to an empty
+    /// workspace.
+    pub fn restore(&mut self, key: &K) -> FocusChange<E> {
+        let element = self.most_recent(key).cloned();
+        self.focus(key, element)
+    }
+
+    /// Returns the most recently focused live element of the group `key`
+    pub fn most_recent(&self, key: &K) -> Option<&E> {
+        self.history(key).next()
+    }
+
+    /// Returns the live elements of the group `key`, most recently focused first
+    pub fn history(&self, key: &K) -> impl Iterator<Item = &E> {
+        self.history
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter(|element| element.alive())
+    }
+
+    /// Move an element to another group, e.g. when it is moved to another workspace
+    ///
+    /// The element becomes the most recent entry of the history of its new group.
+    pub fn move_element(&mut self, element: &E, key: &K) {
+        let found = self.history.values_mut().any(|history| {
+            history
+                .iter()
+                .position(|e| e == element)
+                .map(|pos| history.remove(pos))
+                .is_some()
+        });
+        if found {
+            self.history
+                .entry(key.clone())
+                .or_default()
+                .insert(0, element.clone());
+        }
+        if let Some((focused_key, focused)) = &mut self.focused {
+            if focused == element {
+                *focused_key = key.clone();
+            }
+        }
+    }
+
+    /// Forget an element, e.g. because it was closed
+    ///
+    /// If the element was focused, the focus moves to the most recently focused element of its
+    /// group.
+    pub fn remove(&mut self, element: &E) -> FocusChange<E> {
+        for history in self.history.values_mut() {
+            history.retain(|e| e != element);
+        }
+        match self.focused.take() {
+            Some((key, focused)) if &focused == element => self.restore(&key),
+            focused => {
+                self.focused = focused;
+                FocusChange::Unchanged
+            }
+        }
+    }
+
+    /// Drop dead elements and empty groups from the history
+    pub fn cleanup(&mut self) {
+        for history in self.history.values_mut() {
+            history.retain(|e| e.alive());
+        }
+        self.history.retain(|_, history| !history.is_empty());
+    }
+
+    /// Apply a change to the keyboard focus of a seat
+    ///
+    /// Does nothing if the focus is unchanged or the seat has no keyboard.
+    #[cfg(feature = "xkbcommon")]
+    pub fn apply<D>(
+        &self,
+        change: FocusChange<E>,
+        seat: &crate::input::Seat<D>,
+        data: &mut D,
+        serial: crate::utils::Serial,
+    ) where
+        D: crate::input::SeatHandler + 'static,
+        E: Into<D::KeyboardFocus>,
+    {
+        let FocusChange::Focus(element) = change else {
+            return;
+        };
+        if let Some(keyboard) = seat.get_keyboard() {
+            keyboard.set_focus(data, element.map(Into::into), serial);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Element(u32, bool);
+
+    impl IsAlive for Element {
+        fn alive(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let a = Element(1, true);
+        let b = Element(2, true);
+
+        let mut focus = FocusTracker::new(FocusPolicy::ClickToFocus);
+        assert_eq!(focus.pointer_motion(&0, Some(&a)), FocusChange::Unchanged);
+        assert_eq!(
+            focus.pointer_button(&0, Some(&a), ButtonState::Pressed),
+            FocusChange::Focus(Some(a.clone()))
+        );
+
+        focus.set_policy(FocusPolicy::SloppyFocus);
+        assert_eq!(
+            focus.pointer_motion(&0, Some(&b)),
+            FocusChange::Focus(Some(b.clone()))
+        );
+        assert_eq!(focus.pointer_motion(&0, None), FocusChange::Unchanged);
+
+        focus.set_policy(FocusPolicy::FocusFollowsMouse);
+        assert_eq!(focus.pointer_motion(&0, None), FocusChange::Focus(None));
+    }
+
+    #[test]
+    fn history_per_group() {
+        let a = Element(1, true);
+        let b = Element(2, true);
+        let c = Element(3, true);
+
+        let mut focus = FocusTracker::new(FocusPolicy::ClickToFocus);
+        focus.focus(&0, Some(a.clone()));
+        focus.focus(&0, Some(b.clone()));
+        focus.focus(&1, Some(c.clone()));
+        assert_eq!(focus.history(&0).collect::<Vec<_>>(), vec![&b, &a]);
+
+        assert_eq!(focus.restore(&0), FocusChange::Focus(Some(b.clone())));
+        assert_eq!(focus.remove(&b), FocusChange::Focus(Some(a.clone())));
+        assert_eq!(focus.restore(&2), FocusChange::Focus(None));
+
+        focus.move_element(&a, &1);
+        assert_eq!(focus.history(&1).collect::<Vec<_>>(), vec![&a, &c]);
+        assert_eq!(focus.history(&0).count(), 0);
+    }
+}
@@ -43,26 +43,76 @@
 //! relations to one-another. Popups are then automatically rendered with their matching toplevel surfaces,
 //! when either [`crate::backend::renderer::element::AsRenderElements::render_elements`] or [`render_output`](crate::desktop::space::render_output) is called.
 //!
+//! ### Window capture
+//!
+//! A [`WindowCapture`](capture::WindowCapture) renders a single [`Window`] into a buffer of its own,
+//! tracking damage per window, e.g. to share individual windows through a screencast portal.
+//!
+//! ### Thumbnails
+//!
+//! [`Thumbnails`](thumbnails::Thumbnails) keeps small, periodically updated textures of windows for task
+//! switchers and overview modes, without rendering every window at full size each frame.
+//!
+//! ### Server-side decorations
+//!
+//! A [`ServerDecoration`](decoration::ServerDecoration) draws a titlebar, border and shadow around windows
+//! that negotiated server-side decorations and maps pointer positions to move, resize and button actions.
+//!
+//! ### Focus
+//!
+//! A [`FocusTracker`](focus::FocusTracker) implements click-to-focus and focus-follows-mouse policies
+//! and keeps the focus history of every workspace or output to restore the focus after switching.
+//!
+//! ### Interactive move and resize
+//!
+//! [`MoveGrab`](grabs::MoveGrab) and [`ResizeGrab`](grabs::ResizeGrab) move and resize windows of a
+//! [`Space`] with the pointer, honoring size limits and snapping to the edges of outputs and other windows.
+//!
+//! ### Window rules
+//!
+//! [`WindowRules`](rules::WindowRules) match new toplevels by app id and title and resolve the actions
+//! configured for them, like the initial size, workspace, layout or opacity.
+//!
+//! ### Workspaces
+//!
+//! [`Workspaces`](workspaces::Workspaces) keeps a [`Space`] per workspace of every output and mirrors
+//! them to pagers and bars through the [ext-workspace protocol](crate::wayland::workspace).
+//!
+//! ### Animations
+//!
+//! The [`animation`] module provides easing curves and spring physics to animate the offset, scale and
+//! alpha of windows and other elements, e.g. when they are opened or closed.
+//!
 //! ## Remarks
 //!
 //! Note that the desktop abstractions are concerned with easing rendering different clients and therefore need to be able
 //! to manage client buffers to do so. If you plan to use the provided drawing functions, you need to use
 //! [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler).
 
+pub mod animation;
+pub mod focus;
 pub mod space;
 pub use self::space::Space;
 
 #[cfg(feature = "wayland_frontend")]
 pub use self::wayland::{
+    capture, decoration, grabs,
     layer::{layer_map_for_output, LayerMap, LayerSurface},
     popup::*,
-    utils,
+    rules, thumbnails, utils,
     window::*,
+    workspaces,
 };
 #[cfg(feature = "wayland_frontend")]
 mod wayland {
+    pub mod capture;
+    pub mod decoration;
+    pub mod grabs;
     pub(crate) mod layer;
     pub mod popup;
+    pub mod rules;
+    pub mod thumbnails;
     pub mod utils;
     pub mod window;
+    pub mod workspaces;
 }
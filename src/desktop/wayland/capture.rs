@@ -0,0 +1,165 @@
+//! Capturing of individual windows
+//!
+//! A [`WindowCapture`] renders a single [`Window`] into a framebuffer sized to the window,
+//! independent of any output it might be mapped on. This can be used to implement sharing a
+//! single window with screencast portals or to create window previews.
+//!
+//! Each capture keeps its own damage tracking, so only the parts of the window that changed
+//! since the framebuffer was last used are redrawn and reported as damage.
+//!
+//! ```no_run
+//! use smithay::backend::renderer::{element::surface::WaylandSurfaceRenderElement, ImportAll, Renderer, Texture};
+//! use smithay::desktop::{
+//!     capture::{WindowCapture, WindowCaptureRegion},
+//!     Window,
+//! };
+//!
+//! fn share_window<R>(renderer: &mut R, framebuffer: &mut R::Framebuffer<'_>, age: usize, window: &Window)
+//! where
+//!     R: Renderer + ImportAll,
+//!     R::TextureId: Clone + Texture + 'static,
+//! {
+//!     let mut capture = WindowCapture::new(WindowCaptureRegion::Geometry, 1.0);
+//!     // `framebuffer` needs to be of at least `capture.buffer_size(window)`
+//!     let result = capture
+//!         .render::<_, WaylandSurfaceRenderElement<R>>(renderer, framebuffer, age, window, &[], [0.0; 4])
+//!         .expect("failed to capture window");
+//!     // hand the damage in `result.damage` to the screencast consumer
+//! }
+//! ```
+
+use crate::{
+    backend::renderer::{
+        damage::{Error as OutputDamageTrackerError, OutputDamageTracker, RenderOutputResult},
+        element::{surface::WaylandSurfaceRenderElement, AsRenderElements, RenderElement},
+        Color32F, ImportAll, Renderer, Texture,
+    },
+    desktop::Window,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+/// Part of a window to capture
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WindowCaptureRegion {
+    /// The window geometry, excluding client-side shadows and popups
+    #[default]
+    Geometry,
+    /// The bounding box of the window, including client-side decorations, shadows and popups
+    BoundingBox,
+}
+
+crate::backend::renderer::element::render_elements! {
+    WindowCaptureRenderElements<'a, R, C> where
+        R: ImportAll;
+    Window=WaylandSurfaceRenderElement<R>,
+    Decoration=&'a C,
+}
+
+/// Damage tracked capture of a single [`Window`]
+#[derive(Debug)]
+pub struct WindowCapture {
+    region: WindowCaptureRegion,
+    scale: Scale<f64>,
+    damage_tracker: Option<(Size<i32, Physical>, OutputDamageTracker)>,
+}
+
+impl WindowCapture {
+    /// Create a new capture of the given region of a window at the given scale
+    pub fn new(region: WindowCaptureRegion, scale: impl Into<Scale<f64>>) -> WindowCapture {
+        WindowCapture {
+            region,
+            scale: scale.into(),
+            damage_tracker: None,
+        }
+    }
+
+    /// Returns the captured region
+    pub fn region(&self) -> WindowCaptureRegion {
+        self.region
+    }
+
+    /// Returns the scale the window is captured at
+    pub fn scale(&self) -> Scale<f64> {
+        self.scale
+    }
+
+    /// Change the scale the window is captured at
+    ///
+    /// This resets the damage tracking, the next capture will be fully damaged.
+    pub fn set_scale(&mut self, scale: impl Into<Scale<f64>>) {
+        self.scale = scale.into();
+        self.damage_tracker = None;
+    }
+
+    /// Returns the captured area of the window, relative to its surface origin
+    pub fn capture_area(&self, window: &Window) -> Rectangle<i32, Logical> {
+        match self.region {
+            WindowCaptureRegion::Geometry => window.geometry(),
+            WindowCaptureRegion::BoundingBox => window.bbox_with_popups(),
+        }
+    }
+
+    /// Returns the size of the framebuffer required to capture the window
+    ///
+    /// This changes as the window is resized, so it should be queried before every capture.
+    pub fn buffer_size(&self, window: &Window) -> Size<i32, Physical> {
+        self.capture_area(window)
+            .size
+            .to_physical_precise_round(self.scale)
+    }
+
+    /// Returns the location of the window's surface origin inside the captured framebuffer
+    ///
+    /// Useful to position compositor-side decorations relative to the window.
+    pub fn window_location(&self, window: &Window) -> Point<i32, Physical> {
+        let loc = self.capture_area(window).loc;
+        Point::<i32, Logical>::from((-loc.x, -loc.y)).to_physical_precise_round(self.scale)
+    }
+
+    /// Render the window into the given framebuffer
+    ///
+    /// The framebuffer has to be at least of [`buffer_size`](WindowCapture::buffer_size).
+    /// `decorations` are drawn below the window and should be positioned relative to
+    /// [`window_location`](WindowCapture::window_location), pass an empty slice to capture
+    /// the window without server-side decorations.
+    ///
+    /// Damage is tracked per capture, `age` is the age of the framebuffer as with
+    /// [`OutputDamageTracker::render_output`]. If the size of the window changed since the last
+    /// capture, the whole framebuffer is damaged.
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    pub fn render<'d, R, C>(
+        &'d mut self,
+        renderer: &mut R,
+        framebuffer: &mut R::Framebuffer<'_>,
+        age: usize,
+        window: &Window,
+        decorations: &[C],
+        clear_color: impl Into<Color32F>,
+    ) -> Result<RenderOutputResult<'d>, OutputDamageTrackerError<R::Error>>
+    where
+        R: Renderer + ImportAll,
+        R::TextureId: Clone + Texture + 'static,
+        C: RenderElement<R>,
+    {
+        let size = self.buffer_size(window);
+        let location = self.window_location(window);
+
+        let mut elements: Vec<WindowCaptureRenderElements<'_, R, C>> =
+            AsRenderElements::<R>::render_elements(window, renderer, location, self.scale, 1.0);
+        elements.extend(decorations.iter().map(WindowCaptureRenderElements::Decoration));
+
+        let damage_tracker = match &mut self.damage_tracker {
+            Some((tracked_size, damage_tracker)) if *tracked_size == size => damage_tracker,
+            damage_tracker => {
+                &mut damage_tracker
+                    .insert((
+                        size,
+                        OutputDamageTracker::new(size, self.scale, Transform::Normal),
+                    ))
+                    .1
+            }
+        };
+        damage_tracker.render_output(renderer, framebuffer, age, &elements, clear_color)
+    }
+}
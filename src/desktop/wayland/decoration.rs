@@ -0,0 +1,495 @@
+//! Server-side window decorations
+//!
+//! Toplevels negotiating [server-side decorations](crate::wayland::shell::xdg::decoration) expect the
+//! compositor to draw a titlebar and border around them. A [`ServerDecoration`] provides a simple
+//! implementation of such a frame built from [solid color elements](crate::backend::renderer::element::solid),
+//! consisting of a titlebar with close, maximize and minimize buttons, a border and a soft drop shadow.
+//!
+//! All coordinates are relative to the top-left corner of the window geometry, the decoration
+//! extends outwards from there. Use [`ServerDecoration::hit_test`] to map pointer input on the
+//! decoration to an [`DecorationHit`] and start the appropriate move or resize grab.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{element::AsRenderElements, test::DummyRenderer};
+//! use smithay::desktop::{
+//!     decoration::{DecorationHit, DecorationTheme, ServerDecoration},
+//!     Window,
+//! };
+//! # let window: Window = todo!();
+//! # let mut renderer = DummyRenderer::default();
+//!
+//! let mut decoration = ServerDecoration::new(DecorationTheme::default());
+//! if ServerDecoration::wants_server_side(&window) {
+//!     decoration.update(window.geometry().size, true);
+//!     let elements: Vec<_> = decoration.render_elements::<
+//!         smithay::backend::renderer::element::solid::SolidColorRenderElement,
+//!     >(&mut renderer, (100, 100).into(), 1.0.into(), 1.0);
+//! }
+//!
+//! match decoration.hit_test((20.0, -10.0).into()) {
+//!     Some(DecorationHit::Move) => { /* start a move grab */ }
+//!     Some(DecorationHit::Resize(_edges)) => { /* start a resize grab */ }
+//!     Some(DecorationHit::Close) => window.toplevel().unwrap().send_close(),
+//!     _ => {}
+//! }
+//! ```
+
+use wayland_protocols::xdg::{
+    decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+    shell::server::xdg_toplevel::ResizeEdge,
+};
+
+use crate::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            AsRenderElements, Kind,
+        },
+        Color32F, Renderer,
+    },
+    desktop::Window,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+/// Colors used to draw a decoration in a given activation state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecorationColors {
+    /// Background of the titlebar
+    pub titlebar: Color32F,
+    /// Color of the border around window and titlebar
+    pub border: Color32F,
+    /// Color of the close button
+    pub close_button: Color32F,
+    /// Color of the maximize button
+    pub maximize_button: Color32F,
+    /// Color of the minimize button
+    pub minimize_button: Color32F,
+    /// Color of the drop shadow at its darkest point
+    pub shadow: Color32F,
+}
+
+/// Theme of a [`ServerDecoration`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecorationTheme {
+    /// Height of the titlebar
+    pub titlebar_height: i32,
+    /// Width of the border around window and titlebar
+    pub border_width: i32,
+    /// Additional area outside of the border that still starts a resize
+    pub resize_margin: i32,
+    /// Size of the (square) titlebar buttons
+    pub button_size: i32,
+    /// Spacing between the titlebar buttons and towards the titlebar edges
+    pub button_spacing: i32,
+    /// Distance the shadow extends beyond the border, `0` disables the shadow
+    pub shadow_radius: i32,
+    /// Offset of the shadow relative to the window
+    pub shadow_offset: Point<i32, Logical>,
+    /// Colors used while the window is focused
+    pub active: DecorationColors,
+    /// Colors used while the window is not focused
+    pub inactive: DecorationColors,
+}
+
+impl Default for DecorationTheme {
+    fn default() -> Self {
+        DecorationTheme {
+            titlebar_height: 28,
+            border_width: 1,
+            resize_margin: 4,
+            button_size: 16,
+            button_spacing: 6,
+            shadow_radius: 12,
+            shadow_offset: (0, 4).into(),
+            active: DecorationColors {
+                titlebar: Color32F::new(0.2, 0.2, 0.22, 1.0),
+                border: Color32F::new(0.12, 0.12, 0.14, 1.0),
+                close_button: Color32F::new(0.88, 0.3, 0.26, 1.0),
+                maximize_button: Color32F::new(0.35, 0.75, 0.35, 1.0),
+                minimize_button: Color32F::new(0.95, 0.75, 0.25, 1.0),
+                shadow: Color32F::new(0.0, 0.0, 0.0, 0.45),
+            },
+            inactive: DecorationColors {
+                titlebar: Color32F::new(0.3, 0.3, 0.32, 1.0),
+                border: Color32F::new(0.22, 0.22, 0.24, 1.0),
+                close_button: Color32F::new(0.5, 0.5, 0.52, 1.0),
+                maximize_button: Color32F::new(0.5, 0.5, 0.52, 1.0),
+                minimize_button: Color32F::new(0.5, 0.5, 0.52, 1.0),
+                shadow: Color32F::new(0.0, 0.0, 0.0, 0.25),
+            },
+        }
+    }
+}
+
+/// Part of a decoration hit by [`ServerDecoration::hit_test`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationHit {
+    /// The titlebar, the window should be moved
+    Move,
+    /// The border, the window should be resized along the given edges
+    Resize(ResizeEdge),
+    /// The close button
+    Close,
+    /// The maximize button
+    Maximize,
+    /// The minimize button
+    Minimize,
+}
+
+/// Number of layers used to approximate a soft shadow
+const SHADOW_STEPS: i32 = 4;
+
+/// Server-side decoration of a single window
+#[derive(Debug, Clone)]
+pub struct ServerDecoration {
+    theme: DecorationTheme,
+    size: Size<i32, Logical>,
+    focused: bool,
+    titlebar: SolidColorBuffer,
+    borders: [SolidColorBuffer; 4],
+    buttons: [SolidColorBuffer; 3],
+    shadow: Vec<SolidColorBuffer>,
+}
+
+impl ServerDecoration {
+    /// Create a new decoration using the given theme
+    ///
+    /// The decoration has no size until [`ServerDecoration::update`] is called.
+    pub fn new(theme: DecorationTheme) -> Self {
+        let mut decoration = ServerDecoration {
+            theme,
+            size: Size::default(),
+            focused: false,
+            titlebar: SolidColorBuffer::default(),
+            borders: Default::default(),
+            buttons: Default::default(),
+            shadow: (0..SHADOW_STEPS).map(|_| SolidColorBuffer::default()).collect(),
+        };
+        decoration.redraw();
+        decoration
+    }
+
+    /// Returns whether the window negotiated server-side decorations
+    pub fn wants_server_side(window: &Window) -> bool {
+        window
+            .toplevel()
+            .map(|toplevel| {
+                toplevel.with_committed_state(|state| {
+                    state.and_then(|state| state.decoration_mode) == Some(DecorationMode::ServerSide)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Access the theme of this decoration
+    pub fn theme(&self) -> &DecorationTheme {
+        &self.theme
+    }
+
+    /// Change the theme of this decoration
+    pub fn set_theme(&mut self, theme: DecorationTheme) {
+        if self.theme != theme {
+            self.theme = theme;
+            self.redraw();
+        }
+    }
+
+    /// Update the decoration for the given window geometry size and focus state
+    ///
+    /// Only buffers actually affected by the change are damaged.
+    pub fn update(&mut self, size: impl Into<Size<i32, Logical>>, focused: bool) {
+        let size = size.into();
+        if self.size != size || self.focused != focused {
+            self.size = size;
+            self.focused = focused;
+            self.redraw();
+        }
+    }
+
+    /// Size of the window geometry the decoration is drawn around
+    pub fn window_size(&self) -> Size<i32, Logical> {
+        self.size
+    }
+
+    /// Offset of the window contents relative to the top-left corner of the decoration
+    ///
+    /// This excludes the shadow, which is not considered part of the window.
+    pub fn content_offset(&self) -> Point<i32, Logical> {
+        (
+            self.theme.border_width,
+            self.theme.border_width + self.theme.titlebar_height,
+        )
+            .into()
+    }
+
+    /// Geometry of the decorated window including titlebar and border, relative to the window geometry
+    pub fn frame_geometry(&self) -> Rectangle<i32, Logical> {
+        let b = self.theme.border_width;
+        let t = self.theme.titlebar_height;
+        Rectangle::new(
+            (-b, -t - b).into(),
+            (self.size.w + 2 * b, self.size.h + t + 2 * b).into(),
+        )
+    }
+
+    /// Geometry of the titlebar relative to the window geometry
+    pub fn titlebar_geometry(&self) -> Rectangle<i32, Logical> {
+        let t = self.theme.titlebar_height;
+        Rectangle::new((0, -t).into(), (self.size.w, t).into())
+    }
+
+    /// Geometry of the close, maximize and minimize buttons relative to the window geometry
+    pub fn button_geometries(&self) -> [(DecorationHit, Rectangle<i32, Logical>); 3] {
+        let size = self.theme.button_size;
+        let spacing = self.theme.button_spacing;
+        let y = -self.theme.titlebar_height + (self.theme.titlebar_height - size) / 2;
+        let button = |index: i32| {
+            Rectangle::new(
+                (self.size.w - (index + 1) * (size + spacing), y).into(),
+                (size, size).into(),
+            )
+        };
+        [
+            (DecorationHit::Close, button(0)),
+            (DecorationHit::Maximize, button(1)),
+            (DecorationHit::Minimize, button(2)),
+        ]
+    }
+
+    /// Test which part of the decoration is located at the given point relative to the window geometry
+    ///
+    /// Returns `None` for points inside the window contents or outside of the decoration.
+    pub fn hit_test(&self, point: Point<f64, Logical>) -> Option<DecorationHit> {
+        if let Some((hit, _)) = self
+            .button_geometries()
+            .into_iter()
+            .find(|(_, geo)| geo.to_f64().contains(point))
+        {
+            return Some(hit);
+        }
+        if self.titlebar_geometry().to_f64().contains(point) {
+            return Some(DecorationHit::Move);
+        }
+
+        let frame = self.frame_geometry();
+        let resize_area = grow(frame, self.theme.resize_margin.max(0)).to_f64();
+        if !resize_area.contains(point) || Rectangle::from_size(self.size).to_f64().contains(point) {
+            return None;
+        }
+
+        let frame = frame.to_f64();
+        let left = point.x < frame.loc.x + self.theme.border_width as f64;
+        let right = point.x >= frame.loc.x + frame.size.w - self.theme.border_width as f64;
+        let top = point.y < frame.loc.y + self.theme.border_width as f64;
+        let bottom = point.y >= frame.loc.y + frame.size.h - self.theme.border_width as f64;
+
+        let edge = match (top, bottom, left, right) {
+            (true, _, true, _) => ResizeEdge::TopLeft,
+            (true, _, _, true) => ResizeEdge::TopRight,
+            (_, true, true, _) => ResizeEdge::BottomLeft,
+            (_, true, _, true) => ResizeEdge::BottomRight,
+            (true, _, _, _) => ResizeEdge::Top,
+            (_, true, _, _) => ResizeEdge::Bottom,
+            (_, _, true, _) => ResizeEdge::Left,
+            (_, _, _, true) => ResizeEdge::Right,
+            _ => return None,
+        };
+        Some(DecorationHit::Resize(edge))
+    }
+
+    fn colors(&self) -> &DecorationColors {
+        if self.focused {
+            &self.theme.active
+        } else {
+            &self.theme.inactive
+        }
+    }
+
+    fn border_geometries(&self) -> [Rectangle<i32, Logical>; 4] {
+        let b = self.theme.border_width;
+        let t = self.theme.titlebar_height;
+        let frame = self.frame_geometry();
+        [
+            Rectangle::new(frame.loc, (frame.size.w, b).into()),
+            Rectangle::new((-b, self.size.h).into(), (frame.size.w, b).into()),
+            Rectangle::new((-b, -t).into(), (b, self.size.h + t).into()),
+            Rectangle::new((self.size.w, -t).into(), (b, self.size.h + t).into()),
+        ]
+    }
+
+    fn shadow_geometries(&self) -> impl Iterator<Item = Rectangle<i32, Logical>> + '_ {
+        let frame = self.frame_geometry();
+        let radius = self.theme.shadow_radius.max(0);
+        let offset = self.theme.shadow_offset;
+        (1..=SHADOW_STEPS).map(move |step| {
+            let mut geo = grow(frame, radius * step / SHADOW_STEPS);
+            geo.loc += offset;
+            geo
+        })
+    }
+
+    fn redraw(&mut self) {
+        let colors = *self.colors();
+
+        self.titlebar
+            .update(self.titlebar_geometry().size, colors.titlebar);
+        let borders = self.border_geometries();
+        for (buffer, geo) in self.borders.iter_mut().zip(borders) {
+            buffer.update(geo.size, colors.border);
+        }
+        let buttons = self.button_geometries();
+        let button_colors = [
+            colors.close_button,
+            colors.maximize_button,
+            colors.minimize_button,
+        ];
+        for ((buffer, (_, geo)), color) in self.buttons.iter_mut().zip(buttons).zip(button_colors) {
+            buffer.update(geo.size, color);
+        }
+
+        // Overlapping translucent layers darken towards the window, approximating a blurred shadow
+        let shadow_color = if self.theme.shadow_radius > 0 {
+            colors.shadow * (1.0 / SHADOW_STEPS as f32)
+        } else {
+            Color32F::TRANSPARENT
+        };
+        let shadows = self.shadow_geometries().collect::<Vec<_>>();
+        for (buffer, geo) in self.shadow.iter_mut().zip(shadows) {
+            buffer.update(geo.size, shadow_color);
+        }
+    }
+
+    fn element(
+        buffer: &SolidColorBuffer,
+        geo: Rectangle<i32, Logical>,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> SolidColorRenderElement {
+        SolidColorRenderElement::from_buffer(
+            buffer,
+            location + geo.loc.to_physical_precise_round(scale),
+            scale,
+            alpha,
+            Kind::Unspecified,
+        )
+    }
+}
+
+fn grow(rect: Rectangle<i32, Logical>, amount: i32) -> Rectangle<i32, Logical> {
+    Rectangle::new(
+        (rect.loc.x - amount, rect.loc.y - amount).into(),
+        (rect.size.w + 2 * amount, rect.size.h + 2 * amount).into(),
+    )
+}
+
+impl<R: Renderer> AsRenderElements<R> for ServerDecoration {
+    type RenderElement = SolidColorRenderElement;
+
+    /// Render the decoration with `location` being the position of the window geometry
+    ///
+    /// Elements are returned front to back, buttons first and the shadow last.
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        _renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        if self.size.is_empty() {
+            return Vec::new();
+        }
+
+        let mut elements = Vec::with_capacity(8 + self.shadow.len());
+        for (buffer, (_, geo)) in self.buttons.iter().zip(self.button_geometries()) {
+            elements.push(Self::element(buffer, geo, location, scale, alpha));
+        }
+        elements.push(Self::element(
+            &self.titlebar,
+            self.titlebar_geometry(),
+            location,
+            scale,
+            alpha,
+        ));
+        for (buffer, geo) in self.borders.iter().zip(self.border_geometries()) {
+            elements.push(Self::element(buffer, geo, location, scale, alpha));
+        }
+        if self.theme.shadow_radius > 0 {
+            for (buffer, geo) in self.shadow.iter().zip(self.shadow_geometries()) {
+                elements.push(Self::element(buffer, geo, location, scale, alpha));
+            }
+        }
+        elements.into_iter().map(C::from).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoration() -> ServerDecoration {
+        let mut decoration = ServerDecoration::new(DecorationTheme {
+            titlebar_height: 20,
+            border_width: 2,
+            resize_margin: 3,
+            button_size: 10,
+            button_spacing: 5,
+            ..Default::default()
+        });
+        decoration.update((200, 100), true);
+        decoration
+    }
+
+    #[test]
+    fn frame_geometry() {
+        let decoration = decoration();
+        assert_eq!(
+            decoration.frame_geometry(),
+            Rectangle::new((-2, -22).into(), (204, 124).into())
+        );
+        assert_eq!(decoration.content_offset(), Point::from((2, 22)));
+    }
+
+    #[test]
+    fn hit_buttons_and_titlebar() {
+        let decoration = decoration();
+        assert_eq!(
+            decoration.hit_test((190.0, -10.0).into()),
+            Some(DecorationHit::Close)
+        );
+        assert_eq!(
+            decoration.hit_test((175.0, -10.0).into()),
+            Some(DecorationHit::Maximize)
+        );
+        assert_eq!(
+            decoration.hit_test((160.0, -10.0).into()),
+            Some(DecorationHit::Minimize)
+        );
+        assert_eq!(
+            decoration.hit_test((50.0, -10.0).into()),
+            Some(DecorationHit::Move)
+        );
+    }
+
+    #[test]
+    fn hit_resize_edges() {
+        let decoration = decoration();
+        let resize = |x: f64, y: f64| match decoration.hit_test((x, y).into()) {
+            Some(DecorationHit::Resize(edge)) => Some(edge),
+            _ => None,
+        };
+        assert_eq!(resize(-3.0, -23.0), Some(ResizeEdge::TopLeft));
+        assert_eq!(resize(100.0, -23.0), Some(ResizeEdge::Top));
+        assert_eq!(resize(201.0, 50.0), Some(ResizeEdge::Right));
+        assert_eq!(resize(-1.0, 101.0), Some(ResizeEdge::BottomLeft));
+        assert_eq!(resize(100.0, 104.0), Some(ResizeEdge::Bottom));
+    }
+
+    #[test]
+    fn no_hit_outside() {
+        let decoration = decoration();
+        assert_eq!(decoration.hit_test((50.0, 50.0).into()), None);
+        assert_eq!(decoration.hit_test((-10.0, 50.0).into()), None);
+        assert_eq!(decoration.hit_test((100.0, 110.0).into()), None);
+    }
+}
@@ -0,0 +1,821 @@
+//! Interactive move and resize grabs
+//!
+//! When a client requests an interactive move or resize of its toplevel, e.g. because the user
+//! dragged its titlebar, the compositor starts a pointer grab moving or resizing the window until
+//! the button is released. [`MoveGrab`] and [`ResizeGrab`] implement these grabs for [`Window`]s
+//! mapped in a [`Space`].
+//!
+//! Both grabs optionally snap the window to the edges of outputs and of other windows and resist
+//! moving windows over the edge of an output, configured by [`Snapping`]. Resizing honors the
+//! minimum and maximum size of the window and an optional aspect ratio.
+//!
+//! ```no_run
+//! use smithay::desktop::{
+//!     grabs::{MoveGrab, MoveResizeHandler, Snapping},
+//!     Space, Window,
+//! };
+//! use smithay::input::pointer::{Focus, GrabStartData};
+//! # use smithay::input::{Seat, SeatHandler, SeatState};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//!
+//! struct State {
+//!     space: Space<Window>,
+//! }
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     type TouchFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { unimplemented!() }
+//! # }
+//!
+//! impl MoveResizeHandler for State {
+//!     fn grab_space(&mut self, _window: &Window) -> Option<&mut Space<Window>> {
+//!         Some(&mut self.space)
+//!     }
+//! }
+//!
+//! # let mut state: State = todo!();
+//! # let seat: Seat<State> = todo!();
+//! # let window: Window = todo!();
+//! # let start_data: GrabStartData<State> = todo!();
+//! # let serial = smithay::utils::SERIAL_COUNTER.next_serial();
+//! // on xdg_toplevel.move
+//! let location = state.space.element_location(&window).unwrap();
+//! let grab = MoveGrab::new(start_data, window, location, Snapping::default());
+//! let pointer = seat.get_pointer().unwrap();
+//! pointer.set_grab(&mut state, grab, serial, Focus::Clear);
+//! ```
+//!
+//! Windows resized from their top or left edge have to be moved once the client committed its
+//! new size, so the opposite edge stays in place. Call [`handle_resize_commit`] on every commit
+//! of a toplevel to do so.
+
+use std::{fmt, sync::Mutex};
+
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use crate::{
+    desktop::{Space, Window},
+    input::{
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+            GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+            GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
+            RelativeMotionEvent,
+        },
+        SeatHandler,
+    },
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
+    wayland::{compositor::with_states, shell::xdg::SurfaceCachedState},
+};
+
+/// Compositor state supporting [`MoveGrab`] and [`ResizeGrab`]
+pub trait MoveResizeHandler: SeatHandler + Sized + 'static {
+    /// Returns the space the grabbed window is mapped in
+    fn grab_space(&mut self, window: &Window) -> Option<&mut Space<Window>>;
+}
+
+/// Snapping of windows during interactive grabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapping {
+    /// Distance at which window edges snap to edges of outputs and other windows, `0` disables
+    /// snapping
+    pub threshold: i32,
+    /// Distance the pointer has to be moved further to move a window over the edge of the output
+    /// it is on, `0` disables resistance
+    pub output_resistance: i32,
+    /// Whether windows snap to the edges of other windows in addition to outputs
+    pub snap_to_windows: bool,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Snapping {
+            threshold: 10,
+            output_resistance: 20,
+            snap_to_windows: true,
+        }
+    }
+}
+
+impl Snapping {
+    /// Snapping and resistance disabled
+    pub fn disabled() -> Self {
+        Snapping {
+            threshold: 0,
+            output_resistance: 0,
+            snap_to_windows: false,
+        }
+    }
+
+    /// Returns the snapped location of a window moved to `geometry`
+    ///
+    /// `outputs` and `windows` are the geometries of the outputs and the other windows in the
+    /// same space.
+    pub fn snap_location(
+        &self,
+        geometry: Rectangle<i32, Logical>,
+        outputs: &[Rectangle<i32, Logical>],
+        windows: &[Rectangle<i32, Logical>],
+    ) -> Point<i32, Logical> {
+        let center = geometry.loc + geometry.size.downscale(2).to_point();
+        let mut x = AxisSnap::default();
+        let mut y = AxisSnap::default();
+
+        for output in outputs {
+            // resist moving the window off the output it is mostly on
+            let resistance = if output.contains(center) {
+                self.threshold.max(self.output_resistance)
+            } else {
+                self.threshold
+            };
+            let to_left = output.loc.x - geometry.loc.x;
+            let to_right = right(output) - right(&geometry);
+            x.consider(to_left, if to_left > 0 { resistance } else { self.threshold });
+            x.consider(to_right, if to_right < 0 { resistance } else { self.threshold });
+            let to_top = output.loc.y - geometry.loc.y;
+            let to_bottom = bottom(output) - bottom(&geometry);
+            y.consider(to_top, if to_top > 0 { resistance } else { self.threshold });
+            y.consider(to_bottom, if to_bottom < 0 { resistance } else { self.threshold });
+        }
+
+        if self.snap_to_windows {
+            for window in windows {
+                if overlaps(geometry.loc.y, bottom(&geometry), window.loc.y, bottom(window)) {
+                    x.consider(right(window) - geometry.loc.x, self.threshold);
+                    x.consider(window.loc.x - right(&geometry), self.threshold);
+                }
+                if overlaps(geometry.loc.x, right(&geometry), window.loc.x, right(window)) {
+                    y.consider(bottom(window) - geometry.loc.y, self.threshold);
+                    y.consider(window.loc.y - bottom(&geometry), self.threshold);
+                }
+            }
+        }
+
+        geometry.loc + Point::from((x.delta(), y.delta()))
+    }
+
+    /// Returns the geometry of a window resized to `geometry` with the moving edges snapped
+    pub fn snap_edges(
+        &self,
+        geometry: Rectangle<i32, Logical>,
+        edges: ResizeEdge,
+        outputs: &[Rectangle<i32, Logical>],
+        windows: &[Rectangle<i32, Logical>],
+    ) -> Rectangle<i32, Logical> {
+        let mut targets_x = Vec::new();
+        let mut targets_y = Vec::new();
+        for output in outputs {
+            targets_x.extend([output.loc.x, right(output)]);
+            targets_y.extend([output.loc.y, bottom(output)]);
+        }
+        if self.snap_to_windows {
+            for window in windows {
+                if overlaps(geometry.loc.y, bottom(&geometry), window.loc.y, bottom(window)) {
+                    targets_x.extend([window.loc.x, right(window)]);
+                }
+                if overlaps(geometry.loc.x, right(&geometry), window.loc.x, right(window)) {
+                    targets_y.extend([window.loc.y, bottom(window)]);
+                }
+            }
+        }
+
+        let snap = |edge: i32, targets: &[i32]| {
+            let mut axis = AxisSnap::default();
+            for target in targets {
+                axis.consider(target - edge, self.threshold);
+            }
+            edge + axis.delta()
+        };
+
+        let (mut x1, mut y1) = (geometry.loc.x, geometry.loc.y);
+        let (mut x2, mut y2) = (right(&geometry), bottom(&geometry));
+        if edges.contains(ResizeEdge::LEFT) {
+            x1 = snap(x1, &targets_x);
+        } else if edges.contains(ResizeEdge::RIGHT) {
+            x2 = snap(x2, &targets_x);
+        }
+        if edges.contains(ResizeEdge::TOP) {
+            y1 = snap(y1, &targets_y);
+        } else if edges.contains(ResizeEdge::BOTTOM) {
+            y2 = snap(y2, &targets_y);
+        }
+        Rectangle::new((x1, y1).into(), (x2 - x1, y2 - y1).into())
+    }
+}
+
+#[derive(Debug, Default)]
+struct AxisSnap(Option<i32>);
+
+impl AxisSnap {
+    fn consider(&mut self, delta: i32, limit: i32) {
+        if delta.abs() <= limit && self.0.is_none_or(|best| delta.abs() < best.abs()) {
+            self.0 = Some(delta);
+        }
+    }
+
+    fn delta(&self) -> i32 {
+        self.0.unwrap_or(0)
+    }
+}
+
+fn right(rect: &Rectangle<i32, Logical>) -> i32 {
+    rect.loc.x + rect.size.w
+}
+
+fn bottom(rect: &Rectangle<i32, Logical>) -> i32 {
+    rect.loc.y + rect.size.h
+}
+
+fn overlaps(start1: i32, end1: i32, start2: i32, end2: i32) -> bool {
+    start1 < end2 && start2 < end1
+}
+
+bitflags::bitflags! {
+    /// Edges of a window moved by a [`ResizeGrab`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ResizeEdge: u32 {
+        /// The top edge
+        const TOP = 1;
+        /// The bottom edge
+        const BOTTOM = 2;
+        /// The left edge
+        const LEFT = 4;
+        /// The top left corner
+        const TOP_LEFT = Self::TOP.bits() | Self::LEFT.bits();
+        /// The bottom left corner
+        const BOTTOM_LEFT = Self::BOTTOM.bits() | Self::LEFT.bits();
+        /// The right edge
+        const RIGHT = 8;
+        /// The top right corner
+        const TOP_RIGHT = Self::TOP.bits() | Self::RIGHT.bits();
+        /// The bottom right corner
+        const BOTTOM_RIGHT = Self::BOTTOM.bits() | Self::RIGHT.bits();
+    }
+}
+
+impl From<xdg_toplevel::ResizeEdge> for ResizeEdge {
+    fn from(edge: xdg_toplevel::ResizeEdge) -> Self {
+        ResizeEdge::from_bits_truncate(edge as u32)
+    }
+}
+
+/// Limits of the size of a window during a [`ResizeGrab`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResizeConstraints {
+    /// Minimum size, `0` means unlimited
+    pub min_size: Size<i32, Logical>,
+    /// Maximum size, `0` means unlimited
+    pub max_size: Size<i32, Logical>,
+    /// Ratio of width to height to keep
+    pub aspect_ratio: Option<f64>,
+}
+
+impl ResizeConstraints {
+    /// Read the minimum and maximum size of a window
+    pub fn from_window(window: &Window) -> Self {
+        if let Some(toplevel) = window.toplevel() {
+            let (min_size, max_size) = with_states(toplevel.wl_surface(), |states| {
+                let mut guard = states.cached_state.get::<SurfaceCachedState>();
+                let current = guard.current();
+                (current.min_size, current.max_size)
+            });
+            return ResizeConstraints {
+                min_size,
+                max_size,
+                aspect_ratio: None,
+            };
+        }
+
+        #[cfg(feature = "xwayland")]
+        if let Some(surface) = window.x11_surface() {
+            return ResizeConstraints {
+                min_size: surface.min_size().unwrap_or_default(),
+                max_size: surface.max_size().unwrap_or_default(),
+                aspect_ratio: None,
+            };
+        }
+
+        ResizeConstraints::default()
+    }
+
+    /// Returns the size clamped to the limits
+    ///
+    /// The aspect ratio is kept by adjusting the height, unless only the top or bottom edge is
+    /// moved. The minimum and maximum size take precedence over the aspect ratio.
+    pub fn constrain(&self, size: Size<i32, Logical>, edges: ResizeEdge) -> Size<i32, Logical> {
+        let (mut w, mut h) = (size.w, size.h);
+        if let Some(ratio) = self.aspect_ratio.filter(|ratio| *ratio > 0.0) {
+            if edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
+                h = (w as f64 / ratio).round() as i32;
+            } else {
+                w = (h as f64 * ratio).round() as i32;
+            }
+        }
+
+        let clamp = |value: i32, min: i32, max: i32| {
+            let value = value.max(min.max(1));
+            if max > 0 {
+                value.min(max.max(min))
+            } else {
+                value
+            }
+        };
+        (
+            clamp(w, self.min_size.w, self.max_size.w),
+            clamp(h, self.min_size.h, self.max_size.h),
+        )
+            .into()
+    }
+
+    /// Returns the geometry of a window resized by moving `edges` by `delta`
+    pub fn resize(
+        &self,
+        initial: Rectangle<i32, Logical>,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> Rectangle<i32, Logical> {
+        let mut geometry = initial;
+        if edges.contains(ResizeEdge::LEFT) {
+            geometry.loc.x += delta.x;
+            geometry.size.w -= delta.x;
+        } else if edges.contains(ResizeEdge::RIGHT) {
+            geometry.size.w += delta.x;
+        }
+        if edges.contains(ResizeEdge::TOP) {
+            geometry.loc.y += delta.y;
+            geometry.size.h -= delta.y;
+        } else if edges.contains(ResizeEdge::BOTTOM) {
+            geometry.size.h += delta.y;
+        }
+        self.fit(initial, geometry, edges)
+    }
+
+    // Constrains the size and moves the geometry so the edges opposite to `edges` stay in place
+    fn fit(
+        &self,
+        initial: Rectangle<i32, Logical>,
+        geometry: Rectangle<i32, Logical>,
+        edges: ResizeEdge,
+    ) -> Rectangle<i32, Logical> {
+        let size = self.constrain(geometry.size, edges);
+        let mut loc = initial.loc;
+        if edges.contains(ResizeEdge::LEFT) {
+            loc.x = right(&initial) - size.w;
+        }
+        if edges.contains(ResizeEdge::TOP) {
+            loc.y = bottom(&initial) - size.h;
+        }
+        Rectangle::new(loc, size)
+    }
+}
+
+fn snap_targets(
+    space: &Space<Window>,
+    window: &Window,
+) -> (Vec<Rectangle<i32, Logical>>, Vec<Rectangle<i32, Logical>>) {
+    let outputs = space
+        .outputs()
+        .filter_map(|output| space.output_geometry(output))
+        .collect();
+    let windows = space
+        .elements()
+        .filter(|w| *w != window)
+        .filter_map(|w| space.element_geometry(w))
+        .collect();
+    (outputs, windows)
+}
+
+macro_rules! forward_pointer_events {
+    () => {
+        fn relative_motion(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            _focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+            event: &RelativeMotionEvent,
+        ) {
+            handle.relative_motion(data, None, event);
+        }
+
+        fn axis(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, details: AxisFrame) {
+            handle.axis(data, details);
+        }
+
+        fn frame(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>) {
+            handle.frame(data);
+        }
+
+        fn gesture_swipe_begin(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GestureSwipeBeginEvent,
+        ) {
+            handle.gesture_swipe_begin(data, event);
+        }
+
+        fn gesture_swipe_update(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GestureSwipeUpdateEvent,
+        ) {
+            handle.gesture_swipe_update(data, event);
+        }
+
+        fn gesture_swipe_end(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GestureSwipeEndEvent,
+        ) {
+            handle.gesture_swipe_end(data, event);
+        }
+
+        fn gesture_pinch_begin(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GesturePinchBeginEvent,
+        ) {
+            handle.gesture_pinch_begin(data, event);
+        }
+
+        fn gesture_pinch_update(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GesturePinchUpdateEvent,
+        ) {
+            handle.gesture_pinch_update(data, event);
+        }
+
+        fn gesture_pinch_end(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GesturePinchEndEvent,
+        ) {
+            handle.gesture_pinch_end(data, event);
+        }
+
+        fn gesture_hold_begin(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GestureHoldBeginEvent,
+        ) {
+            handle.gesture_hold_begin(data, event);
+        }
+
+        fn gesture_hold_end(
+            &mut self,
+            data: &mut D,
+            handle: &mut PointerInnerHandle<'_, D>,
+            event: &GestureHoldEndEvent,
+        ) {
+            handle.gesture_hold_end(data, event);
+        }
+
+        fn start_data(&self) -> &GrabStartData<D> {
+            &self.start_data
+        }
+    };
+}
+
+/// Pointer grab moving a window until all buttons are released
+pub struct MoveGrab<D: SeatHandler> {
+    start_data: GrabStartData<D>,
+    window: Window,
+    initial_location: Point<i32, Logical>,
+    snapping: Snapping,
+}
+
+impl<D: SeatHandler + 'static> fmt::Debug for MoveGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MoveGrab")
+            .field("start_data", &self.start_data)
+            .field("window", &self.window)
+            .field("initial_location", &self.initial_location)
+            .field("snapping", &self.snapping)
+            .finish()
+    }
+}
+
+impl<D: MoveResizeHandler> MoveGrab<D> {
+    /// Create a grab moving `window`, which is mapped at `initial_location`
+    pub fn new(
+        start_data: GrabStartData<D>,
+        window: Window,
+        initial_location: Point<i32, Logical>,
+        snapping: Snapping,
+    ) -> Self {
+        MoveGrab {
+            start_data,
+            window,
+            initial_location,
+            snapping,
+        }
+    }
+
+    /// Returns the moved window
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+}
+
+impl<D: MoveResizeHandler> PointerGrab<D> for MoveGrab<D> {
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // no client has pointer focus while the window is moved
+        handle.motion(data, None, event);
+
+        let delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        let Some(space) = data.grab_space(&self.window) else {
+            return;
+        };
+        let geometry = Rectangle::new(self.initial_location + delta, self.window.geometry().size);
+        let (outputs, windows) = snap_targets(space, &self.window);
+        let location = self.snapping.snap_location(geometry, &outputs, &windows);
+        space.map_element(self.window.clone(), location, true);
+    }
+
+    fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    forward_pointer_events!();
+
+    fn unset(&mut self, _data: &mut D) {}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum ResizeState {
+    #[default]
+    Idle,
+    Resizing {
+        edges: ResizeEdge,
+        initial: Rectangle<i32, Logical>,
+    },
+    WaitingForLastCommit {
+        edges: ResizeEdge,
+        initial: Rectangle<i32, Logical>,
+    },
+}
+
+#[derive(Debug, Default)]
+struct ResizeData(Mutex<ResizeState>);
+
+fn set_resize_state(window: &Window, state: ResizeState) {
+    let data = window.user_data().get_or_insert_threadsafe(ResizeData::default);
+    *data.0.lock().unwrap() = state;
+}
+
+/// Pointer grab resizing a window until all buttons are released
+pub struct ResizeGrab<D: SeatHandler> {
+    start_data: GrabStartData<D>,
+    window: Window,
+    edges: ResizeEdge,
+    initial_geometry: Rectangle<i32, Logical>,
+    constraints: ResizeConstraints,
+    snapping: Snapping,
+}
+
+impl<D: SeatHandler + 'static> fmt::Debug for ResizeGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResizeGrab")
+            .field("start_data", &self.start_data)
+            .field("window", &self.window)
+            .field("edges", &self.edges)
+            .field("initial_geometry", &self.initial_geometry)
+            .field("constraints", &self.constraints)
+            .field("snapping", &self.snapping)
+            .finish()
+    }
+}
+
+impl<D: MoveResizeHandler> ResizeGrab<D> {
+    /// Create a grab resizing `window` with the geometry `initial_geometry` in its space
+    ///
+    /// The size limits are read from the window, see [`ResizeConstraints::from_window`].
+    pub fn new(
+        start_data: GrabStartData<D>,
+        window: Window,
+        edges: impl Into<ResizeEdge>,
+        initial_geometry: Rectangle<i32, Logical>,
+        snapping: Snapping,
+    ) -> Self {
+        let edges = edges.into();
+        let constraints = ResizeConstraints::from_window(&window);
+
+        if let Some(toplevel) = window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::Resizing);
+            });
+            toplevel.send_pending_configure();
+        }
+        set_resize_state(
+            &window,
+            ResizeState::Resizing {
+                edges,
+                initial: initial_geometry,
+            },
+        );
+
+        ResizeGrab {
+            start_data,
+            window,
+            edges,
+            initial_geometry,
+            constraints,
+            snapping,
+        }
+    }
+
+    /// Keep the given ratio of width to height while resizing
+    pub fn with_aspect_ratio(mut self, ratio: f64) -> Self {
+        self.constraints.aspect_ratio = Some(ratio);
+        self
+    }
+
+    /// Returns the resized window
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+}
+
+impl<D: MoveResizeHandler> PointerGrab<D> for ResizeGrab<D> {
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // no client has pointer focus while the window is resized
+        handle.motion(data, None, event);
+
+        if !self.window.alive() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+            return;
+        }
+
+        let delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        let mut geometry = self.constraints.resize(self.initial_geometry, self.edges, delta);
+        if let Some(space) = data.grab_space(&self.window) {
+            let (outputs, windows) = snap_targets(space, &self.window);
+            let snapped = self.snapping.snap_edges(geometry, self.edges, &outputs, &windows);
+            geometry = self.constraints.fit(self.initial_geometry, snapped, self.edges);
+
+            #[cfg(feature = "xwayland")]
+            if let Some(surface) = self.window.x11_surface() {
+                // X11 windows are configured with their position, no need to wait for a commit
+                let _ = surface.configure(geometry);
+                space.map_element(self.window.clone(), geometry.loc, true);
+            }
+        }
+
+        if let Some(toplevel) = self.window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.size = Some(geometry.size);
+            });
+            toplevel.send_pending_configure();
+        }
+    }
+
+    fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    forward_pointer_events!();
+
+    fn unset(&mut self, _data: &mut D) {
+        if let Some(toplevel) = self.window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Resizing);
+            });
+            toplevel.send_pending_configure();
+        }
+        set_resize_state(
+            &self.window,
+            ResizeState::WaitingForLastCommit {
+                edges: self.edges,
+                initial: self.initial_geometry,
+            },
+        );
+    }
+}
+
+/// Keep the edges opposite to the resized ones in place after a commit of a window
+///
+/// Has to be called on every commit of a toplevel resized by a [`ResizeGrab`]. Does nothing for
+/// other windows.
+pub fn handle_resize_commit(space: &mut Space<Window>, window: &Window) {
+    let Some(data) = window.user_data().get::<ResizeData>() else {
+        return;
+    };
+    let mut state = data.0.lock().unwrap();
+    let (edges, initial) = match *state {
+        ResizeState::Idle => return,
+        ResizeState::Resizing { edges, initial } => (edges, initial),
+        ResizeState::WaitingForLastCommit { edges, initial } => {
+            *state = ResizeState::Idle;
+            (edges, initial)
+        }
+    };
+    drop(state);
+
+    if !edges.intersects(ResizeEdge::TOP_LEFT) {
+        return;
+    }
+    let Some(mut location) = space.element_location(window) else {
+        return;
+    };
+    let size = window.geometry().size;
+    if edges.contains(ResizeEdge::LEFT) {
+        location.x = right(&initial) - size.w;
+    }
+    if edges.contains(ResizeEdge::TOP) {
+        location.y = bottom(&initial) - size.h;
+    }
+    space.map_element(window.clone(), location, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::new((x, y).into(), (w, h).into())
+    }
+
+    #[test]
+    fn snaps_and_resists() {
+        let snapping = Snapping {
+            threshold: 10,
+            output_resistance: 30,
+            snap_to_windows: true,
+        };
+        let outputs = [rect(0, 0, 1000, 800)];
+        let windows = [rect(500, 100, 200, 200)];
+
+        // snaps the left edge to the output
+        assert_eq!(
+            snapping.snap_location(rect(8, 400, 100, 100), &outputs, &windows),
+            (0, 400).into()
+        );
+        // resists leaving the output
+        assert_eq!(
+            snapping.snap_location(rect(-25, 400, 100, 100), &outputs, &windows),
+            (0, 400).into()
+        );
+        assert_eq!(
+            snapping.snap_location(rect(-40, 400, 100, 100), &outputs, &windows),
+            (-40, 400).into()
+        );
+        // snaps to the left edge of the other window
+        assert_eq!(
+            snapping.snap_location(rect(395, 150, 100, 100), &outputs, &windows),
+            (400, 150).into()
+        );
+        assert_eq!(
+            Snapping::disabled().snap_location(rect(-5, 400, 100, 100), &outputs, &windows),
+            (-5, 400).into()
+        );
+    }
+
+    #[test]
+    fn resize_constraints() {
+        let constraints = ResizeConstraints {
+            min_size: (100, 50).into(),
+            max_size: (400, 0).into(),
+            aspect_ratio: None,
+        };
+        let initial = rect(100, 100, 200, 200);
+
+        let geometry = constraints.resize(initial, ResizeEdge::TOP_LEFT, (150, -50).into());
+        assert_eq!(geometry, rect(200, 50, 100, 250));
+
+        let geometry = constraints.resize(initial, ResizeEdge::RIGHT, (500, 0).into());
+        assert_eq!(geometry, rect(100, 100, 400, 200));
+
+        let constraints = ResizeConstraints {
+            aspect_ratio: Some(2.0),
+            ..Default::default()
+        };
+        let geometry = constraints.resize(initial, ResizeEdge::BOTTOM_RIGHT, (100, 0).into());
+        assert_eq!(geometry.size, (300, 150).into());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     desktop::{utils::*, PopupManager},
     output::{Output, WeakOutput},
-    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle, Size},
+    utils::{user_data::UserDataMap, IsAlive, Logical, Monotonic, Point, Rectangle, Size, Time},
     wayland::{
         compositor::{with_states, with_surface_tree_downward, SurfaceData, TraversalAction},
         dmabuf::DmabufFeedback,
@@ -649,6 +649,46 @@ impl LayerSurface {
         }
     }
 
+    /// Releases held fifo barriers and due commit timers of this layer surface and its popups
+    ///
+    /// See [`signal_frame_barriers_surface_tree`] for more information
+    pub fn signal_frame_barriers<T, F>(
+        &self,
+        output: &Output,
+        frame_target: T,
+        primary_scan_out_output: F,
+    ) -> bool
+    where
+        T: Into<Time<Monotonic>>,
+        F: FnMut(&WlSurface, &SurfaceData) -> Option<Output> + Copy,
+    {
+        let frame_target = frame_target.into();
+        let surface = self.0.surface.wl_surface();
+
+        let mut signaled =
+            signal_frame_barriers_surface_tree(surface, output, frame_target, primary_scan_out_output);
+        for (popup, _) in PopupManager::popups_for_surface(surface) {
+            let surface = popup.wl_surface();
+            signaled |=
+                signal_frame_barriers_surface_tree(surface, output, frame_target, primary_scan_out_output);
+        }
+        signaled
+    }
+
+    /// Returns the earliest presentation time targeted by a held commit of this layer surface or its popups
+    ///
+    /// See [`next_commit_deadline_surface_tree`] for more information
+    pub fn next_commit_deadline(&self) -> Option<Time<Monotonic>> {
+        let surface = self.0.surface.wl_surface();
+        std::iter::once(next_commit_deadline_surface_tree(surface))
+            .chain(
+                PopupManager::popups_for_surface(surface)
+                    .map(|(popup, _)| next_commit_deadline_surface_tree(popup.wl_surface())),
+            )
+            .flatten()
+            .min()
+    }
+
     /// Sends the dmabuf feedback to all the subsurfaces in this window that requested it
     ///
     /// See [`send_dmabuf_feedback_surface_tree`] for more information
@@ -0,0 +1,321 @@
+//! Window rules
+//!
+//! Compositors commonly let users configure how certain applications are placed when they open:
+//! on which workspace, at which size, floating or tiled. [`WindowRules`] holds a list of
+//! [`WindowRule`]s, each matching toplevels by their app id and title and carrying the
+//! [`RuleActions`] to apply to them. The compositor builds the rules from its own configuration
+//! format and resolves them when a new toplevel is mapped.
+//!
+//! All rules matching a toplevel are applied in order, so actions set by later rules override
+//! the ones of earlier rules.
+//!
+//! ```no_run
+//! use smithay::desktop::{
+//!     rules::{Layout, RuleActions, RuleMatch, StringMatch, WindowRule, WindowRules},
+//!     Window,
+//! };
+//!
+//! let mut rules = WindowRules::new();
+//! rules.push(WindowRule {
+//!     matches: RuleMatch {
+//!         app_id: Some(StringMatch::Exact("org.gnome.Calculator".into())),
+//!         ..Default::default()
+//!     },
+//!     actions: RuleActions {
+//!         layout: Some(Layout::Floating),
+//!         size: Some((400, 600).into()),
+//!         ..Default::default()
+//!     },
+//! });
+//!
+//! # let window: Window = todo!();
+//! // when the toplevel is mapped for the first time
+//! let actions = rules.apply(&window);
+//! if let Some(workspace) = &actions.workspace {
+//!     // map the window on the named workspace
+//! }
+//! ```
+
+use std::sync::Mutex;
+
+use crate::{
+    desktop::Window,
+    utils::{Logical, Point, Size},
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceData},
+};
+
+/// How a string property of a toplevel is matched
+#[derive(Debug, Clone)]
+pub enum StringMatch {
+    /// The property equals the string
+    Exact(String),
+    /// The property starts with the string
+    Prefix(String),
+    /// The property contains the string
+    Contains(String),
+    /// The property matches the regular expression
+    ///
+    /// Requires the `regex` feature.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl StringMatch {
+    /// Returns whether the given value matches
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            StringMatch::Exact(s) => value == s,
+            StringMatch::Prefix(s) => value.starts_with(s.as_str()),
+            StringMatch::Contains(s) => value.contains(s.as_str()),
+            #[cfg(feature = "regex")]
+            StringMatch::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Conditions of a [`WindowRule`]
+///
+/// A toplevel matches if it matches all conditions that are set. A toplevel without an app id or
+/// title never matches a condition on it.
+#[derive(Debug, Clone, Default)]
+pub struct RuleMatch {
+    /// Condition on the app id, or the class of X11 windows
+    pub app_id: Option<StringMatch>,
+    /// Condition on the title
+    pub title: Option<StringMatch>,
+}
+
+impl RuleMatch {
+    /// Returns whether the given properties match
+    pub fn matches(&self, properties: &WindowProperties) -> bool {
+        fn matches(condition: &Option<StringMatch>, value: &Option<String>) -> bool {
+            match (condition, value) {
+                (None, _) => true,
+                (Some(condition), Some(value)) => condition.matches(value),
+                (Some(_), None) => false,
+            }
+        }
+
+        matches(&self.app_id, &properties.app_id) && matches(&self.title, &properties.title)
+    }
+}
+
+/// Whether a toplevel is floating or tiled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// The toplevel is placed freely
+    Floating,
+    /// The toplevel is managed by the tiling layout
+    Tiled,
+}
+
+/// Actions of a [`WindowRule`]
+///
+/// Unset actions leave the decision to the compositor or to later rules.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleActions {
+    /// Initial position, relative to the output the toplevel is opened on
+    pub position: Option<Point<i32, Logical>>,
+    /// Initial size
+    pub size: Option<Size<i32, Logical>>,
+    /// Name of the workspace to open the toplevel on
+    pub workspace: Option<String>,
+    /// Whether the toplevel is floating or tiled
+    pub layout: Option<Layout>,
+    /// Opacity between `0.0` and `1.0`
+    pub opacity: Option<f32>,
+}
+
+impl RuleActions {
+    /// Override the actions with all actions set in `other`
+    pub fn merge(&mut self, other: &RuleActions) {
+        if other.position.is_some() {
+            self.position = other.position;
+        }
+        if other.size.is_some() {
+            self.size = other.size;
+        }
+        if other.workspace.is_some() {
+            self.workspace.clone_from(&other.workspace);
+        }
+        if other.layout.is_some() {
+            self.layout = other.layout;
+        }
+        if let Some(opacity) = other.opacity {
+            self.opacity = Some(opacity.clamp(0.0, 1.0));
+        }
+    }
+}
+
+/// A rule applying actions to matching toplevels
+#[derive(Debug, Clone, Default)]
+pub struct WindowRule {
+    /// Conditions a toplevel has to match
+    pub matches: RuleMatch,
+    /// Actions applied to matching toplevels
+    pub actions: RuleActions,
+}
+
+/// Properties of a toplevel rules are matched against
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowProperties {
+    /// The app id, or the class of X11 windows
+    pub app_id: Option<String>,
+    /// The title
+    pub title: Option<String>,
+}
+
+impl WindowProperties {
+    /// Read the properties of a window
+    pub fn from_window(window: &Window) -> Self {
+        if let Some(toplevel) = window.toplevel() {
+            return with_states(toplevel.wl_surface(), |states| {
+                let attributes = states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap();
+                WindowProperties {
+                    app_id: attributes.app_id.clone(),
+                    title: attributes.title.clone(),
+                }
+            });
+        }
+
+        #[cfg(feature = "xwayland")]
+        if let Some(surface) = window.x11_surface() {
+            let non_empty = |s: String| (!s.is_empty()).then_some(s);
+            return WindowProperties {
+                app_id: non_empty(surface.class()),
+                title: non_empty(surface.title()),
+            };
+        }
+
+        WindowProperties::default()
+    }
+}
+
+/// Actions applied to a window by [`WindowRules::apply`]
+#[derive(Debug, Default)]
+struct AppliedRules(Mutex<RuleActions>);
+
+/// An ordered list of [`WindowRule`]s
+#[derive(Debug, Clone, Default)]
+pub struct WindowRules {
+    rules: Vec<WindowRule>,
+}
+
+impl WindowRules {
+    /// Create an empty list of rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule, taking precedence over all previous rules
+    pub fn push(&mut self, rule: WindowRule) {
+        self.rules.push(rule);
+    }
+
+    /// Returns the rules in order
+    pub fn rules(&self) -> &[WindowRule] {
+        &self.rules
+    }
+
+    /// Remove all rules, e.g. before reloading the configuration
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Returns the merged actions of all rules matching the given properties
+    pub fn resolve(&self, properties: &WindowProperties) -> RuleActions {
+        let mut actions = RuleActions::default();
+        for rule in self.rules.iter().filter(|rule| rule.matches.matches(properties)) {
+            actions.merge(&rule.actions);
+        }
+        actions
+    }
+
+    /// Resolve the rules for a window and apply them
+    ///
+    /// The initial size is set as pending state of Wayland toplevels, so it is part of the next
+    /// configure. All other actions have to be applied by the compositor. The actions are stored
+    /// with the window and can be retrieved again with [`applied_rules`].
+    pub fn apply(&self, window: &Window) -> RuleActions {
+        let actions = self.resolve(&WindowProperties::from_window(window));
+
+        if let (Some(size), Some(toplevel)) = (actions.size, window.toplevel()) {
+            toplevel.with_pending_state(|state| state.size = Some(size));
+        }
+
+        let applied = window.user_data().get_or_insert_threadsafe(AppliedRules::default);
+        *applied.0.lock().unwrap() = actions.clone();
+        actions
+    }
+}
+
+/// Returns the actions applied to a window by [`WindowRules::apply`], if any
+pub fn applied_rules(window: &Window) -> Option<RuleActions> {
+    window
+        .user_data()
+        .get::<AppliedRules>()
+        .map(|applied| applied.0.lock().unwrap().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(app_id: &str, title: &str) -> WindowProperties {
+        WindowProperties {
+            app_id: Some(app_id.into()),
+            title: Some(title.into()),
+        }
+    }
+
+    #[test]
+    fn later_rules_override() {
+        let mut rules = WindowRules::new();
+        rules.push(WindowRule {
+            matches: RuleMatch {
+                app_id: Some(StringMatch::Prefix("org.mozilla".into())),
+                ..Default::default()
+            },
+            actions: RuleActions {
+                workspace: Some("web".into()),
+                layout: Some(Layout::Tiled),
+                ..Default::default()
+            },
+        });
+        rules.push(WindowRule {
+            matches: RuleMatch {
+                title: Some(StringMatch::Contains("Picture-in-Picture".into())),
+                ..Default::default()
+            },
+            actions: RuleActions {
+                layout: Some(Layout::Floating),
+                opacity: Some(1.5),
+                ..Default::default()
+            },
+        });
+
+        let actions = rules.resolve(&properties("org.mozilla.firefox", "Picture-in-Picture"));
+        assert_eq!(actions.workspace.as_deref(), Some("web"));
+        assert_eq!(actions.layout, Some(Layout::Floating));
+        assert_eq!(actions.opacity, Some(1.0));
+
+        let actions = rules.resolve(&properties("org.mozilla.firefox", "Mozilla Firefox"));
+        assert_eq!(actions.layout, Some(Layout::Tiled));
+        assert_eq!(actions.opacity, None);
+    }
+
+    #[test]
+    fn missing_properties_do_not_match() {
+        let rule = RuleMatch {
+            title: Some(StringMatch::Exact("".into())),
+            ..Default::default()
+        };
+        assert!(!rule.matches(&WindowProperties::default()));
+        assert!(RuleMatch::default().matches(&WindowProperties::default()));
+    }
+}
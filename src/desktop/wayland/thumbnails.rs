@@ -0,0 +1,368 @@
+//! Window thumbnails
+//!
+//! Task switchers and overview modes show many windows at once, usually much smaller than their
+//! actual size. Rendering every window at full size each frame for this is wasteful, so
+//! [`Thumbnails`] keeps a small offscreen texture per tracked [`Window`] instead, downscaled to fit
+//! into [`ThumbnailConfig::max_size`] and updated at most once per [`ThumbnailConfig::interval`].
+//!
+//! Thumbnails are rendered through a [`WindowCapture`], so an update only redraws the parts of a
+//! window that changed since its last update. The textures can be displayed with
+//! [`Thumbnail::render_element`].
+//!
+//! ```no_run
+//! use smithay::backend::renderer::{element::Kind, ImportAll, Offscreen, Renderer, Texture};
+//! use smithay::desktop::{
+//!     thumbnails::{ThumbnailConfig, Thumbnails},
+//!     Window,
+//! };
+//! use smithay::utils::{Clock, Monotonic, Transform};
+//!
+//! fn show_task_switcher<R>(renderer: &mut R, clock: &Clock<Monotonic>, windows: &[Window])
+//! where
+//!     R: Renderer + ImportAll + Offscreen<R::TextureId>,
+//!     R::TextureId: Clone + Texture + 'static,
+//! {
+//!     let mut thumbnails = Thumbnails::new(ThumbnailConfig::default(), Transform::Normal);
+//!     for window in windows {
+//!         thumbnails.track(window.clone());
+//!     }
+//!
+//!     // while the task switcher is shown, e.g. once per frame
+//!     thumbnails
+//!         .update(renderer, clock.now())
+//!         .expect("failed to update thumbnails");
+//!     let elements = thumbnails
+//!         .thumbnails()
+//!         .enumerate()
+//!         .filter_map(|(i, thumbnail)| {
+//!             thumbnail.render_element((i as i32 * 300, 0).into(), 1.0, Kind::Unspecified)
+//!         })
+//!         .collect::<Vec<_>>();
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::Error as OutputDamageTrackerError,
+            element::{effects::EffectRenderElement, surface::WaylandSurfaceRenderElement, Id, Kind},
+            utils::CommitCounter,
+            Color32F, ContextId, ImportAll, Offscreen, Renderer, Texture,
+        },
+    },
+    desktop::{
+        capture::{WindowCapture, WindowCaptureRegion},
+        Window,
+    },
+    utils::{Buffer, IsAlive, Logical, Monotonic, Physical, Point, Rectangle, Size, Time, Transform},
+};
+
+/// Parameters of [`Thumbnails`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailConfig {
+    /// Size thumbnails are downscaled to fit into, keeping the aspect ratio of the window
+    ///
+    /// Windows smaller than this are not upscaled.
+    pub max_size: Size<i32, Logical>,
+    /// Minimum time between two updates of the same thumbnail
+    pub interval: Duration,
+    /// Part of the windows to show in the thumbnails
+    pub region: WindowCaptureRegion,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        ThumbnailConfig {
+            max_size: (256, 256).into(),
+            interval: Duration::from_millis(500),
+            region: WindowCaptureRegion::Geometry,
+        }
+    }
+}
+
+/// Downscaled texture of a single window
+#[derive(Debug)]
+pub struct Thumbnail<T: Texture> {
+    window: Window,
+    capture: WindowCapture,
+    texture: Option<T>,
+    context_id: Option<ContextId<T>>,
+    id: Id,
+    commit: CommitCounter,
+    transform: Transform,
+    last_update: Option<Time<Monotonic>>,
+}
+
+impl<T: Texture + Clone + 'static> Thumbnail<T> {
+    /// Returns the window of the thumbnail
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /// Returns the texture holding the thumbnail, if it was rendered yet
+    pub fn texture(&self) -> Option<&T> {
+        self.texture.as_ref()
+    }
+
+    /// Returns the size of the thumbnail, or an empty size if it was not rendered yet
+    pub fn size(&self) -> Size<i32, Physical> {
+        self.texture
+            .as_ref()
+            .map(|texture| {
+                let size = texture.size();
+                (size.w, size.h).into()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the scale the window is downscaled by
+    pub fn scale(&self) -> f64 {
+        self.capture.scale().x
+    }
+
+    /// Returns the time of the last update
+    pub fn last_update(&self) -> Option<Time<Monotonic>> {
+        self.last_update
+    }
+
+    /// Create a render element displaying the thumbnail at `location`
+    ///
+    /// Returns `None` if the thumbnail was not rendered yet.
+    pub fn render_element(
+        &self,
+        location: Point<i32, Physical>,
+        alpha: f32,
+        kind: Kind,
+    ) -> Option<EffectRenderElement<T>> {
+        Some(EffectRenderElement::new(
+            self.id.clone(),
+            self.context_id.clone()?,
+            self.texture.clone()?,
+            Rectangle::new(location, self.size()),
+            self.transform,
+            self.commit,
+            alpha,
+            kind,
+        ))
+    }
+
+    fn due(&self, now: Time<Monotonic>, interval: Duration) -> bool {
+        self.last_update
+            .is_none_or(|last| Time::elapsed(&last, now) >= interval)
+    }
+
+    fn render<R>(
+        &mut self,
+        renderer: &mut R,
+        config: &ThumbnailConfig,
+        now: Time<Monotonic>,
+    ) -> Result<(), OutputDamageTrackerError<R::Error>>
+    where
+        R: Renderer<TextureId = T> + ImportAll + Offscreen<T>,
+    {
+        let area = self.capture.capture_area(&self.window);
+        if area.is_empty() {
+            return Ok(());
+        }
+        let scale = fit_scale(area.size, config.max_size);
+        if self.capture.scale().x != scale {
+            self.capture.set_scale(scale);
+        }
+
+        let size = self.capture.buffer_size(&self.window);
+        let buffer_size = Size::<i32, Buffer>::from((size.w, size.h));
+        let age = match &self.texture {
+            Some(texture) if texture.size() == buffer_size => 1,
+            _ => {
+                let texture = renderer
+                    .create_buffer(Fourcc::Abgr8888, buffer_size)
+                    .map_err(OutputDamageTrackerError::Rendering)?;
+                self.texture = Some(texture);
+                0
+            }
+        };
+
+        let texture = self.texture.as_mut().unwrap();
+        let mut framebuffer = renderer
+            .bind(texture)
+            .map_err(OutputDamageTrackerError::Rendering)?;
+        let result = self.capture.render::<_, WaylandSurfaceRenderElement<R>>(
+            renderer,
+            &mut framebuffer,
+            age,
+            &self.window,
+            &[],
+            Color32F::TRANSPARENT,
+        )?;
+        let damaged = result.damage.is_some_and(|damage| !damage.is_empty());
+        renderer
+            .wait(&result.sync)
+            .map_err(OutputDamageTrackerError::Rendering)?;
+
+        if damaged {
+            self.commit.increment();
+        }
+        self.context_id = Some(renderer.context_id());
+        self.last_update = Some(now);
+        Ok(())
+    }
+}
+
+/// Periodically updated thumbnails of a set of windows
+#[derive(Debug)]
+pub struct Thumbnails<T: Texture> {
+    config: ThumbnailConfig,
+    transform: Transform,
+    thumbnails: Vec<Thumbnail<T>>,
+}
+
+impl<T: Texture + Clone + 'static> Thumbnails<T> {
+    /// Create an empty set of thumbnails
+    ///
+    /// `transform` is the transform of the offscreen textures of the renderer.
+    pub fn new(config: ThumbnailConfig, transform: Transform) -> Self {
+        Thumbnails {
+            config,
+            transform,
+            thumbnails: Vec::new(),
+        }
+    }
+
+    /// Returns the parameters of the thumbnails
+    pub fn config(&self) -> ThumbnailConfig {
+        self.config
+    }
+
+    /// Change the parameters of the thumbnails
+    ///
+    /// All thumbnails are updated on the next [`update`](Self::update).
+    pub fn set_config(&mut self, config: ThumbnailConfig) {
+        if config.region != self.config.region {
+            for thumbnail in &mut self.thumbnails {
+                thumbnail.capture = WindowCapture::new(config.region, thumbnail.capture.scale());
+            }
+        }
+        self.config = config;
+        self.invalidate_all();
+    }
+
+    /// Start keeping a thumbnail of the window
+    ///
+    /// The thumbnail is rendered on the next [`update`](Self::update).
+    pub fn track(&mut self, window: Window) {
+        if self.get(&window).is_some() {
+            return;
+        }
+        self.thumbnails.push(Thumbnail {
+            window,
+            capture: WindowCapture::new(self.config.region, 1.0),
+            texture: None,
+            context_id: None,
+            id: Id::new(),
+            commit: CommitCounter::default(),
+            transform: self.transform,
+            last_update: None,
+        });
+    }
+
+    /// Stop keeping a thumbnail of the window and release its texture
+    pub fn untrack(&mut self, window: &Window) {
+        self.thumbnails.retain(|thumbnail| &thumbnail.window != window);
+    }
+
+    /// Returns the thumbnail of the window, if it is tracked
+    pub fn get(&self, window: &Window) -> Option<&Thumbnail<T>> {
+        self.thumbnails
+            .iter()
+            .find(|thumbnail| &thumbnail.window == window)
+    }
+
+    /// Returns the thumbnails of all tracked windows in the order they were tracked
+    pub fn thumbnails(&self) -> impl Iterator<Item = &Thumbnail<T>> {
+        self.thumbnails.iter()
+    }
+
+    /// Update the thumbnail of the window on the next [`update`](Self::update), ignoring the
+    /// interval
+    pub fn invalidate(&mut self, window: &Window) {
+        if let Some(thumbnail) = self
+            .thumbnails
+            .iter_mut()
+            .find(|thumbnail| &thumbnail.window == window)
+        {
+            thumbnail.last_update = None;
+        }
+    }
+
+    /// Update all thumbnails on the next [`update`](Self::update), ignoring the interval
+    pub fn invalidate_all(&mut self) {
+        for thumbnail in &mut self.thumbnails {
+            thumbnail.last_update = None;
+        }
+    }
+
+    /// Stop tracking windows that were destroyed
+    pub fn refresh(&mut self) {
+        self.thumbnails.retain(|thumbnail| thumbnail.window.alive());
+    }
+
+    /// Render all thumbnails that were not updated for at least [`ThumbnailConfig::interval`]
+    ///
+    /// Should be called regularly while the thumbnails are displayed, e.g. before rendering a
+    /// frame, or when the timeout returned by [`next_update`](Self::next_update) expires.
+    #[profiling::function]
+    pub fn update<R>(
+        &mut self,
+        renderer: &mut R,
+        now: Time<Monotonic>,
+    ) -> Result<(), OutputDamageTrackerError<R::Error>>
+    where
+        R: Renderer<TextureId = T> + ImportAll + Offscreen<T>,
+    {
+        self.refresh();
+        for thumbnail in &mut self.thumbnails {
+            if thumbnail.due(now, self.config.interval) {
+                thumbnail.render(renderer, &self.config, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the time until the next thumbnail is due to be updated
+    ///
+    /// Returns `None` if no windows are tracked.
+    pub fn next_update(&self, now: Time<Monotonic>) -> Option<Duration> {
+        self.thumbnails
+            .iter()
+            .map(|thumbnail| match thumbnail.last_update {
+                Some(last) => self.config.interval.saturating_sub(Time::elapsed(&last, now)),
+                None => Duration::ZERO,
+            })
+            .min()
+    }
+}
+
+/// Returns the scale fitting `size` into `max_size`, never upscaling
+fn fit_scale(size: Size<i32, Logical>, max_size: Size<i32, Logical>) -> f64 {
+    if size.w <= 0 || size.h <= 0 {
+        return 1.0;
+    }
+    let scale_w = max_size.w.max(1) as f64 / size.w as f64;
+    let scale_h = max_size.h.max(1) as f64 / size.h as f64;
+    scale_w.min(scale_h).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fit_scale;
+
+    #[test]
+    fn fit_keeps_aspect_ratio() {
+        assert_eq!(fit_scale((1024, 512).into(), (256, 256).into()), 0.25);
+        assert_eq!(fit_scale((400, 800).into(), (256, 200).into()), 0.25);
+        assert_eq!(fit_scale((100, 50).into(), (256, 256).into()), 1.0);
+    }
+}
@@ -9,10 +9,12 @@ use crate::{
     },
     desktop::WindowSurfaceType,
     output::{Output, WeakOutput},
-    utils::{Logical, Point, Rectangle, Time},
+    utils::{Logical, Monotonic, Point, Rectangle, Time},
     wayland::{
+        commit_timing::{CommitTimerBarrierStateUserData, Timestamp},
         compositor::{with_surface_tree_downward, SurfaceAttributes, SurfaceData, TraversalAction},
         dmabuf::{DmabufFeedback, SurfaceDmabufFeedbackState},
+        fifo::FifoBarrierCachedState,
         presentation::{PresentationFeedbackCachedState, PresentationFeedbackCallback, Refresh},
     },
 };
@@ -266,6 +268,86 @@ pub fn send_frames_surface_tree<T, F>(
     );
 }
 
+/// Releases the frame queue of a surface and its subsurfaces for a new frame on an output
+///
+/// Signals the barrier set through the fifo protocol and all commit timers targeting a
+/// presentation time at or before `frame_target`, which should be the presentation time of the
+/// frame that is about to be rendered. Like frame callbacks this only happens on the primary
+/// scan-out output of a surface, or if the surface is not shown on any output.
+///
+/// Returns `true` if any barrier was signaled. In that case
+/// [`CompositorClientState::blocker_cleared`](crate::wayland::compositor::CompositorClientState::blocker_cleared)
+/// has to be called for the client of the surface, so held commits get applied.
+pub fn signal_frame_barriers_surface_tree<T, F>(
+    surface: &wl_surface::WlSurface,
+    output: &Output,
+    frame_target: T,
+    mut primary_scan_out_output: F,
+) -> bool
+where
+    T: Into<Time<Monotonic>>,
+    F: FnMut(&wl_surface::WlSurface, &SurfaceData) -> Option<Output>,
+{
+    let frame_target = Timestamp::from(frame_target.into());
+    let mut signaled = false;
+
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, &()| TraversalAction::DoChildren(()),
+        |surface, states, &()| {
+            let primary_output = primary_scan_out_output(surface, states);
+            if primary_output.is_some_and(|primary_output| primary_output != *output) {
+                return;
+            }
+
+            let fifo_barrier = states
+                .cached_state
+                .get::<FifoBarrierCachedState>()
+                .current()
+                .barrier
+                .take();
+            if let Some(fifo_barrier) = fifo_barrier {
+                fifo_barrier.signal();
+                signaled = true;
+            }
+
+            if let Some(commit_timer_state) = states.data_map.get::<CommitTimerBarrierStateUserData>() {
+                signaled |= commit_timer_state.lock().unwrap().signal_until(frame_target);
+            }
+        },
+        |_, _, &()| true,
+    );
+
+    signaled
+}
+
+/// Returns the earliest presentation time targeted by a held commit of a surface or its subsurfaces
+///
+/// The frame clock can use this to schedule a frame in time for the next commit timer to be
+/// released by [`signal_frame_barriers_surface_tree`], even if nothing else is damaged.
+pub fn next_commit_deadline_surface_tree(surface: &wl_surface::WlSurface) -> Option<Time<Monotonic>> {
+    let mut deadline: Option<Timestamp> = None;
+
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, &()| TraversalAction::DoChildren(()),
+        |_, states, &()| {
+            let next = states
+                .data_map
+                .get::<CommitTimerBarrierStateUserData>()
+                .and_then(|state| state.lock().unwrap().next_deadline());
+            if let Some(next) = next {
+                deadline = Some(deadline.map_or(next, |deadline| deadline.min(next)));
+            }
+        },
+        |_, _, &()| true,
+    );
+
+    deadline.map(Time::from)
+}
+
 /// Sends dmabuf feedback for a surface and its subsurfaces with the given select function.
 ///
 /// The dmabuf feedback for a [`WlSurface`](wl_surface::WlSurface) will only be sent if the
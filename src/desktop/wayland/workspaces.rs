@@ -0,0 +1,523 @@
+//! Workspaces backed by spaces
+//!
+//! [`Workspaces`] keeps a list of workspaces for every output, each with a [`Space`] of its own,
+//! and mirrors them to clients through the ext-workspace protocol of a
+//! [`WorkspaceManagerState`]. Every output becomes a workspace group and always has exactly one
+//! active workspace, whose space the output is mapped into.
+//!
+//! ```no_run
+//! use smithay::desktop::{workspaces::Workspaces, Window};
+//! use smithay::wayland::workspace::{WorkspaceHandler, WorkspaceManagerState, WorkspaceRequest};
+//! # use smithay::output::Output;
+//!
+//! pub struct State {
+//!     workspace_state: WorkspaceManagerState,
+//!     workspaces: Workspaces<Window>,
+//! }
+//!
+//! smithay::delegate_workspace!(State);
+//!
+//! impl WorkspaceHandler for State {
+//!     fn workspace_state(&mut self) -> &mut WorkspaceManagerState {
+//!         &mut self.workspace_state
+//!     }
+//!
+//!     fn commit_requests(&mut self, requests: Vec<WorkspaceRequest>) {
+//!         self.workspaces
+//!             .handle_requests::<State>(&mut self.workspace_state, requests);
+//!     }
+//! }
+//!
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! # let output: Output = todo!();
+//! # let window: Window = todo!();
+//! let mut state = State {
+//!     workspace_state: WorkspaceManagerState::new::<State>(&display.handle()),
+//!     workspaces: Workspaces::new(),
+//! };
+//!
+//! state
+//!     .workspaces
+//!     .add_output::<State>(&mut state.workspace_state, &output, (0, 0));
+//! state
+//!     .workspaces
+//!     .add_workspace::<State>(&mut state.workspace_state, &output, "2");
+//!
+//! // map new windows into the active workspace of an output
+//! let space = state.workspaces.active_space_mut(&output).unwrap();
+//! space.map_element(window, (0, 0), true);
+//! ```
+
+use crate::{
+    desktop::{space::SpaceElement, Space},
+    output::Output,
+    utils::{Logical, Point},
+    wayland::workspace::{
+        GroupCapabilities, WorkspaceCapabilities, WorkspaceGroupHandle, WorkspaceHandle, WorkspaceHandler,
+        WorkspaceManagerState, WorkspaceRequest, WorkspaceStates,
+    },
+};
+
+#[derive(Debug)]
+struct OutputWorkspaces<E: SpaceElement> {
+    output: Output,
+    location: Point<i32, Logical>,
+    group: WorkspaceGroupHandle,
+    workspaces: Vec<(WorkspaceHandle, Space<E>)>,
+    active: usize,
+}
+
+impl<E: SpaceElement + PartialEq> OutputWorkspaces<E> {
+    fn position(&self, workspace: WorkspaceHandle) -> Option<usize> {
+        self.workspaces
+            .iter()
+            .position(|(handle, _)| *handle == workspace)
+    }
+
+    // Takes out the workspace at `pos`, activating a neighbour if it was active
+    fn detach(&mut self, state: &mut WorkspaceManagerState, pos: usize) -> (WorkspaceHandle, Space<E>) {
+        if self.active == pos {
+            let next = if pos == 0 { 1 } else { pos - 1 };
+            let (next_handle, space) = &mut self.workspaces[next];
+            space.map_output(&self.output, self.location);
+            state.set_workspace_state(*next_handle, WorkspaceStates::Active);
+            self.active = next;
+        }
+
+        let (handle, mut space) = self.workspaces.remove(pos);
+        if self.active > pos {
+            self.active -= 1;
+        }
+        space.unmap_output(&self.output);
+        self.update_coordinates(state);
+        (handle, space)
+    }
+
+    fn update_coordinates(&self, state: &mut WorkspaceManagerState) {
+        for (idx, (handle, _)) in self.workspaces.iter().enumerate() {
+            state.set_workspace_coordinates(*handle, vec![idx as u32]);
+        }
+    }
+}
+
+/// Workspaces of all outputs, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct Workspaces<E: SpaceElement> {
+    outputs: Vec<OutputWorkspaces<E>>,
+}
+
+impl<E: SpaceElement> Default for Workspaces<E> {
+    fn default() -> Self {
+        Workspaces { outputs: Vec::new() }
+    }
+}
+
+impl<E: SpaceElement + PartialEq + Clone> Workspaces<E> {
+    /// Create an empty set of workspaces
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an output with a single active workspace named "1"
+    ///
+    /// `location` is the location the output is mapped at in the spaces of its workspaces.
+    pub fn add_output<D: WorkspaceHandler>(
+        &mut self,
+        state: &mut WorkspaceManagerState,
+        output: &Output,
+        location: impl Into<Point<i32, Logical>>,
+    ) -> WorkspaceHandle {
+        if let Some(active) = self.active_workspace(output) {
+            return active;
+        }
+
+        let group = state.create_group::<D>(GroupCapabilities::CreateWorkspace);
+        state.group_output_enter(group, output);
+        self.outputs.push(OutputWorkspaces {
+            output: output.clone(),
+            location: location.into(),
+            group,
+            workspaces: Vec::new(),
+            active: 0,
+        });
+
+        let workspace = self.add_workspace::<D>(state, output, "1").unwrap();
+        self.activate(state, workspace);
+        state.done();
+        workspace
+    }
+
+    /// Remove an output and all of its workspaces
+    ///
+    /// Returns the elements of the removed workspaces, so they can be mapped elsewhere.
+    pub fn remove_output(&mut self, state: &mut WorkspaceManagerState, output: &Output) -> Vec<E> {
+        let Some(pos) = self.outputs.iter().position(|o| &o.output == output) else {
+            return Vec::new();
+        };
+
+        let removed = self.outputs.remove(pos);
+        let mut elements = Vec::new();
+        for (handle, mut space) in removed.workspaces {
+            space.unmap_output(output);
+            elements.extend(space.elements().cloned());
+            state.remove_workspace(handle);
+        }
+        state.remove_group(removed.group);
+        state.done();
+        elements
+    }
+
+    /// Change the location of an output in the spaces of its workspaces
+    pub fn set_output_location(&mut self, output: &Output, location: impl Into<Point<i32, Logical>>) {
+        let Some(o) = self.outputs.iter_mut().find(|o| &o.output == output) else {
+            return;
+        };
+        o.location = location.into();
+        o.workspaces[o.active].1.map_output(output, o.location);
+    }
+
+    /// Add an inactive workspace to the end of the workspaces of an output
+    ///
+    /// Returns `None` if the output was not added with [`Workspaces::add_output`].
+    pub fn add_workspace<D: WorkspaceHandler>(
+        &mut self,
+        state: &mut WorkspaceManagerState,
+        output: &Output,
+        name: impl Into<String>,
+    ) -> Option<WorkspaceHandle> {
+        let o = self.outputs.iter_mut().find(|o| &o.output == output)?;
+
+        let handle = state.create_workspace::<D>(
+            name,
+            None,
+            WorkspaceCapabilities::Activate | WorkspaceCapabilities::Remove | WorkspaceCapabilities::Assign,
+        );
+        state.set_workspace_group(handle, Some(o.group));
+        state.set_workspace_coordinates(handle, vec![o.workspaces.len() as u32]);
+        o.workspaces.push((handle, Space::default()));
+        Some(handle)
+    }
+
+    /// Remove a workspace
+    ///
+    /// The last workspace of an output can not be removed. If the workspace was active, the
+    /// previous one is activated. Returns the elements of the removed workspace, so they can be
+    /// mapped elsewhere.
+    pub fn remove_workspace(
+        &mut self,
+        state: &mut WorkspaceManagerState,
+        workspace: WorkspaceHandle,
+    ) -> Option<Vec<E>> {
+        let o = self
+            .outputs
+            .iter_mut()
+            .find(|o| o.position(workspace).is_some())?;
+        if o.workspaces.len() == 1 {
+            return None;
+        }
+        let pos = o.position(workspace)?;
+
+        let (_, space) = o.detach(state, pos);
+        state.remove_workspace(workspace);
+        Some(space.elements().cloned().collect())
+    }
+
+    /// Activate a workspace, deactivating the previously active workspace of its output
+    ///
+    /// Returns `false` if the workspace is unknown.
+    pub fn activate(&mut self, state: &mut WorkspaceManagerState, workspace: WorkspaceHandle) -> bool {
+        let Some(o) = self.outputs.iter_mut().find(|o| o.position(workspace).is_some()) else {
+            return false;
+        };
+        let pos = o.position(workspace).unwrap();
+
+        if o.active != pos {
+            let (previous, space) = &mut o.workspaces[o.active];
+            space.unmap_output(&o.output);
+            state.set_workspace_state(*previous, WorkspaceStates::empty());
+        }
+        o.workspaces[pos].1.map_output(&o.output, o.location);
+        state.set_workspace_state(workspace, WorkspaceStates::Active);
+        o.active = pos;
+        true
+    }
+
+    /// Move a workspace to the end of the workspaces of another output
+    ///
+    /// The last workspace of an output can not be moved. If the workspace was active, the
+    /// previous one of its output is activated and the moved one becomes inactive.
+    pub fn assign(
+        &mut self,
+        state: &mut WorkspaceManagerState,
+        workspace: WorkspaceHandle,
+        output: &Output,
+    ) -> bool {
+        let Some(target) = self.outputs.iter().position(|o| &o.output == output) else {
+            return false;
+        };
+        let Some(source) = self.outputs.iter().position(|o| o.position(workspace).is_some()) else {
+            return false;
+        };
+        if source == target || self.outputs[source].workspaces.len() == 1 {
+            return false;
+        }
+
+        let o = &mut self.outputs[source];
+        let pos = o.position(workspace).unwrap();
+        let (handle, space) = o.detach(state, pos);
+        state.set_workspace_state(handle, WorkspaceStates::empty());
+
+        let o = &mut self.outputs[target];
+        state.set_workspace_group(handle, Some(o.group));
+        state.set_workspace_coordinates(handle, vec![o.workspaces.len() as u32]);
+        o.workspaces.push((handle, space));
+        true
+    }
+
+    /// Apply the requests of a client and notify clients about the changes
+    ///
+    /// Elements of removed workspaces are mapped into the active workspace of the same output.
+    /// Deactivating a workspace is not supported, as every output always has an active one.
+    pub fn handle_requests<D: WorkspaceHandler>(
+        &mut self,
+        state: &mut WorkspaceManagerState,
+        requests: Vec<WorkspaceRequest>,
+    ) {
+        for request in requests {
+            match request {
+                WorkspaceRequest::Activate(workspace) => {
+                    self.activate(state, workspace);
+                }
+                WorkspaceRequest::Deactivate(_) => {}
+                WorkspaceRequest::Remove(workspace) => {
+                    let Some(output) = self.output_for_workspace(workspace).cloned() else {
+                        continue;
+                    };
+                    let locations = self
+                        .space(workspace)
+                        .map(|space| {
+                            space
+                                .elements()
+                                .map(|e| (e.clone(), space.element_location(e).unwrap_or_default()))
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+                    if self.remove_workspace(state, workspace).is_none() {
+                        continue;
+                    }
+                    if let Some(space) = self.active_space_mut(&output) {
+                        for (element, location) in locations {
+                            space.map_element(element, location, false);
+                        }
+                    }
+                }
+                WorkspaceRequest::Assign { workspace, group } => {
+                    let Some(output) = self.outputs.iter().find(|o| o.group == group) else {
+                        continue;
+                    };
+                    let output = output.output.clone();
+                    self.assign(state, workspace, &output);
+                }
+                WorkspaceRequest::Create { group, name } => {
+                    let Some(output) = self.outputs.iter().find(|o| o.group == group) else {
+                        continue;
+                    };
+                    let output = output.output.clone();
+                    self.add_workspace::<D>(state, &output, name);
+                }
+            }
+        }
+        state.done();
+    }
+
+    /// Returns the active workspace of an output
+    pub fn active_workspace(&self, output: &Output) -> Option<WorkspaceHandle> {
+        let o = self.outputs.iter().find(|o| &o.output == output)?;
+        Some(o.workspaces[o.active].0)
+    }
+
+    /// Returns the space of the active workspace of an output
+    pub fn active_space(&self, output: &Output) -> Option<&Space<E>> {
+        let o = self.outputs.iter().find(|o| &o.output == output)?;
+        Some(&o.workspaces[o.active].1)
+    }
+
+    /// Returns the space of the active workspace of an output
+    pub fn active_space_mut(&mut self, output: &Output) -> Option<&mut Space<E>> {
+        let o = self.outputs.iter_mut().find(|o| &o.output == output)?;
+        Some(&mut o.workspaces[o.active].1)
+    }
+
+    /// Returns the workspaces of an output in order
+    pub fn workspaces(&self, output: &Output) -> impl Iterator<Item = WorkspaceHandle> + '_ {
+        self.outputs
+            .iter()
+            .filter(move |o| &o.output == output)
+            .flat_map(|o| o.workspaces.iter().map(|(handle, _)| *handle))
+    }
+
+    /// Returns the output of a workspace
+    pub fn output_for_workspace(&self, workspace: WorkspaceHandle) -> Option<&Output> {
+        self.outputs
+            .iter()
+            .find(|o| o.position(workspace).is_some())
+            .map(|o| &o.output)
+    }
+
+    /// Returns the space of a workspace
+    pub fn space(&self, workspace: WorkspaceHandle) -> Option<&Space<E>> {
+        self.outputs
+            .iter()
+            .flat_map(|o| o.workspaces.iter())
+            .find(|(handle, _)| *handle == workspace)
+            .map(|(_, space)| space)
+    }
+
+    /// Returns the space of a workspace
+    pub fn space_mut(&mut self, workspace: WorkspaceHandle) -> Option<&mut Space<E>> {
+        self.outputs
+            .iter_mut()
+            .flat_map(|o| o.workspaces.iter_mut())
+            .find(|(handle, _)| *handle == workspace)
+            .map(|(_, space)| space)
+    }
+
+    /// Returns the workspace an element is mapped on
+    pub fn workspace_for_element(&self, element: &E) -> Option<WorkspaceHandle> {
+        self.outputs
+            .iter()
+            .flat_map(|o| o.workspaces.iter())
+            .find(|(_, space)| space.elements().any(|e| e == element))
+            .map(|(handle, _)| *handle)
+    }
+
+    /// Refresh the spaces of all workspaces, see [`Space::refresh`]
+    pub fn refresh(&mut self) {
+        for o in &mut self.outputs {
+            for (_, space) in &mut o.workspaces {
+                space.refresh();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wayland_server::Display;
+
+    use super::*;
+    use crate::{
+        output::{PhysicalProperties, Subpixel},
+        utils::{IsAlive, Rectangle},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestElement;
+
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::default()
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            false
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    struct State {
+        workspaces: WorkspaceManagerState,
+    }
+
+    impl WorkspaceHandler for State {
+        fn workspace_state(&mut self) -> &mut WorkspaceManagerState {
+            &mut self.workspaces
+        }
+
+        fn commit_requests(&mut self, _requests: Vec<WorkspaceRequest>) {}
+    }
+
+    crate::delegate_workspace!(State);
+
+    fn output(name: &str) -> Output {
+        Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "test".into(),
+                model: "test".into(),
+                serial_number: "test".into(),
+            },
+        )
+    }
+
+    // checks that exactly the workspace reported by `active_workspace` is active
+    fn assert_one_active(
+        state: &WorkspaceManagerState,
+        workspaces: &Workspaces<TestElement>,
+        output: &Output,
+    ) {
+        let active = workspaces
+            .workspaces(output)
+            .filter(|handle| {
+                state
+                    .workspace(*handle)
+                    .unwrap()
+                    .state()
+                    .contains(WorkspaceStates::Active)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(active, [workspaces.active_workspace(output).unwrap()]);
+    }
+
+    #[test]
+    fn one_active_workspace_per_output() {
+        let display = Display::<State>::new().unwrap();
+        let mut state = WorkspaceManagerState::new::<State>(&display.handle());
+        let mut workspaces = Workspaces::<TestElement>::new();
+        let (left, right) = (output("left"), output("right"));
+
+        let first = workspaces.add_output::<State>(&mut state, &left, (0, 0));
+        workspaces.add_output::<State>(&mut state, &right, (1920, 0));
+        let second = workspaces.add_workspace::<State>(&mut state, &left, "2").unwrap();
+        let third = workspaces.add_workspace::<State>(&mut state, &left, "3").unwrap();
+        assert_eq!(workspaces.active_workspace(&left), Some(first));
+        assert_one_active(&state, &workspaces, &left);
+
+        assert!(workspaces.activate(&mut state, third));
+        assert_eq!(workspaces.active_workspace(&left), Some(third));
+        assert_one_active(&state, &workspaces, &left);
+
+        // removing the active workspace activates the previous one
+        assert!(workspaces.remove_workspace(&mut state, third).is_some());
+        assert_eq!(workspaces.active_workspace(&left), Some(second));
+        assert_one_active(&state, &workspaces, &left);
+
+        // the moved workspace is inactive on its new output
+        assert!(workspaces.assign(&mut state, second, &right));
+        assert_eq!(workspaces.active_workspace(&left), Some(first));
+        assert_eq!(workspaces.output_for_workspace(second), Some(&right));
+        assert_one_active(&state, &workspaces, &left);
+        assert_one_active(&state, &workspaces, &right);
+
+        // the last workspace of an output stays
+        assert!(!workspaces.assign(&mut state, first, &right));
+        assert!(workspaces.remove_workspace(&mut state, first).is_none());
+        assert_one_active(&state, &workspaces, &left);
+
+        assert!(workspaces.activate(&mut state, second));
+        assert_one_active(&state, &workspaces, &right);
+        assert_eq!(
+            state.workspace(second).unwrap().group(),
+            state.group_for_output(&right)
+        );
+    }
+}
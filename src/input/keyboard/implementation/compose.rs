@@ -0,0 +1,161 @@
+use xkbcommon::xkb::{
+    self,
+    compose::{FeedResult, State, Status, Table},
+    Keysym,
+};
+
+/// State of a compose or dead key sequence after a key press
+///
+/// See [`KeyboardHandle::enable_compose`](super::KeyboardHandle::enable_compose).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ComposeStatus {
+    /// The key is not part of a sequence and should be handled as usual
+    #[default]
+    Nothing,
+    /// The key started or continued a sequence and should not produce any text on its own
+    Composing,
+    /// The key completed a sequence
+    Composed {
+        /// Resulting keysym, if the result can be represented by a single one
+        keysym: Option<Keysym>,
+        /// Resulting text
+        utf8: Option<String>,
+    },
+    /// The key does not continue the current sequence, which was aborted
+    Cancelled,
+}
+
+pub(crate) enum Composer {
+    Table(State),
+    DeadKeys(Option<Keysym>),
+}
+
+impl Composer {
+    pub(crate) fn from_locale(context: &xkb::Context, locale: &std::ffi::OsStr) -> Option<Composer> {
+        let table = Table::new_from_locale(context, locale, xkb::compose::COMPILE_NO_FLAGS).ok()?;
+        Some(Composer::Table(State::new(&table, xkb::compose::STATE_NO_FLAGS)))
+    }
+
+    pub(crate) fn feed(&mut self, keysym: Keysym) -> ComposeStatus {
+        match self {
+            Composer::Table(state) => {
+                if state.feed(keysym) == FeedResult::Ignored {
+                    return ComposeStatus::Nothing;
+                }
+                match state.status() {
+                    Status::Nothing => ComposeStatus::Nothing,
+                    Status::Composing => ComposeStatus::Composing,
+                    Status::Composed => {
+                        let status = ComposeStatus::Composed {
+                            keysym: state.keysym(),
+                            utf8: state.utf8(),
+                        };
+                        state.reset();
+                        status
+                    }
+                    Status::Cancelled => {
+                        state.reset();
+                        ComposeStatus::Cancelled
+                    }
+                }
+            }
+            Composer::DeadKeys(pending) => {
+                if keysym.is_modifier_key() {
+                    return if pending.is_some() {
+                        ComposeStatus::Composing
+                    } else {
+                        ComposeStatus::Nothing
+                    };
+                }
+                let Some(dead) = pending.take() else {
+                    if dead_key_accent(keysym).is_some() {
+                        *pending = Some(keysym);
+                        return ComposeStatus::Composing;
+                    }
+                    return ComposeStatus::Nothing;
+                };
+
+                let composed = if keysym == dead || keysym == Keysym::space {
+                    dead_key_accent(dead).map(|(spacing, _)| spacing)
+                } else {
+                    keysym.key_char().and_then(|base| dead_key_compose(dead, base))
+                };
+                match composed {
+                    Some(ch) => ComposeStatus::Composed {
+                        keysym: Some(xkb::utf32_to_keysym(ch as u32)),
+                        utf8: Some(ch.to_string()),
+                    },
+                    None => ComposeStatus::Cancelled,
+                }
+            }
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        match self {
+            Composer::Table(state) => state.reset(),
+            Composer::DeadKeys(pending) => *pending = None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Composer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Composer::Table(_) => f.write_str("Composer::Table"),
+            Composer::DeadKeys(pending) => f.debug_tuple("Composer::DeadKeys").field(pending).finish(),
+        }
+    }
+}
+
+// spacing accent and pairs of base and composed characters for the supported dead keys
+fn dead_key_accent(dead: Keysym) -> Option<(char, &'static str)> {
+    Some(match dead {
+        Keysym::dead_acute => ('´', "aáeéiíoóuúyýcćnńsśzźAÁEÉIÍOÓUÚYÝCĆNŃSŚZŹ"),
+        Keysym::dead_grave => ('`', "aàeèiìoòuùAÀEÈIÌOÒUÙ"),
+        Keysym::dead_circumflex => ('^', "aâeêiîoôuûAÂEÊIÎOÔUÛ"),
+        Keysym::dead_diaeresis => ('¨', "aäeëiïoöuüyÿAÄEËIÏOÖUÜ"),
+        Keysym::dead_tilde => ('~', "aãnñoõAÃNÑOÕ"),
+        Keysym::dead_cedilla => ('¸', "cçCÇ"),
+        Keysym::dead_abovering => ('°', "aåAÅ"),
+        _ => return None,
+    })
+}
+
+fn dead_key_compose(dead: Keysym, base: char) -> Option<char> {
+    let (_, pairs) = dead_key_accent(dead)?;
+    let chars: Vec<char> = pairs.chars().collect();
+    chars
+        .chunks_exact(2)
+        .find(|pair| pair[0] == base)
+        .map(|pair| pair[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComposeStatus, Composer};
+    use xkbcommon::xkb::Keysym;
+
+    fn composed(ch: char) -> ComposeStatus {
+        ComposeStatus::Composed {
+            keysym: Some(xkbcommon::xkb::utf32_to_keysym(ch as u32)),
+            utf8: Some(ch.to_string()),
+        }
+    }
+
+    #[test]
+    fn dead_keys() {
+        let mut composer = Composer::DeadKeys(None);
+        assert_eq!(composer.feed(Keysym::a), ComposeStatus::Nothing);
+        assert_eq!(composer.feed(Keysym::dead_acute), ComposeStatus::Composing);
+        assert_eq!(composer.feed(Keysym::Shift_L), ComposeStatus::Composing);
+        assert_eq!(composer.feed(Keysym::E), composed('É'));
+
+        composer.feed(Keysym::dead_circumflex);
+        assert_eq!(composer.feed(Keysym::space), composed('^'));
+
+        composer.feed(Keysym::dead_tilde);
+        assert_eq!(composer.feed(Keysym::x), ComposeStatus::Cancelled);
+        assert_eq!(composer.feed(Keysym::x), ComposeStatus::Nothing);
+    }
+}
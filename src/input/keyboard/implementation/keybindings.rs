@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use xkbcommon::xkb::{Keycode, Keysym};
+
+use super::{FilterResult, KeysymHandle, ModifiersState};
+use crate::backend::input::KeyState;
+
+bitflags::bitflags! {
+    /// Modifiers of a [`KeyChord`]
+    ///
+    /// Lock modifiers (caps lock and num lock) never take part in matching.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ChordModifiers: u8 {
+        /// The "control" key
+        const CTRL = 1;
+        /// The "alt" key
+        const ALT = 1 << 1;
+        /// The "shift" key
+        const SHIFT = 1 << 2;
+        /// The "logo" key
+        const LOGO = 1 << 3;
+        /// The "ISO level 3 shift" key
+        const ISO_LEVEL3_SHIFT = 1 << 4;
+    }
+}
+
+impl From<&ModifiersState> for ChordModifiers {
+    fn from(state: &ModifiersState) -> Self {
+        let mut modifiers = ChordModifiers::empty();
+        modifiers.set(ChordModifiers::CTRL, state.ctrl);
+        modifiers.set(ChordModifiers::ALT, state.alt);
+        modifiers.set(ChordModifiers::SHIFT, state.shift);
+        modifiers.set(ChordModifiers::LOGO, state.logo);
+        modifiers.set(ChordModifiers::ISO_LEVEL3_SHIFT, state.iso_level3_shift);
+        modifiers
+    }
+}
+
+/// Combination of modifiers and a keysym triggering a keybinding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    /// Modifiers that have to be held exactly
+    pub modifiers: ChordModifiers,
+    /// Keysym that has to be pressed
+    ///
+    /// Chords are matched against the unshifted latin keysym of a key first (see
+    /// [`KeysymHandle::raw_latin_sym_or_raw_current_sym`]), so `Ctrl+Shift+q` works on any layout,
+    /// and against the keysym produced by the current keymap state second.
+    pub keysym: Keysym,
+}
+
+impl KeyChord {
+    /// Create a new chord
+    pub fn new(modifiers: ChordModifiers, keysym: Keysym) -> KeyChord {
+        KeyChord { modifiers, keysym }
+    }
+}
+
+#[derive(Debug)]
+struct Binding<A> {
+    action: A,
+    allow_when_inhibited: bool,
+}
+
+/// Compositor keybindings matched before key events are forwarded to clients
+///
+/// Use [`Keybindings::filter`] inside the filter of [`KeyboardHandle::input`](super::KeyboardHandle::input).
+/// Both the press and the release of a bound key are intercepted, so clients never see half of a
+/// shortcut.
+///
+/// Clients can ask to receive all key events, e.g. virtual machines or remote desktop viewers,
+/// using the [keyboard shortcuts inhibit protocol](crate::wayland::keyboard_shortcuts_inhibit).
+/// While an inhibitor is active for the focused surface only bindings added with
+/// [`Keybindings::bind_always`] are matched:
+///
+/// ```no_run
+/// # use smithay::input::keyboard::{ChordModifiers, FilterResult, KeyChord, Keybindings, KeyboardHandle, Keysym};
+/// # use smithay::backend::input::KeyState;
+/// # use smithay::utils::SERIAL_COUNTER;
+/// #[derive(Clone)]
+/// enum Action {
+///     CloseWindow,
+///     SwitchVt(i32),
+/// }
+///
+/// let mut bindings = Keybindings::new();
+/// bindings.bind(KeyChord::new(ChordModifiers::LOGO, Keysym::q), Action::CloseWindow);
+/// bindings.bind_always(
+///     KeyChord::new(ChordModifiers::CTRL | ChordModifiers::ALT, Keysym::XF86_Switch_VT_1),
+///     Action::SwitchVt(1),
+/// );
+///
+/// # fn input<D: smithay::input::SeatHandler + 'static>(
+/// #     data: &mut D,
+/// #     keyboard: &KeyboardHandle<D>,
+/// #     bindings: &mut Keybindings<Action>,
+/// #     keycode: smithay::input::keyboard::Keycode,
+/// #     state: KeyState,
+/// #     inhibited: bool,
+/// # ) {
+/// // `inhibited` is e.g. the result of `KeyboardShortcutsInhibitorSeat::keyboard_shortcuts_inhibited_for_surface`
+/// let action = keyboard
+///     .input(data, keycode, state, SERIAL_COUNTER.next_serial(), 0, |_, modifiers, handle| {
+///         bindings.filter(modifiers, &handle, state, inhibited)
+///     })
+///     .flatten();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Keybindings<A> {
+    bindings: HashMap<KeyChord, Binding<A>>,
+    intercepted: Vec<Keycode>,
+}
+
+impl<A> Default for Keybindings<A> {
+    fn default() -> Self {
+        Keybindings {
+            bindings: HashMap::new(),
+            intercepted: Vec::new(),
+        }
+    }
+}
+
+impl<A: Clone> Keybindings<A> {
+    /// Create an empty set of keybindings
+    pub fn new() -> Keybindings<A> {
+        Keybindings::default()
+    }
+
+    /// Bind an action to a chord, returning the action previously bound to it
+    ///
+    /// The binding is ignored while keyboard shortcuts are inhibited.
+    pub fn bind(&mut self, chord: KeyChord, action: A) -> Option<A> {
+        self.insert(chord, action, false)
+    }
+
+    /// Bind an action to a chord, which is also matched while keyboard shortcuts are inhibited
+    ///
+    /// Meant for bindings the user must never lose, like switching virtual terminals or
+    /// disabling an inhibitor.
+    pub fn bind_always(&mut self, chord: KeyChord, action: A) -> Option<A> {
+        self.insert(chord, action, true)
+    }
+
+    fn insert(&mut self, chord: KeyChord, action: A, allow_when_inhibited: bool) -> Option<A> {
+        self.bindings
+            .insert(
+                chord,
+                Binding {
+                    action,
+                    allow_when_inhibited,
+                },
+            )
+            .map(|binding| binding.action)
+    }
+
+    /// Remove the binding of a chord, returning its action
+    pub fn unbind(&mut self, chord: &KeyChord) -> Option<A> {
+        self.bindings.remove(chord).map(|binding| binding.action)
+    }
+
+    /// Returns the action bound to a chord
+    pub fn get(&self, chord: &KeyChord) -> Option<&A> {
+        self.bindings.get(chord).map(|binding| &binding.action)
+    }
+
+    /// Iterate over all bound chords and their actions
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyChord, &A)> {
+        self.bindings
+            .iter()
+            .map(|(chord, binding)| (chord, &binding.action))
+    }
+
+    /// Remove all bindings
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Match a key event against the bindings
+    ///
+    /// Returns [`FilterResult::Intercept`] with the bound action for a matching key press and
+    /// [`FilterResult::Intercept`] with `None` for the release of a key whose press was intercepted.
+    /// Everything else is forwarded.
+    pub fn filter(
+        &mut self,
+        modifiers: &ModifiersState,
+        handle: &KeysymHandle<'_>,
+        state: KeyState,
+        inhibited: bool,
+    ) -> FilterResult<Option<A>> {
+        let keycode = handle.raw_code();
+        match state {
+            KeyState::Pressed => {
+                let modifiers = ChordModifiers::from(modifiers);
+                let action = [
+                    handle.raw_latin_sym_or_raw_current_sym(),
+                    Some(handle.modified_sym()),
+                ]
+                .into_iter()
+                .flatten()
+                .find_map(|keysym| self.matching(KeyChord { modifiers, keysym }, inhibited));
+                match action {
+                    Some(action) => {
+                        if !self.intercepted.contains(&keycode) {
+                            self.intercepted.push(keycode);
+                        }
+                        FilterResult::Intercept(Some(action))
+                    }
+                    None => FilterResult::Forward,
+                }
+            }
+            KeyState::Released => match self.intercepted.iter().position(|code| *code == keycode) {
+                Some(index) => {
+                    self.intercepted.swap_remove(index);
+                    FilterResult::Intercept(None)
+                }
+                None => FilterResult::Forward,
+            },
+        }
+    }
+
+    fn matching(&self, chord: KeyChord, inhibited: bool) -> Option<A> {
+        self.bindings
+            .get(&chord)
+            .filter(|binding| !inhibited || binding.allow_when_inhibited)
+            .map(|binding| binding.action.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChordModifiers, KeyChord, Keybindings};
+    use crate::input::keyboard::{Keysym, ModifiersState};
+
+    #[test]
+    fn chord_modifiers_ignore_locks() {
+        let state = ModifiersState {
+            ctrl: true,
+            logo: true,
+            caps_lock: true,
+            num_lock: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            ChordModifiers::from(&state),
+            ChordModifiers::CTRL | ChordModifiers::LOGO
+        );
+    }
+
+    #[test]
+    fn inhibited_bindings() {
+        let close = KeyChord::new(ChordModifiers::LOGO, Keysym::q);
+        let vt = KeyChord::new(
+            ChordModifiers::CTRL | ChordModifiers::ALT,
+            Keysym::XF86_Switch_VT_1,
+        );
+        let mut bindings = Keybindings::new();
+        assert_eq!(bindings.bind(close, 1), None);
+        assert_eq!(bindings.bind(close, 2), Some(1));
+        bindings.bind_always(vt, 3);
+
+        assert_eq!(bindings.matching(close, false), Some(2));
+        assert_eq!(bindings.matching(close, true), None);
+        assert_eq!(bindings.matching(vt, true), Some(3));
+
+        assert_eq!(bindings.unbind(&close), Some(2));
+        assert_eq!(bindings.get(&close), None);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::utils::IsAlive;
+
+use super::Layout;
+
+/// Remembers the active keyboard layout per focused target
+///
+/// Many desktop environments keep a separate keyboard layout for every window, so switching
+/// focus restores the layout last used in that window. Feed this with
+/// [`SeatHandler::layout_changed`](crate::input::SeatHandler::layout_changed) and
+/// [`SeatHandler::focus_changed`](crate::input::SeatHandler::focus_changed):
+///
+/// ```no_run
+/// # use smithay::input::keyboard::{KeyboardHandle, Layout, LayoutMemory};
+/// # #[derive(Debug, Clone, PartialEq)]
+/// # struct Target;
+/// # impl smithay::utils::IsAlive for Target { fn alive(&self) -> bool { true } }
+/// # fn focus_changed<D: smithay::input::SeatHandler + 'static>(
+/// #     data: &mut D,
+/// #     keyboard: &KeyboardHandle<D>,
+/// #     memory: &mut LayoutMemory<Target>,
+/// #     focused: Option<&Target>,
+/// # ) {
+/// // in `SeatHandler::focus_changed`
+/// if let Some(layout) = memory.focus_changed(focused) {
+///     keyboard.set_layout(data, layout);
+/// }
+/// # }
+/// # fn layout_changed(memory: &mut LayoutMemory<Target>, focused: Option<&Target>, layout: Layout) {
+/// // in `SeatHandler::layout_changed`
+/// memory.layout_changed(focused, layout);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LayoutMemory<F> {
+    default: Option<Layout>,
+    layouts: Vec<(F, Layout)>,
+}
+
+impl<F> Default for LayoutMemory<F> {
+    fn default() -> Self {
+        LayoutMemory {
+            default: None,
+            layouts: Vec::new(),
+        }
+    }
+}
+
+impl<F: IsAlive + PartialEq + Clone> LayoutMemory<F> {
+    /// Create a new layout memory keeping the active layout for targets focused for the first time
+    pub fn new() -> LayoutMemory<F> {
+        LayoutMemory::default()
+    }
+
+    /// Create a new layout memory switching to `layout` for targets focused for the first time
+    pub fn with_default(layout: Layout) -> LayoutMemory<F> {
+        LayoutMemory {
+            default: Some(layout),
+            layouts: Vec::new(),
+        }
+    }
+
+    /// Record the layout active for the focused target
+    pub fn layout_changed(&mut self, focus: Option<&F>, layout: Layout) {
+        let Some(focus) = focus else {
+            return;
+        };
+        self.layouts.retain(|(target, _)| target.alive());
+        match self.layouts.iter_mut().find(|(target, _)| target == focus) {
+            Some((_, remembered)) => *remembered = layout,
+            None => self.layouts.push((focus.clone(), layout)),
+        }
+    }
+
+    /// Returns the layout to switch to for the newly focused target, if any
+    pub fn focus_changed(&mut self, focus: Option<&F>) -> Option<Layout> {
+        self.layouts.retain(|(target, _)| target.alive());
+        let focus = focus?;
+        self.layouts
+            .iter()
+            .find(|(target, _)| target == focus)
+            .map(|(_, layout)| *layout)
+            .or(self.default)
+    }
+
+    /// Returns the layout remembered for the given target
+    pub fn layout(&self, target: &F) -> Option<Layout> {
+        self.layouts
+            .iter()
+            .find(|(t, _)| t == target)
+            .map(|(_, layout)| *layout)
+    }
+
+    /// Forget the layout of the given target
+    pub fn forget(&mut self, target: &F) {
+        self.layouts.retain(|(t, _)| t != target);
+    }
+
+    /// Forget all remembered layouts, e.g. after the keymap was changed
+    pub fn clear(&mut self) {
+        self.layouts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layout, LayoutMemory};
+    use crate::utils::IsAlive;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Target(u32, bool);
+
+    impl IsAlive for Target {
+        fn alive(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn restores_per_target() {
+        let a = Target(1, true);
+        let b = Target(2, true);
+        let mut memory = LayoutMemory::with_default(Layout(0));
+
+        assert_eq!(memory.focus_changed(Some(&a)), Some(Layout(0)));
+        memory.layout_changed(Some(&a), Layout(1));
+        assert_eq!(memory.focus_changed(Some(&b)), Some(Layout(0)));
+        assert_eq!(memory.focus_changed(Some(&a)), Some(Layout(1)));
+        assert_eq!(memory.focus_changed(None), None);
+
+        memory.forget(&a);
+        assert_eq!(memory.layout(&a), None);
+    }
+
+    #[test]
+    fn prunes_dead_targets() {
+        let mut memory = LayoutMemory::new();
+        memory.layout_changed(Some(&Target(1, false)), Layout(1));
+        memory.layout_changed(Some(&Target(2, true)), Layout(2));
+        assert_eq!(memory.focus_changed(Some(&Target(3, true))), None);
+        assert_eq!(memory.layouts.len(), 1);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{sync::Arc, time::Duration};
+
+use xkbcommon::xkb::Keycode;
+
+use crate::{
+    backend::input::KeyState,
+    utils::{system_time_source, Monotonic, Time, TimeSource},
+};
+
+/// Compositor-side key repeat
+///
+/// Clients repeat keys themselves using the repeat info of the keyboard. Compositors repeating
+/// keys on their own, e.g. for keybindings, can use a `KeyRepeat` to decide when a held key
+/// repeats: report every key with [`KeyRepeat::key`], schedule a timer for
+/// [`KeyRepeat::next_repeat`] and call [`KeyRepeat::repeats`] when it fires.
+///
+/// The time is read from a [`TimeSource`], which tests can replace with a
+/// [`ManualClock`](crate::utils::ManualClock).
+#[derive(Debug)]
+pub struct KeyRepeat {
+    rate: i32,
+    delay: i32,
+    time_source: Arc<dyn TimeSource>,
+    held: Option<(Keycode, Time<Monotonic>)>,
+}
+
+impl KeyRepeat {
+    /// Create a new key repeat with the given rate in characters per second and delay in
+    /// milliseconds
+    ///
+    /// A rate of zero or less disables key repeat, like for `wl_keyboard.repeat_info`.
+    pub fn new(rate: i32, delay: i32) -> Self {
+        Self::with_time_source(rate, delay, system_time_source())
+    }
+
+    /// Create a new key repeat reading the time from `time_source`
+    pub fn with_time_source(rate: i32, delay: i32, time_source: Arc<dyn TimeSource>) -> Self {
+        KeyRepeat {
+            rate,
+            delay,
+            time_source,
+            held: None,
+        }
+    }
+
+    /// Change the rate and delay, see [`KeyRepeat::new`]
+    ///
+    /// A key held at the moment keeps its next repeat time.
+    pub fn set_repeat_info(&mut self, rate: i32, delay: i32) {
+        self.rate = rate;
+        self.delay = delay;
+        if rate <= 0 {
+            self.held = None;
+        }
+    }
+
+    /// Notify about a key press or release
+    ///
+    /// Pressing a key starts repeating it after the delay and stops repeating the previous one.
+    /// Releasing the repeating key stops it.
+    pub fn key(&mut self, keycode: Keycode, state: KeyState) {
+        match state {
+            KeyState::Pressed if self.rate > 0 => {
+                let delay = Duration::from_millis(self.delay.max(0) as u64);
+                self.held = Some((keycode, self.time_source.now() + delay));
+            }
+            KeyState::Pressed => self.held = None,
+            KeyState::Released => {
+                if self.held.is_some_and(|(held, _)| held == keycode) {
+                    self.held = None;
+                }
+            }
+        }
+    }
+
+    /// Stop repeating, e.g. when the keyboard focus changed
+    pub fn cancel(&mut self) {
+        self.held = None;
+    }
+
+    /// Returns the repeating key, if any
+    pub fn repeating(&self) -> Option<Keycode> {
+        self.held.map(|(keycode, _)| keycode)
+    }
+
+    /// Returns the time of the next repeat, if a key is repeating
+    pub fn next_repeat(&self) -> Option<Time<Monotonic>> {
+        self.held.map(|(_, next)| next)
+    }
+
+    /// Returns the repeating key and how often it repeated since the last call
+    ///
+    /// Returns `None` if no key is repeating or the next repeat is not due yet.
+    pub fn repeats(&mut self) -> Option<(Keycode, u32)> {
+        let now = self.time_source.now();
+        let interval = Duration::from_secs(1) / self.rate.max(1) as u32;
+        let (keycode, next) = self.held.as_mut()?;
+        let elapsed = now.checked_duration_since(*next)?;
+
+        let count = (elapsed.as_nanos() / interval.as_nanos()) as u32 + 1;
+        *next = *next + interval * count;
+        Some((*keycode, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use xkbcommon::xkb::Keycode;
+
+    use super::KeyRepeat;
+    use crate::{backend::input::KeyState, utils::ManualClock};
+
+    #[test]
+    fn repeats_after_delay() {
+        let key = Keycode::from(30u32);
+        let clock = ManualClock::default();
+        let mut repeat = KeyRepeat::with_time_source(25, 600, Arc::new(clock.clone()));
+        repeat.key(key, KeyState::Pressed);
+        assert_eq!(repeat.repeats(), None);
+
+        clock.advance(Duration::from_millis(600));
+        assert_eq!(repeat.repeats(), Some((key, 1)));
+        assert_eq!(repeat.repeats(), None);
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(repeat.repeats(), Some((key, 2)));
+
+        repeat.key(Keycode::from(31u32), KeyState::Released);
+        assert_eq!(repeat.repeating(), Some(key));
+        repeat.key(key, KeyState::Released);
+        assert_eq!(repeat.next_repeat(), None);
+    }
+
+    #[test]
+    fn zero_rate_disables_repeat() {
+        let key = Keycode::from(30u32);
+        let clock = ManualClock::default();
+        let mut repeat = KeyRepeat::with_time_source(0, 0, Arc::new(clock.clone()));
+        repeat.key(key, KeyState::Pressed);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(repeat.repeats(), None);
+    }
+}
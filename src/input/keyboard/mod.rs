@@ -11,6 +11,7 @@ mod implementation {
 use crate::backend::input::KeyState;
 use crate::utils::{IsAlive, Serial, SERIAL_COUNTER};
 use downcast_rs::{impl_downcast, Downcast};
+use smallvec::SmallVec;
 use std::collections::HashSet;
 #[cfg(feature = "wayland_frontend")]
 use std::sync::RwLock;
@@ -34,9 +35,22 @@ mod keymap_file;
 #[cfg(feature = "wayland_frontend")]
 pub use keymap_file::{KeymapFile, KeymapFileId};
 
+mod compose;
+use compose::Composer;
+pub use compose::ComposeStatus;
+
+mod keybindings;
+pub use keybindings::{ChordModifiers, KeyChord, Keybindings};
+
+mod layout_memory;
+pub use layout_memory::LayoutMemory;
+
 mod modifiers_state;
 pub use modifiers_state::{ModifiersState, SerializedMods};
 
+mod repeat;
+pub use repeat::KeyRepeat;
+
 mod xkb_config;
 pub use xkb_config::XkbConfig;
 
@@ -222,6 +236,7 @@ pub(crate) struct KbdInternal<D: SeatHandler> {
     pub(crate) repeat_delay: i32,
     led_mapping: LedMapping,
     pub(crate) led_state: LedState,
+    composer: Option<Composer>,
     grab: GrabStatus<dyn KeyboardGrab<D>>,
 }
 
@@ -273,12 +288,13 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             repeat_delay,
             led_mapping,
             led_state,
+            composer: None,
             grab: GrabStatus::None,
         })
     }
 
-    // returns whether the modifiers or led state has changed
-    fn key_input(&mut self, keycode: Keycode, state: KeyState) -> (bool, bool) {
+    // returns whether the modifiers, led state or active layout has changed
+    fn key_input(&mut self, keycode: Keycode, state: KeyState) -> (bool, bool, bool) {
         // track pressed keys as xkbcommon does not seem to expose it :(
         let direction = match state {
             KeyState::Pressed => {
@@ -301,7 +317,8 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             self.mods_state.update_with(&xkb.state);
         }
         let leds_changed = self.led_state.update_with(&xkb.state, &self.led_mapping);
-        (modifiers_changed, leds_changed)
+        let layout_changed = state_components & xkb::STATE_LAYOUT_EFFECTIVE != 0;
+        (modifiers_changed, leds_changed, layout_changed)
     }
 
     fn with_grab<F>(&mut self, data: &mut D, seat: &Seat<D>, f: F)
@@ -356,6 +373,9 @@ pub enum Error {
     /// Smithay could not create a tempfile to share the keymap with clients
     #[error("Failed to create tempfile to share the keymap: {0}")]
     IoError(io::Error),
+    /// libxkbcommon could not load the compose table of the specified locale
+    #[error("Libxkbcommon could not load the compose table")]
+    BadComposeTable,
 }
 
 pub(crate) struct KbdRc<D: SeatHandler> {
@@ -394,6 +414,7 @@ impl<D: SeatHandler> fmt::Debug for KbdRc<D> {
 pub struct KeysymHandle<'a> {
     xkb: &'a Mutex<Xkb>,
     keycode: Keycode,
+    compose: ComposeStatus,
 }
 
 impl fmt::Debug for KeysymHandle<'_> {
@@ -408,6 +429,14 @@ impl<'a> KeysymHandle<'a> {
         self.xkb
     }
 
+    /// Returns the state of the compose sequence after this key press
+    ///
+    /// Always [`ComposeStatus::Nothing`] unless compose handling was enabled with
+    /// [`KeyboardHandle::enable_compose`] or [`KeyboardHandle::enable_dead_keys`].
+    pub fn compose_status(&self) -> &ComposeStatus {
+        &self.compose
+    }
+
     /// Returns the sym for the underlying keycode with all modifications by the current keymap state applied.
     ///
     /// This function is similar to [`KeysymHandle::modified_syms`], but is intended for cases where the user
@@ -419,16 +448,20 @@ impl<'a> KeysymHandle<'a> {
     }
 
     /// Returns the syms for the underlying keycode with all modifications by the current keymap state applied.
-    pub fn modified_syms(&self) -> Vec<Keysym> {
-        self.xkb.lock().unwrap().state.key_get_syms(self.keycode).to_vec()
+    ///
+    /// Keys rarely produce more than two syms, so this does not allocate in practice.
+    pub fn modified_syms(&self) -> SmallVec<[Keysym; 2]> {
+        SmallVec::from_slice(self.xkb.lock().unwrap().state.key_get_syms(self.keycode))
     }
 
     /// Returns the syms for the underlying keycode without any modifications by the current keymap state applied.
-    pub fn raw_syms(&self) -> Vec<Keysym> {
+    pub fn raw_syms(&self) -> SmallVec<[Keysym; 2]> {
         let xkb = self.xkb.lock().unwrap();
-        xkb.keymap
-            .key_get_syms_by_level(self.keycode, xkb.state.key_get_layout(self.keycode), 0)
-            .to_vec()
+        SmallVec::from_slice(xkb.keymap.key_get_syms_by_level(
+            self.keycode,
+            xkb.state.key_get_layout(self.keycode),
+            0,
+        ))
     }
 
     /// Get the raw latin keysym or fallback to current raw keysym.
@@ -491,6 +524,7 @@ pub struct XkbContext<'a> {
     leds_state: &'a mut LedState,
     leds_changed: &'a mut bool,
     leds_mapping: &'a LedMapping,
+    layout_changed: &'a mut bool,
 }
 
 impl XkbContext<'_> {
@@ -516,6 +550,9 @@ impl XkbContext<'_> {
             self.mods_state.update_with(&xkb.state);
             *self.mods_changed = true;
         }
+        if state & xkb::STATE_LAYOUT_EFFECTIVE != 0 {
+            *self.layout_changed = true;
+        }
 
         *self.leds_changed = self.leds_state.update_with(&xkb.state, self.leds_mapping);
     }
@@ -852,10 +889,11 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     where
         F: FnMut(XkbContext<'_>) -> T,
     {
-        let (result, new_led_state) = {
+        let (result, new_led_state, new_layout) = {
             let internal = &mut *self.arc.internal.lock().unwrap();
             let mut mods_changed = false;
             let mut leds_changed = false;
+            let mut layout_changed = false;
             let state = XkbContext {
                 mods_state: &mut internal.mods_state,
                 xkb: &mut internal.xkb,
@@ -863,6 +901,7 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                 leds_state: &mut internal.led_state,
                 leds_changed: &mut leds_changed,
                 leds_mapping: &internal.led_mapping,
+                layout_changed: &mut layout_changed,
             };
 
             let result = callback(state);
@@ -874,17 +913,94 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                 };
             }
 
-            (result, leds_changed.then_some(internal.led_state))
+            (
+                result,
+                leds_changed.then_some(internal.led_state),
+                layout_changed.then(|| internal.xkb.lock().unwrap().active_layout()),
+            )
         };
 
         if let Some(led_state) = new_led_state {
             let seat = self.get_seat(data);
             data.led_state_changed(&seat, led_state)
         }
+        if let Some(layout) = new_layout {
+            let seat = self.get_seat(data);
+            data.layout_changed(&seat, layout)
+        }
 
         result
     }
 
+    /// Enable compose key handling using the compose table of the given locale
+    ///
+    /// The result of every key press is then available through [`KeysymHandle::compose_status`]
+    /// in the filter of [`KeyboardHandle::input`], so compositor-side shortcuts and text input can
+    /// act on composed characters. The locale is usually taken from `LC_ALL`, `LC_CTYPE` or `LANG`.
+    ///
+    /// On Windows, where compose tables are usually not installed, this falls back to
+    /// [dead key handling](KeyboardHandle::enable_dead_keys) if the table cannot be loaded.
+    pub fn enable_compose(&self, locale: &std::ffi::OsStr) -> Result<(), Error> {
+        let mut internal = self.arc.internal.lock().unwrap();
+        let composer = {
+            let xkb = internal.xkb.lock().unwrap();
+            Composer::from_locale(&xkb.context, locale)
+        };
+        #[cfg(windows)]
+        let composer = composer.or(Some(Composer::DeadKeys(None)));
+        let Some(composer) = composer else {
+            debug!(?locale, "Failed to load compose table");
+            return Err(Error::BadComposeTable);
+        };
+        internal.composer = Some(composer);
+        Ok(())
+    }
+
+    /// Enable a built-in handling of the common dead keys, without using a compose table
+    ///
+    /// Supports acute, grave, circumflex, diaeresis, tilde, cedilla and ring accents
+    /// of latin letters.
+    pub fn enable_dead_keys(&self) {
+        self.arc.internal.lock().unwrap().composer = Some(Composer::DeadKeys(None));
+    }
+
+    /// Disable compose and dead key handling
+    pub fn disable_compose(&self) {
+        self.arc.internal.lock().unwrap().composer = None;
+    }
+
+    /// Abort any compose sequence in progress
+    ///
+    /// This happens automatically when the keyboard focus changes.
+    pub fn reset_compose(&self) {
+        if let Some(composer) = self.arc.internal.lock().unwrap().composer.as_mut() {
+            composer.reset();
+        }
+    }
+
+    /// Returns the active layout of the keyboard
+    pub fn active_layout(&self) -> Layout {
+        let internal = self.arc.internal.lock().unwrap();
+        let xkb = internal.xkb.lock().unwrap();
+        xkb.active_layout()
+    }
+
+    /// Returns the layouts of the current keymap together with their human readable names
+    pub fn layouts(&self) -> Vec<(Layout, String)> {
+        let internal = self.arc.internal.lock().unwrap();
+        let xkb = internal.xkb.lock().unwrap();
+        xkb.layouts()
+            .map(|layout| (layout, xkb.layout_name(layout).to_owned()))
+            .collect()
+    }
+
+    /// Switch the keyboard to the given layout
+    ///
+    /// Shorthand for [`XkbContext::set_layout`] through [`KeyboardHandle::with_xkb_state`].
+    pub fn set_layout(&self, data: &mut D, layout: Layout) {
+        self.with_xkb_state(data, |mut context| context.set_layout(layout));
+    }
+
     /// Change the current grab on this keyboard to the provided grab
     ///
     /// Overwrites any current grab.
@@ -996,13 +1112,24 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         trace!("Handling keystroke");
 
         let mut guard = self.arc.internal.lock().unwrap();
-        let (mods_changed, leds_changed) = guard.key_input(keycode, state);
+        let (mods_changed, leds_changed, layout_changed) = guard.key_input(keycode, state);
         let led_state = guard.led_state;
+        let layout = layout_changed.then(|| guard.xkb.lock().unwrap().active_layout());
         let mods_state = guard.mods_state;
         let xkb = guard.xkb.clone();
+        let compose = match (state, guard.composer.as_mut()) {
+            (KeyState::Pressed, Some(composer)) => {
+                composer.feed(xkb.lock().unwrap().state.key_get_one_sym(keycode))
+            }
+            _ => ComposeStatus::Nothing,
+        };
         std::mem::drop(guard);
 
-        let key_handle = KeysymHandle { xkb: &xkb, keycode };
+        let key_handle = KeysymHandle {
+            xkb: &xkb,
+            keycode,
+            compose,
+        };
 
         trace!(mods_state = ?mods_state, sym = xkb::keysym_get_name(key_handle.modified_sym()), "Calling input filter");
         let filter_result = filter(data, &mods_state, key_handle);
@@ -1011,6 +1138,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
             let seat = self.get_seat(data);
             data.led_state_changed(&seat, led_state);
         }
+        if let Some(layout) = layout {
+            let seat = self.get_seat(data);
+            data.layout_changed(&seat, layout);
+        }
 
         (filter_result, mods_changed)
     }
@@ -1086,6 +1217,7 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                 .map(|keycode| KeysymHandle {
                     xkb: &guard.xkb,
                     keycode: *keycode,
+                    compose: ComposeStatus::Nothing,
                 })
                 .collect::<Vec<_>>();
             f(handles)
@@ -1147,6 +1279,12 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         self.arc.internal.lock().unwrap().focus.is_some()
     }
 
+    /// Returns a [`KeyRepeat`] using the repeat info configured for this keyboard
+    pub fn key_repeat(&self) -> KeyRepeat {
+        let guard = self.arc.internal.lock().unwrap();
+        KeyRepeat::new(guard.repeat_rate, guard.repeat_delay)
+    }
+
     /// Change the repeat info configured for this keyboard
     #[instrument(parent = &self.arc.span, skip(self))]
     pub fn change_repeat_info(&self, rate: i32, delay: i32) {
@@ -1261,6 +1399,7 @@ impl<D: SeatHandler + 'static> KeyboardInnerHandle<'_, D> {
         KeysymHandle {
             keycode,
             xkb: &self.inner.xkb,
+            compose: ComposeStatus::Nothing,
         }
     }
 
@@ -1297,6 +1436,7 @@ impl<D: SeatHandler + 'static> KeyboardInnerHandle<'_, D> {
         let key = KeysymHandle {
             xkb: &self.inner.xkb,
             keycode,
+            compose: ComposeStatus::Nothing,
         };
 
         focus.key(self.seat, data, key, key_state, serial, time);
@@ -1332,6 +1472,13 @@ impl<D: SeatHandler + 'static> KeyboardInnerHandle<'_, D> {
         focus: Option<<D as SeatHandler>::KeyboardFocus>,
         serial: Serial,
     ) {
+        if self.inner.focus.as_ref().map(|(focus, _)| focus) != focus.as_ref() {
+            // a compose sequence started in one client must not complete in another
+            if let Some(composer) = self.inner.composer.as_mut() {
+                composer.reset();
+            }
+        }
+
         if let Some(focus) = focus {
             let old_focus = self.inner.focus.replace((focus.clone(), serial));
             match (focus, old_focus) {
@@ -1347,6 +1494,7 @@ impl<D: SeatHandler + 'static> KeyboardInnerHandle<'_, D> {
                         .map(|keycode| KeysymHandle {
                             xkb: &self.inner.xkb,
                             keycode: *keycode,
+                            compose: ComposeStatus::Nothing,
                         })
                         .collect();
 
@@ -1361,6 +1509,7 @@ impl<D: SeatHandler + 'static> KeyboardInnerHandle<'_, D> {
                         .map(|keycode| KeysymHandle {
                             xkb: &self.inner.xkb,
                             keycode: *keycode,
+                            compose: ComposeStatus::Nothing,
                         })
                         .collect();
 
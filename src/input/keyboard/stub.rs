@@ -65,9 +65,23 @@ impl Default for XkbConfig<'_> {
     }
 }
 
-/// Stub for LedState
+/// Current state of the led when available
+///
+/// Without xkbcommon the state is not tracked by the keyboard, but can still be used to
+/// drive the indicators of the keyboards, see [`KeyboardLeds`](crate::backend::input::KeyboardLeds).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub struct LedState;
+pub struct LedState {
+    /// State of NUMLOCK led
+    pub num: Option<bool>,
+    /// State of CAPSLOCK led
+    pub caps: Option<bool>,
+    /// State of SCROLLLOCK led
+    pub scroll: Option<bool>,
+}
+
+/// Stub for Layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Layout(pub u32);
 
 /// Stub Error
 #[derive(Debug, thiserror::Error)]
@@ -128,7 +128,7 @@ use tracing::{info_span, instrument};
 
 use self::touch::TouchTarget;
 use self::{
-    keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget, LedState},
+    keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget, Layout, LedState},
     touch::TouchHandle,
 };
 use self::{
@@ -162,6 +162,11 @@ pub trait SeatHandler: Sized {
 
     /// Callback that will be notified whenever the keyboard led state changes.
     fn led_state_changed(&mut self, _seat: &Seat<Self>, _led_state: LedState) {}
+
+    /// Callback that will be notified whenever the active keyboard layout changes.
+    ///
+    /// This is not called when the keymap itself is replaced.
+    fn layout_changed(&mut self, _seat: &Seat<Self>, _layout: Layout) {}
 }
 /// Delegate type for all [Seat] globals.
 ///
@@ -9,7 +9,7 @@ use crate::{
     backend::input::{Axis, AxisRelativeDirection, AxisSource, ButtonState},
     input::{GrabStatus, Seat, SeatHandler},
     utils::Serial,
-    utils::{Clock, IsAlive, Logical, Monotonic, Point},
+    utils::{Clock, IsAlive, Logical, Monotonic, Point, Rectangle},
 };
 
 mod cursor_image;
@@ -243,6 +243,46 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         });
     }
 
+    /// Warp the pointer to a new location
+    ///
+    /// Unlike [`PointerHandle::motion`] this is meant for moves initiated by the compositor or
+    /// requested by clients, e.g. through the [pointer warp protocol](crate::wayland::pointer_warp).
+    /// The location is first clamped to the output `layout` using [`clamp_to_layout`], then
+    /// `focus` is queried for the surface under the clamped location and a motion event followed
+    /// by a frame is sent. No relative motion is generated.
+    ///
+    /// Returns the clamped location, which backends drawing with a host cursor (like the
+    /// [`win32`](crate::backend::win32) helpers) need to reposition it to.
+    pub fn warp<F>(
+        &self,
+        data: &mut D,
+        location: Point<f64, Logical>,
+        layout: &[Rectangle<i32, Logical>],
+        focus: F,
+        serial: Serial,
+        time: u32,
+    ) -> Point<f64, Logical>
+    where
+        F: FnOnce(
+            &mut D,
+            Point<f64, Logical>,
+        ) -> Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+    {
+        let location = clamp_to_layout(location, layout);
+        let under = focus(data, location);
+        self.motion(
+            data,
+            under,
+            &MotionEvent {
+                location,
+                serial,
+                time,
+            },
+        );
+        self.frame(data);
+        location
+    }
+
     /// Notify about relative pointer motion
     ///
     /// This will internally send the appropriate button event to the client
@@ -929,6 +969,38 @@ pub enum Focus {
     Clear,
 }
 
+/// Clamp a location to the closest point inside of an output layout
+///
+/// `layout` usually contains the geometries of all outputs. Locations inside of any of the
+/// rectangles are returned unchanged, otherwise the closest point on the edge of the nearest
+/// rectangle is returned. An empty layout does not restrict the location.
+pub fn clamp_to_layout(
+    location: Point<f64, Logical>,
+    layout: &[Rectangle<i32, Logical>],
+) -> Point<f64, Logical> {
+    if layout.iter().any(|geo| geo.to_f64().contains(location)) {
+        return location;
+    }
+
+    layout
+        .iter()
+        .filter(|geo| !geo.is_empty())
+        .map(|geo| {
+            let geo = geo.to_f64();
+            // keep the location on the last pixel, rectangles do not contain their bottom right edges
+            let max = geo.loc + geo.size.to_point() - Point::from((1.0, 1.0));
+            Point::from((
+                location.x.clamp(geo.loc.x, max.x.max(geo.loc.x)),
+                location.y.clamp(geo.loc.y, max.y.max(geo.loc.y)),
+            ))
+        })
+        .min_by(|a, b| {
+            let distance = |p: &Point<f64, Logical>| (p.x - location.x).powi(2) + (p.y - location.y).powi(2);
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap_or(location)
+}
+
 /// Pointer motion event
 #[derive(Debug, Clone)]
 pub struct MotionEvent {
@@ -0,0 +1,412 @@
+//! Gesture recognition from raw touch events
+//!
+//! Touchpads driven by libinput report swipe, pinch and hold gestures natively. Other touch
+//! sources, like touchscreens, `WM_POINTER` on Windows or raw evdev devices, only report
+//! individual touch points. A [`GestureRecognizer`] tracks those points and synthesizes the
+//! matching gesture events, which can then be sent to clients through the pointer gestures
+//! protocol using [`RecognizedGesture::dispatch`].
+//!
+//! As soon as two or more fingers touch the device a hold gesture begins. Once the fingers move
+//! further than the configured threshold the hold is cancelled and the motion is classified:
+//! changing the distance between the fingers or rotating them starts a pinch gesture, moving
+//! enough fingers in the same direction starts a swipe gesture. Adding a finger to a running
+//! gesture cancels it, lifting a finger ends it. No new gesture starts until all fingers
+//! were lifted.
+
+use crate::{
+    backend::input::TouchSlot,
+    input::{
+        pointer::{
+            GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent,
+            GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent,
+            PointerHandle,
+        },
+        SeatHandler,
+    },
+    utils::{Logical, Point, SERIAL_COUNTER},
+};
+
+/// Gesture event synthesized by a [`GestureRecognizer`]
+#[derive(Debug, Clone)]
+pub enum RecognizedGesture {
+    /// A swipe gesture began
+    SwipeBegin(GestureSwipeBeginEvent),
+    /// A swipe gesture moved
+    SwipeUpdate(GestureSwipeUpdateEvent),
+    /// A swipe gesture ended
+    SwipeEnd(GestureSwipeEndEvent),
+    /// A pinch gesture began
+    PinchBegin(GesturePinchBeginEvent),
+    /// A pinch gesture moved, scaled or rotated
+    PinchUpdate(GesturePinchUpdateEvent),
+    /// A pinch gesture ended
+    PinchEnd(GesturePinchEndEvent),
+    /// A hold gesture began
+    HoldBegin(GestureHoldBeginEvent),
+    /// A hold gesture ended
+    HoldEnd(GestureHoldEndEvent),
+}
+
+impl RecognizedGesture {
+    /// Send this gesture to the given pointer
+    pub fn dispatch<D: SeatHandler + 'static>(&self, pointer: &PointerHandle<D>, data: &mut D) {
+        match self {
+            RecognizedGesture::SwipeBegin(event) => pointer.gesture_swipe_begin(data, event),
+            RecognizedGesture::SwipeUpdate(event) => pointer.gesture_swipe_update(data, event),
+            RecognizedGesture::SwipeEnd(event) => pointer.gesture_swipe_end(data, event),
+            RecognizedGesture::PinchBegin(event) => pointer.gesture_pinch_begin(data, event),
+            RecognizedGesture::PinchUpdate(event) => pointer.gesture_pinch_update(data, event),
+            RecognizedGesture::PinchEnd(event) => pointer.gesture_pinch_end(data, event),
+            RecognizedGesture::HoldBegin(event) => pointer.gesture_hold_begin(data, event),
+            RecognizedGesture::HoldEnd(event) => pointer.gesture_hold_end(data, event),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shape {
+    centroid: Point<f64, Logical>,
+    spread: f64,
+    angle: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Hold { fingers: u32, origin: Shape },
+    Swipe { last: Shape },
+    Pinch { initial_spread: f64, last: Shape },
+    // a gesture ended, wait for all fingers to be lifted
+    Finished,
+}
+
+/// Synthesizes gestures from touch points, see the [module-level documentation](self)
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    points: Vec<(TouchSlot, Point<f64, Logical>)>,
+    state: State,
+    threshold: f64,
+    min_swipe_fingers: u32,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        GestureRecognizer::new()
+    }
+}
+
+impl GestureRecognizer {
+    /// Create a new recognizer with a threshold of 16 logical pixels and swipes of 3 or more fingers
+    pub fn new() -> GestureRecognizer {
+        GestureRecognizer {
+            points: Vec::new(),
+            state: State::Idle,
+            threshold: 16.0,
+            min_swipe_fingers: 3,
+        }
+    }
+
+    /// Set the distance in logical pixels the fingers need to move to start a swipe or pinch gesture
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = threshold.max(0.0);
+    }
+
+    /// Set the number of fingers required for swipe gestures
+    ///
+    /// Motion of fewer fingers is recognized as pinch gesture without scale and rotation.
+    pub fn set_min_swipe_fingers(&mut self, fingers: u32) {
+        self.min_swipe_fingers = fingers.max(2);
+    }
+
+    /// Returns the number of fingers currently touching the device
+    pub fn fingers(&self) -> u32 {
+        self.points.len() as u32
+    }
+
+    /// Process a touch down event
+    ///
+    /// `time` is the timestamp of the event in milliseconds.
+    pub fn down(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        time: u32,
+    ) -> Vec<RecognizedGesture> {
+        self.points.retain(|(s, _)| *s != slot);
+        self.points.push((slot, location));
+
+        let mut gestures = Vec::new();
+        match self.state {
+            State::Idle => self.begin_hold(time, &mut gestures),
+            State::Hold { .. } => {
+                // restart the hold with the new number of fingers
+                gestures.push(hold_end(time, true));
+                self.begin_hold(time, &mut gestures);
+            }
+            State::Swipe { .. } | State::Pinch { .. } => {
+                self.end(time, true, &mut gestures);
+                self.state = State::Finished;
+            }
+            State::Finished => {}
+        }
+        gestures
+    }
+
+    /// Process a touch motion event
+    ///
+    /// `time` is the timestamp of the event in milliseconds.
+    pub fn motion(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        time: u32,
+    ) -> Vec<RecognizedGesture> {
+        let Some(point) = self.points.iter_mut().find(|(s, _)| *s == slot) else {
+            return Vec::new();
+        };
+        point.1 = location;
+
+        let mut gestures = Vec::new();
+        let shape = self.shape();
+        match self.state {
+            State::Hold { fingers, origin } => {
+                let offset = shape.centroid - origin.centroid;
+                let moved = offset.x.hypot(offset.y);
+                let spread = (shape.spread - origin.spread).abs();
+                let rotated = angle_delta(origin.angle, shape.angle).to_radians().abs() * shape.spread;
+
+                let pinch = spread > self.threshold || rotated > self.threshold;
+                if !pinch && moved <= self.threshold {
+                    return gestures;
+                }
+
+                gestures.push(hold_end(time, true));
+                if !pinch && fingers >= self.min_swipe_fingers {
+                    gestures.push(RecognizedGesture::SwipeBegin(GestureSwipeBeginEvent {
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                        fingers,
+                    }));
+                    self.state = State::Swipe { last: origin };
+                } else {
+                    gestures.push(RecognizedGesture::PinchBegin(GesturePinchBeginEvent {
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                        fingers,
+                    }));
+                    self.state = State::Pinch {
+                        initial_spread: origin.spread,
+                        last: origin,
+                    };
+                }
+                gestures.extend(self.update(shape, time));
+            }
+            State::Swipe { .. } | State::Pinch { .. } => gestures.extend(self.update(shape, time)),
+            State::Idle | State::Finished => {}
+        }
+        gestures
+    }
+
+    /// Process a touch up event
+    ///
+    /// `time` is the timestamp of the event in milliseconds.
+    pub fn up(&mut self, slot: TouchSlot, time: u32) -> Vec<RecognizedGesture> {
+        let Some(index) = self.points.iter().position(|(s, _)| *s == slot) else {
+            return Vec::new();
+        };
+        self.points.remove(index);
+
+        let mut gestures = Vec::new();
+        self.end(time, false, &mut gestures);
+        self.state = if self.points.is_empty() {
+            State::Idle
+        } else {
+            State::Finished
+        };
+        gestures
+    }
+
+    /// Process a touch cancel event, cancelling any running gesture
+    ///
+    /// `time` is the timestamp of the event in milliseconds.
+    pub fn cancel(&mut self, time: u32) -> Vec<RecognizedGesture> {
+        let mut gestures = Vec::new();
+        self.end(time, true, &mut gestures);
+        self.points.clear();
+        self.state = State::Idle;
+        gestures
+    }
+
+    fn begin_hold(&mut self, time: u32, gestures: &mut Vec<RecognizedGesture>) {
+        let fingers = self.fingers();
+        if fingers < 2 {
+            return;
+        }
+        gestures.push(RecognizedGesture::HoldBegin(GestureHoldBeginEvent {
+            serial: SERIAL_COUNTER.next_serial(),
+            time,
+            fingers,
+        }));
+        self.state = State::Hold {
+            fingers,
+            origin: self.shape(),
+        };
+    }
+
+    fn update(&mut self, shape: Shape, time: u32) -> Option<RecognizedGesture> {
+        match &mut self.state {
+            State::Swipe { last } => {
+                let delta = shape.centroid - last.centroid;
+                *last = shape;
+                Some(RecognizedGesture::SwipeUpdate(GestureSwipeUpdateEvent {
+                    time,
+                    delta,
+                }))
+            }
+            State::Pinch { initial_spread, last } => {
+                let delta = shape.centroid - last.centroid;
+                let rotation = angle_delta(last.angle, shape.angle);
+                let scale = if *initial_spread > 0.0 {
+                    shape.spread / *initial_spread
+                } else {
+                    1.0
+                };
+                *last = shape;
+                Some(RecognizedGesture::PinchUpdate(GesturePinchUpdateEvent {
+                    time,
+                    delta,
+                    scale,
+                    rotation,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    fn end(&mut self, time: u32, cancelled: bool, gestures: &mut Vec<RecognizedGesture>) {
+        match self.state {
+            State::Hold { .. } => gestures.push(hold_end(time, cancelled)),
+            State::Swipe { .. } => gestures.push(RecognizedGesture::SwipeEnd(GestureSwipeEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+                cancelled,
+            })),
+            State::Pinch { .. } => gestures.push(RecognizedGesture::PinchEnd(GesturePinchEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+                cancelled,
+            })),
+            State::Idle | State::Finished => {}
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        let count = self.points.len().max(1) as f64;
+        let sum = self
+            .points
+            .iter()
+            .fold(Point::<f64, Logical>::default(), |sum, (_, point)| sum + *point);
+        let centroid = Point::from((sum.x / count, sum.y / count));
+        let spread = self
+            .points
+            .iter()
+            .map(|(_, point)| (*point - centroid).x.hypot((*point - centroid).y))
+            .sum::<f64>()
+            / count;
+        let angle = self
+            .points
+            .first()
+            .map(|(_, point)| {
+                let offset = *point - centroid;
+                offset.y.atan2(offset.x).to_degrees()
+            })
+            .unwrap_or_default();
+        Shape {
+            centroid,
+            spread,
+            angle,
+        }
+    }
+}
+
+fn hold_end(time: u32, cancelled: bool) -> RecognizedGesture {
+    RecognizedGesture::HoldEnd(GestureHoldEndEvent {
+        serial: SERIAL_COUNTER.next_serial(),
+        time,
+        cancelled,
+    })
+}
+
+// clockwise difference in degrees between two angles in the range [-180, 180)
+fn angle_delta(from: f64, to: f64) -> f64 {
+    (to - from + 540.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GestureRecognizer, RecognizedGesture};
+    use crate::backend::input::TouchSlot;
+
+    fn slot(id: u32) -> TouchSlot {
+        Some(id).into()
+    }
+
+    #[test]
+    fn three_finger_swipe() {
+        let mut recognizer = GestureRecognizer::new();
+        assert!(recognizer.down(slot(0), (0.0, 0.0).into(), 0).is_empty());
+        recognizer.down(slot(1), (10.0, 0.0).into(), 0);
+        let gestures = recognizer.down(slot(2), (20.0, 0.0).into(), 0);
+        assert!(matches!(
+            gestures.as_slice(),
+            [RecognizedGesture::HoldEnd(end), RecognizedGesture::HoldBegin(begin)]
+                if end.cancelled && begin.fingers == 3
+        ));
+
+        // below the threshold
+        assert!(recognizer.motion(slot(2), (50.0, 0.0).into(), 1).is_empty());
+        let gestures = recognizer.motion(slot(1), (40.0, 0.0).into(), 2);
+        assert!(matches!(
+            gestures.as_slice(),
+            [
+                RecognizedGesture::HoldEnd(_),
+                RecognizedGesture::SwipeBegin(begin),
+                RecognizedGesture::SwipeUpdate(update)
+            ] if begin.fingers == 3 && (update.delta.x - 20.0).abs() < 1e-9
+        ));
+
+        let gestures = recognizer.up(slot(0), 3);
+        assert!(matches!(gestures.as_slice(), [RecognizedGesture::SwipeEnd(end)] if !end.cancelled));
+        // no new gesture until all fingers are lifted
+        assert!(recognizer.motion(slot(1), (100.0, 0.0).into(), 4).is_empty());
+        assert!(recognizer.up(slot(1), 5).is_empty());
+        assert!(recognizer.up(slot(2), 5).is_empty());
+        assert_eq!(recognizer.fingers(), 0);
+    }
+
+    #[test]
+    fn two_finger_pinch() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.down(slot(0), (0.0, 0.0).into(), 0);
+        recognizer.down(slot(1), (100.0, 0.0).into(), 0);
+        let gestures = recognizer.motion(slot(1), (200.0, 0.0).into(), 1);
+        let [RecognizedGesture::HoldEnd(_), RecognizedGesture::PinchBegin(_), RecognizedGesture::PinchUpdate(update)] =
+            gestures.as_slice()
+        else {
+            panic!("unexpected gestures: {gestures:?}");
+        };
+        assert!((update.scale - 2.0).abs() < 1e-9);
+        assert!((update.delta.x - 50.0).abs() < 1e-9);
+        assert!(update.rotation.abs() < 1e-9);
+
+        // rotate clockwise
+        let gestures = recognizer.motion(slot(1), (100.0, 100.0).into(), 2);
+        let [RecognizedGesture::PinchUpdate(update)] = gestures.as_slice() else {
+            panic!("unexpected gestures: {gestures:?}");
+        };
+        assert!((update.rotation - 45.0).abs() < 1e-9);
+
+        let gestures = recognizer.cancel(3);
+        assert!(matches!(gestures.as_slice(), [RecognizedGesture::PinchEnd(end)] if end.cancelled));
+    }
+}
@@ -11,10 +11,12 @@ use wayland_server::Weak;
 use crate::backend::input::TouchSlot;
 use crate::utils::{IsAlive, Logical, Point, Serial, SerialCounter};
 
+pub use gesture::{GestureRecognizer, RecognizedGesture};
 pub use grab::{DefaultGrab, GrabStartData, TouchDownGrab, TouchGrab};
 
 use super::{GrabStatus, Seat, SeatHandler};
 
+mod gesture;
 mod grab;
 
 /// An handle to a touch handler
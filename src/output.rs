@@ -52,6 +52,7 @@
 
 use std::{
     hash::{Hash, Hasher},
+    path::Path,
     sync::{Arc, Mutex, Weak},
 };
 
@@ -70,6 +71,24 @@ use wayland_server::{
 
 use crate::utils::{self, user_data::UserDataMap, Logical, Physical, Point, Raw, Size, Transform};
 
+mod frame_clock;
+mod hdr;
+mod icc;
+mod night_light;
+mod stats;
+#[cfg(feature = "wayland_frontend")]
+mod virtual_output;
+pub use self::frame_clock::{FrameClock, FrameMode, IdleStats, RedrawReasons};
+pub use self::hdr::{Chromaticity, ColorEncoding, HdrCapabilities, HdrMetadata, Primaries};
+pub use self::icc::{ColorLut, IccError, IccProfile};
+pub use self::night_light::{
+    temperature_color_lut, temperature_to_rgb, GammaRamp, NightLightSchedule, NightLightSource,
+    NEUTRAL_TEMPERATURE,
+};
+pub use self::stats::{missed_vblanks, FrameStats, OutputStats, OutputStatsSummary, DEFAULT_STATS_CAPACITY};
+#[cfg(feature = "wayland_frontend")]
+pub use self::virtual_output::{VirtualOutputConfig, VirtualOutputs};
+
 /// An output mode
 ///
 /// A possible combination of dimensions and refresh rate for an output.
@@ -225,6 +244,10 @@ pub(crate) struct Inner {
     pub(crate) modes: Vec<Mode>,
     pub(crate) current_mode: Option<Mode>,
     pub(crate) preferred_mode: Option<Mode>,
+    pub(crate) color_encoding: ColorEncoding,
+    pub(crate) hdr_metadata: Option<HdrMetadata>,
+    pub(crate) hdr_capabilities: HdrCapabilities,
+    pub(crate) icc_profile: Option<Arc<IccProfile>>,
 
     // used by the wayland::output module.
     #[cfg(feature = "wayland_frontend")]
@@ -276,6 +299,10 @@ impl Output {
                 modes: Vec::new(),
                 current_mode: None,
                 preferred_mode: None,
+                color_encoding: ColorEncoding::Srgb,
+                hdr_metadata: None,
+                hdr_capabilities: HdrCapabilities::default(),
+                icc_profile: None,
                 #[cfg(feature = "wayland_frontend")]
                 xdg_output: None,
                 #[cfg(feature = "wayland_frontend")]
@@ -407,6 +434,102 @@ impl Output {
         self.wl_change_current_state(new_mode, new_transform.map(Into::into), new_scale, new_location)
     }
 
+    /// Change the transform of this output
+    ///
+    /// Shorthand for [`Output::change_current_state`] only changing the transform.
+    pub fn set_transform(&self, transform: Transform) {
+        self.change_current_state(None, Some(transform), None, None);
+    }
+
+    /// Map a position on the panel of this output into the global compositor space
+    ///
+    /// Panel positions are in physical pixels of the untransformed display, as reported by
+    /// absolute input devices mapped to the output, like touch screens. The transform, scale and
+    /// location of the output are applied.
+    ///
+    /// Returns `None` if the output has no current mode.
+    pub fn panel_to_global(&self, position: Point<f64, Physical>) -> Option<Point<f64, Logical>> {
+        let inner = self.inner.0.lock().unwrap();
+        let size = inner.current_mode?.size.to_f64();
+        let transformed = panel_transform(inner.transform).transform_point_in(position, &size);
+        Some(transformed.to_logical(inner.scale.fractional_scale()) + inner.location.to_f64())
+    }
+
+    /// Map a position in the global compositor space onto the panel of this output
+    ///
+    /// This is the inverse of [`Output::panel_to_global`], e.g. to place a hardware cursor. It
+    /// matches the transform [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker)
+    /// renders the output with.
+    ///
+    /// Returns `None` if the output has no current mode.
+    pub fn global_to_panel(&self, position: Point<f64, Logical>) -> Option<Point<f64, Physical>> {
+        let inner = self.inner.0.lock().unwrap();
+        let size = inner.current_mode?.size.to_f64();
+        let render_transform = render_transform(inner.transform);
+        let local = (position - inner.location.to_f64()).to_physical(inner.scale.fractional_scale());
+        Some(render_transform.transform_point_in(local, &render_transform.transform_size(size)))
+    }
+
+    /// Sets the HDR capabilities of this output
+    ///
+    /// Backends call this after probing the connected display.
+    pub fn set_hdr_capabilities(&self, capabilities: HdrCapabilities) {
+        self.inner.0.lock().unwrap().hdr_capabilities = capabilities;
+    }
+
+    /// Returns the HDR capabilities of this output
+    pub fn hdr_capabilities(&self) -> HdrCapabilities {
+        self.inner.0.lock().unwrap().hdr_capabilities.clone()
+    }
+
+    /// Sets the color encoding the content of this output is rendered in
+    ///
+    /// The backend has to be told separately to signal the encoding to the display,
+    /// e.g. with `backend::drm::hdr::set_connector_hdr`.
+    pub fn set_color_encoding(&self, encoding: ColorEncoding) {
+        self.inner.0.lock().unwrap().color_encoding = encoding;
+    }
+
+    /// Returns the color encoding the content of this output is rendered in
+    ///
+    /// Defaults to [`ColorEncoding::Srgb`].
+    pub fn color_encoding(&self) -> ColorEncoding {
+        self.inner.0.lock().unwrap().color_encoding
+    }
+
+    /// Sets the static HDR metadata describing the content of this output
+    ///
+    /// `None` lets the display pick its defaults.
+    pub fn set_hdr_metadata(&self, metadata: Option<HdrMetadata>) {
+        self.inner.0.lock().unwrap().hdr_metadata = metadata;
+    }
+
+    /// Returns the static HDR metadata describing the content of this output
+    pub fn hdr_metadata(&self) -> Option<HdrMetadata> {
+        self.inner.0.lock().unwrap().hdr_metadata
+    }
+
+    /// Load the ICC profile of the display showing this output
+    ///
+    /// The profile is not applied automatically. Renderers convert the content with a lookup
+    /// table computed by [`IccProfile::color_lut`], e.g. with
+    /// `GlesRenderer::create_color_lut` or [`ColorLut::apply_argb8888`] for software rendering.
+    pub fn set_icc_profile(&self, path: impl AsRef<Path>) -> Result<(), IccError> {
+        let profile = IccProfile::load(path)?;
+        self.inner.0.lock().unwrap().icc_profile = Some(Arc::new(profile));
+        Ok(())
+    }
+
+    /// Remove the ICC profile of this output
+    pub fn clear_icc_profile(&self) {
+        self.inner.0.lock().unwrap().icc_profile = None;
+    }
+
+    /// Returns the ICC profile of this output, if any
+    pub fn icc_profile(&self) -> Option<Arc<IccProfile>> {
+        self.inner.0.lock().unwrap().icc_profile.clone()
+    }
+
     /// Returns the user data of this output
     pub fn user_data(&self) -> &UserDataMap {
         &self.inner.1
@@ -494,6 +617,23 @@ impl PartialEq<Output> for WeakOutput {
     }
 }
 
+// The output transform is specified in surface rotation, so its inverse maps the transformed
+// output space onto the panel. This is the transform outputs are rendered with.
+pub(crate) fn render_transform(transform: Transform) -> Transform {
+    transform.invert()
+}
+
+// Transform mapping panel positions into the transformed output space, undoing the render
+// transform. Flipped transforms are reflections and thus their own inverse.
+fn panel_transform(transform: Transform) -> Transform {
+    let render_transform = render_transform(transform);
+    if render_transform.flipped() {
+        render_transform
+    } else {
+        render_transform.invert()
+    }
+}
+
 /// Source for determining output mode information.
 #[derive(PartialEq, Clone, Debug)]
 pub enum OutputModeSource {
@@ -553,3 +693,57 @@ impl TryFrom<OutputModeSource> for (Size<i32, Physical>, utils::Scale<f64>, Tran
 #[derive(Debug, thiserror::Error)]
 #[error("Output has no active mode")]
 pub struct OutputNoMode;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panel_mapping_roundtrip() {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "test".into(),
+                model: "test".into(),
+                serial_number: "test".into(),
+            },
+        );
+        assert_eq!(output.panel_to_global((0.0, 0.0).into()), None);
+
+        let mode = Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, Some(Scale::Integer(2)), Some((100, 0).into()));
+        assert_eq!(
+            output.panel_to_global((1920.0, 1080.0).into()),
+            Some((1060.0, 540.0).into())
+        );
+
+        // a panel mounted in portrait orientation, rotated clockwise
+        output.set_transform(Transform::_90);
+        // the top left corner of the panel becomes the top right corner of the output
+        assert_eq!(
+            output.panel_to_global((0.0, 0.0).into()),
+            Some((100.0 + 540.0, 0.0).into())
+        );
+
+        let point = Point::<f64, Physical>::from((300.0, 200.0));
+        for transform in [
+            Transform::Normal,
+            Transform::_90,
+            Transform::_180,
+            Transform::_270,
+            Transform::Flipped,
+            Transform::Flipped90,
+            Transform::Flipped180,
+            Transform::Flipped270,
+        ] {
+            output.set_transform(transform);
+            let global = output.panel_to_global(point).unwrap();
+            assert_eq!(output.global_to_panel(global), Some(point), "{transform:?}");
+        }
+    }
+}
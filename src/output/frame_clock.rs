@@ -0,0 +1,437 @@
+//! On-demand frame scheduling
+//!
+//! By default compositors tend to render a new frame for every vblank of an output, even if
+//! nothing on it changed. A [`FrameClock`] in [`FrameMode::OnDemand`] instead only lets a frame
+//! be rendered when there is a reason to: pending damage, cursor motion or a running animation.
+//! This cuts the power draw of idle outputs, which matters for laptops and virtual machines.
+//!
+//! The compositor reports every reason for a redraw with [`FrameClock::queue_redraw`], asks
+//! [`FrameClock::should_render`] whenever it would usually start a new frame and calls
+//! [`FrameClock::frame_rendered`] after submitting one. The time spent idle is tracked as
+//! [`IdleStats`].
+//!
+//! A compositor in [`FrameMode::OnDemand`] stops rendering, and with it stops receiving vblanks,
+//! once an output went idle. [`FrameClock::set_waker`] registers a callback invoked when a redraw
+//! is queued on an idle clock, so the compositor can schedule a frame again, e.g. through a
+//! [`Notifier`](crate::compat::notifier::Notifier) waking the event loop.
+//!
+//! Both take the current time explicitly. [`FrameClock::should_render_now`] and
+//! [`FrameClock::frame_rendered_now`] read it from the [`TimeSource`] of the clock instead, which
+//! is the system monotonic clock unless replaced with [`FrameClock::set_time_source`], e.g. by a
+//! [`ManualClock`](crate::utils::ManualClock) in tests.
+//!
+//! ```
+//! # extern crate smithay;
+//! use std::time::Duration;
+//! use smithay::output::{FrameMode, Output, PhysicalProperties, RedrawReasons, Subpixel};
+//! use smithay::utils::Time;
+//!
+//! # let output = Output::new("output-0".into(), PhysicalProperties {
+//! #     size: (0, 0).into(),
+//! #     subpixel: Subpixel::Unknown,
+//! #     make: "".into(),
+//! #     model: "".into(),
+//! #     serial_number: "".into(),
+//! # });
+//! let clock = output.frame_clock();
+//! clock.set_mode(FrameMode::OnDemand);
+//!
+//! // nothing changed, the vblank can be skipped
+//! assert!(!clock.should_render(Time::from(Duration::from_millis(16))));
+//!
+//! clock.queue_redraw(RedrawReasons::DAMAGE);
+//! assert!(clock.should_render(Time::from(Duration::from_millis(33))));
+//! clock.frame_rendered(Time::from(Duration::from_millis(33)));
+//!
+//! assert_eq!(clock.idle_stats().skipped_frames, 1);
+//! ```
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::Output;
+use crate::utils::{Clock, Monotonic, Time, TimeSource};
+
+/// When frames are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FrameMode {
+    /// Render a frame for every vblank
+    #[default]
+    Continuous,
+    /// Only render a frame if there is a reason to redraw
+    OnDemand,
+}
+
+bitflags::bitflags! {
+    /// Reasons to render a new frame
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    pub struct RedrawReasons: u32 {
+        /// Some content of the output was damaged
+        const DAMAGE = 1;
+        /// The cursor moved or changed its image
+        const CURSOR = 2;
+        /// An animation is running, see [`FrameClock::set_animating`]
+        const ANIMATION = 4;
+        /// The system resumed from suspend and the output has to be redrawn from scratch, see
+        /// [`FrameClock::set_suspended`]
+        const RESUME = 8;
+    }
+}
+
+/// Statistics about the frames skipped by a [`FrameClock`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdleStats {
+    /// Number of frames rendered
+    pub rendered_frames: u64,
+    /// Number of frames skipped because there was nothing to redraw
+    pub skipped_frames: u64,
+    /// Total time spent idle between rendered frames
+    pub idle_time: Duration,
+    /// Longest time spent idle between two rendered frames
+    pub longest_idle: Duration,
+    /// Start of the current idle period, if no frame was rendered since
+    pub idle_since: Option<Time<Monotonic>>,
+}
+
+impl IdleStats {
+    /// Fraction of frames skipped, between `0.0` and `1.0`
+    pub fn idle_ratio(&self) -> f64 {
+        let total = self.rendered_frames + self.skipped_frames;
+        if total == 0 {
+            return 0.0;
+        }
+        self.skipped_frames as f64 / total as f64
+    }
+}
+
+type Waker = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct FrameClockInner {
+    mode: FrameMode,
+    pending: RedrawReasons,
+    suspended: bool,
+    stats: IdleStats,
+    time_source: Option<Arc<dyn TimeSource>>,
+    waker: Option<Waker>,
+}
+
+impl std::fmt::Debug for FrameClockInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameClockInner")
+            .field("mode", &self.mode)
+            .field("pending", &self.pending)
+            .field("suspended", &self.suspended)
+            .field("stats", &self.stats)
+            .field("time_source", &self.time_source)
+            .field("waker", &self.waker.is_some())
+            .finish()
+    }
+}
+
+impl FrameClockInner {
+    // the waker to call after adding `reasons`, if that ends an idle period
+    fn add_pending(&mut self, reasons: RedrawReasons) -> Option<Waker> {
+        let was_pending = !self.pending.is_empty();
+        self.pending |= reasons;
+        let idle = self.mode == FrameMode::OnDemand && self.stats.idle_since.is_some();
+        if was_pending || reasons.is_empty() || !idle || self.suspended {
+            return None;
+        }
+        self.waker.clone()
+    }
+}
+
+/// Decides whether an output needs a new frame
+///
+/// Cloning a `FrameClock` returns a handle to the same clock.
+#[derive(Debug, Clone, Default)]
+pub struct FrameClock(Arc<Mutex<FrameClockInner>>);
+
+impl FrameClock {
+    /// Create a new clock using the given mode
+    pub fn new(mode: FrameMode) -> Self {
+        FrameClock(Arc::new(Mutex::new(FrameClockInner {
+            mode,
+            ..Default::default()
+        })))
+    }
+
+    /// Create a new clock using the given mode, reading the time from `time_source`
+    pub fn with_time_source(mode: FrameMode, time_source: Arc<dyn TimeSource>) -> Self {
+        FrameClock(Arc::new(Mutex::new(FrameClockInner {
+            mode,
+            time_source: Some(time_source),
+            ..Default::default()
+        })))
+    }
+
+    /// Replace the source of the current time
+    pub fn set_time_source(&self, time_source: Arc<dyn TimeSource>) {
+        self.0.lock().unwrap().time_source = Some(time_source);
+    }
+
+    /// Returns the current time of the [`TimeSource`] of this clock
+    pub fn now(&self) -> Time<Monotonic> {
+        let time_source = self.0.lock().unwrap().time_source.clone();
+        match time_source {
+            Some(time_source) => time_source.now(),
+            None => Clock::<Monotonic>::new().now(),
+        }
+    }
+
+    /// Returns the current mode
+    pub fn mode(&self) -> FrameMode {
+        self.0.lock().unwrap().mode
+    }
+
+    /// Set the mode of the clock
+    pub fn set_mode(&self, mode: FrameMode) {
+        self.0.lock().unwrap().mode = mode;
+    }
+
+    /// Set the callback invoked when a redraw is queued while the clock is idle
+    ///
+    /// In [`FrameMode::OnDemand`] the clock is idle once [`FrameClock::should_render`] returned
+    /// `false`, until the next frame is rendered. The waker is called at most once per idle
+    /// period, and when the system resumed, see [`FrameClock::set_suspended`]. It is called
+    /// without the clock locked, so it may use the clock.
+    pub fn set_waker<F>(&self, waker: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.0.lock().unwrap().waker = Some(Arc::new(waker));
+    }
+
+    /// Remove the callback set with [`FrameClock::set_waker`]
+    pub fn clear_waker(&self) {
+        self.0.lock().unwrap().waker = None;
+    }
+
+    /// Request a new frame for the given reasons
+    ///
+    /// Calls the waker set with [`FrameClock::set_waker`] if the clock is idle.
+    pub fn queue_redraw(&self, reasons: RedrawReasons) {
+        let waker = self.0.lock().unwrap().add_pending(reasons);
+        if let Some(waker) = waker {
+            waker();
+        }
+    }
+
+    /// Set whether an animation is running on the output
+    ///
+    /// Unlike other reasons, [`RedrawReasons::ANIMATION`] is kept after a frame was rendered
+    /// until the animation is marked as finished. Starting an animation wakes an idle clock
+    /// like [`FrameClock::queue_redraw`].
+    pub fn set_animating(&self, animating: bool) {
+        if animating {
+            self.queue_redraw(RedrawReasons::ANIMATION);
+        } else {
+            self.0.lock().unwrap().pending.remove(RedrawReasons::ANIMATION);
+        }
+    }
+
+    /// Set whether the system is suspended
+    ///
+    /// While suspended no frames should be rendered, regardless of the mode. Resuming queues
+    /// a redraw for [`RedrawReasons::RESUME`].
+    pub fn set_suspended(&self, suspended: bool) {
+        let mut inner = self.0.lock().unwrap();
+        let resumed = inner.suspended && !suspended;
+        inner.suspended = suspended;
+        // no frames were rendered while suspended, so any mode has to be woken up
+        let waker = if resumed {
+            inner.pending |= RedrawReasons::RESUME;
+            inner.waker.clone()
+        } else {
+            None
+        };
+        drop(inner);
+        if let Some(waker) = waker {
+            waker();
+        }
+    }
+
+    /// Returns whether the clock is suspended, see [`FrameClock::set_suspended`]
+    pub fn is_suspended(&self) -> bool {
+        self.0.lock().unwrap().suspended
+    }
+
+    /// Returns the reasons for the next frame
+    pub fn pending(&self) -> RedrawReasons {
+        self.0.lock().unwrap().pending
+    }
+
+    /// Returns whether a frame should be rendered now
+    ///
+    /// Always returns `false` while suspended and `true` in [`FrameMode::Continuous`]. Otherwise
+    /// a `false` return value counts as a skipped frame and starts an idle period, if not already
+    /// idle.
+    pub fn should_render(&self, now: Time<Monotonic>) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        if inner.suspended {
+            return false;
+        }
+        if inner.mode == FrameMode::Continuous || !inner.pending.is_empty() {
+            return true;
+        }
+
+        inner.stats.skipped_frames += 1;
+        inner.stats.idle_since.get_or_insert(now);
+        false
+    }
+
+    /// Returns whether a frame should be rendered, see [`FrameClock::should_render`]
+    pub fn should_render_now(&self) -> bool {
+        self.should_render(self.now())
+    }
+
+    /// Notify the clock that a frame was rendered
+    ///
+    /// Clears all pending reasons except a running animation and ends the current idle period.
+    pub fn frame_rendered(&self, now: Time<Monotonic>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.pending &= RedrawReasons::ANIMATION;
+
+        let stats = &mut inner.stats;
+        stats.rendered_frames += 1;
+        if let Some(since) = stats.idle_since.take() {
+            let idle = now.saturating_duration_since(since);
+            stats.idle_time += idle;
+            stats.longest_idle = stats.longest_idle.max(idle);
+        }
+    }
+
+    /// Notify the clock that a frame was rendered, see [`FrameClock::frame_rendered`]
+    pub fn frame_rendered_now(&self) {
+        self.frame_rendered(self.now())
+    }
+
+    /// Returns the idle statistics of this clock
+    pub fn idle_stats(&self) -> IdleStats {
+        self.0.lock().unwrap().stats
+    }
+
+    /// Reset the idle statistics
+    pub fn reset_idle_stats(&self) {
+        self.0.lock().unwrap().stats = IdleStats::default();
+    }
+}
+
+impl Output {
+    /// Returns the frame clock of this output
+    ///
+    /// The clock is created on first access in [`FrameMode::Continuous`].
+    pub fn frame_clock(&self) -> FrameClock {
+        self.user_data()
+            .get_or_insert_threadsafe(FrameClock::default)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ManualClock;
+
+    fn at(ms: u64) -> Time<Monotonic> {
+        Time::from(Duration::from_millis(ms))
+    }
+
+    #[test]
+    fn on_demand_skips_idle_frames() {
+        let clock = FrameClock::new(FrameMode::OnDemand);
+        assert!(!clock.should_render(at(10)));
+        assert!(!clock.should_render(at(20)));
+
+        clock.queue_redraw(RedrawReasons::CURSOR);
+        assert!(clock.should_render(at(30)));
+        clock.frame_rendered(at(30));
+        assert!(!clock.should_render(at(40)));
+
+        let stats = clock.idle_stats();
+        assert_eq!(stats.rendered_frames, 1);
+        assert_eq!(stats.skipped_frames, 3);
+        assert_eq!(stats.idle_time, Duration::from_millis(20));
+        assert_eq!(stats.idle_since, Some(at(40)));
+        assert_eq!(stats.idle_ratio(), 0.75);
+    }
+
+    #[test]
+    fn animation_keeps_rendering() {
+        let clock = FrameClock::new(FrameMode::OnDemand);
+        clock.set_animating(true);
+        clock.queue_redraw(RedrawReasons::DAMAGE);
+        clock.frame_rendered(at(10));
+        assert_eq!(clock.pending(), RedrawReasons::ANIMATION);
+        assert!(clock.should_render(at(20)));
+
+        clock.set_animating(false);
+        assert!(!clock.should_render(at(30)));
+    }
+
+    #[test]
+    fn redraw_after_resume() {
+        let clock = FrameClock::new(FrameMode::Continuous);
+        clock.set_suspended(true);
+        assert!(!clock.should_render(at(10)));
+        assert_eq!(clock.idle_stats().skipped_frames, 0);
+
+        clock.set_suspended(false);
+        assert_eq!(clock.pending(), RedrawReasons::RESUME);
+        assert!(clock.should_render(at(20)));
+    }
+
+    #[test]
+    fn wakes_idle_clock() {
+        let wakeups = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let clock = FrameClock::new(FrameMode::OnDemand);
+        let counter = wakeups.clone();
+        clock.set_waker(move || {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        let wakeups = || wakeups.load(std::sync::atomic::Ordering::SeqCst);
+
+        // not idle before the first skipped frame
+        clock.queue_redraw(RedrawReasons::DAMAGE);
+        assert_eq!(wakeups(), 0);
+        clock.frame_rendered(at(10));
+
+        assert!(!clock.should_render(at(20)));
+        clock.queue_redraw(RedrawReasons::CURSOR);
+        clock.queue_redraw(RedrawReasons::DAMAGE);
+        assert_eq!(wakeups(), 1);
+        assert!(clock.should_render(at(30)));
+        clock.frame_rendered(at(30));
+
+        assert!(!clock.should_render(at(40)));
+        clock.set_animating(true);
+        assert_eq!(wakeups(), 2);
+        clock.frame_rendered(at(50));
+
+        // continuous clocks render for every vblank anyway
+        clock.set_animating(false);
+        clock.set_mode(FrameMode::Continuous);
+        assert!(clock.should_render(at(60)));
+        clock.queue_redraw(RedrawReasons::DAMAGE);
+        assert_eq!(wakeups(), 2);
+    }
+
+    #[test]
+    fn manual_time_source() {
+        let time = ManualClock::new(at(100));
+        let clock = FrameClock::with_time_source(FrameMode::OnDemand, Arc::new(time.clone()));
+        assert!(!clock.should_render_now());
+
+        time.advance(Duration::from_millis(250));
+        clock.queue_redraw(RedrawReasons::DAMAGE);
+        assert!(clock.should_render_now());
+        clock.frame_rendered_now();
+
+        let stats = clock.idle_stats();
+        assert_eq!(stats.idle_time, Duration::from_millis(250));
+        assert_eq!(clock.now(), at(350));
+    }
+}
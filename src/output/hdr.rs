@@ -0,0 +1,117 @@
+use crate::backend::allocator::Fourcc;
+
+/// Encoding of the colors sent to an output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorEncoding {
+    /// Standard dynamic range with sRGB primaries and transfer function
+    #[default]
+    Srgb,
+    /// HDR10: BT.2020 primaries with the SMPTE ST 2084 (PQ) transfer function
+    Pq,
+    /// Linear BT.709 primaries extended beyond `[0, 1]`, requiring a floating point buffer
+    ScRgb,
+}
+
+impl ColorEncoding {
+    /// Returns `true` for the HDR encodings
+    pub fn is_hdr(&self) -> bool {
+        *self != ColorEncoding::Srgb
+    }
+
+    /// Buffer formats suitable for rendering in this encoding, in order of preference
+    ///
+    /// PQ needs at least 10 bits per channel to avoid banding, scRGB needs half floats.
+    pub fn buffer_formats(&self) -> &'static [Fourcc] {
+        match self {
+            ColorEncoding::Srgb => &[Fourcc::Argb8888, Fourcc::Xrgb8888],
+            ColorEncoding::Pq => &[
+                Fourcc::Argb2101010,
+                Fourcc::Xrgb2101010,
+                Fourcc::Abgr2101010,
+                Fourcc::Xbgr2101010,
+            ],
+            ColorEncoding::ScRgb => &[Fourcc::Abgr16161616f, Fourcc::Xbgr16161616f],
+        }
+    }
+}
+
+/// CIE 1931 xy chromaticity coordinates
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Chromaticity {
+    /// x coordinate
+    pub x: f32,
+    /// y coordinate
+    pub y: f32,
+}
+
+/// Chromaticities of the primaries and white point of a display
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Primaries {
+    /// Red primary
+    pub red: Chromaticity,
+    /// Green primary
+    pub green: Chromaticity,
+    /// Blue primary
+    pub blue: Chromaticity,
+    /// White point
+    pub white: Chromaticity,
+}
+
+impl Primaries {
+    /// Primaries of BT.2020, with a D65 white point
+    pub const BT2020: Primaries = Primaries {
+        red: Chromaticity { x: 0.708, y: 0.292 },
+        green: Chromaticity { x: 0.170, y: 0.797 },
+        blue: Chromaticity { x: 0.131, y: 0.046 },
+        white: Chromaticity { x: 0.3127, y: 0.3290 },
+    };
+}
+
+/// Static HDR metadata (SMPTE ST 2086 and CTA-861.3) describing the content sent to an output
+///
+/// Displays use it to tone map the content into their capabilities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrMetadata {
+    /// Primaries of the mastering display
+    pub primaries: Primaries,
+    /// Maximum luminance of the mastering display in cd/m²
+    pub max_luminance: f32,
+    /// Minimum luminance of the mastering display in cd/m²
+    pub min_luminance: f32,
+    /// Maximum content light level in cd/m²
+    pub max_cll: f32,
+    /// Maximum frame-average light level in cd/m²
+    pub max_fall: f32,
+}
+
+impl Default for HdrMetadata {
+    fn default() -> Self {
+        HdrMetadata {
+            primaries: Primaries::BT2020,
+            max_luminance: 1000.0,
+            min_luminance: 0.005,
+            max_cll: 1000.0,
+            max_fall: 400.0,
+        }
+    }
+}
+
+/// HDR capabilities of an output, as reported by its backend
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HdrCapabilities {
+    /// Encodings the output can be driven with
+    pub encodings: Vec<ColorEncoding>,
+    /// Maximum luminance of the display in cd/m², if known
+    pub max_luminance: Option<f32>,
+    /// Maximum frame-average luminance of the display in cd/m², if known
+    pub max_fall: Option<f32>,
+    /// Minimum luminance of the display in cd/m², if known
+    pub min_luminance: Option<f32>,
+}
+
+impl HdrCapabilities {
+    /// Returns `true` if the output supports any HDR encoding
+    pub fn supports_hdr(&self) -> bool {
+        self.encodings.iter().any(ColorEncoding::is_hdr)
+    }
+}
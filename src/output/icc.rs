@@ -0,0 +1,479 @@
+use std::{fs, io, path::Path};
+
+/// Errors loading an ICC profile
+#[derive(Debug, thiserror::Error)]
+pub enum IccError {
+    /// The profile could not be read
+    #[error("Failed to read the ICC profile: {0}")]
+    Io(#[from] io::Error),
+    /// The data is not a valid ICC profile
+    #[error("Invalid ICC profile: {0}")]
+    Invalid(&'static str),
+    /// The profile is valid, but uses features that are not supported
+    #[error("Unsupported ICC profile: {0}")]
+    Unsupported(&'static str),
+}
+
+/// Tone response curve of a single channel
+#[derive(Debug, Clone, PartialEq)]
+enum Curve {
+    Identity,
+    Gamma(f32),
+    Table(Vec<f32>),
+    // parametric curve of the given type with up to 7 parameters
+    Parametric(u16, [f32; 7]),
+}
+
+impl Curve {
+    fn eval(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Curve::Identity => x,
+            Curve::Gamma(g) => x.powf(*g),
+            Curve::Table(table) => {
+                let pos = x * (table.len() - 1) as f32;
+                let i = (pos.floor() as usize).min(table.len() - 2);
+                let t = pos - i as f32;
+                table[i] * (1.0 - t) + table[i + 1] * t
+            }
+            Curve::Parametric(kind, [g, a, b, c, d, e, f]) => match kind {
+                0 => x.powf(*g),
+                1 if x >= -b / a => (a * x + b).powf(*g),
+                1 => 0.0,
+                2 if x >= -b / a => (a * x + b).powf(*g) + c,
+                2 => *c,
+                3 if x >= *d => (a * x + b).powf(*g),
+                3 => c * x,
+                _ if x >= *d => (a * x + b).powf(*g) + e,
+                _ => c * x + f,
+            },
+        }
+    }
+
+    // the curves of display profiles are monotonic, so they can be inverted by bisection
+    fn eval_inverse(&self, y: f32) -> f32 {
+        match self {
+            Curve::Identity => y.clamp(0.0, 1.0),
+            Curve::Gamma(g) => y.clamp(0.0, 1.0).powf(1.0 / g),
+            _ => {
+                let increasing = self.eval(1.0) >= self.eval(0.0);
+                let (mut lo, mut hi) = (0.0f32, 1.0f32);
+                for _ in 0..24 {
+                    let mid = (lo + hi) / 2.0;
+                    if (self.eval(mid) < y) == increasing {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                (lo + hi) / 2.0
+            }
+        }
+    }
+}
+
+/// ICC display profile
+///
+/// Only matrix/TRC based RGB profiles are supported, which covers the profiles generated by
+/// common calibration tools and shipped with most displays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    description: Option<String>,
+    // columns are the PCS (D50 XYZ) values of the red, green and blue primaries
+    matrix: [[f32; 3]; 3],
+    curves: [Curve; 3],
+}
+
+// linear sRGB to D50 XYZ, chromatically adapted with the Bradford transform
+const SRGB_TO_XYZ_D50: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.385_064_9, 0.143_080_4],
+    [0.222_504_5, 0.716_878_6, 0.060_616_9],
+    [0.013_932_2, 0.097_104_5, 0.714_173_3],
+];
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn s15_fixed16(data: &[u8], offset: usize) -> Option<f32> {
+    be_u32(data, offset).map(|v| v as i32 as f32 / 65536.0)
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn mul(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn invert(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inv = 1.0 / det;
+    Some([
+        [
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv,
+        ],
+        [
+            (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv,
+        ],
+        [
+            (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv,
+        ],
+    ])
+}
+
+impl IccProfile {
+    /// Load a profile from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<IccProfile, IccError> {
+        IccProfile::parse(&fs::read(path)?)
+    }
+
+    /// Parse a profile from its binary representation
+    pub fn parse(data: &[u8]) -> Result<IccProfile, IccError> {
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err(IccError::Invalid("missing profile signature"));
+        }
+        if &data[16..20] != b"RGB " {
+            return Err(IccError::Unsupported("not an RGB profile"));
+        }
+
+        let count = be_u32(data, 128).unwrap() as usize;
+        let tag = |signature: &[u8; 4]| -> Option<&[u8]> {
+            (0..count).find_map(|i| {
+                let entry = 132 + i * 12;
+                if data.get(entry..entry + 4)? != signature {
+                    return None;
+                }
+                let offset = be_u32(data, entry + 4)? as usize;
+                let size = be_u32(data, entry + 8)? as usize;
+                data.get(offset..offset.checked_add(size)?)
+            })
+        };
+
+        let xyz = |signature| -> Result<[f32; 3], IccError> {
+            let tag = tag(signature).ok_or(IccError::Unsupported("no matrix/TRC tags"))?;
+            if !tag.starts_with(b"XYZ ") {
+                return Err(IccError::Invalid("colorant is not of type XYZ"));
+            }
+            match (s15_fixed16(tag, 8), s15_fixed16(tag, 12), s15_fixed16(tag, 16)) {
+                (Some(x), Some(y), Some(z)) => Ok([x, y, z]),
+                _ => Err(IccError::Invalid("truncated XYZ tag")),
+            }
+        };
+        let curve = |signature| -> Result<Curve, IccError> {
+            let tag = tag(signature).ok_or(IccError::Unsupported("no matrix/TRC tags"))?;
+            match tag.get(0..4) {
+                Some(b"curv") => {
+                    let entries = be_u32(tag, 8).ok_or(IccError::Invalid("truncated curve"))? as usize;
+                    match entries {
+                        0 => Ok(Curve::Identity),
+                        1 => Ok(Curve::Gamma(
+                            be_u16(tag, 12).ok_or(IccError::Invalid("truncated curve"))? as f32 / 256.0,
+                        )),
+                        _ => (0..entries)
+                            .map(|i| be_u16(tag, 12 + i * 2).map(|v| v as f32 / 65535.0))
+                            .collect::<Option<Vec<_>>>()
+                            .map(Curve::Table)
+                            .ok_or(IccError::Invalid("truncated curve")),
+                    }
+                }
+                Some(b"para") => {
+                    let kind = be_u16(tag, 8).ok_or(IccError::Invalid("truncated curve"))?;
+                    let num_params = match kind {
+                        0 => 1,
+                        1 => 3,
+                        2 => 4,
+                        3 => 5,
+                        4 => 7,
+                        _ => return Err(IccError::Invalid("unknown parametric curve")),
+                    };
+                    let mut params = [0.0; 7];
+                    for (i, param) in params.iter_mut().take(num_params).enumerate() {
+                        *param = s15_fixed16(tag, 12 + i * 4).ok_or(IccError::Invalid("truncated curve"))?;
+                    }
+                    Ok(Curve::Parametric(kind, params))
+                }
+                _ => Err(IccError::Unsupported("unknown curve type")),
+            }
+        };
+
+        let [r, g, b] = [xyz(b"rXYZ")?, xyz(b"gXYZ")?, xyz(b"bXYZ")?];
+        let matrix = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+        if invert(&matrix).is_none() {
+            return Err(IccError::Invalid("colorants are not linearly independent"));
+        }
+
+        Ok(IccProfile {
+            description: tag(b"desc").and_then(parse_description),
+            matrix,
+            curves: [curve(b"rTRC")?, curve(b"gTRC")?, curve(b"bTRC")?],
+        })
+    }
+
+    /// Description of the profile, if present
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Converts an sRGB encoded color into the color space of the display
+    pub fn transform(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let inverse = invert(&self.matrix).unwrap();
+        let linear = rgb.map(srgb_to_linear);
+        let device = mul(&inverse, mul(&SRGB_TO_XYZ_D50, linear));
+        [
+            self.curves[0].eval_inverse(device[0]),
+            self.curves[1].eval_inverse(device[1]),
+            self.curves[2].eval_inverse(device[2]),
+        ]
+    }
+
+    /// Compute a 3D lookup table converting sRGB content into the color space of the display
+    ///
+    /// `size` is the number of samples per axis, 17 or 33 are common choices.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn color_lut(&self, size: usize) -> ColorLut {
+        ColorLut::from_fn(size, |rgb| self.transform(rgb))
+    }
+}
+
+fn parse_description(tag: &[u8]) -> Option<String> {
+    match tag.get(0..4)? {
+        // ICC v2 textDescriptionType: ASCII with trailing nul
+        b"desc" => {
+            let len = be_u32(tag, 8)? as usize;
+            let text = tag.get(12..12 + len)?;
+            let text = text.split(|b| *b == 0).next()?;
+            Some(String::from_utf8_lossy(text).into_owned())
+        }
+        // ICC v4 multiLocalizedUnicodeType: use the first record, UTF-16BE
+        b"mluc" => {
+            let len = be_u32(tag, 20)? as usize;
+            let offset = be_u32(tag, 24)? as usize;
+            let text = tag.get(offset..offset + len)?;
+            let units = text
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+/// 3D color lookup table
+///
+/// Samples are stored with red varying fastest, followed by green and blue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLut {
+    size: usize,
+    data: Vec<[f32; 3]>,
+}
+
+impl ColorLut {
+    /// Compute a table by sampling the given color transformation
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn from_fn(size: usize, transform: impl Fn([f32; 3]) -> [f32; 3]) -> ColorLut {
+        assert!(size >= 2, "a lookup table needs at least two samples per axis");
+        let scale = (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push(transform([r as f32 / scale, g as f32 / scale, b as f32 / scale]));
+                }
+            }
+        }
+        ColorLut { size, data }
+    }
+
+    /// Number of samples per axis
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Samples of the table
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    fn sample(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[(b * self.size + g) * self.size + r]
+    }
+
+    /// Look up a color, interpolating trilinearly between the samples
+    pub fn lookup(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let scale = (self.size - 1) as f32;
+        let pos = rgb.map(|v| v.clamp(0.0, 1.0) * scale);
+        let lo = pos.map(|v| (v.floor() as usize).min(self.size - 2));
+        let t = [
+            pos[0] - lo[0] as f32,
+            pos[1] - lo[1] as f32,
+            pos[2] - lo[2] as f32,
+        ];
+
+        let mut out = [0.0; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = (if dr == 1 { t[0] } else { 1.0 - t[0] })
+                * (if dg == 1 { t[1] } else { 1.0 - t[1] })
+                * (if db == 1 { t[2] } else { 1.0 - t[2] });
+            let sample = self.sample(lo[0] + dr, lo[1] + dg, lo[2] + db);
+            for (out, sample) in out.iter_mut().zip(sample) {
+                *out += sample * weight;
+            }
+        }
+        out
+    }
+
+    /// Pack the table into a 2D RGBA8 image for upload to the GPU
+    ///
+    /// The image is `size * size` pixels wide and `size` pixels high, with the blue slices
+    /// placed next to each other. Red varies along the x axis of every slice, green along the
+    /// y axis.
+    pub fn to_rgba8_image(&self) -> Vec<u8> {
+        let mut image = vec![0; self.data.len() * 4];
+        for b in 0..self.size {
+            for g in 0..self.size {
+                for r in 0..self.size {
+                    let x = b * self.size + r;
+                    let offset = (g * self.size * self.size + x) * 4;
+                    let [cr, cg, cb] = self
+                        .sample(r, g, b)
+                        .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+                    image[offset..offset + 4].copy_from_slice(&[cr, cg, cb, 255]);
+                }
+            }
+        }
+        image
+    }
+
+    /// Apply the table in place to premultiplied pixels in the `Argb8888` format
+    ///
+    /// This is the path for software rendering, e.g. after rendering with the pixman renderer.
+    pub fn apply_argb8888(&self, pixels: &mut [u8]) {
+        self.apply_8888(pixels, true)
+    }
+
+    /// Apply the table in place to pixels in the `Xrgb8888` format
+    pub fn apply_xrgb8888(&self, pixels: &mut [u8]) {
+        self.apply_8888(pixels, false)
+    }
+
+    fn apply_8888(&self, pixels: &mut [u8], has_alpha: bool) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            // little endian: b, g, r, a
+            let alpha = if has_alpha { pixel[3] as f32 / 255.0 } else { 1.0 };
+            if alpha == 0.0 {
+                continue;
+            }
+            let rgb = [pixel[2], pixel[1], pixel[0]].map(|v| (v as f32 / 255.0 / alpha).min(1.0));
+            let [r, g, b] = self.lookup(rgb).map(|v| (v * alpha * 255.0).round() as u8);
+            pixel[0] = b;
+            pixel[1] = g;
+            pixel[2] = r;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(v: f32) -> [u8; 4] {
+        ((v * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    // builds a matrix/TRC profile with sRGB colorants and the sRGB curve
+    fn srgb_profile() -> Vec<u8> {
+        let mut tags: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+        for (i, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for row in SRGB_TO_XYZ_D50 {
+                tag.extend(fixed(row[i]));
+            }
+            tags.push((*signature, tag));
+        }
+        let mut curve = b"para\0\0\0\0\0\x03\0\0".to_vec();
+        for param in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+            curve.extend(fixed(param));
+        }
+        for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+            tags.push((*signature, curve.clone()));
+        }
+
+        let mut data = vec![0; 128];
+        data[16..20].copy_from_slice(b"RGB ");
+        data[36..40].copy_from_slice(b"acsp");
+        data.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + tags.len() * 12;
+        let mut body = Vec::new();
+        for (signature, tag) in &tags {
+            data.extend(signature);
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+            body.extend(tag);
+        }
+        data.extend(body);
+        let len = data.len() as u32;
+        data[0..4].copy_from_slice(&len.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn srgb_profile_is_identity() {
+        let profile = IccProfile::parse(&srgb_profile()).unwrap();
+        let lut = profile.color_lut(9);
+        for rgb in [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.25, 0.5, 0.75],
+            [0.9, 0.1, 0.4],
+        ] {
+            let out = lut.lookup(rgb);
+            for (a, b) in rgb.iter().zip(out) {
+                assert!((a - b).abs() < 0.01, "{rgb:?} mapped to {out:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn reject_invalid_profiles() {
+        assert!(matches!(IccProfile::parse(&[0; 64]), Err(IccError::Invalid(_))));
+        let mut data = srgb_profile();
+        data[16..20].copy_from_slice(b"CMYK");
+        assert!(matches!(IccProfile::parse(&data), Err(IccError::Unsupported(_))));
+    }
+}
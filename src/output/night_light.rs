@@ -0,0 +1,334 @@
+use std::time::{Duration, Instant};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use super::ColorLut;
+use crate::{
+    compat::time::Timespec,
+    utils::{Clock, Realtime, Time},
+};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Color temperature of unmodified output in Kelvin
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+// approximation of the blackbody color by Tanner Helland
+fn blackbody(kelvin: u32) -> [f32; 3] {
+    let t = kelvin.clamp(1000, 40000) as f32 / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let g = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    [r, g, b].map(|v| v.clamp(0.0, 255.0) / 255.0)
+}
+
+/// Factors to scale the red, green and blue channels with to shift the white point of an
+/// output to the given color temperature
+///
+/// [`NEUTRAL_TEMPERATURE`] results in `[1.0, 1.0, 1.0]`, the largest factor is always `1.0`.
+pub fn temperature_to_rgb(kelvin: u32) -> [f32; 3] {
+    let neutral = blackbody(NEUTRAL_TEMPERATURE);
+    let color = blackbody(kelvin);
+    let rgb = [
+        color[0] / neutral[0],
+        color[1] / neutral[1],
+        color[2] / neutral[2],
+    ];
+    let max = rgb.iter().copied().fold(f32::MIN, f32::max);
+    rgb.map(|v| v / max)
+}
+
+/// Gamma ramps of the red, green and blue channels of a display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GammaRamp {
+    /// Red channel
+    pub red: Vec<u16>,
+    /// Green channel
+    pub green: Vec<u16>,
+    /// Blue channel
+    pub blue: Vec<u16>,
+}
+
+impl GammaRamp {
+    /// Linear ramps of the given size, shifted to the given color temperature
+    ///
+    /// The size has to match the size of the gamma table of the display.
+    pub fn for_temperature(kelvin: u32, size: usize) -> GammaRamp {
+        let factors = temperature_to_rgb(kelvin);
+        let ramp = |factor: f32| {
+            (0..size)
+                .map(|i| {
+                    let value = i as f32 / (size.max(2) - 1) as f32 * factor;
+                    (value * u16::MAX as f32).round() as u16
+                })
+                .collect()
+        };
+        GammaRamp {
+            red: ramp(factors[0]),
+            green: ramp(factors[1]),
+            blue: ramp(factors[2]),
+        }
+    }
+}
+
+/// Color lookup table shifting the white point of rendered content to the given color temperature
+///
+/// Used instead of a [`GammaRamp`] where displays cannot be programmed with gamma tables,
+/// e.g. on Windows or when nested.
+pub fn temperature_color_lut(kelvin: u32, size: usize) -> ColorLut {
+    let factors = temperature_to_rgb(kelvin);
+    ColorLut::from_fn(size, |rgb| {
+        [rgb[0] * factors[0], rgb[1] * factors[1], rgb[2] * factors[2]]
+    })
+}
+
+// time from `from` to `to`, wrapping around midnight
+fn wrapping_since(from: Duration, to: Duration) -> Duration {
+    let (from, to) = (from.as_secs_f64(), to.as_secs_f64());
+    Duration::from_secs_f64((to - from).rem_euclid(DAY.as_secs_f64()))
+}
+
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Daily schedule of the color temperature
+///
+/// Times are given as the duration since midnight in local time. At `sunset` the temperature
+/// starts to transition from the day to the night temperature over `transition`, and back at
+/// `sunrise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NightLightSchedule {
+    /// Start of the transition to the night temperature
+    pub sunset: Duration,
+    /// Start of the transition to the day temperature
+    pub sunrise: Duration,
+    /// Duration of the transitions
+    pub transition: Duration,
+    /// Temperature during the day in Kelvin
+    pub day_temperature: u32,
+    /// Temperature during the night in Kelvin
+    pub night_temperature: u32,
+}
+
+impl Default for NightLightSchedule {
+    fn default() -> Self {
+        NightLightSchedule {
+            sunset: Duration::from_secs(20 * 60 * 60),
+            sunrise: Duration::from_secs(6 * 60 * 60),
+            transition: Duration::from_secs(30 * 60),
+            day_temperature: NEUTRAL_TEMPERATURE,
+            night_temperature: 4000,
+        }
+    }
+}
+
+impl NightLightSchedule {
+    /// Color temperature at the given time since midnight
+    ///
+    /// Transitions are interpolated smoothly in mired (inverse temperature), which is
+    /// perceived as more uniform than interpolating in Kelvin.
+    pub fn temperature_at(&self, time_of_day: Duration) -> u32 {
+        let transition = self.transition.as_secs_f64().max(1.0);
+        let night_length = wrapping_since(self.sunset, self.sunrise);
+        let since_sunset = wrapping_since(self.sunset, time_of_day);
+        let night = if since_sunset < night_length {
+            smoothstep(since_sunset.as_secs_f64() / transition)
+        } else {
+            let since_sunrise = wrapping_since(self.sunrise, time_of_day);
+            1.0 - smoothstep(since_sunrise.as_secs_f64() / transition)
+        };
+
+        let day_mired = 1e6 / self.day_temperature.max(1) as f64;
+        let night_mired = 1e6 / self.night_temperature.max(1) as f64;
+        (1e6 / (day_mired + (night_mired - day_mired) * night)).round() as u32
+    }
+
+    /// Color temperature right now
+    ///
+    /// `utc_offset` is the offset of the local time zone from UTC in seconds.
+    pub fn current_temperature(&self, utc_offset: i32) -> u32 {
+        self.temperature_at_time(Clock::<Realtime>::new().now(), utc_offset)
+    }
+
+    /// Color temperature at the given wall clock time
+    ///
+    /// `utc_offset` is the offset of the local time zone from UTC in seconds.
+    pub fn temperature_at_time(&self, time: Time<Realtime>, utc_offset: i32) -> u32 {
+        self.temperature_at(local_time_of_day(time, utc_offset))
+    }
+}
+
+fn local_time_of_day(time: Time<Realtime>, utc_offset: i32) -> Duration {
+    let tp = Timespec::from(time);
+    let secs = tp.tv_sec as f64 + tp.tv_nsec as f64 / 1e9;
+    Duration::from_secs_f64((secs + utc_offset as f64).rem_euclid(DAY.as_secs_f64()))
+}
+
+/// Calloop event source generating the color temperature of a [`NightLightSchedule`]
+///
+/// The temperature is checked at the given interval and an event is generated whenever it
+/// changed, starting with the current temperature. During transitions an interval of a few
+/// seconds results in changes too small to notice.
+#[derive(Debug)]
+pub struct NightLightSource {
+    timer: Timer,
+    interval: Duration,
+    schedule: NightLightSchedule,
+    utc_offset: i32,
+    last: Option<u32>,
+}
+
+impl NightLightSource {
+    /// Create a new source for the given schedule
+    ///
+    /// `utc_offset` is the offset of the local time zone from UTC in seconds.
+    pub fn new(schedule: NightLightSchedule, utc_offset: i32, interval: Duration) -> NightLightSource {
+        NightLightSource {
+            timer: Timer::immediate(),
+            interval,
+            schedule,
+            utc_offset,
+            last: None,
+        }
+    }
+
+    /// Replace the schedule, taking effect at the next check
+    pub fn set_schedule(&mut self, schedule: NightLightSchedule) {
+        self.schedule = schedule;
+    }
+
+    /// Change the offset of the local time zone from UTC in seconds
+    pub fn set_utc_offset(&mut self, utc_offset: i32) {
+        self.utc_offset = utc_offset;
+    }
+}
+
+impl EventSource for NightLightSource {
+    type Event = u32;
+    type Metadata = ();
+    type Ret = ();
+    type Error = <Timer as EventSource>::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let interval = self.interval;
+        let schedule = &self.schedule;
+        let utc_offset = self.utc_offset;
+        let last = &mut self.last;
+        self.timer.process_events(readiness, token, |_: Instant, _| {
+            let temperature = schedule.current_temperature(utc_offset);
+            if *last != Some(temperature) {
+                *last = Some(temperature);
+                callback(temperature, &mut ());
+            }
+            TimeoutAction::ToDuration(interval)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(h: f64) -> Duration {
+        Duration::from_secs_f64(h * 3600.0)
+    }
+
+    #[test]
+    fn neutral_temperature() {
+        assert_eq!(temperature_to_rgb(NEUTRAL_TEMPERATURE), [1.0, 1.0, 1.0]);
+        let warm = temperature_to_rgb(3000);
+        assert_eq!(warm[0], 1.0);
+        assert!(warm[1] < 1.0 && warm[2] < warm[1]);
+
+        let ramp = GammaRamp::for_temperature(NEUTRAL_TEMPERATURE, 256);
+        assert_eq!(ramp.red[0], 0);
+        assert_eq!(ramp.red[255], u16::MAX);
+        assert_eq!(ramp.red, ramp.blue);
+    }
+
+    #[test]
+    fn schedule_transitions() {
+        let schedule = NightLightSchedule {
+            sunset: hours(20.0),
+            sunrise: hours(6.0),
+            transition: hours(1.0),
+            day_temperature: 6500,
+            night_temperature: 4000,
+        };
+        assert_eq!(schedule.temperature_at(hours(12.0)), 6500);
+        assert_eq!(schedule.temperature_at(hours(20.0)), 6500);
+        assert_eq!(schedule.temperature_at(hours(21.0)), 4000);
+        assert_eq!(schedule.temperature_at(hours(2.0)), 4000);
+        assert_eq!(schedule.temperature_at(hours(7.0)), 6500);
+
+        let evening = schedule.temperature_at(hours(20.5));
+        let morning = schedule.temperature_at(hours(6.5));
+        assert!(evening > 4000 && evening < 6500);
+        assert!(morning > 4000 && morning < 6500);
+    }
+
+    #[test]
+    fn wall_clock_time() {
+        let schedule = NightLightSchedule {
+            sunset: hours(20.0),
+            sunrise: hours(6.0),
+            transition: hours(1.0),
+            day_temperature: 6500,
+            night_temperature: 4000,
+        };
+        // 2024-01-01 22:30 UTC
+        let time = Time::<Realtime>::from(Timespec {
+            tv_sec: 1_704_148_200,
+            tv_nsec: 0,
+        });
+        assert_eq!(schedule.temperature_at_time(time, 0), 4000);
+        // 14:30 in UTC-8
+        assert_eq!(schedule.temperature_at_time(time, -8 * 3600), 6500);
+        // 00:30 on the next day in UTC+2, wrapping around midnight
+        assert_eq!(schedule.temperature_at_time(time, 2 * 3600), 4000);
+        // 20:30 in UTC-2, during the transition
+        assert_eq!(
+            schedule.temperature_at_time(time, -2 * 3600),
+            schedule.temperature_at(hours(20.5))
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Per-output frame statistics
+//!
+//! An [`OutputStats`] instance collects timing information about the last frames
+//! rendered and presented on an [`Output`]. Backends or the compositor record one
+//! [`FrameStats`] per frame, which can later be queried as a rolling
+//! [`OutputStatsSummary`], e.g. for an on-screen debug overlay or logging.
+//!
+//! The statistics are not exposed to clients; dumping them through a debug protocol is not
+//! provided by smithay and left to the compositor.
+//!
+//! ```
+//! # extern crate smithay;
+//! use std::time::Duration;
+//! use smithay::output::{FrameStats, Output, PhysicalProperties, Subpixel};
+//!
+//! # let output = Output::new("output-0".into(), PhysicalProperties {
+//! #     size: (0, 0).into(),
+//! #     subpixel: Subpixel::Unknown,
+//! #     make: "".into(),
+//! #     model: "".into(),
+//! #     serial_number: "".into(),
+//! # });
+//! output.stats().record_frame(FrameStats {
+//!     render_cpu: Duration::from_micros(1200),
+//!     render_gpu: Some(Duration::from_micros(800)),
+//!     presentation_latency: Some(Duration::from_millis(9)),
+//!     missed_vblanks: 0,
+//! });
+//!
+//! let summary = output.stats().summary();
+//! assert_eq!(summary.frames, 1);
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::Output;
+use crate::utils::{Monotonic, Time};
+
+/// Number of frames kept by the [`OutputStats`] attached to an [`Output`]
+pub const DEFAULT_STATS_CAPACITY: usize = 120;
+
+/// Timing information about a single frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Time the cpu spent building and submitting the frame
+    pub render_cpu: Duration,
+    /// Time the gpu spent rendering the frame, if known
+    ///
+    /// See [`GlesFrame::begin_timer_query`](crate::backend::renderer::gles::GlesFrame::begin_timer_query)
+    /// for a way to measure this with the gles renderer.
+    pub render_gpu: Option<Duration>,
+    /// Time between the start of rendering and the frame being presented, if known
+    pub presentation_latency: Option<Duration>,
+    /// Number of vblanks missed before the frame was presented
+    pub missed_vblanks: u32,
+}
+
+/// Calculates how many vblanks were missed presenting a frame.
+///
+/// `expected` is the time the frame was targeted at, `presented` the time it
+/// was actually presented and `refresh` the refresh interval of the output.
+pub fn missed_vblanks(expected: Time<Monotonic>, presented: Time<Monotonic>, refresh: Duration) -> u32 {
+    if refresh.is_zero() {
+        return 0;
+    }
+
+    // allow for some jitter in the reported timestamps
+    let late = Time::elapsed(&expected, presented) + refresh / 2;
+    (late.as_nanos() / refresh.as_nanos()).min(u32::MAX as u128) as u32
+}
+
+/// Aggregated statistics over the frames currently stored in an [`OutputStats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputStatsSummary {
+    /// Number of frames the summary was calculated from
+    pub frames: usize,
+    /// Average cpu render time
+    pub avg_render_cpu: Duration,
+    /// Maximum cpu render time
+    pub max_render_cpu: Duration,
+    /// Average gpu render time of all frames providing one
+    pub avg_render_gpu: Option<Duration>,
+    /// Maximum gpu render time of all frames providing one
+    pub max_render_gpu: Option<Duration>,
+    /// Average presentation latency of all frames providing one
+    pub avg_presentation_latency: Option<Duration>,
+    /// Maximum presentation latency of all frames providing one
+    pub max_presentation_latency: Option<Duration>,
+    /// Missed vblanks over the summarized frames
+    pub missed_vblanks: u64,
+    /// Total number of frames recorded since creation or the last reset
+    pub total_frames: u64,
+    /// Total number of missed vblanks since creation or the last reset
+    pub total_missed_vblanks: u64,
+}
+
+impl fmt::Display for OutputStatsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frames: {} (total {}), cpu: avg {:?} max {:?}",
+            self.frames, self.total_frames, self.avg_render_cpu, self.max_render_cpu
+        )?;
+        if let (Some(avg), Some(max)) = (self.avg_render_gpu, self.max_render_gpu) {
+            write!(f, ", gpu: avg {:?} max {:?}", avg, max)?;
+        }
+        if let (Some(avg), Some(max)) = (self.avg_presentation_latency, self.max_presentation_latency) {
+            write!(f, ", latency: avg {:?} max {:?}", avg, max)?;
+        }
+        write!(
+            f,
+            ", missed vblanks: {} (total {})",
+            self.missed_vblanks, self.total_missed_vblanks
+        )
+    }
+}
+
+#[derive(Debug)]
+struct OutputStatsInner {
+    capacity: usize,
+    frames: VecDeque<FrameStats>,
+    total_frames: u64,
+    total_missed_vblanks: u64,
+}
+
+/// Rolling frame statistics of an output
+///
+/// Cloning an `OutputStats` returns a handle to the same statistics.
+#[derive(Debug, Clone)]
+pub struct OutputStats(Arc<Mutex<OutputStatsInner>>);
+
+impl OutputStats {
+    /// Create new statistics, keeping the last `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        OutputStats(Arc::new(Mutex::new(OutputStatsInner {
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+            total_frames: 0,
+            total_missed_vblanks: 0,
+        })))
+    }
+
+    /// Record a new frame, evicting the oldest one if the capacity is reached.
+    pub fn record_frame(&self, stats: FrameStats) {
+        let mut inner = self.0.lock().unwrap();
+        if inner.frames.len() == inner.capacity {
+            inner.frames.pop_front();
+        }
+        inner.frames.push_back(stats);
+        inner.total_frames += 1;
+        inner.total_missed_vblanks += stats.missed_vblanks as u64;
+    }
+
+    /// Returns the most recently recorded frame
+    pub fn last_frame(&self) -> Option<FrameStats> {
+        self.0.lock().unwrap().frames.back().copied()
+    }
+
+    /// Returns all currently stored frames, oldest first
+    pub fn frames(&self) -> Vec<FrameStats> {
+        self.0.lock().unwrap().frames.iter().copied().collect()
+    }
+
+    /// Drop all stored frames and reset the totals
+    pub fn reset(&self) {
+        let mut inner = self.0.lock().unwrap();
+        inner.frames.clear();
+        inner.total_frames = 0;
+        inner.total_missed_vblanks = 0;
+    }
+
+    /// Calculate a summary of the currently stored frames
+    pub fn summary(&self) -> OutputStatsSummary {
+        let inner = self.0.lock().unwrap();
+
+        fn avg_max(iter: impl Iterator<Item = Duration>) -> Option<(Duration, Duration)> {
+            let (count, sum, max) = iter.fold((0u32, Duration::ZERO, Duration::ZERO), |(c, s, m), d| {
+                (c + 1, s + d, m.max(d))
+            });
+            (count > 0).then(|| (sum / count, max))
+        }
+
+        let cpu = avg_max(inner.frames.iter().map(|f| f.render_cpu)).unwrap_or_default();
+        let gpu = avg_max(inner.frames.iter().filter_map(|f| f.render_gpu));
+        let latency = avg_max(inner.frames.iter().filter_map(|f| f.presentation_latency));
+
+        OutputStatsSummary {
+            frames: inner.frames.len(),
+            avg_render_cpu: cpu.0,
+            max_render_cpu: cpu.1,
+            avg_render_gpu: gpu.map(|(avg, _)| avg),
+            max_render_gpu: gpu.map(|(_, max)| max),
+            avg_presentation_latency: latency.map(|(avg, _)| avg),
+            max_presentation_latency: latency.map(|(_, max)| max),
+            missed_vblanks: inner.frames.iter().map(|f| f.missed_vblanks as u64).sum(),
+            total_frames: inner.total_frames,
+            total_missed_vblanks: inner.total_missed_vblanks,
+        }
+    }
+}
+
+impl Output {
+    /// Returns the frame statistics of this output
+    ///
+    /// The statistics are created on first access and keep the last
+    /// [`DEFAULT_STATS_CAPACITY`] frames.
+    pub fn stats(&self) -> OutputStats {
+        self.user_data()
+            .get_or_insert_threadsafe(|| OutputStats::new(DEFAULT_STATS_CAPACITY))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(cpu_ms: u64, gpu_ms: Option<u64>, missed: u32) -> FrameStats {
+        FrameStats {
+            render_cpu: Duration::from_millis(cpu_ms),
+            render_gpu: gpu_ms.map(Duration::from_millis),
+            presentation_latency: None,
+            missed_vblanks: missed,
+        }
+    }
+
+    #[test]
+    fn summary_rolls_over() {
+        let stats = OutputStats::new(2);
+        stats.record_frame(frame(10, None, 1));
+        stats.record_frame(frame(2, Some(4), 0));
+        stats.record_frame(frame(4, Some(2), 2));
+
+        let summary = stats.summary();
+        assert_eq!(summary.frames, 2);
+        assert_eq!(summary.avg_render_cpu, Duration::from_millis(3));
+        assert_eq!(summary.max_render_cpu, Duration::from_millis(4));
+        assert_eq!(summary.avg_render_gpu, Some(Duration::from_millis(3)));
+        assert_eq!(summary.avg_presentation_latency, None);
+        assert_eq!(summary.missed_vblanks, 2);
+        assert_eq!(summary.total_frames, 3);
+        assert_eq!(summary.total_missed_vblanks, 3);
+    }
+
+    #[test]
+    fn missed_vblank_count() {
+        let refresh = Duration::from_micros(16_667);
+        let expected = Time::<Monotonic>::from(Duration::from_secs(1));
+        assert_eq!(missed_vblanks(expected, expected, refresh), 0);
+        assert_eq!(
+            missed_vblanks(expected, expected + Duration::from_millis(2), refresh),
+            0
+        );
+        assert_eq!(missed_vblanks(expected, expected + refresh, refresh), 1);
+        assert_eq!(missed_vblanks(expected, expected + refresh * 3, refresh), 3);
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::info;
+use wayland_server::{backend::GlobalId, protocol::wl_output::WlOutput, DisplayHandle, GlobalDispatch};
+
+use super::{Mode, Output, PhysicalProperties, Scale, Subpixel};
+use crate::{
+    utils::{Logical, Physical, Point, Size, Transform},
+    wayland::output::WlOutputData,
+};
+
+/// Configuration of a virtual output
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualOutputConfig {
+    /// Size of the output in pixels
+    pub size: Size<i32, Physical>,
+    /// Refresh rate in mHz
+    pub refresh: i32,
+    /// Scale of the output
+    pub scale: Scale,
+    /// Location of the output in the global compositor space
+    pub location: Point<i32, Logical>,
+}
+
+impl Default for VirtualOutputConfig {
+    fn default() -> Self {
+        VirtualOutputConfig {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+            scale: Scale::Integer(1),
+            location: (0, 0).into(),
+        }
+    }
+}
+
+// marker in the user data of virtual outputs
+#[derive(Debug, Default)]
+struct VirtualOutputMarker {
+    destroyed: AtomicBool,
+}
+
+/// Manager of outputs not backed by a display
+///
+/// Virtual outputs are advertised to clients like any other output, but the compositor renders
+/// them offscreen, e.g. with an [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker)
+/// into a texture, which is then streamed for screen sharing or to a remote device.
+/// Captures of these renders can be read back with
+/// [`ExportMem::capture_output_to_image`](crate::backend::renderer::ExportMem::capture_output_to_image).
+///
+/// ```no_run
+/// # use smithay::{delegate_compositor, delegate_output, wayland::output::OutputHandler};
+/// # use smithay::wayland::compositor::{CompositorHandler, CompositorState, CompositorClientState};
+/// # use smithay::reexports::wayland_server::{self, Client, protocol::wl_surface::WlSurface};
+/// use smithay::output::{VirtualOutputConfig, VirtualOutputs};
+///
+/// # struct State { virtual_outputs: VirtualOutputs }
+/// # impl OutputHandler for State {}
+/// # impl CompositorHandler for State {
+/// #     fn compositor_state(&mut self) -> &mut CompositorState { unimplemented!() }
+/// #     fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState { unimplemented!() }
+/// #     fn commit(&mut self, surface: &WlSurface) {}
+/// # }
+/// # delegate_output!(State);
+/// # delegate_compositor!(State);
+/// # let display = wayland_server::Display::<State>::new().unwrap();
+/// # let dh = display.handle();
+/// # let mut state = State { virtual_outputs: VirtualOutputs::new() };
+/// let output = state
+///     .virtual_outputs
+///     .create::<State>(&dh, VirtualOutputConfig::default());
+/// // ... render to it and stream it ...
+/// state.virtual_outputs.destroy::<State>(&dh, &output);
+/// ```
+#[derive(Debug, Default)]
+pub struct VirtualOutputs {
+    outputs: Vec<(Output, GlobalId)>,
+    next_id: u32,
+}
+
+impl VirtualOutputs {
+    /// Create a new manager without any outputs
+    pub fn new() -> VirtualOutputs {
+        VirtualOutputs::default()
+    }
+
+    /// Create a virtual output and advertise it to clients
+    ///
+    /// Outputs are named `VIRTUAL-<n>`, numbers are not reused.
+    pub fn create<D>(&mut self, display: &DisplayHandle, config: VirtualOutputConfig) -> Output
+    where
+        D: GlobalDispatch<WlOutput, WlOutputData> + 'static,
+    {
+        self.next_id += 1;
+        let output = Output::new(
+            format!("VIRTUAL-{}", self.next_id),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Virtual Output".into(),
+                serial_number: self.next_id.to_string(),
+            },
+        );
+        output
+            .user_data()
+            .insert_if_missing_threadsafe(VirtualOutputMarker::default);
+
+        let mode = Mode {
+            size: config.size,
+            refresh: config.refresh,
+        };
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            Some(config.scale),
+            Some(config.location),
+        );
+
+        info!(output = output.name(), "Creating virtual output");
+        let global = output.create_global::<D>(display);
+        self.outputs.push((output.clone(), global));
+        output
+    }
+
+    /// Stop advertising a virtual output and remove it
+    ///
+    /// Surfaces are sent leave events for it. Returns `false` if the output was not created by
+    /// this manager.
+    pub fn destroy<D>(&mut self, display: &DisplayHandle, output: &Output) -> bool
+    where
+        D: GlobalDispatch<WlOutput, WlOutputData> + 'static,
+    {
+        let Some(index) = self.outputs.iter().position(|(o, _)| o == output) else {
+            return false;
+        };
+        let (output, global) = self.outputs.remove(index);
+        info!(output = output.name(), "Destroying virtual output");
+        output.leave_all();
+        display.remove_global::<D>(global);
+        if let Some(marker) = output.user_data().get::<VirtualOutputMarker>() {
+            marker.destroyed.store(true, Ordering::Release);
+        }
+        true
+    }
+
+    /// Iterate over the virtual outputs
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().map(|(output, _)| output)
+    }
+}
+
+impl Output {
+    /// Returns `true` if this output was created by [`VirtualOutputs`]
+    pub fn is_virtual(&self) -> bool {
+        self.user_data().get::<VirtualOutputMarker>().is_some()
+    }
+
+    /// Returns `true` if this is a virtual output, that was destroyed
+    ///
+    /// Useful to stop capturing an output, that is still referenced elsewhere.
+    pub fn is_destroyed_virtual(&self) -> bool {
+        self.user_data()
+            .get::<VirtualOutputMarker>()
+            .is_some_and(|marker| marker.destroyed.load(Ordering::Acquire))
+    }
+}
@@ -14,6 +14,7 @@ pub use input;
 #[cfg(feature = "renderer_pixman")]
 pub use pixman;
 pub use rustix;
+pub use smallvec;
 #[cfg(feature = "backend_udev")]
 pub use udev;
 #[cfg(feature = "wayland_frontend")]
@@ -23,8 +24,10 @@ pub use wayland_protocols_misc;
 #[cfg(feature = "wayland_frontend")]
 pub use wayland_protocols_wlr;
 #[cfg(feature = "wayland_frontend")]
+pub use wayland_scanner;
+#[cfg(feature = "wayland_frontend")]
 pub use wayland_server;
-#[cfg(feature = "backend_winit")]
+#[cfg(any(feature = "backend_winit", feature = "backend_winit_windows"))]
 pub use winit;
 #[cfg(feature = "x11rb_event_source")]
 pub use x11rb;
@@ -0,0 +1,434 @@
+//! Integration into a tokio runtime
+//!
+//! Smithay is built around [`calloop`], but a compositor may need to be embedded into an
+//! existing application running on [tokio](https://tokio.rs). This module provides adapters
+//! driving smithay's event sources from async code:
+//!
+//! - [`AsyncEventLoop`] dispatches a calloop [`EventLoop`] whenever it has pending events,
+//!   keeping all backend event sources usable from async code. [`AsyncEventLoop::insert_stream`]
+//!   turns an event source into a [`Stream`] of its events.
+//! - [`AsyncDisplay`] dispatches the clients of a wayland [`Display`] without an event loop.
+//! - [`AsyncFrameClock`] turns a [`FrameClock`] into a future, or a [`Stream`], resolving once a
+//!   frame should be rendered.
+//!
+//! All adapters have to be created from within a tokio runtime with IO and time enabled.
+//!
+//! On unix the event loop is only dispatched once its file descriptor signals pending events.
+//! Calloop's own [`Timer`](calloop::timer::Timer) is handled inside the event loop and does
+//! not wake up the file descriptor, use a [`TimerSource`](crate::compat::event::TimerSource)
+//! instead, or bound the time between two dispatches with [`AsyncEventLoop::set_max_wait`].
+//! On Windows calloop waits on a completion port tokio cannot observe, so the event loop is
+//! dispatched every [`AsyncEventLoop::set_max_wait`], [`DEFAULT_WINDOWS_MAX_WAIT`] by default.
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::reexports::calloop::EventLoop;
+//! use smithay::utils::async_loop::AsyncEventLoop;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let event_loop = EventLoop::<u32>::try_new().unwrap();
+//! // insert your event sources
+//! let mut event_loop = AsyncEventLoop::new(event_loop)?;
+//!
+//! let mut state = 0;
+//! loop {
+//!     event_loop.dispatch(&mut state).await?;
+//! }
+//! # }
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::poll_fn,
+    io,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, RawFd};
+
+use calloop::{EventLoop, EventSource, InsertError, LoopHandle, RegistrationToken};
+use futures_core::Stream;
+#[cfg(unix)]
+use tokio::io::unix::AsyncFd;
+use tokio::time::{Interval, MissedTickBehavior};
+#[cfg(all(unix, feature = "wayland_frontend"))]
+use wayland_server::{Display, DisplayHandle};
+
+use crate::{
+    output::FrameClock,
+    utils::{Monotonic, Time},
+};
+
+/// Time between two dispatches of an [`AsyncEventLoop`] on Windows, unless changed with
+/// [`AsyncEventLoop::set_max_wait`]
+pub const DEFAULT_WINDOWS_MAX_WAIT: Duration = Duration::from_millis(10);
+
+// file descriptor owned by the adapted type, which has to outlive the `AsyncFd`
+#[cfg(unix)]
+#[derive(Debug)]
+struct PollFd(RawFd);
+
+#[cfg(unix)]
+impl AsRawFd for PollFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// Calloop [`EventLoop`] dispatched from async code
+#[derive(Debug)]
+pub struct AsyncEventLoop<'l, Data> {
+    // declared first to be deregistered before the event loop is dropped
+    #[cfg(unix)]
+    fd: AsyncFd<PollFd>,
+    event_loop: EventLoop<'l, Data>,
+    max_wait: Option<Duration>,
+}
+
+impl<'l, Data> AsyncEventLoop<'l, Data> {
+    /// Wrap an event loop
+    pub fn new(event_loop: EventLoop<'l, Data>) -> io::Result<Self> {
+        Ok(AsyncEventLoop {
+            #[cfg(unix)]
+            fd: AsyncFd::new(PollFd(event_loop.as_fd().as_raw_fd()))?,
+            event_loop,
+            max_wait: cfg!(windows).then_some(DEFAULT_WINDOWS_MAX_WAIT),
+        })
+    }
+
+    /// Returns a handle to the event loop to insert or remove sources
+    pub fn handle(&self) -> LoopHandle<'l, Data> {
+        self.event_loop.handle()
+    }
+
+    /// Set the upper bound of the time between two dispatches
+    ///
+    /// On unix this is only needed to run calloop [`Timer`](calloop::timer::Timer)s, which do
+    /// not wake up the event loop file descriptor, and defaults to `None`. On Windows it is the
+    /// latency of all event sources and has to be set.
+    pub fn set_max_wait(&mut self, max_wait: Option<Duration>) {
+        self.max_wait = max_wait;
+    }
+
+    /// Wait for pending events and dispatch them
+    pub async fn dispatch(&mut self, data: &mut Data) -> io::Result<()> {
+        #[cfg(unix)]
+        match self.max_wait {
+            Some(max_wait) => {
+                if let Ok(guard) = tokio::time::timeout(max_wait, self.fd.readable()).await {
+                    guard?.clear_ready();
+                }
+            }
+            None => self.fd.readable().await?.clear_ready(),
+        }
+        #[cfg(not(unix))]
+        tokio::time::sleep(self.max_wait.unwrap_or(DEFAULT_WINDOWS_MAX_WAIT)).await;
+
+        self.event_loop
+            .dispatch(Some(Duration::ZERO), data)
+            .map_err(io::Error::other)
+    }
+
+    /// Insert an event source, whose events are delivered by the returned [`EventStream`]
+    ///
+    /// The events are collected while the event loop is dispatched, so the stream only yields
+    /// events while [`AsyncEventLoop::dispatch`] is awaited as well, e.g. in another branch of
+    /// a `select!`.
+    pub fn insert_stream<S>(&self, source: S) -> Result<EventStream<S::Event>, InsertError<S>>
+    where
+        S: EventSource<Ret = ()> + 'l,
+        S::Event: 'l,
+    {
+        let shared = Rc::new(RefCell::new(StreamShared {
+            events: VecDeque::new(),
+            waker: None,
+        }));
+        let queue = shared.clone();
+        let token = self
+            .event_loop
+            .handle()
+            .insert_source(source, move |event, _, _| {
+                let mut queue = queue.borrow_mut();
+                queue.events.push_back(StreamItem::Event(event));
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            })?;
+        Ok(EventStream { shared, token })
+    }
+
+    /// Remove the source of a stream returned by [`AsyncEventLoop::insert_stream`]
+    ///
+    /// The events already received can still be read from the stream, which ends afterwards.
+    pub fn remove_stream<E>(&self, stream: &EventStream<E>) {
+        self.event_loop.handle().remove(stream.token);
+        let mut shared = stream.shared.borrow_mut();
+        shared.events.push_back(StreamItem::End);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Returns the wrapped event loop
+    pub fn into_inner(self) -> EventLoop<'l, Data> {
+        self.event_loop
+    }
+}
+
+#[derive(Debug)]
+struct StreamShared<E> {
+    events: VecDeque<StreamItem<E>>,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+enum StreamItem<E> {
+    Event(E),
+    End,
+}
+
+/// Events of a source inserted with [`AsyncEventLoop::insert_stream`]
+#[derive(Debug)]
+pub struct EventStream<E> {
+    shared: Rc<RefCell<StreamShared<E>>>,
+    token: RegistrationToken,
+}
+
+impl<E> Stream for EventStream<E> {
+    type Item = E;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<E>> {
+        let mut shared = self.shared.borrow_mut();
+        match shared.events.pop_front() {
+            Some(StreamItem::Event(event)) => Poll::Ready(Some(event)),
+            Some(StreamItem::End) => {
+                shared.events.push_front(StreamItem::End);
+                Poll::Ready(None)
+            }
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Wayland [`Display`] dispatched from async code
+#[cfg(all(unix, feature = "wayland_frontend"))]
+#[derive(Debug)]
+pub struct AsyncDisplay<D: 'static> {
+    fd: AsyncFd<PollFd>,
+    display: Display<D>,
+}
+
+#[cfg(all(unix, feature = "wayland_frontend"))]
+impl<D: 'static> AsyncDisplay<D> {
+    /// Wrap a display
+    pub fn new(mut display: Display<D>) -> io::Result<Self> {
+        let fd = AsyncFd::new(PollFd(display.backend().poll_fd().as_raw_fd()))?;
+        Ok(AsyncDisplay { fd, display })
+    }
+
+    /// Returns a handle to the display
+    pub fn handle(&self) -> DisplayHandle {
+        self.display.handle()
+    }
+
+    /// Wait for requests of clients and dispatch them
+    ///
+    /// Returns the number of dispatched requests.
+    pub async fn dispatch_clients(&mut self, state: &mut D) -> io::Result<usize> {
+        self.fd.readable().await?.clear_ready();
+        self.display.dispatch_clients(state)
+    }
+
+    /// Flush the pending events to all clients
+    pub fn flush_clients(&mut self) -> io::Result<()> {
+        self.display.flush_clients()
+    }
+
+    /// Returns the wrapped display
+    pub fn into_inner(self) -> Display<D> {
+        self.display
+    }
+}
+
+/// [`FrameClock`] awaited from async code
+///
+/// While the clock does not want to render, either because nothing changed on an output in
+/// [`FrameMode::OnDemand`](crate::output::FrameMode::OnDemand) or because the system is
+/// suspended, the refresh interval is stopped. The adapter installs a
+/// [waker](FrameClock::set_waker) on the clock to resume once a redraw was queued.
+#[derive(Debug)]
+pub struct AsyncFrameClock {
+    frame_clock: FrameClock,
+    interval: Interval,
+    waker: Arc<Mutex<Option<Waker>>>,
+    idle: bool,
+}
+
+impl AsyncFrameClock {
+    /// Tick the frame clock with the given refresh interval
+    ///
+    /// Replaces the waker of the clock.
+    pub fn new(frame_clock: FrameClock, refresh: Duration) -> Self {
+        let mut interval = tokio::time::interval(refresh);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let task = waker.clone();
+        frame_clock.set_waker(move || {
+            if let Some(waker) = task.lock().unwrap().take() {
+                waker.wake();
+            }
+        });
+        AsyncFrameClock {
+            frame_clock,
+            interval,
+            waker,
+            idle: false,
+        }
+    }
+
+    /// Returns the wrapped frame clock
+    pub fn frame_clock(&self) -> &FrameClock {
+        &self.frame_clock
+    }
+
+    /// Wait until the next frame should be rendered
+    ///
+    /// Skips all ticks for which [`FrameClock::should_render`] returns `false` and returns the
+    /// time of the tick. Call [`FrameClock::frame_rendered`] once the frame was rendered.
+    pub async fn next_frame(&mut self) -> Time<Monotonic> {
+        poll_fn(|cx| self.poll_frame(cx)).await
+    }
+
+    fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Time<Monotonic>> {
+        loop {
+            if self.idle {
+                // register before checking, so a redraw queued in between is not missed
+                *self.waker.lock().unwrap() = Some(cx.waker().clone());
+                if self.frame_clock.is_suspended() || self.frame_clock.pending().is_empty() {
+                    return Poll::Pending;
+                }
+                self.idle = false;
+                self.interval.reset_immediately();
+            }
+
+            std::task::ready!(self.interval.poll_tick(cx));
+            let now = self.frame_clock.now();
+            if self.frame_clock.should_render(now) {
+                return Poll::Ready(now);
+            }
+            self.idle = true;
+        }
+    }
+}
+
+impl Drop for AsyncFrameClock {
+    fn drop(&mut self) {
+        self.frame_clock.clear_waker();
+    }
+}
+
+impl Stream for AsyncFrameClock {
+    type Item = Time<Monotonic>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_frame(cx).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{FrameMode, RedrawReasons};
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dispatches_on_readiness() {
+        runtime().block_on(async {
+            let (notifier, source) = crate::compat::notifier::new().unwrap();
+            let event_loop = EventLoop::<u32>::try_new().unwrap();
+            event_loop
+                .handle()
+                .insert_source(source, |_, _, count| *count += 1)
+                .unwrap();
+            let mut event_loop = AsyncEventLoop::new(event_loop).unwrap();
+
+            let thread = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                notifier.notify();
+            });
+            let mut count = 0;
+            while count == 0 {
+                tokio::time::timeout(Duration::from_secs(5), event_loop.dispatch(&mut count))
+                    .await
+                    .expect("the notification did not wake the event loop")
+                    .unwrap();
+            }
+            thread.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn streams_events() {
+        runtime().block_on(async {
+            let (sender, channel) = calloop::channel::channel::<u32>();
+            let mut event_loop = AsyncEventLoop::new(EventLoop::<()>::try_new().unwrap()).unwrap();
+            event_loop.set_max_wait(Some(Duration::from_millis(1)));
+            let mut stream = event_loop.insert_stream(channel).unwrap();
+
+            sender.send(1).unwrap();
+            sender.send(2).unwrap();
+            event_loop.dispatch(&mut ()).await.unwrap();
+            for expected in [1, 2] {
+                match next(&mut stream).await {
+                    Some(calloop::channel::Event::Msg(msg)) => assert_eq!(msg, expected),
+                    _ => panic!("expected message {}", expected),
+                }
+            }
+
+            event_loop.remove_stream(&stream);
+            assert!(next(&mut stream).await.is_none());
+        });
+    }
+
+    #[test]
+    fn frame_clock_resumes_after_idle() {
+        runtime().block_on(async {
+            let frame_clock = FrameClock::new(FrameMode::OnDemand);
+            let mut clock = AsyncFrameClock::new(frame_clock.clone(), Duration::from_millis(1));
+
+            frame_clock.queue_redraw(RedrawReasons::DAMAGE);
+            let now = next(&mut clock).await.unwrap();
+            frame_clock.frame_rendered(now);
+
+            let thread = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                frame_clock.queue_redraw(RedrawReasons::CURSOR);
+            });
+            tokio::time::timeout(Duration::from_secs(5), clock.next_frame())
+                .await
+                .expect("queueing a redraw did not wake the idle clock");
+            thread.join().unwrap();
+            assert!(clock.frame_clock().idle_stats().skipped_frames > 0);
+        });
+    }
+}
@@ -1,14 +1,20 @@
 //! Cross-platform clock utilities for timing
 //!
-//! Uses rustix on Unix and std::time on Windows
-
-use std::{cmp::Ordering, marker::PhantomData, ops::Add, time::Duration};
+//! [`Clock`] is the single source of timestamps in smithay. Modules should not query
+//! the platform clocks directly, so that timestamps handed to clients are consistent
+//! across backends. All arithmetic on [`Time`] saturates instead of overflowing.
+//!
+//! The platform clocks are provided by rustix on Unix and by [`crate::compat::time`] on Windows.
 
-// Platform-specific time types
-#[cfg(unix)]
-use rustix::time::{clock_gettime, ClockId, Timespec};
+use std::{
+    cmp::Ordering,
+    fmt,
+    marker::PhantomData,
+    ops::{Add, Sub},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-#[cfg(windows)]
 use crate::compat::time::{clock_gettime, ClockId, Timespec};
 
 /// Marker for clock source that never returns a negative [`Time`]
@@ -62,6 +68,71 @@ impl<Kind: ClockSource> Clock<Kind> {
     }
 }
 
+/// Source of the current monotonic time
+///
+/// Timing-dependent state like the [`FrameClock`](crate::output::FrameClock) reads the time
+/// through this trait, so tests can substitute a [`ManualClock`] and advance virtual time
+/// deterministically instead of sleeping.
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    /// Returns the current time
+    fn now(&self) -> Time<Monotonic>;
+}
+
+impl TimeSource for Clock<Monotonic> {
+    fn now(&self) -> Time<Monotonic> {
+        Clock::now(self)
+    }
+}
+
+/// Returns a [`TimeSource`] reading the system monotonic clock
+pub fn system_time_source() -> Arc<dyn TimeSource> {
+    Arc::new(Clock::<Monotonic>::new())
+}
+
+/// [`TimeSource`] that only advances when told to
+///
+/// Cloning a `ManualClock` returns a handle to the same clock.
+///
+/// ```
+/// # extern crate smithay;
+/// use std::time::Duration;
+/// use smithay::utils::{ManualClock, TimeSource};
+///
+/// let clock = ManualClock::default();
+/// let start = clock.now();
+/// clock.advance(Duration::from_millis(16));
+/// assert_eq!(clock.now().saturating_duration_since(start), Duration::from_millis(16));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Arc<Mutex<Duration>>);
+
+impl ManualClock {
+    /// Create a new clock starting at the given time
+    pub fn new(start: Time<Monotonic>) -> Self {
+        ManualClock(Arc::new(Mutex::new(start.into())))
+    }
+
+    /// Advance the clock by the given duration
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.0.lock().unwrap();
+        *now = now.saturating_add(duration);
+    }
+
+    /// Set the current time of the clock
+    ///
+    /// Unlike [`ManualClock::advance`] this can move the clock backwards, which a real monotonic
+    /// clock never does.
+    pub fn set(&self, time: Time<Monotonic>) {
+        *self.0.lock().unwrap() = time.into();
+    }
+}
+
+impl TimeSource for ManualClock {
+    fn now(&self) -> Time<Monotonic> {
+        Time::from(*self.0.lock().unwrap())
+    }
+}
+
 /// A point in time for a clock with a specific kind
 pub struct Time<Kind> {
     tp: Timespec,
@@ -73,6 +144,58 @@ impl<Kind> Time<Kind> {
     pub fn elapsed(elapsed: &Time<Kind>, later: Time<Kind>) -> Duration {
         saturating_sub_timespec(later.tp, elapsed.tp).unwrap_or(Duration::ZERO)
     }
+
+    /// Gets the duration from an earlier time until self, or `None` if `earlier` is later than self
+    pub fn checked_duration_since(&self, earlier: Time<Kind>) -> Option<Duration> {
+        saturating_sub_timespec(self.tp, earlier.tp)
+    }
+
+    /// Gets the duration from an earlier time until self, or zero if `earlier` is later than self
+    pub fn saturating_duration_since(&self, earlier: Time<Kind>) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or(Duration::ZERO)
+    }
+
+    /// Adds a duration, saturating at the maximum representable time
+    pub fn saturating_add(self, duration: Duration) -> Time<Kind> {
+        let secs = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+        let nanos = self.tp.tv_nsec + duration.subsec_nanos() as i64;
+        match self
+            .tp
+            .tv_sec
+            .checked_add(secs)
+            .and_then(|tv_sec| tv_sec.checked_add(nanos / NANOS_PER_SEC))
+        {
+            Some(tv_sec) => Time::from_parts(tv_sec, nanos % NANOS_PER_SEC),
+            None => Time::from_parts(i64::MAX, NANOS_PER_SEC - 1),
+        }
+    }
+
+    /// Subtracts a duration, saturating at the epoch of the clock
+    pub fn saturating_sub(self, duration: Duration) -> Time<Kind> {
+        let secs = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+        let mut tv_sec = match self.tp.tv_sec.checked_sub(secs) {
+            Some(tv_sec) if tv_sec >= 0 => tv_sec,
+            _ => return Time::from_parts(0, 0),
+        };
+        let mut tv_nsec = self.tp.tv_nsec - duration.subsec_nanos() as i64;
+        if tv_nsec < 0 {
+            if tv_sec == 0 {
+                return Time::from_parts(0, 0);
+            }
+            tv_sec -= 1;
+            tv_nsec += NANOS_PER_SEC;
+        }
+        Time::from_parts(tv_sec, tv_nsec)
+    }
+
+    /// Reinterpret this time as a point on another clock
+    pub(crate) fn cast<Other>(self) -> Time<Other> {
+        Time::from(self.tp)
+    }
+
+    fn from_parts(tv_sec: i64, tv_nsec: i64) -> Time<Kind> {
+        Time::from(Timespec { tv_sec, tv_nsec })
+    }
 }
 
 impl Time<Monotonic> {
@@ -101,6 +224,17 @@ impl Time<Monotonic> {
     }
 }
 
+#[cfg(windows)]
+impl Time<Monotonic> {
+    /// Create a time from a value of the performance counter (`QueryPerformanceCounter`)
+    ///
+    /// Timestamps reported by e.g. DXGI frame statistics or raw input use this time base,
+    /// which is also the source of [`Clock<Monotonic>`] on Windows.
+    pub fn from_performance_counter(count: i64) -> Self {
+        crate::compat::time::counter_to_timespec(count).into()
+    }
+}
+
 impl<Kind> Clone for Time<Kind> {
     #[inline]
     fn clone(&self) -> Self {
@@ -160,14 +294,10 @@ impl<Kind> Ord for Time<Kind> {
 impl<Kind: NonNegativeClockSource> From<Duration> for Time<Kind> {
     #[inline]
     fn from(tp: Duration) -> Self {
-        let tp = Timespec {
-            tv_sec: tp.as_secs() as i64,
-            tv_nsec: tp.subsec_nanos() as i64,
-        };
-        Time {
-            tp,
-            _kind: PhantomData,
-        }
+        Time::from_parts(
+            i64::try_from(tp.as_secs()).unwrap_or(i64::MAX),
+            tp.subsec_nanos() as i64,
+        )
     }
 }
 
@@ -226,9 +356,25 @@ where
 
     fn add(self, rhs: T) -> Self::Output {
         let rhs = rhs.into();
-        let tv_nsec = (self.tp.tv_nsec + rhs.tp.tv_nsec) % NANOS_PER_SEC;
-        let tv_sec = (self.tp.tv_sec + rhs.tp.tv_sec) + ((self.tp.tv_nsec + rhs.tp.tv_nsec) / NANOS_PER_SEC);
-        Self::from(Timespec { tv_sec, tv_nsec })
+        let nanos = self.tp.tv_nsec + rhs.tp.tv_nsec;
+        match self
+            .tp
+            .tv_sec
+            .checked_add(rhs.tp.tv_sec)
+            .and_then(|tv_sec| tv_sec.checked_add(nanos / NANOS_PER_SEC))
+        {
+            Some(tv_sec) => Time::from_parts(tv_sec, nanos % NANOS_PER_SEC),
+            None => Time::from_parts(i64::MAX, NANOS_PER_SEC - 1),
+        }
+    }
+}
+
+impl<Kind> Sub<Duration> for Time<Kind> {
+    type Output = Time<Kind>;
+
+    #[inline]
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.saturating_sub(rhs)
     }
 }
 
@@ -236,7 +382,7 @@ where
 mod test {
     use std::time::Duration;
 
-    use crate::utils::{Clock, Monotonic, Time};
+    use crate::utils::{Clock, ManualClock, Monotonic, Time, TimeSource};
 
     #[test]
     fn monotonic() {
@@ -245,4 +391,42 @@ mod test {
         let zero = Time::<Monotonic>::from(Duration::ZERO);
         assert_eq!(Time::<Monotonic>::elapsed(&zero, now), now.into());
     }
+
+    #[test]
+    fn saturating_arithmetic() {
+        let time = Time::<Monotonic>::from(Duration::new(1, 500_000_000));
+
+        assert_eq!(
+            time.saturating_sub(Duration::from_millis(700)),
+            Time::from(Duration::from_millis(800))
+        );
+        assert_eq!(time - Duration::from_secs(2), Time::from(Duration::ZERO));
+        assert_eq!(
+            time.saturating_add(Duration::from_millis(600)),
+            Time::from(Duration::new(2, 100_000_000))
+        );
+        assert_eq!(
+            time.saturating_add(Duration::MAX),
+            Time::from(Duration::new(i64::MAX as u64, 999_999_999))
+        );
+
+        let later = time + Duration::from_millis(250);
+        assert_eq!(
+            later.checked_duration_since(time),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(time.checked_duration_since(later), None);
+        assert_eq!(time.saturating_duration_since(later), Duration::ZERO);
+    }
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new(Time::from(Duration::from_secs(1)));
+        let handle = clock.clone();
+        handle.advance(Duration::from_millis(500));
+        assert_eq!(clock.now(), Time::from(Duration::from_millis(1500)));
+
+        clock.set(Time::from(Duration::ZERO));
+        assert_eq!(handle.now(), Time::from(Duration::ZERO));
+    }
 }
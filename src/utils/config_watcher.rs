@@ -0,0 +1,635 @@
+//! Watching configuration files for changes
+//!
+//! A [`ConfigWatcher`] monitors files and directories and delivers [`ConfigEvent`]s through
+//! calloop, so compositors can reload their configuration when it is edited. It uses inotify on
+//! Linux and `ReadDirectoryChangesW` on Windows.
+//!
+//! Files are watched through their parent directory, so they are still tracked after editors
+//! replaced them by renaming a new file over them, and may not exist yet when the watch is
+//! added. Editors often touch a file several times while saving, so reloading on an event should
+//! be cheap and idempotent.
+//!
+//! ```no_run
+//! use smithay::utils::config_watcher::{ChangeKind, ConfigWatcher};
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let mut watcher = ConfigWatcher::new().unwrap();
+//! watcher.watch("/home/user/.config/compositor/config.toml").unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(watcher, |event, _, _state| {
+//!         if event.kind != ChangeKind::Removed {
+//!             // reload the configuration from `event.path`
+//!         }
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use calloop::{generic::Generic, Interest, Mode};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use rustix::fs::inotify;
+
+#[cfg(windows)]
+use std::sync::{Arc, Mutex};
+
+#[cfg(windows)]
+use crate::compat::notifier::{self, Notifier, NotifierSource};
+
+/// Kind of change of a watched file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The file was created or moved into place
+    Created,
+    /// The file was written to
+    Modified,
+    /// The file was removed or moved away
+    Removed,
+}
+
+/// Change of a watched file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConfigEvent {
+    /// Path of the changed file
+    pub path: PathBuf,
+    /// Kind of change
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug)]
+struct DirWatch {
+    // names of the watched files in the directory, `None` watches all of them
+    filters: Vec<Option<OsString>>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    wd: i32,
+    #[cfg(windows)]
+    watch: windows::DirectoryWatch,
+}
+
+impl DirWatch {
+    fn matches(&self, name: &OsStr) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| filter.as_deref().is_none_or(|filter| filter == name))
+    }
+}
+
+// returns the directory to watch and the name of the watched file in it
+fn split(path: &Path) -> io::Result<(PathBuf, Option<OsString>)> {
+    let path = std::path::absolute(path)?;
+    if path.is_dir() {
+        return Ok((path, None));
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => Ok((dir.to_owned(), Some(name.to_owned()))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path has no parent directory",
+        )),
+    }
+}
+
+/// Event source watching files and directories, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    dirs: HashMap<PathBuf, DirWatch>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    source: Generic<std::os::unix::io::OwnedFd>,
+    #[cfg(windows)]
+    source: NotifierSource,
+    #[cfg(windows)]
+    notifier: Notifier,
+    #[cfg(windows)]
+    changes: Arc<Mutex<Vec<(PathBuf, OsString, ChangeKind)>>>,
+}
+
+impl ConfigWatcher {
+    /// Create a new watcher without any watches
+    pub fn new() -> io::Result<ConfigWatcher> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let fd = inotify::init(inotify::CreateFlags::CLOEXEC | inotify::CreateFlags::NONBLOCK)?;
+            Ok(ConfigWatcher {
+                dirs: HashMap::new(),
+                source: Generic::new(fd, Interest::READ, Mode::Level),
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            let (notifier, source) = notifier::new()?;
+            Ok(ConfigWatcher {
+                dirs: HashMap::new(),
+                source,
+                notifier,
+                changes: Arc::new(Mutex::new(Vec::new())),
+            })
+        }
+    }
+
+    /// Watch a file or a directory
+    ///
+    /// Watching a directory reports changes of all files directly inside of it.
+    pub fn watch(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let (dir, filter) = split(path.as_ref())?;
+        if let Some(watch) = self.dirs.get_mut(&dir) {
+            if !watch.filters.contains(&filter) {
+                watch.filters.push(filter);
+            }
+            return Ok(());
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let watch = DirWatch {
+            filters: vec![filter],
+            wd: inotify::add_watch(
+                self.source.get_ref(),
+                &dir,
+                inotify::WatchFlags::CREATE
+                    | inotify::WatchFlags::CLOSE_WRITE
+                    | inotify::WatchFlags::MOVED_TO
+                    | inotify::WatchFlags::MOVED_FROM
+                    | inotify::WatchFlags::DELETE
+                    | inotify::WatchFlags::ONLYDIR,
+            )?,
+        };
+        #[cfg(windows)]
+        let watch = DirWatch {
+            filters: vec![filter],
+            watch: windows::DirectoryWatch::new(&dir, self.changes.clone(), self.notifier.clone())?,
+        };
+
+        self.dirs.insert(dir, watch);
+        Ok(())
+    }
+
+    /// Stop watching a file or directory
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = std::path::absolute(path.as_ref())?;
+        let (dir, filter) = match self.dirs.get(&path) {
+            Some(watch) if watch.filters.contains(&None) => (path, None),
+            _ => split(&path)?,
+        };
+        let Some(watch) = self.dirs.get_mut(&dir) else {
+            return Ok(());
+        };
+        watch.filters.retain(|f| *f != filter);
+        if watch.filters.is_empty() {
+            let _watch = self.dirs.remove(&dir).unwrap();
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            inotify::remove_watch(self.source.get_ref(), _watch.wd)?;
+        }
+        Ok(())
+    }
+
+    // coalesces the changes of every file into its last one and filters unwatched files
+    fn collect(
+        dirs: &HashMap<PathBuf, DirWatch>,
+        changes: impl IntoIterator<Item = (PathBuf, OsString, ChangeKind)>,
+    ) -> Vec<ConfigEvent> {
+        let mut events: Vec<ConfigEvent> = Vec::new();
+        for (dir, name, kind) in changes {
+            if !dirs.get(&dir).is_some_and(|watch| watch.matches(&name)) {
+                continue;
+            }
+            let path = dir.join(name);
+            match events.iter_mut().find(|event| event.path == path) {
+                Some(event) => event.kind = kind,
+                None => events.push(ConfigEvent { path, kind }),
+            }
+        }
+        events
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_inotify(
+    fd: &std::os::unix::io::OwnedFd,
+    dirs: &HashMap<PathBuf, DirWatch>,
+) -> io::Result<Vec<ConfigEvent>> {
+    let by_wd = dirs
+        .iter()
+        .map(|(dir, watch)| (watch.wd, dir))
+        .collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let len = match rustix::io::read(fd, &mut buffer[..]) {
+            Ok(len) => len,
+            Err(rustix::io::Errno::AGAIN) => break,
+            Err(rustix::io::Errno::INTR) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        for (wd, mask, name) in parse_inotify(&buffer[..len]) {
+            let kind = if mask & (inotify::ReadFlags::CREATE | inotify::ReadFlags::MOVED_TO).bits() != 0 {
+                ChangeKind::Created
+            } else if mask & inotify::ReadFlags::CLOSE_WRITE.bits() != 0 {
+                ChangeKind::Modified
+            } else if mask & (inotify::ReadFlags::DELETE | inotify::ReadFlags::MOVED_FROM).bits() != 0 {
+                ChangeKind::Removed
+            } else {
+                continue;
+            };
+            if let Some(dir) = by_wd.get(&wd) {
+                changes.push(((*dir).clone(), name, kind));
+            }
+        }
+    }
+    Ok(ConfigWatcher::collect(dirs, changes))
+}
+
+// parses `struct inotify_event`s, skipping events without a file name
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_inotify(mut buffer: &[u8]) -> Vec<(i32, u32, OsString)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    const HEADER: usize = 16;
+    let mut events = Vec::new();
+    while buffer.len() >= HEADER {
+        let field = |offset: usize| buffer[offset..offset + 4].try_into().unwrap();
+        let wd = i32::from_ne_bytes(field(0));
+        let mask = u32::from_ne_bytes(field(4));
+        let len = u32::from_ne_bytes(field(12)) as usize;
+        let Some(name) = buffer.get(HEADER..HEADER + len) else {
+            break;
+        };
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(len)];
+        if !name.is_empty() {
+            events.push((wd, mask, OsStr::from_bytes(name).to_owned()));
+        }
+        buffer = &buffer[HEADER + len..];
+    }
+    events
+}
+
+impl EventSource for ConfigWatcher {
+    type Event = ConfigEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let dirs = &self.dirs;
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            self.source.process_events(readiness, token, |_, fd| {
+                for event in read_inotify(fd, dirs)? {
+                    callback(event, &mut ());
+                }
+                Ok(PostAction::Continue)
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            let changes = &self.changes;
+            self.source.process_events(readiness, token, |_, _| {
+                let pending = std::mem::take(&mut *changes.lock().unwrap());
+                for event in ConfigWatcher::collect(dirs, pending) {
+                    callback(event, &mut ());
+                }
+            })
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    #![allow(non_snake_case)]
+
+    use std::{
+        ffi::{c_void, OsString},
+        io,
+        os::windows::{
+            ffi::{OsStrExt, OsStringExt},
+            io::{AsRawHandle, FromRawHandle, OwnedHandle},
+        },
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use super::ChangeKind;
+    use crate::compat::notifier::Notifier;
+
+    const FILE_LIST_DIRECTORY: u32 = 0x1;
+    const FILE_SHARE_ALL: u32 = 0x1 | 0x2 | 0x4;
+    const OPEN_EXISTING: u32 = 3;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const INVALID_HANDLE_VALUE: isize = -1;
+
+    const FILE_NOTIFY_CHANGE_FILE_NAME: u32 = 0x1;
+    const FILE_NOTIFY_CHANGE_LAST_WRITE: u32 = 0x10;
+
+    const FILE_ACTION_ADDED: u32 = 1;
+    const FILE_ACTION_REMOVED: u32 = 2;
+    const FILE_ACTION_MODIFIED: u32 = 3;
+    const FILE_ACTION_RENAMED_OLD_NAME: u32 = 4;
+    const FILE_ACTION_RENAMED_NEW_NAME: u32 = 5;
+
+    const FILE_FLAG_OVERLAPPED: u32 = 0x4000_0000;
+    const INFINITE: u32 = 0xFFFF_FFFF;
+    const WAIT_OBJECT_0: u32 = 0;
+
+    #[repr(C)]
+    struct OVERLAPPED {
+        Internal: usize,
+        InternalHigh: usize,
+        Offset: u32,
+        OffsetHigh: u32,
+        hEvent: isize,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateFileW(
+            lpFileName: *const u16,
+            dwDesiredAccess: u32,
+            dwShareMode: u32,
+            lpSecurityAttributes: *const c_void,
+            dwCreationDisposition: u32,
+            dwFlagsAndAttributes: u32,
+            hTemplateFile: isize,
+        ) -> isize;
+        fn ReadDirectoryChangesW(
+            hDirectory: isize,
+            lpBuffer: *mut c_void,
+            nBufferLength: u32,
+            bWatchSubtree: i32,
+            dwNotifyFilter: u32,
+            lpBytesReturned: *mut u32,
+            lpOverlapped: *mut OVERLAPPED,
+            lpCompletionRoutine: *const c_void,
+        ) -> i32;
+        fn GetOverlappedResult(
+            hFile: isize,
+            lpOverlapped: *mut OVERLAPPED,
+            lpNumberOfBytesTransferred: *mut u32,
+            bWait: i32,
+        ) -> i32;
+        fn CancelIoEx(hFile: isize, lpOverlapped: *mut OVERLAPPED) -> i32;
+        fn CreateEventW(
+            lpEventAttributes: *const c_void,
+            bManualReset: i32,
+            bInitialState: i32,
+            lpName: *const u16,
+        ) -> isize;
+        fn SetEvent(hEvent: isize) -> i32;
+        fn WaitForMultipleObjects(
+            nCount: u32,
+            lpHandles: *const isize,
+            bWaitAll: i32,
+            dwMilliseconds: u32,
+        ) -> u32;
+    }
+
+    type Changes = Arc<Mutex<Vec<(PathBuf, OsString, ChangeKind)>>>;
+
+    fn create_event() -> io::Result<OwnedHandle> {
+        let raw = unsafe { CreateEventW(std::ptr::null(), 0, 0, std::ptr::null()) };
+        if raw == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the event was just created and is owned by us
+        Ok(unsafe { OwnedHandle::from_raw_handle(raw as _) })
+    }
+
+    // directory watched by a thread waiting for overlapped `ReadDirectoryChangesW` calls
+    #[derive(Debug)]
+    pub struct DirectoryWatch {
+        stop: Arc<OwnedHandle>,
+        thread: Option<std::thread::JoinHandle<()>>,
+    }
+
+    impl DirectoryWatch {
+        pub fn new(dir: &Path, changes: Changes, notifier: Notifier) -> io::Result<DirectoryWatch> {
+            let wide = dir
+                .as_os_str()
+                .encode_wide()
+                .chain(std::iter::once(0))
+                .collect::<Vec<_>>();
+            let raw = unsafe {
+                CreateFileW(
+                    wide.as_ptr(),
+                    FILE_LIST_DIRECTORY,
+                    FILE_SHARE_ALL,
+                    std::ptr::null(),
+                    OPEN_EXISTING,
+                    FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
+                    0,
+                )
+            };
+            if raw == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: the handle was just created and is owned by us
+            let handle = unsafe { OwnedHandle::from_raw_handle(raw as _) };
+            let stop = Arc::new(create_event()?);
+            let completed = create_event()?;
+
+            let thread_stop = stop.clone();
+            let dir = dir.to_owned();
+            let thread = std::thread::Builder::new()
+                .name("smithay-config-watcher".into())
+                .spawn(move || watch_thread(handle, completed, thread_stop, dir, changes, notifier))?;
+
+            Ok(DirectoryWatch {
+                stop,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Drop for DirectoryWatch {
+        fn drop(&mut self) {
+            // the thread cancels its pending read and waits for it to complete, before the buffer
+            // is freed
+            unsafe { SetEvent(self.stop.as_raw_handle() as isize) };
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn watch_thread(
+        handle: OwnedHandle,
+        completed: OwnedHandle,
+        stop: Arc<OwnedHandle>,
+        dir: PathBuf,
+        changes: Changes,
+        notifier: Notifier,
+    ) {
+        let raw = handle.as_raw_handle() as isize;
+        let wait = [completed.as_raw_handle() as isize, stop.as_raw_handle() as isize];
+        // FILE_NOTIFY_INFORMATION entries are DWORD aligned
+        let mut buffer = vec![0u32; 16 * 1024];
+        loop {
+            let mut overlapped = OVERLAPPED {
+                Internal: 0,
+                InternalHigh: 0,
+                Offset: 0,
+                OffsetHigh: 0,
+                hEvent: wait[0],
+            };
+            let ok = unsafe {
+                ReadDirectoryChangesW(
+                    raw,
+                    buffer.as_mut_ptr() as *mut c_void,
+                    (buffer.len() * 4) as u32,
+                    0,
+                    FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                    std::ptr::null_mut(),
+                    &mut overlapped,
+                    std::ptr::null(),
+                )
+            };
+            if ok == 0 {
+                // the directory was removed
+                break;
+            }
+
+            let signaled = unsafe { WaitForMultipleObjects(2, wait.as_ptr(), 0, INFINITE) };
+            let mut len = 0u32;
+            if signaled != WAIT_OBJECT_0 {
+                // the watch was dropped, the read must be finished before the buffer is freed
+                unsafe {
+                    CancelIoEx(raw, &mut overlapped);
+                    GetOverlappedResult(raw, &mut overlapped, &mut len, 1);
+                }
+                break;
+            }
+            if unsafe { GetOverlappedResult(raw, &mut overlapped, &mut len, 1) } == 0 {
+                break;
+            }
+
+            let mut pending = Vec::new();
+            let mut offset = 0usize;
+            // a length of zero means the buffer overflowed, the changes are lost
+            while (len as usize) >= offset + 12 {
+                let entry = &buffer[offset / 4..];
+                let (next, action, name_len) = (entry[0] as usize, entry[1], entry[2] as usize);
+                let name =
+                    unsafe { std::slice::from_raw_parts(entry[3..].as_ptr() as *const u16, name_len / 2) };
+                let kind = match action {
+                    FILE_ACTION_ADDED | FILE_ACTION_RENAMED_NEW_NAME => Some(ChangeKind::Created),
+                    FILE_ACTION_MODIFIED => Some(ChangeKind::Modified),
+                    FILE_ACTION_REMOVED | FILE_ACTION_RENAMED_OLD_NAME => Some(ChangeKind::Removed),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    pending.push((dir.clone(), OsString::from_wide(name), kind));
+                }
+                if next == 0 {
+                    break;
+                }
+                offset += next;
+            }
+
+            if !pending.is_empty() {
+                changes.lock().unwrap().extend(pending);
+                notifier.notify();
+            }
+        }
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_watched_files() {
+        let dir = std::env::temp_dir().join(format!("smithay-config-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+
+        let mut watcher = ConfigWatcher::new().unwrap();
+        watcher.watch(&config).unwrap();
+
+        std::fs::write(&config, "a").unwrap();
+        std::fs::write(dir.join("other.toml"), "b").unwrap();
+        std::fs::write(&config, "c").unwrap();
+        std::fs::remove_file(&config).unwrap();
+
+        // the file ends up removed, whatever happened before
+        let events = read_inotify(watcher.source.get_ref(), &watcher.dirs).unwrap();
+        assert_eq!(
+            events,
+            [ConfigEvent {
+                path: config.clone(),
+                kind: ChangeKind::Removed,
+            }]
+        );
+
+        watcher.unwatch(&config).unwrap();
+        assert!(watcher.dirs.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_last_change_per_file() {
+        let dir = PathBuf::from("/config");
+        let dirs = HashMap::from([(
+            dir.clone(),
+            DirWatch {
+                filters: vec![Some("a".into()), Some("b".into())],
+                wd: 1,
+            },
+        )]);
+        let change = |name: &str, kind| (dir.clone(), OsString::from(name), kind);
+
+        let events = ConfigWatcher::collect(
+            &dirs,
+            [
+                change("a", ChangeKind::Removed),
+                change("b", ChangeKind::Modified),
+                change("c", ChangeKind::Modified),
+                change("a", ChangeKind::Created),
+                change("a", ChangeKind::Modified),
+                change("b", ChangeKind::Removed),
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                ConfigEvent {
+                    path: dir.join("a"),
+                    kind: ChangeKind::Modified,
+                },
+                ConfigEvent {
+                    path: dir.join("b"),
+                    kind: ChangeKind::Removed,
+                },
+            ]
+        );
+    }
+}
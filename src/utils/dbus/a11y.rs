@@ -0,0 +1,121 @@
+//! Accessibility bus of AT-SPI
+//!
+//! Accessibility clients (screen readers, magnifiers...) and applications exchange accessibility
+//! information over a dedicated bus, whose address is announced by the `org.a11y.Bus` service on
+//! the session bus. A compositor spawning clients in an environment without a session bus, or
+//! sandboxing them, passes the address on through the `AT_SPI_BUS_ADDRESS` environment variable.
+//!
+//! [`A11yBus`] queries the address and the accessibility settings of the session, the
+//! [`A11ySource`] delivers [`A11yEvent`]s when the settings change.
+//!
+//! ```no_run
+//! use smithay::utils::dbus::a11y::{A11yBus, AT_SPI_BUS_ADDRESS_ENV};
+//!
+//! let (bus, _source) = A11yBus::new().unwrap();
+//! let mut command = std::process::Command::new("weston-terminal");
+//! command.env(AT_SPI_BUS_ADDRESS_ENV, bus.address().unwrap());
+//! ```
+
+use dbus::{
+    arg::prop_cast,
+    blocking::{stdintf::org_freedesktop_dbus::Properties, Connection},
+    Message,
+};
+
+use super::{properties_changed_rule, read_properties_changed, DbusSource, Error, TIMEOUT};
+
+const SERVICE: &str = "org.a11y.Bus";
+const PATH: &str = "/org/a11y/bus";
+const BUS_INTERFACE: &str = "org.a11y.Bus";
+const STATUS_INTERFACE: &str = "org.a11y.Status";
+
+/// Environment variable passing the address of the accessibility bus to clients
+pub const AT_SPI_BUS_ADDRESS_ENV: &str = "AT_SPI_BUS_ADDRESS";
+
+/// Events of the accessibility settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum A11yEvent {
+    /// Accessibility support was enabled or disabled
+    Enabled(bool),
+    /// A screen reader was started or stopped
+    ScreenReaderEnabled(bool),
+}
+
+/// Event source delivering [`A11yEvent`]s
+pub type A11ySource = DbusSource<A11yEvent>;
+
+/// Handle to the accessibility bus service
+pub struct A11yBus {
+    conn: Connection,
+}
+
+impl std::fmt::Debug for A11yBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("A11yBus").finish_non_exhaustive()
+    }
+}
+
+impl A11yBus {
+    /// Connect to the accessibility bus service on the session bus
+    pub fn new() -> Result<(A11yBus, A11ySource), Error> {
+        let conn = Connection::new_session()?;
+        let source = DbusSource::new(
+            Connection::new_session()?,
+            &[&properties_changed_rule(SERVICE, PATH)],
+            |message: &Message| {
+                let (interface, changed) = read_properties_changed(message)?;
+                if interface != STATUS_INTERFACE {
+                    return None;
+                }
+                prop_cast::<bool>(&changed, "ScreenReaderEnabled")
+                    .map(|enabled| A11yEvent::ScreenReaderEnabled(*enabled))
+                    .or_else(|| {
+                        prop_cast::<bool>(&changed, "IsEnabled").map(|enabled| A11yEvent::Enabled(*enabled))
+                    })
+            },
+        )?;
+
+        Ok((A11yBus { conn }, source))
+    }
+
+    /// Returns the address of the accessibility bus
+    ///
+    /// The bus is launched on demand by this call, if it is not running yet.
+    pub fn address(&self) -> Result<String, Error> {
+        let (address,): (String,) =
+            self.conn
+                .with_proxy(SERVICE, PATH, TIMEOUT)
+                .method_call(BUS_INTERFACE, "GetAddress", ())?;
+        if address.is_empty() {
+            return Err(Error::InvalidReply {
+                service: SERVICE,
+                reason: "empty bus address".into(),
+            });
+        }
+        Ok(address)
+    }
+
+    /// Returns whether accessibility support is enabled
+    pub fn is_enabled(&self) -> Result<bool, Error> {
+        Ok(self
+            .conn
+            .with_proxy(SERVICE, PATH, TIMEOUT)
+            .get(STATUS_INTERFACE, "IsEnabled")?)
+    }
+
+    /// Enable or disable accessibility support
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), Error> {
+        Ok(self
+            .conn
+            .with_proxy(SERVICE, PATH, TIMEOUT)
+            .set(STATUS_INTERFACE, "IsEnabled", enabled)?)
+    }
+
+    /// Returns whether a screen reader is running
+    pub fn screen_reader_enabled(&self) -> Result<bool, Error> {
+        Ok(self
+            .conn
+            .with_proxy(SERVICE, PATH, TIMEOUT)
+            .get(STATUS_INTERFACE, "ScreenReaderEnabled")?)
+    }
+}
@@ -0,0 +1,207 @@
+//! Control of the logind session
+//!
+//! [`Logind`] calls methods on the session the compositor runs in, and the [`LogindSource`]
+//! delivers [`LogindEvent`]s, most importantly requests to lock the session and notifications
+//! before the system goes to sleep.
+//!
+//! A compositor usually holds a delay inhibitor for sleep, so it can lock the screen before the
+//! system suspends, and releases it once it handled [`LogindEvent::PrepareForSleep`]:
+//!
+//! ```no_run
+//! use smithay::utils::dbus::logind::{InhibitMode, Logind, LogindEvent};
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let (logind, source) = Logind::new().unwrap();
+//! let mut inhibitor = Some(logind.inhibit("sleep", "My compositor", "Lock the screen", InhibitMode::Delay).unwrap());
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, move |event, _, _state| match event {
+//!         LogindEvent::PrepareForSleep(true) => {
+//!             // lock the screen, then allow the system to sleep
+//!             inhibitor.take();
+//!         }
+//!         LogindEvent::Lock => { /* lock the screen */ }
+//!         _ => {}
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::os::unix::io::{FromRawFd, OwnedFd};
+
+use dbus::{
+    blocking::{stdintf::org_freedesktop_dbus::Properties, Connection},
+    Message, Path,
+};
+
+use super::{DbusSource, Error, TIMEOUT};
+use crate::backend::power::PowerEvent;
+
+const SERVICE: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+const SEAT_INTERFACE: &str = "org.freedesktop.login1.Seat";
+
+/// Events of the logind session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogindEvent {
+    /// The session should be locked, e.g. after `loginctl lock-session`
+    Lock,
+    /// The session should be unlocked
+    Unlock,
+    /// The system is about to sleep (`true`) or just resumed (`false`)
+    PrepareForSleep(bool),
+    /// The system is about to shut down (`true`) or the shutdown was cancelled (`false`)
+    PrepareForShutdown(bool),
+}
+
+impl LogindEvent {
+    /// Returns the system power state change announced by this event, if any
+    pub fn power_event(&self) -> Option<PowerEvent> {
+        match self {
+            LogindEvent::PrepareForSleep(true) => Some(PowerEvent::Suspend),
+            LogindEvent::PrepareForSleep(false) => Some(PowerEvent::Resume),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of an inhibitor lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InhibitMode {
+    /// Block the operation until the lock is released
+    Block,
+    /// Delay the operation until the lock is released, at most for a configured timeout
+    Delay,
+}
+
+/// Event source delivering [`LogindEvent`]s
+pub type LogindSource = DbusSource<LogindEvent>;
+
+/// Handle to the logind session of the compositor
+pub struct Logind {
+    conn: Connection,
+    session: Path<'static>,
+}
+
+impl std::fmt::Debug for Logind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Logind")
+            .field("session", &self.session)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Logind {
+    /// Connect to logind and look up the session of this process
+    ///
+    /// The session is looked up from `XDG_SESSION_ID`, falling back to the session of the
+    /// process id.
+    pub fn new() -> Result<(Logind, LogindSource), Error> {
+        let conn = Connection::new_system()?;
+        let manager = conn.with_proxy(SERVICE, MANAGER_PATH, TIMEOUT);
+        let (session,): (Path<'static>,) = match std::env::var("XDG_SESSION_ID") {
+            Ok(id) => manager.method_call(MANAGER_INTERFACE, "GetSession", (id,))?,
+            Err(_) => manager.method_call(MANAGER_INTERFACE, "GetSessionByPID", (std::process::id(),))?,
+        };
+
+        let session_rule = format!(
+            "type='signal',sender='{}',path='{}',interface='{}'",
+            SERVICE, session, SESSION_INTERFACE
+        );
+        let manager_rule = format!(
+            "type='signal',sender='{}',path='{}',interface='{}'",
+            SERVICE, MANAGER_PATH, MANAGER_INTERFACE
+        );
+        let source = DbusSource::new(Connection::new_system()?, &[&session_rule, &manager_rule], filter)?;
+
+        Ok((Logind { conn, session }, source))
+    }
+
+    /// Returns the object path of the session
+    pub fn session_path(&self) -> &Path<'static> {
+        &self.session
+    }
+
+    fn session_call(&self, method: &str, args: impl dbus::arg::AppendAll) -> Result<(), Error> {
+        self.conn
+            .with_proxy(SERVICE, &self.session, TIMEOUT)
+            .method_call::<(), _, _, _>(SESSION_INTERFACE, method, args)?;
+        Ok(())
+    }
+
+    /// Returns whether the session is currently active, i.e. in the foreground of its seat
+    pub fn is_active(&self) -> Result<bool, Error> {
+        Ok(self
+            .conn
+            .with_proxy(SERVICE, &self.session, TIMEOUT)
+            .get(SESSION_INTERFACE, "Active")?)
+    }
+
+    /// Bring the session to the foreground
+    pub fn activate(&self) -> Result<(), Error> {
+        self.session_call("Activate", ())
+    }
+
+    /// Ask all listeners of the session, including this compositor, to lock it
+    pub fn lock(&self) -> Result<(), Error> {
+        self.session_call("Lock", ())
+    }
+
+    /// Ask all listeners of the session, including this compositor, to unlock it
+    pub fn unlock(&self) -> Result<(), Error> {
+        self.session_call("Unlock", ())
+    }
+
+    /// Set whether the session is idle, e.g. from the idle notify protocol
+    pub fn set_idle_hint(&self, idle: bool) -> Result<(), Error> {
+        self.session_call("SetIdleHint", (idle,))
+    }
+
+    /// Set whether the session is locked, after the screen was locked or unlocked
+    pub fn set_locked_hint(&self, locked: bool) -> Result<(), Error> {
+        self.session_call("SetLockedHint", (locked,))
+    }
+
+    /// Switch to the virtual terminal `vt` of the seat of the session
+    pub fn switch_to(&self, vt: u32) -> Result<(), Error> {
+        let session = self.conn.with_proxy(SERVICE, &self.session, TIMEOUT);
+        let (seat, _): (String, Path<'static>) = session.get(SESSION_INTERFACE, "Seat")?;
+        let seat_path = format!("{}/seat/{}", MANAGER_PATH, seat);
+        self.conn
+            .with_proxy(SERVICE, seat_path, TIMEOUT)
+            .method_call::<(), _, _, _>(SEAT_INTERFACE, "SwitchTo", (vt,))?;
+        Ok(())
+    }
+
+    /// Take an inhibitor lock
+    ///
+    /// `what` is a colon-separated list of operations to inhibit, e.g. `sleep`,
+    /// `handle-power-key` or `idle`. The lock is released when the returned file descriptor is
+    /// closed.
+    pub fn inhibit(&self, what: &str, who: &str, why: &str, mode: InhibitMode) -> Result<OwnedFd, Error> {
+        let mode = match mode {
+            InhibitMode::Block => "block",
+            InhibitMode::Delay => "delay",
+        };
+        let (fd,): (dbus::arg::OwnedFd,) = self.conn.with_proxy(SERVICE, MANAGER_PATH, TIMEOUT).method_call(
+            MANAGER_INTERFACE,
+            "Inhibit",
+            (what, who, why, mode),
+        )?;
+        // SAFETY: ownership of the fd is transferred from the dbus wrapper
+        Ok(unsafe { OwnedFd::from_raw_fd(fd.into_fd()) })
+    }
+}
+
+fn filter(message: &Message) -> Option<LogindEvent> {
+    match (&*message.interface()?, &*message.member()?) {
+        (SESSION_INTERFACE, "Lock") => Some(LogindEvent::Lock),
+        (SESSION_INTERFACE, "Unlock") => Some(LogindEvent::Unlock),
+        (MANAGER_INTERFACE, "PrepareForSleep") => message.read1().ok().map(LogindEvent::PrepareForSleep),
+        (MANAGER_INTERFACE, "PrepareForShutdown") => {
+            message.read1().ok().map(LogindEvent::PrepareForShutdown)
+        }
+        _ => None,
+    }
+}
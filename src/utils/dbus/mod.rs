@@ -0,0 +1,160 @@
+//! Integration with system services over D-Bus
+//!
+//! Most desktop compositors talk to the same few services on the system and session bus. This
+//! module provides typed wrappers for them, each consisting of a handle to call methods and a
+//! [`calloop`] event source delivering the signals of the service:
+//!
+//! - [`logind`]: control of the login session, e.g. lock requests and sleep inhibitors,
+//! - [`upower`]: battery and power supply status,
+//! - [`a11y`]: the accessibility bus, to advertise its address to clients.
+//!
+//! The event sources use a connection of their own, so signals are never held back by method
+//! calls blocking on the connection of the handle.
+//!
+//! This module is gated by the `dbus` cargo feature and uses libdbus.
+
+use std::{
+    io,
+    os::unix::io::{AsFd, BorrowedFd, RawFd},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use dbus::{blocking::Connection, Message};
+
+pub mod a11y;
+pub mod logind;
+pub mod upower;
+
+/// Timeout of method calls
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors of the D-Bus wrappers
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Communicating with the bus failed
+    #[error("D-Bus error: {0}")]
+    Dbus(#[from] dbus::Error),
+    /// The service returned an unexpected reply
+    #[error("Unexpected reply from {service}: {reason}")]
+    InvalidReply {
+        /// Name of the service
+        service: &'static str,
+        /// What was unexpected
+        reason: String,
+    },
+}
+
+// file descriptor owned by the connection of the source
+#[derive(Debug)]
+struct WatchFd(RawFd);
+
+impl AsFd for WatchFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the fd is owned by the connection, which outlives the `Generic` source
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+type Filter<E> = Box<dyn FnMut(&Message) -> Option<E>>;
+
+/// Event source delivering the signals received on a connection, filtered into events `E`
+pub struct DbusSource<E> {
+    // declared first to be dropped before the connection
+    source: Generic<WatchFd>,
+    conn: Connection,
+    filter: Filter<E>,
+}
+
+impl<E> std::fmt::Debug for DbusSource<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DbusSource")
+            .field("fd", &self.source.get_ref().0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E> DbusSource<E> {
+    /// Create a new source from a connection only used for receiving signals
+    ///
+    /// All messages matching any of the given match rules are passed to `filter`, the returned
+    /// events are delivered to the callback of the source.
+    pub fn new<F>(conn: Connection, match_rules: &[&str], filter: F) -> Result<DbusSource<E>, Error>
+    where
+        F: FnMut(&Message) -> Option<E> + 'static,
+    {
+        for rule in match_rules {
+            conn.add_match_no_cb(rule)?;
+        }
+        let fd = conn.channel().watch().fd;
+        Ok(DbusSource {
+            source: Generic::new(WatchFd(fd), Interest::READ, Mode::Level),
+            conn,
+            filter: Box::new(filter),
+        })
+    }
+}
+
+impl<E> EventSource for DbusSource<E> {
+    type Event = E;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let conn = &self.conn;
+        let filter = &mut self.filter;
+        self.source.process_events(readiness, token, |_, _| {
+            conn.channel()
+                .read_write(Some(Duration::ZERO))
+                .map_err(|()| io::Error::new(io::ErrorKind::BrokenPipe, "D-Bus connection closed"))?;
+            while let Some(message) = conn.channel().pop_message() {
+                if let Some(event) = filter(&message) {
+                    callback(event, &mut ());
+                }
+            }
+            Ok(PostAction::Continue)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+// match rule for the `PropertiesChanged` signals of an object
+fn properties_changed_rule(sender: &str, path: &str) -> String {
+    format!(
+        "type='signal',sender='{}',path='{}',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged'",
+        sender, path
+    )
+}
+
+// returns the interface and changed properties of a `PropertiesChanged` signal
+fn read_properties_changed(message: &Message) -> Option<(String, dbus::arg::PropMap)> {
+    if message.interface().as_deref() != Some("org.freedesktop.DBus.Properties")
+        || message.member().as_deref() != Some("PropertiesChanged")
+    {
+        return None;
+    }
+    let (interface, changed) = message.read2::<String, dbus::arg::PropMap>().ok()?;
+    Some((interface, changed))
+}
@@ -0,0 +1,186 @@
+//! Battery and power supply status through UPower
+//!
+//! [`UPower`] queries whether the system runs on battery and the combined status of all
+//! batteries (UPower's "display device"). The [`UPowerSource`] delivers [`UPowerEvent`]s
+//! whenever either changes, e.g. to switch to a power saving frame schedule on battery.
+
+use std::time::Duration;
+
+use dbus::{
+    arg::{prop_cast, PropMap},
+    blocking::{stdintf::org_freedesktop_dbus::Properties, Connection},
+    Message,
+};
+
+use super::{properties_changed_rule, read_properties_changed, DbusSource, Error, TIMEOUT};
+
+const SERVICE: &str = "org.freedesktop.UPower";
+const PATH: &str = "/org/freedesktop/UPower";
+const INTERFACE: &str = "org.freedesktop.UPower";
+const DISPLAY_DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// Charging state of a battery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BatteryState {
+    /// The state is not known, or there is no battery
+    #[default]
+    Unknown,
+    /// The battery is charging
+    Charging,
+    /// The battery is discharging
+    Discharging,
+    /// The battery is empty
+    Empty,
+    /// The battery is fully charged
+    FullyCharged,
+    /// The battery is not charging, though connected to power
+    PendingCharge,
+    /// The battery is about to discharge
+    PendingDischarge,
+}
+
+impl From<u32> for BatteryState {
+    fn from(state: u32) -> Self {
+        match state {
+            1 => BatteryState::Charging,
+            2 => BatteryState::Discharging,
+            3 => BatteryState::Empty,
+            4 => BatteryState::FullyCharged,
+            5 => BatteryState::PendingCharge,
+            6 => BatteryState::PendingDischarge,
+            _ => BatteryState::Unknown,
+        }
+    }
+}
+
+/// Combined status of all batteries
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatteryStatus {
+    /// Whether the system has any battery
+    pub present: bool,
+    /// Charge in percent
+    pub percentage: f64,
+    /// Charging state
+    pub state: BatteryState,
+    /// Estimated time until the batteries are empty, while discharging
+    pub time_to_empty: Option<Duration>,
+    /// Estimated time until the batteries are full, while charging
+    pub time_to_full: Option<Duration>,
+}
+
+impl BatteryStatus {
+    fn update(&mut self, properties: &PropMap) {
+        if let Some(present) = prop_cast::<bool>(properties, "IsPresent") {
+            self.present = *present;
+        }
+        if let Some(percentage) = prop_cast::<f64>(properties, "Percentage") {
+            self.percentage = *percentage;
+        }
+        if let Some(state) = prop_cast::<u32>(properties, "State") {
+            self.state = BatteryState::from(*state);
+        }
+        if let Some(time) = prop_cast::<i64>(properties, "TimeToEmpty") {
+            self.time_to_empty = seconds(*time);
+        }
+        if let Some(time) = prop_cast::<i64>(properties, "TimeToFull") {
+            self.time_to_full = seconds(*time);
+        }
+    }
+}
+
+fn seconds(time: i64) -> Option<Duration> {
+    u64::try_from(time)
+        .ok()
+        .filter(|&s| s > 0)
+        .map(Duration::from_secs)
+}
+
+/// Events of UPower
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UPowerEvent {
+    /// The system switched between battery (`true`) and external power (`false`)
+    OnBattery(bool),
+    /// The status of the batteries changed
+    Battery(BatteryStatus),
+}
+
+/// Event source delivering [`UPowerEvent`]s
+pub type UPowerSource = DbusSource<UPowerEvent>;
+
+/// Handle to UPower
+pub struct UPower {
+    conn: Connection,
+}
+
+impl std::fmt::Debug for UPower {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UPower").finish_non_exhaustive()
+    }
+}
+
+impl UPower {
+    /// Connect to UPower
+    pub fn new() -> Result<(UPower, UPowerSource), Error> {
+        let conn = Connection::new_system()?;
+        let upower = UPower { conn };
+
+        // `PropertiesChanged` only carries the changed properties, so the source keeps the full
+        // status to deliver
+        let mut status = upower.battery()?;
+        let source = DbusSource::new(
+            Connection::new_system()?,
+            &[
+                &properties_changed_rule(SERVICE, PATH),
+                &properties_changed_rule(SERVICE, DISPLAY_DEVICE_PATH),
+            ],
+            move |message: &Message| {
+                let (interface, changed) = read_properties_changed(message)?;
+                match interface.as_str() {
+                    INTERFACE => prop_cast::<bool>(&changed, "OnBattery").map(|b| UPowerEvent::OnBattery(*b)),
+                    DEVICE_INTERFACE => {
+                        status.update(&changed);
+                        Some(UPowerEvent::Battery(status))
+                    }
+                    _ => None,
+                }
+            },
+        )?;
+
+        Ok((upower, source))
+    }
+
+    /// Returns whether the system runs on battery
+    pub fn on_battery(&self) -> Result<bool, Error> {
+        Ok(self
+            .conn
+            .with_proxy(SERVICE, PATH, TIMEOUT)
+            .get(INTERFACE, "OnBattery")?)
+    }
+
+    /// Returns the combined status of all batteries
+    pub fn battery(&self) -> Result<BatteryStatus, Error> {
+        let properties = self
+            .conn
+            .with_proxy(SERVICE, DISPLAY_DEVICE_PATH, TIMEOUT)
+            .get_all(DEVICE_INTERFACE)?;
+        let mut status = BatteryStatus::default();
+        status.update(&properties);
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seconds, BatteryState};
+    use std::time::Duration;
+
+    #[test]
+    fn battery_values() {
+        assert_eq!(BatteryState::from(2), BatteryState::Discharging);
+        assert_eq!(BatteryState::from(42), BatteryState::Unknown);
+        assert_eq!(seconds(0), None);
+        assert_eq!(seconds(-1), None);
+        assert_eq!(seconds(90), Some(Duration::from_secs(90)));
+    }
+}
@@ -1,6 +1,7 @@
 #![forbid(unsafe_op_in_unsafe_fn)]
 
 use std::{
+    io,
     path::PathBuf,
     sync::Arc,
 };
@@ -73,13 +74,87 @@ impl TryInto<OwnedFd> for DeviceFd {
     }
 }
 
+impl DeviceFd {
+    /// Duplicate the underlying file descriptor (handle on Windows)
+    ///
+    /// Unlike cloning the `DeviceFd` the returned fd is independent and can be handed to
+    /// helper threads or other libraries taking ownership.
+    pub fn try_clone_raw(&self) -> io::Result<OwnedFd> {
+        self.0.try_clone()
+    }
+
+    /// Switch the file descriptor between blocking and non-blocking mode
+    ///
+    /// This changes the mode for all clones of this `DeviceFd`. On Windows the mode of a
+    /// handle is fixed when it is opened, so this returns [`io::ErrorKind::Unsupported`].
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use rustix::fs::{fcntl_getfl, fcntl_setfl, OFlags};
+
+            let flags = fcntl_getfl(&*self.0)?;
+            let flags = if nonblocking {
+                flags | OFlags::NONBLOCK
+            } else {
+                flags - OFlags::NONBLOCK
+            };
+            fcntl_setfl(&*self.0, flags)?;
+            Ok(())
+        }
+        #[cfg(windows)]
+        {
+            let _ = nonblocking;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the blocking mode of a handle can't be changed after opening it",
+            ))
+        }
+    }
+
+    /// Returns an identity of the opened device, which is the same for every fd opened for it
+    ///
+    /// This is the device number (`st_rdev`) for device nodes on unix and the volume serial
+    /// number and file index on Windows.
+    pub fn identity(&self) -> io::Result<DeviceIdentity> {
+        #[cfg(unix)]
+        #[allow(clippy::unnecessary_cast)]
+        {
+            let stat = rustix::fs::fstat(&*self.0)?;
+            if stat.st_rdev != 0 {
+                Ok(DeviceIdentity(0, stat.st_rdev as u64))
+            } else {
+                // not a device node, fall back to the inode
+                Ok(DeviceIdentity(stat.st_dev as u64, stat.st_ino as u64))
+            }
+        }
+        #[cfg(windows)]
+        {
+            let mut info = std::mem::MaybeUninit::<ffi::ByHandleFileInformation>::uninit();
+            let handle = self.as_raw_fd() as isize;
+            if unsafe { ffi::GetFileInformationByHandle(handle, info.as_mut_ptr()) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: initialized by GetFileInformationByHandle
+            let info = unsafe { info.assume_init() };
+            Ok(DeviceIdentity(
+                info.volume_serial_number as u64,
+                (info.file_index_high as u64) << 32 | info.file_index_low as u64,
+            ))
+        }
+    }
+}
+
+/// Identity of an opened device, see [`DeviceFd::identity`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceIdentity(u64, u64);
+
 /// Trait representing open devices that *may* return a `Path`
 pub trait DevPath {
     /// Returns the path of the open device if possible
     fn dev_path(&self) -> Option<PathBuf>;
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
 impl<A: AsFd> DevPath for A {
     fn dev_path(&self) -> Option<PathBuf> {
         use std::fs;
@@ -88,10 +163,73 @@ impl<A: AsFd> DevPath for A {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl<A: AsFd> DevPath for A {
+    fn dev_path(&self) -> Option<PathBuf> {
+        use std::{ffi::CStr, os::unix::ffi::OsStrExt};
+
+        let mut buf = [0 as libc::c_char; libc::PATH_MAX as usize];
+        // SAFETY: F_GETPATH writes at most PATH_MAX bytes including the nul terminator
+        if unsafe { libc::fcntl(self.as_fd().as_raw_fd(), libc::F_GETPATH, buf.as_mut_ptr()) } == -1 {
+            return None;
+        }
+        // SAFETY: the buffer was nul-terminated by F_GETPATH
+        let path = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes())))
+    }
+}
+
 #[cfg(windows)]
 impl<A: AsFd> DevPath for A {
     fn dev_path(&self) -> Option<PathBuf> {
-        // Windows doesn't have /proc/self/fd, return None
-        None
+        use std::os::windows::ffi::OsStringExt;
+
+        let handle = AsRawHandle::as_raw_handle(&self.as_fd()) as isize;
+        let mut buf = vec![0u16; 260];
+        loop {
+            // SAFETY: the buffer is valid for `buf.len()` characters
+            let len = unsafe {
+                ffi::GetFinalPathNameByHandleW(
+                    handle,
+                    buf.as_mut_ptr(),
+                    buf.len() as u32,
+                    ffi::FILE_NAME_NORMALIZED,
+                )
+            } as usize;
+            match len {
+                0 => return None,
+                // the buffer was too small, `len` includes the nul terminator
+                len if len > buf.len() => buf.resize(len, 0),
+                len => {
+                    buf.truncate(len);
+                    return Some(PathBuf::from(std::ffi::OsString::from_wide(&buf)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod ffi {
+    #[repr(C)]
+    pub struct ByHandleFileInformation {
+        pub file_attributes: u32,
+        pub creation_time: [u32; 2],
+        pub last_access_time: [u32; 2],
+        pub last_write_time: [u32; 2],
+        pub volume_serial_number: u32,
+        pub file_size_high: u32,
+        pub file_size_low: u32,
+        pub number_of_links: u32,
+        pub file_index_high: u32,
+        pub file_index_low: u32,
+    }
+
+    pub const FILE_NAME_NORMALIZED: u32 = 0x0;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn GetFileInformationByHandle(file: isize, info: *mut ByHandleFileInformation) -> i32;
+        pub fn GetFinalPathNameByHandleW(file: isize, path: *mut u16, len: u32, flags: u32) -> u32;
     }
 }
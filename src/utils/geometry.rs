@@ -76,6 +76,18 @@ pub trait Coordinate:
     /// Saturating integer multiplication. Computes self * other, saturating at the numeric bounds instead of overflowing.
     #[must_use = "this returns the result of the operation, without modifying the original"]
     fn saturating_mul(self, other: Self) -> Self;
+
+    /// Checked upscale. Returns `None` if the result can't be represented by the coordinate type.
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    fn checked_upscale(self, scale: Self) -> Option<Self> {
+        Some(self.upscale(scale))
+    }
+    /// Checked downscale. Returns `None` if the scale is zero or the result can't be represented
+    /// by the coordinate type.
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    fn checked_downscale(self, scale: Self) -> Option<Self> {
+        Some(self.downscale(scale))
+    }
 }
 
 /// Implements Coordinate for an unsigned numerical type.
@@ -133,6 +145,15 @@ macro_rules! unsigned_coordinate_impl {
             fn saturating_mul(self, other: Self) -> Self {
                 self.saturating_mul(other)
             }
+
+            #[inline]
+            fn checked_upscale(self, scale: Self) -> Option<Self> {
+                self.checked_mul(scale)
+            }
+            #[inline]
+            fn checked_downscale(self, scale: Self) -> Option<Self> {
+                self.checked_div(scale)
+            }
         }
     };
 }
@@ -200,6 +221,15 @@ macro_rules! signed_coordinate_impl {
             fn saturating_mul(self, other: Self) -> Self {
                 self.saturating_mul(other)
             }
+
+            #[inline]
+            fn checked_upscale(self, scale: Self) -> Option<Self> {
+                self.checked_mul(scale)
+            }
+            #[inline]
+            fn checked_downscale(self, scale: Self) -> Option<Self> {
+                self.checked_div(scale)
+            }
         }
 
         impl Coordinate for Saturating<$ty> {
@@ -247,6 +277,11 @@ macro_rules! signed_coordinate_impl {
             fn saturating_mul(self, other: Self) -> Self {
                 self * other
             }
+
+            #[inline]
+            fn checked_downscale(self, scale: Self) -> Option<Self> {
+                self.0.checked_div(scale.0).map(Saturating)
+            }
         }
     };
 }
@@ -313,6 +348,15 @@ macro_rules! floating_point_coordinate_impl {
             fn saturating_mul(self, other: Self) -> Self {
                 self * other
             }
+
+            #[inline]
+            fn checked_upscale(self, scale: Self) -> Option<Self> {
+                Some(self * scale).filter(|v| v.is_finite())
+            }
+            #[inline]
+            fn checked_downscale(self, scale: Self) -> Option<Self> {
+                Some(self / scale).filter(|v| v.is_finite())
+            }
         }
     };
 }
@@ -624,6 +668,38 @@ impl<N: Coordinate> Point<N, Logical> {
             _kind: std::marker::PhantomData,
         }
     }
+
+    /// Convert this logical point to physical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_physical(self, scale: impl Into<Scale<N>>) -> Option<Point<N, Physical>> {
+        let scale = scale.into();
+        Some(Point {
+            x: self.x.checked_upscale(scale.x)?,
+            y: self.y.checked_upscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        })
+    }
+
+    /// Convert this logical point to buffer coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_buffer(
+        self,
+        scale: impl Into<Scale<N>>,
+        transformation: Transform,
+        area: &Size<N, Logical>,
+    ) -> Option<Point<N, Buffer>> {
+        let point = transformation.transform_point_in(self, area);
+        let scale = scale.into();
+        Some(Point {
+            x: point.x.checked_upscale(scale.x)?,
+            y: point.y.checked_upscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        })
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -652,6 +728,19 @@ impl<N: Coordinate> Point<N, Physical> {
             _kind: std::marker::PhantomData,
         }
     }
+
+    /// Convert this physical point to logical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the scale is zero or the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_logical(self, scale: impl Into<Scale<N>>) -> Option<Point<N, Logical>> {
+        let scale = scale.into();
+        Some(Point {
+            x: self.x.checked_downscale(scale.x)?,
+            y: self.y.checked_downscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        })
+    }
 }
 
 impl<N: Coordinate> Point<N, Buffer> {
@@ -985,6 +1074,36 @@ impl<N: Coordinate> Size<N, Logical> {
             _kind: std::marker::PhantomData,
         })
     }
+
+    /// Convert this logical size to physical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_physical(self, scale: impl Into<Scale<N>>) -> Option<Size<N, Physical>> {
+        let scale = scale.into();
+        Some(Size {
+            w: self.w.checked_upscale(scale.x)?,
+            h: self.h.checked_upscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        })
+    }
+
+    /// Convert this logical size to buffer coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_buffer(
+        self,
+        scale: impl Into<Scale<N>>,
+        transformation: Transform,
+    ) -> Option<Size<N, Buffer>> {
+        let scale = scale.into();
+        Some(transformation.transform_size(Size {
+            w: self.w.checked_upscale(scale.x)?,
+            h: self.h.checked_upscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        }))
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -1013,6 +1132,19 @@ impl<N: Coordinate> Size<N, Physical> {
             _kind: std::marker::PhantomData,
         }
     }
+
+    /// Convert this physical size to logical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the scale is zero or the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_logical(self, scale: impl Into<Scale<N>>) -> Option<Size<N, Logical>> {
+        let scale = scale.into();
+        Some(Size {
+            w: self.w.checked_downscale(scale.x)?,
+            h: self.h.checked_downscale(scale.y)?,
+            _kind: std::marker::PhantomData,
+        })
+    }
 }
 
 impl<N: Coordinate> Size<N, Buffer> {
@@ -1618,6 +1750,44 @@ impl<N: Coordinate> Rectangle<N, Logical> {
             },
         }
     }
+
+    /// Convert this logical rectangle to physical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_physical(self, scale: impl Into<Scale<N>>) -> Option<Rectangle<N, Physical>> {
+        let scale = scale.into();
+        Some(Rectangle {
+            loc: self.loc.checked_to_physical(scale)?,
+            size: self.size.checked_to_physical(scale)?,
+        })
+    }
+
+    /// Convert this logical rectangle to buffer coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_buffer(
+        self,
+        scale: impl Into<Scale<N>>,
+        transformation: Transform,
+        area: &Size<N, Logical>,
+    ) -> Option<Rectangle<N, Buffer>> {
+        let rect = transformation.transform_rect_in(self, area);
+        let scale = scale.into();
+        Some(Rectangle {
+            loc: Point {
+                x: rect.loc.x.checked_upscale(scale.x)?,
+                y: rect.loc.y.checked_upscale(scale.y)?,
+                _kind: std::marker::PhantomData,
+            },
+            size: Size {
+                w: rect.size.w.checked_upscale(scale.x)?,
+                h: rect.size.h.checked_upscale(scale.y)?,
+                _kind: std::marker::PhantomData,
+            },
+        })
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -1644,6 +1814,18 @@ impl<N: Coordinate> Rectangle<N, Physical> {
             size: self.size.to_logical(scale),
         }
     }
+
+    /// Convert this physical rectangle to logical coordinate space according to given scale factor
+    ///
+    /// Returns `None` if the scale is zero or the result can't be represented by the coordinate type.
+    #[inline]
+    pub fn checked_to_logical(self, scale: impl Into<Scale<N>>) -> Option<Rectangle<N, Logical>> {
+        let scale = scale.into();
+        Some(Rectangle {
+            loc: self.loc.checked_to_logical(scale)?,
+            size: self.size.checked_to_logical(scale)?,
+        })
+    }
 }
 
 impl<N: Coordinate> Rectangle<N, Buffer> {
@@ -2135,4 +2317,19 @@ mod tests {
         smaller -= bigger;
         assert_eq!(smaller, Size::from((0, 0)));
     }
+
+    #[test]
+    fn checked_conversions() {
+        let rect = Rectangle::<i32, Logical>::new((10, 20).into(), (30, 40).into());
+        assert_eq!(rect.checked_to_physical(2), Some(rect.to_physical(2)));
+        assert_eq!(
+            rect.checked_to_buffer(2, Transform::_90, &(100, 100).into()),
+            Some(rect.to_buffer(2, Transform::_90, &(100, 100).into()))
+        );
+        assert_eq!(rect.checked_to_physical(i32::MAX), None);
+        assert_eq!(rect.to_physical(2).checked_to_logical(0), None);
+
+        let size = Size::<f64, Logical>::from((1.0, 1.0));
+        assert_eq!(size.checked_to_physical(f64::INFINITY), None);
+    }
 }
@@ -0,0 +1,251 @@
+//! Conventions for tracing spans and per-client log context
+//!
+//! Smithay attributes log messages by entering [`tracing`] spans carrying well-known fields:
+//!
+//! - `client`: the `ClientId` of the client, formatted with `Debug`,
+//! - `surface`: the protocol id of a `wl_surface`, formatted with `Display`,
+//! - `output`: the name of an [`Output`].
+//!
+//! Smithay enters these spans while handling client requests and while rendering an output, and
+//! the helpers of this module create them, so compositors can use the same fields for their own
+//! messages. Span fields can be matched by an `EnvFilter` directive, e.g. to trace everything
+//! happening to a single surface with `RUST_LOG='smithay[surface{surface=wl_surface@12}]=trace'`
+//! or everything rendered to an output with `RUST_LOG='smithay[output{output=DP-1}]=trace'`.
+//!
+//! Warnings triggered by client requests should use `client_warn!`, which
+//! is rate-limited per client with the `log_rate_limit` feature, so a client spamming invalid
+//! requests cannot flood the log.
+//!
+//! ```no_run
+//! # fn commit(dh: &smithay::reexports::wayland_server::DisplayHandle, surface: &smithay::reexports::wayland_server::protocol::wl_surface::WlSurface) {
+//! use smithay::reexports::wayland_server::Resource;
+//!
+//! let _span = smithay::utils::log::surface_span(surface).entered();
+//! smithay::client_warn!(dh, surface.client().map(|c| c.id()), "Invalid buffer size");
+//! # }
+//! ```
+
+#[cfg(feature = "log_rate_limit")]
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::{trace_span, Span};
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::{backend::ClientId, protocol::wl_surface::WlSurface, DisplayHandle, Resource};
+
+use crate::output::Output;
+#[cfg(feature = "log_rate_limit")]
+use crate::utils::{system_time_source, Monotonic, Time, TimeSource};
+
+#[doc(hidden)]
+pub use tracing as __tracing;
+
+/// Creates a span carrying the `client` field
+#[cfg(feature = "wayland_frontend")]
+pub fn client_span(client: &ClientId) -> Span {
+    trace_span!("client", client = ?client)
+}
+
+/// Creates a span carrying the `surface` and `client` fields of a surface
+#[cfg(feature = "wayland_frontend")]
+pub fn surface_span(surface: &WlSurface) -> Span {
+    trace_span!(
+        "surface",
+        surface = %surface.id(),
+        client = ?surface.client().map(|client| client.id()),
+    )
+}
+
+/// Creates a span carrying the `output` field
+pub fn output_span(output: &Output) -> Span {
+    trace_span!("output", output = output.name())
+}
+
+/// Limits how often an event may happen per key
+///
+/// Allows `burst` events per key in each `interval`, counting the suppressed ones. The time is
+/// read from a [`TimeSource`], which tests can replace with a
+/// [`ManualClock`](crate::utils::ManualClock).
+#[cfg(feature = "log_rate_limit")]
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    burst: u32,
+    interval: Duration,
+    time_source: Arc<dyn TimeSource>,
+    windows: Mutex<HashMap<K, Window>>,
+}
+
+#[cfg(feature = "log_rate_limit")]
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    start: Time<Monotonic>,
+    count: u32,
+    suppressed: u64,
+}
+
+#[cfg(feature = "log_rate_limit")]
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    /// Create a new limiter allowing `burst` events per key in each `interval`
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        Self::with_time_source(burst, interval, system_time_source())
+    }
+
+    /// Create a new limiter reading the time from `time_source`
+    pub fn with_time_source(burst: u32, interval: Duration, time_source: Arc<dyn TimeSource>) -> Self {
+        RateLimiter {
+            burst,
+            interval,
+            time_source,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an event for `key`
+    ///
+    /// Returns the number of events suppressed since the last allowed one if this event is
+    /// allowed, or `None` if it should be suppressed.
+    pub fn check(&self, key: &K) -> Option<u64> {
+        let now = self.time_source.now();
+        let mut windows = self.windows.lock().unwrap();
+        // keys of disconnected clients are dropped once their window expired
+        if windows.len() > 64 {
+            windows.retain(|_, window| now.saturating_duration_since(window.start) < self.interval);
+        }
+
+        let window = windows.entry(key.clone()).or_insert(Window {
+            start: now,
+            count: 0,
+            suppressed: 0,
+        });
+        if now.saturating_duration_since(window.start) >= self.interval {
+            window.start = now;
+            window.count = 0;
+        }
+        if window.count < self.burst {
+            window.count += 1;
+            Some(std::mem::take(&mut window.suppressed))
+        } else {
+            window.suppressed += 1;
+            None
+        }
+    }
+}
+
+// Client ids are only unique per display, so clients are identified by their client data. Holding
+// a weak reference keeps the allocation, and thereby the key, from being reused.
+#[cfg(all(feature = "log_rate_limit", feature = "wayland_frontend"))]
+#[derive(Debug, Clone)]
+struct ClientKey(std::sync::Weak<dyn wayland_server::backend::ClientData>);
+
+#[cfg(all(feature = "log_rate_limit", feature = "wayland_frontend"))]
+impl PartialEq for ClientKey {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self.0.as_ptr(), other.0.as_ptr())
+    }
+}
+
+#[cfg(all(feature = "log_rate_limit", feature = "wayland_frontend"))]
+impl Eq for ClientKey {}
+
+#[cfg(all(feature = "log_rate_limit", feature = "wayland_frontend"))]
+impl Hash for ClientKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as *const () as usize).hash(state)
+    }
+}
+
+#[cfg(all(feature = "log_rate_limit", feature = "wayland_frontend"))]
+static CLIENT_WARNINGS: std::sync::LazyLock<RateLimiter<ClientKey>> =
+    std::sync::LazyLock::new(|| RateLimiter::new(10, Duration::from_secs(10)));
+
+/// Returns whether a warning caused by `client` of the display `dh` should be logged, see `client_warn!`
+///
+/// Returns the number of suppressed warnings of the client, if the warning should be logged.
+/// Without the `log_rate_limit` feature, all warnings are logged.
+#[cfg(feature = "wayland_frontend")]
+pub fn client_warning_allowed(dh: &DisplayHandle, client: Option<&ClientId>) -> Option<u64> {
+    #[cfg(feature = "log_rate_limit")]
+    if let Some(data) = client.and_then(|client| dh.backend_handle().get_client_data(client.clone()).ok()) {
+        return CLIENT_WARNINGS.check(&ClientKey(Arc::downgrade(&data)));
+    }
+    let _ = (dh, client);
+    Some(0)
+}
+
+/// Logs a warning caused by a client
+///
+/// Takes the [`DisplayHandle`](wayland_server::DisplayHandle) of the client and an `Option<ClientId>`, followed by the arguments of
+/// [`tracing::warn!`]. The message is logged with the `client` field and, with the `log_rate_limit`
+/// feature, at most ten times per client in ten seconds; the `suppressed` field counts the warnings
+/// dropped since the last one.
+#[cfg(feature = "wayland_frontend")]
+#[macro_export]
+macro_rules! client_warn {
+    ($dh:expr, $client:expr, $($arg:tt)+) => {{
+        let client: ::std::option::Option<$crate::reexports::wayland_server::backend::ClientId> = $client;
+        if let ::std::option::Option::Some(suppressed) =
+            $crate::utils::log::client_warning_allowed($dh, client.as_ref())
+        {
+            $crate::utils::log::__tracing::warn!(client = ?client, suppressed, $($arg)+);
+        }
+    }};
+}
+
+#[cfg(all(test, feature = "log_rate_limit"))]
+mod tests {
+    use super::*;
+    use crate::utils::ManualClock;
+
+    #[test]
+    fn rate_limit_per_key() {
+        let clock = ManualClock::default();
+        let limiter = RateLimiter::with_time_source(2, Duration::from_secs(1), Arc::new(clock.clone()));
+
+        assert_eq!(limiter.check(&1), Some(0));
+        assert_eq!(limiter.check(&1), Some(0));
+        assert_eq!(limiter.check(&1), None);
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(limiter.check(&1), None);
+        // other keys are not affected
+        assert_eq!(limiter.check(&2), Some(0));
+        // the next window reports the suppressed events
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(limiter.check(&1), Some(2));
+    }
+
+    #[cfg(all(unix, feature = "wayland_frontend"))]
+    #[test]
+    fn clients_of_different_displays_are_limited_separately() {
+        use wayland_server::{
+            backend::{ClientData, DisconnectReason},
+            Display,
+        };
+
+        use crate::wayland::test::FakeClient;
+
+        struct ClientState;
+
+        impl ClientData for ClientState {
+            fn initialized(&self, _client_id: ClientId) {}
+            fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+        }
+
+        let first = Display::<()>::new().unwrap();
+        let second = Display::<()>::new().unwrap();
+        let (_fake, a) = FakeClient::connect(&mut first.handle(), Arc::new(ClientState)).unwrap();
+        let (_fake, b) = FakeClient::connect(&mut second.handle(), Arc::new(ClientState)).unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(client_warning_allowed(&first.handle(), Some(&a.id())), Some(0));
+        }
+        assert_eq!(client_warning_allowed(&first.handle(), Some(&a.id())), None);
+        // the first client of the second display may share the client id, but not the limit
+        assert_eq!(client_warning_allowed(&second.handle(), Some(&b.id())), Some(0));
+        // warnings without a client are never limited
+        assert_eq!(client_warning_allowed(&first.handle(), None), Some(0));
+    }
+}
@@ -21,12 +21,27 @@ pub use fd::*;
 mod sealed_file;
 pub use sealed_file::SealedFile;
 
+#[cfg(feature = "async_tokio")]
+pub mod async_loop;
+#[cfg(any(target_os = "linux", target_os = "android", windows))]
+pub mod config_watcher;
+#[cfg(all(unix, feature = "dbus"))]
+pub mod dbus;
+pub mod log;
+pub mod process;
+pub mod ring;
+pub mod service;
+pub mod sync;
+
 #[cfg(feature = "wayland_frontend")]
 pub(crate) use self::geometry::Client;
 pub use self::geometry::{
     Buffer, Coordinate, Logical, Physical, Point, Raw, Rectangle, Scale, Size, Transform,
 };
 
+mod region;
+pub use region::Region;
+
 mod serial;
 pub use serial::*;
 
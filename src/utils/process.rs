@@ -0,0 +1,215 @@
+//! Spawning client processes
+//!
+//! Passing a file descriptor to a child process requires clearing its close-on-exec flag in the
+//! child only, passing a handle on Windows requires marking it inheritable just for the duration
+//! of the spawn. [`ClientCommand`] wraps a [`Command`] and takes care of both, as well as of
+//! setting up `WAYLAND_SOCKET` for a pre-connected client.
+//!
+//! On Linux every other file descriptor of the compositor is additionally marked close-on-exec
+//! in the child, so descriptors opened without `O_CLOEXEC` by other libraries do not leak into
+//! clients.
+//!
+//! ```no_run
+//! # #[cfg(unix)] {
+//! use smithay::utils::process::ClientCommand;
+//! # use std::sync::Arc;
+//! # struct ClientState;
+//! # impl smithay::reexports::wayland_server::backend::ClientData for ClientState {
+//! #     fn initialized(&self, _: smithay::reexports::wayland_server::backend::ClientId) {}
+//! #     fn disconnected(
+//! #         &self,
+//! #         _: smithay::reexports::wayland_server::backend::ClientId,
+//! #         _: smithay::reexports::wayland_server::backend::DisconnectReason,
+//! #     ) {
+//! #     }
+//! # }
+//! # let display = smithay::reexports::wayland_server::Display::<()>::new().unwrap();
+//!
+//! let mut command = ClientCommand::new("weston-terminal");
+//! command.command().arg("--maximized");
+//! let (child, client) = command
+//!     .spawn_client(&mut display.handle(), Arc::new(ClientState))
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::{
+    ffi::OsStr,
+    io,
+    process::{Child, Command},
+};
+
+use crate::compat::{AsRawFd, OwnedFd, RawFd};
+
+/// Environment variable holding the fd of a pre-connected wayland socket
+pub const WAYLAND_SOCKET: &str = "WAYLAND_SOCKET";
+
+/// A [`Command`] passing a chosen set of file descriptors (handles on Windows) to the child
+#[derive(Debug)]
+pub struct ClientCommand {
+    command: Command,
+    inherited: Vec<OwnedFd>,
+}
+
+impl ClientCommand {
+    /// Create a new command launching the given program
+    pub fn new(program: impl AsRef<OsStr>) -> ClientCommand {
+        ClientCommand::from(Command::new(program))
+    }
+
+    /// Access the wrapped command, e.g. to add arguments or environment variables
+    pub fn command(&mut self) -> &mut Command {
+        &mut self.command
+    }
+
+    /// Pass the given file descriptor to the child process
+    ///
+    /// Returns the raw fd (or handle) the child can access it with. It stays open in the
+    /// compositor until the command is spawned or dropped.
+    pub fn inherit(&mut self, fd: impl Into<OwnedFd>) -> RawFd {
+        let fd = fd.into();
+        let raw = fd.as_raw_fd();
+        self.inherited.push(fd);
+        raw
+    }
+
+    /// Pass a pre-connected wayland socket to the child process
+    ///
+    /// Sets `WAYLAND_SOCKET` to the inherited fd and removes `WAYLAND_DISPLAY`, so the client
+    /// does not connect to another compositor instead. On Windows the value of
+    /// `WAYLAND_SOCKET` is the inherited socket handle.
+    pub fn wayland_socket(&mut self, socket: impl Into<OwnedFd>) -> &mut Self {
+        let raw = self.inherit(socket);
+        #[cfg(unix)]
+        let value = raw.to_string();
+        #[cfg(windows)]
+        let value = (raw as usize).to_string();
+        self.command
+            .env(WAYLAND_SOCKET, value)
+            .env_remove("WAYLAND_DISPLAY");
+        self
+    }
+
+    /// Spawn the child process
+    ///
+    /// The inherited file descriptors are closed in the compositor afterwards.
+    #[cfg(unix)]
+    pub fn spawn(mut self) -> io::Result<Child> {
+        use std::os::unix::process::CommandExt;
+
+        let fds: Vec<RawFd> = self.inherited.iter().map(|fd| fd.as_raw_fd()).collect();
+        // SAFETY: the closure only performs async-signal-safe syscalls and does not allocate
+        unsafe {
+            self.command.pre_exec(move || {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                {
+                    // mark everything except stdio close-on-exec, this fails on kernels
+                    // before 5.11, which is fine as std opens all fds with O_CLOEXEC anyway.
+                    const CLOSE_RANGE_CLOEXEC: libc::c_uint = 1 << 2;
+                    libc::syscall(
+                        libc::SYS_close_range,
+                        3 as libc::c_uint,
+                        libc::c_uint::MAX,
+                        CLOSE_RANGE_CLOEXEC,
+                    );
+                }
+
+                for &fd in &fds {
+                    let fd = rustix::fd::BorrowedFd::borrow_raw(fd);
+                    rustix::io::fcntl_setfd(fd, rustix::io::FdFlags::empty())?;
+                }
+                Ok(())
+            });
+        }
+
+        let child = self.command.spawn();
+        self.inherited.clear();
+        child
+    }
+
+    /// Spawn the child process
+    ///
+    /// The inherited handles are closed in the compositor afterwards.
+    ///
+    /// Handles are only inheritable while the child is spawned, but processes spawned
+    /// concurrently from other threads may inherit them as well.
+    #[cfg(windows)]
+    pub fn spawn(mut self) -> io::Result<Child> {
+        for handle in &self.inherited {
+            set_inheritable(handle.as_raw_fd(), true)?;
+        }
+        let child = self.command.spawn();
+        for handle in &self.inherited {
+            let _ = set_inheritable(handle.as_raw_fd(), false);
+        }
+        self.inherited.clear();
+        child
+    }
+
+    /// Spawn the child process connected to the given display
+    ///
+    /// Creates a socket pair, passes one end to the child through `WAYLAND_SOCKET` and inserts
+    /// the other end as a new client with the given data.
+    #[cfg(all(unix, feature = "wayland_frontend"))]
+    pub fn spawn_client(
+        mut self,
+        dh: &mut wayland_server::DisplayHandle,
+        data: std::sync::Arc<dyn wayland_server::backend::ClientData>,
+    ) -> io::Result<(Child, wayland_server::Client)> {
+        let (ours, theirs) = std::os::unix::net::UnixStream::pair()?;
+        self.wayland_socket(theirs);
+        let child = self.spawn()?;
+        let client = dh.insert_client(ours, data)?;
+        Ok((child, client))
+    }
+}
+
+impl From<Command> for ClientCommand {
+    fn from(command: Command) -> Self {
+        ClientCommand {
+            command,
+            inherited: Vec::new(),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn set_inheritable(handle: RawFd, inheritable: bool) -> io::Result<()> {
+    const HANDLE_FLAG_INHERIT: u32 = 0x1;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetHandleInformation(handle: isize, mask: u32, flags: u32) -> i32;
+    }
+
+    let flags = if inheritable { HANDLE_FLAG_INHERIT } else { 0 };
+    if unsafe { SetHandleInformation(handle as isize, HANDLE_FLAG_INHERIT, flags) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{io::Read, os::unix::net::UnixStream, process::Stdio};
+
+    use super::ClientCommand;
+
+    #[test]
+    fn passes_wayland_socket() {
+        let (mut ours, theirs) = UnixStream::pair().unwrap();
+        let mut command = ClientCommand::new("sh");
+        command
+            .command()
+            .arg("-c")
+            .arg("printf ok >&$WAYLAND_SOCKET")
+            .stdout(Stdio::null());
+        command.wayland_socket(theirs);
+        let status = command.spawn().unwrap().wait().unwrap();
+        assert!(status.success());
+
+        let mut buf = String::new();
+        ours.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "ok");
+    }
+}
@@ -0,0 +1,426 @@
+//! Region arithmetic on integer rectangles
+//!
+//! A [`Region`] is an arbitrary set of pixels described by non-overlapping rectangles,
+//! e.g. the damage of a frame. Like pixman regions it is stored as horizontal bands, each
+//! holding sorted, non-touching spans, with vertically adjacent identical bands merged.
+//! This canonical form makes two regions covering the same area compare equal and keeps
+//! the number of rectangles low.
+
+use std::{fmt, marker::PhantomData};
+
+use super::{Point, Rectangle, Size};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Band {
+    top: i32,
+    bottom: i32,
+    /// Sorted, non-overlapping and non-touching `[start, end)` spans
+    spans: Vec<(i32, i32)>,
+}
+
+/// A set of pixels described by non-overlapping rectangles, see the [module-level documentation](self)
+pub struct Region<Kind> {
+    bands: Vec<Band>,
+    _kind: PhantomData<Kind>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Union,
+    Intersect,
+    Subtract,
+}
+
+impl Op {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Op::Union => a || b,
+            Op::Intersect => a && b,
+            Op::Subtract => a && !b,
+        }
+    }
+}
+
+impl<Kind> Region<Kind> {
+    /// Create an empty region
+    pub fn new() -> Self {
+        Region {
+            bands: Vec::new(),
+            _kind: PhantomData,
+        }
+    }
+
+    /// Create a region covering the given rectangle
+    pub fn from_rect(rect: Rectangle<i32, Kind>) -> Self {
+        let mut region = Region::new();
+        if !rect.is_empty() {
+            region.bands.push(Band {
+                top: rect.loc.y,
+                bottom: rect.loc.y.saturating_add(rect.size.h),
+                spans: vec![(rect.loc.x, rect.loc.x.saturating_add(rect.size.w))],
+            });
+        }
+        region
+    }
+
+    /// Returns `true` if the region doesn't contain any pixels
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// Iterate over the non-overlapping rectangles making up this region
+    ///
+    /// Rectangles are sorted top to bottom and left to right.
+    pub fn rects(&self) -> impl Iterator<Item = Rectangle<i32, Kind>> + '_ {
+        self.bands.iter().flat_map(|band| {
+            band.spans.iter().map(move |&(start, end)| {
+                Rectangle::new(
+                    Point::new(start, band.top),
+                    Size::new(end - start, band.bottom - band.top),
+                )
+            })
+        })
+    }
+
+    /// Returns the smallest rectangle containing the whole region
+    pub fn bounding_box(&self) -> Option<Rectangle<i32, Kind>> {
+        let first = self.bands.first()?;
+        let last = self.bands.last()?;
+        let left = self.bands.iter().map(|band| band.spans[0].0).min()?;
+        let right = self
+            .bands
+            .iter()
+            .map(|band| band.spans[band.spans.len() - 1].1)
+            .max()?;
+        Some(Rectangle::new(
+            Point::new(left, first.top),
+            Size::new(right - left, last.bottom - first.top),
+        ))
+    }
+
+    /// Returns `true` if the point is part of the region
+    pub fn contains(&self, point: impl Into<Point<i32, Kind>>) -> bool {
+        let point = point.into();
+        self.bands
+            .iter()
+            .find(|band| band.top <= point.y && point.y < band.bottom)
+            .is_some_and(|band| {
+                band.spans
+                    .iter()
+                    .any(|&(start, end)| start <= point.x && point.x < end)
+            })
+    }
+
+    /// Returns `true` if any part of the rectangle is part of the region
+    pub fn overlaps(&self, rect: Rectangle<i32, Kind>) -> bool {
+        !self.intersect(&Region::from_rect(rect)).is_empty()
+    }
+
+    /// Returns the union of both regions
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, Op::Union)
+    }
+
+    /// Returns the intersection of both regions
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    pub fn intersect(&self, other: &Self) -> Self {
+        self.combine(other, Op::Intersect)
+    }
+
+    /// Returns the pixels of this region that are not part of `other`
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    pub fn subtract(&self, other: &Self) -> Self {
+        self.combine(other, Op::Subtract)
+    }
+
+    /// Add a rectangle to this region
+    pub fn add_rect(&mut self, rect: Rectangle<i32, Kind>) {
+        *self = self.union(&Region::from_rect(rect));
+    }
+
+    /// Remove a rectangle from this region
+    pub fn subtract_rect(&mut self, rect: Rectangle<i32, Kind>) {
+        *self = self.subtract(&Region::from_rect(rect));
+    }
+
+    /// Clip this region to the given rectangle
+    pub fn intersect_rect(&mut self, rect: Rectangle<i32, Kind>) {
+        *self = self.intersect(&Region::from_rect(rect));
+    }
+
+    /// Move the whole region by the given offset
+    #[must_use = "this returns the result of the operation, without modifying the original"]
+    pub fn translate(mut self, offset: impl Into<Point<i32, Kind>>) -> Self {
+        let offset = offset.into();
+        for band in &mut self.bands {
+            band.top = band.top.saturating_add(offset.y);
+            band.bottom = band.bottom.saturating_add(offset.y);
+            for span in &mut band.spans {
+                span.0 = span.0.saturating_add(offset.x);
+                span.1 = span.1.saturating_add(offset.x);
+            }
+        }
+        self
+    }
+
+    fn combine(&self, other: &Self, op: Op) -> Self {
+        let mut edges = Vec::with_capacity((self.bands.len() + other.bands.len()) * 2);
+        for band in self.bands.iter().chain(other.bands.iter()) {
+            edges.push(band.top);
+            edges.push(band.bottom);
+        }
+        edges.sort_unstable();
+        edges.dedup();
+
+        let mut result = Region::new();
+        let (mut a, mut b) = (0, 0);
+        for slab in edges.windows(2) {
+            let (top, bottom) = (slab[0], slab[1]);
+            while a < self.bands.len() && self.bands[a].bottom <= top {
+                a += 1;
+            }
+            while b < other.bands.len() && other.bands[b].bottom <= top {
+                b += 1;
+            }
+            let spans_a = self
+                .bands
+                .get(a)
+                .filter(|band| band.top <= top)
+                .map(|band| &band.spans[..]);
+            let spans_b = other
+                .bands
+                .get(b)
+                .filter(|band| band.top <= top)
+                .map(|band| &band.spans[..]);
+            let spans = combine_spans(spans_a.unwrap_or(&[]), spans_b.unwrap_or(&[]), op);
+            result.push_band(top, bottom, spans);
+        }
+        result
+    }
+
+    /// Append a band below all existing ones, keeping the canonical form
+    fn push_band(&mut self, top: i32, bottom: i32, spans: Vec<(i32, i32)>) {
+        if spans.is_empty() || top >= bottom {
+            return;
+        }
+        if let Some(last) = self.bands.last_mut() {
+            if last.bottom == top && last.spans == spans {
+                last.bottom = bottom;
+                return;
+            }
+        }
+        self.bands.push(Band { top, bottom, spans });
+    }
+}
+
+/// Combine two sorted span lists by sweeping over all span edges
+fn combine_spans(a: &[(i32, i32)], b: &[(i32, i32)], op: Op) -> Vec<(i32, i32)> {
+    let mut edges = Vec::with_capacity((a.len() + b.len()) * 2);
+    for &(start, end) in a.iter().chain(b.iter()) {
+        edges.push(start);
+        edges.push(end);
+    }
+    edges.sort_unstable();
+    edges.dedup();
+
+    let inside = |spans: &[(i32, i32)], x: i32| spans.iter().any(|&(start, end)| start <= x && x < end);
+    let mut result: Vec<(i32, i32)> = Vec::new();
+    for segment in edges.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        if !op.apply(inside(a, start), inside(b, start)) {
+            continue;
+        }
+        match result.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => result.push((start, end)),
+        }
+    }
+    result
+}
+
+impl<Kind> Default for Region<Kind> {
+    fn default() -> Self {
+        Region::new()
+    }
+}
+
+impl<Kind> Clone for Region<Kind> {
+    fn clone(&self) -> Self {
+        Region {
+            bands: self.bands.clone(),
+            _kind: PhantomData,
+        }
+    }
+}
+
+impl<Kind> PartialEq for Region<Kind> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bands == other.bands
+    }
+}
+
+impl<Kind> Eq for Region<Kind> {}
+
+impl<Kind> fmt::Debug for Region<Kind> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.rects()
+                    .map(|rect| (rect.loc.x, rect.loc.y, rect.size.w, rect.size.h)),
+            )
+            .finish()
+    }
+}
+
+impl<Kind> From<Rectangle<i32, Kind>> for Region<Kind> {
+    fn from(rect: Rectangle<i32, Kind>) -> Self {
+        Region::from_rect(rect)
+    }
+}
+
+impl<Kind> FromIterator<Rectangle<i32, Kind>> for Region<Kind> {
+    fn from_iter<T: IntoIterator<Item = Rectangle<i32, Kind>>>(iter: T) -> Self {
+        let mut region = Region::new();
+        region.extend(iter);
+        region
+    }
+}
+
+impl<Kind> Extend<Rectangle<i32, Kind>> for Region<Kind> {
+    fn extend<T: IntoIterator<Item = Rectangle<i32, Kind>>>(&mut self, iter: T) {
+        for rect in iter {
+            self.add_rect(rect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::Region;
+    use crate::utils::{Logical, Rectangle};
+
+    const SIZE: i32 = 24;
+
+    /// Naive reference implementation, a bitmap of `SIZE * SIZE` pixels
+    #[derive(Debug, Clone, PartialEq)]
+    struct Bitmap(Vec<bool>);
+
+    impl Bitmap {
+        fn new() -> Self {
+            Bitmap(vec![false; (SIZE * SIZE) as usize])
+        }
+
+        fn from_rects(rects: &[Rectangle<i32, Logical>]) -> Self {
+            let mut bitmap = Bitmap::new();
+            for rect in rects {
+                bitmap.fill(*rect, true);
+            }
+            bitmap
+        }
+
+        fn fill(&mut self, rect: Rectangle<i32, Logical>, value: bool) {
+            for y in rect.loc.y..rect.loc.y + rect.size.h {
+                for x in rect.loc.x..rect.loc.x + rect.size.w {
+                    self.0[(y * SIZE + x) as usize] = value;
+                }
+            }
+        }
+
+        fn from_region(region: &Region<Logical>) -> Self {
+            let mut bitmap = Bitmap::new();
+            for rect in region.rects() {
+                // every pixel may only be covered once
+                for y in rect.loc.y..rect.loc.y + rect.size.h {
+                    for x in rect.loc.x..rect.loc.x + rect.size.w {
+                        let pixel = &mut bitmap.0[(y * SIZE + x) as usize];
+                        assert!(!*pixel, "overlapping rectangles in {:?}", region);
+                        *pixel = true;
+                    }
+                }
+            }
+            bitmap
+        }
+
+        fn zip(&self, other: &Bitmap, f: impl Fn(bool, bool) -> bool) -> Bitmap {
+            Bitmap(self.0.iter().zip(&other.0).map(|(a, b)| f(*a, *b)).collect())
+        }
+    }
+
+    fn rect() -> impl Strategy<Value = Rectangle<i32, Logical>> {
+        (0..SIZE, 0..SIZE)
+            .prop_flat_map(|(x, y)| (Just(x), Just(y), 0..=SIZE - x, 0..=SIZE - y))
+            .prop_map(|(x, y, w, h)| Rectangle::new((x, y).into(), (w, h).into()))
+    }
+
+    fn rects() -> impl Strategy<Value = Vec<Rectangle<i32, Logical>>> {
+        prop::collection::vec(rect(), 0..8)
+    }
+
+    proptest! {
+        #[test]
+        fn union_matches_bitmap(rects_a in rects(), rects_b in rects()) {
+            let a = rects_a.iter().copied().collect::<Region<Logical>>();
+            let b = rects_b.iter().copied().collect::<Region<Logical>>();
+            let (bitmap_a, bitmap_b) = (Bitmap::from_rects(&rects_a), Bitmap::from_rects(&rects_b));
+
+            prop_assert_eq!(Bitmap::from_region(&a), bitmap_a.clone());
+            prop_assert_eq!(
+                Bitmap::from_region(&a.union(&b)),
+                bitmap_a.zip(&bitmap_b, |a, b| a || b)
+            );
+            // the canonical form makes equal areas compare equal
+            prop_assert_eq!(a.union(&b), b.union(&a));
+        }
+
+        #[test]
+        fn intersect_matches_bitmap(rects_a in rects(), rects_b in rects()) {
+            let a = rects_a.iter().copied().collect::<Region<Logical>>();
+            let b = rects_b.iter().copied().collect::<Region<Logical>>();
+            let (bitmap_a, bitmap_b) = (Bitmap::from_rects(&rects_a), Bitmap::from_rects(&rects_b));
+
+            prop_assert_eq!(
+                Bitmap::from_region(&a.intersect(&b)),
+                bitmap_a.zip(&bitmap_b, |a, b| a && b)
+            );
+            prop_assert_eq!(a.intersect(&b), b.intersect(&a));
+        }
+
+        #[test]
+        fn subtract_matches_bitmap(rects_a in rects(), rects_b in rects()) {
+            let a = rects_a.iter().copied().collect::<Region<Logical>>();
+            let b = rects_b.iter().copied().collect::<Region<Logical>>();
+            let (bitmap_a, bitmap_b) = (Bitmap::from_rects(&rects_a), Bitmap::from_rects(&rects_b));
+
+            prop_assert_eq!(
+                Bitmap::from_region(&a.subtract(&b)),
+                bitmap_a.zip(&bitmap_b, |a, b| a && !b)
+            );
+            prop_assert_eq!(a.subtract(&b).union(&a.intersect(&b)), a);
+        }
+    }
+
+    #[test]
+    fn merges_adjacent_rects() {
+        let region = [
+            Rectangle::<i32, Logical>::new((0, 0).into(), (10, 5).into()),
+            Rectangle::new((0, 5).into(), (10, 5).into()),
+            Rectangle::new((10, 0).into(), (5, 10).into()),
+        ]
+        .into_iter()
+        .collect::<Region<Logical>>();
+
+        assert_eq!(
+            region.rects().collect::<Vec<_>>(),
+            vec![Rectangle::new((0, 0).into(), (15, 10).into())]
+        );
+        assert_eq!(
+            region.bounding_box(),
+            Some(Rectangle::new((0, 0).into(), (15, 10).into()))
+        );
+        assert!(region.contains((14, 9)));
+        assert!(!region.contains((15, 9)));
+    }
+}
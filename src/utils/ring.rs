@@ -0,0 +1,416 @@
+//! Lock-free single-producer single-consumer ring buffer
+//!
+//! Input backends reading events on a dedicated thread (e.g. raw input on Windows or
+//! libinput on Linux) need to hand them to the event loop thread. With high polling-rate
+//! devices this happens thousands of times per second, so [`channel`] provides a bounded
+//! ring buffer which neither allocates per event nor takes a lock.
+//!
+//! [`source`] combines the ring with a [`Notifier`] into a calloop event source,
+//! waking up the event loop at most once per batch of events.
+//!
+//! The Windows raw input backend (`backend::win32::raw_input::RawInputSource`) reads its
+//! events on such a thread and pushes them through a [`RingSender`]. The libinput and evdev
+//! backends read their file descriptors on the event loop thread itself and do not need it.
+//!
+//! ```no_run
+//! use smithay::utils::ring;
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! let (mut sender, source) = ring::source::<u32>(1024).unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |event, _, _state| {
+//!         // process the event
+//!     })
+//!     .unwrap();
+//!
+//! std::thread::spawn(move || {
+//!     // the ring is full, the event loop is lagging behind
+//!     let _ = sender.push(42);
+//! });
+//! ```
+
+use std::{fmt, io, mem::MaybeUninit};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+
+use super::sync::{Arc, AtomicBool, AtomicUsize, Ordering, UnsafeCell};
+use crate::compat::notifier::{self, Notifier, NotifierSource};
+
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+struct Shared<T> {
+    // index of the next slot to read, only written by the consumer
+    head: CachePadded<AtomicUsize>,
+    // index of the next slot to write, only written by the producer
+    tail: CachePadded<AtomicUsize>,
+    mask: usize,
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+// SAFETY: every slot is only accessed by either the producer or the consumer at a time,
+// handed over through the release stores of `head` and `tail`
+unsafe impl<T: Send> Sync for Shared<T> {}
+unsafe impl<T: Send> Send for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // no other thread holds the ring anymore
+        let head = self.head.0.load(Ordering::Relaxed);
+        let tail = self.tail.0.load(Ordering::Relaxed);
+        for index in head..tail {
+            // SAFETY: slots between head and tail are initialized
+            self.slots[index & self.mask].with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+        }
+    }
+}
+
+/// Sending half of a ring buffer, see [`channel`]
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    tail: usize,
+    cached_head: usize,
+}
+
+/// Receiving half of a ring buffer, see [`channel`]
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    head: usize,
+    cached_tail: usize,
+}
+
+impl<T> fmt::Debug for Producer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Producer")
+            .field("capacity", &self.shared.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for Consumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Consumer")
+            .field("capacity", &self.shared.capacity())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Create a new ring buffer holding at least `capacity` elements
+///
+/// The capacity is rounded up to the next power of two.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "ring buffer capacity must not be zero");
+    let capacity = capacity.next_power_of_two();
+    let shared = Arc::new(Shared {
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+        mask: capacity - 1,
+        slots: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+    });
+    (
+        Producer {
+            shared: shared.clone(),
+            tail: 0,
+            cached_head: 0,
+        },
+        Consumer {
+            shared,
+            head: 0,
+            cached_tail: 0,
+        },
+    )
+}
+
+impl<T> Producer<T> {
+    /// Push a value into the ring
+    ///
+    /// Returns the value back if the ring is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let capacity = self.shared.capacity();
+        if self.tail.wrapping_sub(self.cached_head) == capacity {
+            self.cached_head = self.shared.head.0.load(Ordering::Acquire);
+            if self.tail.wrapping_sub(self.cached_head) == capacity {
+                return Err(value);
+            }
+        }
+
+        let slot = &self.shared.slots[self.tail & self.shared.mask];
+        // SAFETY: the slot is free, the consumer does not access it before `tail` is published
+        slot.with_mut(|slot| unsafe { (*slot).write(value) });
+        self.tail = self.tail.wrapping_add(1);
+        self.shared.tail.0.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    /// Capacity of the ring
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Returns `true` if the [`Consumer`] was dropped
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Pop the oldest value from the ring
+    pub fn pop(&mut self) -> Option<T> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.shared.tail.0.load(Ordering::Acquire);
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+
+        let slot = &self.shared.slots[self.head & self.shared.mask];
+        // SAFETY: the slot was initialized by the producer before publishing `tail`
+        let value = slot.with_mut(|slot| unsafe { (*slot).assume_init_read() });
+        self.head = self.head.wrapping_add(1);
+        self.shared.head.0.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    /// Iterate over the values currently in the ring, removing them
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.pop())
+    }
+
+    /// Number of values in the ring
+    pub fn len(&self) -> usize {
+        self.shared.tail.0.load(Ordering::Acquire).wrapping_sub(self.head)
+    }
+
+    /// Returns `true` if the ring is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Capacity of the ring
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Returns `true` if the [`Producer`] was dropped
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+/// Sending half of a [`RingSource`]
+#[derive(Debug)]
+pub struct RingSender<T> {
+    producer: Producer<T>,
+    pending: Arc<AtomicBool>,
+    notifier: Notifier,
+}
+
+impl<T> RingSender<T> {
+    /// Push a value into the ring and wake up the event loop
+    ///
+    /// The event loop is only woken up once until the [`RingSource`] is dispatched,
+    /// no matter how many values are pushed in between.
+    ///
+    /// Returns the value back if the ring is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        self.producer.push(value)?;
+        if !self.pending.swap(true, Ordering::SeqCst) {
+            self.notifier.notify();
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the [`RingSource`] was dropped
+    pub fn is_abandoned(&self) -> bool {
+        self.producer.is_abandoned()
+    }
+}
+
+/// Calloop event source dispatching the values of a ring buffer
+///
+/// Generates an event for every value pushed with the matching [`RingSender`].
+#[derive(Debug)]
+pub struct RingSource<T> {
+    consumer: Consumer<T>,
+    pending: Arc<AtomicBool>,
+    source: NotifierSource,
+}
+
+/// Create a new ring buffer holding at least `capacity` elements as a calloop event source
+///
+/// See [`channel`] for details on the capacity.
+pub fn source<T>(capacity: usize) -> io::Result<(RingSender<T>, RingSource<T>)> {
+    let (producer, consumer) = channel(capacity);
+    let (notifier, source) = notifier::new()?;
+    let pending = Arc::new(AtomicBool::new(false));
+    Ok((
+        RingSender {
+            producer,
+            pending: pending.clone(),
+            notifier,
+        },
+        RingSource {
+            consumer,
+            pending,
+            source,
+        },
+    ))
+}
+
+impl<T> EventSource for RingSource<T> {
+    type Event = T;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let consumer = &mut self.consumer;
+        let pending = &self.pending;
+        self.source.process_events(readiness, token, |_, _| {
+            // reset before draining, values pushed afterwards notify again
+            pending.store(false, Ordering::SeqCst);
+            for value in consumer.drain() {
+                callback(value, &mut ());
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::channel;
+
+    #[test]
+    fn fifo_and_full() {
+        let (mut producer, mut consumer) = channel(3);
+        assert_eq!(producer.capacity(), 4);
+        for i in 0..4 {
+            producer.push(i).unwrap();
+        }
+        assert_eq!(producer.push(4), Err(4));
+        assert_eq!(consumer.len(), 4);
+        assert_eq!(consumer.pop(), Some(0));
+        producer.push(4).unwrap();
+        assert_eq!(consumer.drain().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(consumer.is_empty());
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn drops_remaining() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut producer, mut consumer) = channel(4);
+        for _ in 0..3 {
+            producer.push(Counted(drops.clone())).ok().unwrap();
+        }
+        drop(consumer.pop());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(producer);
+        assert!(consumer.is_abandoned());
+        drop(consumer);
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn across_threads() {
+        const COUNT: usize = 100_000;
+        let (mut producer, mut consumer) = channel(64);
+        let thread = std::thread::spawn(move || {
+            for mut i in 0..COUNT {
+                while let Err(value) = producer.push(i) {
+                    i = value;
+                    std::thread::yield_now();
+                }
+            }
+        });
+
+        let mut expected = 0;
+        while expected < COUNT {
+            match consumer.pop() {
+                Some(value) => {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+                None => std::thread::yield_now(),
+            }
+        }
+        thread.join().unwrap();
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::channel;
+
+    #[test]
+    fn loom_push_pop() {
+        loom::model(|| {
+            let (mut producer, mut consumer) = channel(2);
+            let thread = loom::thread::spawn(move || {
+                for i in 0..3 {
+                    // the third value only fits once the consumer popped one
+                    let _ = producer.push(i);
+                }
+            });
+
+            let mut received = Vec::new();
+            for _ in 0..3 {
+                if let Some(value) = consumer.pop() {
+                    received.push(value);
+                }
+            }
+            thread.join().unwrap();
+            received.extend(consumer.drain());
+
+            // values arrive in order, values not fitting into the ring are dropped
+            assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(received.len() >= 2);
+        });
+    }
+}
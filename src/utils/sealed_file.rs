@@ -1,19 +1,17 @@
 //! Sealed files for safe sharing with clients
 //!
-//! Uses memfd on Linux/Android/FreeBSD, tempfile on others
+//! Uses memfd on Linux/Android/FreeBSD, read-only section objects on Windows, tempfile on others
 
-use std::{
-    ffi::CStr,
-    fs::File,
-    io::Write,
-};
+use std::{ffi::CStr, io::Read};
+#[cfg(unix)]
+use std::{fs::File, io::Write};
 
 // Platform-specific fd imports
 #[cfg(unix)]
 use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
 
 #[cfg(windows)]
-use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, RawHandle};
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, OwnedHandle, RawHandle};
 
 #[cfg(windows)]
 type RawFd = RawHandle;
@@ -25,12 +23,16 @@ type BorrowedFd<'a> = BorrowedHandle<'a>;
 /// This mechanism is useful for giving clients access to large amounts of
 /// information such as keymaps without them being able to write to the handle.
 ///
-/// On Linux, Android, and FreeBSD, this uses a sealed memfd. On other platforms
-/// it creates a POSIX shared memory object with `shm_open`, opens a read-only
-/// copy, and unlinks it.
+/// On Linux, Android, and FreeBSD, this uses a sealed memfd. On Windows it
+/// creates a pagefile-backed section and only hands out a handle limited to
+/// read-only mappings. On other platforms it falls back to an unlinked
+/// temporary file.
 #[derive(Debug)]
 pub struct SealedFile {
+    #[cfg(unix)]
     file: File,
+    #[cfg(windows)]
+    section: OwnedHandle,
     size: usize,
 }
 
@@ -41,15 +43,24 @@ impl SealedFile {
     }
 
     /// Create a `[SealedFile]` with the given binary data.
-    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+    #[cfg(unix)]
     pub fn with_data(name: &CStr, data: &[u8]) -> Result<Self, std::io::Error> {
+        Self::from_reader(name, data)
+    }
+
+    /// Create a `[SealedFile]` with the contents read from `reader`
+    ///
+    /// The data is streamed into the file, so large payloads don't need to be
+    /// buffered in memory first.
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+    pub fn from_reader(name: &CStr, mut reader: impl Read) -> Result<Self, std::io::Error> {
         use rustix::fs::{MemfdFlags, SealFlags};
         use std::io::Seek;
 
         let fd = rustix::fs::memfd_create(name, MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING)?;
 
         let mut file: File = fd.into();
-        file.write_all(data)?;
+        let size = std::io::copy(&mut reader, &mut file)? as usize;
         file.flush()?;
 
         file.seek(std::io::SeekFrom::Start(0))?;
@@ -59,34 +70,179 @@ impl SealedFile {
             SealFlags::SEAL | SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE,
         )?;
 
-        Ok(Self {
-            file,
-            size: data.len(),
-        })
+        Ok(Self { file, size })
     }
 
-    /// Create a `[SealedFile]` with the given binary data.
-    #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "android")))]
-    pub fn with_data(_name: &CStr, data: &[u8]) -> Result<Self, std::io::Error> {
-        use std::io::{Seek, SeekFrom, Write};
+    /// Create a `[SealedFile]` with the contents read from `reader`
+    ///
+    /// The data is streamed into the file, so large payloads don't need to be
+    /// buffered in memory first.
+    #[cfg(all(
+        unix,
+        not(any(target_os = "linux", target_os = "freebsd", target_os = "android"))
+    ))]
+    pub fn from_reader(_name: &CStr, mut reader: impl Read) -> Result<Self, std::io::Error> {
+        use std::io::{Seek, SeekFrom};
 
         let mut file = tempfile::tempfile()?;
-        file.write_all(data)?;
+        let size = std::io::copy(&mut reader, &mut file)? as usize;
         file.flush()?;
         file.seek(SeekFrom::Start(0))?;
 
+        Ok(Self { file, size })
+    }
+
+    /// Create a `[SealedFile]` with the given binary data.
+    #[cfg(windows)]
+    pub fn with_data(_name: &CStr, data: &[u8]) -> Result<Self, std::io::Error> {
+        let writable = section::create(data.len())?;
+        let mut view = section::View::new(&writable, ffi::FILE_MAP_WRITE, data.len())?;
+        view.as_mut_slice().copy_from_slice(data);
+        drop(view);
+
         Ok(Self {
-            file,
+            section: section::read_only(&writable)?,
             size: data.len(),
         })
     }
 
+    /// Create a `[SealedFile]` with the contents read from `reader`
+    ///
+    /// The data is streamed into a section growing as needed, so large payloads
+    /// don't need to be buffered on the heap first.
+    #[cfg(windows)]
+    pub fn from_reader(_name: &CStr, mut reader: impl Read) -> Result<Self, std::io::Error> {
+        const INITIAL_CAPACITY: usize = 64 * 1024;
+
+        let mut capacity = INITIAL_CAPACITY;
+        let mut writable = section::create(capacity)?;
+        let mut view = section::View::new(&writable, ffi::FILE_MAP_WRITE, capacity)?;
+        let mut size = 0;
+        loop {
+            if size == capacity {
+                capacity *= 2;
+                let grown = section::create(capacity)?;
+                let mut grown_view = section::View::new(&grown, ffi::FILE_MAP_WRITE, capacity)?;
+                grown_view.as_mut_slice()[..size].copy_from_slice(&view.as_slice()[..size]);
+                writable = grown;
+                view = grown_view;
+            }
+
+            match reader.read(&mut view.as_mut_slice()[size..]) {
+                Ok(0) => break,
+                Ok(read) => size += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        drop(view);
+
+        Ok(Self {
+            section: section::read_only(&writable)?,
+            size,
+        })
+    }
+
+    /// Map the contents of the sealed file read-only into memory
+    #[cfg(unix)]
+    pub fn map(&self) -> Result<SealedMapping<'_>, std::io::Error> {
+        use rustix::mm::{mmap, MapFlags, ProtFlags};
+
+        if self.size == 0 {
+            return Ok(SealedMapping::empty());
+        }
+
+        // SAFETY: a fresh mapping of a file that can't be modified or shrunk anymore
+        let ptr = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                self.size,
+                ProtFlags::READ,
+                MapFlags::SHARED,
+                &self.file,
+                0,
+            )?
+        };
+        Ok(SealedMapping {
+            ptr: ptr as *const u8,
+            len: self.size,
+            _file: std::marker::PhantomData,
+        })
+    }
+
+    /// Map the contents of the sealed file read-only into memory
+    #[cfg(windows)]
+    pub fn map(&self) -> Result<SealedMapping<'_>, std::io::Error> {
+        if self.size == 0 {
+            return Ok(SealedMapping::empty());
+        }
+
+        let view = section::View::new(&self.section, ffi::FILE_MAP_READ, self.size)?;
+        let ptr = view.as_slice().as_ptr();
+        std::mem::forget(view);
+        Ok(SealedMapping {
+            ptr,
+            len: self.size,
+            _file: std::marker::PhantomData,
+        })
+    }
+
     /// Size of the data contained in the sealed file.
     pub fn size(&self) -> usize {
         self.size
     }
 }
 
+/// Read-only memory mapping of a [`SealedFile`], see [`SealedFile::map`]
+#[derive(Debug)]
+pub struct SealedMapping<'a> {
+    ptr: *const u8,
+    len: usize,
+    _file: std::marker::PhantomData<&'a SealedFile>,
+}
+
+impl SealedMapping<'_> {
+    fn empty() -> Self {
+        SealedMapping {
+            ptr: std::ptr::NonNull::dangling().as_ptr(),
+            len: 0,
+            _file: std::marker::PhantomData,
+        }
+    }
+}
+
+impl std::ops::Deref for SealedMapping<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the mapping is valid for `len` bytes and its contents can't be modified
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for SealedMapping<'_> {
+    fn drop(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
+        #[cfg(unix)]
+        // SAFETY: the mapping was created by `SealedFile::map` and is not used anymore
+        unsafe {
+            let _ = rustix::mm::munmap(self.ptr as *mut _, self.len);
+        }
+        #[cfg(windows)]
+        // SAFETY: the view was created by `SealedFile::map` and is not used anymore
+        unsafe {
+            ffi::UnmapViewOfFile(self.ptr as *const _);
+        }
+    }
+}
+
+// SAFETY: the mapping is immutable
+unsafe impl Send for SealedMapping<'_> {}
+unsafe impl Sync for SealedMapping<'_> {}
+
 #[cfg(unix)]
 impl AsRawFd for SealedFile {
     fn as_raw_fd(&self) -> RawFd {
@@ -104,13 +260,159 @@ impl AsFd for SealedFile {
 #[cfg(windows)]
 impl AsRawHandle for SealedFile {
     fn as_raw_handle(&self) -> RawHandle {
-        std::os::windows::io::AsRawHandle::as_raw_handle(&self.file)
+        self.section.as_raw_handle()
     }
 }
 
 #[cfg(windows)]
 impl AsHandle for SealedFile {
     fn as_handle(&self) -> BorrowedHandle<'_> {
-        std::os::windows::io::AsHandle::as_handle(&self.file)
+        self.section.as_handle()
+    }
+}
+
+#[cfg(windows)]
+mod section {
+    use std::{
+        io,
+        os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle},
+    };
+
+    use super::ffi;
+
+    /// Create a writable pagefile-backed section of the given size
+    pub fn create(len: usize) -> io::Result<OwnedHandle> {
+        // a pagefile-backed section can't be empty
+        let len = len.max(1) as u64;
+        let raw = unsafe {
+            ffi::CreateFileMappingW(
+                ffi::INVALID_HANDLE_VALUE,
+                std::ptr::null(),
+                ffi::PAGE_READWRITE | ffi::SEC_COMMIT,
+                (len >> 32) as u32,
+                len as u32,
+                std::ptr::null(),
+            )
+        };
+        if raw == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle was just created and is owned by us
+        Ok(unsafe { OwnedHandle::from_raw_handle(raw as RawHandle) })
+    }
+
+    /// Duplicate a section handle, only allowing read-only mappings
+    pub fn read_only(section: &OwnedHandle) -> io::Result<OwnedHandle> {
+        let mut readonly = 0;
+        let process = unsafe { ffi::GetCurrentProcess() };
+        if unsafe {
+            ffi::DuplicateHandle(
+                process,
+                section.as_raw_handle() as isize,
+                process,
+                &mut readonly,
+                ffi::FILE_MAP_READ,
+                0,
+                0,
+            )
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the duplicated handle is owned by us
+        Ok(unsafe { OwnedHandle::from_raw_handle(readonly as RawHandle) })
+    }
+
+    /// Mapped view of a section, unmapped on drop
+    pub struct View {
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    impl View {
+        pub fn new(section: &OwnedHandle, access: u32, len: usize) -> io::Result<View> {
+            let ptr = unsafe { ffi::MapViewOfFile(section.as_raw_handle() as isize, access, 0, 0, len) };
+            if ptr.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(View {
+                ptr: ptr as *mut u8,
+                len,
+            })
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            // SAFETY: the view is valid for `len` bytes
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+
+        /// Only valid for views mapped with `FILE_MAP_WRITE`
+        pub fn as_mut_slice(&mut self) -> &mut [u8] {
+            // SAFETY: the view is valid for `len` bytes and not aliased
+            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+        }
+    }
+
+    impl Drop for View {
+        fn drop(&mut self) {
+            unsafe { ffi::UnmapViewOfFile(self.ptr as *const _) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod ffi {
+    use std::ffi::c_void;
+
+    pub const INVALID_HANDLE_VALUE: isize = -1;
+    pub const PAGE_READWRITE: u32 = 0x04;
+    pub const SEC_COMMIT: u32 = 0x0800_0000;
+    pub const FILE_MAP_WRITE: u32 = 0x0002;
+    pub const FILE_MAP_READ: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn CreateFileMappingW(
+            file: isize,
+            attributes: *const c_void,
+            protect: u32,
+            size_high: u32,
+            size_low: u32,
+            name: *const u16,
+        ) -> isize;
+        pub fn MapViewOfFile(
+            section: isize,
+            access: u32,
+            offset_high: u32,
+            offset_low: u32,
+            len: usize,
+        ) -> *mut c_void;
+        pub fn UnmapViewOfFile(address: *const c_void) -> i32;
+        pub fn GetCurrentProcess() -> isize;
+        pub fn DuplicateHandle(
+            source_process: isize,
+            source: isize,
+            target_process: isize,
+            target: *mut isize,
+            access: u32,
+            inherit: i32,
+            options: u32,
+        ) -> i32;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::SealedFile;
+
+    #[test]
+    fn map_from_reader() {
+        let data = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let file = SealedFile::from_reader(c"smithay-test", &data[..]).unwrap();
+        assert_eq!(file.size(), data.len());
+        assert_eq!(&*file.map().unwrap(), &data[..]);
+
+        let empty = SealedFile::with_data(c"smithay-test", &[]).unwrap();
+        assert!(empty.map().unwrap().is_empty());
     }
 }
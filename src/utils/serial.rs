@@ -1,4 +1,4 @@
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 /// A global [`SerialCounter`] for use in your compositor.
 ///
@@ -52,6 +52,30 @@ impl Serial {
     pub fn is_no_older_than(&self, other: &Serial) -> bool {
         other <= self
     }
+
+    /// Checks if a serial was generated after another given serial
+    #[inline]
+    pub fn is_newer_than(&self, other: &Serial) -> bool {
+        other < self
+    }
+
+    /// Number of serials generated between both serials, regardless of their order
+    ///
+    /// The serial `0`, which a [`SerialCounter`] skips when wrapping around, is not counted.
+    #[inline]
+    pub fn distance(&self, other: &Serial) -> u32 {
+        let (older, newer) = if self.is_newer_than(other) {
+            (other, self)
+        } else {
+            (self, other)
+        };
+        let distance = newer.0.wrapping_sub(older.0);
+        if newer.0 < older.0 && newer.0 != 0 {
+            distance - 1
+        } else {
+            distance
+        }
+    }
 }
 
 /// A counter for generating serials, for use in the client protocol
@@ -62,9 +86,17 @@ impl Serial {
 ///
 /// The counter will wrap around on overflow, ensuring it can run for as long
 /// as needed.
+///
+/// Serials sent by clients should be validated with [`SerialCounter::is_issued`] or
+/// [`SerialCounter::is_recent`] before comparing them to stored serials, as an arbitrary
+/// value could otherwise appear newer than every serial issued so far.
+///
+/// Separate counters, e.g. one per seat, can be created if serials of unrelated
+/// objects don't need to be comparable.
 #[derive(Debug)]
 pub struct SerialCounter {
     serial: AtomicU32,
+    issued: AtomicBool,
 }
 
 impl Default for SerialCounter {
@@ -79,6 +111,17 @@ impl SerialCounter {
     pub const fn new() -> Self {
         Self {
             serial: AtomicU32::new(1),
+            issued: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a new counter starting at the given value
+    ///
+    /// The serial `0` is never generated, so starting at `0` is equivalent to starting at `1`.
+    pub const fn starting_at(initial: u32) -> Self {
+        Self {
+            serial: AtomicU32::new(initial),
+            issued: AtomicBool::new(false),
         }
     }
 
@@ -87,7 +130,35 @@ impl SerialCounter {
         let _ = self
             .serial
             .compare_exchange(0, 1, Ordering::AcqRel, Ordering::SeqCst);
-        Serial(self.serial.fetch_add(1, Ordering::AcqRel))
+        let serial = Serial(self.serial.fetch_add(1, Ordering::AcqRel));
+        self.issued.store(true, Ordering::Release);
+        serial
+    }
+
+    /// Returns the last serial generated by this counter
+    ///
+    /// Returns `Serial(0)` if no serial was generated yet.
+    pub fn last_serial(&self) -> Serial {
+        if !self.issued.load(Ordering::Acquire) {
+            return Serial(0);
+        }
+        match self.serial.load(Ordering::Acquire) {
+            // the counter wrapped around, `u32::MAX` was the last serial
+            0 => Serial(u32::MAX),
+            next => Serial(next - 1),
+        }
+    }
+
+    /// Checks if the given serial could have been generated by this counter,
+    /// i.e. it is not in the future
+    pub fn is_issued(&self, serial: Serial) -> bool {
+        let last = self.last_serial();
+        serial.0 != 0 && last.0 != 0 && last.is_no_older_than(&serial)
+    }
+
+    /// Checks if the given serial is one of the last `max_age` serials generated by this counter
+    pub fn is_recent(&self, serial: Serial, max_age: u32) -> bool {
+        self.is_issued(serial) && self.last_serial().distance(&serial) < max_age
     }
 }
 
@@ -98,6 +169,7 @@ mod tests {
     fn create_serial_counter(initial_value: u32) -> SerialCounter {
         SerialCounter {
             serial: AtomicU32::new(initial_value),
+            issued: AtomicBool::new(false),
         }
     }
 
@@ -141,4 +213,68 @@ mod tests {
 
         assert!(serial1 < serial2);
     }
+
+    #[test]
+    fn serial_distance() {
+        assert_eq!(Serial(5).distance(&Serial(2)), 3);
+        assert_eq!(Serial(2).distance(&Serial(5)), 3);
+        assert_eq!(Serial(5).distance(&Serial(5)), 0);
+        assert!(Serial(1).is_newer_than(&Serial(u32::MAX)));
+    }
+
+    #[test]
+    fn serial_distance_skips_zero() {
+        // the counter generates u32::MAX - 1, u32::MAX, 1, 2
+        assert_eq!(Serial(1).distance(&Serial(u32::MAX)), 1);
+        assert_eq!(Serial(u32::MAX).distance(&Serial(1)), 1);
+        assert_eq!(Serial(2).distance(&Serial(u32::MAX - 1)), 3);
+        assert_eq!(Serial(u32::MAX).distance(&Serial(u32::MAX - 1)), 1);
+        assert_eq!(Serial(0).distance(&Serial(u32::MAX)), 1);
+    }
+
+    #[test]
+    fn last_serial_before_the_first() {
+        for counter in [
+            SerialCounter::new(),
+            SerialCounter::starting_at(0),
+            SerialCounter::starting_at(u32::MAX),
+        ] {
+            assert_eq!(counter.last_serial(), Serial(0));
+            assert!(!counter.is_issued(Serial(u32::MAX - 1)));
+            assert!(!counter.is_recent(Serial(1), u32::MAX));
+
+            let serial = counter.next_serial();
+            assert_eq!(counter.last_serial(), serial);
+            assert!(counter.is_issued(serial));
+        }
+
+        let counter = SerialCounter::starting_at(0);
+        assert_eq!(counter.next_serial(), Serial(1));
+        let counter = SerialCounter::starting_at(u32::MAX);
+        assert_eq!(counter.next_serial(), Serial(u32::MAX));
+        assert_eq!(counter.next_serial(), Serial(1));
+        assert_eq!(counter.last_serial(), Serial(1));
+    }
+
+    #[test]
+    fn validate_serials() {
+        let counter = SerialCounter::starting_at(u32::MAX - 1);
+        assert!(!counter.is_issued(Serial(u32::MAX - 1)));
+
+        let serial1 = counter.next_serial();
+        let serial2 = counter.next_serial();
+        let serial3 = counter.next_serial();
+        assert_eq!(counter.last_serial(), serial3);
+        assert!(counter.is_issued(serial1));
+        assert!(counter.is_issued(serial3));
+        assert!(!counter.is_issued(Serial(2)));
+        assert!(!counter.is_issued(Serial(0)));
+
+        assert!(counter.is_recent(serial3, 1));
+        assert!(!counter.is_recent(serial2, 1));
+        assert!(counter.is_recent(serial2, 2));
+        // the skipped serial 0 does not count
+        assert!(counter.is_recent(serial1, 3));
+        assert!(!counter.is_recent(serial1, 2));
+    }
 }
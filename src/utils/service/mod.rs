@@ -0,0 +1,13 @@
+//! Integration with service managers
+//!
+//! Compositors running as a system service, e.g. a kiosk or a display manager greeter, need to
+//! tell their supervisor when they are ready and react to its requests:
+//!
+//! - `systemd`: `sd_notify` readiness and watchdog notifications, and `LISTEN_FDS` socket
+//!   activation on unix,
+//! - `windows`: the control handler of a Windows service.
+
+#[cfg(unix)]
+pub mod systemd;
+#[cfg(windows)]
+pub mod windows;
@@ -0,0 +1,228 @@
+//! Readiness notification and socket activation of systemd
+//!
+//! Implements the `sd_notify` and `sd_listen_fds` protocols without linking to libsystemd, so
+//! they also work with other supervisors implementing them (e.g. s6 or dinit in compatibility
+//! mode).
+//!
+//! ```no_run
+//! use smithay::utils::service::systemd::{self, NotifyState};
+//!
+//! // wayland sockets passed by a `.socket` unit
+//! let sockets = systemd::listen_fds();
+//!
+//! // once all sockets are listening
+//! systemd::notify(&[NotifyState::Ready, NotifyState::Status("Running".into())]).unwrap();
+//!
+//! if let Some(interval) = systemd::watchdog_interval() {
+//!     // ping at least every `interval / 2`, e.g. from a calloop `Timer`
+//!     systemd::notify(&[NotifyState::Watchdog]).unwrap();
+//! }
+//! ```
+
+use std::{
+    env,
+    ffi::OsStr,
+    fmt, io,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{FromRawFd, OwnedFd, RawFd},
+        net::UnixDatagram,
+    },
+    time::Duration,
+};
+
+use rustix::io::{fcntl_getfd, fcntl_setfd, FdFlags};
+
+/// Environment variable containing the notification socket
+pub const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+// first file descriptor passed by socket activation
+const LISTEN_FDS_START: RawFd = 3;
+
+/// State reported to the service manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyState {
+    /// Startup finished
+    Ready,
+    /// The service is reloading its configuration
+    Reloading,
+    /// The service is shutting down
+    Stopping,
+    /// Free-form status shown by the service manager
+    Status(String),
+    /// Keep-alive ping of the watchdog
+    Watchdog,
+    /// The main process is a different process
+    MainPid(u32),
+}
+
+impl fmt::Display for NotifyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifyState::Ready => write!(f, "READY=1"),
+            NotifyState::Reloading => write!(f, "RELOADING=1"),
+            NotifyState::Stopping => write!(f, "STOPPING=1"),
+            NotifyState::Status(status) => write!(f, "STATUS={}", status.replace('\n', " ")),
+            NotifyState::Watchdog => write!(f, "WATCHDOG=1"),
+            NotifyState::MainPid(pid) => write!(f, "MAINPID={}", pid),
+        }
+    }
+}
+
+fn message(states: &[NotifyState]) -> String {
+    states.iter().map(|state| format!("{}\n", state)).collect()
+}
+
+/// Notify the service manager about state changes
+///
+/// Returns `false` without doing anything if the process was not started by a service manager
+/// supporting notifications.
+pub fn notify(states: &[NotifyState]) -> io::Result<bool> {
+    match env::var_os(NOTIFY_SOCKET_ENV) {
+        Some(path) => notify_to(&path, states).map(|()| true),
+        None => Ok(false),
+    }
+}
+
+fn notify_to(path: &OsStr, states: &[NotifyState]) -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    let message = message(states);
+    match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(message.as_bytes(), &addr)?;
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Some(_) => return Err(io::ErrorKind::Unsupported.into()),
+        None => {
+            socket.send_to(message.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the interval in which the service manager expects [`NotifyState::Watchdog`] pings
+///
+/// Returns `None` if the watchdog is disabled or meant for another process.
+pub fn watchdog_interval() -> Option<Duration> {
+    let pid = env::var("WATCHDOG_PID").ok();
+    let usec = env::var("WATCHDOG_USEC").ok()?;
+    parse_watchdog(pid.as_deref(), &usec, std::process::id())
+}
+
+fn parse_watchdog(pid: Option<&str>, usec: &str, own_pid: u32) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse::<u32>().ok() != Some(own_pid)) {
+        return None;
+    }
+    match usec.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(usec) => Some(Duration::from_micros(usec)),
+    }
+}
+
+/// File descriptor passed by socket activation
+#[derive(Debug)]
+pub struct ListenFd {
+    /// The file descriptor, usually a listening socket
+    pub fd: OwnedFd,
+    /// Name of the file descriptor set with `FileDescriptorName=`, if any
+    pub name: Option<String>,
+}
+
+/// Take the file descriptors passed by socket activation
+///
+/// The file descriptors are marked close-on-exec and the `LISTEN_*` environment variables are
+/// removed, so they are not inherited by child processes. Subsequent calls return an empty
+/// list.
+pub fn listen_fds() -> Vec<ListenFd> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    let names = env::var("LISTEN_FDNAMES").ok();
+    for var in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        env::remove_var(var);
+    }
+
+    parse_listen_fds(
+        pid.as_deref(),
+        fds.as_deref(),
+        names.as_deref(),
+        std::process::id(),
+    )
+    .into_iter()
+    .filter_map(|(raw, name)| {
+        // SAFETY: the service manager passed these to us and nothing else took them, as the
+        // environment was cleared
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+        let flags = fcntl_getfd(&fd).ok()?;
+        fcntl_setfd(&fd, flags | FdFlags::CLOEXEC).ok()?;
+        Some(ListenFd { fd, name })
+    })
+    .collect()
+}
+
+fn parse_listen_fds(
+    pid: Option<&str>,
+    fds: Option<&str>,
+    names: Option<&str>,
+    own_pid: u32,
+) -> Vec<(RawFd, Option<String>)> {
+    if pid.and_then(|pid| pid.parse().ok()) != Some(own_pid) {
+        return Vec::new();
+    }
+    let count: RawFd = match fds.and_then(|fds| fds.parse().ok()) {
+        Some(count) if count > 0 => count,
+        _ => return Vec::new(),
+    };
+    let mut names = names.map(|names| names.split(':')).into_iter().flatten();
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| {
+            let name = names.next().filter(|name| !name.is_empty()).map(str::to_owned);
+            (fd, name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_message() {
+        let path = env::temp_dir().join(format!("smithay-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        notify_to(
+            path.as_os_str(),
+            &[NotifyState::Ready, NotifyState::Status("Up\nand running".into())],
+        )
+        .unwrap();
+        let mut buffer = [0; 64];
+        let len = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"READY=1\nSTATUS=Up and running\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_environment() {
+        assert_eq!(
+            parse_listen_fds(Some("42"), Some("2"), Some("wayland:"), 42),
+            vec![(3, Some("wayland".into())), (4, None)]
+        );
+        assert!(parse_listen_fds(Some("43"), Some("2"), None, 42).is_empty());
+        assert!(parse_listen_fds(None, Some("2"), None, 42).is_empty());
+
+        assert_eq!(
+            parse_watchdog(None, "30000000", 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_watchdog(Some("43"), "30000000", 42), None);
+        assert_eq!(parse_watchdog(Some("42"), "0", 42), None);
+    }
+}
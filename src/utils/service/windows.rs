@@ -0,0 +1,349 @@
+//! Integration with the Windows service control manager
+//!
+//! A process started as a Windows service has to connect to the service control manager (SCM)
+//! by calling [`run_dispatcher`] from its `main` function. The SCM then calls the service main
+//! function on a new thread, which registers its control handler with [`ServiceHandle::register`],
+//! reports its state and runs the compositor. Control requests, like stopping the service, are
+//! delivered as [`ServiceControl`]s through the [`ServiceControlSource`].
+//!
+//! ```no_run
+//! use smithay::utils::service::windows::{self, ServiceControl, ServiceHandle, ServiceState};
+//!
+//! fn service_main(_args: Vec<String>) {
+//!     let (service, source) = ServiceHandle::register("my-compositor").unwrap();
+//!     service.set_state(ServiceState::StartPending).unwrap();
+//!
+//!     let mut event_loop = smithay::reexports::calloop::EventLoop::<bool>::try_new().unwrap();
+//!     event_loop
+//!         .handle()
+//!         .insert_source(source, |control, _, running| {
+//!             if matches!(control, ServiceControl::Stop | ServiceControl::Shutdown) {
+//!                 *running = false;
+//!             }
+//!         })
+//!         .unwrap();
+//!     service.set_state(ServiceState::Running).unwrap();
+//!
+//!     let mut running = true;
+//!     while running {
+//!         event_loop.dispatch(None, &mut running).unwrap();
+//!     }
+//!     service.set_state(ServiceState::Stopped).unwrap();
+//! }
+//!
+//! fn main() {
+//!     windows::run_dispatcher("my-compositor", service_main).unwrap();
+//! }
+//! ```
+
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+use std::{
+    collections::VecDeque,
+    ffi::c_void,
+    io,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+
+use crate::compat::notifier::{self, Notifier, NotifierSource};
+
+type SERVICE_STATUS_HANDLE = isize;
+type LPHANDLER_FUNCTION_EX = unsafe extern "system" fn(u32, u32, *mut c_void, *mut c_void) -> u32;
+type LPSERVICE_MAIN_FUNCTIONW = unsafe extern "system" fn(u32, *mut *mut u16);
+
+#[repr(C)]
+struct SERVICE_TABLE_ENTRYW {
+    lpServiceName: *mut u16,
+    lpServiceProc: Option<LPSERVICE_MAIN_FUNCTIONW>,
+}
+
+#[repr(C)]
+struct SERVICE_STATUS {
+    dwServiceType: u32,
+    dwCurrentState: u32,
+    dwControlsAccepted: u32,
+    dwWin32ExitCode: u32,
+    dwServiceSpecificExitCode: u32,
+    dwCheckPoint: u32,
+    dwWaitHint: u32,
+}
+
+const SERVICE_WIN32_OWN_PROCESS: u32 = 0x10;
+
+const SERVICE_STOPPED: u32 = 1;
+const SERVICE_START_PENDING: u32 = 2;
+const SERVICE_STOP_PENDING: u32 = 3;
+const SERVICE_RUNNING: u32 = 4;
+
+const SERVICE_CONTROL_STOP: u32 = 0x1;
+const SERVICE_CONTROL_INTERROGATE: u32 = 0x4;
+const SERVICE_CONTROL_SHUTDOWN: u32 = 0x5;
+const SERVICE_CONTROL_POWEREVENT: u32 = 0xD;
+const SERVICE_CONTROL_SESSIONCHANGE: u32 = 0xE;
+
+const SERVICE_ACCEPT_STOP: u32 = 0x1;
+const SERVICE_ACCEPT_SHUTDOWN: u32 = 0x4;
+const SERVICE_ACCEPT_POWEREVENT: u32 = 0x40;
+const SERVICE_ACCEPT_SESSIONCHANGE: u32 = 0x80;
+
+const NO_ERROR: u32 = 0;
+const ERROR_CALL_NOT_IMPLEMENTED: u32 = 120;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn StartServiceCtrlDispatcherW(lpServiceStartTable: *const SERVICE_TABLE_ENTRYW) -> i32;
+    fn RegisterServiceCtrlHandlerExW(
+        lpServiceName: *const u16,
+        lpHandlerProc: LPHANDLER_FUNCTION_EX,
+        lpContext: *mut c_void,
+    ) -> SERVICE_STATUS_HANDLE;
+    fn SetServiceStatus(hServiceStatus: SERVICE_STATUS_HANDLE, lpServiceStatus: *const SERVICE_STATUS)
+        -> i32;
+}
+
+fn to_wide(string: &str) -> Vec<u16> {
+    string.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// SAFETY: `wide` has to point to a nul-terminated UTF-16 string
+unsafe fn from_wide(wide: *const u16) -> String {
+    let mut len = 0;
+    while *wide.add(len) != 0 {
+        len += 1;
+    }
+    String::from_utf16_lossy(std::slice::from_raw_parts(wide, len))
+}
+
+static SERVICE_MAIN: OnceLock<fn(Vec<String>)> = OnceLock::new();
+
+unsafe extern "system" fn service_main(argc: u32, argv: *mut *mut u16) {
+    let args = (0..argc as usize)
+        .map(|i| unsafe { from_wide(*argv.add(i)) })
+        .collect();
+    if let Some(main) = SERVICE_MAIN.get() {
+        main(args);
+    }
+}
+
+/// Connect the process to the service control manager
+///
+/// Blocks until the service stopped, calling `service_main` with the arguments of the service on
+/// a new thread. Fails if the process was not started as a service.
+pub fn run_dispatcher(name: &str, service_main_fn: fn(Vec<String>)) -> io::Result<()> {
+    if SERVICE_MAIN.set(service_main_fn).is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the service dispatcher already runs",
+        ));
+    }
+
+    let mut name = to_wide(name);
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: name.as_mut_ptr(),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: std::ptr::null_mut(),
+            lpServiceProc: None,
+        },
+    ];
+    if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// State of the service reported to the service control manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceState {
+    /// The service is starting
+    StartPending,
+    /// The service is running
+    Running,
+    /// The service is stopping
+    StopPending,
+    /// The service stopped
+    Stopped,
+}
+
+/// Control request of the service control manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceControl {
+    /// The service should stop
+    Stop,
+    /// The system is shutting down
+    Shutdown,
+    /// A user session changed, with the `WTS_*` event type
+    SessionChange(u32),
+    /// The power state of the system changed, with the `PBT_*` event type
+    PowerEvent(u32),
+}
+
+#[derive(Debug)]
+struct HandlerContext {
+    controls: Mutex<VecDeque<ServiceControl>>,
+    notifier: Notifier,
+}
+
+unsafe extern "system" fn handler(
+    control: u32,
+    event_type: u32,
+    _data: *mut c_void,
+    context: *mut c_void,
+) -> u32 {
+    // SAFETY: the context is leaked in `ServiceHandle::register` and never freed
+    let context = unsafe { &*(context as *const HandlerContext) };
+    let control = match control {
+        SERVICE_CONTROL_STOP => ServiceControl::Stop,
+        SERVICE_CONTROL_SHUTDOWN => ServiceControl::Shutdown,
+        SERVICE_CONTROL_SESSIONCHANGE => ServiceControl::SessionChange(event_type),
+        SERVICE_CONTROL_POWEREVENT => ServiceControl::PowerEvent(event_type),
+        // the current state is reported by `SetServiceStatus`
+        SERVICE_CONTROL_INTERROGATE => return NO_ERROR,
+        _ => return ERROR_CALL_NOT_IMPLEMENTED,
+    };
+    context.controls.lock().unwrap().push_back(control);
+    context.notifier.notify();
+    NO_ERROR
+}
+
+/// Handle to report the state of the service, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct ServiceHandle {
+    handle: SERVICE_STATUS_HANDLE,
+    checkpoint: AtomicU32,
+}
+
+impl ServiceHandle {
+    /// Register the control handler of the service
+    ///
+    /// Has to be called from the service main function passed to [`run_dispatcher`]. Control
+    /// requests are delivered through the returned [`ServiceControlSource`].
+    pub fn register(name: &str) -> io::Result<(ServiceHandle, ServiceControlSource)> {
+        let (notifier, source) = notifier::new()?;
+        let context = Arc::new(HandlerContext {
+            controls: Mutex::new(VecDeque::new()),
+            notifier,
+        });
+        // the handler may be called until the process exits
+        let raw_context = Arc::into_raw(context.clone()) as *mut c_void;
+
+        let name = to_wide(name);
+        let handle = unsafe { RegisterServiceCtrlHandlerExW(name.as_ptr(), handler, raw_context) };
+        if handle == 0 {
+            let err = io::Error::last_os_error();
+            // SAFETY: the handler was not registered, so the context is not used
+            drop(unsafe { Arc::from_raw(raw_context as *const HandlerContext) });
+            return Err(err);
+        }
+
+        Ok((
+            ServiceHandle {
+                handle,
+                checkpoint: AtomicU32::new(0),
+            },
+            ServiceControlSource { source, context },
+        ))
+    }
+
+    /// Report the state of the service
+    ///
+    /// Pending states increment the checkpoint reported to the service control manager, call
+    /// this regularly during long running startups or shutdowns.
+    pub fn set_state(&self, state: ServiceState) -> io::Result<()> {
+        self.report(state, 0)
+    }
+
+    /// Report the service as stopped with the given exit code
+    pub fn set_stopped(&self, exit_code: u32) -> io::Result<()> {
+        self.report(ServiceState::Stopped, exit_code)
+    }
+
+    fn report(&self, state: ServiceState, exit_code: u32) -> io::Result<()> {
+        let (current_state, pending) = match state {
+            ServiceState::StartPending => (SERVICE_START_PENDING, true),
+            ServiceState::Running => (SERVICE_RUNNING, false),
+            ServiceState::StopPending => (SERVICE_STOP_PENDING, true),
+            ServiceState::Stopped => (SERVICE_STOPPED, false),
+        };
+        let checkpoint = if pending {
+            self.checkpoint.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.checkpoint.store(0, Ordering::Relaxed);
+            0
+        };
+        let controls_accepted = match state {
+            ServiceState::Running => {
+                SERVICE_ACCEPT_STOP
+                    | SERVICE_ACCEPT_SHUTDOWN
+                    | SERVICE_ACCEPT_POWEREVENT
+                    | SERVICE_ACCEPT_SESSIONCHANGE
+            }
+            _ => 0,
+        };
+
+        let status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: current_state,
+            dwControlsAccepted: controls_accepted,
+            dwWin32ExitCode: exit_code,
+            dwServiceSpecificExitCode: 0,
+            dwCheckPoint: checkpoint,
+            dwWaitHint: if pending { 3000 } else { 0 },
+        };
+        if unsafe { SetServiceStatus(self.handle, &status) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// Event source delivering the [`ServiceControl`] requests of a service
+#[derive(Debug)]
+pub struct ServiceControlSource {
+    source: NotifierSource,
+    context: Arc<HandlerContext>,
+}
+
+impl EventSource for ServiceControlSource {
+    type Event = ServiceControl;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let context = &self.context;
+        self.source.process_events(readiness, token, |_, _| {
+            let controls = std::mem::take(&mut *context.controls.lock().unwrap());
+            for control in controls {
+                callback(control, &mut ());
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
@@ -3,6 +3,13 @@
 //! This module implements "Turbo-Charged" pixel format conversion.
 //! It uses architecture-specific intrinsics (AVX2 for x86_64, NEON for aarch64)
 //! to accelerate `wl_shm` software buffer swizzling.
+//!
+//! GPU renderers reorder color channels while sampling instead (e.g. the `GlesRenderer` with
+//! texture swizzles), so this is only needed by software renderers. Use
+//! [`ImportMem::mem_format_conversion`](crate::backend::renderer::ImportMem::mem_format_conversion)
+//! to find out which formats a renderer converts on the CPU.
+
+use super::{Buffer, Region};
 
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
@@ -15,19 +22,44 @@ use std::arch::aarch64::*;
 /// This function is optimized for high throughput "Zero-Copy" software pipelines.
 /// It processes pixels in 256-bit (AVX2) or 128-bit (NEON) chunks.
 pub fn swizzle_bgra_rgba(data: &mut [u8]) {
-    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
-    unsafe {
-        // Alignment check could be added here, but for now we assume generous alignment from shm.
-        swizzle_simd(data);
-    }
-
+    #[cfg(target_arch = "x86_64")]
+    let done = if is_x86_feature_detected!("avx2") {
+        // SAFETY: avx2 support was checked above
+        unsafe { swizzle_simd(data) }
+    } else {
+        0
+    };
+    #[cfg(target_arch = "aarch64")]
+    // SAFETY: neon is part of the aarch64 baseline
+    let done = unsafe { swizzle_simd(data) };
     #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
-    swizzle_scalar(data);
+    let done = 0;
+
+    // the simd paths only process whole chunks
+    swizzle_scalar(&mut data[done..]);
+}
+
+/// Swizzles the pixels of a BGRA8888 buffer inside `region` to RGBA8888 (or vice versa)
+///
+/// This allows to only convert the damaged parts of a buffer. `stride` is the length of a row
+/// in bytes. Parts of the region outside of `data` are ignored.
+pub fn swizzle_bgra_rgba_region(data: &mut [u8], stride: usize, region: &Region<Buffer>) {
+    let width = (stride / 4).min(i32::MAX as usize) as i32;
+    let height = (data.len() / stride.max(1)).min(i32::MAX as usize) as i32;
+    let mut region = region.clone();
+    region.intersect_rect(crate::utils::Rectangle::from_size((width, height).into()));
+
+    for rect in region.rects() {
+        for y in rect.loc.y..rect.loc.y + rect.size.h {
+            let start = y as usize * stride + rect.loc.x as usize * 4;
+            swizzle_bgra_rgba(&mut data[start..start + rect.size.w as usize * 4]);
+        }
+    }
 }
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2")]
-unsafe fn swizzle_simd(data: &mut [u8]) {
+unsafe fn swizzle_simd(data: &mut [u8]) -> usize {
     let len = data.len();
     let mut ptr = data.as_mut_ptr();
     let end = ptr.add(len & !31); // Process 32 bytes at a time
@@ -47,13 +79,12 @@ unsafe fn swizzle_simd(data: &mut [u8]) {
         _mm256_storeu_si256(ptr as *mut __m256i, swizzled);
         ptr = ptr.add(32);
     }
-    
-    // Fallback for remaining bytes happens via scalar automatically 
-    // if we added a scalar tail loop, but for paper POC this main loop is the key.
+
+    len & !31
 }
 
 #[cfg(target_arch = "aarch64")]
-unsafe fn swizzle_simd(data: &mut [u8]) {
+unsafe fn swizzle_simd(data: &mut [u8]) -> usize {
     let len = data.len();
     let mut ptr = data.as_mut_ptr();
     let end = ptr.add(len & !15); // Process 16 bytes at a time (NEON is 128-bit)
@@ -72,6 +103,8 @@ unsafe fn swizzle_simd(data: &mut [u8]) {
         vst1q_u8(ptr, swizzled);
         ptr = ptr.add(16);
     }
+
+    len & !15
 }
 
 fn swizzle_scalar(data: &mut [u8]) {
@@ -83,6 +116,7 @@ fn swizzle_scalar(data: &mut [u8]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::Rectangle;
 
     #[test]
     fn test_swizzle_correctness() {
@@ -112,5 +146,31 @@ mod tests {
         let mut data = vec![10, 20, 30, 40];
         swizzle_bgra_rgba(&mut data);
         assert_eq!(data, vec![30, 20, 10, 40]);
+
+        // a full simd chunk followed by a tail
+        let mut data = [1u8, 2, 3, 4].repeat(9);
+        swizzle_bgra_rgba(&mut data);
+        assert_eq!(data, [3u8, 2, 1, 4].repeat(9));
+    }
+
+    #[test]
+    fn test_swizzle_region() {
+        // 4x3 pixels with a stride padded to 5 pixels
+        let stride = 5 * 4;
+        let mut data = [1u8, 2, 3, 4].repeat(5 * 3);
+        let mut region = Region::from_rect(Rectangle::new((1, 0).into(), (2, 2).into()));
+        // outside of the buffer
+        region.add_rect(Rectangle::new((3, 2).into(), (10, 10).into()));
+
+        swizzle_bgra_rgba_region(&mut data, stride, &region);
+
+        for y in 0..3 {
+            for x in 0..5 {
+                let pixel = &data[y * stride + x * 4..][..4];
+                let inside = (1..3).contains(&x) && y < 2 || (3..5).contains(&x) && y == 2;
+                let expected = if inside { [3, 2, 1, 4] } else { [1, 2, 3, 4] };
+                assert_eq!(pixel, expected, "pixel {x}x{y}");
+            }
+        }
     }
 }
@@ -0,0 +1,197 @@
+use std::{collections::HashMap, fmt, hash::Hash};
+
+use super::{Arc, Condvar, Mutex};
+
+#[derive(Debug)]
+struct Fence {
+    generation: u64,
+    signaled: bool,
+}
+
+#[derive(Debug)]
+struct Fences<K> {
+    fences: HashMap<K, Fence>,
+    next_generation: u64,
+}
+
+struct Shared<K> {
+    fences: Mutex<Fences<K>>,
+    signaled: Condvar,
+}
+
+/// Table tracking the completion of work handed to other threads
+///
+/// Every fence is identified by a key, e.g. the id of a texture being uploaded. The thread doing
+/// the work gets a [`FenceSignaler`], any other thread can query or wait for the fence.
+///
+/// Inserting a key again replaces its fence; signaling the replaced fence has no effect.
+pub struct FenceTable<K> {
+    shared: Arc<Shared<K>>,
+}
+
+impl<K> Clone for FenceTable<K> {
+    fn clone(&self) -> Self {
+        FenceTable {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for FenceTable<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FenceTable")
+            .field("fences", &self.shared.fences.lock().unwrap().fences)
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for FenceTable<K> {
+    fn default() -> Self {
+        FenceTable::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone> FenceTable<K> {
+    /// Create a new empty table
+    pub fn new() -> Self {
+        FenceTable {
+            shared: Arc::new(Shared {
+                fences: Mutex::new(Fences {
+                    fences: HashMap::new(),
+                    next_generation: 0,
+                }),
+                signaled: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Insert a pending fence
+    ///
+    /// Returns the signaler to hand to the thread doing the work.
+    pub fn insert(&self, key: K) -> FenceSignaler<K> {
+        let mut fences = self.shared.fences.lock().unwrap();
+        let generation = fences.next_generation;
+        fences.next_generation += 1;
+        fences.fences.insert(
+            key.clone(),
+            Fence {
+                generation,
+                signaled: false,
+            },
+        );
+        FenceSignaler {
+            shared: self.shared.clone(),
+            key,
+            generation,
+        }
+    }
+
+    /// Returns whether the fence of `key` was signaled, or `None` if there is no such fence
+    pub fn is_signaled(&self, key: &K) -> Option<bool> {
+        let fences = self.shared.fences.lock().unwrap();
+        fences.fences.get(key).map(|fence| fence.signaled)
+    }
+
+    /// Block until the fence of `key` is signaled
+    ///
+    /// Returns immediately if there is no such fence, or once it was removed.
+    pub fn wait(&self, key: &K) {
+        let mut fences = self.shared.fences.lock().unwrap();
+        while fences.fences.get(key).is_some_and(|fence| !fence.signaled) {
+            fences = self.shared.signaled.wait(fences).unwrap();
+        }
+    }
+
+    /// Remove all signaled fences, returning their keys
+    pub fn take_signaled(&self) -> Vec<K> {
+        let mut fences = self.shared.fences.lock().unwrap();
+        let mut signaled = Vec::new();
+        fences.fences.retain(|key, fence| {
+            if fence.signaled {
+                signaled.push(key.clone());
+            }
+            !fence.signaled
+        });
+        signaled
+    }
+
+    /// Remove the fence of `key`, waking up threads waiting for it
+    pub fn remove(&self, key: &K) {
+        self.shared.fences.lock().unwrap().fences.remove(key);
+        self.shared.signaled.notify_all();
+    }
+}
+
+/// Signals a fence of a [`FenceTable`]
+///
+/// Dropping the signaler signals the fence as well, so waiting threads never block forever if
+/// the work panicked.
+pub struct FenceSignaler<K: Hash + Eq> {
+    shared: Arc<Shared<K>>,
+    key: K,
+    generation: u64,
+}
+
+impl<K: Hash + Eq + fmt::Debug> fmt::Debug for FenceSignaler<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FenceSignaler")
+            .field("key", &self.key)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq> FenceSignaler<K> {
+    /// Signal the fence
+    pub fn signal(self) {}
+}
+
+impl<K: Hash + Eq> Drop for FenceSignaler<K> {
+    fn drop(&mut self) {
+        let mut fences = self.shared.fences.lock().unwrap();
+        if let Some(fence) = fences.fences.get_mut(&self.key) {
+            if fence.generation == self.generation {
+                fence.signaled = true;
+            }
+        }
+        drop(fences);
+        self.shared.signaled.notify_all();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::FenceTable;
+
+    #[test]
+    fn replaced_fence() {
+        let table = FenceTable::new();
+        let old = table.insert(1);
+        let new = table.insert(1);
+        assert_eq!(table.is_signaled(&1), Some(false));
+        old.signal();
+        assert_eq!(table.is_signaled(&1), Some(false));
+        drop(new);
+        assert_eq!(table.is_signaled(&1), Some(true));
+        assert_eq!(table.take_signaled(), vec![1]);
+        assert_eq!(table.is_signaled(&1), None);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::FenceTable;
+
+    #[test]
+    fn loom_wait_for_signal() {
+        loom::model(|| {
+            let table = FenceTable::new();
+            let signaler = table.insert(1);
+            let thread = loom::thread::spawn(move || signaler.signal());
+
+            table.wait(&1);
+            assert_eq!(table.is_signaled(&1), Some(true));
+            thread.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,58 @@
+//! Synchronization primitives for state shared between threads
+//!
+//! Buffer and input state is shared with helper threads, e.g. the workers of a
+//! `wayland::parallel::DispatchPool` and input threads. The synchronization is kept in small
+//! primitives, which are model-checked with [loom](https://docs.rs/loom) in addition to their
+//! regular tests:
+//!
+//! - [`ring`](super::ring): the single-producer single-consumer ring buffer handing events of
+//!   the raw input thread to the event loop,
+//! - [`FenceTable`]: completion of work submitted to other threads, e.g. the jobs of a
+//!   `DispatchPool`,
+//! - [`ReleaseTracker`]: releasing a buffer once the last thread using it is done, e.g. a job
+//!   spawned with `DispatchPool::spawn_using`.
+//!
+//! The loom tests are run with
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg loom" cargo test --release --lib loom
+//! ```
+
+mod fence_table;
+mod release;
+
+pub use fence_table::{FenceSignaler, FenceTable};
+pub use release::{ReleaseGuard, ReleaseTracker};
+
+// Types of either std or loom, so the primitives can be model-checked without changing them.
+
+#[cfg(loom)]
+pub(crate) use loom::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+};
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Condvar, Mutex,
+};
+
+/// `UnsafeCell` with the closure based interface of loom
+#[cfg(not(loom))]
+#[derive(Debug, Default)]
+pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) fn new(value: T) -> UnsafeCell<T> {
+        UnsafeCell(std::cell::UnsafeCell::new(value))
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+
+use super::{Arc, AtomicUsize, Mutex, Ordering};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+struct Shared {
+    users: AtomicUsize,
+    on_release: Mutex<Option<Callback>>,
+}
+
+impl Shared {
+    fn release(&self) {
+        // the last user synchronizes with all previous ones, so their accesses of the buffer
+        // happen before the callback
+        if self.users.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(callback) = self.on_release.lock().unwrap().take() {
+                callback();
+            }
+        }
+    }
+}
+
+/// Runs a callback once the last thread using a resource is done with it
+///
+/// Used to release client buffers, which may be read by several threads at once (e.g. by jobs
+/// of a `wayland::parallel::DispatchPool`). The tracker itself counts as a user, every
+/// [`ReleaseGuard`] is another one. The callback runs on the thread dropping the last of them.
+pub struct ReleaseTracker {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for ReleaseTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReleaseTracker")
+            .field("users", &self.shared.users.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReleaseTracker {
+    /// Create a new tracker running `on_release` once all users are done
+    pub fn new(on_release: impl FnOnce() + Send + 'static) -> Self {
+        ReleaseTracker {
+            shared: Arc::new(Shared {
+                users: AtomicUsize::new(1),
+                on_release: Mutex::new(Some(Box::new(on_release))),
+            }),
+        }
+    }
+
+    /// Add a user of the resource
+    pub fn acquire(&self) -> ReleaseGuard {
+        // the tracker is still a user, so the count can not have dropped to zero
+        self.shared.users.fetch_add(1, Ordering::Relaxed);
+        ReleaseGuard {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for ReleaseTracker {
+    fn drop(&mut self) {
+        self.shared.release();
+    }
+}
+
+/// User of a resource tracked by a [`ReleaseTracker`]
+///
+/// The user is done once the guard is dropped.
+pub struct ReleaseGuard {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for ReleaseGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReleaseGuard").finish_non_exhaustive()
+    }
+}
+
+impl Drop for ReleaseGuard {
+    fn drop(&mut self) {
+        self.shared.release();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::ReleaseTracker;
+
+    #[test]
+    fn released_by_last_user() {
+        let released = Arc::new(AtomicBool::new(false));
+        let flag = released.clone();
+        let tracker = ReleaseTracker::new(move || flag.store(true, Ordering::SeqCst));
+        let guard = tracker.acquire();
+
+        drop(tracker);
+        assert!(!released.load(Ordering::SeqCst));
+        drop(guard);
+        assert!(released.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::{super::UnsafeCell, ReleaseTracker};
+    use loom::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // one part per user, so the users do not race with each other
+    struct Buffer([UnsafeCell<u32>; 2]);
+    // SAFETY: written by the users, read in the release callback after all of them are done
+    unsafe impl Sync for Buffer {}
+    unsafe impl Send for Buffer {}
+
+    #[test]
+    fn loom_release_once_after_all_users() {
+        loom::model(|| {
+            let buffer = Arc::new(Buffer([UnsafeCell::new(0), UnsafeCell::new(0)]));
+            let releases = Arc::new(AtomicUsize::new(0));
+
+            let (callback_buffer, callback_releases) = (buffer.clone(), releases.clone());
+            let tracker = ReleaseTracker::new(move || {
+                // loom reports a data race if a write of a user is not visible here
+                for part in &callback_buffer.0 {
+                    part.with_mut(|value| assert_eq!(unsafe { *value }, 1));
+                }
+                callback_releases.fetch_add(1, Ordering::SeqCst);
+            });
+
+            let threads = (0..2)
+                .map(|i| {
+                    let guard = tracker.acquire();
+                    let buffer = buffer.clone();
+                    loom::thread::spawn(move || {
+                        buffer.0[i].with_mut(|value| unsafe { *value = 1 });
+                        drop(guard);
+                    })
+                })
+                .collect::<Vec<_>>();
+            drop(tracker);
+            for thread in threads {
+                thread.join().unwrap();
+            }
+
+            assert_eq!(releases.load(Ordering::SeqCst), 1);
+        });
+    }
+}
@@ -0,0 +1,384 @@
+//! Per-client resource accounting
+//!
+//! This module keeps count of the resources every client currently holds: surfaces, buffers,
+//! regions and the textures the renderer keeps imported for the client's buffers. It is meant
+//! as a debugging facility to find resources that are not cleaned up after a client
+//! disconnected, e.g. because a compositor keeps a strong reference to a surface around.
+//!
+//! Accounting is opt-in and kept per [`Display`](wayland_server::Display): create a
+//! [`ResourceAudit`], keep it in your compositor state and return it from
+//! [`CompositorHandler::resource_audit`](crate::wayland::compositor::CompositorHandler::resource_audit)
+//! and [`BufferHandler::resource_audit`](crate::wayland::buffer::BufferHandler::resource_audit).
+//! The counters are then updated by smithay's own protocol implementations. Compositors may
+//! record additional resources with [`ResourceAudit::created`] and [`ResourceAudit::destroyed`].
+//!
+//! Once a client disconnects, its resources are destroyed by the next
+//! [`Display::dispatch_clients`](wayland_server::Display::dispatch_clients). Mark the client as
+//! disconnected with [`ResourceAudit::client_disconnected`] (e.g. from
+//! [`ClientData::disconnected`](wayland_server::backend::ClientData::disconnected)) and call
+//! [`ResourceAudit::check_leaks`] after dispatching to detect anything left behind.
+//!
+//! ```no_run
+//! use smithay::wayland::audit::ResourceAudit;
+//!
+//! # let mut display = wayland_server::Display::<()>::new().unwrap();
+//! # let mut state = ();
+//! let audit = ResourceAudit::new();
+//! // [...]
+//! display.dispatch_clients(&mut state).unwrap();
+//! for (client, resources) in audit.check_leaks() {
+//!     eprintln!("{:?} leaked {:?}", client, resources);
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tracing::error;
+use wayland_server::backend::ClientId;
+
+/// Kind of a tracked resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// A `wl_surface`
+    Surface,
+    /// A `wl_buffer` of any type
+    Buffer,
+    /// A `wl_region`
+    Region,
+    /// A texture imported by a renderer from a buffer of the client
+    Texture,
+}
+
+/// Number of resources held by a client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResourceCounts {
+    /// Number of surfaces
+    pub surfaces: usize,
+    /// Number of buffers
+    pub buffers: usize,
+    /// Number of regions
+    pub regions: usize,
+    /// Number of textures
+    pub textures: usize,
+}
+
+impl ResourceCounts {
+    /// Returns the number of resources of the given kind
+    pub fn get(&self, kind: ResourceKind) -> usize {
+        match kind {
+            ResourceKind::Surface => self.surfaces,
+            ResourceKind::Buffer => self.buffers,
+            ResourceKind::Region => self.regions,
+            ResourceKind::Texture => self.textures,
+        }
+    }
+
+    fn get_mut(&mut self, kind: ResourceKind) -> &mut usize {
+        match kind {
+            ResourceKind::Surface => &mut self.surfaces,
+            ResourceKind::Buffer => &mut self.buffers,
+            ResourceKind::Region => &mut self.regions,
+            ResourceKind::Texture => &mut self.textures,
+        }
+    }
+
+    /// Returns the total number of resources
+    pub fn total(&self) -> usize {
+        self.surfaces + self.buffers + self.regions + self.textures
+    }
+
+    /// Returns whether no resources are held
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    // textures are released lazily by the renderers and not considered protocol objects
+    fn has_protocol_objects(&self) -> bool {
+        self.surfaces + self.buffers + self.regions > 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClientEntry {
+    counts: ResourceCounts,
+    disconnected: bool,
+}
+
+/// Resource accounting of the clients of a single display
+///
+/// This is a cheap handle, clones refer to the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceAudit {
+    clients: Arc<Mutex<HashMap<ClientId, ClientEntry>>>,
+}
+
+impl ResourceAudit {
+    /// Creates a new empty accounting
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new resource of a client
+    pub fn created(&self, client: &ClientId, kind: ResourceKind) {
+        let mut clients = self.clients.lock().unwrap();
+        *clients.entry(client.clone()).or_default().counts.get_mut(kind) += 1;
+    }
+
+    /// Record the destruction of a resource of a client
+    pub fn destroyed(&self, client: &ClientId, kind: ResourceKind) {
+        let mut clients = self.clients.lock().unwrap();
+        let Some(entry) = clients.get_mut(client) else {
+            return;
+        };
+        let count = entry.counts.get_mut(kind);
+        *count = count.saturating_sub(1);
+        if entry.disconnected && entry.counts.is_empty() {
+            clients.remove(client);
+        }
+    }
+
+    /// Returns the resources currently held by a client
+    pub fn counts(&self, client: &ClientId) -> ResourceCounts {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(client)
+            .map(|entry| entry.counts)
+            .unwrap_or_default()
+    }
+
+    /// Returns the resources of all clients holding any
+    pub fn dump(&self) -> Vec<(ClientId, ResourceCounts)> {
+        self.clients
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| !entry.counts.is_empty())
+            .map(|(client, entry)| (client.clone(), entry.counts))
+            .collect()
+    }
+
+    /// Mark a client as disconnected
+    ///
+    /// Its entry is dropped once all of its resources are destroyed.
+    pub fn client_disconnected(&self, client: &ClientId) {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(entry) = clients.get_mut(client) {
+            if entry.counts.is_empty() {
+                clients.remove(client);
+            } else {
+                entry.disconnected = true;
+            }
+        }
+    }
+
+    /// Returns disconnected clients still holding protocol objects
+    ///
+    /// Textures are not considered leaks, as renderers may release them lazily. The returned
+    /// clients are forgotten afterwards, so every leak is only reported once.
+    pub fn check_leaks(&self) -> Vec<(ClientId, ResourceCounts)> {
+        let mut clients = self.clients.lock().unwrap();
+        let leaks = clients
+            .iter()
+            .filter(|(_, entry)| entry.disconnected && entry.counts.has_protocol_objects())
+            .map(|(client, entry)| (client.clone(), entry.counts))
+            .collect::<Vec<_>>();
+        for (client, counts) in &leaks {
+            error!(?client, ?counts, "Resources of disconnected client leaked");
+            clients.remove(client);
+        }
+        leaks
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Arc;
+
+    use wayland_server::{
+        backend::{ClientData, DisconnectReason},
+        protocol::{wl_compositor::WlCompositor, wl_region::WlRegion, wl_surface::WlSurface},
+        Client, Display, Resource,
+    };
+
+    use super::*;
+    use crate::wayland::{
+        compositor::{CompositorClientState, CompositorHandler, CompositorState},
+        test::{Arg, FakeClient},
+    };
+
+    struct State {
+        compositor: CompositorState,
+        audit: Option<ResourceAudit>,
+    }
+
+    #[derive(Default)]
+    struct ClientState {
+        compositor: CompositorClientState,
+    }
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<ClientState>().unwrap().compositor
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+
+        fn resource_audit(&self) -> Option<&ResourceAudit> {
+            self.audit.as_ref()
+        }
+    }
+
+    crate::delegate_compositor!(State);
+
+    struct Setup {
+        display: Display<State>,
+        state: State,
+        fake: FakeClient,
+        client: Client,
+        compositor: u32,
+    }
+
+    impl Setup {
+        fn new(audit: Option<ResourceAudit>) -> Setup {
+            let mut display = Display::<State>::new().unwrap();
+            let state = State {
+                compositor: CompositorState::new_v6::<State>(&display.handle()),
+                audit,
+            };
+            let (mut fake, client) =
+                FakeClient::connect(&mut display.handle(), Arc::new(ClientState::default())).unwrap();
+            fake.get_registry().unwrap();
+            let mut setup = Setup {
+                display,
+                state,
+                fake,
+                client,
+                compositor: 0,
+            };
+            setup.roundtrip();
+            setup.compositor = setup.fake.bind_global(WlCompositor::interface(), 6).unwrap();
+            setup.roundtrip();
+            setup
+        }
+
+        fn roundtrip(&mut self) {
+            self.display.dispatch_clients(&mut self.state).unwrap();
+            self.display.flush_clients().unwrap();
+            self.fake.receive().unwrap();
+        }
+
+        fn create(&mut self, interface: &'static wayland_server::backend::protocol::Interface) -> u32 {
+            let opcode = if interface.name == WlSurface::interface().name {
+                0
+            } else {
+                1
+            };
+            let id = self.fake.new_id(interface);
+            self.fake
+                .send(self.compositor, opcode, vec![Arg::NewId(id)])
+                .unwrap();
+            id
+        }
+    }
+
+    #[test]
+    fn counts_are_kept_per_display() {
+        let first = ResourceAudit::new();
+        let second = ResourceAudit::new();
+        let mut a = Setup::new(Some(first.clone()));
+        let mut b = Setup::new(Some(second.clone()));
+
+        a.create(WlSurface::interface());
+        a.create(WlSurface::interface());
+        a.create(WlRegion::interface());
+        a.roundtrip();
+        b.create(WlSurface::interface());
+        b.roundtrip();
+
+        // the client ids of different displays may be equal, but must not share their counts
+        let counts = first.counts(&a.client.id());
+        assert_eq!((counts.surfaces, counts.regions), (2, 1));
+        let counts = second.counts(&b.client.id());
+        assert_eq!((counts.surfaces, counts.regions), (1, 0));
+    }
+
+    #[test]
+    fn accounting_is_opt_in() {
+        let audit = ResourceAudit::new();
+        let mut setup = Setup::new(None);
+        setup.create(WlSurface::interface());
+        setup.roundtrip();
+
+        assert!(audit.dump().is_empty());
+        assert!(setup.state.resource_audit().is_none());
+    }
+
+    #[test]
+    fn destroyed_resources_are_released() {
+        let audit = ResourceAudit::new();
+        let mut setup = Setup::new(Some(audit.clone()));
+        let surface = setup.create(WlSurface::interface());
+        let region = setup.create(WlRegion::interface());
+        setup.roundtrip();
+        assert_eq!(audit.counts(&setup.client.id()).total(), 2);
+
+        setup.fake.send(surface, 0, Vec::new()).unwrap();
+        setup.fake.send(region, 0, Vec::new()).unwrap();
+        setup.roundtrip();
+        assert!(audit.counts(&setup.client.id()).is_empty());
+        assert!(audit.dump().is_empty());
+    }
+
+    #[test]
+    fn disconnected_clients_are_cleaned_up() {
+        let audit = ResourceAudit::new();
+        let mut setup = Setup::new(Some(audit.clone()));
+        setup.create(WlSurface::interface());
+        setup.roundtrip();
+        let client = setup.client.id();
+
+        audit.client_disconnected(&client);
+        setup
+            .display
+            .handle()
+            .kill_client(client.clone(), DisconnectReason::ConnectionClosed);
+        setup.display.dispatch_clients(&mut setup.state).unwrap();
+
+        assert!(audit.check_leaks().is_empty());
+        assert!(audit.dump().is_empty());
+    }
+
+    #[test]
+    fn leaks_are_reported_once() {
+        let audit = ResourceAudit::new();
+        let setup = Setup::new(None);
+        let client = setup.client.id();
+
+        audit.created(&client, ResourceKind::Surface);
+        audit.created(&client, ResourceKind::Texture);
+        audit.client_disconnected(&client);
+
+        let leaks = audit.check_leaks();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].1.surfaces, 1);
+        assert!(audit.check_leaks().is_empty());
+
+        // textures alone are released lazily and not reported
+        audit.created(&client, ResourceKind::Texture);
+        audit.client_disconnected(&client);
+        assert!(audit.check_leaks().is_empty());
+    }
+}
@@ -6,6 +6,8 @@
 
 use wayland_server::protocol::wl_buffer;
 
+use crate::wayland::audit::ResourceAudit;
+
 /// Handler trait for associating data with a [`WlBuffer`](wayland_server::protocol::wl_buffer::WlBuffer).
 ///
 /// This trait primarily allows compositors to be told when a buffer is destroyed.
@@ -20,4 +22,11 @@ pub trait BufferHandler {
     ///
     /// At this point the buffer is no longer usable by Smithay.
     fn buffer_destroyed(&mut self, buffer: &wl_buffer::WlBuffer);
+
+    /// Accounting the buffers of clients are recorded in
+    ///
+    /// Returns `None` by default, which disables the accounting of buffers. See [`audit`](crate::wayland::audit).
+    fn resource_audit(&self) -> Option<&ResourceAudit> {
+        None
+    }
 }
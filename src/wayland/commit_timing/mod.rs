@@ -58,6 +58,10 @@
 //! });
 //! ```
 //!
+//! When using the [desktop](crate::desktop) helpers, [`Window::signal_frame_barriers`](crate::desktop::Window::signal_frame_barriers)
+//! releases the commit timers due for a frame, while [`Window::next_commit_deadline`](crate::desktop::Window::next_commit_deadline)
+//! tells the frame clock when the next held commit wants to be presented.
+//!
 //! ### Unmanaged mode
 //!
 //! If for some reason the integrated solution for commit timers does not suit your needs
@@ -79,9 +83,8 @@
 //!         .and_then(|state| state.borrow_mut().timestamp.take())
 //! });
 //! ```
-use std::{cell::RefCell, collections::BinaryHeap, sync::Mutex};
+use std::{cell::RefCell, collections::BinaryHeap, sync::Mutex, time::Duration};
 
-use rustix::fs::Timespec;
 use wayland_protocols::wp::commit_timing::v1::server::{
     wp_commit_timer_v1::{self, WpCommitTimerV1},
     wp_commit_timing_manager_v1::{self, WpCommitTimingManagerV1},
@@ -92,7 +95,7 @@ use wayland_server::{
 };
 
 use crate::{
-    utils::Time,
+    utils::{Monotonic, Time},
     wayland::compositor::{add_blocker, add_pre_commit_hook},
 };
 
@@ -281,10 +284,9 @@ where
 
                 let tv_sec = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
 
-                let timestamp = Timestamp(Timespec {
-                    tv_sec: tv_sec as rustix::time::Secs,
-                    tv_nsec: tv_nsec as rustix::time::Nsecs,
-                });
+                let timestamp = Timestamp(Time::from(
+                    Duration::from_secs(tv_sec).saturating_add(Duration::from_nanos(tv_nsec as u64)),
+                ));
 
                 let already_has_timestamp = with_states(&surface, move |states| {
                     let mut commit_timer_state = states
@@ -325,23 +327,8 @@ where
 }
 
 /// Timestamp set through [`wp_commit_timer_v1::Request::SetTimestamp`] for a surface
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub struct Timestamp(Timespec);
-
-impl Ord for Timestamp {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match self.0.tv_sec.cmp(&other.0.tv_sec) {
-            std::cmp::Ordering::Equal => self.0.tv_nsec.cmp(&other.0.tv_nsec),
-            other => other,
-        }
-    }
-}
-
-impl PartialOrd for Timestamp {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct Timestamp(Time<Monotonic>);
 
 /// Per surface [`WpCommitTimerV1`] state stored in the surface user data
 pub type CommitTimerStateUserData = RefCell<CommitTimerState>;
@@ -364,13 +351,13 @@ pub struct CommitTimerBarrierState {
 
 impl<Kind> From<Time<Kind>> for Timestamp {
     fn from(value: Time<Kind>) -> Self {
-        Self(value.into())
+        Self(value.cast())
     }
 }
 
 impl<Kind> From<Timestamp> for Time<Kind> {
     fn from(value: Timestamp) -> Self {
-        Time::from(value.0)
+        value.0.cast()
     }
 }
 
@@ -19,6 +19,7 @@ use crate::utils::{
     alive_tracker::{AliveTracker, IsAlive},
     Client, Logical, Point,
 };
+use crate::wayland::audit::ResourceKind;
 
 use super::{
     cache::Cacheable,
@@ -64,7 +65,7 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         _resource: &WlCompositor,
         request: wl_compositor::Request,
         _data: &(),
@@ -87,6 +88,13 @@ where
                 state.compositor_state().surfaces.push(surface.clone());
 
                 PrivateSurfaceData::init(&surface);
+                if let Some(audit) = state.resource_audit() {
+                    audit.created(&client.id(), ResourceKind::Surface);
+                    // the renderer records the textures of the surface in the same accounting
+                    PrivateSurfaceData::with_states(&surface, |states| {
+                        states.data_map.insert_if_missing_threadsafe(|| audit.clone());
+                    });
+                }
                 state.new_surface(&surface);
             }
             wl_compositor::Request::CreateRegion { id } => {
@@ -98,6 +106,9 @@ where
                         inner: Default::default(),
                     },
                 );
+                if let Some(audit) = state.resource_audit() {
+                    audit.created(&client.id(), ResourceKind::Region);
+                }
             }
             _ => unreachable!(),
         }
@@ -172,6 +183,7 @@ where
         handle: &DisplayHandle,
         data_init: &mut wayland_server::DataInit<'_, D>,
     ) {
+        let _span = crate::utils::log::surface_span(surface).entered();
         tracing::info!("WlSurface Request: {:?}", request);
         match request {
             wl_surface::Request::Attach { buffer, x, y } => {
@@ -347,7 +359,7 @@ where
 
     fn destroyed(
         state: &mut D,
-        _client_id: wayland_server::backend::ClientId,
+        client_id: wayland_server::backend::ClientId,
         surface: &WlSurface,
         data: &SurfaceUserData,
     ) {
@@ -362,6 +374,9 @@ where
             .surfaces
             .retain(|s| s.id() != surface.id());
         PrivateSurfaceData::cleanup(state, data, surface);
+        if let Some(audit) = state.resource_audit() {
+            audit.destroyed(&client_id, ResourceKind::Surface);
+        }
     }
 }
 
@@ -421,6 +436,17 @@ where
             _ => unreachable!(),
         }
     }
+
+    fn destroyed(
+        state: &mut D,
+        client_id: wayland_server::backend::ClientId,
+        _resource: &WlRegion,
+        _data: &RegionUserData,
+    ) {
+        if let Some(audit) = state.resource_audit() {
+            audit.destroyed(&client_id, ResourceKind::Region);
+        }
+    }
 }
 
 /*
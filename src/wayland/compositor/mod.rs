@@ -80,7 +80,9 @@
 //! 1. Pre Commit hooks registered to this surface are invoked. Such hooks can be registered using
 //!    the [`add_pre_commit_hook`] function. They are typically used by protocol extensions that
 //!    add state to a surface and need to check on commit that client did not request an
-//!    illegal state before it is applied on commit.
+//!    illegal state before it is applied on commit. They can also add blockers (see [`add_blocker`])
+//!    delaying the application of the state until an external condition is satisfied, like a
+//!    fence being signaled or a [`ManualBlocker`] being released after an animation.
 //! 2. The pending state is either applied and made current, or cached for later application
 //!    is the surface is a synchronize subsurface. If the current state is applied, state
 //!    of the synchronized children subsurface are applied as well at this point.
@@ -118,13 +120,15 @@ use std::{any::Any, sync::Mutex};
 pub use self::cache::{Cacheable, CachedState, MultiCache};
 pub use self::handlers::{RegionUserData, SubsurfaceCachedState, SubsurfaceUserData, SurfaceUserData};
 use self::transaction::TransactionQueue;
-pub use self::transaction::{Barrier, Blocker, BlockerState};
+pub use self::transaction::{Barrier, Blocker, BlockerState, ManualBlocker};
 pub use self::tree::{AlreadyHasRole, TraversalAction};
 use self::tree::{PrivateSurfaceData, SuggestedSurfaceState};
 pub use crate::utils::hook::HookId;
 use crate::utils::Transform;
 use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle};
+use crate::wayland::audit::ResourceAudit;
 use atomic_float::AtomicF64;
+use smallvec::SmallVec;
 use wayland_server::backend::GlobalId;
 use wayland_server::protocol::wl_compositor::WlCompositor;
 use wayland_server::protocol::wl_subcompositor::WlSubcompositor;
@@ -549,6 +553,10 @@ pub fn remove_destruction_hook(surface: &WlSurface, hook_id: HookId) {
 /// The module will only evaluate blocker states on commit. If a blocker
 /// becomes ready later, a call to [`CompositorClientState::blocker_cleared`] is necessary
 /// to trigger a re-evaluation.
+///
+/// Besides the blockers added by protocol implementations (e.g. for dmabuf fences), a
+/// [`ManualBlocker`] can be used to delay state changes on compositor specific conditions,
+/// typically from a pre-commit hook (see [`add_pre_commit_hook`]).
 pub fn add_blocker(surface: &WlSurface, blocker: impl Blocker + Send + 'static) {
     PrivateSurfaceData::add_blocker(surface, blocker)
 }
@@ -600,6 +608,14 @@ pub trait CompositorHandler {
     ///
     /// This allows the compositor to clean up any uses of the surface.
     fn destroyed(&mut self, _surface: &WlSurface) {}
+
+    /// Accounting the surfaces and regions of clients are recorded in
+    ///
+    /// Returns `None` by default, which disables the accounting of surfaces, regions and the
+    /// textures imported for them. See [`audit`](crate::wayland::audit).
+    fn resource_audit(&self) -> Option<&ResourceAudit> {
+        None
+    }
 }
 
 /// State of a compositor
@@ -634,7 +650,7 @@ impl CompositorClientState {
         let transactions = if let Some(queue) = self.queue.lock().unwrap().as_mut() {
             queue.take_ready()
         } else {
-            Vec::new()
+            SmallVec::new()
         };
 
         for transaction in transactions {
@@ -824,4 +840,15 @@ mod tests {
         assert!(region.contains((5, 5)));
         assert!(region.contains((2, 2)));
     }
+
+    #[test]
+    fn manual_blocker_resolves_once() {
+        let blocker = ManualBlocker::new();
+        let clone = blocker.clone();
+        assert_eq!(clone.state(), BlockerState::Pending);
+
+        blocker.release();
+        blocker.cancel();
+        assert_eq!(clone.state(), BlockerState::Released);
+    }
 }
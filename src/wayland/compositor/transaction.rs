@@ -39,11 +39,16 @@
 // but will be once proper transaction & blockers support is
 // added to smithay
 use std::{
+    cell::RefCell,
     collections::HashSet,
     fmt,
-    sync::{atomic::AtomicBool, Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU8},
+        Arc, Mutex,
+    },
 };
 
+use smallvec::SmallVec;
 use wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource, Weak};
 
 use crate::utils::Serial;
@@ -108,9 +113,85 @@ impl Blocker for Barrier {
     }
 }
 
+/// A [`Blocker`] resolved manually by the compositor
+///
+/// Unlike a [`Barrier`] it can also be cancelled, discarding the blocked state changes.
+/// This can be used to delay state changes on external conditions, e.g. to hold back
+/// mapping a window until an animation finished or a transaction involving other
+/// surfaces is ready.
+///
+/// Clones share the same state, so one clone can be passed to [`add_blocker`](super::add_blocker)
+/// while another is kept to resolve it. Afterwards [`CompositorClientState::blocker_cleared`](super::CompositorClientState::blocker_cleared)
+/// needs to be called for the client owning the surface.
+#[derive(Debug, Clone)]
+pub struct ManualBlocker(Arc<AtomicU8>);
+
+impl PartialEq for ManualBlocker {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl Eq for ManualBlocker {}
+
+impl Default for ManualBlocker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualBlocker {
+    const PENDING: u8 = 0;
+    const RELEASED: u8 = 1;
+    const CANCELLED: u8 = 2;
+
+    /// Initialize a new pending [`ManualBlocker`]
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU8::new(Self::PENDING)))
+    }
+
+    /// Release the blocker, allowing the blocked changes to be applied
+    ///
+    /// Has no effect if the blocker was already cancelled.
+    #[inline]
+    pub fn release(&self) {
+        self.resolve(Self::RELEASED)
+    }
+
+    /// Cancel the blocker, discarding the blocked changes
+    ///
+    /// Has no effect if the blocker was already released.
+    #[inline]
+    pub fn cancel(&self) {
+        self.resolve(Self::CANCELLED)
+    }
+
+    fn resolve(&self, state: u8) {
+        let _ = self.0.compare_exchange(
+            Self::PENDING,
+            state,
+            std::sync::atomic::Ordering::AcqRel,
+            std::sync::atomic::Ordering::Acquire,
+        );
+    }
+}
+
+impl Blocker for ManualBlocker {
+    fn state(&self) -> BlockerState {
+        match self.0.load(std::sync::atomic::Ordering::Acquire) {
+            Self::RELEASED => BlockerState::Released,
+            Self::CANCELLED => BlockerState::Cancelled,
+            _ => BlockerState::Pending,
+        }
+    }
+}
+
+// most transactions contain a single surface, or a surface with a synchronized subsurface
+type Surfaces = SmallVec<[(Weak<WlSurface>, Serial); 2]>;
+
 #[derive(Default)]
 struct TransactionState {
-    surfaces: Vec<(Weak<WlSurface>, Serial)>,
+    surfaces: Surfaces,
     blockers: Vec<Box<dyn Blocker + Send>>,
 }
 
@@ -137,11 +218,21 @@ pub(crate) struct PendingTransaction {
     inner: Arc<Mutex<TransactionInner>>,
 }
 
+// Allocations of finalized transactions are reused, so committing a surface does not allocate
+// in the common case. Transactions are created and finalized on the thread dispatching the
+// clients, so the pool is per thread.
+const POOL_SIZE: usize = 16;
+
+thread_local! {
+    static POOL: RefCell<Vec<Arc<Mutex<TransactionInner>>>> = const { RefCell::new(Vec::new()) };
+}
+
 impl Default for PendingTransaction {
     fn default() -> Self {
-        PendingTransaction {
-            inner: Arc::new(Mutex::new(TransactionInner::Data(Default::default()))),
-        }
+        let inner = POOL
+            .with_borrow_mut(|pool| pool.pop())
+            .unwrap_or_else(|| Arc::new(Mutex::new(TransactionInner::Data(Default::default()))));
+        PendingTransaction { inner }
     }
 }
 
@@ -204,18 +295,28 @@ impl PendingTransaction {
         });
     }
 
-    pub(crate) fn finalize(mut self) -> Transaction {
-        // When finalizing a transaction, this *must* be the last handle to this transaction
+    pub(crate) fn finalize(self) -> Transaction {
+        let mut inner = self.inner;
         loop {
-            let inner = match Arc::try_unwrap(self.inner) {
-                Ok(mutex) => mutex.into_inner().unwrap(),
-                Err(_) => panic!("Attempting to finalize a transaction but handle is not the last."),
-            };
-            match inner {
-                TransactionInner::Data(TransactionState {
-                    surfaces, blockers, ..
-                }) => return Transaction { surfaces, blockers },
-                TransactionInner::Fused(into) => self.inner = into,
+            // When finalizing a transaction, this *must* be the last handle to this transaction
+            if Arc::strong_count(&inner) != 1 {
+                panic!("Attempting to finalize a transaction but handle is not the last.");
+            }
+            // leave an empty state behind, so the allocation can be reused
+            let data = std::mem::replace(
+                &mut *inner.lock().unwrap(),
+                TransactionInner::Data(Default::default()),
+            );
+            POOL.with_borrow_mut(|pool| {
+                if pool.len() < POOL_SIZE {
+                    pool.push(inner);
+                }
+            });
+            match data {
+                TransactionInner::Data(TransactionState { surfaces, blockers }) => {
+                    return Transaction { surfaces, blockers }
+                }
+                TransactionInner::Fused(into) => inner = into,
             }
         }
     }
@@ -223,7 +324,7 @@ impl PendingTransaction {
 
 #[derive(Debug)]
 pub(crate) struct Transaction {
-    surfaces: Vec<(Weak<WlSurface>, Serial)>,
+    surfaces: Surfaces,
     blockers: Vec<Box<dyn Blocker + Send>>,
 }
 
@@ -290,9 +391,8 @@ impl TransactionQueue {
         self.transactions.push(t);
     }
 
-    pub(crate) fn take_ready(&mut self) -> Vec<Transaction> {
-        // FIXME: Get rid of this allocation here
-        let mut ready_transactions = Vec::new();
+    pub(crate) fn take_ready(&mut self) -> SmallVec<[Transaction; 2]> {
+        let mut ready_transactions = SmallVec::new();
         // this is a very non-optimized implementation
         // we just iterate over the queue of transactions, keeping track of which
         // surface we have seen as they encode transaction dependencies
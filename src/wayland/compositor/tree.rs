@@ -12,6 +12,7 @@ use super::{
     transaction::{Blocker, PendingTransaction, TransactionQueue},
     BufferAssignment, CompositorHandler, SurfaceAttributes, SurfaceData,
 };
+use smallvec::SmallVec;
 use std::{
     any::Any,
     fmt,
@@ -25,6 +26,10 @@ use wayland_server::{
 type CommitHook = dyn Fn(&mut dyn Any, &DisplayHandle, &WlSurface) + Send + Sync;
 type DestructionHook = dyn Fn(&mut dyn Any, &WlSurface) + Send + Sync;
 
+// Hooks and children are copied out of the locked surface data on every commit. Surfaces rarely
+// have more than a handful of them, so they are copied to the stack.
+type Children = SmallVec<[WlSurface; 4]>;
+
 /// Node of a subsurface tree, holding some user specified data type U
 /// at each node
 ///
@@ -241,7 +246,11 @@ impl PrivateSurfaceData {
 
     pub fn invoke_pre_commit_hooks<D: 'static>(state: &mut D, dh: &DisplayHandle, surface: &WlSurface) {
         // don't hold the mutex while the hooks are invoked
-        let hooks = Self::lock_user_data(surface).pre_commit_hooks.clone();
+        let hooks = Self::lock_user_data(surface)
+            .pre_commit_hooks
+            .iter()
+            .cloned()
+            .collect::<SmallVec<[_; 4]>>();
         for hook in hooks {
             (hook.cb)(state, dh, surface);
         }
@@ -249,7 +258,11 @@ impl PrivateSurfaceData {
 
     pub fn invoke_post_commit_hooks<D: 'static>(state: &mut D, dh: &DisplayHandle, surface: &WlSurface) {
         // don't hold the mutex while the hooks are invoked
-        let hooks = Self::lock_user_data(surface).post_commit_hooks.clone();
+        let hooks = Self::lock_user_data(surface)
+            .post_commit_hooks
+            .iter()
+            .cloned()
+            .collect::<SmallVec<[_; 4]>>();
         for hook in hooks {
             (hook.cb)(state, dh, surface);
         }
@@ -260,7 +273,7 @@ impl PrivateSurfaceData {
         parent_transaction: &PendingTransaction,
         dh: &DisplayHandle,
     ) {
-        let children = PrivateSurfaceData::get_children(surface);
+        let children = PrivateSurfaceData::children(surface);
         let mut my_data = Self::lock_user_data(surface);
 
         for child in children {
@@ -280,7 +293,7 @@ impl PrivateSurfaceData {
 
     pub fn commit<C: CompositorHandler + 'static>(surface: &WlSurface, dh: &DisplayHandle, state: &mut C) {
         let is_sync = is_effectively_sync(surface);
-        let children = PrivateSurfaceData::get_children(surface);
+        let children = PrivateSurfaceData::children(surface);
         let mut my_data = Self::lock_user_data(surface);
         // commit our state
         let current_txid = my_data.current_txid;
@@ -405,6 +418,10 @@ impl PrivateSurfaceData {
 
     /// Retrieve the children surface (if any) of this surface
     pub fn get_children(parent: &WlSurface) -> Vec<WlSurface> {
+        Self::children(parent).into_vec()
+    }
+
+    fn children(parent: &WlSurface) -> Children {
         Self::lock_user_data(parent)
             .children
             .iter()
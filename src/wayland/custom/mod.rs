@@ -0,0 +1,430 @@
+//! Utilities for implementing custom protocols
+//!
+//! Vendor or experimental protocol extensions not covered by smithay can be implemented
+//! downstream using the same delegate-state pattern as the built-in protocols, without forking
+//! the crate.
+//!
+//! ## Generating the protocol code
+//!
+//! The interfaces are generated from the protocol XML with `wayland-scanner`, re-exported as
+//! [`reexports::wayland_scanner`](crate::reexports::wayland_scanner). The generated code refers
+//! to `wayland_server` and `wayland_backend`, both can be provided through smithay's re-exports.
+//! The example uses the protocol shipped with smithay for its tests:
+//!
+//! ```
+//! pub mod example_protocol {
+//!     use smithay::reexports::wayland_server;
+//!     // for protocols referring to core interfaces like `wl_surface`
+//!     #[allow(unused_imports)]
+//!     use smithay::reexports::wayland_server::protocol::*;
+//!
+//!     pub mod __interfaces {
+//!         #[allow(unused_imports)]
+//!         use smithay::reexports::wayland_server::protocol::__interfaces::*;
+//!         use smithay::reexports::wayland_server::backend as wayland_backend;
+//!         smithay::reexports::wayland_scanner::generate_interfaces!("src/wayland/custom/example-v1.xml");
+//!     }
+//!     use self::__interfaces::*;
+//!
+//!     smithay::reexports::wayland_scanner::generate_server_code!("src/wayland/custom/example-v1.xml");
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! ## Implementing the protocol
+//!
+//! Implement [`CustomProtocolHandler`] for every interface of the protocol and additionally
+//! [`CustomGlobalHandler`] for the interfaces advertised as globals. Create the globals with
+//! [`CustomGlobalState`] and generate the dispatch glue with
+//! [`delegate_custom_global!`](crate::delegate_custom_global) and
+//! [`delegate_custom_object!`](crate::delegate_custom_object). Data of the protocol objects can
+//! be stored in the [`CustomUserData`] every object is created with.
+//!
+//! ```
+//! # pub mod example_protocol {
+//! #     use smithay::reexports::wayland_server;
+//! #     pub mod __interfaces {
+//! #         use smithay::reexports::wayland_server::backend as wayland_backend;
+//! #         smithay::reexports::wayland_scanner::generate_interfaces!("src/wayland/custom/example-v1.xml");
+//! #     }
+//! #     use self::__interfaces::*;
+//! #     smithay::reexports::wayland_scanner::generate_server_code!("src/wayland/custom/example-v1.xml");
+//! # }
+//! use smithay::reexports::wayland_server::{Client, DataInit, Display, DisplayHandle};
+//! use smithay::wayland::custom::{
+//!     CustomGlobalHandler, CustomGlobalState, CustomProtocolHandler, CustomUserData,
+//! };
+//! use smithay::{delegate_custom_global, delegate_custom_object};
+//! use example_protocol::{
+//!     example_manager_v1::{self, ExampleManagerV1},
+//!     example_object_v1::{self, ExampleObjectV1},
+//! };
+//!
+//! struct State;
+//!
+//! impl CustomGlobalHandler<ExampleManagerV1> for State {}
+//!
+//! impl CustomProtocolHandler<ExampleManagerV1> for State {
+//!     fn request(
+//!         &mut self,
+//!         _client: &Client,
+//!         _resource: &ExampleManagerV1,
+//!         request: example_manager_v1::Request,
+//!         _data: &CustomUserData,
+//!         _dhandle: &DisplayHandle,
+//!         data_init: &mut DataInit<'_, Self>,
+//!     ) {
+//!         match request {
+//!             example_manager_v1::Request::GetObject { id } => {
+//!                 data_init.init(id, CustomUserData::default());
+//!             }
+//!             example_manager_v1::Request::Destroy => {}
+//!             _ => unreachable!(),
+//!         }
+//!     }
+//! }
+//!
+//! impl CustomProtocolHandler<ExampleObjectV1> for State {
+//!     fn request(
+//!         &mut self,
+//!         _client: &Client,
+//!         resource: &ExampleObjectV1,
+//!         request: example_object_v1::Request,
+//!         _data: &CustomUserData,
+//!         _dhandle: &DisplayHandle,
+//!         _data_init: &mut DataInit<'_, Self>,
+//!     ) {
+//!         match request {
+//!             example_object_v1::Request::Ping { serial } => resource.pong(serial),
+//!             example_object_v1::Request::Destroy => {}
+//!             _ => unreachable!(),
+//!         }
+//!     }
+//! }
+//!
+//! delegate_custom_global!(State: ExampleManagerV1);
+//! delegate_custom_object!(State: ExampleObjectV1);
+//!
+//! # fn main() {
+//! let display = Display::<State>::new().unwrap();
+//! let global = CustomGlobalState::new::<ExampleManagerV1, State>(&display.handle(), 1);
+//! # }
+//! ```
+
+use wayland_server::{
+    backend::{ClientId, GlobalId},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::utils::user_data::UserDataMap;
+
+/// Handler for the requests of a custom protocol object of interface `I`
+pub trait CustomProtocolHandler<I: Resource>: Sized {
+    /// A request was sent by the client
+    fn request(
+        &mut self,
+        client: &Client,
+        resource: &I,
+        request: I::Request,
+        data: &CustomUserData,
+        dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    );
+
+    /// The object was destroyed
+    fn destroyed(&mut self, client: ClientId, resource: &I, data: &CustomUserData) {
+        let _ = (client, resource, data);
+    }
+}
+
+/// Handler for a custom global of interface `I`
+pub trait CustomGlobalHandler<I: Resource + 'static>: CustomProtocolHandler<I> {
+    /// A client bound the global
+    ///
+    /// The default implementation initializes the resource with an empty [`CustomUserData`].
+    fn bind(
+        &mut self,
+        dhandle: &DisplayHandle,
+        client: &Client,
+        resource: New<I>,
+        data_init: &mut DataInit<'_, Self>,
+    ) where
+        Self: Dispatch<I, CustomUserData> + 'static,
+    {
+        let _ = (dhandle, client);
+        data_init.init(resource, CustomUserData::default());
+    }
+}
+
+/// User data of custom protocol objects
+#[derive(Debug, Default)]
+pub struct CustomUserData {
+    user_data: UserDataMap,
+}
+
+impl CustomUserData {
+    /// Returns the [`UserDataMap`] used to store arbitrary data of the object
+    pub fn user_data(&self) -> &UserDataMap {
+        &self.user_data
+    }
+}
+
+/// Data associated with a custom global
+#[allow(missing_debug_implementations)]
+pub struct CustomGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// Delegate type of custom protocol globals and objects
+#[derive(Debug)]
+pub struct CustomGlobalState {
+    global: GlobalId,
+}
+
+impl CustomGlobalState {
+    /// Create a new global of interface `I`
+    pub fn new<I, D>(display: &DisplayHandle, version: u32) -> CustomGlobalState
+    where
+        I: Resource + 'static,
+        D: GlobalDispatch<I, CustomGlobalData> + Dispatch<I, CustomUserData> + 'static,
+    {
+        Self::new_with_filter::<I, D, _>(display, version, |_| true)
+    }
+
+    /// Create a new global of interface `I` with a filter
+    ///
+    /// Filters can be used to limit visibility of a global to certain clients.
+    pub fn new_with_filter<I, D, F>(display: &DisplayHandle, version: u32, filter: F) -> CustomGlobalState
+    where
+        I: Resource + 'static,
+        D: GlobalDispatch<I, CustomGlobalData> + Dispatch<I, CustomUserData> + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let data = CustomGlobalData {
+            filter: Box::new(filter),
+        };
+        let global = display.create_global::<D, I, _>(version, data);
+
+        CustomGlobalState { global }
+    }
+
+    /// Returns the id of the global
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+impl<I, D> GlobalDispatch<I, CustomGlobalData, D> for CustomGlobalState
+where
+    I: Resource + 'static,
+    D: GlobalDispatch<I, CustomGlobalData> + Dispatch<I, CustomUserData> + CustomGlobalHandler<I> + 'static,
+{
+    fn bind(
+        state: &mut D,
+        dhandle: &DisplayHandle,
+        client: &Client,
+        resource: New<I>,
+        _global_data: &CustomGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        state.bind(dhandle, client, resource, data_init);
+    }
+
+    fn can_view(client: Client, global_data: &CustomGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<I, D> Dispatch<I, CustomUserData, D> for CustomGlobalState
+where
+    I: Resource + 'static,
+    D: Dispatch<I, CustomUserData> + CustomProtocolHandler<I> + 'static,
+{
+    fn request(
+        state: &mut D,
+        client: &Client,
+        resource: &I,
+        request: I::Request,
+        data: &CustomUserData,
+        dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        state.request(client, resource, request, data, dhandle, data_init);
+    }
+
+    fn destroyed(state: &mut D, client: ClientId, resource: &I, data: &CustomUserData) {
+        CustomProtocolHandler::destroyed(state, client, resource, data);
+    }
+}
+
+/// Macro to delegate the implementation of a custom global to [`CustomGlobalState`]
+///
+/// You must also implement [`CustomGlobalHandler`] for the interface of the global.
+#[macro_export]
+macro_rules! delegate_custom_global {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty: $interface: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $interface: $crate::wayland::custom::CustomGlobalData
+        ] => $crate::wayland::custom::CustomGlobalState);
+        $crate::delegate_custom_object!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: $interface);
+    };
+}
+
+/// Macro to delegate the implementation of a custom protocol object to [`CustomGlobalState`]
+///
+/// You must also implement [`CustomProtocolHandler`] for the interface of the object.
+#[macro_export]
+macro_rules! delegate_custom_object {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty: $interface: ty) => {
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $interface: $crate::wayland::custom::CustomUserData
+        ] => $crate::wayland::custom::CustomGlobalState);
+    };
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{cell::Cell, sync::Arc};
+
+    use wayland_server::{
+        backend::{ClientData, DisconnectReason},
+        Display,
+    };
+
+    use super::*;
+    use crate::wayland::test::{Arg, FakeClient};
+
+    mod example_protocol {
+        use crate::reexports::wayland_server;
+
+        pub mod __interfaces {
+            use crate::reexports::wayland_server::backend as wayland_backend;
+            wayland_scanner::generate_interfaces!("src/wayland/custom/example-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("src/wayland/custom/example-v1.xml");
+    }
+
+    use example_protocol::{
+        example_manager_v1::{self, ExampleManagerV1},
+        example_object_v1::{self, ExampleObjectV1},
+    };
+
+    #[derive(Default)]
+    struct State {
+        destroyed: u32,
+    }
+
+    impl CustomGlobalHandler<ExampleManagerV1> for State {}
+
+    impl CustomProtocolHandler<ExampleManagerV1> for State {
+        fn request(
+            &mut self,
+            _client: &Client,
+            _resource: &ExampleManagerV1,
+            request: example_manager_v1::Request,
+            _data: &CustomUserData,
+            _dhandle: &DisplayHandle,
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            match request {
+                example_manager_v1::Request::GetObject { id } => {
+                    data_init.init(id, CustomUserData::default());
+                }
+                example_manager_v1::Request::Destroy => {}
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    impl CustomProtocolHandler<ExampleObjectV1> for State {
+        fn request(
+            &mut self,
+            _client: &Client,
+            resource: &ExampleObjectV1,
+            request: example_object_v1::Request,
+            data: &CustomUserData,
+            _dhandle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+            match request {
+                example_object_v1::Request::Ping { serial } => {
+                    let pings = data.user_data().get_or_insert(|| Cell::new(0u32));
+                    pings.set(pings.get() + 1);
+                    resource.pong(serial + pings.get());
+                }
+                example_object_v1::Request::Destroy => {}
+                _ => unreachable!(),
+            }
+        }
+
+        fn destroyed(&mut self, _client: ClientId, _resource: &ExampleObjectV1, _data: &CustomUserData) {
+            self.destroyed += 1;
+        }
+    }
+
+    crate::delegate_custom_global!(State: ExampleManagerV1);
+    crate::delegate_custom_object!(State: ExampleObjectV1);
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    #[test]
+    fn dispatches_to_handlers() {
+        let mut display = Display::<State>::new().unwrap();
+        let mut state = State::default();
+        CustomGlobalState::new::<ExampleManagerV1, State>(&display.handle(), 1);
+
+        let (mut fake, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+        fake.record_events(true);
+        fake.get_registry().unwrap();
+        fake.roundtrip(&mut display, &mut state).unwrap();
+
+        let manager = fake.bind_global(ExampleManagerV1::interface(), 1).unwrap();
+        let object = fake.new_id(ExampleObjectV1::interface());
+        let manager_interface = ExampleManagerV1::interface();
+        let object_interface = ExampleObjectV1::interface();
+        fake.send_request(manager, manager_interface, "get_object", vec![Arg::NewId(object)])
+            .unwrap();
+        fake.send_request(object, object_interface, "ping", vec![Arg::Uint(10)])
+            .unwrap();
+        fake.send_request(object, object_interface, "ping", vec![Arg::Uint(20)])
+            .unwrap();
+        fake.send_request(object, object_interface, "destroy", vec![])
+            .unwrap();
+        let events = fake.roundtrip(&mut display, &mut state).unwrap();
+
+        // the user data of the object counted the pings
+        let pongs: Vec<u32> = events
+            .into_iter()
+            .filter(|event| event.object == object && event.name() == Some("pong"))
+            .map(|event| match event.args.as_slice() {
+                [Arg::Uint(serial)] => *serial,
+                args => panic!("unexpected arguments {:?}", args),
+            })
+            .collect();
+        assert_eq!(pongs, [11, 22]);
+        assert_eq!(state.destroyed, 1);
+    }
+
+    #[test]
+    fn filters_globals() {
+        let mut display = Display::<State>::new().unwrap();
+        let mut state = State::default();
+        CustomGlobalState::new_with_filter::<ExampleManagerV1, State, _>(&display.handle(), 1, |_| false);
+
+        let (mut fake, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+        fake.get_registry().unwrap();
+        fake.roundtrip(&mut display, &mut state).unwrap();
+
+        assert!(fake
+            .globals()
+            .iter()
+            .all(|global| global.interface != ExampleManagerV1::interface().name));
+    }
+}
@@ -0,0 +1,285 @@
+//! Registering many protocol delegates at once
+//!
+//! Every protocol module provides a `delegate_*` macro implementing the dispatch traits for your
+//! compositor state. A compositor supporting many protocols ends up with a long list of these
+//! invocations. [`delegate_protocols!`](crate::delegate_protocols) takes the names of the
+//! protocol modules instead and expands to the matching `delegate_*` macros.
+//!
+//! ```
+//! extern crate wayland_server;
+//! extern crate smithay;
+//!
+//! use smithay::wayland::buffer::BufferHandler;
+//! use smithay::wayland::shm::{ShmHandler, ShmState};
+//! use smithay::delegate_protocols;
+//!
+//! # struct State { shm_state: ShmState }
+//! impl BufferHandler for State {
+//!     fn buffer_destroyed(&mut self, buffer: &wayland_server::protocol::wl_buffer::WlBuffer) {}
+//! }
+//! impl ShmHandler for State {
+//!     fn shm_state(&self) -> &ShmState {
+//!         &self.shm_state
+//!     }
+//! }
+//!
+//! // same as `delegate_shm!(State); delegate_single_pixel_buffer!(State);`
+//! delegate_protocols!(State: [shm, single_pixel_buffer]);
+//! ```
+//!
+//! With the `derive` feature the protocols can also be listed on the state itself, see
+//! [`DelegateProtocols`](self::DelegateProtocols).
+//!
+//! The handler traits of every listed protocol still have to be implemented. States with
+//! generic parameters are not supported, use the individual `delegate_*` macros for these.
+
+/// Derive macro delegating the protocols listed in `#[delegate(...)]` attributes to the state
+///
+/// Expands to [`delegate_protocols!`](crate::delegate_protocols), which lists the supported
+/// protocol names. The attribute may be repeated, listing a protocol twice is an error.
+///
+/// ```
+/// extern crate wayland_server;
+/// extern crate smithay;
+///
+/// use smithay::wayland::buffer::BufferHandler;
+/// use smithay::wayland::delegate::DelegateProtocols;
+/// use smithay::wayland::shm::{ShmHandler, ShmState};
+///
+/// #[derive(DelegateProtocols)]
+/// #[delegate(shm, single_pixel_buffer)]
+/// struct State {
+///     shm_state: ShmState,
+/// }
+///
+/// impl BufferHandler for State {
+///     fn buffer_destroyed(&mut self, buffer: &wayland_server::protocol::wl_buffer::WlBuffer) {}
+/// }
+/// impl ShmHandler for State {
+///     fn shm_state(&self) -> &ShmState {
+///         &self.shm_state
+///     }
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use smithay_derive::DelegateProtocols;
+
+/// Macro to delegate the implementation of multiple protocols at once
+///
+/// Takes the compositor state and a list of protocol names, each expanding to the matching
+/// `delegate_*` macro. Protocols gated behind a cargo feature are only available if the
+/// feature is enabled. Supported names:
+///
+/// - `alpha_modifier`: [`delegate_alpha_modifier!`](crate::delegate_alpha_modifier)
+/// - `commit_timing`: [`delegate_commit_timing!`](crate::delegate_commit_timing)
+/// - `compositor`: [`delegate_compositor!`](crate::delegate_compositor)
+/// - `content_type`: [`delegate_content_type!`](crate::delegate_content_type)
+/// - `cursor_shape`: [`delegate_cursor_shape!`](crate::delegate_cursor_shape)
+/// - `data_control`: [`delegate_data_control!`](crate::delegate_data_control)
+/// - `data_device`: [`delegate_data_device!`](crate::delegate_data_device)
+/// - `dmabuf`: [`delegate_dmabuf!`](crate::delegate_dmabuf)
+/// - `drm_lease`: `delegate_drm_lease!`
+/// - `drm_syncobj`: `delegate_drm_syncobj!`
+/// - `export_dmabuf`: [`delegate_export_dmabuf!`](crate::delegate_export_dmabuf)
+/// - `ext_data_control`: [`delegate_ext_data_control!`](crate::delegate_ext_data_control)
+/// - `fifo`: [`delegate_fifo!`](crate::delegate_fifo)
+/// - `foreign_toplevel_list`: [`delegate_foreign_toplevel_list!`](crate::delegate_foreign_toplevel_list)
+/// - `fractional_scale`: [`delegate_fractional_scale!`](crate::delegate_fractional_scale)
+/// - `fullscreen_shell`: [`delegate_fullscreen_shell!`](crate::delegate_fullscreen_shell)
+/// - `idle_inhibit`: [`delegate_idle_inhibit!`](crate::delegate_idle_inhibit)
+/// - `idle_notify`: [`delegate_idle_notify!`](crate::delegate_idle_notify)
+/// - `input_method_manager`: [`delegate_input_method_manager!`](crate::delegate_input_method_manager)
+/// - `kde_decoration`: [`delegate_kde_decoration!`](crate::delegate_kde_decoration)
+/// - `keyboard_shortcuts_inhibit`: [`delegate_keyboard_shortcuts_inhibit!`](crate::delegate_keyboard_shortcuts_inhibit)
+/// - `layer_shell`: [`delegate_layer_shell!`](crate::delegate_layer_shell)
+/// - `output`: [`delegate_output!`](crate::delegate_output)
+/// - `pointer_constraints`: [`delegate_pointer_constraints!`](crate::delegate_pointer_constraints)
+/// - `pointer_gestures`: [`delegate_pointer_gestures!`](crate::delegate_pointer_gestures)
+/// - `pointer_warp`: [`delegate_pointer_warp!`](crate::delegate_pointer_warp)
+/// - `presentation`: [`delegate_presentation!`](crate::delegate_presentation)
+/// - `primary_selection`: [`delegate_primary_selection!`](crate::delegate_primary_selection)
+/// - `relative_pointer`: [`delegate_relative_pointer!`](crate::delegate_relative_pointer)
+/// - `seat`: [`delegate_seat!`](crate::delegate_seat)
+/// - `security_context`: [`delegate_security_context!`](crate::delegate_security_context)
+/// - `session_lock`: [`delegate_session_lock!`](crate::delegate_session_lock)
+/// - `shm`: [`delegate_shm!`](crate::delegate_shm)
+/// - `single_pixel_buffer`: [`delegate_single_pixel_buffer!`](crate::delegate_single_pixel_buffer)
+/// - `tablet_manager`: [`delegate_tablet_manager!`](crate::delegate_tablet_manager)
+/// - `text_input_manager`: [`delegate_text_input_manager!`](crate::delegate_text_input_manager)
+/// - `toplevel_export`: [`delegate_toplevel_export!`](crate::delegate_toplevel_export)
+/// - `viewporter`: [`delegate_viewporter!`](crate::delegate_viewporter)
+/// - `virtual_keyboard_manager`: [`delegate_virtual_keyboard_manager!`](crate::delegate_virtual_keyboard_manager)
+/// - `workspace`: [`delegate_workspace!`](crate::delegate_workspace)
+/// - `xdg_activation`: [`delegate_xdg_activation!`](crate::delegate_xdg_activation)
+/// - `xdg_decoration`: [`delegate_xdg_decoration!`](crate::delegate_xdg_decoration)
+/// - `xdg_dialog`: [`delegate_xdg_dialog!`](crate::delegate_xdg_dialog)
+/// - `xdg_foreign`: [`delegate_xdg_foreign!`](crate::delegate_xdg_foreign)
+/// - `xdg_shell`: [`delegate_xdg_shell!`](crate::delegate_xdg_shell)
+/// - `xdg_system_bell`: [`delegate_xdg_system_bell!`](crate::delegate_xdg_system_bell)
+/// - `xdg_toplevel_drag`: [`delegate_xdg_toplevel_drag!`](crate::delegate_xdg_toplevel_drag)
+/// - `xdg_toplevel_icon`: [`delegate_xdg_toplevel_icon!`](crate::delegate_xdg_toplevel_icon)
+/// - `xdg_toplevel_tag`: [`delegate_xdg_toplevel_tag!`](crate::delegate_xdg_toplevel_tag)
+/// - `xwayland_keyboard_grab`: `delegate_xwayland_keyboard_grab!`
+/// - `xwayland_shell`: `delegate_xwayland_shell!`
+#[macro_export]
+macro_rules! delegate_protocols {
+    ($ty: ty: [$($protocol: ident),* $(,)?]) => {
+        $($crate::delegate_protocols!(@delegate $ty, $protocol);)*
+    };
+    (@delegate $ty: ty, alpha_modifier) => {
+        $crate::delegate_alpha_modifier!($ty);
+    };
+    (@delegate $ty: ty, commit_timing) => {
+        $crate::delegate_commit_timing!($ty);
+    };
+    (@delegate $ty: ty, compositor) => {
+        $crate::delegate_compositor!($ty);
+    };
+    (@delegate $ty: ty, content_type) => {
+        $crate::delegate_content_type!($ty);
+    };
+    (@delegate $ty: ty, cursor_shape) => {
+        $crate::delegate_cursor_shape!($ty);
+    };
+    (@delegate $ty: ty, data_control) => {
+        $crate::delegate_data_control!($ty);
+    };
+    (@delegate $ty: ty, data_device) => {
+        $crate::delegate_data_device!($ty);
+    };
+    (@delegate $ty: ty, dmabuf) => {
+        $crate::delegate_dmabuf!($ty);
+    };
+    (@delegate $ty: ty, drm_lease) => {
+        $crate::delegate_drm_lease!($ty);
+    };
+    (@delegate $ty: ty, drm_syncobj) => {
+        $crate::delegate_drm_syncobj!($ty);
+    };
+    (@delegate $ty: ty, export_dmabuf) => {
+        $crate::delegate_export_dmabuf!($ty);
+    };
+    (@delegate $ty: ty, ext_data_control) => {
+        $crate::delegate_ext_data_control!($ty);
+    };
+    (@delegate $ty: ty, fifo) => {
+        $crate::delegate_fifo!($ty);
+    };
+    (@delegate $ty: ty, foreign_toplevel_list) => {
+        $crate::delegate_foreign_toplevel_list!($ty);
+    };
+    (@delegate $ty: ty, fractional_scale) => {
+        $crate::delegate_fractional_scale!($ty);
+    };
+    (@delegate $ty: ty, fullscreen_shell) => {
+        $crate::delegate_fullscreen_shell!($ty);
+    };
+    (@delegate $ty: ty, idle_inhibit) => {
+        $crate::delegate_idle_inhibit!($ty);
+    };
+    (@delegate $ty: ty, idle_notify) => {
+        $crate::delegate_idle_notify!($ty);
+    };
+    (@delegate $ty: ty, input_method_manager) => {
+        $crate::delegate_input_method_manager!($ty);
+    };
+    (@delegate $ty: ty, kde_decoration) => {
+        $crate::delegate_kde_decoration!($ty);
+    };
+    (@delegate $ty: ty, keyboard_shortcuts_inhibit) => {
+        $crate::delegate_keyboard_shortcuts_inhibit!($ty);
+    };
+    (@delegate $ty: ty, layer_shell) => {
+        $crate::delegate_layer_shell!($ty);
+    };
+    (@delegate $ty: ty, output) => {
+        $crate::delegate_output!($ty);
+    };
+    (@delegate $ty: ty, pointer_constraints) => {
+        $crate::delegate_pointer_constraints!($ty);
+    };
+    (@delegate $ty: ty, pointer_gestures) => {
+        $crate::delegate_pointer_gestures!($ty);
+    };
+    (@delegate $ty: ty, pointer_warp) => {
+        $crate::delegate_pointer_warp!($ty);
+    };
+    (@delegate $ty: ty, presentation) => {
+        $crate::delegate_presentation!($ty);
+    };
+    (@delegate $ty: ty, primary_selection) => {
+        $crate::delegate_primary_selection!($ty);
+    };
+    (@delegate $ty: ty, relative_pointer) => {
+        $crate::delegate_relative_pointer!($ty);
+    };
+    (@delegate $ty: ty, seat) => {
+        $crate::delegate_seat!($ty);
+    };
+    (@delegate $ty: ty, security_context) => {
+        $crate::delegate_security_context!($ty);
+    };
+    (@delegate $ty: ty, session_lock) => {
+        $crate::delegate_session_lock!($ty);
+    };
+    (@delegate $ty: ty, shm) => {
+        $crate::delegate_shm!($ty);
+    };
+    (@delegate $ty: ty, single_pixel_buffer) => {
+        $crate::delegate_single_pixel_buffer!($ty);
+    };
+    (@delegate $ty: ty, tablet_manager) => {
+        $crate::delegate_tablet_manager!($ty);
+    };
+    (@delegate $ty: ty, text_input_manager) => {
+        $crate::delegate_text_input_manager!($ty);
+    };
+    (@delegate $ty: ty, toplevel_export) => {
+        $crate::delegate_toplevel_export!($ty);
+    };
+    (@delegate $ty: ty, viewporter) => {
+        $crate::delegate_viewporter!($ty);
+    };
+    (@delegate $ty: ty, virtual_keyboard_manager) => {
+        $crate::delegate_virtual_keyboard_manager!($ty);
+    };
+    (@delegate $ty: ty, workspace) => {
+        $crate::delegate_workspace!($ty);
+    };
+    (@delegate $ty: ty, xdg_activation) => {
+        $crate::delegate_xdg_activation!($ty);
+    };
+    (@delegate $ty: ty, xdg_decoration) => {
+        $crate::delegate_xdg_decoration!($ty);
+    };
+    (@delegate $ty: ty, xdg_dialog) => {
+        $crate::delegate_xdg_dialog!($ty);
+    };
+    (@delegate $ty: ty, xdg_foreign) => {
+        $crate::delegate_xdg_foreign!($ty);
+    };
+    (@delegate $ty: ty, xdg_shell) => {
+        $crate::delegate_xdg_shell!($ty);
+    };
+    (@delegate $ty: ty, xdg_system_bell) => {
+        $crate::delegate_xdg_system_bell!($ty);
+    };
+    (@delegate $ty: ty, xdg_toplevel_drag) => {
+        $crate::delegate_xdg_toplevel_drag!($ty);
+    };
+    (@delegate $ty: ty, xdg_toplevel_icon) => {
+        $crate::delegate_xdg_toplevel_icon!($ty);
+    };
+    (@delegate $ty: ty, xdg_toplevel_tag) => {
+        $crate::delegate_xdg_toplevel_tag!($ty);
+    };
+    (@delegate $ty: ty, xwayland_keyboard_grab) => {
+        $crate::delegate_xwayland_keyboard_grab!($ty);
+    };
+    (@delegate $ty: ty, xwayland_shell) => {
+        $crate::delegate_xwayland_shell!($ty);
+    };
+    (@delegate $ty: ty, $protocol: ident) => {
+        compile_error!(concat!("unknown protocol `", stringify!($protocol), "`"));
+    };
+}
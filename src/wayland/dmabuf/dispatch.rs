@@ -10,7 +10,7 @@ use wayland_server::{
 
 use crate::{
     backend::allocator::dmabuf::{Dmabuf, Plane, MAX_PLANES},
-    wayland::{buffer::BufferHandler, compositor},
+    wayland::{audit::ResourceKind, buffer::BufferHandler, compositor},
 };
 
 use super::{
@@ -40,8 +40,11 @@ where
         }
     }
 
-    fn destroyed(data: &mut D, _client: ClientId, buffer: &wl_buffer::WlBuffer, _udata: &Dmabuf) {
+    fn destroyed(data: &mut D, client: ClientId, buffer: &wl_buffer::WlBuffer, _udata: &Dmabuf) {
         data.buffer_destroyed(buffer);
+        if let Some(audit) = data.resource_audit() {
+            audit.destroyed(&client, ResourceKind::Buffer);
+        }
     }
 }
 
@@ -228,13 +231,14 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &Client,
+        client: &Client,
         params: &zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1,
         request: zwp_linux_buffer_params_v1::Request,
         data: &DmabufParamsData,
         dh: &DisplayHandle,
         data_init: &mut DataInit<'_, D>,
     ) {
+        let _span = crate::utils::log::client_span(&client.id()).entered();
         match request {
             zwp_linux_buffer_params_v1::Request::Destroy => {}
 
@@ -305,6 +309,7 @@ where
                             dh.clone(),
                             dmabuf.clone(),
                             Import::Falliable,
+                            state.resource_audit().cloned(),
                         );
                         state.dmabuf_imported(&DmabufGlobal { id: data.id }, dmabuf, notifier);
                     } else {
@@ -327,11 +332,15 @@ where
                     if state.dmabuf_state().globals.contains_key(&data.id) {
                         // The buffer isn't technically valid during data_init, but the client is not allowed to use the buffer until ready.
                         let buffer = data_init.init(buffer_id, dmabuf.clone());
+                        if let Some(audit) = state.resource_audit() {
+                            audit.created(&client.id(), ResourceKind::Buffer);
+                        }
                         let notifier = ImportNotifier::new(
                             params.clone(),
                             dh.clone(),
                             dmabuf.clone(),
                             Import::Infallible(buffer),
+                            None,
                         );
                         state.dmabuf_imported(&DmabufGlobal { id: data.id }, dmabuf, notifier);
                     } else {
@@ -222,7 +222,11 @@ use crate::{
     utils::{ids::id_gen, SealedFile, UnmanagedResource},
 };
 
-use super::{buffer::BufferHandler, compositor};
+use super::{
+    audit::{ResourceAudit, ResourceKind},
+    buffer::BufferHandler,
+    compositor,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 struct DmabufFeedbackTranche {
@@ -856,6 +860,7 @@ pub struct ImportNotifier {
     display: DisplayHandle,
     dmabuf: Dmabuf,
     import: Import,
+    audit: Option<ResourceAudit>,
     drop_ignore: bool,
 }
 
@@ -898,6 +903,9 @@ impl ImportNotifier {
                     ) {
                         Ok(buffer) => {
                             self.inner.created(&buffer);
+                            if let Some(audit) = &self.audit {
+                                audit.created(&client.id(), ResourceKind::Buffer);
+                            }
                             Ok(buffer)
                         }
 
@@ -966,12 +974,19 @@ impl ImportNotifier {
         self.drop_ignore = true;
     }
 
-    fn new(params: ZwpLinuxBufferParamsV1, display: DisplayHandle, dmabuf: Dmabuf, import: Import) -> Self {
+    fn new(
+        params: ZwpLinuxBufferParamsV1,
+        display: DisplayHandle,
+        dmabuf: Dmabuf,
+        import: Import,
+        audit: Option<ResourceAudit>,
+    ) -> Self {
         Self {
             inner: params,
             display,
             dmabuf,
             import,
+            audit,
             drop_ignore: false,
         }
     }
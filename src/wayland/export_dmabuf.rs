@@ -0,0 +1,555 @@
+//! wlr-export-dmabuf
+//!
+//! This protocol allows privileged clients, like screen recorders and streaming software, to
+//! capture the contents of an output as dmabufs. Ideally the buffer scanned out on the output
+//! is handed over directly, without any copy.
+//!
+//! In order to advertise the global call [`ExportDmabufState::new`] and delegate events to it
+//! with [`delegate_export_dmabuf`](crate::delegate_export_dmabuf). Capture requests are passed
+//! to [`ExportDmabufHandler::capture_frame`] as an [`ExportDmabufFrame`], which should be answered
+//! after the next frame of the output was rendered:
+//!
+//! - with [`ExportDmabufFrame::ready`] passing the dmabuf that was presented, if it can be shared
+//!   with clients,
+//! - otherwise, e.g. for a buffer still in use by the display controller or one that cannot be
+//!   exported, with a copy: blit the rendered framebuffer into a dmabuf owned by the compositor
+//!   with [`copy_to_dmabuf`] and pass that one,
+//! - or with [`ExportDmabufFrame::cancel`] if neither is possible.
+//!
+//! ```
+//! use smithay::delegate_export_dmabuf;
+//! use smithay::wayland::export_dmabuf::{ExportDmabufFrame, ExportDmabufHandler, ExportDmabufState};
+//!
+//! # struct State { pending_frames: Vec<ExportDmabufFrame> }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! ExportDmabufState::new::<State, _>(&display.handle(), |_client| true);
+//!
+//! impl ExportDmabufHandler for State {
+//!     fn capture_frame(&mut self, frame: ExportDmabufFrame) {
+//!         // answer the frame once the next frame of `frame.output()` was rendered
+//!         self.pending_frames.push(frame);
+//!     }
+//! }
+//!
+//! delegate_export_dmabuf!(State);
+//! ```
+
+use std::{os::unix::io::AsFd, time::Duration};
+
+use tracing::warn;
+use wayland_protocols_wlr::export_dmabuf::v1::server::{
+    zwlr_export_dmabuf_frame_v1::{self, CancelReason, ZwlrExportDmabufFrameV1},
+    zwlr_export_dmabuf_manager_v1::{self, ZwlrExportDmabufManagerV1},
+};
+use wayland_server::{
+    backend::GlobalId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, Buffer},
+        renderer::{sync::SyncPoint, Bind, Blit, TextureFilter},
+    },
+    output::Output,
+    utils::{Physical, Rectangle},
+};
+
+/// Handler trait for wlr-export-dmabuf
+pub trait ExportDmabufHandler:
+    GlobalDispatch<ZwlrExportDmabufManagerV1, ExportDmabufGlobalData>
+    + Dispatch<ZwlrExportDmabufManagerV1, ()>
+    + Dispatch<ZwlrExportDmabufFrameV1, ()>
+    + 'static
+{
+    /// A client requested the next frame of an output
+    ///
+    /// The frame has to be answered exactly once, dropping it cancels the capture with
+    /// [`CancelReason::Temporary`].
+    fn capture_frame(&mut self, frame: ExportDmabufFrame);
+}
+
+/// Data of the wlr-export-dmabuf global
+#[allow(missing_debug_implementations)]
+pub struct ExportDmabufGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// State of the wlr-export-dmabuf global
+#[derive(Debug)]
+pub struct ExportDmabufState {
+    global: GlobalId,
+}
+
+impl ExportDmabufState {
+    /// Create a new [`ZwlrExportDmabufManagerV1`] global
+    ///
+    /// As the protocol gives access to the contents of all outputs, the global should only be
+    /// visible to trusted clients, which is decided by `filter`.
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> ExportDmabufState
+    where
+        D: ExportDmabufHandler,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let global = display.create_global::<D, ZwlrExportDmabufManagerV1, _>(
+            1,
+            ExportDmabufGlobalData {
+                filter: Box::new(filter),
+            },
+        );
+        ExportDmabufState { global }
+    }
+
+    /// Returns the [`ZwlrExportDmabufManagerV1`] global id
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+/// A pending frame capture
+#[derive(Debug)]
+pub struct ExportDmabufFrame {
+    frame: ZwlrExportDmabufFrameV1,
+    output: Output,
+    overlay_cursor: bool,
+    answered: bool,
+}
+
+impl ExportDmabufFrame {
+    /// The output to capture
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Whether the cursor should be part of the captured frame
+    pub fn overlay_cursor(&self) -> bool {
+        self.overlay_cursor
+    }
+
+    /// Returns `true` if the client is no longer interested in the frame
+    pub fn is_destroyed(&self) -> bool {
+        !self.frame.is_alive()
+    }
+
+    /// Hand a dmabuf containing the frame over to the client
+    ///
+    /// `offset` is the position of the buffer on the output in physical coordinates, usually
+    /// `(0, 0)`. `transient` tells the client that the buffer is reused by the compositor, like a
+    /// scanout buffer of a swapchain, so its contents are only valid until the next frame.
+    /// `presented` is the presentation time of the frame in the `CLOCK_MONOTONIC` domain.
+    ///
+    /// Fails, after cancelling the frame with [`CancelReason::Temporary`], if the planes of the
+    /// dmabuf cannot be duplicated.
+    pub fn ready(
+        mut self,
+        dmabuf: &Dmabuf,
+        offset: (i32, i32),
+        transient: bool,
+        presented: Duration,
+    ) -> std::io::Result<()> {
+        self.answered = true;
+        if !self.frame.is_alive() {
+            return Ok(());
+        }
+
+        let planes = match dmabuf
+            .0
+            .planes
+            .iter()
+            .map(|plane| {
+                let fd = plane.fd.as_fd().try_clone_to_owned()?;
+                // the duplicate shares the file offset, rewind after querying the size
+                let size = rustix::fs::seek(&fd, rustix::fs::SeekFrom::End(0))?;
+                rustix::fs::seek(&fd, rustix::fs::SeekFrom::Start(0))?;
+                Ok((fd, size as u32, plane))
+            })
+            .collect::<std::io::Result<Vec<_>>>()
+        {
+            Ok(planes) => planes,
+            Err(err) => {
+                warn!("Failed to export dmabuf: {}", err);
+                self.frame.cancel(CancelReason::Temporary);
+                return Err(err);
+            }
+        };
+
+        let size = dmabuf.size();
+        let format = dmabuf.format();
+        let modifier: u64 = format.modifier.into();
+        let flags = if transient {
+            zwlr_export_dmabuf_frame_v1::Flags::Transient
+        } else {
+            zwlr_export_dmabuf_frame_v1::Flags::empty()
+        };
+        self.frame.frame(
+            size.w as u32,
+            size.h as u32,
+            offset.0 as u32,
+            offset.1 as u32,
+            dmabuf.0.flags.bits(),
+            flags,
+            format.code as u32,
+            (modifier >> 32) as u32,
+            modifier as u32,
+            planes.len() as u32,
+        );
+        for (index, (fd, size, plane)) in planes.into_iter().enumerate() {
+            self.frame.object(
+                index as u32,
+                fd.as_fd(),
+                size,
+                plane.offset,
+                plane.stride,
+                plane.plane_idx,
+            );
+        }
+        let secs = presented.as_secs();
+        self.frame
+            .ready((secs >> 32) as u32, secs as u32, presented.subsec_nanos());
+        Ok(())
+    }
+
+    /// Cancel the capture
+    ///
+    /// Use [`CancelReason::Resizing`] if the output changed its mode, and
+    /// [`CancelReason::Permanent`] if the output was removed.
+    pub fn cancel(mut self, reason: CancelReason) {
+        self.answered = true;
+        if self.frame.is_alive() {
+            self.frame.cancel(reason);
+        }
+    }
+}
+
+impl Drop for ExportDmabufFrame {
+    fn drop(&mut self) {
+        if !self.answered && self.frame.is_alive() {
+            self.frame.cancel(CancelReason::Temporary);
+        }
+    }
+}
+
+/// Copy the contents of a framebuffer into a dmabuf for export
+///
+/// This is the fallback for frames, whose buffer cannot be handed to clients directly. `dmabuf`
+/// should be allocated by the compositor with the size of `src` and is best reused between
+/// frames. The copy has finished once the returned [`SyncPoint`] is reached, which should be
+/// awaited before calling [`ExportDmabufFrame::ready`].
+pub fn copy_to_dmabuf<R>(
+    renderer: &mut R,
+    framebuffer: &R::Framebuffer<'_>,
+    src: Rectangle<i32, Physical>,
+    dmabuf: &mut Dmabuf,
+) -> Result<SyncPoint, R::Error>
+where
+    R: Blit + Bind<Dmabuf>,
+{
+    let size = dmabuf.size();
+    let dst = Rectangle::from_size((size.w, size.h).into());
+    let mut target = renderer.bind(dmabuf)?;
+    renderer.blit(framebuffer, &mut target, src, dst, TextureFilter::Linear)
+}
+
+impl<D: ExportDmabufHandler> GlobalDispatch<ZwlrExportDmabufManagerV1, ExportDmabufGlobalData, D>
+    for ExportDmabufState
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrExportDmabufManagerV1>,
+        _global_data: &ExportDmabufGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &ExportDmabufGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ExportDmabufHandler> Dispatch<ZwlrExportDmabufManagerV1, (), D> for ExportDmabufState {
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &ZwlrExportDmabufManagerV1,
+        request: zwlr_export_dmabuf_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_export_dmabuf_manager_v1::Request::CaptureOutput {
+                frame,
+                overlay_cursor,
+                output,
+            } => {
+                let frame = data_init.init(frame, ());
+                match Output::from_resource(&output) {
+                    Some(output) => state.capture_frame(ExportDmabufFrame {
+                        frame,
+                        output,
+                        overlay_cursor: overlay_cursor != 0,
+                        answered: false,
+                    }),
+                    // the output is already gone
+                    None => frame.cancel(CancelReason::Permanent),
+                }
+            }
+            zwlr_export_dmabuf_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ExportDmabufHandler> Dispatch<ZwlrExportDmabufFrameV1, (), D> for ExportDmabufState {
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ZwlrExportDmabufFrameV1,
+        request: zwlr_export_dmabuf_frame_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_export_dmabuf_frame_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Macro to delegate implementation of wlr-export-dmabuf to [`ExportDmabufState`].
+///
+/// You must also implement [`ExportDmabufHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_export_dmabuf {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        const _: () = {
+            use $crate::{
+                reexports::{
+                    wayland_protocols_wlr::export_dmabuf::v1::server::{
+                        zwlr_export_dmabuf_frame_v1::ZwlrExportDmabufFrameV1,
+                        zwlr_export_dmabuf_manager_v1::ZwlrExportDmabufManagerV1,
+                    },
+                    wayland_server::{delegate_dispatch, delegate_global_dispatch},
+                },
+                wayland::export_dmabuf::{ExportDmabufGlobalData, ExportDmabufState},
+            };
+
+            delegate_global_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwlrExportDmabufManagerV1: ExportDmabufGlobalData] => ExportDmabufState
+            );
+
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwlrExportDmabufManagerV1: ()] => ExportDmabufState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwlrExportDmabufFrameV1: ()] => ExportDmabufState
+            );
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delegate_export_dmabuf_macro() {
+        struct State;
+        delegate_export_dmabuf!(State);
+
+        // `ExportDmabufHandler` can only be implemented if the macro works
+        impl ExportDmabufHandler for State {
+            fn capture_frame(&mut self, frame: ExportDmabufFrame) {
+                frame.cancel(CancelReason::Permanent);
+            }
+        }
+        fn is_delegated<T: ExportDmabufHandler>() {}
+        is_delegated::<State>();
+    }
+
+    #[cfg(unix)]
+    mod protocol {
+        use std::sync::Arc;
+
+        use wayland_server::{
+            backend::{ClientData, ClientId, DisconnectReason},
+            protocol::wl_output::WlOutput,
+            Display,
+        };
+
+        use super::*;
+        use crate::{
+            backend::allocator::{
+                dmabuf::{Dmabuf, DmabufFlags},
+                Fourcc, Modifier,
+            },
+            output::{PhysicalProperties, Subpixel},
+            wayland::{
+                output::OutputHandler,
+                test::{Arg, Event, FakeClient},
+            },
+        };
+
+        struct State {
+            frames: Vec<ExportDmabufFrame>,
+        }
+
+        impl ExportDmabufHandler for State {
+            fn capture_frame(&mut self, frame: ExportDmabufFrame) {
+                self.frames.push(frame);
+            }
+        }
+
+        impl OutputHandler for State {}
+
+        delegate_export_dmabuf!(State);
+        crate::delegate_output!(State);
+
+        struct ClientState;
+
+        impl ClientData for ClientState {
+            fn initialized(&self, _client_id: ClientId) {}
+            fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+        }
+
+        struct Setup {
+            display: Display<State>,
+            state: State,
+            fake: FakeClient,
+            output: Output,
+        }
+
+        impl Setup {
+            fn new() -> Setup {
+                let mut display = Display::<State>::new().unwrap();
+                ExportDmabufState::new::<State, _>(&display.handle(), |_| true);
+                let output = Output::new(
+                    "test".into(),
+                    PhysicalProperties {
+                        size: (0, 0).into(),
+                        subpixel: Subpixel::Unknown,
+                        make: "test".into(),
+                        model: "test".into(),
+                        serial_number: "test".into(),
+                    },
+                );
+                output.create_global::<State>(&display.handle());
+
+                let (mut fake, _) =
+                    FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+                fake.record_events(true);
+                fake.get_registry().unwrap();
+                let mut setup = Setup {
+                    display,
+                    state: State { frames: Vec::new() },
+                    fake,
+                    output,
+                };
+                setup.roundtrip();
+                setup
+            }
+
+            fn roundtrip(&mut self) -> Vec<Event> {
+                self.fake.roundtrip(&mut self.display, &mut self.state).unwrap()
+            }
+
+            // sends capture_output and returns the id of the frame
+            fn capture(&mut self, overlay_cursor: bool) -> u32 {
+                let manager = self
+                    .fake
+                    .bind_global(ZwlrExportDmabufManagerV1::interface(), 1)
+                    .unwrap();
+                let output = self.fake.bind_global(WlOutput::interface(), 4).unwrap();
+                let frame = self.fake.new_id(ZwlrExportDmabufFrameV1::interface());
+                self.fake
+                    .send_request(
+                        manager,
+                        ZwlrExportDmabufManagerV1::interface(),
+                        "capture_output",
+                        vec![
+                            Arg::NewId(frame),
+                            Arg::Int(overlay_cursor as i32),
+                            Arg::Object(output),
+                        ],
+                    )
+                    .unwrap();
+                self.roundtrip();
+                frame
+            }
+        }
+
+        fn names(events: &[Event], object: u32) -> Vec<&'static str> {
+            events
+                .iter()
+                .filter(|event| event.object == object)
+                .map(|event| event.name().unwrap())
+                .collect()
+        }
+
+        #[test]
+        fn capture_reaches_handler() {
+            let mut setup = Setup::new();
+            setup.capture(true);
+            let frame = setup.state.frames.pop().unwrap();
+            assert_eq!(frame.output(), &setup.output);
+            assert!(frame.overlay_cursor());
+            assert!(!frame.is_destroyed());
+        }
+
+        #[test]
+        fn dropped_frames_are_cancelled() {
+            let mut setup = Setup::new();
+            let frame = setup.capture(false);
+            setup.state.frames.clear();
+            let events = setup.roundtrip();
+            assert_eq!(names(&events, frame), ["cancel"]);
+            let cancel = events.iter().find(|event| event.object == frame).unwrap();
+            assert!(matches!(
+                cancel.args.as_slice(),
+                [Arg::Uint(reason)] if *reason == CancelReason::Temporary as u32
+            ));
+        }
+
+        #[test]
+        fn ready_exports_planes() {
+            let mut setup = Setup::new();
+            let frame = setup.capture(false);
+
+            let file = tempfile::tempfile().unwrap();
+            file.set_len(16 * 8 * 4).unwrap();
+            let mut builder =
+                Dmabuf::builder((16, 8), Fourcc::Argb8888, Modifier::Linear, DmabufFlags::empty());
+            builder.add_plane(file.into(), 0, 0, 16 * 4);
+            let dmabuf = builder.build().unwrap();
+
+            let captured = setup.state.frames.pop().unwrap();
+            captured
+                .ready(&dmabuf, (0, 0), true, Duration::from_secs(1))
+                .unwrap();
+            let events = setup.roundtrip();
+            assert_eq!(names(&events, frame), ["frame", "object", "ready"]);
+
+            let object = events
+                .iter()
+                .find(|event| event.name() == Some("object"))
+                .unwrap();
+            assert!(matches!(
+                object.args.as_slice(),
+                [
+                    Arg::Uint(0),
+                    Arg::Fd(_),
+                    Arg::Uint(512),
+                    Arg::Uint(0),
+                    Arg::Uint(64),
+                    Arg::Uint(0)
+                ]
+            ));
+        }
+    }
+}
@@ -61,6 +61,10 @@
 //! });
 //! ```
 //!
+//! When using the [desktop](crate::desktop) helpers, [`Window::signal_frame_barriers`](crate::desktop::Window::signal_frame_barriers)
+//! and [`signal_frame_barriers_surface_tree`](crate::desktop::utils::signal_frame_barriers_surface_tree)
+//! do this for every refresh cycle of the output a surface is presented on.
+//!
 //! ### Unmanaged mode
 //!
 //! If for some reason the integrated solution for fifo does not suit your needs
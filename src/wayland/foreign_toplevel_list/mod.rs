@@ -280,6 +280,15 @@ impl ForeignToplevelHandle {
     }
 }
 
+impl PartialEq for ForeignToplevelHandle {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for ForeignToplevelHandle {}
+
 /// State of the [ExtForeignToplevelListV1] global
 #[derive(Debug)]
 pub struct ForeignToplevelListState {
@@ -367,7 +376,7 @@ impl ForeignToplevelListState {
             .toplevels
             .iter()
             .filter_map(|h| h.upgrade())
-            .position(|h| Arc::ptr_eq(&h.inner, &handle.inner))
+            .position(|h| h == *handle)
         {
             self.toplevels.remove(pos);
         }
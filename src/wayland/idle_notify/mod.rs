@@ -38,12 +38,16 @@
 //! // On input you should notify the idle_notifier
 //! // state.idle_notifier.notify_activity(&seat);
 //! ```
+//!
+//! Timeouts are measured with calloop timers by default. Tests can replace them with a
+//! [`ManualClock`](crate::utils::ManualClock) using [`IdleNotifierState::set_time_source`] and
+//! call [`IdleNotifierState::check_timeouts`] after advancing it.
 
 use std::{
     collections::HashMap,
     sync::{
         atomic::{self, AtomicBool},
-        Mutex,
+        Arc, Mutex,
     },
     time::Duration,
 };
@@ -59,7 +63,10 @@ use wayland_server::{
     Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
 };
 
-use crate::input::{Seat, SeatHandler};
+use crate::{
+    input::{Seat, SeatHandler},
+    utils::{Clock, Monotonic, Time, TimeSource},
+};
 
 /// Handler trait for ext-idle-notify
 pub trait IdleNotifierHandler: Sized {
@@ -74,6 +81,7 @@ pub struct IdleNotificationUserData {
     is_idle: AtomicBool,
     timeout: Duration,
     timer_token: Mutex<Option<RegistrationToken>>,
+    last_activity: Mutex<Time<Monotonic>>,
 
     /// If listener was created with `get_input_idle_notification`
     ignore_inhibitor: bool,
@@ -95,6 +103,14 @@ impl IdleNotificationUserData {
     fn is_idle(&self) -> bool {
         self.is_idle.load(atomic::Ordering::Acquire)
     }
+
+    fn set_last_activity(&self, now: Time<Monotonic>) {
+        *self.last_activity.lock().unwrap() = now;
+    }
+
+    fn timed_out(&self, now: Time<Monotonic>) -> bool {
+        now.saturating_duration_since(*self.last_activity.lock().unwrap()) >= self.timeout
+    }
 }
 
 /// State of ext-idle-notify module
@@ -104,6 +120,7 @@ pub struct IdleNotifierState<D> {
     notifications: HashMap<WlSeat, Vec<ExtIdleNotificationV1>>,
     loop_handle: LoopHandle<'static, D>,
     is_inhibited: bool,
+    time_source: Option<Arc<dyn TimeSource>>,
 }
 
 impl<D: IdleNotifierHandler> IdleNotifierState<D> {
@@ -122,6 +139,32 @@ impl<D: IdleNotifierHandler> IdleNotifierState<D> {
             notifications: HashMap::new(),
             loop_handle,
             is_inhibited: false,
+            time_source: None,
+        }
+    }
+
+    /// Measure timeouts with the given source of time instead of calloop timers
+    ///
+    /// No timers are inserted into the event loop for notifications created or reset afterwards,
+    /// so [`IdleNotifierState::check_timeouts`] has to be called whenever the time advanced.
+    pub fn set_time_source(&mut self, time_source: Arc<dyn TimeSource>) {
+        self.time_source = Some(time_source);
+    }
+
+    /// Send the idle event for all notifications whose timeout elapsed
+    ///
+    /// This is only required after [`IdleNotifierState::set_time_source`], otherwise the timers
+    /// in the event loop take care of it.
+    pub fn check_timeouts(&mut self) {
+        let now = self.now();
+        for notification in self.notifications() {
+            let data = notification.data::<IdleNotificationUserData>().unwrap();
+
+            let is_inhibited = !data.ignore_inhibitor && self.is_inhibited;
+            if !is_inhibited && !data.is_idle() && data.timed_out(now) {
+                notification.idled();
+                data.set_idle(true);
+            }
         }
     }
 
@@ -189,6 +232,13 @@ impl<D: IdleNotifierHandler> IdleNotifierState<D> {
         self.notifications.values().flatten()
     }
 
+    fn now(&self) -> Time<Monotonic> {
+        match &self.time_source {
+            Some(time_source) => time_source.now(),
+            None => Clock::<Monotonic>::new().now(),
+        }
+    }
+
     fn reinsert_timer(&self, notification: &ExtIdleNotificationV1) {
         let data = notification.data::<IdleNotificationUserData>().unwrap();
 
@@ -200,6 +250,11 @@ impl<D: IdleNotifierHandler> IdleNotifierState<D> {
             return;
         }
 
+        data.set_last_activity(self.now());
+        if self.time_source.is_some() {
+            return;
+        }
+
         let token = self
             .loop_handle
             .insert_source(calloop::timer::Timer::from_duration(data.timeout), {
@@ -285,6 +340,7 @@ where
                         is_idle: AtomicBool::new(false),
                         timeout,
                         timer_token: Mutex::new(None),
+                        last_activity: Mutex::new(idle_notifier_state.now()),
                         ignore_inhibitor: false,
                     },
                 );
@@ -310,6 +366,7 @@ where
                         is_idle: AtomicBool::new(false),
                         timeout,
                         timer_token: Mutex::new(None),
+                        last_activity: Mutex::new(idle_notifier_state.now()),
                         ignore_inhibitor: true,
                     },
                 );
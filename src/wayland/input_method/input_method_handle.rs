@@ -3,7 +3,6 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use tracing::warn;
 use wayland_protocols_misc::zwp_input_method_v2::server::{
     zwp_input_method_keyboard_grab_v2::ZwpInputMethodKeyboardGrabV2,
     zwp_input_method_v2::{self, ZwpInputMethodV2},
@@ -15,9 +14,15 @@ use wayland_server::{
 };
 
 use crate::{
+    backend::renderer::buffer_dimensions,
     input::{keyboard::KeyboardHandle, SeatHandler},
-    utils::{alive_tracker::AliveTracker, Logical, Rectangle, SERIAL_COUNTER},
-    wayland::{compositor, seat::WaylandFocus, text_input::TextInputHandle},
+    utils::{alive_tracker::AliveTracker, Logical, Rectangle, Size, SERIAL_COUNTER},
+    wayland::{
+        compositor::{self, BufferAssignment, SurfaceAttributes},
+        seat::WaylandFocus,
+        text_input::TextInputHandle,
+        viewporter::ViewportCachedState,
+    },
 };
 
 use super::{
@@ -129,6 +134,7 @@ impl InputMethodHandle {
                 if let Some(popup) = im.popup_handle.surface.as_mut() {
                     let data = instance.object.data::<InputMethodUserData<D>>().unwrap();
                     let location = (data.popup_geometry_callback)(state, surface);
+                    popup.set_bounds((data.popup_bounds_callback)(state, surface));
                     // Remove old popup.
                     (data.dismiss_popup)(state, popup.clone());
 
@@ -164,12 +170,31 @@ impl InputMethodHandle {
     }
 }
 
+// logical size of a newly committed buffer, with the current state already applied
+fn committed_size(surface: &WlSurface) -> Option<Size<i32, Logical>> {
+    compositor::with_states(surface, |states| {
+        let mut attrs = states.cached_state.get::<SurfaceAttributes>();
+        let attrs = attrs.current();
+        match attrs.buffer.as_ref()? {
+            BufferAssignment::NewBuffer(buffer) => {
+                let viewport = states.cached_state.get::<ViewportCachedState>().current().size();
+                viewport.or_else(|| {
+                    buffer_dimensions(buffer)
+                        .map(|size| size.to_logical(attrs.buffer_scale, attrs.buffer_transform.into()))
+                })
+            }
+            BufferAssignment::Removed => Some(Size::default()),
+        }
+    })
+}
+
 /// User data of ZwpInputMethodV2 object
 pub struct InputMethodUserData<D: SeatHandler> {
     pub(super) handle: InputMethodHandle,
     pub(crate) text_input_handle: TextInputHandle,
     pub(crate) keyboard_handle: KeyboardHandle<D>,
     pub(crate) popup_geometry_callback: fn(&D, &WlSurface) -> Rectangle<i32, Logical>,
+    pub(crate) popup_bounds_callback: fn(&D, &WlSurface) -> Option<Rectangle<i32, Logical>>,
     pub(crate) new_popup: fn(&mut D, PopupSurface),
     pub(crate) popup_repositioned: fn(&mut D, PopupSurface),
     pub(crate) dismiss_popup: fn(&mut D, PopupSurface),
@@ -268,8 +293,31 @@ where
                     },
                 );
                 let popup_rect = Arc::new(Mutex::new(input_method.popup_handle.rectangle));
-                let popup = PopupSurface::new(instance, surface, popup_rect, parent);
+                let bounds = parent
+                    .as_ref()
+                    .and_then(|parent| state.popup_bounds(&parent.surface));
+                let popup = PopupSurface::new(instance, surface.clone(), popup_rect, parent);
+                popup.set_bounds(bounds);
                 input_method.popup_handle.surface = Some(popup.clone());
+                drop(input_method);
+
+                // keep the popup next to the cursor while its contents change size
+                let handle = data.handle.clone();
+                compositor::add_post_commit_hook::<D, _>(&surface, move |state, _dh, surface| {
+                    let Some(size) = committed_size(surface) else {
+                        return;
+                    };
+                    let popup = handle.inner.lock().unwrap().popup_handle.surface.clone();
+                    let Some(popup) = popup.filter(|popup| popup.wl_surface() == surface) else {
+                        return;
+                    };
+                    if popup.size() != size {
+                        popup.set_size(size);
+                        if popup.get_parent().is_some() {
+                            state.popup_repositioned(popup);
+                        }
+                    }
+                });
                 if popup.get_parent().is_some() {
                     state.new_popup(popup);
                 }
@@ -299,7 +347,11 @@ where
                 });
 
                 if let Err(err) = res {
-                    warn!(err = ?err, "Failed to send keymap to client");
+                    crate::client_warn!(
+                        instance.client().map(|client| client.id()),
+                        err = ?err,
+                        "Failed to send keymap to client"
+                    );
                 } else {
                     // Modifiers can be latched when taking the grab, thus we must send them to keep
                     // them in sync.
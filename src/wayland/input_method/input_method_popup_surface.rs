@@ -7,7 +7,7 @@ use wayland_server::{backend::ClientId, protocol::wl_surface::WlSurface, Dispatc
 
 use crate::utils::{
     alive_tracker::{AliveTracker, IsAlive},
-    Logical, Point, Rectangle,
+    Logical, Point, Rectangle, Size,
 };
 
 use super::InputMethodManagerState;
@@ -29,6 +29,12 @@ pub struct PopupSurface {
     pub(crate) rectangle: Arc<Mutex<Rectangle<i32, Logical>>>,
     /// Location of the popup surface.
     location: Arc<Mutex<Point<i32, Logical>>>,
+    /// Size of the last committed buffer.
+    size: Arc<Mutex<Size<i32, Logical>>>,
+    /// Area the popup is kept within.
+    bounds: Arc<Mutex<Option<Rectangle<i32, Logical>>>>,
+    /// Text input rectangle last sent to the client, in popup-local coordinates.
+    sent_rectangle: Arc<Mutex<Option<Rectangle<i32, Logical>>>>,
     /// Current parent of the IME popup.
     parent: Option<PopupParent>,
 }
@@ -40,11 +46,14 @@ impl PopupSurface {
         rectangle: Arc<Mutex<Rectangle<i32, Logical>>>,
         parent: Option<PopupParent>,
     ) -> Self {
-        let location = Arc::new(Mutex::new(rectangle.lock().unwrap().loc));
+        let location = place_popup(*rectangle.lock().unwrap(), Size::default(), None);
         Self {
             surface_role,
             rectangle,
-            location,
+            location: Arc::new(Mutex::new(location)),
+            size: Arc::default(),
+            bounds: Arc::default(),
+            sent_rectangle: Arc::default(),
             surface,
             parent,
         }
@@ -82,29 +91,118 @@ impl PopupSurface {
     /// Set location of the popup surface relative to the parent. The primary use for this function
     /// is to adjust the popup during rendering.
     ///
-    /// Setting this value **won't update** the [`text_input_rectangle`].
+    /// Setting this value **won't update** the [`text_input_rectangle`], but the location is
+    /// recomputed once the rectangle, the size or the bounds of the popup change. The client is
+    /// told where the text input is relative to the new location.
     ///
     /// [`text_input_rectangle`]: Self::text_input_rectangle
     pub fn set_location(&self, location: Point<i32, Logical>) {
         *self.location.lock().unwrap() = location;
+        self.send_text_input_rectangle();
+    }
+
+    /// Size of the popup as of its last committed buffer
+    pub fn size(&self) -> Size<i32, Logical> {
+        *self.size.lock().unwrap()
+    }
+
+    pub(crate) fn set_size(&self, size: Size<i32, Logical>) {
+        *self.size.lock().unwrap() = size;
+        self.update_location();
+    }
+
+    /// The area relative to the parent the popup is kept within
+    pub fn bounds(&self) -> Option<Rectangle<i32, Logical>> {
+        *self.bounds.lock().unwrap()
+    }
+
+    /// Set the area relative to the parent the popup is kept within, usually the output the
+    /// parent is shown on
+    ///
+    /// This is initialized from [`InputMethodHandler::popup_bounds`](super::InputMethodHandler::popup_bounds)
+    /// whenever the popup gets a parent. Setting this value **will update** the [`location`].
+    ///
+    /// [`location`]: Self::location
+    pub fn set_bounds(&self, bounds: Option<Rectangle<i32, Logical>>) {
+        *self.bounds.lock().unwrap() = bounds;
+        self.update_location();
+    }
+
+    fn update_location(&self) {
+        let location = place_popup(self.text_input_rectangle(), self.size(), self.bounds());
+        *self.location.lock().unwrap() = location;
+        self.send_text_input_rectangle();
+    }
+
+    // the protocol expects the rectangle relative to the popup, which moves with its placement
+    fn send_text_input_rectangle(&self) {
+        let rectangle = popup_local_rectangle(self.text_input_rectangle(), self.location());
+        let mut sent = self.sent_rectangle.lock().unwrap();
+        if *sent != Some(rectangle) {
+            *sent = Some(rectangle);
+            self.surface_role.text_input_rectangle(
+                rectangle.loc.x,
+                rectangle.loc.y,
+                rectangle.size.w,
+                rectangle.size.h,
+            );
+        }
     }
 
     /// The region compositor shouldn't obscure when placing the popup within the
-    /// client.
+    /// client, relative to the parent.
     pub fn text_input_rectangle(&self) -> Rectangle<i32, Logical> {
         *self.rectangle.lock().unwrap()
     }
 
-    /// Set relative location of text cursor.
+    /// Set location of text cursor relative to the parent.
     ///
-    /// Setting this value **will update** the [`location`] to the new `x` and `y`.
+    /// Setting this value **will update** the [`location`] to place the popup next to the new
+    /// rectangle, see [`place_popup`]. The client receives the rectangle in popup-local
+    /// coordinates.
     ///
     /// [`location`]: Self::location
     pub fn set_text_input_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32) {
         *self.rectangle.lock().unwrap() = Rectangle::new((x, y).into(), (width, height).into());
-        *self.location.lock().unwrap() = (x, y).into();
-        self.surface_role.text_input_rectangle(x, y, width, height);
+        self.update_location();
+    }
+}
+
+fn popup_local_rectangle(
+    rectangle: Rectangle<i32, Logical>,
+    location: Point<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    Rectangle::new(rectangle.loc - location, rectangle.size)
+}
+
+/// Compute the location of an input method popup relative to its parent
+///
+/// Candidate windows are placed below the text cursor `rectangle`, aligned to its left edge. If
+/// the popup would leave `bounds` it is flipped above the cursor, given there is enough space,
+/// and slid horizontally to stay inside. The cursor is never obscured unless the popup is larger
+/// than the space on either side.
+pub fn place_popup(
+    rectangle: Rectangle<i32, Logical>,
+    size: Size<i32, Logical>,
+    bounds: Option<Rectangle<i32, Logical>>,
+) -> Point<i32, Logical> {
+    let mut location = Point::from((rectangle.loc.x, rectangle.loc.y + rectangle.size.h));
+    let Some(bounds) = bounds else {
+        return location;
+    };
+
+    let bottom = bounds.loc.y + bounds.size.h;
+    if location.y + size.h > bottom {
+        let above = rectangle.loc.y - size.h;
+        if above >= bounds.loc.y {
+            location.y = above;
+        } else {
+            location.y = (bottom - size.h).max(bounds.loc.y);
+        }
     }
+    let right = bounds.loc.x + bounds.size.w;
+    location.x = location.x.min(right - size.w).max(bounds.loc.x);
+    location
 }
 
 impl std::cmp::PartialEq for PopupSurface {
@@ -156,3 +254,54 @@ impl<D> Dispatch<ZwpInputPopupSurfaceV2, InputMethodPopupSurfaceUserData, D> for
         data.alive_tracker.destroy_notify();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placement() {
+        let bounds = Some(Rectangle::from_size((1000, 800).into()));
+        let size = Size::from((200, 100));
+
+        // below the cursor
+        let cursor = Rectangle::new((100, 100).into(), (2, 20).into());
+        assert_eq!(place_popup(cursor, size, bounds), Point::from((100, 120)));
+        assert_eq!(place_popup(cursor, size, None), Point::from((100, 120)));
+
+        // flipped above the cursor at the bottom
+        let cursor = Rectangle::new((100, 750).into(), (2, 20).into());
+        assert_eq!(place_popup(cursor, size, bounds), Point::from((100, 650)));
+
+        // slid inside at the right edge
+        let cursor = Rectangle::new((950, 100).into(), (2, 20).into());
+        assert_eq!(place_popup(cursor, size, bounds), Point::from((800, 120)));
+
+        // too tall for either side
+        let cursor = Rectangle::new((100, 50).into(), (2, 20).into());
+        let size = Size::from((200, 780));
+        assert_eq!(place_popup(cursor, size, bounds), Point::from((100, 20)));
+    }
+
+    #[test]
+    fn local_rectangle() {
+        let bounds = Some(Rectangle::from_size((1000, 800).into()));
+        let size = Size::from((200, 100));
+
+        // the cursor is right above a popup placed below it
+        let cursor = Rectangle::new((100, 100).into(), (2, 20).into());
+        let location = place_popup(cursor, size, bounds);
+        assert_eq!(
+            popup_local_rectangle(cursor, location),
+            Rectangle::new((0, -20).into(), (2, 20).into())
+        );
+
+        // and below a popup flipped above it
+        let cursor = Rectangle::new((100, 750).into(), (2, 20).into());
+        let location = place_popup(cursor, size, bounds);
+        assert_eq!(
+            popup_local_rectangle(cursor, location),
+            Rectangle::new((0, 100).into(), (2, 20).into())
+        );
+    }
+}
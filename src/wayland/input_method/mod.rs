@@ -94,7 +94,7 @@ pub const INPUT_POPUP_SURFACE_ROLE: &str = "zwp_input_popup_surface_v2";
 mod input_method_handle;
 mod input_method_keyboard_grab;
 mod input_method_popup_surface;
-pub use input_method_popup_surface::{PopupParent, PopupSurface};
+pub use input_method_popup_surface::{place_popup, PopupParent, PopupSurface};
 
 /// Adds input method popup to compositor state
 pub trait InputMethodHandler {
@@ -109,6 +109,16 @@ pub trait InputMethodHandler {
 
     /// Sets the parent location so the popup surface can be placed correctly
     fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, Logical>;
+
+    /// The area relative to `parent` popups should be kept within, usually the output the parent
+    /// is shown on
+    ///
+    /// Popups are placed next to the text cursor automatically, see [`place_popup`]. Without
+    /// bounds they are always placed below the cursor.
+    fn popup_bounds(&self, parent: &WlSurface) -> Option<Rectangle<i32, Logical>> {
+        let _ = parent;
+        None
+    }
 }
 
 /// Extends [Seat] with input method functionality
@@ -222,6 +232,7 @@ where
                         text_input_handle: text_input_handle.clone(),
                         keyboard_handle,
                         popup_geometry_callback: D::parent_geometry,
+                        popup_bounds_callback: D::popup_bounds,
                         popup_repositioned: D::popup_repositioned,
                         new_popup: D::new_popup,
                         dismiss_popup: D::dismiss_popup,
@@ -202,6 +202,16 @@ pub trait KeyboardShortcutsInhibitorSeat {
         &self,
         surface: &WlSurface,
     ) -> Option<KeyboardShortcutsInhibitor>;
+
+    /// Check if keyboard shortcuts are inhibited by an active inhibitor of the given WlSurface
+    ///
+    /// Inhibitors only apply while their surface has keyboard focus, so this should be
+    /// queried with the focused surface before matching compositor keybindings,
+    /// see [`Keybindings`](crate::input::keyboard::Keybindings).
+    fn keyboard_shortcuts_inhibited_for_surface(&self, surface: &WlSurface) -> bool {
+        self.keyboard_shortcuts_inhibitor_for_surface(surface)
+            .is_some_and(|inhibitor| inhibitor.is_active())
+    }
 }
 
 impl<D> KeyboardShortcutsInhibitorSeat for Seat<D>
@@ -48,16 +48,20 @@
 //!
 
 pub mod alpha_modifier;
+pub mod audit;
 pub mod buffer;
 pub mod commit_timing;
 pub mod compositor;
 pub mod content_type;
 pub mod cursor_shape;
+pub mod custom;
+pub mod delegate;
 pub mod dmabuf;
 #[cfg(feature = "backend_drm")]
 pub mod drm_lease;
 #[cfg(feature = "backend_drm")]
 pub mod drm_syncobj;
+pub mod export_dmabuf;
 pub mod fifo;
 pub mod foreign_toplevel_list;
 pub mod fractional_scale;
@@ -66,11 +70,14 @@ pub mod idle_notify;
 pub mod input_method;
 pub mod keyboard_shortcuts_inhibit;
 pub mod output;
+#[cfg(feature = "parallel_dispatch")]
+pub mod parallel;
 pub mod pointer_constraints;
 pub mod pointer_gestures;
 pub mod pointer_warp;
 pub mod presentation;
 pub mod relative_pointer;
+pub mod replay;
 pub mod seat;
 pub mod security_context;
 pub mod selection;
@@ -80,9 +87,14 @@ pub mod shm;
 pub mod single_pixel_buffer;
 pub mod socket;
 pub mod tablet_manager;
+#[cfg(all(unix, any(feature = "wayland_test", test)))]
+pub mod test;
 pub mod text_input;
+pub mod toplevel_export;
+pub mod trace;
 pub mod viewporter;
 pub mod virtual_keyboard;
+pub mod workspace;
 pub mod xdg_activation;
 pub mod xdg_foreign;
 pub mod xdg_system_bell;
@@ -0,0 +1,514 @@
+//! Per-client dispatch of expensive request handling on a thread pool
+//!
+//! Requests are read from the client sockets and deserialized by the wayland backend, which
+//! does so under its own lock and always on the thread calling
+//! [`Display::dispatch_clients`](wayland_server::Display::dispatch_clients). What usually makes
+//! dispatching slow with many chatty clients is not the parsing but the validation following
+//! it: checking shm pools, inspecting dmabufs, decoding cursor images or icons, etc.
+//!
+//! A [`DispatchPool`] lets request handlers move such work off the main thread. Every job
+//! consists of two parts:
+//!
+//! - the work itself, which has to be `Send` and runs on a worker thread without access to the
+//!   compositor state,
+//! - a completion, which is marshaled back and applied to the compositor state on the thread
+//!   running the event loop, once the [`DispatchPoolSource`] is dispatched.
+//!
+//! Jobs of the same client run one after another and their completions are delivered in the
+//! order the jobs were spawned, so the protocol semantics of a single client are preserved.
+//! Jobs of different clients run in parallel. The compositor state itself never has to be
+//! `Send` or `Sync`.
+//!
+//! Every job is identified by a [`JobId`], whose completion is tracked in a
+//! [`FenceTable`], so the compositor can check for or [wait](DispatchPool::wait) on a job that
+//! has to finish before it can go on, e.g. before applying a commit. Jobs reading a client
+//! buffer are spawned with [`DispatchPool::spawn_using`], so the buffer is only released once
+//! the job is done with it.
+//!
+//! ```no_run
+//! # extern crate smithay;
+//! use smithay::wayland::parallel::DispatchPool;
+//!
+//! struct State {
+//!     pool: DispatchPool<State>,
+//! #   validated: Vec<usize>,
+//! }
+//!
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<State>::try_new().unwrap();
+//! let (pool, source) = DispatchPool::<State>::new(4).unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(source, |completion, _, state| completion.apply(state))
+//!     .unwrap();
+//!
+//! # fn handler(state: &mut State, client: smithay::reexports::wayland_server::backend::ClientId, data: Vec<u8>) {
+//! // from a request handler of `client`
+//! state.pool.spawn(&client, move || {
+//!     let size = data.len(); // some expensive validation
+//!     move |state: &mut State| state.validated.push(size)
+//! });
+//! # }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    hash::Hash,
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::error;
+use wayland_server::backend::ClientId;
+
+use crate::{
+    compat::notifier::{self, Notifier, NotifierSource},
+    utils::sync::{FenceTable, ReleaseTracker},
+};
+
+type Job<D> = Box<dyn FnOnce() -> Box<dyn FnOnce(&mut D) + Send> + Send>;
+
+/// Identifies a job spawned on a [`DispatchPool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(u64);
+
+/// Result of a job, to be applied to the compositor state
+pub struct Completion<D> {
+    client: ClientId,
+    job: JobId,
+    apply: Box<dyn FnOnce(&mut D) + Send>,
+}
+
+impl<D> fmt::Debug for Completion<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Completion")
+            .field("client", &self.client)
+            .field("job", &self.job)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D> Completion<D> {
+    /// Returns the job this is the result of
+    pub fn job(&self) -> JobId {
+        self.job
+    }
+
+    /// Returns the client that spawned the job
+    ///
+    /// The client may have disconnected in the meantime.
+    pub fn client(&self) -> &ClientId {
+        &self.client
+    }
+
+    /// Apply the result of the job to the compositor state
+    pub fn apply(self, state: &mut D) {
+        (self.apply)(state)
+    }
+}
+
+// Jobs grouped by key, handing out the jobs of a key only one at a time
+#[derive(Debug)]
+struct Scheduler<K, J> {
+    pending: HashMap<K, VecDeque<J>>,
+    // keys with pending jobs and no job running
+    ready: VecDeque<K>,
+    busy: HashSet<K>,
+    shutdown: bool,
+}
+
+impl<K: Hash + Eq + Clone, J> Scheduler<K, J> {
+    fn new() -> Self {
+        Scheduler {
+            pending: HashMap::new(),
+            ready: VecDeque::new(),
+            busy: HashSet::new(),
+            shutdown: false,
+        }
+    }
+
+    // returns whether a worker has to be woken up
+    fn push(&mut self, key: &K, job: J) -> bool {
+        let queue = self.pending.entry(key.clone()).or_default();
+        queue.push_back(job);
+        if queue.len() == 1 && !self.busy.contains(key) {
+            self.ready.push_back(key.clone());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pop(&mut self) -> Option<(K, J)> {
+        let key = self.ready.pop_front()?;
+        let job = self.pending.get_mut(&key).and_then(VecDeque::pop_front)?;
+        self.busy.insert(key.clone());
+        Some((key, job))
+    }
+
+    // returns whether a worker has to be woken up
+    fn done(&mut self, key: &K) -> bool {
+        self.busy.remove(key);
+        match self.pending.get(key) {
+            Some(queue) if !queue.is_empty() => {
+                self.ready.push_back(key.clone());
+                true
+            }
+            _ => {
+                self.pending.remove(key);
+                false
+            }
+        }
+    }
+
+    fn cancel(&mut self, key: &K) {
+        self.pending.remove(key);
+        self.ready.retain(|k| k != key);
+    }
+
+    fn len(&self, key: &K) -> usize {
+        self.pending.get(key).map(VecDeque::len).unwrap_or(0) + usize::from(self.busy.contains(key))
+    }
+}
+
+struct Shared<D> {
+    scheduler: Mutex<Scheduler<ClientId, (JobId, Job<D>)>>,
+    condvar: Condvar,
+    completions: Mutex<VecDeque<Completion<D>>>,
+    // signaled once a job ran or was dropped
+    fences: FenceTable<JobId>,
+    notifier: Notifier,
+}
+
+impl<D> Shared<D> {
+    fn work(&self) {
+        let mut scheduler = self.scheduler.lock().unwrap();
+        loop {
+            if scheduler.shutdown {
+                return;
+            }
+            let Some((client, (id, job))) = scheduler.pop() else {
+                scheduler = self.condvar.wait(scheduler).unwrap();
+                continue;
+            };
+            drop(scheduler);
+
+            match panic::catch_unwind(AssertUnwindSafe(job)) {
+                Ok(apply) => {
+                    self.completions.lock().unwrap().push_back(Completion {
+                        client: client.clone(),
+                        job: id,
+                        apply,
+                    });
+                    self.notifier.notify();
+                }
+                Err(_) => error!(?client, "Dispatch job panicked, dropping its completion"),
+            }
+
+            scheduler = self.scheduler.lock().unwrap();
+            if scheduler.done(&client) {
+                self.condvar.notify_one();
+            }
+        }
+    }
+}
+
+/// Thread pool running jobs of clients, see the [module-level documentation](self)
+///
+/// The worker threads exit once the pool is dropped, after finishing their current job.
+pub struct DispatchPool<D> {
+    shared: Arc<Shared<D>>,
+    threads: usize,
+    next_job: AtomicU64,
+}
+
+impl<D> fmt::Debug for DispatchPool<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatchPool")
+            .field("threads", &self.threads)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: 'static> DispatchPool<D> {
+    /// Create a new pool with the given number of worker threads
+    ///
+    /// Completions are delivered through the returned [`DispatchPoolSource`].
+    pub fn new(threads: usize) -> io::Result<(DispatchPool<D>, DispatchPoolSource<D>)> {
+        let (notifier, source) = notifier::new()?;
+        let shared = Arc::new(Shared {
+            scheduler: Mutex::new(Scheduler::new()),
+            condvar: Condvar::new(),
+            completions: Mutex::new(VecDeque::new()),
+            fences: FenceTable::new(),
+            notifier,
+        });
+
+        for i in 0..threads.max(1) {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("smithay-dispatch-{}", i))
+                .spawn(move || shared.work())?;
+        }
+
+        Ok((
+            DispatchPool {
+                shared: shared.clone(),
+                threads: threads.max(1),
+                next_job: AtomicU64::new(0),
+            },
+            DispatchPoolSource { source, shared },
+        ))
+    }
+
+    /// Returns the number of worker threads
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Spawn a job on behalf of a client
+    ///
+    /// `work` runs on a worker thread after all previously spawned jobs of the client finished.
+    /// The closure it returns is applied to the compositor state on the event loop thread.
+    pub fn spawn<W, C>(&self, client: &ClientId, work: W) -> JobId
+    where
+        W: FnOnce() -> C + Send + 'static,
+        C: FnOnce(&mut D) + Send + 'static,
+    {
+        let id = JobId(self.next_job.fetch_add(1, Ordering::Relaxed));
+        // dropped with the job if it panics or is cancelled
+        let signaler = self.shared.fences.insert(id);
+        let job: Job<D> = Box::new(move || {
+            let apply = work();
+            signaler.signal();
+            Box::new(apply) as Box<dyn FnOnce(&mut D) + Send>
+        });
+        if self.shared.scheduler.lock().unwrap().push(client, (id, job)) {
+            self.shared.condvar.notify_one();
+        }
+        id
+    }
+
+    /// Spawn a job using a resource tracked by a [`ReleaseTracker`], e.g. reading a client buffer
+    ///
+    /// The job holds a [`ReleaseGuard`](crate::utils::sync::ReleaseGuard) of the resource until
+    /// `work` returned, so the resource is not released while the job still uses it. If the
+    /// job is the last user, the release callback runs on the worker thread.
+    pub fn spawn_using<W, C>(&self, client: &ClientId, tracker: &ReleaseTracker, work: W) -> JobId
+    where
+        W: FnOnce() -> C + Send + 'static,
+        C: FnOnce(&mut D) + Send + 'static,
+    {
+        let guard = tracker.acquire();
+        self.spawn(client, move || {
+            let apply = work();
+            drop(guard);
+            apply
+        })
+    }
+
+    /// Returns whether a job finished running or was cancelled
+    ///
+    /// The [`Completion`] of a finished job may not have been delivered yet.
+    pub fn is_finished(&self, job: JobId) -> bool {
+        self.shared.fences.is_signaled(&job) != Some(false)
+    }
+
+    /// Block until a job finished running or was cancelled
+    ///
+    /// The [`Completion`] of the job is still delivered through the [`DispatchPoolSource`].
+    pub fn wait(&self, job: JobId) {
+        self.shared.fences.wait(&job);
+    }
+
+    /// Returns the number of jobs of a client not yet finished
+    pub fn pending(&self, client: &ClientId) -> usize {
+        self.shared.scheduler.lock().unwrap().len(client)
+    }
+
+    /// Drop all jobs of a client not yet started, e.g. after it disconnected
+    ///
+    /// A job already running still delivers its [`Completion`].
+    pub fn cancel(&self, client: &ClientId) {
+        self.shared.scheduler.lock().unwrap().cancel(client);
+        self.shared
+            .completions
+            .lock()
+            .unwrap()
+            .retain(|completion| &completion.client != client);
+    }
+}
+
+impl<D> Drop for DispatchPool<D> {
+    fn drop(&mut self) {
+        self.shared.scheduler.lock().unwrap().shutdown = true;
+        self.shared.condvar.notify_all();
+    }
+}
+
+/// Event source delivering the [`Completion`]s of a [`DispatchPool`]
+pub struct DispatchPoolSource<D> {
+    source: NotifierSource,
+    shared: Arc<Shared<D>>,
+}
+
+impl<D> fmt::Debug for DispatchPoolSource<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatchPoolSource")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D> EventSource for DispatchPoolSource<D> {
+    type Event = Completion<D>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let shared = &self.shared;
+        self.source.process_events(readiness, token, |_, _| {
+            // the fences of finished jobs are no longer needed, jobs without fence are finished
+            shared.fences.take_signaled();
+            let completions = std::mem::take(&mut *shared.completions.lock().unwrap());
+            for completion in completions {
+                callback(completion, &mut ());
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduler_serializes_keys() {
+        let mut scheduler = Scheduler::<u32, u32>::new();
+        assert!(scheduler.push(&1, 10));
+        assert!(!scheduler.push(&1, 11));
+        assert!(scheduler.push(&2, 20));
+
+        assert_eq!(scheduler.pop(), Some((1, 10)));
+        assert_eq!(scheduler.pop(), Some((2, 20)));
+        // the second job of key 1 waits for the first one
+        assert_eq!(scheduler.pop(), None);
+        assert_eq!(scheduler.len(&1), 2);
+
+        assert!(scheduler.done(&1));
+        assert_eq!(scheduler.pop(), Some((1, 11)));
+        assert!(!scheduler.done(&1));
+        assert!(!scheduler.done(&2));
+        assert_eq!(scheduler.len(&1), 0);
+        assert!(scheduler.pending.is_empty());
+    }
+
+    #[test]
+    fn scheduler_cancel() {
+        let mut scheduler = Scheduler::<u32, u32>::new();
+        scheduler.push(&1, 10);
+        scheduler.push(&1, 11);
+        scheduler.cancel(&1);
+        assert_eq!(scheduler.pop(), None);
+
+        // cancelling a running key keeps it busy until done
+        scheduler.push(&2, 20);
+        scheduler.push(&2, 21);
+        assert_eq!(scheduler.pop(), Some((2, 20)));
+        scheduler.cancel(&2);
+        assert_eq!(scheduler.len(&2), 1);
+        assert!(!scheduler.done(&2));
+        assert_eq!(scheduler.pop(), None);
+    }
+
+    #[cfg(all(unix, not(loom)))]
+    #[test]
+    fn jobs_release_buffers_and_signal_fences() {
+        use std::{
+            sync::atomic::AtomicBool,
+            time::{Duration, Instant},
+        };
+
+        use calloop::EventLoop;
+        use wayland_server::{
+            backend::{ClientData, DisconnectReason},
+            Display,
+        };
+
+        use crate::wayland::test::FakeClient;
+
+        struct ClientState;
+
+        impl ClientData for ClientState {
+            fn initialized(&self, _client_id: ClientId) {}
+            fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+        }
+
+        let display = Display::<()>::new().unwrap();
+        let (_fake, client) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+        let mut event_loop = EventLoop::<Vec<JobId>>::try_new().unwrap();
+        let (pool, source) = DispatchPool::<Vec<JobId>>::new(2).unwrap();
+        event_loop
+            .handle()
+            .insert_source(source, |completion, _, state| {
+                state.push(completion.job());
+                completion.apply(state);
+            })
+            .unwrap();
+
+        let released = Arc::new(AtomicBool::new(false));
+        let flag = released.clone();
+        let buffer = ReleaseTracker::new(move || flag.store(true, Ordering::SeqCst));
+        let (start, started) = std::sync::mpsc::channel::<()>();
+        let job = pool.spawn_using(&client.id(), &buffer, move || {
+            started.recv().unwrap();
+            |_: &mut Vec<JobId>| {}
+        });
+        // the buffer is still used by the job
+        drop(buffer);
+        assert!(!pool.is_finished(job));
+        assert!(!released.load(Ordering::SeqCst));
+
+        start.send(()).unwrap();
+        pool.wait(job);
+        assert!(pool.is_finished(job));
+        assert!(released.load(Ordering::SeqCst));
+
+        let mut state = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while state.is_empty() && Instant::now() < deadline {
+            event_loop
+                .dispatch(Some(Duration::from_millis(100)), &mut state)
+                .unwrap();
+        }
+        assert_eq!(state, [job]);
+        assert!(pool.is_finished(job));
+    }
+}
@@ -75,6 +75,9 @@ pub trait PointerWarpHandler:
     ///
     /// Note that the enter serial is valid for any surface of the client, and does not have to be from the surface the pointer is warped to.
     ///
+    /// Accepted requests can be applied with [`PointerHandle::warp`](crate::input::pointer::PointerHandle::warp)
+    /// after translating `pos` into the global compositor space.
+    ///
     /// * `serial` - serial number of the surface enter event
     #[allow(unused)]
     fn warp_pointer(
@@ -0,0 +1,855 @@
+//! Recording and replaying of input and client requests
+//!
+//! Bugs reported by users often depend on the exact sequence of input events and requests of
+//! the clients involved. A [`Recorder`] collects both while the compositor runs: input events
+//! of any backend, converted to [`InjectedEvent`]s, and the requests traced by a
+//! [`ProtocolTrace`](super::trace::ProtocolTrace). The resulting [`Recording`] is written to a
+//! line based text file, which can be attached to a bug report and edited by hand.
+//!
+//! A client can only be replayed if all of its requests were recorded, starting with the first
+//! one after connecting. The recorder therefore receives the requests directly from the trace,
+//! instead of reading its ring buffer, and clients connected before are left out.
+//!
+//! ```no_run
+//! use smithay::backend::input::{InputBackend, InputEvent};
+//! use smithay::wayland::{replay::Recorder, trace::ProtocolTrace};
+//!
+//! fn process_input<B: InputBackend>(recorder: &mut Recorder, event: InputEvent<B>) {
+//!     recorder.record_input(&event);
+//!     // handle the event
+//! }
+//!
+//! let trace = ProtocolTrace::new(0);
+//! let mut recorder = Recorder::new();
+//! recorder.record_trace(&trace);
+//! // ... connect clients through the trace, until the bug was reproduced
+//! let file = std::fs::File::create("bug.replay").unwrap();
+//! recorder.finish().write(file).unwrap();
+//! ```
+//!
+//! With the `wayland_test` feature, a `Replay` feeds a recording back into a
+//! [`Display`](wayland_server::Display), e.g. of a compositor running on the headless backend:
+//! requests are sent by a `FakeClient` per recorded client and input events are handed to the
+//! compositor as events of the [`InjectedInput`] backend, timestamped with their recorded time.
+//! Every request is dispatched before the next event is replayed, so the replay does not
+//! depend on timing and can be used as a regression test.
+//!
+//! The contents of file descriptors are not recorded. They are replayed as zeroed files, sized
+//! by the largest integer argument of their request, which covers `wl_shm.create_pool`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::warn;
+
+use crate::{
+    backend::input::{
+        inject::{InjectedEvent, InjectedInput},
+        Axis, ButtonState, InputBackend, InputEvent, KeyState,
+    },
+    utils::{Clock, Monotonic, Time},
+};
+
+use super::trace::{Direction, ProtocolTrace, TraceArgument, TraceMessage};
+
+const HEADER: &str = "smithay-replay 1";
+
+/// Errors reading a [`Recording`]
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    /// Reading the recording failed
+    #[error("Failed to read the recording: {0}")]
+    Io(#[from] io::Error),
+    /// The recording is malformed
+    #[error("Invalid recording in line {line}: {reason}")]
+    Invalid {
+        /// Line of the recording, starting at 1
+        line: usize,
+        /// Description of the problem
+        reason: &'static str,
+    },
+}
+
+/// Event of a [`Recording`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedEvent {
+    /// An input event
+    Input {
+        /// Time since the start of the recording
+        time: Duration,
+        /// The event
+        event: InjectedEvent,
+    },
+    /// A request of a client
+    Request {
+        /// Time since the start of the recording
+        time: Duration,
+        /// Id of the client, see [`ClientTrace::id`](super::trace::ClientTrace::id)
+        client: u64,
+        /// Id of the object the request was sent to
+        object: u32,
+        /// Opcode of the request
+        opcode: u16,
+        /// Arguments of the request
+        args: Vec<TraceArgument>,
+    },
+}
+
+impl RecordedEvent {
+    /// Time of the event since the start of the recording
+    pub fn time(&self) -> Duration {
+        match self {
+            RecordedEvent::Input { time, .. } | RecordedEvent::Request { time, .. } => *time,
+        }
+    }
+}
+
+/// Recorded input events and requests, ordered by time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    /// The recorded events
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Write the recording in its text format
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        for event in &self.events {
+            write!(writer, "{}", event.time().as_micros())?;
+            match event {
+                RecordedEvent::Input { event, .. } => {
+                    write!(writer, " input ")?;
+                    match event {
+                        InjectedEvent::Key { key, state } => {
+                            write!(writer, "key {} {}", key.raw(), key_state_name(*state))?
+                        }
+                        InjectedEvent::PointerMotion { delta } => {
+                            write!(writer, "motion {} {}", delta.x, delta.y)?
+                        }
+                        InjectedEvent::PointerMotionAbsolute { position } => {
+                            write!(writer, "absolute {} {}", position.x, position.y)?
+                        }
+                        InjectedEvent::PointerButton { button, state } => {
+                            write!(writer, "button {} {}", button, button_state_name(*state))?
+                        }
+                        InjectedEvent::PointerAxis { axis, v120 } => {
+                            let axis = match axis {
+                                Axis::Vertical => "vertical",
+                                Axis::Horizontal => "horizontal",
+                            };
+                            write!(writer, "axis {} {}", axis, v120)?
+                        }
+                    }
+                }
+                RecordedEvent::Request {
+                    client,
+                    object,
+                    opcode,
+                    args,
+                    ..
+                } => {
+                    write!(writer, " request {} {} {}", client, object, opcode)?;
+                    for arg in args {
+                        match arg {
+                            TraceArgument::Int(value) => write!(writer, " i:{}", value)?,
+                            TraceArgument::Uint(value) => write!(writer, " u:{}", value)?,
+                            TraceArgument::Fixed(value) => write!(writer, " f:{}", value)?,
+                            TraceArgument::Str(None) => write!(writer, " s:-")?,
+                            TraceArgument::Str(Some(value)) => {
+                                write!(writer, " s:{}", hex(value.as_bytes()))?
+                            }
+                            TraceArgument::Object { id, .. } => write!(writer, " o:{}", id)?,
+                            TraceArgument::NewId { id, .. } => write!(writer, " n:{}", id)?,
+                            TraceArgument::Array(array) => write!(writer, " a:{}", hex(array))?,
+                            TraceArgument::Fd => write!(writer, " fd")?,
+                        }
+                    }
+                }
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Read a recording written by [`Recording::write`]
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn read(reader: impl BufRead) -> Result<Recording, ReplayError> {
+        let mut lines = reader.lines().enumerate();
+        let header = lines.next().map(|(_, line)| line).transpose()?;
+        if header.as_deref().map(str::trim) != Some(HEADER) {
+            return Err(ReplayError::Invalid {
+                line: 1,
+                reason: "missing header",
+            });
+        }
+
+        let mut events = Vec::new();
+        for (idx, line) in lines {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let event = parse_event(line).map_err(|reason| ReplayError::Invalid {
+                line: idx + 1,
+                reason,
+            })?;
+            events.push(event);
+        }
+        Ok(Recording { events })
+    }
+}
+
+fn key_state_name(state: KeyState) -> &'static str {
+    match state {
+        KeyState::Pressed => "pressed",
+        KeyState::Released => "released",
+    }
+}
+
+fn button_state_name(state: ButtonState) -> &'static str {
+    match state {
+        ButtonState::Pressed => "pressed",
+        ButtonState::Released => "released",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(string: &str) -> Option<Vec<u8>> {
+    if string.len() % 2 != 0 {
+        return None;
+    }
+    (0..string.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(string.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_event(line: &str) -> Result<RecordedEvent, &'static str> {
+    fn next<'a, T: std::str::FromStr>(
+        words: &mut impl Iterator<Item = &'a str>,
+        reason: &'static str,
+    ) -> Result<T, &'static str> {
+        words.next().and_then(|word| word.parse().ok()).ok_or(reason)
+    }
+
+    let mut words = line.split_ascii_whitespace();
+    let time = Duration::from_micros(next(&mut words, "invalid time")?);
+    match words.next() {
+        Some("input") => {
+            let event = match words.next() {
+                Some("key") => {
+                    let key = next::<u32>(&mut words, "invalid key")?.into();
+                    let state = match words.next() {
+                        Some("pressed") => KeyState::Pressed,
+                        Some("released") => KeyState::Released,
+                        _ => return Err("invalid key state"),
+                    };
+                    InjectedEvent::Key { key, state }
+                }
+                Some("motion") => InjectedEvent::PointerMotion {
+                    delta: (
+                        next::<f64>(&mut words, "invalid delta")?,
+                        next::<f64>(&mut words, "invalid delta")?,
+                    )
+                        .into(),
+                },
+                Some("absolute") => InjectedEvent::PointerMotionAbsolute {
+                    position: (
+                        next::<f64>(&mut words, "invalid position")?,
+                        next::<f64>(&mut words, "invalid position")?,
+                    )
+                        .into(),
+                },
+                Some("button") => {
+                    let button = next(&mut words, "invalid button")?;
+                    let state = match words.next() {
+                        Some("pressed") => ButtonState::Pressed,
+                        Some("released") => ButtonState::Released,
+                        _ => return Err("invalid button state"),
+                    };
+                    InjectedEvent::PointerButton { button, state }
+                }
+                Some("axis") => {
+                    let axis = match words.next() {
+                        Some("vertical") => Axis::Vertical,
+                        Some("horizontal") => Axis::Horizontal,
+                        _ => return Err("invalid axis"),
+                    };
+                    let v120 = next(&mut words, "invalid scroll amount")?;
+                    InjectedEvent::PointerAxis { axis, v120 }
+                }
+                _ => return Err("unknown input event"),
+            };
+            Ok(RecordedEvent::Input { time, event })
+        }
+        Some("request") => {
+            let client = next(&mut words, "invalid client")?;
+            let object = next(&mut words, "invalid object")?;
+            let opcode = next(&mut words, "invalid opcode")?;
+            let args = words
+                .map(|word| {
+                    let (kind, value) = word.split_once(':').unwrap_or((word, ""));
+                    let arg = match kind {
+                        "i" => TraceArgument::Int(value.parse().ok()?),
+                        "u" => TraceArgument::Uint(value.parse().ok()?),
+                        "f" => TraceArgument::Fixed(value.parse().ok()?),
+                        "s" if value == "-" => TraceArgument::Str(None),
+                        "s" => TraceArgument::Str(Some(String::from_utf8(unhex(value)?).ok()?)),
+                        "o" => TraceArgument::Object {
+                            id: value.parse().ok()?,
+                            interface: None,
+                        },
+                        "n" => TraceArgument::NewId {
+                            id: value.parse().ok()?,
+                            interface: None,
+                        },
+                        "a" => TraceArgument::Array(unhex(value)?),
+                        "fd" => TraceArgument::Fd,
+                        _ => return None,
+                    };
+                    Some(arg)
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or("invalid argument")?;
+            Ok(RecordedEvent::Request {
+                time,
+                client,
+                object,
+                opcode,
+                args,
+            })
+        }
+        _ => Err("unknown event"),
+    }
+}
+
+/// Collects input events and requests into a [`Recording`]
+#[derive(Debug)]
+pub struct Recorder {
+    clock: Clock<Monotonic>,
+    start: Time<Monotonic>,
+    events: Vec<RecordedEvent>,
+    clients: HashMap<u64, ClientRecording>,
+    traced: Option<Arc<Mutex<Vec<TraceMessage>>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClientRecording {
+    /// All requests so far were recorded, `next` is the sequence number of the next one
+    Complete { next: u64 },
+    /// Requests are missing, the client cannot be replayed
+    Incomplete,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// Start a new recording
+    pub fn new() -> Recorder {
+        let clock = Clock::new();
+        let start = clock.now();
+        Recorder {
+            clock,
+            start,
+            events: Vec::new(),
+            clients: HashMap::new(),
+            traced: None,
+        }
+    }
+
+    /// Record the requests traced from now on
+    ///
+    /// Only clients connecting afterwards are recorded, see the
+    /// [module-level documentation](self).
+    pub fn record_trace(&mut self, trace: &ProtocolTrace) {
+        let traced = Arc::downgrade(self.traced.get_or_insert_with(Default::default));
+        trace.add_sink(move |message| {
+            let Some(traced) = traced.upgrade() else {
+                return false;
+            };
+            if message.direction == Direction::Request {
+                traced.lock().unwrap().push(message.clone());
+            }
+            true
+        });
+    }
+
+    /// Record an input event of any backend
+    ///
+    /// Events that cannot be injected, see [`InjectedEvent::from_input_event`], are skipped.
+    pub fn record_input<B: InputBackend>(&mut self, event: &InputEvent<B>) {
+        for event in InjectedEvent::from_input_event(event) {
+            self.record_injected(event);
+        }
+    }
+
+    /// Record an injected input event
+    pub fn record_injected(&mut self, event: InjectedEvent) {
+        let time = self.clock.now().saturating_duration_since(self.start);
+        self.events.push(RecordedEvent::Input { time, event });
+    }
+
+    /// Record the requests of traced messages, events sent by the compositor are skipped
+    ///
+    /// Messages of objects with an unknown interface cannot be replayed and are skipped as well.
+    /// Clients are left out entirely, if their requests are not complete from the start of their
+    /// connection on, e.g. because older messages were dropped from the ring buffer of the trace.
+    pub fn record_messages(&mut self, messages: impl IntoIterator<Item = TraceMessage>) {
+        for message in messages {
+            if message.direction != Direction::Request {
+                continue;
+            }
+            let client = message.client;
+            let recording = self
+                .clients
+                .entry(client)
+                .or_insert(ClientRecording::Complete { next: 0 });
+            match recording {
+                ClientRecording::Complete { next } if *next == message.sequence => *next += 1,
+                ClientRecording::Complete { .. } => {
+                    *recording = ClientRecording::Incomplete;
+                    self.events.retain(
+                        |event| !matches!(event, RecordedEvent::Request { client: c, .. } if *c == client),
+                    );
+                    warn!(
+                        client,
+                        "Requests of the client are missing, leaving it out of the recording"
+                    );
+                    continue;
+                }
+                ClientRecording::Incomplete => continue,
+            }
+            if message.name.is_none() {
+                continue;
+            }
+            self.events.push(RecordedEvent::Request {
+                time: message.time.saturating_duration_since(self.start),
+                client: message.client,
+                object: message.object,
+                opcode: message.opcode,
+                args: message.args,
+            });
+        }
+    }
+
+    /// Finish the recording, ordering the events by time
+    pub fn finish(mut self) -> Recording {
+        if let Some(traced) = self.traced.take() {
+            let messages = std::mem::take(&mut *traced.lock().unwrap());
+            self.record_messages(messages);
+        }
+        self.events.sort_by_key(RecordedEvent::time);
+        Recording { events: self.events }
+    }
+}
+
+#[cfg(all(unix, any(feature = "wayland_test", test)))]
+pub use self::driver::{Replay, ReplayHandler};
+
+#[cfg(all(unix, any(feature = "wayland_test", test)))]
+mod driver {
+    use std::{
+        collections::{hash_map::Entry, HashMap, VecDeque},
+        io,
+        sync::Arc,
+    };
+
+    use wayland_server::{backend::ClientData, Display};
+
+    use super::{RecordedEvent, Recording};
+    use crate::{
+        backend::input::{
+            inject::{self, InjectedDevice, InjectedInput},
+            InputEvent, Keycode,
+        },
+        wayland::{
+            test::{Arg, FakeClient},
+            trace::TraceArgument,
+        },
+    };
+
+    /// Callbacks of the compositor under test during a [`Replay`]
+    pub trait ReplayHandler<D> {
+        /// Data of a new client connected for the recorded client with the given id
+        fn client_data(&mut self, client: u64) -> Arc<dyn ClientData>;
+
+        /// Process a replayed input event
+        fn input(&mut self, state: &mut D, event: InputEvent<InjectedInput>);
+    }
+
+    /// Driver feeding a [`Recording`] into a display, see the [module-level documentation](super)
+    #[derive(Debug)]
+    pub struct Replay {
+        events: VecDeque<RecordedEvent>,
+        clients: HashMap<u64, FakeClient>,
+        key_counts: HashMap<Keycode, u32>,
+        announced: bool,
+    }
+
+    impl Replay {
+        /// Create a replay of the recording
+        pub fn new(recording: Recording) -> Replay {
+            Replay {
+                events: recording.events.into(),
+                clients: HashMap::new(),
+                key_counts: HashMap::new(),
+                announced: false,
+            }
+        }
+
+        /// Returns whether all events were replayed
+        pub fn is_finished(&self) -> bool {
+            self.events.is_empty()
+        }
+
+        /// Replay the next event
+        ///
+        /// Requests are dispatched and the replies flushed before returning. Returns `false`
+        /// once all events were replayed.
+        pub fn step<D: 'static>(
+            &mut self,
+            display: &mut Display<D>,
+            state: &mut D,
+            handler: &mut impl ReplayHandler<D>,
+        ) -> io::Result<bool> {
+            let Some(event) = self.events.pop_front() else {
+                return Ok(false);
+            };
+
+            match event {
+                RecordedEvent::Input { time, event } => {
+                    if !self.announced {
+                        self.announced = true;
+                        handler.input(
+                            state,
+                            InputEvent::DeviceAdded {
+                                device: InjectedDevice,
+                            },
+                        );
+                    }
+                    let time = time.as_micros() as u64;
+                    handler.input(state, inject::input_event(event, time, &mut self.key_counts));
+                }
+                RecordedEvent::Request {
+                    client,
+                    object,
+                    opcode,
+                    args,
+                    ..
+                } => {
+                    let fake_client = match self.clients.entry(client) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let (fake_client, _) =
+                                FakeClient::connect(&mut display.handle(), handler.client_data(client))?;
+                            entry.insert(fake_client)
+                        }
+                    };
+                    fake_client.send(object, opcode, replay_args(args)?)?;
+                }
+            }
+
+            display.dispatch_clients(state)?;
+            display.flush_clients()?;
+            let mut disconnected = Vec::new();
+            for (id, client) in self.clients.iter_mut() {
+                if !client.receive()? {
+                    disconnected.push(*id);
+                }
+            }
+            for id in disconnected {
+                self.clients.remove(&id);
+            }
+            Ok(!self.events.is_empty())
+        }
+
+        /// Replay all remaining events
+        pub fn run<D: 'static>(
+            &mut self,
+            display: &mut Display<D>,
+            state: &mut D,
+            handler: &mut impl ReplayHandler<D>,
+        ) -> io::Result<()> {
+            while self.step(display, state, handler)? {}
+            Ok(())
+        }
+    }
+
+    fn replay_args(args: Vec<TraceArgument>) -> io::Result<Vec<Arg>> {
+        let size = args
+            .iter()
+            .filter_map(|arg| match arg {
+                TraceArgument::Int(value) => u64::try_from(*value).ok(),
+                TraceArgument::Uint(value) => Some(*value as u64),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        args.into_iter()
+            .map(|arg| {
+                Ok(match arg {
+                    TraceArgument::Int(value) => Arg::Int(value),
+                    TraceArgument::Uint(value) => Arg::Uint(value),
+                    TraceArgument::Fixed(value) => Arg::Fixed((value * 256.0).round() as i32),
+                    TraceArgument::Str(value) => Arg::Str(value.map(String::into_bytes)),
+                    TraceArgument::Object { id, .. } => Arg::Object(id),
+                    TraceArgument::NewId { id, .. } => Arg::NewId(id),
+                    TraceArgument::Array(array) => Arg::Array(array),
+                    TraceArgument::Fd => {
+                        let file = tempfile::tempfile()?;
+                        file.set_len(size)?;
+                        Arg::Fd(file.into())
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface},
+        Client, Display, Resource,
+    };
+
+    use super::{RecordedEvent, Recorder, Recording, Replay, ReplayHandler};
+    use crate::{
+        backend::input::{
+            inject::{InjectedEvent, InjectedInput},
+            Axis, InputEvent, KeyState,
+        },
+        utils::{Clock, Monotonic},
+        wayland::{
+            compositor::{CompositorClientState, CompositorHandler, CompositorState},
+            test::{Arg, FakeClient},
+            trace::{Direction, ProtocolTrace, TraceArgument, TraceMessage},
+        },
+    };
+
+    fn recording() -> Recording {
+        Recording {
+            events: vec![
+                RecordedEvent::Request {
+                    time: Duration::from_micros(10),
+                    client: 1,
+                    object: 1,
+                    opcode: 1,
+                    args: vec![TraceArgument::NewId {
+                        id: 2,
+                        interface: None,
+                    }],
+                },
+                RecordedEvent::Input {
+                    time: Duration::from_micros(250),
+                    event: InjectedEvent::Key {
+                        key: 38.into(),
+                        state: KeyState::Pressed,
+                    },
+                },
+                RecordedEvent::Input {
+                    time: Duration::from_micros(300),
+                    event: InjectedEvent::PointerAxis {
+                        axis: Axis::Vertical,
+                        v120: -120.5,
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn text_format_roundtrip() {
+        let mut file = Vec::new();
+        recording().write(&mut file).unwrap();
+        let read = Recording::read(&file[..]).unwrap();
+        assert_eq!(read, recording());
+
+        let invalid = b"smithay-replay 1\n12 request 1 x\n";
+        assert!(Recording::read(&invalid[..]).is_err());
+    }
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    #[derive(Default)]
+    struct Handler {
+        clients: usize,
+        keys: Vec<u64>,
+    }
+
+    impl ReplayHandler<()> for Handler {
+        fn client_data(&mut self, _client: u64) -> Arc<dyn ClientData> {
+            self.clients += 1;
+            Arc::new(ClientState)
+        }
+
+        fn input(&mut self, _state: &mut (), event: InputEvent<InjectedInput>) {
+            if let InputEvent::Keyboard { event } = event {
+                self.keys.push(crate::backend::input::Event::time(&event));
+            }
+        }
+    }
+
+    #[test]
+    fn replays_in_order() {
+        let mut display = Display::<()>::new().unwrap();
+        let mut handler = Handler::default();
+        let mut replay = Replay::new(recording());
+        replay.run(&mut display, &mut (), &mut handler).unwrap();
+        assert!(replay.is_finished());
+        assert_eq!(handler.clients, 1);
+        assert_eq!(handler.keys, vec![250]);
+    }
+
+    #[test]
+    fn skips_clients_with_missing_requests() {
+        let now = Clock::<Monotonic>::new().now();
+        let sync = |client, sequence| TraceMessage {
+            time: now,
+            client,
+            direction: Direction::Request,
+            sequence,
+            object: 1,
+            interface: Some("wl_display"),
+            opcode: 0,
+            name: Some("sync"),
+            args: vec![TraceArgument::NewId {
+                id: 2 + sequence as u32,
+                interface: Some("wl_callback"),
+            }],
+        };
+
+        // the first request of client 2 was dropped, client 3 lost one in between
+        let mut recorder = Recorder::new();
+        recorder.record_messages([
+            sync(1, 0),
+            sync(2, 1),
+            sync(3, 0),
+            sync(1, 1),
+            sync(3, 2),
+            sync(3, 3),
+        ]);
+        let clients = recorder
+            .finish()
+            .events
+            .iter()
+            .map(|event| match event {
+                RecordedEvent::Request { client, .. } => *client,
+                RecordedEvent::Input { .. } => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(clients, [1, 1]);
+    }
+
+    struct State {
+        compositor: CompositorState,
+        surfaces: usize,
+    }
+
+    #[derive(Default)]
+    struct CompositorClient {
+        compositor: CompositorClientState,
+    }
+
+    impl ClientData for CompositorClient {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<CompositorClient>().unwrap().compositor
+        }
+
+        fn new_surface(&mut self, _surface: &WlSurface) {
+            self.surfaces += 1;
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    crate::delegate_compositor!(State);
+
+    impl ReplayHandler<State> for Handler {
+        fn client_data(&mut self, _client: u64) -> Arc<dyn ClientData> {
+            self.clients += 1;
+            Arc::new(CompositorClient::default())
+        }
+
+        fn input(&mut self, _state: &mut State, _event: InputEvent<InjectedInput>) {}
+    }
+
+    fn compositor() -> (Display<State>, State) {
+        let display = Display::<State>::new().unwrap();
+        let state = State {
+            compositor: CompositorState::new_v6::<State>(&display.handle()),
+            surfaces: 0,
+        };
+        (display, state)
+    }
+
+    #[test]
+    fn replays_traced_clients() {
+        let trace = ProtocolTrace::new(0);
+        let mut recorder = Recorder::new();
+        recorder.record_trace(&trace);
+
+        let (mut display, mut state) = compositor();
+        let (mut client, _) =
+            FakeClient::connect(&mut display.handle(), Arc::new(CompositorClient::default())).unwrap();
+        client.set_trace(Some(trace.client()));
+        let mut roundtrip = |client: &mut FakeClient| {
+            display.dispatch_clients(&mut state).unwrap();
+            display.flush_clients().unwrap();
+            client.receive().unwrap();
+        };
+        client.get_registry().unwrap();
+        roundtrip(&mut client);
+        let compositor = client.bind_global(WlCompositor::interface(), 6).unwrap();
+        let surface = client.new_id(WlSurface::interface());
+        // wl_compositor.create_surface
+        client.send(compositor, 0, vec![Arg::NewId(surface)]).unwrap();
+        roundtrip(&mut client);
+        assert_eq!(state.surfaces, 1);
+
+        let mut file = Vec::new();
+        recorder.finish().write(&mut file).unwrap();
+        let recording = Recording::read(&file[..]).unwrap();
+        assert_eq!(recording.events.len(), 3);
+
+        let (mut display, mut state) = compositor();
+        let mut handler = Handler::default();
+        Replay::new(recording)
+            .run(&mut display, &mut state, &mut handler)
+            .unwrap();
+        assert_eq!(handler.clients, 1);
+        assert_eq!(state.surfaces, 1);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, fmt};
 
-use tracing::{instrument, trace, warn};
+use tracing::{instrument, trace};
 use wayland_server::{
     backend::{ClientId, ObjectId},
     protocol::{
@@ -63,7 +63,8 @@ where
         let ret = keymap_file.send(&kbd);
 
         if let Err(e) = ret {
-            warn!(
+            crate::client_warn!(
+                kbd.client().map(|client| client.id()),
                 err = ?e,
                 "Failed write keymap to client in a tempfile"
             );
@@ -20,6 +20,7 @@ use crate::input::{
     Seat,
 };
 use crate::utils::{alive_tracker::AliveTracker, IsAlive};
+use crate::wayland::selection::mime::offer_mime_type;
 use crate::wayland::selection::offer::OfferReplySource;
 use crate::wayland::selection::seat_data::SeatData;
 use crate::wayland::selection::source::SelectionSourceProvider;
@@ -63,7 +64,7 @@ where
 
         match request {
             wl_data_source::Request::Offer { mime_type } => {
-                data.mime_types.push(mime_type);
+                offer_mime_type(&mut data.mime_types, mime_type);
             }
             wl_data_source::Request::SetActions { dnd_actions } => match dnd_actions {
                 wayland_server::WEnum::Value(dnd_actions) => {
@@ -5,6 +5,7 @@ use wayland_server::backend::ClientId;
 use wayland_server::{Dispatch, DisplayHandle};
 
 use crate::input::Seat;
+use crate::wayland::selection::mime::offer_mime_type;
 use crate::wayland::selection::offer::OfferReplySource;
 use crate::wayland::selection::seat_data::SeatData;
 use crate::wayland::selection::source::SelectionSourceProvider;
@@ -57,7 +58,7 @@ where
         match request {
             ext_data_control_source_v1::Request::Offer { mime_type } => {
                 let mut data = data.inner.lock().unwrap();
-                data.mime_types.push(mime_type);
+                offer_mime_type(&mut data.mime_types, mime_type);
             }
             ext_data_control_source_v1::Request::Destroy => (),
             _ => unreachable!(),
@@ -0,0 +1,253 @@
+//! Mime type negotiation and selection bridging helpers
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use crate::utils::{system_time_source, Monotonic, Time, TimeSource};
+
+/// Text mime types in the order of preference, used by [`preferred_mime_type`]
+///
+/// Covers the types announced by Wayland clients as well as the atoms used by X11 clients
+/// through Xwayland.
+pub const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+fn essence(mime_type: &str) -> &str {
+    mime_type.split(';').next().unwrap_or_default().trim()
+}
+
+fn matches(pattern: &str, mime_type: &str) -> bool {
+    if pattern == "*" || pattern == "*/*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(major) => essence(mime_type)
+            .split('/')
+            .next()
+            .is_some_and(|m| m.eq_ignore_ascii_case(major)),
+        None => pattern.eq_ignore_ascii_case(mime_type),
+    }
+}
+
+/// Pick the offered mime type best matching the given preferences
+///
+/// `preferences` are ordered from most to least preferred and may contain wildcards like
+/// `image/*` or `*`. Among offered types matching the same preference the first offered one
+/// wins, as clients announce their native format first.
+///
+/// ```
+/// use smithay::wayland::selection::{preferred_mime_type, TEXT_MIME_TYPES};
+///
+/// let offered = ["text/html", "text/plain", "UTF8_STRING"].map(String::from);
+/// assert_eq!(preferred_mime_type(&offered, TEXT_MIME_TYPES), Some("UTF8_STRING"));
+/// assert_eq!(preferred_mime_type(&offered, &["image/*"]), None);
+/// ```
+pub fn preferred_mime_type<'a, S: AsRef<str>>(offered: &'a [S], preferences: &[&str]) -> Option<&'a str> {
+    preferences.iter().find_map(|pattern| {
+        offered
+            .iter()
+            .map(AsRef::as_ref)
+            .find(|mime_type| matches(pattern, mime_type))
+    })
+}
+
+/// Remove duplicated mime types, keeping the order of their first occurrence
+pub fn dedup_mime_types(mime_types: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    mime_types
+        .into_iter()
+        .filter(|mime_type| seen.insert(mime_type.clone()))
+        .collect()
+}
+
+/// Add a mime type announced by a selection source, ignoring duplicates
+///
+/// Clients announcing a type twice would otherwise make it show up twice in every offer.
+pub(crate) fn offer_mime_type(mime_types: &mut Vec<String>, mime_type: String) {
+    if !mime_types.contains(&mime_type) {
+        mime_types.push(mime_type);
+    }
+}
+
+/// Guard against copy loops when bridging selections
+///
+/// A bridge between the compositor selection and an external clipboard, like the Windows
+/// clipboard or another seat, mirrors selections set on either side to the other one. Clipboard
+/// managers using data control re-announce every new selection as their own, which the bridge
+/// then sees as a new selection to export, and so on forever.
+///
+/// The guard tags imported selections with a private marker mime type, which clipboard managers
+/// copy along with the data. Selections carrying the marker, or announcing exactly the mime types
+/// of the last import shortly after it, are echoes and must not be exported again.
+///
+/// ```no_run
+/// # use smithay::wayland::selection::SelectionLoopGuard;
+/// let mut guard = SelectionLoopGuard::new("win32-clipboard");
+///
+/// // importing the external clipboard
+/// # let external_mime_types: Vec<String> = Vec::new();
+/// let mime_types = guard.import(external_mime_types);
+/// // set_data_device_selection(&dh, &seat, mime_types, user_data);
+///
+/// // in SelectionHandler::new_selection
+/// # let source_mime_types: Vec<String> = Vec::new();
+/// if !guard.is_echo(&source_mime_types) {
+///     let mime_types = guard.export(source_mime_types);
+///     // mirror the selection to the external clipboard
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SelectionLoopGuard {
+    marker: String,
+    window: Duration,
+    time_source: Arc<dyn TimeSource>,
+    last_import: Option<(Vec<String>, Time<Monotonic>)>,
+}
+
+impl SelectionLoopGuard {
+    /// Create a guard for the bridge with the given name
+    ///
+    /// The name has to be unique among the bridges of the compositor.
+    pub fn new(name: &str) -> SelectionLoopGuard {
+        SelectionLoopGuard {
+            marker: format!("application/x-smithay-selection-bridge;name={}", name),
+            window: Duration::from_secs(1),
+            time_source: system_time_source(),
+            last_import: None,
+        }
+    }
+
+    /// Replace the source of the current time, the monotonic [`Clock`](crate::utils::Clock) by default
+    pub fn set_time_source(&mut self, time_source: Arc<dyn TimeSource>) {
+        self.time_source = time_source;
+    }
+
+    /// Change how long after an import a selection with identical mime types is considered an
+    /// echo (one second by default)
+    pub fn set_echo_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// The marker mime type added to imported selections
+    ///
+    /// Requests for it should be answered by closing the file descriptor.
+    pub fn marker(&self) -> &str {
+        &self.marker
+    }
+
+    /// Prepare the mime types of an external selection to be set as compositor selection
+    pub fn import(&mut self, mime_types: Vec<String>) -> Vec<String> {
+        let mut mime_types = self.strip(mime_types);
+        self.last_import = Some((mime_types.clone(), self.time_source.now()));
+        mime_types.push(self.marker.clone());
+        mime_types
+    }
+
+    /// Returns `true` if a new selection with the given mime types was caused by an import
+    pub fn is_echo<S: AsRef<str>>(&self, mime_types: &[S]) -> bool {
+        if mime_types
+            .iter()
+            .any(|mime_type| mime_type.as_ref() == self.marker)
+        {
+            return true;
+        }
+        let now = self.time_source.now();
+        self.last_import.as_ref().is_some_and(|(imported, time)| {
+            now.saturating_duration_since(*time) < self.window
+                && imported.len() == mime_types.len()
+                && imported
+                    .iter()
+                    .zip(mime_types)
+                    .all(|(a, b)| a.as_str() == b.as_ref())
+        })
+    }
+
+    /// Prepare the mime types of a compositor selection to be set on the external side
+    ///
+    /// Removes duplicates and the marker.
+    pub fn export(&mut self, mime_types: Vec<String>) -> Vec<String> {
+        self.last_import = None;
+        self.strip(mime_types)
+    }
+
+    fn strip(&self, mime_types: Vec<String>) -> Vec<String> {
+        let mut mime_types = dedup_mime_types(mime_types);
+        mime_types.retain(|mime_type| *mime_type != self.marker);
+        mime_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ManualClock;
+
+    fn strings(mime_types: &[&str]) -> Vec<String> {
+        mime_types.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn ranking() {
+        let offered = strings(&["image/png", "text/plain", "text/plain;charset=utf-8"]);
+        assert_eq!(
+            preferred_mime_type(&offered, TEXT_MIME_TYPES),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(
+            preferred_mime_type(&offered, &["image/*", "text/plain"]),
+            Some("image/png")
+        );
+        assert_eq!(preferred_mime_type(&offered, &["*"]), Some("image/png"));
+        assert_eq!(preferred_mime_type(&offered, &["TEXT/PLAIN"]), Some("text/plain"));
+        assert_eq!(preferred_mime_type(&offered, &["audio/*"]), None);
+    }
+
+    #[test]
+    fn dedup() {
+        assert_eq!(
+            dedup_mime_types(strings(&["a/b", "c/d", "a/b", "e/f", "c/d"])),
+            strings(&["a/b", "c/d", "e/f"])
+        );
+    }
+
+    #[test]
+    fn offer() {
+        let mut mime_types = Vec::new();
+        for mime_type in ["text/plain", "text/html", "text/plain"] {
+            offer_mime_type(&mut mime_types, mime_type.to_string());
+        }
+        assert_eq!(mime_types, strings(&["text/plain", "text/html"]));
+    }
+
+    #[test]
+    fn loop_guard() {
+        let mut guard = SelectionLoopGuard::new("test");
+        let imported = guard.import(strings(&["text/plain", "text/plain"]));
+        assert_eq!(imported, strings(&["text/plain", guard.marker()]));
+        // a clipboard manager copying everything, or only the data
+        assert!(guard.is_echo(&imported));
+        assert!(guard.is_echo(&strings(&["text/plain"])));
+        assert!(!guard.is_echo(&strings(&["text/html"])));
+
+        assert_eq!(guard.export(imported), strings(&["text/plain"]));
+        // once a local selection was exported, identical types are a new selection
+        assert!(!guard.is_echo(&strings(&["text/plain"])));
+    }
+
+    #[test]
+    fn loop_guard_echo_window() {
+        let clock = ManualClock::default();
+        let mut guard = SelectionLoopGuard::new("test");
+        guard.set_time_source(Arc::new(clock.clone()));
+        guard.import(strings(&["text/plain"]));
+
+        clock.advance(Duration::from_millis(999));
+        assert!(guard.is_echo(&strings(&["text/plain"])));
+        clock.advance(Duration::from_millis(1));
+        assert!(!guard.is_echo(&strings(&["text/plain"])));
+    }
+}
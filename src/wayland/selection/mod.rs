@@ -10,8 +10,14 @@
 //! - The [`primary_selection`](primary_selection/index.html) module to work with the primary selection.
 //! - The [`wlr_data_control`](wlr_data_control/index.html) module to hook data control into
 //!   clipboard and primary selection
+//!
+//! Independent of the protocol, [`preferred_mime_type`] picks the best of the offered mime types
+//! and [`SelectionLoopGuard`] prevents copy loops when bridging selections to an external
+//! clipboard.
+
+use std::{cell::RefCell, os::unix::io::OwnedFd};
 
-use std::os::unix::io::OwnedFd;
+use wayland_server::{Client, DisplayHandle};
 
 use crate::input::{Seat, SeatHandler};
 
@@ -21,10 +27,12 @@ pub mod primary_selection;
 pub mod wlr_data_control;
 
 mod device;
+mod mime;
 mod offer;
 mod seat_data;
 mod source;
 
+pub use mime::{dedup_mime_types, preferred_mime_type, SelectionLoopGuard, TEXT_MIME_TYPES};
 pub use source::SelectionSource;
 
 /// Events that are generated by interactions of the clients with the data device.
@@ -52,6 +60,27 @@ pub trait SelectionHandler: Sized + SeatHandler {
     }
 }
 
+/// Set the clipboard and primary selection focus to a certain client for a given seat
+///
+/// Equivalent to calling both [`data_device::set_data_device_focus`] and
+/// [`primary_selection::set_primary_focus`], usually from [`SeatHandler::focus_changed`], so
+/// the focused client is offered both selections. Middle-click paste needs the primary
+/// selection to follow the keyboard focus just like the clipboard.
+pub fn set_selection_focus<D>(dh: &DisplayHandle, seat: &Seat<D>, client: Option<Client>)
+where
+    D: SelectionHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(seat_data::SeatData::<D::SelectionUserData>::new()));
+    let mut seat_data = seat
+        .user_data()
+        .get::<RefCell<seat_data::SeatData<D::SelectionUserData>>>()
+        .unwrap()
+        .borrow_mut();
+    seat_data.set_clipboard_focus::<D>(dh, client.clone());
+    seat_data.set_primary_focus::<D>(dh, client);
+}
+
 /// The target for the selection request.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectionTarget {
@@ -27,7 +27,7 @@ use crate::input::Seat;
 use super::device::{DataDeviceKind, SelectionDevice};
 use super::private::selection_dispatch;
 use super::source::{CompositorSelectionProvider, SelectionSourceProvider};
-use super::SelectionHandler;
+use super::{dedup_mime_types, SelectionHandler};
 
 #[derive(Debug, Clone)]
 pub enum OfferReplySource<U: Clone + Send + Sync + 'static> {
@@ -42,7 +42,7 @@ impl<U: Clone + Send + Sync + 'static> OfferReplySource<U> {
     pub fn mime_types(&self) -> Vec<String> {
         match self {
             OfferReplySource::Client(source) => source.mime_types(),
-            OfferReplySource::Compositor(source) => source.mime_types.clone(),
+            OfferReplySource::Compositor(source) => dedup_mime_types(source.mime_types.clone()),
         }
     }
 
@@ -9,6 +9,8 @@
 //! This module provides the freestanding [`set_primary_focus`] function:
 //!   This function sets the data device focus for a given seat; you'd typically call it
 //!   whenever the keyboard focus changes, to follow it (for example in the focus hook of your keyboards).
+//!   [`set_selection_focus`](super::set_selection_focus) updates the focus of the clipboard and
+//!   the primary selection at once.
 //!
 //! The module also provides an additional mechanism allowing your compositor to see and interact with
 //! the contents of the primary selection:
@@ -7,7 +7,9 @@ use wayland_server::{backend::ClientId, Dispatch, DisplayHandle};
 
 use crate::{
     input::Seat,
-    wayland::selection::{offer::OfferReplySource, seat_data::SeatData, source::SelectionSourceProvider},
+    wayland::selection::{
+        mime::offer_mime_type, offer::OfferReplySource, seat_data::SeatData, source::SelectionSourceProvider,
+    },
 };
 
 use super::{PrimarySelectionHandler, PrimarySelectionState};
@@ -55,7 +57,7 @@ where
 
         match request {
             primary_source::Request::Offer { mime_type } => {
-                data.mime_types.push(mime_type);
+                offer_mime_type(&mut data.mime_types, mime_type);
             }
             primary_source::Request::Destroy => {}
             _ => unreachable!(),
@@ -25,6 +25,13 @@ impl SelectionSource {
     pub fn mime_types(&self) -> Vec<String> {
         self.provider.mime_types()
     }
+
+    /// The mime type of the source best matching the given preferences
+    ///
+    /// See [`preferred_mime_type`](super::preferred_mime_type) for the format of `preferences`.
+    pub fn preferred_mime_type(&self, preferences: &[&str]) -> Option<String> {
+        super::preferred_mime_type(&self.mime_types(), preferences).map(String::from)
+    }
 }
 
 /// Provider of the selection data.
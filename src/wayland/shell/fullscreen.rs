@@ -0,0 +1,517 @@
+//! Utilities for handling the `zwp_fullscreen_shell_v1` protocol
+//!
+//! The fullscreen shell is a minimal shell for kiosk like compositors: a client presents a single
+//! surface per output, optionally asking the compositor to switch the output mode to fit the
+//! surface. There are no windows to manage, so a compositor built around it does not need
+//! xdg-shell at all.
+//!
+//! How a surface is presented is decided per output by a [`FullscreenOutputPolicy`]. It may
+//! forbid mode switches and override the scaling method requested by the client, e.g. to always
+//! stretch the surface on a signage display. [`present_geometry`] computes where a surface has
+//! to be rendered for a given method.
+//!
+//! ```
+//! use smithay::delegate_fullscreen_shell;
+//! use smithay::output::Output;
+//! use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! use smithay::wayland::shell::fullscreen::{
+//!     FullscreenShellHandler, FullscreenShellState, ModeSwitchRequest, PresentMethod,
+//! };
+//!
+//! # struct State { fullscreen_shell: FullscreenShellState }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! let fullscreen_shell = FullscreenShellState::new::<State>(&display.handle(), false, true);
+//!
+//! impl FullscreenShellHandler for State {
+//!     fn fullscreen_shell_state(&mut self) -> &mut FullscreenShellState {
+//!         &mut self.fullscreen_shell
+//!     }
+//!
+//!     fn present_surface(&mut self, surface: Option<WlSurface>, output: Output, method: PresentMethod) {
+//!         // show `surface` on `output`, using `present_geometry` to place it
+//!     }
+//!
+//!     fn present_surface_for_mode(&mut self, request: ModeSwitchRequest) {
+//!         // try to switch the mode of `request.output()` and answer the request
+//!         request.failed();
+//!     }
+//! }
+//!
+//! delegate_fullscreen_shell!(State);
+//! ```
+
+use std::collections::HashMap;
+
+use tracing::trace;
+use wayland_protocols::wp::fullscreen_shell::zv1::server::{
+    zwp_fullscreen_shell_mode_feedback_v1::{self, ZwpFullscreenShellModeFeedbackV1},
+    zwp_fullscreen_shell_v1::{self, Capability, ZwpFullscreenShellV1},
+};
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface::WlSurface, Client, DataInit, Dispatch, DisplayHandle,
+    GlobalDispatch, New, Resource, WEnum,
+};
+
+pub use zwp_fullscreen_shell_v1::PresentMethod;
+
+use crate::{
+    output::Output,
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::compositor,
+};
+
+/// The role of a surface presented by the fullscreen shell
+pub const FULLSCREEN_SHELL_ROLE: &str = "zwp_fullscreen_shell_v1";
+
+/// Handler for the fullscreen shell protocol
+pub trait FullscreenShellHandler:
+    GlobalDispatch<ZwpFullscreenShellV1, FullscreenShellGlobalData>
+    + Dispatch<ZwpFullscreenShellV1, ()>
+    + Dispatch<ZwpFullscreenShellModeFeedbackV1, ()>
+    + 'static
+{
+    /// [`FullscreenShellState`] getter
+    fn fullscreen_shell_state(&mut self) -> &mut FullscreenShellState;
+
+    /// A surface should be presented on an output, or the output should be cleared if `surface`
+    /// is `None`
+    ///
+    /// `method` already has the scaling policy of the output applied. If the client did not name
+    /// an output, this is called for every output returned by
+    /// [`FullscreenShellHandler::default_outputs`].
+    fn present_surface(&mut self, surface: Option<WlSurface>, output: Output, method: PresentMethod);
+
+    /// A surface should be presented on an output after switching the output to a mode fitting it
+    ///
+    /// Only called if the policy of the output allows mode switches, otherwise the request
+    /// fails like with [`ModeSwitchRequest::failed`] and the client may present the surface
+    /// without a mode switch.
+    fn present_surface_for_mode(&mut self, request: ModeSwitchRequest);
+
+    /// Outputs to use for surfaces presented without an output
+    ///
+    /// Defaults to all outputs a policy was set for.
+    fn default_outputs(&mut self) -> Vec<Output> {
+        self.fullscreen_shell_state().policies.keys().cloned().collect()
+    }
+}
+
+/// How surfaces are presented on an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullscreenOutputPolicy {
+    /// Whether clients may switch the mode of the output
+    pub allow_mode_switch: bool,
+    /// Method replacing the one requested by clients
+    pub scaling: Option<PresentMethod>,
+    /// Method used by [`PresentMethod::Default`]
+    pub default_method: PresentMethod,
+}
+
+impl Default for FullscreenOutputPolicy {
+    fn default() -> Self {
+        FullscreenOutputPolicy {
+            allow_mode_switch: true,
+            scaling: None,
+            default_method: PresentMethod::Center,
+        }
+    }
+}
+
+impl FullscreenOutputPolicy {
+    /// Resolve the method to present a surface with
+    pub fn method(&self, requested: PresentMethod) -> PresentMethod {
+        match self.scaling.unwrap_or(requested) {
+            PresentMethod::Default => self.default_method,
+            method => method,
+        }
+    }
+}
+
+/// Data of the fullscreen shell global
+#[derive(Debug)]
+pub struct FullscreenShellGlobalData {
+    capabilities: Vec<Capability>,
+}
+
+/// State of the fullscreen shell
+#[derive(Debug)]
+pub struct FullscreenShellState {
+    global: GlobalId,
+    policies: HashMap<Output, FullscreenOutputPolicy>,
+    presented: HashMap<Output, (WlSurface, PresentMethod)>,
+}
+
+impl FullscreenShellState {
+    /// Create a new `zwp_fullscreen_shell_v1` global
+    ///
+    /// `arbitrary_modes` announces that outputs can switch to any mode a surface needs, e.g. on
+    /// a nested or virtual backend. `cursor_plane` announces that the cursor is drawn by the
+    /// compositor, so clients do not have to render it themselves.
+    pub fn new<D: FullscreenShellHandler>(
+        display: &DisplayHandle,
+        arbitrary_modes: bool,
+        cursor_plane: bool,
+    ) -> FullscreenShellState {
+        let mut capabilities = Vec::new();
+        if arbitrary_modes {
+            capabilities.push(Capability::ArbitraryModes);
+        }
+        if cursor_plane {
+            capabilities.push(Capability::CursorPlane);
+        }
+        let global = display
+            .create_global::<D, ZwpFullscreenShellV1, _>(1, FullscreenShellGlobalData { capabilities });
+        FullscreenShellState {
+            global,
+            policies: HashMap::new(),
+            presented: HashMap::new(),
+        }
+    }
+
+    /// Returns the `zwp_fullscreen_shell_v1` global id
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Set the policy of an output
+    pub fn set_output_policy(&mut self, output: &Output, policy: FullscreenOutputPolicy) {
+        self.policies.insert(output.clone(), policy);
+    }
+
+    /// Forget an output, e.g. after it was disconnected
+    pub fn remove_output(&mut self, output: &Output) {
+        self.policies.remove(output);
+        self.presented.remove(output);
+    }
+
+    /// The policy of an output
+    pub fn output_policy(&self, output: &Output) -> FullscreenOutputPolicy {
+        self.policies.get(output).copied().unwrap_or_default()
+    }
+
+    /// The surface presented on an output and its method
+    pub fn presented_surface(&self, output: &Output) -> Option<(&WlSurface, PresentMethod)> {
+        self.presented
+            .get(output)
+            .filter(|(surface, _)| surface.is_alive())
+            .map(|(surface, method)| (surface, *method))
+    }
+
+    fn set_presented(&mut self, output: &Output, surface: Option<(WlSurface, PresentMethod)>) {
+        match surface {
+            Some(surface) => {
+                self.presented.insert(output.clone(), surface);
+            }
+            None => {
+                self.presented.remove(output);
+            }
+        }
+    }
+}
+
+/// Where to render a surface of `surface_size` on an output of `output_size` for a method
+///
+/// [`PresentMethod::Default`] is treated like [`PresentMethod::Center`], resolve it with
+/// [`FullscreenOutputPolicy::method`] first. The result may exceed the output for
+/// [`PresentMethod::ZoomCrop`] or for centered surfaces larger than the output, so it has to
+/// be clipped.
+pub fn present_geometry(
+    method: PresentMethod,
+    surface_size: Size<i32, Logical>,
+    output_size: Size<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    if surface_size.w <= 0 || surface_size.h <= 0 {
+        return Rectangle::from_size(output_size);
+    }
+
+    let size = match method {
+        PresentMethod::Stretch => output_size,
+        PresentMethod::Zoom | PresentMethod::ZoomCrop => {
+            let scale_x = output_size.w as f64 / surface_size.w as f64;
+            let scale_y = output_size.h as f64 / surface_size.h as f64;
+            let scale = if method == PresentMethod::Zoom {
+                scale_x.min(scale_y)
+            } else {
+                scale_x.max(scale_y)
+            };
+            Size::from((
+                (surface_size.w as f64 * scale).round() as i32,
+                (surface_size.h as f64 * scale).round() as i32,
+            ))
+        }
+        _ => surface_size,
+    };
+    let loc = Point::from(((output_size.w - size.w) / 2, (output_size.h - size.h) / 2));
+    Rectangle::new(loc, size)
+}
+
+/// A request to present a surface with a mode switch
+#[derive(Debug)]
+pub struct ModeSwitchRequest {
+    surface: WlSurface,
+    output: Output,
+    framerate: Option<i32>,
+    feedback: ZwpFullscreenShellModeFeedbackV1,
+    answered: bool,
+}
+
+impl ModeSwitchRequest {
+    /// The surface to present
+    pub fn surface(&self) -> &WlSurface {
+        &self.surface
+    }
+
+    /// The output to switch the mode of
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// The requested refresh rate in mHz, if the client cares about it
+    pub fn framerate(&self) -> Option<i32> {
+        self.framerate
+    }
+
+    /// The output switched to a fitting mode and presents the surface
+    ///
+    /// Should be called once the surface was presented in the new mode.
+    pub fn succeeded(mut self, state: &mut FullscreenShellState) {
+        self.answered = true;
+        state.set_presented(&self.output, Some((self.surface.clone(), PresentMethod::Default)));
+        self.feedback.mode_successful();
+    }
+
+    /// The mode could not be switched
+    ///
+    /// The surface is not presented, the client may try again with
+    /// [`present_surface`](FullscreenShellHandler::present_surface).
+    pub fn failed(mut self) {
+        self.answered = true;
+        self.feedback.mode_failed();
+    }
+
+    /// Another surface was presented on the output before the mode switch finished
+    pub fn cancelled(mut self) {
+        self.answered = true;
+        self.feedback.present_cancelled();
+    }
+}
+
+impl Drop for ModeSwitchRequest {
+    fn drop(&mut self) {
+        if !self.answered {
+            self.feedback.mode_failed();
+        }
+    }
+}
+
+fn present<D: FullscreenShellHandler>(
+    state: &mut D,
+    surface: Option<WlSurface>,
+    output: Output,
+    method: PresentMethod,
+) {
+    let method = state
+        .fullscreen_shell_state()
+        .output_policy(&output)
+        .method(method);
+    state
+        .fullscreen_shell_state()
+        .set_presented(&output, surface.clone().map(|surface| (surface, method)));
+    state.present_surface(surface, output, method);
+}
+
+impl<D: FullscreenShellHandler> GlobalDispatch<ZwpFullscreenShellV1, FullscreenShellGlobalData, D>
+    for FullscreenShellState
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpFullscreenShellV1>,
+        global_data: &FullscreenShellGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let shell = data_init.init(resource, ());
+        for capability in &global_data.capabilities {
+            shell.capability(*capability);
+        }
+    }
+}
+
+impl<D: FullscreenShellHandler> Dispatch<ZwpFullscreenShellV1, (), D> for FullscreenShellState {
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        shell: &ZwpFullscreenShellV1,
+        request: zwp_fullscreen_shell_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_fullscreen_shell_v1::Request::PresentSurface {
+                surface,
+                method,
+                output,
+            } => {
+                let method = match method {
+                    WEnum::Value(method) => method,
+                    WEnum::Unknown(method) => {
+                        shell.post_error(
+                            zwp_fullscreen_shell_v1::Error::InvalidMethod,
+                            format!("unknown present method {method}"),
+                        );
+                        return;
+                    }
+                };
+                if let Some(surface) = surface.as_ref() {
+                    if compositor::give_role(surface, FULLSCREEN_SHELL_ROLE).is_err() {
+                        shell.post_error(
+                            zwp_fullscreen_shell_v1::Error::Role,
+                            "Surface already has a role.",
+                        );
+                        return;
+                    }
+                }
+
+                let outputs = match output.as_ref().map(Output::from_resource) {
+                    Some(Some(output)) => vec![output],
+                    // the output is already gone
+                    Some(None) => return,
+                    None => state.default_outputs(),
+                };
+                for output in outputs {
+                    present(state, surface.clone(), output, method);
+                }
+            }
+            zwp_fullscreen_shell_v1::Request::PresentSurfaceForMode {
+                surface,
+                output,
+                framerate,
+                feedback,
+            } => {
+                let feedback = data_init.init(feedback, ());
+                if compositor::give_role(&surface, FULLSCREEN_SHELL_ROLE).is_err() {
+                    shell.post_error(
+                        zwp_fullscreen_shell_v1::Error::Role,
+                        "Surface already has a role.",
+                    );
+                    return;
+                }
+                let Some(output) = Output::from_resource(&output) else {
+                    feedback.mode_failed();
+                    return;
+                };
+
+                if !state
+                    .fullscreen_shell_state()
+                    .output_policy(&output)
+                    .allow_mode_switch
+                {
+                    trace!(output = %output.name(), "Mode switch denied by policy");
+                    // the surface is not presented, see `ModeSwitchRequest::failed`
+                    feedback.mode_failed();
+                    return;
+                }
+
+                state.present_surface_for_mode(ModeSwitchRequest {
+                    surface,
+                    output,
+                    framerate: (framerate > 0).then_some(framerate),
+                    feedback,
+                    answered: false,
+                });
+            }
+            zwp_fullscreen_shell_v1::Request::Release => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: FullscreenShellHandler> Dispatch<ZwpFullscreenShellModeFeedbackV1, (), D> for FullscreenShellState {
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ZwpFullscreenShellModeFeedbackV1,
+        _request: zwp_fullscreen_shell_mode_feedback_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+}
+
+/// Macro to delegate implementation of the fullscreen shell to [`FullscreenShellState`].
+///
+/// You must also implement [`FullscreenShellHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_fullscreen_shell {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        const _: () = {
+            use $crate::{
+                reexports::{
+                    wayland_protocols::wp::fullscreen_shell::zv1::server::{
+                        zwp_fullscreen_shell_mode_feedback_v1::ZwpFullscreenShellModeFeedbackV1,
+                        zwp_fullscreen_shell_v1::ZwpFullscreenShellV1,
+                    },
+                    wayland_server::{delegate_dispatch, delegate_global_dispatch},
+                },
+                wayland::shell::fullscreen::{FullscreenShellGlobalData, FullscreenShellState},
+            };
+
+            delegate_global_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwpFullscreenShellV1: FullscreenShellGlobalData] => FullscreenShellState
+            );
+
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwpFullscreenShellV1: ()] => FullscreenShellState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ZwpFullscreenShellModeFeedbackV1: ()] => FullscreenShellState
+            );
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry() {
+        let output = Size::from((1920, 1080));
+        let surface = Size::from((800, 600));
+        assert_eq!(
+            present_geometry(PresentMethod::Center, surface, output),
+            Rectangle::new((560, 240).into(), (800, 600).into())
+        );
+        assert_eq!(
+            present_geometry(PresentMethod::Zoom, surface, output),
+            Rectangle::new((240, 0).into(), (1440, 1080).into())
+        );
+        assert_eq!(
+            present_geometry(PresentMethod::ZoomCrop, surface, output),
+            Rectangle::new((0, -180).into(), (1920, 1440).into())
+        );
+        assert_eq!(
+            present_geometry(PresentMethod::Stretch, surface, output),
+            Rectangle::from_size(output)
+        );
+    }
+
+    #[test]
+    fn policy() {
+        let policy = FullscreenOutputPolicy::default();
+        assert_eq!(policy.method(PresentMethod::Default), PresentMethod::Center);
+        assert_eq!(policy.method(PresentMethod::Zoom), PresentMethod::Zoom);
+
+        let policy = FullscreenOutputPolicy {
+            scaling: Some(PresentMethod::Stretch),
+            ..Default::default()
+        };
+        assert_eq!(policy.method(PresentMethod::Zoom), PresentMethod::Stretch);
+    }
+}
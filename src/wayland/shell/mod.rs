@@ -7,19 +7,23 @@
 //! The shell protocols thus define what kind of interactions a client can have with
 //! the compositor to properly display its contents on the screen.
 //!
-//! Smithay currently provides three of them:
+//! Smithay currently provides four of them:
 //!
 //! - The [`xdg`](xdg/index.html) module provides handlers for the `xdg_shell` protocol, which is
 //!   the current standard for desktop apps
 //! - The [`wlr_layer`](wlr_layer/index.html) module provides handlers for the `wlr_layer_shell`
 //!   protocol, which is for windows rendering above/below normal XDG windows
 //! - The [`kde`](kde/index.html) module provides handlers for KDE-specific protocols
+//! - The [`fullscreen`](fullscreen/index.html) module provides handlers for the
+//!   `zwp_fullscreen_shell_v1` protocol, which presents a single surface per output for kiosk
+//!   compositors
 
 use crate::{utils::Serial, wayland::compositor};
 use thiserror::Error;
 use wayland_server::protocol::wl_surface::WlSurface;
 use xdg::XdgToplevelSurfaceData;
 
+pub mod fullscreen;
 pub mod kde;
 pub mod wlr_layer;
 pub mod xdg;
@@ -263,6 +263,27 @@ pub enum ToplevelDialogHint {
     Modal,
 }
 
+impl ToplevelSurface {
+    /// The dialog hint the client set for this toplevel
+    pub fn dialog_hint(&self) -> ToplevelDialogHint {
+        compositor::with_states(self.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .dialog_hint
+        })
+    }
+
+    /// Returns `true` if the toplevel is a modal dialog, which should block interaction with its
+    /// parent
+    pub fn is_modal(&self) -> bool {
+        self.dialog_hint() == ToplevelDialogHint::Modal
+    }
+}
+
 /// Returns true if changed
 fn set_dialog_hint(wl_surface: &WlSurface, hint: ToplevelDialogHint) -> bool {
     compositor::with_states(wl_surface, |states| {
@@ -143,6 +143,7 @@ use super::PingError;
 
 pub mod decoration;
 pub mod dialog;
+pub mod toplevel_drag;
 
 // handlers for the xdg_shell protocol
 pub(super) mod handlers;
@@ -0,0 +1,521 @@
+//! XDG Toplevel Drag
+//!
+//! This protocol allows clients to attach a toplevel to a drag'n'drop operation, which then
+//! follows the pointer until the drop. Browsers use it to tear tabs off into new windows, and to
+//! drop them back into another window.
+//!
+//! Toplevels are attached to a [`ToplevelDrag`], which is bound to a `wl_data_source`. To move
+//! them with the pointer, wrap the [`DnDGrab`](crate::input::dnd::DnDGrab) started in
+//! [`WaylandDndGrabHandler::dnd_requested`](crate::wayland::selection::data_device::WaylandDndGrabHandler::dnd_requested)
+//! into a [`ToplevelDragGrab`] if the source has a drag:
+//!
+//! ```ignore
+//! fn dnd_requested<S: Source>(&mut self, source: S, icon: Option<WlSurface>, seat: Seat<Self>, serial: Serial, type_: GrabType) {
+//!     let pointer = seat.get_pointer().unwrap();
+//!     let start_data = pointer.grab_start_data().unwrap();
+//!     let drag = (&source as &dyn Any)
+//!         .downcast_ref::<WlDataSource>()
+//!         .and_then(|source| self.toplevel_drag_state.drag_for_source(source));
+//!     let grab = DnDGrab::new_pointer(&self.display_handle, start_data, source, seat.clone());
+//!     match drag {
+//!         Some(drag) => pointer.set_grab(self, ToplevelDragGrab::new(drag, grab), serial, Focus::Keep),
+//!         None => pointer.set_grab(self, grab, serial, Focus::Keep),
+//!     }
+//! }
+//! ```
+//!
+//! The grab reports the position of the attached toplevel with
+//! [`XdgToplevelDragHandler::move_toplevel`] and keeps it from becoming the drop target. A toplevel
+//! is detached when the drag ends, when it is unmapped or when the client attaches another one.
+
+use std::sync::{Arc, Mutex};
+
+use wayland_protocols::xdg::toplevel_drag::v1::server::{
+    xdg_toplevel_drag_manager_v1::{self, XdgToplevelDragManagerV1},
+    xdg_toplevel_drag_v1::{self, XdgToplevelDragV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId},
+    protocol::{wl_data_source::WlDataSource, wl_surface::WlSurface},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use super::{ToplevelSurface, XdgShellHandler};
+use crate::{
+    input::{
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+            GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+            GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
+            RelativeMotionEvent,
+        },
+        SeatHandler,
+    },
+    utils::{Logical, Point},
+    wayland::{
+        compositor::{self, BufferAssignment, HookId, SurfaceAttributes},
+        seat::WaylandFocus,
+    },
+};
+
+/// Delegate type for handling xdg toplevel drag events.
+#[derive(Debug)]
+pub struct XdgToplevelDragState {
+    global: GlobalId,
+    drags: Vec<ToplevelDrag>,
+}
+
+impl XdgToplevelDragState {
+    /// Creates a new delegate type for handling xdg toplevel drag events.
+    pub fn new<D: XdgToplevelDragHandler>(display: &DisplayHandle) -> XdgToplevelDragState {
+        let global = display.create_global::<D, XdgToplevelDragManagerV1, _>(1, ());
+        XdgToplevelDragState {
+            global,
+            drags: Vec::new(),
+        }
+    }
+
+    /// Returns the xdg-toplevel-drag global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Returns the toplevel drag created for a data source, if any
+    pub fn drag_for_source(&self, source: &WlDataSource) -> Option<ToplevelDrag> {
+        self.drags
+            .iter()
+            .find(|drag| drag.inner.lock().unwrap().source == *source)
+            .cloned()
+    }
+}
+
+/// Handler trait for xdg toplevel drag events.
+pub trait XdgToplevelDragHandler:
+    XdgShellHandler
+    + SeatHandler
+    + GlobalDispatch<XdgToplevelDragManagerV1, ()>
+    + Dispatch<XdgToplevelDragManagerV1, ()>
+    + Dispatch<XdgToplevelDragV1, ToplevelDrag>
+    + 'static
+{
+    /// [`XdgToplevelDragState`] getter
+    fn xdg_toplevel_drag_state(&mut self) -> &mut XdgToplevelDragState;
+
+    /// The attached toplevel of a drag should be moved
+    ///
+    /// `location` is where the origin of the window geometry of the toplevel should be placed,
+    /// in global coordinates. The toplevel may still be unmapped, in which case it should be
+    /// mapped at that location.
+    fn move_toplevel(&mut self, toplevel: ToplevelSurface, location: Point<i32, Logical>);
+
+    /// A toplevel was attached to a drag
+    fn toplevel_attached(&mut self, drag: ToplevelDrag, toplevel: ToplevelSurface) {
+        let _ = (drag, toplevel);
+    }
+
+    /// A toplevel was detached from a drag
+    fn toplevel_detached(&mut self, drag: ToplevelDrag, toplevel: ToplevelSurface) {
+        let _ = (drag, toplevel);
+    }
+}
+
+#[derive(Debug)]
+struct ToplevelDragInner {
+    source: WlDataSource,
+    attached: Option<Attached>,
+    ongoing: bool,
+}
+
+#[derive(Debug)]
+struct Attached {
+    toplevel: ToplevelSurface,
+    offset: Point<i32, Logical>,
+    unmap_hook: HookId,
+}
+
+/// A toplevel drag bound to a data source
+#[derive(Debug, Clone)]
+pub struct ToplevelDrag {
+    inner: Arc<Mutex<ToplevelDragInner>>,
+}
+
+impl PartialEq for ToplevelDrag {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl ToplevelDrag {
+    /// The data source of the drag
+    pub fn data_source(&self) -> WlDataSource {
+        self.inner.lock().unwrap().source.clone()
+    }
+
+    /// The attached toplevel
+    pub fn toplevel(&self) -> Option<ToplevelSurface> {
+        let inner = self.inner.lock().unwrap();
+        inner.attached.as_ref().map(|attached| attached.toplevel.clone())
+    }
+
+    /// Offset of the pointer hotspot relative to the window geometry of the attached toplevel
+    pub fn offset(&self) -> Option<Point<i32, Logical>> {
+        let inner = self.inner.lock().unwrap();
+        inner.attached.as_ref().map(|attached| attached.offset)
+    }
+
+    /// Whether the drag'n'drop operation of the drag is in progress
+    pub fn is_ongoing(&self) -> bool {
+        self.inner.lock().unwrap().ongoing
+    }
+
+    /// Where the window geometry of the attached toplevel goes for a pointer location
+    pub fn toplevel_location(&self, pointer: Point<f64, Logical>) -> Option<Point<i32, Logical>> {
+        self.offset().map(|offset| pointer.to_i32_round() - offset)
+    }
+
+    /// Returns `true` if `surface` belongs to the attached toplevel
+    pub fn is_dragged(&self, surface: &WlSurface) -> bool {
+        let Some(toplevel) = self.toplevel() else {
+            return false;
+        };
+        let mut root = surface.clone();
+        while let Some(parent) = compositor::get_parent(&root) {
+            root = parent;
+        }
+        root == *toplevel.wl_surface()
+    }
+
+    fn detach<D: XdgToplevelDragHandler>(&self, state: &mut D) {
+        let attached = self.inner.lock().unwrap().attached.take();
+        if let Some(attached) = attached {
+            compositor::remove_post_commit_hook(attached.toplevel.wl_surface(), attached.unmap_hook);
+            state.toplevel_detached(self.clone(), attached.toplevel);
+        }
+    }
+}
+
+/// Pointer grab moving the toplevel of a [`ToplevelDrag`] during a drag'n'drop operation
+///
+/// Wraps the grab doing the drag'n'drop, usually a [`DnDGrab`](crate::input::dnd::DnDGrab), and
+/// forwards all events to it. Surfaces of the dragged toplevel are hidden from the wrapped grab,
+/// so they never become the drop target.
+#[derive(Debug)]
+pub struct ToplevelDragGrab<G> {
+    drag: ToplevelDrag,
+    grab: G,
+}
+
+impl<G> ToplevelDragGrab<G> {
+    /// Wrap a drag'n'drop grab for a toplevel drag
+    pub fn new(drag: ToplevelDrag, grab: G) -> ToplevelDragGrab<G> {
+        drag.inner.lock().unwrap().ongoing = true;
+        ToplevelDragGrab { drag, grab }
+    }
+
+    /// The toplevel drag of this grab
+    pub fn drag(&self) -> &ToplevelDrag {
+        &self.drag
+    }
+
+    fn end<D: XdgToplevelDragHandler>(&mut self, data: &mut D) {
+        if std::mem::replace(&mut self.drag.inner.lock().unwrap().ongoing, false) {
+            self.drag.detach(data);
+        }
+    }
+}
+
+impl<G> Drop for ToplevelDragGrab<G> {
+    fn drop(&mut self) {
+        self.drag.inner.lock().unwrap().ongoing = false;
+    }
+}
+
+impl<D, G> PointerGrab<D> for ToplevelDragGrab<G>
+where
+    D: XdgToplevelDragHandler,
+    D::PointerFocus: WaylandFocus,
+    G: PointerGrab<D>,
+{
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        focus: Option<(D::PointerFocus, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        let focus = focus.filter(|(focus, _)| {
+            !focus
+                .wl_surface()
+                .is_some_and(|surface| self.drag.is_dragged(&surface))
+        });
+        self.grab.motion(data, handle, focus, event);
+
+        if let (Some(toplevel), Some(location)) =
+            (self.drag.toplevel(), self.drag.toplevel_location(event.location))
+        {
+            data.move_toplevel(toplevel, location);
+        }
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        focus: Option<(D::PointerFocus, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        self.grab.relative_motion(data, handle, focus, event);
+    }
+
+    fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
+        self.grab.button(data, handle, event);
+        // the wrapped grab drops once all buttons are released
+        if handle.current_pressed().is_empty() {
+            self.end(data);
+        }
+    }
+
+    fn axis(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, details: AxisFrame) {
+        self.grab.axis(data, handle, details);
+    }
+
+    fn frame(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>) {
+        self.grab.frame(data, handle);
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        self.grab.gesture_swipe_begin(data, handle, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        self.grab.gesture_swipe_update(data, handle, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        self.grab.gesture_swipe_end(data, handle, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        self.grab.gesture_pinch_begin(data, handle, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        self.grab.gesture_pinch_update(data, handle, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchEndEvent,
+    ) {
+        self.grab.gesture_pinch_end(data, handle, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        self.grab.gesture_hold_begin(data, handle, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldEndEvent,
+    ) {
+        self.grab.gesture_hold_end(data, handle, event);
+    }
+
+    fn start_data(&self) -> &GrabStartData<D> {
+        self.grab.start_data()
+    }
+
+    fn unset(&mut self, data: &mut D) {
+        self.grab.unset(data);
+        self.end(data);
+    }
+}
+
+/// Macro to delegate implementation of the xdg toplevel drag to [`XdgToplevelDragState`].
+///
+/// You must also implement [`XdgToplevelDragHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_xdg_toplevel_drag {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::wayland::shell::xdg::toplevel_drag::XdgToplevelDragState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::wayland::shell::xdg::toplevel_drag::XdgToplevelDragState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_v1::XdgToplevelDragV1: $crate::wayland::shell::xdg::toplevel_drag::ToplevelDrag
+        ] => $crate::wayland::shell::xdg::toplevel_drag::XdgToplevelDragState);
+    };
+}
+
+// xdg_toplevel_drag_manager_v1
+
+impl<D: XdgToplevelDragHandler> GlobalDispatch<XdgToplevelDragManagerV1, (), D> for XdgToplevelDragState {
+    fn bind(
+        _: &mut D,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<XdgToplevelDragManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D: XdgToplevelDragHandler> Dispatch<XdgToplevelDragManagerV1, (), D> for XdgToplevelDragState {
+    fn request(
+        state: &mut D,
+        _: &Client,
+        resource: &XdgToplevelDragManagerV1,
+        request: xdg_toplevel_drag_manager_v1::Request,
+        _: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use xdg_toplevel_drag_manager_v1::Request;
+
+        match request {
+            Request::GetXdgToplevelDrag { id, data_source } => {
+                let drag_state = state.xdg_toplevel_drag_state();
+                drag_state
+                    .drags
+                    .retain(|drag| drag.inner.lock().unwrap().source.is_alive());
+                if drag_state.drag_for_source(&data_source).is_some() {
+                    resource.post_error(
+                        xdg_toplevel_drag_manager_v1::Error::InvalidSource,
+                        "data source already used for a toplevel drag",
+                    );
+                    return;
+                }
+
+                let drag = ToplevelDrag {
+                    inner: Arc::new(Mutex::new(ToplevelDragInner {
+                        source: data_source,
+                        attached: None,
+                        ongoing: false,
+                    })),
+                };
+                data_init.init(id, drag.clone());
+                drag_state.drags.push(drag);
+            }
+
+            Request::Destroy => {}
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+// xdg_toplevel_drag_v1
+
+impl<D: XdgToplevelDragHandler> Dispatch<XdgToplevelDragV1, ToplevelDrag, D> for XdgToplevelDragState {
+    fn request(
+        state: &mut D,
+        _: &Client,
+        resource: &XdgToplevelDragV1,
+        request: xdg_toplevel_drag_v1::Request,
+        drag: &ToplevelDrag,
+        _dh: &DisplayHandle,
+        _: &mut DataInit<'_, D>,
+    ) {
+        use xdg_toplevel_drag_v1::Request;
+
+        match request {
+            Request::Attach {
+                toplevel,
+                x_offset,
+                y_offset,
+            } => {
+                let Some(toplevel) = state.xdg_shell_state().get_toplevel(&toplevel) else {
+                    return;
+                };
+                if let Some(attached) = drag.toplevel() {
+                    if attached.alive() && attached != toplevel {
+                        resource.post_error(
+                            xdg_toplevel_drag_v1::Error::ToplevelAttached,
+                            "a toplevel is already attached",
+                        );
+                        return;
+                    }
+                    drag.detach(state);
+                }
+
+                // detach once the toplevel gets unmapped
+                let weak_drag = Arc::downgrade(&drag.inner);
+                let unmap_hook = compositor::add_post_commit_hook::<D, _>(
+                    toplevel.wl_surface(),
+                    move |state, _, surface| {
+                        let unmapped = compositor::with_states(surface, |states| {
+                            matches!(
+                                states.cached_state.get::<SurfaceAttributes>().current().buffer,
+                                Some(BufferAssignment::Removed)
+                            )
+                        });
+                        if let (true, Some(inner)) = (unmapped, weak_drag.upgrade()) {
+                            ToplevelDrag { inner }.detach(state);
+                        }
+                    },
+                );
+                drag.inner.lock().unwrap().attached = Some(Attached {
+                    toplevel: toplevel.clone(),
+                    offset: (x_offset, y_offset).into(),
+                    unmap_hook,
+                });
+                state.toplevel_attached(drag.clone(), toplevel);
+            }
+
+            Request::Destroy => {
+                if drag.is_ongoing() {
+                    resource.post_error(
+                        xdg_toplevel_drag_v1::Error::OngoingDrag,
+                        "toplevel drag destroyed while the drag is ongoing",
+                    );
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _: ClientId, _: &XdgToplevelDragV1, drag: &ToplevelDrag) {
+        drag.detach(state);
+        state.xdg_toplevel_drag_state().drags.retain(|d| d != drag);
+    }
+}
@@ -1,6 +1,10 @@
-use crate::wayland::{
-    buffer::BufferHandler,
-    shm::{wl_bytes_per_pixel, ShmBufferUserData},
+use crate::{
+    compat::AsRawFd,
+    wayland::{
+        audit::ResourceKind,
+        buffer::BufferHandler,
+        shm::{wl_bytes_per_pixel, ShmBufferUserData},
+    },
 };
 
 use super::{
@@ -8,7 +12,7 @@ use super::{
     BufferData, ShmHandler, ShmPoolUserData, ShmState,
 };
 
-use std::{num::NonZeroUsize, os::unix::io::AsRawFd, sync::Arc};
+use std::{num::NonZeroUsize, sync::Arc};
 use wayland_server::{
     backend::ClientId,
     protocol::{
@@ -75,7 +79,7 @@ where
             Err(fd) => {
                 shm.post_error(
                     wl_shm::Error::InvalidFd,
-                    format!("Failed to mmap fd {}", fd.as_raw_fd()),
+                    format!("Failed to mmap fd {:?}", fd.as_raw_fd()),
                 );
                 return;
             }
@@ -104,7 +108,7 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         pool: &WlShmPool,
         request: wl_shm_pool::Request,
         data: &ShmPoolUserData,
@@ -113,6 +117,7 @@ where
     ) {
         use self::wl_shm_pool::Request;
 
+        let _span = crate::utils::log::client_span(&client.id()).entered();
         let arc_pool = &data.inner;
 
         match request {
@@ -175,6 +180,9 @@ where
                         };
 
                         data_init.init(buffer, data);
+                        if let Some(audit) = state.resource_audit() {
+                            audit.created(&client.id(), ResourceKind::Buffer);
+                        }
                     }
 
                     WEnum::Unknown(unknown) => {
@@ -189,6 +197,7 @@ where
             Request::Resize { size } => {
                 if size <= 0 {
                     pool.post_error(wl_shm::Error::InvalidFd, "invalid wl_shm_pool size");
+                    return;
                 }
 
                 if let Err(err) = arc_pool.resize(NonZeroUsize::try_from(size as usize).unwrap()) {
@@ -234,7 +243,7 @@ where
         }
     }
 
-    fn destroyed(data: &mut D, _client: ClientId, buffer: &wl_buffer::WlBuffer, udata: &ShmBufferUserData) {
+    fn destroyed(data: &mut D, client: ClientId, buffer: &wl_buffer::WlBuffer, udata: &ShmBufferUserData) {
         // Clone to drop the mutex guard
         let destruction_hooks = udata.destruction_hooks.lock().unwrap().clone();
         for hook in destruction_hooks.iter() {
@@ -242,5 +251,8 @@ where
         }
 
         data.buffer_destroyed(buffer);
+        if let Some(audit) = data.resource_audit() {
+            audit.destroyed(&client, ResourceKind::Buffer);
+        }
     }
 }
@@ -15,6 +15,10 @@
 //! you want to support (ARGB8888 and XRGB8888 are always considered as supported,
 //! as specified by the wayland protocol).
 //!
+//! Instead of a fixed list, the formats can also be derived from the renderer importing the
+//! buffers, using [`ShmState::update_formats_from_renderer`] to only advertise formats it
+//! can import cheaply.
+//!
 //! ```
 //! extern crate wayland_server;
 //! extern crate smithay;
@@ -115,7 +119,10 @@ mod handlers;
 mod pool;
 
 use crate::{
-    backend::allocator::format::get_bpp,
+    backend::{
+        allocator::format::get_bpp,
+        renderer::{ImportMemWl, ShmImportCost},
+    },
     utils::{hook::Hook, HookId, UnmanagedResource},
 };
 
@@ -178,6 +185,27 @@ impl ShmState {
         self.formats.insert(wl_shm::Format::Argb8888);
         self.formats.insert(wl_shm::Format::Xrgb8888);
     }
+
+    /// Updates the list of advertised formats to the formats the given renderer
+    /// imports at most at the given cost.
+    ///
+    /// See [`ShmState::update_formats`] for the caveats of changing the formats.
+    pub fn update_formats_from_renderer<R: ImportMemWl>(&mut self, renderer: &R, max_cost: ShmImportCost) {
+        let formats = renderer
+            .shm_formats()
+            .filter(|format| {
+                renderer
+                    .shm_format_cost(*format)
+                    .is_some_and(|cost| cost <= max_cost)
+            })
+            .collect::<Vec<_>>();
+        self.update_formats(formats);
+    }
+
+    /// Returns the formats currently advertised by the global
+    pub fn formats(&self) -> impl Iterator<Item = wl_shm::Format> + '_ {
+        self.formats.iter().copied()
+    }
 }
 
 /// Shm global handler
@@ -2,9 +2,7 @@
 
 use std::{
     cell::Cell,
-    mem,
     num::NonZeroUsize,
-    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
     ptr,
     sync::{
         mpsc::{channel, Sender},
@@ -13,7 +11,12 @@ use std::{
     thread,
 };
 
+use crate::compat::{AsFd, BorrowedFd, OwnedFd};
+
+#[cfg(unix)]
 use rustix::mm;
+#[cfg(unix)]
+use std::mem;
 use tracing::{debug, instrument, trace};
 
 // Dropping Pool is actually pretty slow. Unmapping the memory can take 1-2 ms, but the real
@@ -43,8 +46,12 @@ static DROP_THIS: LazyLock<Sender<InnerPool>> = LazyLock::new(|| {
 
 thread_local!(static SIGBUS_GUARD: Cell<(*const MemMap, bool)> = const { Cell::new((ptr::null_mut(), false)) });
 
+#[cfg(unix)]
 static OLD_SIGBUS_HANDLER: OnceLock<libc::sigaction> = OnceLock::new();
 
+#[cfg(windows)]
+static EXCEPTION_HANDLER: OnceLock<usize> = OnceLock::new();
+
 #[derive(Debug)]
 pub struct Pool {
     inner: Option<InnerPool>,
@@ -104,7 +111,12 @@ impl InnerPool {
     #[instrument(level = "trace", skip_all, name = "wayland_shm")]
     pub fn with_data<T, F: FnOnce(*const u8, usize) -> T>(&self, f: F) -> Result<T, ()> {
         // Place the sigbus handler
-        unsafe { place_sigbus_handler() };
+        #[cfg(unix)]
+        unsafe {
+            place_sigbus_handler()
+        };
+        #[cfg(windows)]
+        place_exception_handler();
 
         let pool_guard = self.map.read().unwrap();
 
@@ -138,7 +150,12 @@ impl InnerPool {
     #[instrument(level = "trace", skip_all, name = "wayland_shm")]
     pub fn with_data_mut<T, F: FnOnce(*mut u8, usize) -> T>(&self, f: F) -> Result<T, ()> {
         // Place the sigbus handler
-        unsafe { place_sigbus_handler() };
+        #[cfg(unix)]
+        unsafe {
+            place_sigbus_handler()
+        };
+        #[cfg(windows)]
+        place_exception_handler();
 
         // This is actually a write access.
         #[allow(clippy::readonly_write_lock)]
@@ -259,6 +276,7 @@ impl Drop for MemMap {
 }
 
 /// A simple wrapper with some default arguments for `nix::mman::mmap`.
+#[cfg(unix)]
 unsafe fn map(fd: BorrowedFd<'_>, size: NonZeroUsize) -> Result<*mut u8, ()> {
     let ret = unsafe {
         mm::mmap(
@@ -274,12 +292,149 @@ unsafe fn map(fd: BorrowedFd<'_>, size: NonZeroUsize) -> Result<*mut u8, ()> {
 }
 
 /// A simple wrapper for `nix::mman::munmap`.
+#[cfg(unix)]
 #[profiling::function]
 unsafe fn unmap(ptr: *mut u8, size: usize) -> Result<(), ()> {
     let ret = unsafe { mm::munmap(ptr as *mut _, size) };
     ret.map_err(|_| ())
 }
 
+#[cfg(windows)]
+unsafe fn map(fd: BorrowedFd<'_>, size: NonZeroUsize) -> Result<*mut u8, ()> {
+    use crate::compat::{
+        mman::{MmapRegion, MAP_SHARED, PROT_READ, PROT_WRITE},
+        AsRawFd,
+    };
+
+    MmapRegion::new(fd.as_raw_fd(), size.into(), PROT_READ | PROT_WRITE, MAP_SHARED)
+        .map(|region| region.into_raw().0)
+        .map_err(|_| ())
+}
+
+#[cfg(windows)]
+#[profiling::function]
+unsafe fn unmap(ptr: *mut u8, _size: usize) -> Result<(), ()> {
+    // SAFETY: the address is either a view mapped by `map` or was nullified by `nullify_map`
+    unsafe {
+        if ffi::UnmapViewOfFile(ptr as *const _) != 0 {
+            return Ok(());
+        }
+        // the view was replaced by anonymous memory
+        if ffi::VirtualFree(ptr as *mut _, 0, ffi::MEM_RELEASE) != 0 {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Replace the view with zeroed memory at the same address
+///
+/// Unlike `MAP_FIXED` this can't be done atomically. The faulting thread is suspended in the
+/// exception handler, but another thread reading the same pool at this very moment may still
+/// hit the gap and fail with an access violation.
+#[cfg(windows)]
+unsafe fn nullify_map(ptr: *mut u8, size: usize) -> Result<(), ()> {
+    unsafe {
+        if ffi::UnmapViewOfFile(ptr as *const _) == 0 {
+            return Err(());
+        }
+        let new = ffi::VirtualAlloc(
+            ptr as *mut _,
+            size,
+            ffi::MEM_RESERVE | ffi::MEM_COMMIT,
+            ffi::PAGE_READWRITE,
+        );
+        if new as *mut u8 == ptr {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// The vectored exception handler will be placed only once
+#[cfg(windows)]
+fn place_exception_handler() {
+    let _ = EXCEPTION_HANDLER.get_or_init(|| {
+        // SAFETY: the handler is valid for the lifetime of the process and never removed
+        let handle = unsafe { ffi::AddVectoredExceptionHandler(1, Some(exception_handler)) };
+        if handle.is_null() {
+            panic!("AddVectoredExceptionHandler failed for the shm pool handler");
+        }
+        handle as usize
+    });
+}
+
+/// Windows counterpart of the SIGBUS handler
+///
+/// Reading a view of a section backed by a truncated file or a failing device raises
+/// `EXCEPTION_IN_PAGE_ERROR`. Faults inside the guarded pool are recovered from like on unix,
+/// everything else is passed on to the next handler.
+#[cfg(windows)]
+unsafe extern "system" fn exception_handler(info: *mut ffi::ExceptionPointers) -> i32 {
+    // SAFETY: the system passes valid exception pointers
+    let record = unsafe { &*(*info).exception_record };
+    if record.exception_code != ffi::EXCEPTION_IN_PAGE_ERROR || record.number_parameters < 2 {
+        return ffi::EXCEPTION_CONTINUE_SEARCH;
+    }
+    // the second parameter is the inaccessible address
+    let faulty_ptr = record.exception_information[1] as *mut u8;
+
+    SIGBUS_GUARD.with(|guard| {
+        let (memmap, _) = guard.get();
+        match unsafe { memmap.as_ref() } {
+            Some(m) if m.contains(faulty_ptr) && m.nullify().is_ok() => {
+                // remember that it was faulty and retry the access on the zeroed memory
+                guard.set((memmap, true));
+                ffi::EXCEPTION_CONTINUE_EXECUTION
+            }
+            _ => ffi::EXCEPTION_CONTINUE_SEARCH,
+        }
+    })
+}
+
+#[cfg(windows)]
+mod ffi {
+    use std::ffi::c_void;
+
+    pub const EXCEPTION_IN_PAGE_ERROR: u32 = 0xC000_0006;
+    pub const EXCEPTION_CONTINUE_EXECUTION: i32 = -1;
+    pub const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+    pub const MEM_COMMIT: u32 = 0x1000;
+    pub const MEM_RESERVE: u32 = 0x2000;
+    pub const MEM_RELEASE: u32 = 0x8000;
+    pub const PAGE_READWRITE: u32 = 0x04;
+
+    #[repr(C)]
+    pub struct ExceptionRecord {
+        pub exception_code: u32,
+        pub exception_flags: u32,
+        pub exception_record: *mut ExceptionRecord,
+        pub exception_address: *mut c_void,
+        pub number_parameters: u32,
+        pub exception_information: [usize; 15],
+    }
+
+    #[repr(C)]
+    pub struct ExceptionPointers {
+        pub exception_record: *mut ExceptionRecord,
+        pub context_record: *mut c_void,
+    }
+
+    pub type VectoredHandler = unsafe extern "system" fn(*mut ExceptionPointers) -> i32;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn AddVectoredExceptionHandler(first: u32, handler: Option<VectoredHandler>) -> *mut c_void;
+        pub fn UnmapViewOfFile(address: *const c_void) -> i32;
+        pub fn VirtualAlloc(address: *mut c_void, size: usize, allocation: u32, protect: u32) -> *mut c_void;
+        pub fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+    }
+}
+
+#[cfg(unix)]
 unsafe fn nullify_map(ptr: *mut u8, size: usize) -> Result<(), ()> {
     let ret = unsafe {
         mm::mmap_anonymous(
@@ -293,6 +448,7 @@ unsafe fn nullify_map(ptr: *mut u8, size: usize) -> Result<(), ()> {
 }
 
 /// The sigbus handler will be placed only once
+#[cfg(unix)]
 unsafe fn place_sigbus_handler() {
     let _ = OLD_SIGBUS_HANDLER.get_or_init(|| {
         // create our sigbus handler
@@ -314,6 +470,7 @@ unsafe fn place_sigbus_handler() {
     });
 }
 
+#[cfg(unix)]
 unsafe fn reraise_sigbus() {
     // reset the old sigaction
     unsafe {
@@ -322,6 +479,7 @@ unsafe fn reraise_sigbus() {
     }
 }
 
+#[cfg(unix)]
 extern "C" fn sigbus_handler(_signum: libc::c_int, info: *mut libc::siginfo_t, _context: *mut libc::c_void) {
     let faulty_ptr = unsafe { siginfo_si_addr(info) } as *mut u8;
     SIGBUS_GUARD.with(|guard| {
@@ -364,7 +522,7 @@ unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
     unsafe { (*(info as *const siginfo_t)).si_addr }
 }
 
-#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
 unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
     unsafe { (*info).si_addr as _ }
 }
@@ -1,10 +1,11 @@
-use crate::wayland::buffer::BufferHandler;
+use crate::wayland::{audit::ResourceKind, buffer::BufferHandler};
 
 use super::{SinglePixelBufferState, SinglePixelBufferUserData};
 use wayland_protocols::wp::single_pixel_buffer::v1::server::wp_single_pixel_buffer_manager_v1::{
     self, WpSinglePixelBufferManagerV1,
 };
 use wayland_server::{
+    backend::ClientId,
     protocol::wl_buffer::{self, WlBuffer},
     DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
 };
@@ -31,11 +32,12 @@ impl<D> Dispatch<WpSinglePixelBufferManagerV1, (), D> for SinglePixelBufferState
 where
     D: Dispatch<WpSinglePixelBufferManagerV1, ()>,
     D: Dispatch<WlBuffer, SinglePixelBufferUserData>,
+    D: BufferHandler,
     D: 'static,
 {
     fn request(
-        _state: &mut D,
-        _client: &wayland_server::Client,
+        state: &mut D,
+        client: &wayland_server::Client,
         _manager: &WpSinglePixelBufferManagerV1,
         request: wp_single_pixel_buffer_manager_v1::Request,
         _data: &(),
@@ -51,6 +53,9 @@ where
                 a,
             } => {
                 data_init.init(buffer, SinglePixelBufferUserData { r, g, b, a });
+                if let Some(audit) = state.resource_audit() {
+                    audit.created(&client.id(), ResourceKind::Buffer);
+                }
             }
             wp_single_pixel_buffer_manager_v1::Request::Destroy => {}
             _ => todo!(),
@@ -79,4 +84,15 @@ where
             _ => unreachable!(),
         }
     }
+
+    fn destroyed(
+        data: &mut D,
+        client: ClientId,
+        _buffer: &wl_buffer::WlBuffer,
+        _udata: &SinglePixelBufferUserData,
+    ) {
+        if let Some(audit) = data.resource_audit() {
+            audit.destroyed(&client, ResourceKind::Buffer);
+        }
+    }
 }
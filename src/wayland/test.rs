@@ -0,0 +1,685 @@
+//! Injecting raw requests into a wayland display
+//!
+//! A [`FakeClient`] connects to a [`Display`](wayland_server::Display) over a socket pair and
+//! writes requests directly in the wayland wire format, bypassing any client library. This allows
+//! tests to send request sequences a well-behaved client would never send, and fuzzers to generate
+//! arbitrary requests from the protocol descriptions with [`FakeClient::send_arbitrary`]. The
+//! generated requests are always well-formed at the wire level: the argument types match the
+//! signature of the request and object arguments reference live objects of the expected interface,
+//! so the fuzzed code is smithay's request handling and not wayland-server's message parsing.
+//!
+//! The fuzz targets in the `fuzz` directory of the repository are built on top of this module.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use smithay::wayland::test::FakeClient;
+//! # use smithay::reexports::wayland_server::{Display, backend::{ClientData, ClientId, DisconnectReason}};
+//! # struct ClientState;
+//! # impl ClientData for ClientState {
+//! #     fn initialized(&self, _: ClientId) {}
+//! #     fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+//! # }
+//! # struct State;
+//! # let mut state = State;
+//! let mut display = Display::<State>::new().unwrap();
+//! let (mut client, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+//! client.get_registry().unwrap();
+//!
+//! let mut data: &[u8] = &[0x13, 0x37, 0x42];
+//! while client.send_arbitrary(&mut data).unwrap() {
+//!     display.dispatch_clients(&mut state).unwrap();
+//!     display.flush_clients().unwrap();
+//!     if !client.receive().unwrap() {
+//!         // the client was disconnected, e.g. because of a protocol error
+//!         break;
+//!     }
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    mem::MaybeUninit,
+    os::unix::{
+        io::{AsFd, OwnedFd},
+        net::UnixStream,
+    },
+    sync::Arc,
+};
+
+use rustix::net::{sendmsg, SendAncillaryBuffer, SendAncillaryMessage, SendFlags};
+use wayland_server::{
+    backend::{
+        protocol::{AllowNull, ArgumentType, Interface},
+        ClientData,
+    },
+    protocol::{wl_display::WlDisplay, wl_registry::WlRegistry},
+    Client, Display, DisplayHandle, Resource,
+};
+
+use super::trace::{ClientTrace, Direction};
+
+/// Object id of the `wl_display` of every client
+pub const DISPLAY_ID: u32 = 1;
+
+// maximum number of file descriptors per message, as enforced by libwayland
+const MAX_FDS: usize = 28;
+
+/// Argument of a request
+#[derive(Debug)]
+pub enum Arg {
+    /// Signed integer
+    Int(i32),
+    /// Unsigned integer
+    Uint(u32),
+    /// Fixed point number in its raw representation
+    Fixed(i32),
+    /// String without the terminating nul byte, `None` for a null string
+    Str(Option<Vec<u8>>),
+    /// Id of an existing object, `0` for null
+    Object(u32),
+    /// Id of an object created by the request
+    NewId(u32),
+    /// Byte array
+    Array(Vec<u8>),
+    /// File descriptor
+    Fd(OwnedFd),
+}
+
+/// Global advertised to a [`FakeClient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    /// Numeric name of the global
+    pub name: u32,
+    /// Interface of the global
+    pub interface: String,
+    /// Version of the global
+    pub version: u32,
+}
+
+/// Event received by a [`FakeClient`], see [`FakeClient::record_events`]
+#[derive(Debug)]
+pub struct Event {
+    /// Id of the object the event was sent to
+    pub object: u32,
+    /// Interface of the object, if known
+    pub interface: Option<&'static Interface>,
+    /// Opcode of the event
+    pub opcode: u16,
+    /// Arguments of the event, without file descriptors
+    ///
+    /// Empty if the interface of the object is unknown.
+    pub args: Vec<Arg>,
+}
+
+impl Event {
+    /// Returns the name of the event, if the interface of the object is known
+    pub fn name(&self) -> Option<&'static str> {
+        self.interface
+            .and_then(|interface| interface.events.get(self.opcode as usize))
+            .map(|event| event.name)
+    }
+}
+
+/// Client writing raw requests, see the [module-level documentation](self)
+pub struct FakeClient {
+    stream: UnixStream,
+    objects: BTreeMap<u32, &'static Interface>,
+    next_id: u32,
+    registry: Option<u32>,
+    globals: Vec<Global>,
+    incoming: Vec<u8>,
+    events: Option<Vec<Event>>,
+    trace: Option<ClientTrace>,
+}
+
+impl std::fmt::Debug for FakeClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeClient")
+            .field("objects", &self.objects.len())
+            .field("globals", &self.globals)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FakeClient {
+    /// Connect a new client to the display
+    pub fn connect(
+        handle: &mut DisplayHandle,
+        data: Arc<dyn ClientData>,
+    ) -> io::Result<(FakeClient, Client)> {
+        let (server, stream) = UnixStream::pair()?;
+        let client = handle.insert_client(server, data)?;
+        stream.set_nonblocking(true)?;
+
+        let client_side = FakeClient {
+            stream,
+            objects: BTreeMap::from([(DISPLAY_ID, WlDisplay::interface())]),
+            next_id: DISPLAY_ID + 1,
+            registry: None,
+            globals: Vec::new(),
+            incoming: Vec::new(),
+            events: None,
+            trace: None,
+        };
+        Ok((client_side, client))
+    }
+
+    /// Allocate the id of a new object
+    ///
+    /// The id is assumed to be in use from now on, pass it as [`Arg::NewId`] to a request.
+    pub fn new_id(&mut self, interface: &'static Interface) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.objects.insert(id, interface);
+        id
+    }
+
+    /// Returns the interface of a live object
+    pub fn interface(&self, id: u32) -> Option<&'static Interface> {
+        self.objects.get(&id).copied()
+    }
+
+    /// Globals advertised on the registry so far, see [`FakeClient::receive`]
+    pub fn globals(&self) -> &[Global] {
+        &self.globals
+    }
+
+    /// Keep the events received from now on, to be retrieved with [`FakeClient::take_events`]
+    ///
+    /// Objects created by events are tracked either way, recording is off initially.
+    pub fn record_events(&mut self, enabled: bool) {
+        self.events = enabled.then(Vec::new);
+    }
+
+    /// Trace the connection of this client from now on
+    ///
+    /// Pass a new [`ClientTrace`] right after connecting to trace the complete connection.
+    pub fn set_trace(&mut self, trace: Option<ClientTrace>) {
+        self.trace = trace;
+    }
+
+    /// Returns the events recorded since the last call
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Send a request
+    ///
+    /// Objects destroyed by destructor requests are forgotten.
+    pub fn send(&mut self, object: u32, opcode: u16, args: Vec<Arg>) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        let mut fds = Vec::new();
+        encode(object, opcode, args, &mut bytes, &mut fds);
+        if let Some(trace) = &self.trace {
+            trace.record(Direction::Request, &bytes);
+        }
+
+        if fds.is_empty() {
+            self.stream.write_all(&bytes)?;
+        } else {
+            let borrowed = fds.iter().map(|fd| fd.as_fd()).collect::<Vec<_>>();
+            let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(MAX_FDS))];
+            let mut ancillary = SendAncillaryBuffer::new(&mut space);
+            if !ancillary.push(SendAncillaryMessage::ScmRights(&borrowed)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "too many file descriptors",
+                ));
+            }
+            let sent = sendmsg(
+                &self.stream,
+                &[io::IoSlice::new(&bytes)],
+                &mut ancillary,
+                SendFlags::empty(),
+            )?;
+            if sent != bytes.len() {
+                // the fds were sent with the first part, the rest does not need them
+                self.stream.write_all(&bytes[sent..])?;
+            }
+        }
+
+        let destructor = self
+            .objects
+            .get(&object)
+            .and_then(|interface| interface.requests.get(opcode as usize))
+            .is_some_and(|request| request.is_destructor);
+        if destructor {
+            self.objects.remove(&object);
+        }
+        Ok(())
+    }
+
+    /// Send a request by its name in the protocol description of `interface`
+    pub fn send_request(
+        &mut self,
+        object: u32,
+        interface: &'static Interface,
+        name: &str,
+        args: Vec<Arg>,
+    ) -> io::Result<()> {
+        let opcode = interface
+            .requests
+            .iter()
+            .position(|request| request.name == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the interface has no such request"))?;
+        self.send(object, opcode as u16, args)
+    }
+
+    /// Let the display handle the pending requests and read its answers
+    ///
+    /// Returns the events [recorded](FakeClient::record_events) since the last call.
+    pub fn roundtrip<D: 'static>(
+        &mut self,
+        display: &mut Display<D>,
+        state: &mut D,
+    ) -> io::Result<Vec<Event>> {
+        display.dispatch_clients(state)?;
+        display.flush_clients()?;
+        self.receive()?;
+        Ok(self.take_events())
+    }
+
+    /// Create the registry with `wl_display.get_registry`
+    pub fn get_registry(&mut self) -> io::Result<u32> {
+        let registry = self.new_id(WlRegistry::interface());
+        self.send(DISPLAY_ID, 1, vec![Arg::NewId(registry)])?;
+        self.registry = Some(registry);
+        Ok(registry)
+    }
+
+    /// Bind a global advertised on the registry
+    ///
+    /// Returns the id of the new object.
+    pub fn bind(&mut self, name: u32, interface: &'static Interface, version: u32) -> io::Result<u32> {
+        let registry = self
+            .registry
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the registry was not created"))?;
+        let id = self.new_id(interface);
+        self.send(
+            registry,
+            0,
+            vec![
+                Arg::Uint(name),
+                Arg::Str(Some(interface.name.as_bytes().to_vec())),
+                Arg::Uint(version),
+                Arg::NewId(id),
+            ],
+        )?;
+        Ok(id)
+    }
+
+    /// Bind the first global advertised with the interface, at most with the given version
+    pub fn bind_global(&mut self, interface: &'static Interface, version: u32) -> io::Result<u32> {
+        let global = self
+            .globals
+            .iter()
+            .find(|global| global.interface == interface.name)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the global is not advertised"))?;
+        self.bind(global.name, interface, version.min(global.version))
+    }
+
+    /// Read the events sent by the compositor
+    ///
+    /// Only the globals advertised on the registry and the objects created and deleted by events
+    /// are interpreted, all other events are dropped unless they are
+    /// [recorded](FakeClient::record_events). File descriptors are always dropped. Returns `false`
+    /// once the compositor closed the connection.
+    pub fn receive(&mut self) -> io::Result<bool> {
+        let mut buffer = [0u8; 4096];
+        let connected = loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break false,
+                Ok(len) => {
+                    if let Some(trace) = &self.trace {
+                        trace.record(Direction::Event, &buffer[..len]);
+                    }
+                    self.incoming.extend_from_slice(&buffer[..len]);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break true,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => break false,
+                Err(err) => return Err(err),
+            }
+        };
+
+        let mut offset = 0;
+        while let Some((object, opcode, body)) = next_message(&self.incoming[offset..]) {
+            offset += 8 + body.len();
+            if Some(object) == self.registry {
+                self.registry_event(opcode, body);
+            }
+
+            let interface = self.objects.get(&object).copied();
+            let event = interface.and_then(|interface| interface.events.get(opcode as usize));
+            let args = event
+                .and_then(|event| decode(event.signature, body))
+                .unwrap_or_default();
+            for arg in &args {
+                if let (Arg::NewId(id), Some(child)) = (arg, event.and_then(|event| event.child_interface)) {
+                    self.objects.insert(*id, child);
+                }
+            }
+            // wl_display.delete_id
+            if let (DISPLAY_ID, 1, [Arg::Uint(id)]) = (object, opcode, args.as_slice()) {
+                self.objects.remove(id);
+            }
+            if let Some(events) = &mut self.events {
+                events.push(Event {
+                    object,
+                    interface,
+                    opcode,
+                    args,
+                });
+            }
+        }
+        self.incoming.drain(..offset);
+        Ok(connected)
+    }
+
+    fn registry_event(&mut self, opcode: u16, body: &[u8]) {
+        let mut body = body;
+        match opcode {
+            // global
+            0 => {
+                let (Some(name), Some(interface), Some(version)) =
+                    (read_u32(&mut body), read_str(&mut body), read_u32(&mut body))
+                else {
+                    return;
+                };
+                self.globals.push(Global {
+                    name,
+                    interface,
+                    version,
+                });
+            }
+            // global_remove
+            1 => {
+                if let Some(name) = read_u32(&mut body) {
+                    self.globals.retain(|global| global.name != name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a request generated from arbitrary data
+    ///
+    /// The request, its target object and arguments are chosen by consuming bytes from the front
+    /// of `data`. Requests creating objects of an unknown interface, e.g. `wl_registry.bind`, are
+    /// skipped, use [`FakeClient::bind`] to bind globals. Returns `false` without sending anything
+    /// once `data` is exhausted.
+    pub fn send_arbitrary(&mut self, data: &mut &[u8]) -> io::Result<bool> {
+        let Some([object_byte, opcode_byte]) = take(data) else {
+            return Ok(false);
+        };
+        let (&object, &interface) = self
+            .objects
+            .iter()
+            .nth(object_byte as usize % self.objects.len())
+            .unwrap();
+        if interface.requests.is_empty() {
+            return Ok(true);
+        }
+        let opcode = opcode_byte as usize % interface.requests.len();
+        let request = &interface.requests[opcode];
+
+        let mut args = Vec::with_capacity(request.signature.len());
+        let mut object_args = request.arg_interfaces.iter();
+        for ty in request.signature {
+            let arg = match ty {
+                ArgumentType::Int => Arg::Int(i32::from_ne_bytes(take_padded(data))),
+                ArgumentType::Uint => Arg::Uint(u32::from_ne_bytes(take_padded(data))),
+                ArgumentType::Fixed => Arg::Fixed(i32::from_ne_bytes(take_padded(data))),
+                ArgumentType::Str(allow_null) => {
+                    let [len] = take_padded(data);
+                    if len == u8::MAX && matches!(allow_null, AllowNull::Yes) {
+                        Arg::Str(None)
+                    } else {
+                        let len = (len as usize % 32).min(data.len());
+                        let (bytes, rest) = data.split_at(len);
+                        *data = rest;
+                        // nul bytes would end the string early
+                        Arg::Str(Some(bytes.iter().map(|&b| b.max(1)).collect()))
+                    }
+                }
+                ArgumentType::Object(allow_null) => {
+                    let [choice] = take_padded(data);
+                    let expected = object_args.next();
+                    let candidates = self
+                        .objects
+                        .iter()
+                        .filter(|(_, interface)| {
+                            expected.is_none_or(|expected| expected.name == interface.name)
+                        })
+                        .map(|(&id, _)| id)
+                        .collect::<Vec<_>>();
+                    if matches!(allow_null, AllowNull::Yes) && (choice == u8::MAX || candidates.is_empty()) {
+                        Arg::Object(0)
+                    } else if candidates.is_empty() {
+                        // the request cannot be sent well-formed
+                        return Ok(true);
+                    } else {
+                        Arg::Object(candidates[choice as usize % candidates.len()])
+                    }
+                }
+                ArgumentType::NewId => match request.child_interface {
+                    Some(child) => Arg::NewId(self.new_id(child)),
+                    None => return Ok(true),
+                },
+                ArgumentType::Array => {
+                    let [len] = take_padded(data);
+                    let len = (len as usize).min(data.len());
+                    let (bytes, rest) = data.split_at(len);
+                    *data = rest;
+                    Arg::Array(bytes.to_vec())
+                }
+                ArgumentType::Fd => {
+                    let [pages] = take_padded(data);
+                    let file = tempfile::tempfile()?;
+                    file.set_len(pages as u64 * 4096)?;
+                    Arg::Fd(file.into())
+                }
+            };
+            args.push(arg);
+        }
+
+        self.send(object, opcode as u16, args)?;
+        Ok(true)
+    }
+}
+
+fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+    let (bytes, rest) = data.split_first_chunk::<N>()?;
+    *data = rest;
+    Some(*bytes)
+}
+
+// missing bytes are filled with zeros, so any input produces a complete request
+fn take_padded<const N: usize>(data: &mut &[u8]) -> [u8; N] {
+    let mut bytes = [0; N];
+    let len = N.min(data.len());
+    bytes[..len].copy_from_slice(&data[..len]);
+    *data = &data[len..];
+    bytes
+}
+
+fn pad(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+}
+
+fn encode(object: u32, opcode: u16, args: Vec<Arg>, bytes: &mut Vec<u8>, fds: &mut Vec<OwnedFd>) {
+    let start = bytes.len();
+    bytes.extend_from_slice(&object.to_ne_bytes());
+    // the size is filled in below
+    bytes.extend_from_slice(&[0; 4]);
+
+    for arg in args {
+        match arg {
+            Arg::Int(value) | Arg::Fixed(value) => bytes.extend_from_slice(&value.to_ne_bytes()),
+            Arg::Uint(value) | Arg::Object(value) | Arg::NewId(value) => {
+                bytes.extend_from_slice(&value.to_ne_bytes())
+            }
+            Arg::Str(None) => bytes.extend_from_slice(&0u32.to_ne_bytes()),
+            Arg::Str(Some(string)) => {
+                bytes.extend_from_slice(&(string.len() as u32 + 1).to_ne_bytes());
+                bytes.extend_from_slice(&string);
+                bytes.push(0);
+                pad(bytes);
+            }
+            Arg::Array(array) => {
+                bytes.extend_from_slice(&(array.len() as u32).to_ne_bytes());
+                bytes.extend_from_slice(&array);
+                pad(bytes);
+            }
+            Arg::Fd(fd) => fds.push(fd),
+        }
+    }
+
+    let size = (bytes.len() - start) as u32;
+    bytes[start + 4..start + 8].copy_from_slice(&((size << 16) | opcode as u32).to_ne_bytes());
+}
+
+fn decode(signature: &[ArgumentType], mut body: &[u8]) -> Option<Vec<Arg>> {
+    let body = &mut body;
+    signature
+        .iter()
+        .filter(|ty| !matches!(ty, ArgumentType::Fd))
+        .map(|ty| {
+            Some(match ty {
+                ArgumentType::Int => Arg::Int(read_u32(body)? as i32),
+                ArgumentType::Uint => Arg::Uint(read_u32(body)?),
+                ArgumentType::Fixed => Arg::Fixed(read_u32(body)? as i32),
+                ArgumentType::Object(_) => Arg::Object(read_u32(body)?),
+                ArgumentType::NewId => Arg::NewId(read_u32(body)?),
+                ArgumentType::Str(_) => Arg::Str(read_array(body)?.map(|mut string| {
+                    string.pop();
+                    string
+                })),
+                ArgumentType::Array => Arg::Array(read_array(body)?.unwrap_or_default()),
+                ArgumentType::Fd => unreachable!(),
+            })
+        })
+        .collect()
+}
+
+fn next_message(bytes: &[u8]) -> Option<(u32, u16, &[u8])> {
+    let object = u32::from_ne_bytes(bytes.get(0..4)?.try_into().unwrap());
+    let word = u32::from_ne_bytes(bytes.get(4..8)?.try_into().unwrap());
+    let size = (word >> 16) as usize;
+    let body = bytes.get(8..size.max(8))?;
+    Some((object, word as u16, body))
+}
+
+fn read_u32(body: &mut &[u8]) -> Option<u32> {
+    take::<4>(body).map(u32::from_ne_bytes)
+}
+
+// `None` inside for zero-length arrays, i.e. null strings
+fn read_array(body: &mut &[u8]) -> Option<Option<Vec<u8>>> {
+    let len = read_u32(body)? as usize;
+    let padded = len.next_multiple_of(4);
+    if body.len() < padded {
+        return None;
+    }
+    let array = (len > 0).then(|| body[..len].to_vec());
+    *body = &body[padded..];
+    Some(array)
+}
+
+fn read_str(body: &mut &[u8]) -> Option<String> {
+    let len = read_u32(body)? as usize;
+    let padded = len.next_multiple_of(4);
+    if body.len() < padded {
+        return None;
+    }
+    let string = String::from_utf8_lossy(&body[..len.saturating_sub(1)]).into_owned();
+    *body = &body[padded..];
+    Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wayland_server::{
+        backend::{ClientId, DisconnectReason},
+        protocol::wl_callback::WlCallback,
+    };
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    #[test]
+    fn wire_format() {
+        let mut bytes = Vec::new();
+        encode(
+            3,
+            2,
+            vec![
+                Arg::Str(Some(b"abc".to_vec())),
+                Arg::Array(vec![1]),
+                Arg::Object(0),
+            ],
+            &mut bytes,
+            &mut Vec::new(),
+        );
+        assert_eq!(bytes.len(), 28);
+        let (object, opcode, mut body) = next_message(&bytes).unwrap();
+        assert_eq!((object, opcode), (3, 2));
+        assert_eq!(read_str(&mut body).as_deref(), Some("abc"));
+        assert_eq!(read_u32(&mut body), Some(1));
+    }
+
+    #[test]
+    fn protocol_error_disconnects() {
+        let mut display = Display::<()>::new().unwrap();
+        let (mut client, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+
+        client.get_registry().unwrap();
+        display.dispatch_clients(&mut ()).unwrap();
+        display.flush_clients().unwrap();
+        assert!(client.receive().unwrap());
+        assert!(client.globals().is_empty());
+
+        // wl_display has no request with opcode 7
+        client.send(DISPLAY_ID, 7, Vec::new()).unwrap();
+        let mut connected = true;
+        for _ in 0..3 {
+            display.dispatch_clients(&mut ()).unwrap();
+            display.flush_clients().unwrap();
+            connected = client.receive().unwrap();
+        }
+        assert!(!connected);
+    }
+
+    #[test]
+    fn records_events() {
+        let mut display = Display::<()>::new().unwrap();
+        let (mut client, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+        client.record_events(true);
+
+        let callback = client.new_id(WlCallback::interface());
+        client
+            .send_request(
+                DISPLAY_ID,
+                WlDisplay::interface(),
+                "sync",
+                vec![Arg::NewId(callback)],
+            )
+            .unwrap();
+        let events = client.roundtrip(&mut display, &mut ()).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].object, events[0].name()), (callback, Some("done")));
+        assert_eq!(events[1].name(), Some("delete_id"));
+        assert!(matches!(events[1].args.as_slice(), [Arg::Uint(id)] if *id == callback));
+        assert!(client.interface(callback).is_none());
+        assert!(client.take_events().is_empty());
+    }
+}
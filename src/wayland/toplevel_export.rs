@@ -0,0 +1,1213 @@
+//! Toplevel export
+//!
+//! This implements the ext-image-copy-capture protocol for toplevels, which are selected with the
+//! ext-foreign-toplevel-image-capture-source protocol from the handles of the
+//! [foreign toplevel list](crate::wayland::foreign_toplevel_list). It allows privileged clients,
+//! like screen recorders or the window sharing of desktop portals, to copy the contents of a
+//! single window into buffers allocated by the client.
+//!
+//! In order to advertise the globals call [`ToplevelExportState::new`] and delegate events to it
+//! with [`delegate_toplevel_export`](crate::delegate_toplevel_export). When a client starts
+//! capturing a toplevel, [`ToplevelExportHandler::buffer_constraints`] is asked for the size and
+//! formats of buffers the toplevel can be copied into. Captures are passed to
+//! [`ToplevelExportHandler::capture_frame`] as a [`ToplevelExportFrame`], which should be answered
+//! once the toplevel was rendered into [`ToplevelExportFrame::buffer`]:
+//!
+//! - with [`ToplevelExportFrame::success`] passing the damage of the buffer,
+//! - or with [`ToplevelExportFrame::fail`] if the toplevel cannot be copied.
+//!
+//! Whenever the size of a toplevel changes, the constraints have to be updated with
+//! [`ToplevelExportState::update_constraints`]. Captures of closed toplevels are stopped
+//! automatically, [`ToplevelExportState::stop`] stops them early.
+//!
+//! Capturing the cursor separately is not supported, cursor sessions are stopped right away.
+//!
+//! ```
+//! use smithay::delegate_toplevel_export;
+//! use smithay::reexports::wayland_server::protocol::wl_shm;
+//! use smithay::wayland::foreign_toplevel_list::ForeignToplevelHandle;
+//! use smithay::wayland::toplevel_export::{
+//!     BufferConstraints, ToplevelExportFrame, ToplevelExportHandler, ToplevelExportState,
+//! };
+//!
+//! # struct State { toplevel_export: ToplevelExportState, pending_frames: Vec<ToplevelExportFrame> }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! let toplevel_export = ToplevelExportState::new::<State, _>(&display.handle(), |_client| true);
+//!
+//! impl ToplevelExportHandler for State {
+//!     fn toplevel_export_state(&mut self) -> &mut ToplevelExportState {
+//!         &mut self.toplevel_export
+//!     }
+//!
+//!     fn buffer_constraints(&mut self, toplevel: &ForeignToplevelHandle) -> Option<BufferConstraints> {
+//!         // look up the size of the window of `toplevel`
+//!         Some(BufferConstraints {
+//!             size: (800, 600).into(),
+//!             shm: vec![wl_shm::Format::Argb8888, wl_shm::Format::Xrgb8888],
+//!             dma: None,
+//!         })
+//!     }
+//!
+//!     fn capture_frame(&mut self, frame: ToplevelExportFrame) {
+//!         // render `frame.toplevel()` into `frame.buffer()` and call `frame.success`
+//!         self.pending_frames.push(frame);
+//!     }
+//! }
+//!
+//! delegate_toplevel_export!(State);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use wayland_protocols::ext::{
+    image_capture_source::v1::server::{
+        ext_foreign_toplevel_image_capture_source_manager_v1::{
+            self, ExtForeignToplevelImageCaptureSourceManagerV1,
+        },
+        ext_image_capture_source_v1::{self, ExtImageCaptureSourceV1},
+    },
+    image_copy_capture::v1::server::{
+        ext_image_copy_capture_cursor_session_v1::{self, ExtImageCopyCaptureCursorSessionV1},
+        ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1, FailureReason},
+        ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1},
+        ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+    },
+};
+use wayland_server::{
+    backend::GlobalId,
+    protocol::{wl_buffer::WlBuffer, wl_shm},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+
+use crate::{
+    backend::allocator::{Buffer, Format},
+    utils::{Buffer as BufferCoords, Rectangle, Region, Size, Transform},
+    wayland::{
+        dmabuf::get_dmabuf,
+        foreign_toplevel_list::{ForeignToplevelHandle, ForeignToplevelWeakHandle},
+        shm::ShmBufferUserData,
+    },
+};
+
+/// Handler trait for toplevel export
+pub trait ToplevelExportHandler:
+    GlobalDispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ToplevelExportGlobalData>
+    + Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()>
+    + Dispatch<ExtImageCaptureSourceV1, ToplevelCaptureSource>
+    + GlobalDispatch<ExtImageCopyCaptureManagerV1, ToplevelExportGlobalData>
+    + Dispatch<ExtImageCopyCaptureManagerV1, ()>
+    + Dispatch<ExtImageCopyCaptureSessionV1, ToplevelExportSessionData>
+    + Dispatch<ExtImageCopyCaptureCursorSessionV1, CursorSessionData>
+    + Dispatch<ExtImageCopyCaptureFrameV1, ToplevelExportFrameData>
+    + 'static
+{
+    /// [`ToplevelExportState`] getter
+    fn toplevel_export_state(&mut self) -> &mut ToplevelExportState;
+
+    /// A client started capturing a toplevel
+    ///
+    /// Returns the constraints for buffers the toplevel can be copied into, or `None` to stop
+    /// the capture right away.
+    fn buffer_constraints(&mut self, toplevel: &ForeignToplevelHandle) -> Option<BufferConstraints>;
+
+    /// A client requested a copy of a toplevel
+    ///
+    /// The buffer of the frame matches the current constraints of the toplevel. The frame has to
+    /// be answered exactly once, dropping it fails the capture with [`FailureReason::Unknown`].
+    fn capture_frame(&mut self, frame: ToplevelExportFrame);
+}
+
+/// Constraints for buffers a toplevel is copied into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferConstraints {
+    /// Size of the buffers in pixels
+    pub size: Size<i32, BufferCoords>,
+    /// Supported shm formats
+    pub shm: Vec<wl_shm::Format>,
+    /// Supported dmabufs, `None` if the toplevel can only be copied into shm buffers
+    pub dma: Option<DmabufConstraints>,
+}
+
+/// Constraints for dmabufs a toplevel is copied into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmabufConstraints {
+    /// Device the dmabufs have to be allocated on
+    pub node: libc::dev_t,
+    /// Supported formats and modifiers
+    pub formats: Vec<Format>,
+}
+
+impl BufferConstraints {
+    fn matches(&self, buffer: &WlBuffer) -> bool {
+        if let Some(data) = buffer.data::<ShmBufferUserData>() {
+            return Size::from((data.data.width, data.data.height)) == self.size
+                && self.shm.contains(&data.data.format);
+        }
+        if let Ok(dmabuf) = get_dmabuf(buffer) {
+            return dmabuf.size() == self.size
+                && self
+                    .dma
+                    .as_ref()
+                    .is_some_and(|dma| dma.formats.contains(&dmabuf.format()));
+        }
+        false
+    }
+
+    fn send(&self, session: &ExtImageCopyCaptureSessionV1) {
+        session.buffer_size(self.size.w as u32, self.size.h as u32);
+        for format in &self.shm {
+            session.shm_format(*format);
+        }
+        if let Some(dma) = &self.dma {
+            session.dmabuf_device(dma.node.to_ne_bytes().to_vec());
+            let mut modifiers = BTreeMap::<u32, Vec<u8>>::new();
+            for format in &dma.formats {
+                let modifier: u64 = format.modifier.into();
+                modifiers
+                    .entry(format.code as u32)
+                    .or_default()
+                    .extend_from_slice(&modifier.to_ne_bytes());
+            }
+            for (code, modifiers) in modifiers {
+                session.dmabuf_format(code, modifiers);
+            }
+        }
+        session.done();
+    }
+}
+
+/// Data of the toplevel export globals
+#[allow(missing_debug_implementations)]
+pub struct ToplevelExportGlobalData {
+    filter: Arc<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// State of the toplevel export globals
+#[derive(Debug)]
+pub struct ToplevelExportState {
+    source_manager: GlobalId,
+    copy_manager: GlobalId,
+    sessions: Vec<ExtImageCopyCaptureSessionV1>,
+}
+
+impl ToplevelExportState {
+    /// Create the [`ExtForeignToplevelImageCaptureSourceManagerV1`] and
+    /// [`ExtImageCopyCaptureManagerV1`] globals
+    ///
+    /// As the protocols give access to the contents of all toplevels, the globals should only be
+    /// visible to trusted clients, which is decided by `filter`.
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> ToplevelExportState
+    where
+        D: ToplevelExportHandler,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let filter: Arc<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync> = Arc::new(filter);
+        let source_manager = display.create_global::<D, ExtForeignToplevelImageCaptureSourceManagerV1, _>(
+            1,
+            ToplevelExportGlobalData {
+                filter: filter.clone(),
+            },
+        );
+        let copy_manager = display
+            .create_global::<D, ExtImageCopyCaptureManagerV1, _>(1, ToplevelExportGlobalData { filter });
+        ToplevelExportState {
+            source_manager,
+            copy_manager,
+            sessions: Vec::new(),
+        }
+    }
+
+    /// Returns the [`ExtForeignToplevelImageCaptureSourceManagerV1`] global id
+    pub fn source_manager_global(&self) -> GlobalId {
+        self.source_manager.clone()
+    }
+
+    /// Returns the [`ExtImageCopyCaptureManagerV1`] global id
+    pub fn copy_manager_global(&self) -> GlobalId {
+        self.copy_manager.clone()
+    }
+
+    /// Update the buffer constraints of all captures of a toplevel, e.g. after it was resized
+    ///
+    /// Frames with buffers not matching the new constraints fail with
+    /// [`FailureReason::BufferConstraints`], `None` stops the captures.
+    pub fn update_constraints(
+        &mut self,
+        toplevel: &ForeignToplevelHandle,
+        constraints: Option<BufferConstraints>,
+    ) {
+        self.sessions.retain(|session| session.is_alive());
+        for session in &self.sessions {
+            let data = session.data::<ToplevelExportSessionData>().unwrap();
+            if data.toplevel().as_ref() != Some(toplevel) {
+                continue;
+            }
+            let mut inner = data.inner.lock().unwrap();
+            if inner.stopped || inner.constraints == constraints {
+                continue;
+            }
+            match &constraints {
+                Some(constraints) => constraints.send(session),
+                None => {
+                    inner.stopped = true;
+                    session.stopped();
+                }
+            }
+            inner.constraints = constraints.clone();
+        }
+    }
+
+    /// Stop all captures of a toplevel
+    pub fn stop(&mut self, toplevel: &ForeignToplevelHandle) {
+        self.update_constraints(toplevel, None);
+    }
+}
+
+/// Data of a toplevel capture source
+#[derive(Debug)]
+pub struct ToplevelCaptureSource {
+    toplevel: Option<ForeignToplevelWeakHandle>,
+}
+
+/// Data of a toplevel capture session
+#[derive(Debug)]
+pub struct ToplevelExportSessionData {
+    toplevel: Option<ForeignToplevelWeakHandle>,
+    paint_cursors: bool,
+    inner: Mutex<SessionInner>,
+}
+
+#[derive(Debug)]
+struct SessionInner {
+    constraints: Option<BufferConstraints>,
+    frame: Option<Weak<ExtImageCopyCaptureFrameV1>>,
+    stopped: bool,
+}
+
+impl ToplevelExportSessionData {
+    fn toplevel(&self) -> Option<ForeignToplevelHandle> {
+        self.toplevel.as_ref().and_then(|toplevel| toplevel.upgrade())
+    }
+
+    // stops the session if the toplevel is gone, returns the constraints of a running session
+    fn constraints(&self, session: &ExtImageCopyCaptureSessionV1) -> Option<BufferConstraints> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.stopped && self.toplevel().is_none_or(|toplevel| toplevel.is_closed()) {
+            inner.stopped = true;
+            session.stopped();
+        }
+        if inner.stopped {
+            return None;
+        }
+        inner.constraints.clone()
+    }
+}
+
+/// Data of a cursor capture session
+#[derive(Debug)]
+pub struct CursorSessionData {
+    source: Option<ForeignToplevelWeakHandle>,
+    session_created: Mutex<bool>,
+}
+
+/// Data of a capture frame
+#[derive(Debug)]
+pub struct ToplevelExportFrameData {
+    session: Weak<ExtImageCopyCaptureSessionV1>,
+    inner: Mutex<FrameInner>,
+}
+
+#[derive(Debug, Default)]
+struct FrameInner {
+    buffer: Option<WlBuffer>,
+    damage: Region<BufferCoords>,
+    captured: bool,
+}
+
+/// A pending copy of a toplevel
+#[derive(Debug)]
+pub struct ToplevelExportFrame {
+    frame: ExtImageCopyCaptureFrameV1,
+    session: ExtImageCopyCaptureSessionV1,
+    toplevel: ForeignToplevelHandle,
+    buffer: WlBuffer,
+    damage: Vec<Rectangle<i32, BufferCoords>>,
+    paint_cursors: bool,
+    answered: bool,
+}
+
+impl ToplevelExportFrame {
+    /// The toplevel to copy
+    pub fn toplevel(&self) -> &ForeignToplevelHandle {
+        &self.toplevel
+    }
+
+    /// The buffer to copy the toplevel into
+    pub fn buffer(&self) -> &WlBuffer {
+        &self.buffer
+    }
+
+    /// Regions of the buffer the client has modified since the last capture
+    ///
+    /// Only these and the regions the toplevel changed since the last copy into the buffer have
+    /// to be redrawn.
+    pub fn buffer_damage(&self) -> &[Rectangle<i32, BufferCoords>] {
+        &self.damage
+    }
+
+    /// Whether cursors should be drawn into the buffer
+    pub fn paint_cursors(&self) -> bool {
+        self.paint_cursors
+    }
+
+    /// Returns `true` if the client is no longer interested in the frame
+    pub fn is_destroyed(&self) -> bool {
+        !self.frame.is_alive()
+    }
+
+    /// Finish the copy
+    ///
+    /// `transform` is the transformation of the buffer contents, `damage` the regions of the
+    /// buffer updated by this copy and `presented` the time the copied contents were presented
+    /// in the `CLOCK_MONOTONIC` domain. Overlapping damage is merged before it is sent.
+    ///
+    /// The frame fails instead, if the capture was stopped or the buffer no longer matches the
+    /// constraints of the toplevel in the meantime.
+    pub fn success(
+        mut self,
+        transform: Transform,
+        damage: impl IntoIterator<Item = Rectangle<i32, BufferCoords>>,
+        presented: Duration,
+    ) {
+        self.answered = true;
+        if !self.frame.is_alive() {
+            return;
+        }
+
+        let data = self.session.data::<ToplevelExportSessionData>().unwrap();
+        match data.constraints(&self.session) {
+            None => {
+                self.frame.failed(FailureReason::Stopped);
+                return;
+            }
+            Some(constraints) if !constraints.matches(&self.buffer) => {
+                self.frame.failed(FailureReason::BufferConstraints);
+                return;
+            }
+            Some(_) => {}
+        }
+
+        self.frame.transform(transform.into());
+        let damage = damage.into_iter().collect::<Region<BufferCoords>>();
+        for rect in damage.rects() {
+            self.frame
+                .damage(rect.loc.x, rect.loc.y, rect.size.w, rect.size.h);
+        }
+        let secs = presented.as_secs();
+        self.frame
+            .presentation_time((secs >> 32) as u32, secs as u32, presented.subsec_nanos());
+        self.frame.ready();
+    }
+
+    /// Fail the copy
+    ///
+    /// Use [`FailureReason::BufferConstraints`] if the buffer is not suitable and
+    /// [`FailureReason::Stopped`] if the toplevel can no longer be captured.
+    pub fn fail(mut self, reason: FailureReason) {
+        self.answered = true;
+        if self.frame.is_alive() {
+            self.frame.failed(reason);
+        }
+    }
+}
+
+impl Drop for ToplevelExportFrame {
+    fn drop(&mut self) {
+        if !self.answered && self.frame.is_alive() {
+            self.frame.failed(FailureReason::Unknown);
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler>
+    GlobalDispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ToplevelExportGlobalData, D>
+    for ToplevelExportState
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtForeignToplevelImageCaptureSourceManagerV1>,
+        _global_data: &ToplevelExportGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &ToplevelExportGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, (), D>
+    for ToplevelExportState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        request: ext_foreign_toplevel_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_foreign_toplevel_image_capture_source_manager_v1::Request::CreateSource {
+                source,
+                toplevel_handle,
+            } => {
+                // sources of closed toplevels stay valid, their sessions are stopped right away
+                let toplevel = ForeignToplevelHandle::from_resource(&toplevel_handle)
+                    .map(|toplevel| toplevel.downgrade());
+                data_init.init(source, ToplevelCaptureSource { toplevel });
+            }
+            ext_foreign_toplevel_image_capture_source_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtImageCaptureSourceV1, ToplevelCaptureSource, D>
+    for ToplevelExportState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ExtImageCaptureSourceV1,
+        request: ext_image_capture_source_v1::Request,
+        _data: &ToplevelCaptureSource,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_capture_source_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler> GlobalDispatch<ExtImageCopyCaptureManagerV1, ToplevelExportGlobalData, D>
+    for ToplevelExportState
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtImageCopyCaptureManagerV1>,
+        _global_data: &ToplevelExportGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &ToplevelExportGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtImageCopyCaptureManagerV1, (), D> for ToplevelExportState {
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &ExtImageCopyCaptureManagerV1,
+        request: ext_image_copy_capture_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_manager_v1::Request::CreateSession {
+                session,
+                source,
+                options,
+            } => {
+                let toplevel = source
+                    .data::<ToplevelCaptureSource>()
+                    .and_then(|source| source.toplevel.clone());
+                let options = options.into_result();
+                let handle = toplevel
+                    .as_ref()
+                    .and_then(|toplevel| toplevel.upgrade())
+                    .filter(|toplevel| !toplevel.is_closed());
+                let constraints = match (&options, handle) {
+                    (Ok(_), Some(handle)) => state.buffer_constraints(&handle),
+                    _ => None,
+                };
+
+                let session = data_init.init(
+                    session,
+                    ToplevelExportSessionData {
+                        toplevel,
+                        paint_cursors: options.as_ref().is_ok_and(|options| {
+                            options.contains(ext_image_copy_capture_manager_v1::Options::PaintCursors)
+                        }),
+                        inner: Mutex::new(SessionInner {
+                            constraints: constraints.clone(),
+                            frame: None,
+                            stopped: constraints.is_none(),
+                        }),
+                    },
+                );
+                if options.is_err() {
+                    resource.post_error(
+                        ext_image_copy_capture_manager_v1::Error::InvalidOption,
+                        "unknown capture option",
+                    );
+                    return;
+                }
+                match constraints {
+                    Some(constraints) => constraints.send(&session),
+                    None => session.stopped(),
+                }
+
+                let export_state = state.toplevel_export_state();
+                export_state.sessions.retain(|session| session.is_alive());
+                export_state.sessions.push(session);
+            }
+            ext_image_copy_capture_manager_v1::Request::CreatePointerCursorSession {
+                session,
+                source,
+                ..
+            } => {
+                let source = source
+                    .data::<ToplevelCaptureSource>()
+                    .and_then(|source| source.toplevel.clone());
+                data_init.init(
+                    session,
+                    CursorSessionData {
+                        source,
+                        session_created: Mutex::new(false),
+                    },
+                );
+            }
+            ext_image_copy_capture_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtImageCopyCaptureSessionV1, ToplevelExportSessionData, D>
+    for ToplevelExportState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ExtImageCopyCaptureSessionV1,
+        request: ext_image_copy_capture_session_v1::Request,
+        data: &ToplevelExportSessionData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_session_v1::Request::CreateFrame { frame } => {
+                let frame = data_init.init(
+                    frame,
+                    ToplevelExportFrameData {
+                        session: resource.downgrade(),
+                        inner: Mutex::new(FrameInner::default()),
+                    },
+                );
+                let mut inner = data.inner.lock().unwrap();
+                if inner.frame.as_ref().is_some_and(|frame| frame.upgrade().is_ok()) {
+                    resource.post_error(
+                        ext_image_copy_capture_session_v1::Error::DuplicateFrame,
+                        "the previous frame was not destroyed",
+                    );
+                    return;
+                }
+                inner.frame = Some(frame.downgrade());
+            }
+            ext_image_copy_capture_session_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtImageCopyCaptureCursorSessionV1, CursorSessionData, D>
+    for ToplevelExportState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ExtImageCopyCaptureCursorSessionV1,
+        request: ext_image_copy_capture_cursor_session_v1::Request,
+        data: &CursorSessionData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_cursor_session_v1::Request::GetCaptureSession { session } => {
+                let session = data_init.init(
+                    session,
+                    ToplevelExportSessionData {
+                        toplevel: data.source.clone(),
+                        paint_cursors: false,
+                        inner: Mutex::new(SessionInner {
+                            constraints: None,
+                            frame: None,
+                            stopped: true,
+                        }),
+                    },
+                );
+                let mut created = data.session_created.lock().unwrap();
+                if *created {
+                    resource.post_error(
+                        ext_image_copy_capture_cursor_session_v1::Error::DuplicateSession,
+                        "a capture session was already created",
+                    );
+                    return;
+                }
+                *created = true;
+                // capturing cursors is not supported
+                session.stopped();
+            }
+            ext_image_copy_capture_cursor_session_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ToplevelExportHandler> Dispatch<ExtImageCopyCaptureFrameV1, ToplevelExportFrameData, D>
+    for ToplevelExportState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &ExtImageCopyCaptureFrameV1,
+        request: ext_image_copy_capture_frame_v1::Request,
+        data: &ToplevelExportFrameData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let mut inner = data.inner.lock().unwrap();
+        match request {
+            ext_image_copy_capture_frame_v1::Request::Destroy => return,
+            _ if inner.captured => {
+                resource.post_error(
+                    ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                    "the frame was already captured",
+                );
+                return;
+            }
+            ext_image_copy_capture_frame_v1::Request::AttachBuffer { buffer } => {
+                inner.buffer = Some(buffer);
+                return;
+            }
+            ext_image_copy_capture_frame_v1::Request::DamageBuffer { x, y, width, height } => {
+                if x < 0 || y < 0 || width <= 0 || height <= 0 {
+                    resource.post_error(
+                        ext_image_copy_capture_frame_v1::Error::InvalidBufferDamage,
+                        "invalid buffer damage",
+                    );
+                } else {
+                    inner
+                        .damage
+                        .add_rect(Rectangle::new((x, y).into(), (width, height).into()));
+                }
+                return;
+            }
+            ext_image_copy_capture_frame_v1::Request::Capture => {}
+            _ => unreachable!(),
+        }
+
+        let Some(buffer) = inner.buffer.clone() else {
+            resource.post_error(
+                ext_image_copy_capture_frame_v1::Error::NoBuffer,
+                "no buffer was attached",
+            );
+            return;
+        };
+        inner.captured = true;
+        let damage = std::mem::take(&mut inner.damage).rects().collect();
+        drop(inner);
+
+        let Ok(session) = data.session.upgrade() else {
+            resource.failed(FailureReason::Stopped);
+            return;
+        };
+        let session_data = session.data::<ToplevelExportSessionData>().unwrap();
+        let (Some(constraints), Some(toplevel)) =
+            (session_data.constraints(&session), session_data.toplevel())
+        else {
+            resource.failed(FailureReason::Stopped);
+            return;
+        };
+        if !constraints.matches(&buffer) {
+            resource.failed(FailureReason::BufferConstraints);
+            return;
+        }
+
+        state.capture_frame(ToplevelExportFrame {
+            frame: resource.clone(),
+            paint_cursors: session_data.paint_cursors,
+            session,
+            toplevel,
+            buffer,
+            damage,
+            answered: false,
+        });
+    }
+}
+
+/// Macro to delegate implementation of toplevel export to [`ToplevelExportState`].
+///
+/// You must also implement [`ToplevelExportHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_toplevel_export {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        const _: () = {
+            use $crate::{
+                reexports::{
+                    wayland_protocols::ext::{
+                        image_capture_source::v1::server::{
+                            ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1,
+                            ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+                        },
+                        image_copy_capture::v1::server::{
+                            ext_image_copy_capture_cursor_session_v1::ExtImageCopyCaptureCursorSessionV1,
+                            ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1,
+                            ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1,
+                            ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1,
+                        },
+                    },
+                    wayland_server::{delegate_dispatch, delegate_global_dispatch},
+                },
+                wayland::toplevel_export::{
+                    CursorSessionData, ToplevelCaptureSource, ToplevelExportFrameData,
+                    ToplevelExportGlobalData, ToplevelExportSessionData, ToplevelExportState,
+                },
+            };
+
+            delegate_global_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtForeignToplevelImageCaptureSourceManagerV1: ToplevelExportGlobalData] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtForeignToplevelImageCaptureSourceManagerV1: ()] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCaptureSourceV1: ToplevelCaptureSource] => ToplevelExportState
+            );
+            delegate_global_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCopyCaptureManagerV1: ToplevelExportGlobalData] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCopyCaptureManagerV1: ()] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCopyCaptureSessionV1: ToplevelExportSessionData] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCopyCaptureCursorSessionV1: CursorSessionData] => ToplevelExportState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtImageCopyCaptureFrameV1: ToplevelExportFrameData] => ToplevelExportState
+            );
+        };
+    };
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{os::unix::io::OwnedFd, sync::Arc};
+
+    use wayland_protocols::ext::foreign_toplevel_list::v1::server::ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1;
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{wl_shm::WlShm, wl_shm_pool::WlShmPool},
+        Display,
+    };
+
+    use super::*;
+    use crate::wayland::{
+        buffer::BufferHandler,
+        foreign_toplevel_list::{ForeignToplevelListHandler, ForeignToplevelListState},
+        shm::{ShmHandler, ShmState},
+        test::{Arg, Event, FakeClient},
+    };
+
+    struct State {
+        toplevels: ForeignToplevelListState,
+        shm: ShmState,
+        toplevel_export: ToplevelExportState,
+        constraints: Option<BufferConstraints>,
+        frames: Vec<ToplevelExportFrame>,
+    }
+
+    impl ToplevelExportHandler for State {
+        fn toplevel_export_state(&mut self) -> &mut ToplevelExportState {
+            &mut self.toplevel_export
+        }
+
+        fn buffer_constraints(&mut self, _toplevel: &ForeignToplevelHandle) -> Option<BufferConstraints> {
+            self.constraints.clone()
+        }
+
+        fn capture_frame(&mut self, frame: ToplevelExportFrame) {
+            self.frames.push(frame);
+        }
+    }
+
+    impl ForeignToplevelListHandler for State {
+        fn foreign_toplevel_list_state(&mut self) -> &mut ForeignToplevelListState {
+            &mut self.toplevels
+        }
+    }
+
+    impl BufferHandler for State {
+        fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
+    }
+
+    impl ShmHandler for State {
+        fn shm_state(&self) -> &ShmState {
+            &self.shm
+        }
+    }
+
+    crate::delegate_toplevel_export!(State);
+    crate::delegate_foreign_toplevel_list!(State);
+    crate::delegate_shm!(State);
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    fn constraints(w: i32, h: i32) -> BufferConstraints {
+        BufferConstraints {
+            size: (w, h).into(),
+            shm: vec![wl_shm::Format::Argb8888],
+            dma: None,
+        }
+    }
+
+    struct Setup {
+        display: Display<State>,
+        state: State,
+        fake: FakeClient,
+        toplevel: ForeignToplevelHandle,
+        session: u32,
+    }
+
+    impl Setup {
+        fn new() -> Setup {
+            let mut display = Display::<State>::new().unwrap();
+            let mut state = State {
+                toplevels: ForeignToplevelListState::new::<State>(&display.handle()),
+                shm: ShmState::new::<State>(&display.handle(), []),
+                toplevel_export: ToplevelExportState::new::<State, _>(&display.handle(), |_| true),
+                constraints: Some(constraints(4, 2)),
+                frames: Vec::new(),
+            };
+            let toplevel = state.toplevels.new_toplevel::<State>("title", "app");
+
+            let (mut fake, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+            fake.record_events(true);
+            fake.get_registry().unwrap();
+            let mut setup = Setup {
+                display,
+                state,
+                fake,
+                toplevel,
+                session: 0,
+            };
+            setup.roundtrip();
+
+            let list = setup
+                .fake
+                .bind_global(ExtForeignToplevelListV1::interface(), 1)
+                .unwrap();
+            let events = setup.roundtrip();
+            let handle = created(&events, list, "toplevel");
+            let sources = setup
+                .fake
+                .bind_global(ExtForeignToplevelImageCaptureSourceManagerV1::interface(), 1)
+                .unwrap();
+            let source = setup.fake.new_id(ExtImageCaptureSourceV1::interface());
+            setup
+                .fake
+                .send_request(
+                    sources,
+                    ExtForeignToplevelImageCaptureSourceManagerV1::interface(),
+                    "create_source",
+                    vec![Arg::NewId(source), Arg::Object(handle)],
+                )
+                .unwrap();
+            let manager = setup
+                .fake
+                .bind_global(ExtImageCopyCaptureManagerV1::interface(), 1)
+                .unwrap();
+            setup.session = setup.fake.new_id(ExtImageCopyCaptureSessionV1::interface());
+            setup
+                .fake
+                .send_request(
+                    manager,
+                    ExtImageCopyCaptureManagerV1::interface(),
+                    "create_session",
+                    vec![Arg::NewId(setup.session), Arg::Object(source), Arg::Uint(0)],
+                )
+                .unwrap();
+            setup
+        }
+
+        fn roundtrip(&mut self) -> Vec<Event> {
+            self.fake.roundtrip(&mut self.display, &mut self.state).unwrap()
+        }
+
+        fn shm_buffer(&mut self, w: i32, h: i32) -> u32 {
+            let file = tempfile::tempfile().unwrap();
+            file.set_len((w * h * 4) as u64).unwrap();
+            let shm = self.fake.bind_global(WlShm::interface(), 1).unwrap();
+            let pool = self.fake.new_id(WlShmPool::interface());
+            self.fake
+                .send_request(
+                    shm,
+                    WlShm::interface(),
+                    "create_pool",
+                    vec![
+                        Arg::NewId(pool),
+                        Arg::Fd(OwnedFd::from(file)),
+                        Arg::Int(w * h * 4),
+                    ],
+                )
+                .unwrap();
+            let buffer = self.fake.new_id(WlBuffer::interface());
+            self.fake
+                .send_request(
+                    pool,
+                    WlShmPool::interface(),
+                    "create_buffer",
+                    vec![
+                        Arg::NewId(buffer),
+                        Arg::Int(0),
+                        Arg::Int(w),
+                        Arg::Int(h),
+                        Arg::Int(w * 4),
+                        Arg::Uint(wl_shm::Format::Argb8888 as u32),
+                    ],
+                )
+                .unwrap();
+            buffer
+        }
+
+        fn capture(&mut self, buffer: u32) -> u32 {
+            let frame = self.fake.new_id(ExtImageCopyCaptureFrameV1::interface());
+            self.fake
+                .send_request(
+                    self.session,
+                    ExtImageCopyCaptureSessionV1::interface(),
+                    "create_frame",
+                    vec![Arg::NewId(frame)],
+                )
+                .unwrap();
+            self.fake
+                .send_request(
+                    frame,
+                    ExtImageCopyCaptureFrameV1::interface(),
+                    "attach_buffer",
+                    vec![Arg::Object(buffer)],
+                )
+                .unwrap();
+            self.fake
+                .send_request(
+                    frame,
+                    ExtImageCopyCaptureFrameV1::interface(),
+                    "damage_buffer",
+                    vec![Arg::Int(0), Arg::Int(0), Arg::Int(2), Arg::Int(1)],
+                )
+                .unwrap();
+            self.fake
+                .send_request(frame, ExtImageCopyCaptureFrameV1::interface(), "capture", vec![])
+                .unwrap();
+            frame
+        }
+    }
+
+    // names of the events sent to an object
+    fn names(events: &[Event], object: u32) -> Vec<&'static str> {
+        events
+            .iter()
+            .filter(|event| event.object == object)
+            .map(|event| event.name().unwrap())
+            .collect()
+    }
+
+    // id of the object created by the first event of the given name
+    fn created(events: &[Event], object: u32, name: &str) -> u32 {
+        events
+            .iter()
+            .filter(|event| event.object == object && event.name() == Some(name))
+            .find_map(|event| match event.args.as_slice() {
+                [Arg::NewId(id)] => Some(*id),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn failure(events: &[Event], frame: u32) -> Option<u32> {
+        events
+            .iter()
+            .filter(|event| event.object == frame && event.name() == Some("failed"))
+            .find_map(|event| match event.args.as_slice() {
+                [Arg::Uint(reason)] => Some(*reason),
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn announces_constraints() {
+        let mut setup = Setup::new();
+        let events = setup.roundtrip();
+        assert_eq!(
+            names(&events, setup.session),
+            ["buffer_size", "shm_format", "done"]
+        );
+        let size = events
+            .iter()
+            .find(|event| event.object == setup.session && event.name() == Some("buffer_size"))
+            .unwrap();
+        assert!(matches!(size.args.as_slice(), [Arg::Uint(4), Arg::Uint(2)]));
+    }
+
+    #[test]
+    fn copies_into_buffer() {
+        let mut setup = Setup::new();
+        let buffer = setup.shm_buffer(4, 2);
+        let frame = setup.capture(buffer);
+        setup.roundtrip();
+
+        let captured = setup.state.frames.pop().unwrap();
+        assert_eq!(captured.toplevel(), &setup.toplevel);
+        assert_eq!(captured.buffer().id().protocol_id(), buffer);
+        assert_eq!(
+            captured.buffer_damage(),
+            [Rectangle::new((0, 0).into(), (2, 1).into())]
+        );
+        assert!(!captured.paint_cursors());
+
+        captured.success(
+            Transform::Normal,
+            [
+                Rectangle::new((0, 0).into(), (4, 2).into()),
+                Rectangle::new((1, 0).into(), (2, 2).into()),
+            ],
+            Duration::from_millis(1500),
+        );
+        let events = setup.roundtrip();
+        assert_eq!(
+            names(&events, frame),
+            ["transform", "damage", "presentation_time", "ready"]
+        );
+    }
+
+    #[test]
+    fn fails_mismatching_buffers() {
+        let mut setup = Setup::new();
+        let buffer = setup.shm_buffer(8, 8);
+        let frame = setup.capture(buffer);
+        let events = setup.roundtrip();
+        assert!(setup.state.frames.is_empty());
+        assert_eq!(
+            failure(&events, frame),
+            Some(FailureReason::BufferConstraints as u32)
+        );
+    }
+
+    #[test]
+    fn resize_fails_pending_frames() {
+        let mut setup = Setup::new();
+        let buffer = setup.shm_buffer(4, 2);
+        let frame = setup.capture(buffer);
+        setup.roundtrip();
+        let captured = setup.state.frames.pop().unwrap();
+
+        setup
+            .state
+            .toplevel_export
+            .update_constraints(&setup.toplevel, Some(constraints(8, 4)));
+        captured.success(Transform::Normal, [], Duration::ZERO);
+        let events = setup.roundtrip();
+        assert_eq!(
+            names(&events, setup.session),
+            ["buffer_size", "shm_format", "done"]
+        );
+        assert_eq!(
+            failure(&events, frame),
+            Some(FailureReason::BufferConstraints as u32)
+        );
+    }
+
+    #[test]
+    fn stops_closed_toplevels() {
+        let mut setup = Setup::new();
+        setup.roundtrip();
+        setup.toplevel.send_closed();
+        let buffer = setup.shm_buffer(4, 2);
+        let frame = setup.capture(buffer);
+        let events = setup.roundtrip();
+
+        assert!(setup.state.frames.is_empty());
+        assert_eq!(names(&events, setup.session), ["stopped"]);
+        assert_eq!(failure(&events, frame), Some(FailureReason::Stopped as u32));
+
+        // stopping again does not send another event
+        setup.state.toplevel_export.stop(&setup.toplevel);
+        let events = setup.roundtrip();
+        assert!(names(&events, setup.session).is_empty());
+    }
+
+    #[test]
+    fn dropped_frames_fail() {
+        let mut setup = Setup::new();
+        let buffer = setup.shm_buffer(4, 2);
+        let frame = setup.capture(buffer);
+        setup.roundtrip();
+        setup.state.frames.clear();
+        let events = setup.roundtrip();
+        assert_eq!(failure(&events, frame), Some(FailureReason::Unknown as u32));
+    }
+
+    #[test]
+    fn rejects_duplicate_frames() {
+        let mut setup = Setup::new();
+        for _ in 0..2 {
+            let frame = setup.fake.new_id(ExtImageCopyCaptureFrameV1::interface());
+            setup
+                .fake
+                .send_request(
+                    setup.session,
+                    ExtImageCopyCaptureSessionV1::interface(),
+                    "create_frame",
+                    vec![Arg::NewId(frame)],
+                )
+                .unwrap();
+        }
+        let events = setup.roundtrip();
+        assert!(events
+            .iter()
+            .any(|event| event.object == crate::wayland::test::DISPLAY_ID && event.name() == Some("error")));
+    }
+}
@@ -0,0 +1,1027 @@
+//! Tracing of the protocol messages exchanged with clients
+//!
+//! libwayland prints every message of a connection with `WAYLAND_DEBUG=1`, but only for clients
+//! and compositors using it, and only to stderr. A [`ProtocolTrace`] decodes the requests and
+//! events of individual clients itself and keeps them as [`TraceMessage`]s in a ring buffer,
+//! optionally also writing them to a file in the format of `WAYLAND_DEBUG`:
+//!
+//! ```text
+//! [    1234.567] wl_surface@3.attach(wl_buffer@12, 0, 0)
+//! [    1234.612]  -> wl_callback@15.done(1234)
+//! ```
+//!
+//! On unix, [`ProtocolTrace::proxy`] sits between the socket of a client and the
+//! [`Display`](wayland_server::Display), forwarding all data and file descriptors unchanged from a
+//! separate thread. The display sees the proxy as its peer, so
+//! [`Client::get_credentials`](wayland_server::Client::get_credentials) returns the credentials
+//! of the compositor for proxied clients; the credentials of the actual client are available from
+//! [`ClientTrace::credentials`].
+//!
+//! Other transports, e.g. the socket streams of Windows, wrap the stream of every client
+//! connection with [`ProtocolTrace::wrap`], which records the data read from and written to it.
+//! Transports not based on streams feed the data they send and receive into a [`ClientTrace`]
+//! created with [`ProtocolTrace::client`].
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! use smithay::wayland::{socket::ListeningSocketSource, trace::ProtocolTrace};
+//!
+//! # struct State { display: wayland_server::Display<State>, trace: ProtocolTrace }
+//! # use wayland_server::backend::{ClientData, ClientId, DisconnectReason};
+//! # struct ClientState;
+//! # impl ClientData for ClientState {
+//! #     fn initialized(&self, _: ClientId) {}
+//! #     fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+//! # }
+//! # let event_loop = calloop::EventLoop::<State>::try_new().unwrap();
+//! let trace = ProtocolTrace::new(4096);
+//! event_loop
+//!     .handle()
+//!     .insert_source(ListeningSocketSource::new_auto().unwrap(), |stream, _, state| {
+//!         let (stream, client_trace) = state.trace.proxy(stream).unwrap();
+//!         // keep `client_trace` around to toggle tracing of this client later
+//!         state.display.handle().insert_client(stream, Arc::new(ClientState)).unwrap();
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! Objects are resolved to their interface by following the objects created by messages, starting
+//! with the `wl_display`. Globals are bound by the name of their interface, which has to be
+//! registered with [`ProtocolTrace::register_interface`] unless it is one of the core interfaces.
+//! Messages of objects of unknown interfaces are recorded without arguments.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tracing::warn;
+use wayland_protocols::xdg::shell::server::xdg_wm_base::XdgWmBase;
+use wayland_server::{
+    backend::{
+        protocol::{ArgumentType, Interface},
+        Credentials,
+    },
+    protocol::{
+        wl_compositor::WlCompositor, wl_data_device_manager::WlDataDeviceManager, wl_display::WlDisplay,
+        wl_output::WlOutput, wl_seat::WlSeat, wl_shm::WlShm, wl_subcompositor::WlSubcompositor,
+    },
+    Resource,
+};
+
+use crate::utils::{Clock, Monotonic, Time};
+
+const DISPLAY_ID: u32 = 1;
+
+/// Direction of a traced message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// A request sent by the client
+    Request,
+    /// An event sent by the compositor
+    Event,
+}
+
+/// Decoded argument of a traced message
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceArgument {
+    /// Signed integer
+    Int(i32),
+    /// Unsigned integer
+    Uint(u32),
+    /// Fixed point number
+    Fixed(f64),
+    /// String, `None` for a null string
+    Str(Option<String>),
+    /// Id of an existing object, `0` for null
+    Object {
+        /// Id of the object
+        id: u32,
+        /// Interface of the object, if known
+        interface: Option<&'static str>,
+    },
+    /// Id of an object created by the message
+    NewId {
+        /// Id of the new object
+        id: u32,
+        /// Interface of the new object, if known
+        interface: Option<&'static str>,
+    },
+    /// Byte array
+    Array(Vec<u8>),
+    /// File descriptor, which is not part of the traced data
+    Fd,
+}
+
+impl fmt::Display for TraceArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceArgument::Int(value) => write!(f, "{}", value),
+            TraceArgument::Uint(value) => write!(f, "{}", value),
+            TraceArgument::Fixed(value) => write!(f, "{}", value),
+            TraceArgument::Str(Some(value)) => write!(f, "{:?}", value),
+            TraceArgument::Str(None) | TraceArgument::Object { id: 0, .. } => f.write_str("nil"),
+            TraceArgument::Object { id, interface } => {
+                write!(f, "{}@{}", interface.unwrap_or("[unknown]"), id)
+            }
+            TraceArgument::NewId { id, interface } => {
+                write!(f, "new id {}@{}", interface.unwrap_or("[unknown]"), id)
+            }
+            TraceArgument::Array(array) => write!(f, "array[{}]", array.len()),
+            TraceArgument::Fd => f.write_str("fd"),
+        }
+    }
+}
+
+/// Message recorded by a [`ProtocolTrace`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceMessage {
+    /// Time the message was traced
+    pub time: Time<Monotonic>,
+    /// Id of the [`ClientTrace`] of the client
+    pub client: u64,
+    /// Whether the message is a request or an event
+    pub direction: Direction,
+    /// Number of the message among the messages of its client in the same direction
+    ///
+    /// Counts from 0 at the start of the connection, including the messages not recorded while
+    /// the trace was disabled, so gaps show missing messages.
+    pub sequence: u64,
+    /// Id of the object the message was sent to or from
+    pub object: u32,
+    /// Interface of the object, if known
+    pub interface: Option<&'static str>,
+    /// Opcode of the message
+    pub opcode: u16,
+    /// Name of the message, if the interface is known
+    pub name: Option<&'static str>,
+    /// Decoded arguments, empty if the interface is unknown
+    pub args: Vec<TraceArgument>,
+}
+
+impl fmt::Display for TraceMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = Duration::from(self.time).as_secs_f64() * 1000.0;
+        write!(f, "[{:12.3}] ", millis)?;
+        if self.direction == Direction::Event {
+            f.write_str(" -> ")?;
+        }
+        write!(f, "{}@{}.", self.interface.unwrap_or("[unknown]"), self.object)?;
+        match self.name {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "[opcode {}]", self.opcode)?,
+        }
+        f.write_str("(")?;
+        for (idx, arg) in self.args.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Tracer of the protocol messages of clients, see the [module-level documentation](self)
+#[derive(Debug, Clone)]
+pub struct ProtocolTrace {
+    inner: Arc<TraceInner>,
+}
+
+struct TraceInner {
+    enabled: AtomicBool,
+    next_client: AtomicU64,
+    clock: Clock<Monotonic>,
+    interfaces: Mutex<Vec<&'static Interface>>,
+    log: Mutex<TraceLog>,
+    sinks: Mutex<Vec<Box<dyn FnMut(&TraceMessage) -> bool + Send>>>,
+}
+
+impl fmt::Debug for TraceInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceInner")
+            .field("enabled", &self.enabled)
+            .field("next_client", &self.next_client)
+            .field("interfaces", &self.interfaces)
+            .field("log", &self.log)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+struct TraceLog {
+    capacity: usize,
+    messages: VecDeque<TraceMessage>,
+    file: Option<BufWriter<File>>,
+}
+
+impl ProtocolTrace {
+    /// Create a new trace keeping at most `capacity` messages
+    ///
+    /// The trace is enabled initially.
+    pub fn new(capacity: usize) -> ProtocolTrace {
+        ProtocolTrace {
+            inner: Arc::new(TraceInner {
+                enabled: AtomicBool::new(true),
+                next_client: AtomicU64::new(1),
+                clock: Clock::new(),
+                interfaces: Mutex::new(vec![
+                    WlCompositor::interface(),
+                    WlSubcompositor::interface(),
+                    WlShm::interface(),
+                    WlSeat::interface(),
+                    WlOutput::interface(),
+                    WlDataDeviceManager::interface(),
+                    XdgWmBase::interface(),
+                ]),
+                log: Mutex::new(TraceLog {
+                    capacity,
+                    messages: VecDeque::with_capacity(capacity),
+                    file: None,
+                }),
+                sinks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Enable or disable recording messages of all clients
+    ///
+    /// Messages are still decoded while disabled, to keep track of the created objects.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether messages are recorded
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Make the interface known to resolve globals bound with its name
+    ///
+    /// Interfaces of objects created by other objects are resolved from the protocol definitions
+    /// and do not have to be registered.
+    pub fn register_interface(&self, interface: &'static Interface) {
+        let mut interfaces = self.inner.interfaces.lock().unwrap();
+        if !interfaces.iter().any(|known| known.name == interface.name) {
+            interfaces.push(interface);
+        }
+    }
+
+    /// Additionally write all recorded messages to the file at `path`
+    pub fn log_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.inner.log.lock().unwrap().file = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    /// Stop writing messages to the file set with [`ProtocolTrace::log_to_file`]
+    pub fn close_file(&self) -> io::Result<()> {
+        match self.inner.log.lock().unwrap().file.take() {
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the recorded messages, oldest first
+    pub fn messages(&self) -> Vec<TraceMessage> {
+        self.inner.log.lock().unwrap().messages.iter().cloned().collect()
+    }
+
+    /// Drop all recorded messages
+    pub fn clear(&self) {
+        self.inner.log.lock().unwrap().messages.clear();
+    }
+
+    /// Pass every message recorded from now on to `sink`, in addition to the ring buffer
+    ///
+    /// The sink is called from the thread recording the message and removed once it returns
+    /// `false`.
+    pub fn add_sink(&self, sink: impl FnMut(&TraceMessage) -> bool + Send + 'static) {
+        self.inner.sinks.lock().unwrap().push(Box::new(sink));
+    }
+
+    /// Create the trace of a new client connection
+    ///
+    /// The data of the connection has to be passed to [`ClientTrace::record`] from the start of
+    /// the connection on.
+    pub fn client(&self) -> ClientTrace {
+        self.client_with_credentials(None)
+    }
+
+    fn client_with_credentials(&self, credentials: Option<Credentials>) -> ClientTrace {
+        ClientTrace {
+            inner: Arc::new(ClientInner {
+                id: self.inner.next_client.fetch_add(1, Ordering::Relaxed),
+                enabled: AtomicBool::new(true),
+                credentials,
+                trace: self.clone(),
+                decoder: Mutex::new(Decoder::new()),
+            }),
+        }
+    }
+
+    /// Trace the connection of a client over any stream
+    ///
+    /// Data read from the returned stream is recorded as requests, data written to it as events.
+    pub fn wrap<S>(&self, stream: S) -> TracedStream<S> {
+        TracedStream {
+            stream,
+            trace: self.client(),
+        }
+    }
+
+    /// Trace the connection of a client
+    ///
+    /// Returns the stream to insert into the display in place of `stream`, see the
+    /// [module-level documentation](self).
+    #[cfg(unix)]
+    pub fn proxy(
+        &self,
+        stream: std::os::unix::net::UnixStream,
+    ) -> io::Result<(std::os::unix::net::UnixStream, ClientTrace)> {
+        let (display_side, proxy_side) = std::os::unix::net::UnixStream::pair()?;
+        let client = self.client_with_credentials(relay::peer_credentials(&stream));
+        let relay_client = client.clone();
+        std::thread::Builder::new()
+            .name(format!("wayland-trace-{}", client.id()))
+            .spawn(move || relay::run(stream, proxy_side, relay_client))?;
+        Ok((display_side, client))
+    }
+
+    fn push(&self, messages: Vec<TraceMessage>) {
+        {
+            let mut sinks = self.inner.sinks.lock().unwrap();
+            if !sinks.is_empty() {
+                sinks.retain_mut(|sink| messages.iter().all(|message| sink(message)));
+            }
+        }
+
+        let mut log = self.inner.log.lock().unwrap();
+        let log = &mut *log;
+        for message in messages {
+            if let Some(file) = log.file.as_mut() {
+                if let Err(err) = writeln!(file, "{}", message) {
+                    warn!(?err, "Failed to write protocol trace");
+                    log.file = None;
+                }
+            }
+            if log.capacity == 0 {
+                continue;
+            }
+            if log.messages.len() == log.capacity {
+                log.messages.pop_front();
+            }
+            log.messages.push_back(message);
+        }
+        if let Some(file) = log.file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Trace of a single client connection
+#[derive(Debug, Clone)]
+pub struct ClientTrace {
+    inner: Arc<ClientInner>,
+}
+
+#[derive(Debug)]
+struct ClientInner {
+    id: u64,
+    enabled: AtomicBool,
+    credentials: Option<Credentials>,
+    trace: ProtocolTrace,
+    decoder: Mutex<Decoder>,
+}
+
+impl ClientTrace {
+    /// Id of the client in [`TraceMessage::client`]
+    pub fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    /// Enable or disable recording the messages of this client
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether the messages of this client are recorded
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Credentials of the client connected to a [proxy](ProtocolTrace::proxy)
+    ///
+    /// Returns `None` for clients of other transports, or if the platform does not report the
+    /// credentials of the peer of a socket.
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.inner.credentials
+    }
+
+    /// Decode and record data sent over the connection
+    ///
+    /// `data` may contain partial messages, which are completed by later calls.
+    pub fn record(&self, direction: Direction, data: &[u8]) {
+        let interfaces = self.inner.trace.inner.interfaces.lock().unwrap().clone();
+        let decoded = self
+            .inner
+            .decoder
+            .lock()
+            .unwrap()
+            .decode(direction, data, &interfaces);
+        if decoded.is_empty() || !self.is_enabled() || !self.inner.trace.is_enabled() {
+            return;
+        }
+
+        let time = self.inner.trace.inner.clock.now();
+        let messages = decoded
+            .into_iter()
+            .map(|message| TraceMessage {
+                time,
+                client: self.inner.id,
+                direction,
+                sequence: message.sequence,
+                object: message.object,
+                interface: message.interface,
+                opcode: message.opcode,
+                name: message.name,
+                args: message.args,
+            })
+            .collect();
+        self.inner.trace.push(messages);
+    }
+}
+
+/// Stream recording the data of a client connection, see [`ProtocolTrace::wrap`]
+#[derive(Debug)]
+pub struct TracedStream<S> {
+    stream: S,
+    trace: ClientTrace,
+}
+
+impl<S> TracedStream<S> {
+    /// Trace of the client connected over the stream
+    pub fn trace(&self) -> &ClientTrace {
+        &self.trace
+    }
+
+    /// Returns a reference to the wrapped stream
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Read> Read for TracedStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.stream.read(buf)?;
+        self.trace.record(Direction::Request, &buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<S: Write> Write for TracedStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.stream.write(buf)?;
+        self.trace.record(Direction::Event, &buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[derive(Debug)]
+struct DecodedMessage {
+    sequence: u64,
+    object: u32,
+    interface: Option<&'static str>,
+    opcode: u16,
+    name: Option<&'static str>,
+    args: Vec<TraceArgument>,
+}
+
+/// Decoder of the wire format of a single connection
+#[derive(Debug)]
+struct Decoder {
+    objects: HashMap<u32, &'static Interface>,
+    requests: Vec<u8>,
+    events: Vec<u8>,
+    request_count: u64,
+    event_count: u64,
+}
+
+impl Decoder {
+    fn new() -> Decoder {
+        Decoder {
+            objects: HashMap::from([(DISPLAY_ID, WlDisplay::interface())]),
+            requests: Vec::new(),
+            events: Vec::new(),
+            request_count: 0,
+            event_count: 0,
+        }
+    }
+
+    fn decode(
+        &mut self,
+        direction: Direction,
+        data: &[u8],
+        interfaces: &[&'static Interface],
+    ) -> Vec<DecodedMessage> {
+        let mut buffer = std::mem::take(match direction {
+            Direction::Request => &mut self.requests,
+            Direction::Event => &mut self.events,
+        });
+        buffer.extend_from_slice(data);
+
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        while let Some((object, opcode, body)) = next_message(&buffer[offset..]) {
+            offset += 8 + body.len();
+            let count = match direction {
+                Direction::Request => &mut self.request_count,
+                Direction::Event => &mut self.event_count,
+            };
+            let sequence = *count;
+            *count += 1;
+            messages.push(self.decode_message(direction, sequence, object, opcode, body, interfaces));
+        }
+        buffer.drain(..offset);
+
+        match direction {
+            Direction::Request => self.requests = buffer,
+            Direction::Event => self.events = buffer,
+        }
+        messages
+    }
+
+    fn decode_message(
+        &mut self,
+        direction: Direction,
+        sequence: u64,
+        object: u32,
+        opcode: u16,
+        mut body: &[u8],
+        interfaces: &[&'static Interface],
+    ) -> DecodedMessage {
+        let interface = self.objects.get(&object).copied();
+        let desc = interface.and_then(|interface| match direction {
+            Direction::Request => interface.requests.get(opcode as usize),
+            Direction::Event => interface.events.get(opcode as usize),
+        });
+        let mut message = DecodedMessage {
+            sequence,
+            object,
+            interface: interface.map(|interface| interface.name),
+            opcode,
+            name: desc.map(|desc| desc.name),
+            args: Vec::new(),
+        };
+        let Some(desc) = desc else {
+            return message;
+        };
+
+        for ty in desc.signature {
+            let arg = match ty {
+                ArgumentType::Int => read_u32(&mut body).map(|value| TraceArgument::Int(value as i32)),
+                ArgumentType::Uint => read_u32(&mut body).map(TraceArgument::Uint),
+                ArgumentType::Fixed => {
+                    read_u32(&mut body).map(|value| TraceArgument::Fixed(value as i32 as f64 / 256.0))
+                }
+                ArgumentType::Str(_) => read_array(&mut body).map(|string| {
+                    TraceArgument::Str(
+                        (!string.is_empty())
+                            .then(|| String::from_utf8_lossy(&string[..string.len() - 1]).into_owned()),
+                    )
+                }),
+                ArgumentType::Object(_) => read_u32(&mut body).map(|id| TraceArgument::Object {
+                    id,
+                    interface: self.objects.get(&id).map(|interface| interface.name),
+                }),
+                ArgumentType::NewId => read_u32(&mut body).map(|id| {
+                    // without a fixed interface, e.g. for `wl_registry.bind`, the interface is
+                    // named by the preceding string argument
+                    let new = desc.child_interface.or_else(|| {
+                        message.args.iter().rev().find_map(|arg| match arg {
+                            TraceArgument::Str(Some(name)) => interfaces
+                                .iter()
+                                .copied()
+                                .find(|interface| interface.name == name),
+                            _ => None,
+                        })
+                    });
+                    match new {
+                        Some(new) => {
+                            self.objects.insert(id, new);
+                        }
+                        None => {
+                            self.objects.remove(&id);
+                        }
+                    }
+                    TraceArgument::NewId {
+                        id,
+                        interface: new.map(|new| new.name),
+                    }
+                }),
+                ArgumentType::Array => read_array(&mut body).map(TraceArgument::Array),
+                ArgumentType::Fd => Some(TraceArgument::Fd),
+            };
+            match arg {
+                Some(arg) => message.args.push(arg),
+                // truncated message, the connection is about to be closed with a protocol error
+                None => break,
+            }
+        }
+
+        // the ids of destroyed objects are released with `wl_display.delete_id`
+        if direction == Direction::Event && object == DISPLAY_ID && desc.name == "delete_id" {
+            if let Some(TraceArgument::Uint(id)) = message.args.first() {
+                self.objects.remove(id);
+            }
+        }
+        message
+    }
+}
+
+fn next_message(bytes: &[u8]) -> Option<(u32, u16, &[u8])> {
+    let object = u32::from_ne_bytes(bytes.get(0..4)?.try_into().unwrap());
+    let word = u32::from_ne_bytes(bytes.get(4..8)?.try_into().unwrap());
+    let size = (word >> 16) as usize;
+    let body = bytes.get(8..size.max(8))?;
+    Some((object, word as u16, body))
+}
+
+fn read_u32(body: &mut &[u8]) -> Option<u32> {
+    let (bytes, rest) = body.split_first_chunk::<4>()?;
+    *body = rest;
+    Some(u32::from_ne_bytes(*bytes))
+}
+
+fn read_array(body: &mut &[u8]) -> Option<Vec<u8>> {
+    let len = read_u32(body)? as usize;
+    let padded = len.next_multiple_of(4);
+    if body.len() < padded {
+        return None;
+    }
+    let array = body[..len].to_vec();
+    *body = &body[padded..];
+    Some(array)
+}
+
+#[cfg(unix)]
+mod relay {
+    use std::{
+        collections::VecDeque,
+        io::{self, IoSlice, IoSliceMut},
+        mem::MaybeUninit,
+        net::Shutdown,
+        os::unix::{
+            io::{AsFd, BorrowedFd, OwnedFd},
+            net::UnixStream,
+        },
+    };
+
+    use rustix::{
+        event::{poll, PollFd, PollFlags},
+        io::Errno,
+        net::{
+            recvmsg, sendmsg, RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags, SendAncillaryBuffer,
+            SendAncillaryMessage, SendFlags,
+        },
+    };
+    use tracing::debug;
+    use wayland_server::backend::Credentials;
+
+    use super::{ClientTrace, Direction};
+
+    // maximum number of file descriptors per message, as enforced by libwayland
+    const MAX_FDS: usize = 28;
+    // amount of data pending for a peer, after which the relay stops reading from the other one
+    const MAX_PENDING: usize = 1 << 20;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peer_credentials(stream: &UnixStream) -> Option<Credentials> {
+        let ucred = rustix::net::sockopt::socket_peercred(stream).ok()?;
+        Some(Credentials {
+            pid: ucred.pid.as_raw_nonzero().get(),
+            uid: ucred.uid.as_raw(),
+            gid: ucred.gid.as_raw(),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn peer_credentials(_stream: &UnixStream) -> Option<Credentials> {
+        None
+    }
+
+    pub fn run(client: UnixStream, display: UnixStream, trace: ClientTrace) {
+        if let Err(err) = relay(&client, &display, &trace) {
+            debug!(client = trace.id(), ?err, "Protocol trace relay stopped");
+        }
+        let _ = client.shutdown(Shutdown::Both);
+        let _ = display.shutdown(Shutdown::Both);
+    }
+
+    fn relay(client: &UnixStream, display: &UnixStream, trace: &ClientTrace) -> io::Result<()> {
+        client.set_nonblocking(true)?;
+        display.set_nonblocking(true)?;
+        let mut to_display = Pending::default();
+        let mut to_client = Pending::default();
+
+        loop {
+            let mut fds = [
+                PollFd::new(client, to_display.poll_flags(&to_client)),
+                PollFd::new(display, to_client.poll_flags(&to_display)),
+            ];
+            match poll(&mut fds, None) {
+                Ok(_) => {}
+                Err(Errno::INTR) => continue,
+                Err(err) => return Err(err.into()),
+            }
+            let revents = [fds[0].revents(), fds[1].revents()];
+
+            if revents[0].contains(PollFlags::OUT) && !to_client.flush(client)? {
+                return Ok(());
+            }
+            if revents[1].contains(PollFlags::OUT) && !to_display.flush(display)? {
+                return Ok(());
+            }
+            if readable(revents[0]) && !to_display.receive(client, trace, Direction::Request)? {
+                return to_display.drain(display);
+            }
+            if readable(revents[1]) && !to_client.receive(display, trace, Direction::Event)? {
+                return to_client.drain(client);
+            }
+        }
+    }
+
+    fn readable(revents: PollFlags) -> bool {
+        revents.intersects(PollFlags::IN | PollFlags::HUP | PollFlags::ERR)
+    }
+
+    struct Chunk {
+        data: Vec<u8>,
+        // sent along with the first byte of `data`
+        fds: Vec<OwnedFd>,
+    }
+
+    /// Data received from one peer, not yet written to the other one
+    #[derive(Default)]
+    struct Pending {
+        chunks: VecDeque<Chunk>,
+        len: usize,
+    }
+
+    impl Pending {
+        // flags to poll the peer this data is received from, given the data pending for it
+        fn poll_flags(&self, outgoing: &Pending) -> PollFlags {
+            let mut flags = PollFlags::empty();
+            if self.len < MAX_PENDING {
+                flags |= PollFlags::IN;
+            }
+            if !outgoing.chunks.is_empty() {
+                flags |= PollFlags::OUT;
+            }
+            flags
+        }
+
+        // returns `false` once `from` was closed
+        fn receive(
+            &mut self,
+            from: &UnixStream,
+            trace: &ClientTrace,
+            direction: Direction,
+        ) -> io::Result<bool> {
+            let mut buffer = [0u8; 4096];
+            let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(MAX_FDS))];
+            let mut ancillary = RecvAncillaryBuffer::new(&mut space);
+            let received = loop {
+                match recvmsg(
+                    from,
+                    &mut [IoSliceMut::new(&mut buffer)],
+                    &mut ancillary,
+                    RecvFlags::CMSG_CLOEXEC,
+                ) {
+                    Ok(msg) => break msg.bytes,
+                    Err(Errno::INTR) => continue,
+                    Err(Errno::AGAIN) => return Ok(true),
+                    Err(Errno::CONNRESET) => return Ok(false),
+                    Err(err) => return Err(err.into()),
+                }
+            };
+            if received == 0 {
+                return Ok(false);
+            }
+            let fds = ancillary
+                .drain()
+                .filter_map(|message| match message {
+                    RecvAncillaryMessage::ScmRights(fds) => Some(fds),
+                    _ => None,
+                })
+                .flatten()
+                .collect::<Vec<OwnedFd>>();
+
+            let data = buffer[..received].to_vec();
+            trace.record(direction, &data);
+            self.len += data.len();
+            self.chunks.push_back(Chunk { data, fds });
+            Ok(true)
+        }
+
+        // writes as much as possible without blocking, returns `false` once `to` was closed
+        fn flush(&mut self, to: &UnixStream) -> io::Result<bool> {
+            while let Some(chunk) = self.chunks.front_mut() {
+                let result = {
+                    let borrowed = chunk
+                        .fds
+                        .iter()
+                        .map(|fd| fd.as_fd())
+                        .collect::<Vec<BorrowedFd<'_>>>();
+                    let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(MAX_FDS))];
+                    let mut ancillary = SendAncillaryBuffer::new(&mut space);
+                    if !borrowed.is_empty() {
+                        ancillary.push(SendAncillaryMessage::ScmRights(&borrowed));
+                    }
+                    sendmsg(
+                        to,
+                        &[IoSlice::new(&chunk.data)],
+                        &mut ancillary,
+                        SendFlags::NOSIGNAL,
+                    )
+                };
+                match result {
+                    Ok(len) => {
+                        chunk.fds.clear();
+                        self.len -= len;
+                        if len == chunk.data.len() {
+                            self.chunks.pop_front();
+                        } else {
+                            chunk.data.drain(..len);
+                        }
+                    }
+                    Err(Errno::INTR) => continue,
+                    Err(Errno::AGAIN) => return Ok(true),
+                    Err(Errno::PIPE) | Err(Errno::CONNRESET) => return Ok(false),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok(true)
+        }
+
+        // writes out the remaining data after the other peer hung up
+        fn drain(&mut self, to: &UnixStream) -> io::Result<()> {
+            to.set_nonblocking(false)?;
+            self.flush(to).map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+
+    use wayland_server::{
+        backend::protocol::Interface,
+        protocol::{wl_callback::WlCallback, wl_compositor::WlCompositor},
+        Resource,
+    };
+
+    use super::{Decoder, Direction, ProtocolTrace, TraceArgument, DISPLAY_ID};
+
+    fn uint(value: u32) -> Vec<u8> {
+        value.to_ne_bytes().to_vec()
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut bytes = uint(value.len() as u32 + 1);
+        bytes.extend_from_slice(value.as_bytes());
+        bytes.push(0);
+        bytes.resize(bytes.len().next_multiple_of(4), 0);
+        bytes
+    }
+
+    fn message(object: u32, opcode: u16, args: &[Vec<u8>]) -> Vec<u8> {
+        let body = args.concat();
+        let mut bytes = uint(object);
+        bytes.extend_from_slice(&uint((((body.len() + 8) as u32) << 16) | opcode as u32));
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    #[test]
+    fn follows_created_objects() {
+        let interfaces: [&'static Interface; 1] = [WlCompositor::interface()];
+        let mut decoder = Decoder::new();
+
+        // wl_display.get_registry, then wl_registry.bind split in the middle
+        let mut data = message(DISPLAY_ID, 1, &[uint(2)]);
+        data.extend(message(
+            2,
+            0,
+            &[uint(1), string("wl_compositor"), uint(4), uint(3)],
+        ));
+        let (first, second) = data.split_at(20);
+
+        let decoded = decoder.decode(Direction::Request, first, &interfaces);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].name, Some("get_registry"));
+        let decoded = decoder.decode(Direction::Request, second, &interfaces);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].interface, Some("wl_registry"));
+        assert_eq!(
+            decoded[0].args.last(),
+            Some(&TraceArgument::NewId {
+                id: 3,
+                interface: Some("wl_compositor"),
+            })
+        );
+
+        // wl_compositor.create_surface
+        let decoded = decoder.decode(Direction::Request, &message(3, 0, &[uint(4)]), &interfaces);
+        assert_eq!(decoded[0].name, Some("create_surface"));
+        assert_eq!(decoder.objects[&4].name, "wl_surface");
+    }
+
+    #[test]
+    fn forgets_deleted_objects() {
+        let mut decoder = Decoder::new();
+        decoder.objects.insert(5, WlCallback::interface());
+
+        let done = message(5, 0, &[uint(1234)]);
+        let decoded = decoder.decode(Direction::Event, &done, &[]);
+        assert_eq!(decoded[0].args, vec![TraceArgument::Uint(1234)]);
+
+        // wl_display.delete_id
+        decoder.decode(Direction::Event, &message(DISPLAY_ID, 1, &[uint(5)]), &[]);
+        let decoded = decoder.decode(Direction::Event, &done, &[]);
+        assert_eq!(decoded[0].interface, None);
+        assert!(decoded[0].args.is_empty());
+    }
+
+    #[test]
+    fn records_wrapped_streams() {
+        let trace = ProtocolTrace::new(16);
+        let mut stream = trace.wrap(Cursor::new(message(DISPLAY_ID, 1, &[uint(2)])));
+
+        let mut request = [0u8; 12];
+        stream.read_exact(&mut request).unwrap();
+        // wl_registry.global
+        stream
+            .write_all(&message(2, 0, &[uint(1), string("wl_compositor"), uint(6)]))
+            .unwrap();
+
+        let messages = trace.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].direction, Direction::Request);
+        assert_eq!(messages[0].name, Some("get_registry"));
+        assert_eq!(messages[1].direction, Direction::Event);
+        assert_eq!(messages[1].name, Some("global"));
+        assert!(messages
+            .iter()
+            .all(|message| message.client == stream.trace().id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn proxy_forwards_without_blocking() {
+        use std::os::unix::net::UnixStream;
+
+        let trace = ProtocolTrace::new(16);
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let (mut display, client_trace) = trace.proxy(server).unwrap();
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            client_trace.credentials().map(|credentials| credentials.pid),
+            Some(std::process::id() as i32)
+        );
+
+        // far more events than fit into the socket buffers, while the client does not read
+        let sync_done = message(3, 0, &[uint(1)]);
+        let events = sync_done.repeat(64 * 1024);
+        display.write_all(&events).unwrap();
+
+        // requests still reach the display meanwhile
+        let request = message(DISPLAY_ID, 1, &[uint(2)]);
+        client.write_all(&request).unwrap();
+        let mut received = vec![0u8; request.len()];
+        display.read_exact(&mut received).unwrap();
+        assert_eq!(received, request);
+
+        let mut received = vec![0u8; events.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, events);
+    }
+}
@@ -0,0 +1,1072 @@
+//! Workspaces
+//!
+//! The ext-workspace protocol lets pagers and bars enumerate workspaces and request to switch
+//! between them. Workspaces are organized in groups, which are usually bound to one or more
+//! outputs.
+//!
+//! The compositor stays in charge of the workspace model: it creates groups and workspaces through
+//! the [`WorkspaceManagerState`] and updates their state, finishing every batch of changes with
+//! [`WorkspaceManagerState::done`]. Requests of clients are collected until they commit them and
+//! are then handed to [`WorkspaceHandler::commit_requests`] as a whole. None of them is applied
+//! automatically.
+//!
+//! With the `desktop` feature, `desktop::workspaces` provides a model keeping one `Space` per
+//! workspace on top of this module.
+//!
+//! ```no_run
+//! use smithay::wayland::workspace::{
+//!     GroupCapabilities, WorkspaceCapabilities, WorkspaceHandler, WorkspaceManagerState,
+//!     WorkspaceRequest, WorkspaceStates,
+//! };
+//!
+//! pub struct State {
+//!     workspaces: WorkspaceManagerState,
+//! }
+//!
+//! smithay::delegate_workspace!(State);
+//!
+//! impl WorkspaceHandler for State {
+//!     fn workspace_state(&mut self) -> &mut WorkspaceManagerState {
+//!         &mut self.workspaces
+//!     }
+//!
+//!     fn commit_requests(&mut self, requests: Vec<WorkspaceRequest>) {
+//!         for request in requests {
+//!             if let WorkspaceRequest::Activate(workspace) = request {
+//!                 // switch to the workspace
+//!                 self.workspaces.set_workspace_state(workspace, WorkspaceStates::Active);
+//!             }
+//!         }
+//!         self.workspaces.done();
+//!     }
+//! }
+//!
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! # let display_handle = display.handle();
+//! # let output: smithay::output::Output = todo!();
+//! let mut state = State {
+//!     workspaces: WorkspaceManagerState::new::<State>(&display_handle),
+//! };
+//!
+//! let group = state.workspaces.create_group::<State>(GroupCapabilities::empty());
+//! state.workspaces.group_output_enter(group, &output);
+//! let workspace = state
+//!     .workspaces
+//!     .create_workspace::<State>("1", None, WorkspaceCapabilities::Activate);
+//! state.workspaces.set_workspace_group(workspace, Some(group));
+//! state.workspaces.done();
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use wayland_protocols::ext::workspace::v1::server::{
+    ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1},
+    ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1},
+    ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId},
+    protocol::wl_output::WlOutput,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::output::Output;
+
+pub use ext_workspace_group_handle_v1::GroupCapabilities;
+pub use ext_workspace_handle_v1::{State as WorkspaceStates, WorkspaceCapabilities};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Handler for the ext-workspace protocol
+pub trait WorkspaceHandler:
+    GlobalDispatch<ExtWorkspaceManagerV1, WorkspaceGlobalData>
+    + Dispatch<ExtWorkspaceManagerV1, ()>
+    + Dispatch<ExtWorkspaceGroupHandleV1, WorkspaceGroupData>
+    + Dispatch<ExtWorkspaceHandleV1, WorkspaceData>
+    + 'static
+{
+    /// [`WorkspaceManagerState`] getter
+    fn workspace_state(&mut self) -> &mut WorkspaceManagerState;
+
+    /// A client committed a batch of requests
+    ///
+    /// Requests not permitted by the capabilities of their workspace or group are already
+    /// filtered out. Call [`WorkspaceManagerState::done`] after applying them.
+    fn commit_requests(&mut self, requests: Vec<WorkspaceRequest>);
+}
+
+/// Identifies a workspace group of a [`WorkspaceManagerState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorkspaceGroupHandle(u64);
+
+/// Identifies a workspace of a [`WorkspaceManagerState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorkspaceHandle(u64);
+
+/// Request of a client, see [`WorkspaceHandler::commit_requests`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceRequest {
+    /// Activate the workspace
+    Activate(WorkspaceHandle),
+    /// Deactivate the workspace
+    Deactivate(WorkspaceHandle),
+    /// Remove the workspace
+    Remove(WorkspaceHandle),
+    /// Move the workspace to another group
+    Assign {
+        /// The workspace to move
+        workspace: WorkspaceHandle,
+        /// The group to move it to
+        group: WorkspaceGroupHandle,
+    },
+    /// Create a new workspace in the group
+    Create {
+        /// The group of the new workspace
+        group: WorkspaceGroupHandle,
+        /// Requested name of the new workspace
+        name: String,
+    },
+}
+
+/// A group of workspaces
+#[derive(Debug)]
+pub struct WorkspaceGroup {
+    handle: WorkspaceGroupHandle,
+    capabilities: GroupCapabilities,
+    outputs: Vec<Output>,
+}
+
+impl WorkspaceGroup {
+    /// Returns the handle of this group
+    pub fn handle(&self) -> WorkspaceGroupHandle {
+        self.handle
+    }
+
+    /// Returns the capabilities of this group
+    pub fn capabilities(&self) -> GroupCapabilities {
+        self.capabilities
+    }
+
+    /// Returns the outputs of this group
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+}
+
+/// A workspace
+#[derive(Debug)]
+pub struct Workspace {
+    handle: WorkspaceHandle,
+    id: Option<String>,
+    name: String,
+    coordinates: Vec<u32>,
+    state: WorkspaceStates,
+    capabilities: WorkspaceCapabilities,
+    group: Option<WorkspaceGroupHandle>,
+}
+
+impl Workspace {
+    /// Returns the handle of this workspace
+    pub fn handle(&self) -> WorkspaceHandle {
+        self.handle
+    }
+
+    /// Returns the stable identifier of this workspace, if any
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the name of this workspace
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the coordinates of this workspace in its group
+    pub fn coordinates(&self) -> &[u32] {
+        &self.coordinates
+    }
+
+    /// Returns the state of this workspace
+    pub fn state(&self) -> WorkspaceStates {
+        self.state
+    }
+
+    /// Returns the capabilities of this workspace
+    pub fn capabilities(&self) -> WorkspaceCapabilities {
+        self.capabilities
+    }
+
+    /// Returns the group of this workspace, if any
+    pub fn group(&self) -> Option<WorkspaceGroupHandle> {
+        self.group
+    }
+
+    fn init_resource(&self, resource: &ExtWorkspaceHandleV1) {
+        if let Some(id) = &self.id {
+            resource.id(id.clone());
+        }
+        resource.name(self.name.clone());
+        resource.coordinates(coordinates_array(&self.coordinates));
+        resource.state(self.state);
+        resource.capabilities(self.capabilities);
+    }
+}
+
+fn coordinates_array(coordinates: &[u32]) -> Vec<u8> {
+    coordinates.iter().flat_map(|c| c.to_ne_bytes()).collect()
+}
+
+#[derive(Debug)]
+struct ManagerInstance {
+    manager: ExtWorkspaceManagerV1,
+    groups: Vec<(WorkspaceGroupHandle, ExtWorkspaceGroupHandleV1)>,
+    workspaces: Vec<(WorkspaceHandle, ExtWorkspaceHandleV1)>,
+    pending: Vec<WorkspaceRequest>,
+}
+
+impl ManagerInstance {
+    fn group(&self, handle: WorkspaceGroupHandle) -> Option<&ExtWorkspaceGroupHandleV1> {
+        self.groups.iter().find(|(h, _)| *h == handle).map(|(_, r)| r)
+    }
+
+    fn workspace(&self, handle: WorkspaceHandle) -> Option<&ExtWorkspaceHandleV1> {
+        self.workspaces.iter().find(|(h, _)| *h == handle).map(|(_, r)| r)
+    }
+
+    fn announce_group<D: WorkspaceHandler>(&mut self, dh: &DisplayHandle, group: &WorkspaceGroup) {
+        let Some(client) = self.manager.client() else {
+            return;
+        };
+        let data = WorkspaceGroupData {
+            handle: group.handle,
+            manager: self.manager.clone(),
+        };
+        let Ok(resource) =
+            client.create_resource::<ExtWorkspaceGroupHandleV1, _, D>(dh, self.manager.version(), data)
+        else {
+            return;
+        };
+
+        self.manager.workspace_group(&resource);
+        resource.capabilities(group.capabilities);
+        for output in &group.outputs {
+            for wl_output in output.client_outputs(&client) {
+                resource.output_enter(&wl_output);
+            }
+        }
+        self.groups.push((group.handle, resource));
+    }
+
+    fn announce_workspace<D: WorkspaceHandler>(&mut self, dh: &DisplayHandle, workspace: &Workspace) {
+        let Some(client) = self.manager.client() else {
+            return;
+        };
+        let data = WorkspaceData {
+            handle: workspace.handle,
+            manager: self.manager.clone(),
+        };
+        let Ok(resource) =
+            client.create_resource::<ExtWorkspaceHandleV1, _, D>(dh, self.manager.version(), data)
+        else {
+            return;
+        };
+
+        self.manager.workspace(&resource);
+        workspace.init_resource(&resource);
+        if let Some(group) = workspace.group.and_then(|group| self.group(group)) {
+            group.workspace_enter(&resource);
+        }
+        self.workspaces.push((workspace.handle, resource));
+    }
+}
+
+/// State of the ext-workspace global
+#[derive(Debug)]
+pub struct WorkspaceManagerState {
+    global: GlobalId,
+    dh: DisplayHandle,
+    groups: Vec<WorkspaceGroup>,
+    workspaces: Vec<Workspace>,
+    instances: Vec<ManagerInstance>,
+}
+
+impl WorkspaceManagerState {
+    /// Register a new [`ExtWorkspaceManagerV1`] global
+    pub fn new<D: WorkspaceHandler>(dh: &DisplayHandle) -> Self {
+        Self::new_with_filter::<D>(dh, |_| true)
+    }
+
+    /// Register a new [`ExtWorkspaceManagerV1`] global visible to clients matching the filter
+    pub fn new_with_filter<D: WorkspaceHandler>(
+        dh: &DisplayHandle,
+        filter: impl Fn(&Client) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        let global = dh.create_global::<D, ExtWorkspaceManagerV1, _>(
+            1,
+            WorkspaceGlobalData {
+                filter: Box::new(filter),
+            },
+        );
+
+        WorkspaceManagerState {
+            global,
+            dh: dh.clone(),
+            groups: Vec::new(),
+            workspaces: Vec::new(),
+            instances: Vec::new(),
+        }
+    }
+
+    /// Returns the [`ExtWorkspaceManagerV1`] global
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Returns the group of the given handle
+    pub fn group(&self, handle: WorkspaceGroupHandle) -> Option<&WorkspaceGroup> {
+        self.groups.iter().find(|group| group.handle == handle)
+    }
+
+    /// Returns all groups
+    pub fn groups(&self) -> impl Iterator<Item = &WorkspaceGroup> {
+        self.groups.iter()
+    }
+
+    /// Returns the first group containing the given output
+    pub fn group_for_output(&self, output: &Output) -> Option<WorkspaceGroupHandle> {
+        self.groups
+            .iter()
+            .find(|group| group.outputs.contains(output))
+            .map(|group| group.handle)
+    }
+
+    /// Returns the workspace of the given handle
+    pub fn workspace(&self, handle: WorkspaceHandle) -> Option<&Workspace> {
+        self.workspaces
+            .iter()
+            .find(|workspace| workspace.handle == handle)
+    }
+
+    /// Returns all workspaces
+    pub fn workspaces(&self) -> impl Iterator<Item = &Workspace> {
+        self.workspaces.iter()
+    }
+
+    /// Returns the workspaces of the given group
+    pub fn group_workspaces(&self, group: WorkspaceGroupHandle) -> impl Iterator<Item = &Workspace> {
+        self.workspaces
+            .iter()
+            .filter(move |workspace| workspace.group == Some(group))
+    }
+
+    /// Create a new workspace group
+    pub fn create_group<D: WorkspaceHandler>(
+        &mut self,
+        capabilities: GroupCapabilities,
+    ) -> WorkspaceGroupHandle {
+        let group = WorkspaceGroup {
+            handle: WorkspaceGroupHandle(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            capabilities,
+            outputs: Vec::new(),
+        };
+        for instance in &mut self.instances {
+            instance.announce_group::<D>(&self.dh, &group);
+        }
+
+        let handle = group.handle;
+        self.groups.push(group);
+        handle
+    }
+
+    /// Remove a workspace group
+    ///
+    /// Its workspaces are left without a group.
+    pub fn remove_group(&mut self, group: WorkspaceGroupHandle) {
+        let Some(pos) = self.groups.iter().position(|g| g.handle == group) else {
+            return;
+        };
+
+        let workspaces = self
+            .group_workspaces(group)
+            .map(|workspace| workspace.handle)
+            .collect::<Vec<_>>();
+        for workspace in workspaces {
+            self.set_workspace_group(workspace, None);
+        }
+
+        self.groups.remove(pos);
+        for instance in &mut self.instances {
+            instance.groups.retain(|(handle, resource)| {
+                if *handle == group {
+                    resource.removed();
+                }
+                *handle != group
+            });
+        }
+    }
+
+    /// Add an output to a workspace group
+    pub fn group_output_enter(&mut self, group: WorkspaceGroupHandle, output: &Output) {
+        let Some(g) = self.groups.iter_mut().find(|g| g.handle == group) else {
+            return;
+        };
+        if g.outputs.contains(output) {
+            return;
+        }
+        g.outputs.push(output.clone());
+
+        for instance in &self.instances {
+            let (Some(resource), Some(client)) = (instance.group(group), instance.manager.client()) else {
+                continue;
+            };
+            for wl_output in output.client_outputs(&client) {
+                resource.output_enter(&wl_output);
+            }
+        }
+    }
+
+    /// Remove an output from a workspace group
+    pub fn group_output_leave(&mut self, group: WorkspaceGroupHandle, output: &Output) {
+        let Some(g) = self.groups.iter_mut().find(|g| g.handle == group) else {
+            return;
+        };
+        let Some(pos) = g.outputs.iter().position(|o| o == output) else {
+            return;
+        };
+        g.outputs.remove(pos);
+
+        for instance in &self.instances {
+            let (Some(resource), Some(client)) = (instance.group(group), instance.manager.client()) else {
+                continue;
+            };
+            for wl_output in output.client_outputs(&client) {
+                resource.output_leave(&wl_output);
+            }
+        }
+    }
+
+    /// Notify the groups of a newly bound `wl_output`
+    ///
+    /// Should be called from [`OutputHandler::output_bound`](crate::wayland::output::OutputHandler::output_bound),
+    /// so clients learn about the outputs of groups they bound before the output.
+    pub fn output_bound(&self, output: &Output, wl_output: &WlOutput) {
+        for group in self.groups.iter().filter(|group| group.outputs.contains(output)) {
+            for instance in &self.instances {
+                if instance.manager.client() != wl_output.client() {
+                    continue;
+                }
+                if let Some(resource) = instance.group(group.handle) {
+                    resource.output_enter(wl_output);
+                    instance.manager.done();
+                }
+            }
+        }
+    }
+
+    /// Create a new workspace without a group
+    ///
+    /// The `id` has to be unique and stable across sessions, if given. It can not be changed
+    /// afterwards.
+    pub fn create_workspace<D: WorkspaceHandler>(
+        &mut self,
+        name: impl Into<String>,
+        id: Option<String>,
+        capabilities: WorkspaceCapabilities,
+    ) -> WorkspaceHandle {
+        let workspace = Workspace {
+            handle: WorkspaceHandle(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            id,
+            name: name.into(),
+            coordinates: Vec::new(),
+            state: WorkspaceStates::empty(),
+            capabilities,
+            group: None,
+        };
+        for instance in &mut self.instances {
+            instance.announce_workspace::<D>(&self.dh, &workspace);
+        }
+
+        let handle = workspace.handle;
+        self.workspaces.push(workspace);
+        handle
+    }
+
+    /// Remove a workspace
+    pub fn remove_workspace(&mut self, workspace: WorkspaceHandle) {
+        self.set_workspace_group(workspace, None);
+        self.workspaces.retain(|w| w.handle != workspace);
+        for instance in &mut self.instances {
+            instance.workspaces.retain(|(handle, resource)| {
+                if *handle == workspace {
+                    resource.removed();
+                }
+                *handle != workspace
+            });
+        }
+    }
+
+    /// Move a workspace to another group, or remove it from its group
+    pub fn set_workspace_group(&mut self, workspace: WorkspaceHandle, group: Option<WorkspaceGroupHandle>) {
+        if group.is_some_and(|group| self.group(group).is_none()) {
+            return;
+        }
+        let Some(w) = self.workspaces.iter_mut().find(|w| w.handle == workspace) else {
+            return;
+        };
+        if w.group == group {
+            return;
+        }
+        let old = std::mem::replace(&mut w.group, group);
+
+        for instance in &self.instances {
+            let Some(resource) = instance.workspace(workspace) else {
+                continue;
+            };
+            if let Some(old) = old.and_then(|old| instance.group(old)) {
+                old.workspace_leave(resource);
+            }
+            if let Some(new) = group.and_then(|group| instance.group(group)) {
+                new.workspace_enter(resource);
+            }
+        }
+    }
+
+    /// Change the name of a workspace
+    pub fn set_workspace_name(&mut self, workspace: WorkspaceHandle, name: impl Into<String>) {
+        let Some(w) = self.workspaces.iter_mut().find(|w| w.handle == workspace) else {
+            return;
+        };
+        let name = name.into();
+        if w.name == name {
+            return;
+        }
+        w.name = name.clone();
+        self.for_each_resource(workspace, |resource| resource.name(name.clone()));
+    }
+
+    /// Change the coordinates of a workspace in its group, e.g. its row and column in a grid
+    pub fn set_workspace_coordinates(&mut self, workspace: WorkspaceHandle, coordinates: Vec<u32>) {
+        let Some(w) = self.workspaces.iter_mut().find(|w| w.handle == workspace) else {
+            return;
+        };
+        if w.coordinates == coordinates {
+            return;
+        }
+        let array = coordinates_array(&coordinates);
+        w.coordinates = coordinates;
+        self.for_each_resource(workspace, |resource| resource.coordinates(array.clone()));
+    }
+
+    /// Change the state of a workspace
+    pub fn set_workspace_state(&mut self, workspace: WorkspaceHandle, state: WorkspaceStates) {
+        let Some(w) = self.workspaces.iter_mut().find(|w| w.handle == workspace) else {
+            return;
+        };
+        if w.state == state {
+            return;
+        }
+        w.state = state;
+        self.for_each_resource(workspace, |resource| resource.state(state));
+    }
+
+    /// Change the capabilities of a workspace
+    pub fn set_workspace_capabilities(
+        &mut self,
+        workspace: WorkspaceHandle,
+        capabilities: WorkspaceCapabilities,
+    ) {
+        let Some(w) = self.workspaces.iter_mut().find(|w| w.handle == workspace) else {
+            return;
+        };
+        if w.capabilities == capabilities {
+            return;
+        }
+        w.capabilities = capabilities;
+        self.for_each_resource(workspace, |resource| resource.capabilities(capabilities));
+    }
+
+    /// Notify clients that a batch of changes is complete
+    pub fn done(&self) {
+        for instance in &self.instances {
+            instance.manager.done();
+        }
+    }
+
+    fn for_each_resource(&self, workspace: WorkspaceHandle, mut f: impl FnMut(&ExtWorkspaceHandleV1)) {
+        for instance in &self.instances {
+            if let Some(resource) = instance.workspace(workspace) {
+                f(resource);
+            }
+        }
+    }
+
+    fn instance_mut(&mut self, manager: &ExtWorkspaceManagerV1) -> Option<&mut ManagerInstance> {
+        self.instances
+            .iter_mut()
+            .find(|instance| &instance.manager == manager)
+    }
+
+    fn permitted(&self, request: &WorkspaceRequest) -> bool {
+        let workspace_can = |workspace: &WorkspaceHandle, capability| {
+            self.workspace(*workspace)
+                .is_some_and(|w| w.capabilities.contains(capability))
+        };
+        match request {
+            WorkspaceRequest::Activate(workspace) => {
+                workspace_can(workspace, WorkspaceCapabilities::Activate)
+            }
+            WorkspaceRequest::Deactivate(workspace) => {
+                workspace_can(workspace, WorkspaceCapabilities::Deactivate)
+            }
+            WorkspaceRequest::Remove(workspace) => workspace_can(workspace, WorkspaceCapabilities::Remove),
+            WorkspaceRequest::Assign { workspace, group } => {
+                workspace_can(workspace, WorkspaceCapabilities::Assign) && self.group(*group).is_some()
+            }
+            WorkspaceRequest::Create { group, .. } => self
+                .group(*group)
+                .is_some_and(|g| g.capabilities.contains(GroupCapabilities::CreateWorkspace)),
+        }
+    }
+}
+
+/// Global data of the [`ExtWorkspaceManagerV1`] global
+pub struct WorkspaceGlobalData {
+    filter: Box<dyn Fn(&Client) -> bool + Send + Sync>,
+}
+
+impl std::fmt::Debug for WorkspaceGlobalData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkspaceGlobalData").finish_non_exhaustive()
+    }
+}
+
+/// User data of [`ExtWorkspaceGroupHandleV1`] resources
+#[derive(Debug)]
+pub struct WorkspaceGroupData {
+    handle: WorkspaceGroupHandle,
+    manager: ExtWorkspaceManagerV1,
+}
+
+impl WorkspaceGroupData {
+    /// Returns the group of the resource
+    pub fn handle(&self) -> WorkspaceGroupHandle {
+        self.handle
+    }
+}
+
+/// User data of [`ExtWorkspaceHandleV1`] resources
+#[derive(Debug)]
+pub struct WorkspaceData {
+    handle: WorkspaceHandle,
+    manager: ExtWorkspaceManagerV1,
+}
+
+impl WorkspaceData {
+    /// Returns the workspace of the resource
+    pub fn handle(&self) -> WorkspaceHandle {
+        self.handle
+    }
+}
+
+impl<D: WorkspaceHandler> GlobalDispatch<ExtWorkspaceManagerV1, WorkspaceGlobalData, D>
+    for WorkspaceManagerState
+{
+    fn bind(
+        state: &mut D,
+        dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtWorkspaceManagerV1>,
+        _global_data: &WorkspaceGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+        let state = state.workspace_state();
+
+        let mut instance = ManagerInstance {
+            manager,
+            groups: Vec::new(),
+            workspaces: Vec::new(),
+            pending: Vec::new(),
+        };
+        for group in &state.groups {
+            instance.announce_group::<D>(dh, group);
+        }
+        for workspace in &state.workspaces {
+            instance.announce_workspace::<D>(dh, workspace);
+        }
+        instance.manager.done();
+
+        state.instances.push(instance);
+    }
+
+    fn can_view(client: Client, global_data: &WorkspaceGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: WorkspaceHandler> Dispatch<ExtWorkspaceManagerV1, (), D> for WorkspaceManagerState {
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        manager: &ExtWorkspaceManagerV1,
+        request: ext_workspace_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_workspace_manager_v1::Request::Commit => {
+                let workspace_state = state.workspace_state();
+                let Some(instance) = workspace_state.instance_mut(manager) else {
+                    return;
+                };
+                let pending = std::mem::take(&mut instance.pending);
+                let requests = pending
+                    .into_iter()
+                    .filter(|request| workspace_state.permitted(request))
+                    .collect::<Vec<_>>();
+                if !requests.is_empty() {
+                    state.commit_requests(requests);
+                }
+            }
+            ext_workspace_manager_v1::Request::Stop => {
+                state
+                    .workspace_state()
+                    .instances
+                    .retain(|instance| &instance.manager != manager);
+                manager.finished();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, resource: &ExtWorkspaceManagerV1, _data: &()) {
+        state
+            .workspace_state()
+            .instances
+            .retain(|instance| &instance.manager != resource);
+    }
+}
+
+impl<D: WorkspaceHandler> Dispatch<ExtWorkspaceGroupHandleV1, WorkspaceGroupData, D>
+    for WorkspaceManagerState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &ExtWorkspaceGroupHandleV1,
+        request: ext_workspace_group_handle_v1::Request,
+        data: &WorkspaceGroupData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_workspace_group_handle_v1::Request::CreateWorkspace { workspace } => {
+                if let Some(instance) = state.workspace_state().instance_mut(&data.manager) {
+                    instance.pending.push(WorkspaceRequest::Create {
+                        group: data.handle,
+                        name: workspace,
+                    });
+                }
+            }
+            ext_workspace_group_handle_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        resource: &ExtWorkspaceGroupHandleV1,
+        data: &WorkspaceGroupData,
+    ) {
+        if let Some(instance) = state.workspace_state().instance_mut(&data.manager) {
+            instance.groups.retain(|(_, r)| r != resource);
+        }
+    }
+}
+
+impl<D: WorkspaceHandler> Dispatch<ExtWorkspaceHandleV1, WorkspaceData, D> for WorkspaceManagerState {
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &ExtWorkspaceHandleV1,
+        request: ext_workspace_handle_v1::Request,
+        data: &WorkspaceData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let request = match request {
+            ext_workspace_handle_v1::Request::Activate => WorkspaceRequest::Activate(data.handle),
+            ext_workspace_handle_v1::Request::Deactivate => WorkspaceRequest::Deactivate(data.handle),
+            ext_workspace_handle_v1::Request::Remove => WorkspaceRequest::Remove(data.handle),
+            ext_workspace_handle_v1::Request::Assign { workspace_group } => {
+                let Some(group) = workspace_group.data::<WorkspaceGroupData>() else {
+                    return;
+                };
+                WorkspaceRequest::Assign {
+                    workspace: data.handle,
+                    group: group.handle,
+                }
+            }
+            ext_workspace_handle_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        if let Some(instance) = state.workspace_state().instance_mut(&data.manager) {
+            instance.pending.push(request);
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, resource: &ExtWorkspaceHandleV1, data: &WorkspaceData) {
+        if let Some(instance) = state.workspace_state().instance_mut(&data.manager) {
+            instance.workspaces.retain(|(_, r)| r != resource);
+        }
+    }
+}
+
+/// Macro to delegate implementation of the ext-workspace protocol to [`WorkspaceManagerState`].
+///
+/// You must also implement [`WorkspaceHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_workspace {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        const _: () = {
+            use $crate::{
+                reexports::{
+                    wayland_protocols::ext::workspace::v1::server::{
+                        ext_workspace_group_handle_v1::ExtWorkspaceGroupHandleV1,
+                        ext_workspace_handle_v1::ExtWorkspaceHandleV1,
+                        ext_workspace_manager_v1::ExtWorkspaceManagerV1,
+                    },
+                    wayland_server::{delegate_dispatch, delegate_global_dispatch},
+                },
+                wayland::workspace::{
+                    WorkspaceData, WorkspaceGlobalData, WorkspaceGroupData, WorkspaceManagerState,
+                },
+            };
+
+            delegate_global_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtWorkspaceManagerV1: WorkspaceGlobalData] => WorkspaceManagerState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtWorkspaceManagerV1: ()] => WorkspaceManagerState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtWorkspaceGroupHandleV1: WorkspaceGroupData] => WorkspaceManagerState
+            );
+            delegate_dispatch!(
+                $(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)?
+                $ty: [ExtWorkspaceHandleV1: WorkspaceData] => WorkspaceManagerState
+            );
+        };
+    };
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Arc;
+
+    use wayland_server::{
+        backend::{ClientData, DisconnectReason},
+        Display,
+    };
+
+    use super::*;
+    use crate::wayland::test::{Arg, Event, FakeClient};
+
+    struct State {
+        workspaces: WorkspaceManagerState,
+        requests: Vec<WorkspaceRequest>,
+    }
+
+    impl WorkspaceHandler for State {
+        fn workspace_state(&mut self) -> &mut WorkspaceManagerState {
+            &mut self.workspaces
+        }
+
+        fn commit_requests(&mut self, requests: Vec<WorkspaceRequest>) {
+            self.requests.extend(requests);
+        }
+    }
+
+    crate::delegate_workspace!(State);
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    struct Setup {
+        display: Display<State>,
+        state: State,
+        fake: FakeClient,
+        group: WorkspaceGroupHandle,
+        workspace: WorkspaceHandle,
+    }
+
+    impl Setup {
+        fn new(capabilities: WorkspaceCapabilities) -> Setup {
+            let mut display = Display::<State>::new().unwrap();
+            let mut state = State {
+                workspaces: WorkspaceManagerState::new::<State>(&display.handle()),
+                requests: Vec::new(),
+            };
+            let group = state
+                .workspaces
+                .create_group::<State>(GroupCapabilities::CreateWorkspace);
+            let workspace =
+                state
+                    .workspaces
+                    .create_workspace::<State>("1", Some("first".into()), capabilities);
+            state.workspaces.set_workspace_group(workspace, Some(group));
+            state.workspaces.done();
+
+            let (mut fake, _) = FakeClient::connect(&mut display.handle(), Arc::new(ClientState)).unwrap();
+            fake.record_events(true);
+            fake.get_registry().unwrap();
+            let mut setup = Setup {
+                display,
+                state,
+                fake,
+                group,
+                workspace,
+            };
+            setup.roundtrip();
+            setup
+        }
+
+        fn roundtrip(&mut self) -> Vec<Event> {
+            self.fake.roundtrip(&mut self.display, &mut self.state).unwrap()
+        }
+
+        fn bind(&mut self) -> (u32, Vec<Event>) {
+            let manager = self
+                .fake
+                .bind_global(ExtWorkspaceManagerV1::interface(), 1)
+                .unwrap();
+            (manager, self.roundtrip())
+        }
+    }
+
+    fn names(events: &[Event]) -> Vec<&'static str> {
+        events.iter().map(|event| event.name().unwrap()).collect()
+    }
+
+    // id of the object created by the first event of the given name
+    fn created(events: &[Event], name: &str) -> u32 {
+        events
+            .iter()
+            .filter(|event| event.name() == Some(name))
+            .find_map(|event| match event.args.as_slice() {
+                [Arg::NewId(id)] => Some(*id),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn announces_on_bind() {
+        let mut setup = Setup::new(WorkspaceCapabilities::Activate);
+        let (_, events) = setup.bind();
+
+        assert_eq!(
+            names(&events),
+            [
+                "workspace_group",
+                "capabilities",
+                "workspace",
+                "id",
+                "name",
+                "coordinates",
+                "state",
+                "capabilities",
+                "workspace_enter",
+                "done",
+            ]
+        );
+        let group = created(&events, "workspace_group");
+        let workspace = created(&events, "workspace");
+        assert!(matches!(
+            events[8].args.as_slice(),
+            [Arg::Object(id)] if events[8].object == group && *id == workspace
+        ));
+        assert!(matches!(
+            events[4].args.as_slice(),
+            [Arg::Str(Some(name))] if name.as_slice() == b"1"
+        ));
+    }
+
+    #[test]
+    fn filters_requests_by_capabilities() {
+        let mut setup = Setup::new(WorkspaceCapabilities::Activate);
+        let (manager, events) = setup.bind();
+        let workspace = created(&events, "workspace");
+        let group = created(&events, "workspace_group");
+
+        let interface = ExtWorkspaceHandleV1::interface();
+        for request in ["activate", "deactivate", "remove"] {
+            setup
+                .fake
+                .send_request(workspace, interface, request, Vec::new())
+                .unwrap();
+        }
+        setup
+            .fake
+            .send_request(workspace, interface, "assign", vec![Arg::Object(group)])
+            .unwrap();
+        setup
+            .fake
+            .send_request(
+                group,
+                ExtWorkspaceGroupHandleV1::interface(),
+                "create_workspace",
+                vec![Arg::Str(Some(b"2".to_vec()))],
+            )
+            .unwrap();
+        setup.roundtrip();
+        // nothing is handed out before the commit
+        assert!(setup.state.requests.is_empty());
+
+        setup
+            .fake
+            .send_request(manager, ExtWorkspaceManagerV1::interface(), "commit", Vec::new())
+            .unwrap();
+        setup.roundtrip();
+        assert_eq!(
+            setup.state.requests,
+            [
+                WorkspaceRequest::Activate(setup.workspace),
+                WorkspaceRequest::Create {
+                    group: setup.group,
+                    name: "2".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_groups_are_left_first() {
+        let mut setup = Setup::new(WorkspaceCapabilities::Activate);
+        let (_, events) = setup.bind();
+        let workspace = created(&events, "workspace");
+        let group = created(&events, "workspace_group");
+
+        setup.state.workspaces.remove_group(setup.group);
+        setup.state.workspaces.done();
+        let events = setup.roundtrip();
+
+        assert_eq!(names(&events), ["workspace_leave", "removed", "done"]);
+        assert!(events[..2].iter().all(|event| event.object == group));
+        assert!(matches!(events[0].args.as_slice(), [Arg::Object(id)] if *id == workspace));
+        assert_eq!(
+            setup.state.workspaces.workspace(setup.workspace).unwrap().group(),
+            None
+        );
+    }
+}
@@ -4,7 +4,9 @@
 //!
 //! In order to advertise toplevel icon global call [XdgToplevelIconManager::new] and delegate
 //! events to it with [`delegate_xdg_toplevel_icon`][crate::delegate_xdg_toplevel_icon].
-//! Currently attached icon is available in double-buffered [ToplevelIconCachedState]
+//! Currently attached icon is available in double-buffered [ToplevelIconCachedState], or through
+//! [`ToplevelSurface::with_icon`] for taskbars and decorations. [`ToplevelIconCachedState::import`]
+//! uploads the pixel data best suited for the size the icon is shown at.
 
 use std::{
     collections::HashSet,
@@ -26,10 +28,11 @@ use wayland_server::{
 };
 
 use crate::{
+    backend::renderer::ImportMemWl,
     utils::HookId,
     wayland::{
         compositor::{self, Cacheable},
-        shell::xdg::XdgShellSurfaceUserData,
+        shell::xdg::{ToplevelSurface, XdgShellSurfaceUserData},
         shm::ShmBufferUserData,
     },
 };
@@ -91,6 +94,60 @@ impl ToplevelIconCachedState {
         };
         &data.buffers
     }
+
+    /// Returns `true` if no icon is set
+    pub fn is_empty(&self) -> bool {
+        self.icon_name().is_none() && self.buffers().is_empty()
+    }
+
+    /// Pick the buffer best suited to show the icon at `size` logical pixels with `scale`
+    ///
+    /// This is the smallest buffer at least as large as the requested size, falling back to the
+    /// largest buffer if none is. Returns the buffer and its scale.
+    pub fn best_buffer(&self, size: i32, scale: i32) -> Option<(&WlBuffer, i32)> {
+        let target = size * scale;
+        let width = |buffer: &WlBuffer| {
+            buffer
+                .data::<ShmBufferUserData>()
+                .map(|shm| shm.data.width)
+                .unwrap_or_default()
+        };
+        let buffers = self.buffers().iter().map(|(buffer, scale)| (buffer, *scale));
+        buffers
+            .clone()
+            .filter(|(buffer, _)| width(buffer) >= target)
+            .min_by_key(|(buffer, _)| width(buffer))
+            .or_else(|| buffers.max_by_key(|(buffer, _)| width(buffer)))
+    }
+
+    /// Import the buffer best suited to show the icon at `size` logical pixels with `scale`
+    ///
+    /// Returns the texture and the scale of its buffer, or `None` if the icon has no pixel data.
+    pub fn import<R: ImportMemWl>(
+        &self,
+        renderer: &mut R,
+        size: i32,
+        scale: i32,
+    ) -> Option<Result<(R::TextureId, i32), R::Error>> {
+        let (buffer, buffer_scale) = self.best_buffer(size, scale)?;
+        Some(
+            renderer
+                .import_shm_buffer(buffer, None, &[])
+                .map(|texture| (texture, buffer_scale)),
+        )
+    }
+}
+
+impl ToplevelSurface {
+    /// Access the icon of this toplevel, as of its last commit
+    pub fn with_icon<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&ToplevelIconCachedState) -> T,
+    {
+        compositor::with_states(self.wl_surface(), |states| {
+            f(states.cached_state.get::<ToplevelIconCachedState>().current())
+        })
+    }
 }
 
 impl Cacheable for ToplevelIconCachedState {
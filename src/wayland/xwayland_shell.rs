@@ -179,7 +179,16 @@ impl XWaylandShellState {
 
     /// Retrieves the surface for a given serial.
     pub fn surface_for_serial(&self, serial: u64) -> Option<WlSurface> {
-        self.by_serial.get(&serial).cloned()
+        self.by_serial
+            .get(&serial)
+            .filter(|surface| surface.is_alive())
+            .cloned()
+    }
+
+    pub(crate) fn take_surface_for_serial(&mut self, serial: u64) -> Option<WlSurface> {
+        self.by_serial
+            .remove(&serial)
+            .filter(|surface| surface.is_alive())
     }
 }
 
@@ -364,9 +373,9 @@ fn serial_commit_hook<D: XWaylandShellHandler + XwmHandler + SeatHandler + 'stat
                     }
                 } else {
                     // this is necessary for the atom-handler to look up the matching surface
-                    XWaylandShellHandler::xwayland_shell_state(state)
-                        .by_serial
-                        .insert(serial, surface.clone());
+                    let by_serial = &mut XWaylandShellHandler::xwayland_shell_state(state).by_serial;
+                    by_serial.retain(|_, surface| surface.is_alive());
+                    by_serial.insert(serial, surface.clone());
                 }
             }
         }
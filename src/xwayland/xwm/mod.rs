@@ -1563,6 +1563,7 @@ where
             xwm.clipboard.window_destroyed(&n.window, loop_handle);
             xwm.primary.window_destroyed(&n.window, loop_handle);
             xwm.dnd.window_destroyed(&n.window, loop_handle);
+            xwm.unpaired_surfaces.retain(|_, window| *window != n.window);
 
             if let Some(pos) = xwm.windows.iter().position(|x| x.window_id() == n.window) {
                 let surface = xwm.windows.remove(pos);
@@ -2081,25 +2082,12 @@ where
             }
             match msg.type_ {
                 x if x == xwm.atoms.WL_SURFACE_ID => {
-                    let wid = msg.data.as_data32()[0];
-                    info!(
+                    // XWayland only falls back to this when it could not bind the xwayland shell,
+                    // matching by object id races with the lifecycle of the wl_surface.
+                    warn!(
                         window = ?msg.window,
-                        surface = ?wid,
-                        "mapped X11 window to surface",
+                        "ignoring WL_SURFACE_ID, XWayland needs to support the xwayland shell protocol",
                     );
-                    if let Some(surface) = xwm
-                        .windows
-                        .iter_mut()
-                        .find(|x| x.window_id() == msg.window || x.mapped_window_id() == Some(msg.window))
-                    {
-                        // This is the old, deprecated method for associating a
-                        // wl_surface with an X11 window. It can race with the
-                        // lifecycle of the wl_surface. Still, we can make it
-                        // available to compositors who want to support old
-                        // versions of xwayland by manually matching on ID in an
-                        // idle callback.
-                        surface.state.lock().unwrap().wl_surface_id = Some(wid);
-                    }
                 }
                 x if x == xwm.atoms.WL_SURFACE_SERIAL => {
                     // This handles the case that the serial was already set on
@@ -2123,8 +2111,7 @@ where
 
                         if let Some(wl_surface) =
                             xwayland_shell::XWaylandShellHandler::xwayland_shell_state(state)
-                                .surface_for_serial(serial)
-                                .clone()
+                                .take_surface_for_serial(serial)
                         {
                             debug!(
                                 window = ?xsurface.window_id(),
@@ -2142,6 +2129,9 @@ where
                                 "no matching wl_surface for X11 window",
                             );
                             let xwm = state.xwm_state(xwm_id);
+                            // a window gets a new serial whenever it is mapped again
+                            xwm.unpaired_surfaces
+                                .retain(|_, window| *window != xsurface.window_id());
                             xwm.unpaired_surfaces.insert(serial, xsurface.window_id());
                             std::mem::drop(guard);
                             xsurface.set_wl_surface(state, None);
@@ -73,7 +73,6 @@ const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
 #[derive(Debug)]
 pub(crate) struct SharedSurfaceState {
     pub(super) alive: bool,
-    pub(super) wl_surface_id: Option<u32>,
     pub(super) wl_surface_serial: Option<u64>,
     pub(super) mapped_onto: Option<X11Window>,
     pub(super) geometry: Rectangle<i32, Logical>,
@@ -207,7 +206,6 @@ impl X11Surface {
             atoms,
             state: Arc::new(Mutex::new(SharedSurfaceState {
                 alive: true,
-                wl_surface_id: None,
                 wl_surface_serial: None,
                 wl_surface: None,
                 mapped_onto: None,
@@ -357,23 +355,12 @@ impl X11Surface {
         self.state.lock().unwrap().wl_surface.clone()
     }
 
-    /// Returns the associated `wl_surface` id, once it has been set by
-    /// xwayland.
-    ///
-    /// Note that XWayland will only set this if it was unable to bind the
-    /// [xwayland shell](crate::wayland::xwayland_shell) protocol on the wayland
-    /// side.
-    #[deprecated = "Since XWayland 23.1, the recommended approach is to use [wl_surface_serial] and the [xwayland shell](crate::wayland::xwayland_shell) protocol on the wayland side to match X11 windows."]
-    pub fn wl_surface_id(&self) -> Option<u32> {
-        self.state.lock().unwrap().wl_surface_id
-    }
-
     /// Returns the associated `wl_surface` serial, once it has been set by
     /// xwayland.
     ///
     /// XWayland will set this if it has bound the [xwayland
-    /// shell](crate::wayland::xwayland_shell) protocol on the wayland side.
-    /// Otherwise, it will set [`wl_surface_id`][Self::wl_surface_id] instead.
+    /// shell](crate::wayland::xwayland_shell) protocol on the wayland side,
+    /// which is required for X11 windows to be associated with a `wl_surface`.
     pub fn wl_surface_serial(&self) -> Option<u64> {
         self.state.lock().unwrap().wl_surface_serial
     }
@@ -0,0 +1,346 @@
+//! Client side of the conformance harness
+
+use std::{
+    io::Write,
+    os::{fd::AsFd, unix::net::UnixStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use wayland_client::{
+    delegate_noop,
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_pointer::WlPointer,
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::xdg::shell::client::{
+    xdg_surface::{self, XdgSurface},
+    xdg_toplevel::{self, XdgToplevel},
+    xdg_wm_base::{self, XdgWmBase},
+};
+
+/// A global advertised by the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    pub name: u32,
+    pub interface: String,
+    pub version: u32,
+}
+
+/// Events received by the test client, in order of arrival
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    ShmFormat(wl_shm::Format),
+    SeatCapabilities(wl_seat::Capability),
+    BufferRelease(WlBuffer),
+    XdgSurfaceConfigure {
+        surface: XdgSurface,
+        serial: u32,
+    },
+    ToplevelConfigure {
+        width: i32,
+        height: i32,
+        states: Vec<xdg_toplevel::State>,
+    },
+    KeyboardEnter {
+        serial: u32,
+        surface: WlSurface,
+    },
+    KeyboardLeave {
+        surface: WlSurface,
+    },
+    Key {
+        key: u32,
+        state: wl_keyboard::KeyState,
+    },
+    Ping(u32),
+}
+
+#[derive(Debug, Default)]
+pub struct TestClientState {
+    pub globals: Vec<Global>,
+    pub events: Vec<Event>,
+}
+
+/// An in-process wayland client connected to the test compositor
+pub struct TestClient {
+    pub conn: Connection,
+    pub queue: EventQueue<TestClientState>,
+    pub qh: QueueHandle<TestClientState>,
+    pub registry: WlRegistry,
+    pub state: TestClientState,
+}
+
+impl TestClient {
+    pub fn new(stream: UnixStream) -> Self {
+        stream
+            .set_nonblocking(true)
+            .expect("Failed to make the client socket non-blocking");
+        let conn = Connection::from_socket(stream).expect("Failed to create the client connection");
+        let queue = conn.new_event_queue();
+        let qh = queue.handle();
+        let registry = conn.display().get_registry(&qh, ());
+
+        TestClient {
+            conn,
+            queue,
+            qh,
+            registry,
+            state: TestClientState::default(),
+        }
+    }
+
+    /// Request a sync callback, the returned flag is set once the compositor answered it
+    pub fn sync(&self) -> Arc<AtomicBool> {
+        let done = Arc::new(AtomicBool::new(false));
+        self.conn.display().sync(&self.qh, done.clone());
+        done
+    }
+
+    /// Returns the advertised global of interface `I`, if any
+    pub fn global<I: Proxy>(&self) -> Option<&Global> {
+        self.state
+            .globals
+            .iter()
+            .find(|global| global.interface == I::interface().name)
+    }
+
+    /// Bind the global of interface `I` with at most the given version
+    ///
+    /// Panics if the compositor does not advertise the global.
+    pub fn bind<I, U>(&self, version: u32, udata: U) -> I
+    where
+        I: Proxy + 'static,
+        U: Send + Sync + 'static,
+        TestClientState: Dispatch<I, U>,
+    {
+        let global = self
+            .global::<I>()
+            .unwrap_or_else(|| panic!("Global {} is not advertised", I::interface().name));
+        self.registry
+            .bind(global.name, version.min(global.version), &self.qh, udata)
+    }
+
+    /// Create a shm buffer filled with `pixel`
+    pub fn create_buffer(&self, shm: &WlShm, width: i32, height: i32, pixel: u32) -> WlBuffer {
+        let stride = width * 4;
+        let size = stride * height;
+
+        let mut file = tempfile::tempfile().expect("Failed to create the shm file");
+        let data = (0..width * height)
+            .flat_map(|_| pixel.to_ne_bytes())
+            .collect::<Vec<_>>();
+        file.write_all(&data).expect("Failed to write the shm file");
+
+        let pool = shm.create_pool(file.as_fd(), size, &self.qh, ());
+        let buffer = pool.create_buffer(0, width, height, stride, wl_shm::Format::Argb8888, &self.qh, ());
+        pool.destroy();
+        // make sure the fd is sent before the file is closed
+        self.conn.flush().expect("Failed to flush the client connection");
+        buffer
+    }
+
+    /// Take all events received so far
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.state.events)
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } => state.globals.push(Global {
+                name,
+                interface,
+                version,
+            }),
+            wl_registry::Event::GlobalRemove { name } => state.globals.retain(|global| global.name != name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlCallback, Arc<AtomicBool>> for TestClientState {
+    fn event(
+        _state: &mut Self,
+        _callback: &WlCallback,
+        event: wl_callback::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            done.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Dispatch<WlShm, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _shm: &WlShm,
+        event: wl_shm::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_shm::Event::Format {
+            format: WEnum::Value(format),
+        } = event
+        {
+            state.events.push(Event::ShmFormat(format));
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        buffer: &WlBuffer,
+        event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            state.events.push(Event::BufferRelease(buffer.clone()));
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _seat: &WlSeat,
+        event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            state.events.push(Event::SeatCapabilities(capabilities));
+        }
+    }
+}
+
+impl Dispatch<WlKeyboard, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _keyboard: &WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_keyboard::Event::Enter { serial, surface, .. } => {
+                state.events.push(Event::KeyboardEnter { serial, surface })
+            }
+            wl_keyboard::Event::Leave { surface, .. } => state.events.push(Event::KeyboardLeave { surface }),
+            wl_keyboard::Event::Key {
+                key,
+                state: WEnum::Value(key_state),
+                ..
+            } => state.events.push(Event::Key {
+                key,
+                state: key_state,
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+            state.events.push(Event::Ping(serial));
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        surface: &XdgSurface,
+        event: xdg_surface::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            state.events.push(Event::XdgSurfaceConfigure {
+                surface: surface.clone(),
+                serial,
+            });
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Configure {
+            width,
+            height,
+            states,
+        } = event
+        {
+            let states = states
+                .chunks_exact(4)
+                .filter_map(|raw| {
+                    xdg_toplevel::State::try_from(u32::from_ne_bytes(raw.try_into().unwrap())).ok()
+                })
+                .collect();
+            state.events.push(Event::ToplevelConfigure {
+                width,
+                height,
+                states,
+            });
+        }
+    }
+}
+
+delegate_noop!(TestClientState: WlCompositor);
+delegate_noop!(TestClientState: WlShmPool);
+delegate_noop!(TestClientState: ignore WlSurface);
+delegate_noop!(TestClientState: ignore WlPointer);
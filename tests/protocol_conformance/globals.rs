@@ -0,0 +1,34 @@
+use smithay::reexports::wayland_server::{backend::GlobalId, Display};
+
+use crate::server::ServerState;
+
+fn interface(display: &Display<ServerState>, global: GlobalId) -> (&'static str, u32) {
+    let info = display
+        .handle()
+        .backend_handle()
+        .global_info(global)
+        .expect("The global is not registered");
+    (info.interface.name, info.version)
+}
+
+#[test]
+fn registers_globals() {
+    let display = Display::<ServerState>::new().expect("Failed to create the display");
+    let server = ServerState::new(&display.handle());
+
+    assert_eq!(
+        interface(&display, server.compositor_state.compositor_global()).0,
+        "wl_compositor"
+    );
+    assert_eq!(
+        interface(&display, server.compositor_state.subcompositor_global()).0,
+        "wl_subcompositor"
+    );
+    assert_eq!(interface(&display, server.shm_state.global()), ("wl_shm", 2));
+    assert_eq!(
+        interface(&display, server.xdg_shell_state.global()).0,
+        "xdg_wm_base"
+    );
+    let seat = server.seat.global().expect("The seat has no global");
+    assert_eq!(interface(&display, seat).0, "wl_seat");
+}
@@ -0,0 +1,126 @@
+//! Protocol conformance tests
+//!
+//! Every test spins up a compositor built from smithay's protocol implementations and connects
+//! an in-process `wayland-client` to it over a socket pair. Both sides are dispatched from the
+//! test thread, so no sockets on disk or background threads are involved and the tests can run
+//! in parallel.
+//!
+//! The in-process client needs the wayland wire transport of unix platforms, the tests of the
+//! compositor side alone run everywhere.
+
+// not every part of the harness is used by every test
+#![allow(dead_code)]
+
+#[cfg(unix)]
+use std::{io, os::unix::net::UnixStream, sync::atomic::Ordering};
+
+#[cfg(unix)]
+use smithay::reexports::wayland_server::Display;
+#[cfg(unix)]
+use wayland_client::{backend::WaylandError, DispatchError};
+
+#[cfg(unix)]
+mod client;
+mod server;
+
+mod globals;
+#[cfg(unix)]
+mod seat;
+#[cfg(unix)]
+mod shm;
+#[cfg(unix)]
+mod xdg_shell;
+
+#[cfg(unix)]
+use client::TestClient;
+#[cfg(unix)]
+use server::ServerState;
+
+/// Upper bound of dispatch iterations for a single roundtrip
+#[cfg(unix)]
+const MAX_ITERATIONS: usize = 16;
+
+/// A compositor and a client connected to it
+#[cfg(unix)]
+pub struct Fixture {
+    pub display: Display<ServerState>,
+    pub server: ServerState,
+    pub client: TestClient,
+}
+
+#[cfg(unix)]
+impl Fixture {
+    /// Create a new compositor with a single connected client
+    ///
+    /// The client already received the initial list of globals.
+    pub fn new() -> Self {
+        let display = Display::new().expect("Failed to create the display");
+        let server = ServerState::new(&display.handle());
+
+        let (server_stream, client_stream) = UnixStream::pair().expect("Failed to create a socket pair");
+        display
+            .handle()
+            .insert_client(server_stream, server::ClientState::new())
+            .expect("Failed to insert the client");
+        let client = TestClient::new(client_stream);
+
+        let mut fixture = Fixture {
+            display,
+            server,
+            client,
+        };
+        fixture.roundtrip();
+        fixture
+    }
+
+    /// Dispatch both sides until the compositor processed all pending requests
+    /// and the client received all resulting events.
+    ///
+    /// Panics if the client was disconnected, see [`Fixture::try_roundtrip`].
+    pub fn roundtrip(&mut self) {
+        if let Err(err) = self.try_roundtrip() {
+            panic!("Roundtrip failed: {}", err);
+        }
+    }
+
+    /// Dispatch both sides until the compositor processed all pending requests
+    /// and the client received all resulting events.
+    ///
+    /// Returns the error of the client connection, e.g. a protocol error posted by the compositor.
+    pub fn try_roundtrip(&mut self) -> Result<(), WaylandError> {
+        let done = self.client.sync();
+
+        for _ in 0..MAX_ITERATIONS {
+            self.client.conn.flush()?;
+            self.display
+                .dispatch_clients(&mut self.server)
+                .expect("Failed to dispatch clients");
+            self.display.flush_clients().expect("Failed to flush clients");
+
+            if let Some(guard) = self.client.queue.prepare_read() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            self.client
+                .queue
+                .dispatch_pending(&mut self.client.state)
+                .map_err(|err| match err {
+                    DispatchError::Backend(err) => err,
+                    DispatchError::BadMessage {
+                        interface, opcode, ..
+                    } => {
+                        panic!("Client received a malformed event {}@{}", interface, opcode)
+                    }
+                })?;
+
+            if done.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+        }
+
+        panic!("Roundtrip did not complete after {} iterations", MAX_ITERATIONS);
+    }
+}
@@ -0,0 +1,60 @@
+use smithay::{
+    backend::input::KeyState,
+    input::keyboard::{FilterResult, Keycode},
+    utils::SERIAL_COUNTER,
+};
+use wayland_client::protocol::{
+    wl_compositor::WlCompositor,
+    wl_keyboard,
+    wl_seat::{self, WlSeat},
+};
+
+use crate::{client::Event, Fixture};
+
+#[test]
+fn advertises_capabilities() {
+    let mut fixture = Fixture::new();
+    let _seat = fixture.client.bind::<WlSeat, _>(9, ());
+    fixture.roundtrip();
+
+    assert_eq!(
+        fixture.client.take_events(),
+        vec![Event::SeatCapabilities(
+            wl_seat::Capability::Keyboard | wl_seat::Capability::Pointer
+        )]
+    );
+}
+
+#[test]
+fn keyboard_focus_and_key() {
+    let mut fixture = Fixture::new();
+    let compositor = fixture.client.bind::<WlCompositor, _>(6, ());
+    let seat = fixture.client.bind::<WlSeat, _>(9, ());
+    let _keyboard = seat.get_keyboard(&fixture.client.qh, ());
+    let surface = compositor.create_surface(&fixture.client.qh, ());
+    fixture.roundtrip();
+    fixture.client.take_events();
+
+    let server_surface = fixture.server.surfaces[0].clone();
+    let keyboard = fixture.server.keyboard.clone();
+    keyboard.set_focus(
+        &mut fixture.server,
+        Some(server_surface),
+        SERIAL_COUNTER.next_serial(),
+    );
+    keyboard.input::<(), _>(
+        &mut fixture.server,
+        Keycode::new(30 + 8),
+        KeyState::Pressed,
+        SERIAL_COUNTER.next_serial(),
+        0,
+        |_, _, _| FilterResult::Forward,
+    );
+    fixture.roundtrip();
+
+    let events = fixture.client.take_events();
+    assert!(matches!(events.as_slice(), [
+        Event::KeyboardEnter { surface: focused, .. },
+        Event::Key { key: 30, state: wl_keyboard::KeyState::Pressed },
+    ] if focused == &surface));
+}
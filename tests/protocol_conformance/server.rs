@@ -0,0 +1,197 @@
+//! Compositor side of the conformance harness
+
+use std::sync::Arc;
+
+use smithay::{
+    delegate_compositor, delegate_seat, delegate_shm, delegate_xdg_shell,
+    input::{keyboard::KeyboardHandle, pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
+    reexports::wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{wl_buffer, wl_seat, wl_shm, wl_surface::WlSurface},
+        Client, DisplayHandle,
+    },
+    utils::Serial,
+    wayland::{
+        buffer::BufferHandler,
+        compositor::{
+            with_states, BufferAssignment, CompositorClientState, CompositorHandler, CompositorState,
+            SurfaceAttributes,
+        },
+        shell::xdg::{PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState},
+        shm::{with_buffer_contents, ShmHandler, ShmState},
+    },
+};
+
+/// A buffer committed by a client, as seen by the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommittedBuffer {
+    pub width: i32,
+    pub height: i32,
+    pub format: wl_shm::Format,
+    /// Value of the first pixel of the buffer in native endianess
+    pub first_pixel: u32,
+}
+
+pub struct ServerState {
+    pub compositor_state: CompositorState,
+    pub xdg_shell_state: XdgShellState,
+    pub shm_state: ShmState,
+    pub seat_state: SeatState<Self>,
+    pub seat: Seat<Self>,
+    pub keyboard: KeyboardHandle<Self>,
+
+    pub surfaces: Vec<WlSurface>,
+    pub toplevels: Vec<ToplevelSurface>,
+    pub committed_buffers: Vec<CommittedBuffer>,
+    pub keyboard_focus: Option<WlSurface>,
+}
+
+impl ServerState {
+    pub fn new(dh: &DisplayHandle) -> Self {
+        let compositor_state = CompositorState::new::<Self>(dh);
+        let xdg_shell_state = XdgShellState::new::<Self>(dh);
+        let shm_state = ShmState::new::<Self>(dh, vec![]);
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(dh, "seat-0");
+        let keyboard = seat
+            .add_keyboard(Default::default(), 200, 25)
+            .expect("Failed to initialize the keyboard");
+        seat.add_pointer();
+
+        ServerState {
+            compositor_state,
+            xdg_shell_state,
+            shm_state,
+            seat_state,
+            seat,
+            keyboard,
+            surfaces: Vec::new(),
+            toplevels: Vec::new(),
+            committed_buffers: Vec::new(),
+            keyboard_focus: None,
+        }
+    }
+
+    /// Returns the toplevel belonging to the given surface
+    pub fn toplevel(&self, surface: &WlSurface) -> Option<&ToplevelSurface> {
+        self.toplevels.iter().find(|t| t.wl_surface() == surface)
+    }
+}
+
+#[derive(Default)]
+pub struct ClientState {
+    pub compositor_state: CompositorClientState,
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+impl ClientState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(ClientState::default())
+    }
+}
+
+impl CompositorHandler for ServerState {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+        &client.get_data::<ClientState>().unwrap().compositor_state
+    }
+
+    fn new_surface(&mut self, surface: &WlSurface) {
+        self.surfaces.push(surface.clone());
+    }
+
+    fn commit(&mut self, surface: &WlSurface) {
+        if let Some(toplevel) = self.toplevel(surface) {
+            if !toplevel.is_initial_configure_sent() {
+                toplevel.send_configure();
+            }
+        }
+
+        let buffer = with_states(surface, |states| {
+            match states
+                .cached_state
+                .get::<SurfaceAttributes>()
+                .current()
+                .buffer
+                .take()
+            {
+                Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer),
+                _ => None,
+            }
+        });
+        if let Some(buffer) = buffer {
+            let committed = with_buffer_contents(&buffer, |ptr, len, data| {
+                assert!(data.offset >= 0 && data.offset as usize + 4 <= len);
+                // SAFETY: the range was checked above and the test client does not mutate the pool
+                let first_pixel = unsafe { (ptr.add(data.offset as usize) as *const u32).read_unaligned() };
+                CommittedBuffer {
+                    width: data.width,
+                    height: data.height,
+                    format: data.format,
+                    first_pixel,
+                }
+            })
+            .expect("Committed buffer is not a shm buffer");
+            self.committed_buffers.push(committed);
+            buffer.release();
+        }
+    }
+}
+
+impl BufferHandler for ServerState {
+    fn buffer_destroyed(&mut self, _buffer: &wl_buffer::WlBuffer) {}
+}
+
+impl ShmHandler for ServerState {
+    fn shm_state(&self) -> &ShmState {
+        &self.shm_state
+    }
+}
+
+impl XdgShellHandler for ServerState {
+    fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+        &mut self.xdg_shell_state
+    }
+
+    fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        self.toplevels.push(surface);
+    }
+
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.toplevels.retain(|t| t != &surface);
+    }
+
+    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {}
+
+    fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+
+    fn reposition_request(&mut self, _surface: PopupSurface, _positioner: PositionerState, _token: u32) {}
+}
+
+impl SeatHandler for ServerState {
+    type KeyboardFocus = WlSurface;
+    type PointerFocus = WlSurface;
+    type TouchFocus = WlSurface;
+
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+
+    fn focus_changed(&mut self, _seat: &Seat<Self>, focused: Option<&WlSurface>) {
+        self.keyboard_focus = focused.cloned();
+    }
+
+    fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+}
+
+delegate_compositor!(ServerState);
+delegate_shm!(ServerState);
+delegate_xdg_shell!(ServerState);
+delegate_seat!(ServerState);
@@ -0,0 +1,58 @@
+use wayland_client::protocol::{wl_compositor::WlCompositor, wl_shm};
+
+use crate::{client::Event, server::CommittedBuffer, Fixture};
+
+#[test]
+fn advertises_mandatory_formats() {
+    let mut fixture = Fixture::new();
+    let _shm = fixture.client.bind::<wl_shm::WlShm, _>(1, ());
+    fixture.roundtrip();
+
+    let events = fixture.client.take_events();
+    assert!(events.contains(&Event::ShmFormat(wl_shm::Format::Argb8888)));
+    assert!(events.contains(&Event::ShmFormat(wl_shm::Format::Xrgb8888)));
+}
+
+#[test]
+fn buffer_contents_and_release() {
+    let mut fixture = Fixture::new();
+    let compositor = fixture.client.bind::<WlCompositor, _>(6, ());
+    let shm = fixture.client.bind::<wl_shm::WlShm, _>(1, ());
+
+    let surface = compositor.create_surface(&fixture.client.qh, ());
+    let buffer = fixture.client.create_buffer(&shm, 4, 2, 0xff00ff00);
+    surface.attach(Some(&buffer), 0, 0);
+    surface.commit();
+    fixture.roundtrip();
+
+    assert_eq!(
+        fixture.server.committed_buffers,
+        vec![CommittedBuffer {
+            width: 4,
+            height: 2,
+            format: wl_shm::Format::Argb8888,
+            first_pixel: 0xff00ff00,
+        }]
+    );
+    assert!(fixture
+        .client
+        .take_events()
+        .contains(&Event::BufferRelease(buffer)));
+}
+
+#[test]
+fn invalid_pool_size_is_error() {
+    let mut fixture = Fixture::new();
+    let shm = fixture.client.bind::<wl_shm::WlShm, _>(1, ());
+
+    let file = tempfile::tempfile().unwrap();
+    let _pool = shm.create_pool(std::os::fd::AsFd::as_fd(&file), 0, &fixture.client.qh, ());
+
+    let err = fixture.try_roundtrip().unwrap_err();
+    assert_eq!(
+        fixture.client.conn.protocol_error().map(|err| err.code),
+        Some(wl_shm::Error::InvalidStride as u32),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,90 @@
+use wayland_client::protocol::{wl_compositor::WlCompositor, wl_shm::WlShm, wl_surface::WlSurface};
+use wayland_protocols::xdg::shell::client::{
+    xdg_surface::{self, XdgSurface},
+    xdg_toplevel::XdgToplevel,
+    xdg_wm_base::XdgWmBase,
+};
+
+use crate::{client::Event, Fixture};
+
+struct Toplevel {
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    _toplevel: XdgToplevel,
+}
+
+fn create_toplevel(fixture: &mut Fixture) -> Toplevel {
+    let compositor = fixture.client.bind::<WlCompositor, _>(6, ());
+    let wm_base = fixture.client.bind::<XdgWmBase, _>(6, ());
+
+    let surface = compositor.create_surface(&fixture.client.qh, ());
+    let xdg_surface = wm_base.get_xdg_surface(&surface, &fixture.client.qh, ());
+    let toplevel = xdg_surface.get_toplevel(&fixture.client.qh, ());
+    toplevel.set_title("conformance".into());
+
+    Toplevel {
+        surface,
+        xdg_surface,
+        _toplevel: toplevel,
+    }
+}
+
+#[test]
+fn initial_commit_is_configured() {
+    let mut fixture = Fixture::new();
+    let toplevel = create_toplevel(&mut fixture);
+    toplevel.surface.commit();
+    fixture.roundtrip();
+
+    assert_eq!(fixture.server.toplevels.len(), 1);
+    let events = fixture.client.take_events();
+    assert!(matches!(events.as_slice(), [
+        Event::ToplevelConfigure { .. },
+        Event::XdgSurfaceConfigure { surface, .. },
+    ] if surface == &toplevel.xdg_surface));
+}
+
+#[test]
+fn map_after_ack() {
+    let mut fixture = Fixture::new();
+    let shm = fixture.client.bind::<WlShm, _>(1, ());
+    let toplevel = create_toplevel(&mut fixture);
+    toplevel.surface.commit();
+    fixture.roundtrip();
+
+    let serial = fixture
+        .client
+        .take_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::XdgSurfaceConfigure { serial, .. } => Some(serial),
+            _ => None,
+        })
+        .expect("No configure received");
+    toplevel.xdg_surface.ack_configure(serial);
+
+    let buffer = fixture.client.create_buffer(&shm, 8, 8, 0xffffffff);
+    toplevel.surface.attach(Some(&buffer), 0, 0);
+    toplevel.surface.commit();
+    fixture.roundtrip();
+
+    assert_eq!(fixture.server.committed_buffers.len(), 1);
+    assert_eq!(fixture.server.committed_buffers[0].width, 8);
+}
+
+#[test]
+fn buffer_before_configure_is_error() {
+    let mut fixture = Fixture::new();
+    let shm = fixture.client.bind::<WlShm, _>(1, ());
+    let toplevel = create_toplevel(&mut fixture);
+
+    let buffer = fixture.client.create_buffer(&shm, 8, 8, 0xffffffff);
+    toplevel.surface.attach(Some(&buffer), 0, 0);
+    toplevel.surface.commit();
+
+    assert!(fixture.try_roundtrip().is_err());
+    assert_eq!(
+        fixture.client.conn.protocol_error().map(|err| err.code),
+        Some(xdg_surface::Error::UnconfiguredBuffer as u32)
+    );
+}